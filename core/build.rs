@@ -0,0 +1,33 @@
+//! Build script: checks that include/emu.h matches the C ABI in src/ffi.rs,
+//! and links CEmu's core for the optional lockstep harness.
+
+#[path = "src/ffi_header.rs"]
+mod ffi_header;
+
+fn main() {
+    check_header();
+
+    println!("cargo:rerun-if-env-changed=CEMU_LOCKSTEP_LIB_DIR");
+    if std::env::var_os("CARGO_FEATURE_CEMU_LOCKSTEP").is_none() {
+        return;
+    }
+    let dir = std::env::var("CEMU_LOCKSTEP_LIB_DIR")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/../tools/cemu-test").to_string());
+    println!("cargo:rustc-link-search=native={}", dir);
+    println!("cargo:rustc-link-lib=static=lockstep");
+    println!("cargo:rustc-link-lib=static=cemucore");
+}
+
+/// Fail the build when emu.h no longer declares exactly what ffi.rs exports
+fn check_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=src/ffi_header.rs");
+    println!("cargo:rerun-if-changed=include/emu.h");
+    let read = |path: &str| {
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    };
+    let problems = ffi_header::check(&read("src/ffi.rs"), &read("include/emu.h"));
+    if !problems.is_empty() {
+        panic!("include/emu.h is out of date with src/ffi.rs:\n{}", problems.join("\n"));
+    }
+}
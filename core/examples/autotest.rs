@@ -0,0 +1,56 @@
+//! Run a CEmu autotester JSON test against the core.
+//!
+//! Usage: cargo run --release --example autotest -- <test.json> [rom]
+//!
+//! The ROM is the one given, else the test's "rom" (relative to the JSON
+//! file). Prints one line per check; exits 2 if any check fails and 1 if
+//! the test could not be run.
+
+use std::path::Path;
+
+use emu_core::autotest::Autotest;
+use emu_core::Emu;
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        fail("Usage: autotest <test.json> [rom]");
+    }
+    let json_path = Path::new(&args[1]);
+    let dir = json_path.parent().unwrap_or(Path::new("."));
+
+    let json = std::fs::read_to_string(json_path).unwrap_or_else(|e| fail(format_args!("{}: {}", args[1], e)));
+    let test = Autotest::parse(&json).unwrap_or_else(|e| fail(format_args!("{}: {}", args[1], e)));
+    let rom_path = match (args.get(2), &test.rom) {
+        (Some(rom), _) => Path::new(rom).to_path_buf(),
+        (None, Some(rom)) => dir.join(rom),
+        (None, None) => fail("no ROM: pass one or set \"rom\" in the test"),
+    };
+    let rom = std::fs::read(&rom_path).unwrap_or_else(|e| fail(format_args!("{}: {}", rom_path.display(), e)));
+
+    let mut emu = Emu::new();
+    if let Err(code) = emu.load_rom(&rom) {
+        fail(format_args!("{}: load failed ({})", rom_path.display(), code));
+    }
+    test.boot(&mut emu, dir).unwrap_or_else(|e| fail(e));
+    let report = test.run(&mut emu).unwrap_or_else(|e| fail(e));
+
+    for check in &report.checks {
+        println!(
+            "[{}] hash {}: {} (CRC {:08X})",
+            if check.passed { "PASS" } else { "FAIL" },
+            check.id,
+            check.description,
+            check.actual
+        );
+    }
+    println!("{}/{} checks passed", report.checks.len() - report.failed(), report.checks.len());
+    if !report.passed() {
+        std::process::exit(2);
+    }
+}
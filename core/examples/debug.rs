@@ -8,9 +8,17 @@
 //! Commands:
 //!   boot              Run boot test with progress reporting
 //!   trace [steps]     Generate trace log for parity comparison (default: 100000)
-//!   screen [output]   Render screen to image file (default: screen.png)
+//!   screen [output]   Render screen to image file (default: screen.png, "-" = terminal)
 //!   vram              Analyze VRAM content (color histogram)
 //!   compare <file>    Compare our trace with CEmu trace file
+//!   hashcap <out>     Record state hashes for cross-build comparison
+//!   hashdiff <a> <b>  Find where two hash captures first differ
+//!   bisect <a> <b>    Find the first instruction where two key recordings diverge
+//!   elf <file.elf>    Run a bare-metal ez80-clang ELF without TI-OS
+//!   watch <file.8xp>  Re-send and run a program each time it is rebuilt
+//!   pipe              Drive the emulator with line commands on stdin
+//!   gdb [port]        Serve the GDB remote protocol (default port 1234)
+//!   record <out>      Write a structured instruction trace (binary or JSONL)
 //!   help              Show this help message
 
 use std::collections::HashMap;
@@ -21,7 +29,7 @@ use std::path::Path;
 use std::process::Command;
 use std::time::Instant;
 
-use emu_core::{Emu, StepInfo, IoTarget, IoOpType, disassemble};
+use emu_core::{Emu, StepInfo, IoTarget, IoOpType, TermStyle, decode, disassemble};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -39,7 +47,7 @@ fn main() {
         }
         "screen" => {
             let output = args.get(2).map(|s| s.as_str()).unwrap_or("screen.png");
-            cmd_screen(output);
+            cmd_screen(output, args.get(3).map(|s| s.as_str()));
         }
         "vram" => cmd_vram(),
         "compare" => {
@@ -135,6 +143,79 @@ fn main() {
             }
             cmd_run(&file_args, timeout_secs, speed);
         }
+        "elf" => {
+            if args.len() < 3 {
+                eprintln!("Usage: debug elf <file.elf> [--timeout <secs>] [--break <symbol>]");
+                return;
+            }
+            let mut timeout_secs = 10u64;
+            let mut break_at: Option<&str> = None;
+            let mut i = 3;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--timeout" => {
+                        if let Some(val) = args.get(i + 1).and_then(|s| s.parse().ok()) {
+                            timeout_secs = val;
+                        }
+                    }
+                    "--break" => break_at = args.get(i + 1).map(|s| s.as_str()),
+                    other => eprintln!("Ignoring unknown option {}", other),
+                }
+                i += 2;
+            }
+            cmd_elf(&args[2], timeout_secs, break_at);
+        }
+        "watch" => {
+            if args.len() < 3 {
+                eprintln!("Usage: debug watch <file.8xp> [lib1.8xv ...] [--timeout <secs>]");
+                return;
+            }
+            let mut timeout_secs = 30u64;
+            let mut file_args: Vec<&str> = Vec::new();
+            let mut i = 2;
+            while i < args.len() {
+                if args[i] == "--timeout" {
+                    if let Some(val) = args.get(i + 1).and_then(|s| s.parse().ok()) {
+                        timeout_secs = val;
+                    }
+                    i += 2;
+                } else {
+                    file_args.push(&args[i]);
+                    i += 1;
+                }
+            }
+            cmd_watch(&file_args, timeout_secs);
+        }
+        "record" => {
+            if args.len() < 3 {
+                eprintln!("Usage: debug record <out> [records] [--jsonl] [--pc <start>-<end>] [--skip <n>]");
+                return;
+            }
+            let mut config = emu_core::TraceConfig { limit: 100_000, ..Default::default() };
+            let mut i = 3;
+            while i < args.len() {
+                let value = args.get(i + 1).map(|s| s.as_str());
+                match args[i].as_str() {
+                    "--jsonl" => {
+                        config.format = emu_core::TraceFormat::Jsonl;
+                        i += 1;
+                        continue;
+                    }
+                    "--pc" => config.pc_range = value.and_then(parse_hex_range),
+                    "--skip" => config.skip = value.and_then(|s| s.parse().ok()).unwrap_or(0),
+                    other => match other.parse() {
+                        Ok(limit) => {
+                            config.limit = limit;
+                            i += 1;
+                            continue;
+                        }
+                        Err(_) => eprintln!("Ignoring unknown option {}", other),
+                    },
+                }
+                i += 2;
+            }
+            cmd_record(&args[2], config);
+        }
         "diagchk" => {
             if args.len() < 3 {
                 eprintln!("Usage: debug diagchk <file.8xp> [lib1.8xv ...]");
@@ -150,6 +231,35 @@ fn main() {
             let count = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(40usize);
             cmd_disasm(addr, count);
         }
+        "hashcap" => {
+            if args.len() < 3 {
+                eprintln!("Usage: debug hashcap <out.txt> [steps] [interval] [from] [keys.txt]");
+                return;
+            }
+            let steps = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(1_000_000);
+            let interval = args.get(4).and_then(|s| s.parse().ok()).unwrap_or(10_000);
+            let from = args.get(5).and_then(|s| s.parse().ok()).unwrap_or(0);
+            cmd_hashcap(&args[2], steps, interval, from, args.get(6).map(|s| s.as_str()));
+        }
+        "hashdiff" => {
+            if args.len() < 4 {
+                eprintln!("Usage: debug hashdiff <a.txt> <b.txt>");
+                return;
+            }
+            cmd_hashdiff(&args[2], &args[3]);
+        }
+        "bisect" => {
+            if args.len() < 4 {
+                eprintln!("Usage: debug bisect <keys_a.txt> <keys_b.txt> [steps]");
+                return;
+            }
+            let steps = args.get(4).and_then(|s| s.parse().ok()).unwrap_or(10_000_000);
+            cmd_bisect(&args[2], &args[3], steps);
+        }
+        #[cfg(feature = "scripting")]
+        "pipe" => cmd_pipe(),
+        #[cfg(feature = "debugger")]
+        "gdb" => cmd_gdb(args.get(2).and_then(|s| s.parse().ok()).unwrap_or(1234)),
         "help" | "--help" | "-h" => print_help(),
         _ => {
             eprintln!("Unknown command: {}", args[1]);
@@ -172,9 +282,12 @@ Commands:
                     Default: 100000 steps
                     Output: traces/ours_<timestamp>.log
 
-  screen [output]   Render screen to image file after boot
+  screen [output] [style]
+                    Render screen to image file after boot
                     Default output: screen.png
                     Runs emulator to completion and saves framebuffer
+                    Output "-" prints to the terminal instead; style is
+                    blocks (default, 24-bit color), braille or ascii
 
   vram              Analyze VRAM content after boot
                     Shows color histogram and pixel statistics
@@ -222,6 +335,47 @@ Commands:
                     Options: --timeout <secs> (default: 30)
                              --speed <N> (e.g. 1=real-time, default: unthrottled)
 
+  elf <file.elf>    Load an ez80-clang ELF without a ROM or TI-OS and run it
+                    from its entry point with debug output capture; sections
+                    go to their link addresses. Reports where it stopped as
+                    symbol+offset.
+                    Options: --timeout <secs> (default: 10)
+                             --break <symbol> (stop when PC reaches it)
+
+  watch <file.8xp> [lib.8xv ...]
+                    Like run, but keeps watching the program file. Each time
+                    the CE toolchain rebuilds it, restores a warm booted state
+                    (libraries installed), re-sends the program and runs it.
+                    A rebuild while the program is running restarts it.
+                    Options: --timeout <secs> per run (default: 30)
+
+  hashcap <out.txt> [steps] [interval] [from] [keys.txt]
+                    Record state hashes and registers every <interval>
+                    instructions (default 10000) up to <steps> (default 1M),
+                    replaying optional key input ("<step> <row> <col> down|up")
+
+  hashdiff <a.txt> <b.txt>
+                    Compare two hashcap files (e.g. before/after a core
+                    change) and print the window where they first differ;
+                    re-run hashcap on that window with interval 1 to find
+                    the exact instruction
+
+  bisect <keys_a.txt> <keys_b.txt> [steps]
+                    Replay two key recordings and report the first
+                    instruction where machine state differs
+  pipe              Drive the emulator with line commands on stdin
+                    Loads the ROM and powers on, then reads commands such as
+                    "idle", "key enter", "run 500", "screen out.ppm", "text"
+                    and answers each with one "ok ..." or "err ..." line on
+                    stdout (see core/src/pipe.rs for the full protocol)
+  gdb [port]        Load the ROM, power on and wait for GDB on 127.0.0.1:port
+                    (default 1234); attach with "target remote :1234"
+  record <out> [records] [--jsonl] [--pc <start>-<end>] [--skip <n>]
+                    Boot and write one record per instruction (PC, registers,
+                    opcode, cycles, IRQ state) to <out>, binary by default
+                    (format in core/src/trace_record.rs). Default 100000
+                    records; --pc takes a hex range such as 020000-020FFF
+
   help              Show this help message
 
 Environment Variables:
@@ -235,6 +389,8 @@ Examples:
   cargo run --release --example debug -- screen output.png
   cargo run --release --example debug -- compare traces/cemu.log
   cargo run --release --example debug -- sendfile DOOM.8xp clibs/*.8xv
+  cargo run --release --example debug -- watch ../myprog/bin/MYPROG.8xp clibs/*.8xv
+  printf 'idle\ntype 6 + 7 enter\ntext\n' | cargo run --release --example debug -- pipe
 "#
     );
 }
@@ -272,6 +428,149 @@ fn create_emu_with_serial_flash(serial_flash: bool) -> Option<Emu> {
     Some(emu)
 }
 
+// === Divergence Bisection ===
+
+fn read_recording(path: Option<&str>) -> Option<emu_core::bisect::InputRecording> {
+    use emu_core::bisect::InputRecording;
+    let Some(path) = path else {
+        return Some(InputRecording::default());
+    };
+    let text = fs::read_to_string(path).map_err(|e| eprintln!("{}: {}", path, e)).ok()?;
+    InputRecording::parse(&text).map_err(|e| eprintln!("{}: {}", path, e)).ok()
+}
+
+fn cmd_hashcap(output: &str, steps: u64, interval: u64, from: u64, keys: Option<&str>) {
+    use emu_core::bisect::StateCapture;
+    let Some(recording) = read_recording(keys) else { return };
+    let Some(emu) = create_emu() else { return };
+
+    let start = Instant::now();
+    let capture = StateCapture::record(emu, &recording, from, steps, interval);
+    if let Err(e) = fs::write(output, capture.to_text()) {
+        eprintln!("{}: {}", output, e);
+        return;
+    }
+    println!("Saved {} entries to {} in {:.1}s", capture.entries.len(), output, start.elapsed().as_secs_f64());
+}
+
+fn cmd_hashdiff(path_a: &str, path_b: &str) {
+    use emu_core::bisect::StateCapture;
+    let read = |path: &str| {
+        let text = fs::read_to_string(path).map_err(|e| eprintln!("{}: {}", path, e)).ok()?;
+        StateCapture::parse(&text).map_err(|e| eprintln!("{}: {}", path, e)).ok()
+    };
+    let (Some(a), Some(b)) = (read(path_a), read(path_b)) else { return };
+
+    let Some(diff) = a.first_difference(&b) else {
+        println!("No difference at common steps ({} / {} entries)", a.entries.len(), b.entries.len());
+        return;
+    };
+    let (ours, theirs) = diff.first;
+    match diff.last_agreed {
+        Some(step) => println!("First difference between step {} and step {}", step, ours.step),
+        None => println!("Captures already differ at step {}", ours.step),
+    }
+    for line in ours.regs.diff(&theirs.regs) {
+        println!("  {}", line.replace("ours=", "A=").replace("ref=", "B="));
+    }
+    if let Some(step) = diff.last_agreed {
+        if ours.step - step > 1 {
+            println!("Narrow with: debug hashcap <out.txt> {} 1 {}", ours.step, step);
+        }
+    }
+}
+
+fn cmd_bisect(keys_a: &str, keys_b: &str, steps: u64) {
+    use emu_core::bisect::{bisect, DEFAULT_INTERVAL};
+    let (Some(a), Some(b)) = (read_recording(Some(keys_a)), read_recording(Some(keys_b))) else { return };
+    let Some(rom) = load_rom() else { return };
+    let start = || {
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).expect("Failed to load ROM");
+        emu.press_on_key();
+        emu
+    };
+
+    match bisect(start, &a, start, &b, steps, DEFAULT_INTERVAL) {
+        Ok(n) => println!("No divergence in {} steps", n),
+        Err(report) => print!("{}", report),
+    }
+}
+
+// === Pipe Mode ===
+
+#[cfg(feature = "scripting")]
+fn cmd_pipe() {
+    let mut emu = match create_emu() {
+        Some(e) => e,
+        None => return,
+    };
+    emu.release_on_key();
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = emu_core::pipe::run(&mut emu, stdin.lock(), stdout.lock()) {
+        eprintln!("pipe: {}", e);
+    }
+}
+
+// === GDB Server ===
+
+#[cfg(feature = "debugger")]
+fn cmd_gdb(port: u16) {
+    let mut emu = match create_emu() {
+        Some(e) => e,
+        None => return,
+    };
+    emu.release_on_key();
+
+    println!("Waiting for GDB on 127.0.0.1:{}", port);
+    if let Err(e) = emu_core::gdb::serve(&mut emu, ("127.0.0.1", port)) {
+        eprintln!("gdb: {}", e);
+    }
+}
+
+// === Structured Trace ===
+
+/// Emulated time `record` runs for before giving up on filling the limit
+const RECORD_MAX_SECS: u32 = 60;
+
+fn parse_hex_range(s: &str) -> Option<(u32, u32)> {
+    let (start, end) = s.split_once('-')?;
+    let hex = |s: &str| u32::from_str_radix(s.trim_start_matches("0x").trim_start_matches("0X"), 16).ok();
+    Some((hex(start)?, hex(end)?))
+}
+
+fn cmd_record(output: &str, config: emu_core::TraceConfig) {
+    let mut emu = match create_emu() {
+        Some(e) => e,
+        None => return,
+    };
+    emu.release_on_key();
+
+    let file = match File::create(output) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("{}: {}", output, e);
+            return;
+        }
+    };
+    emu.set_trace(config, Box::new(BufWriter::new(file)));
+
+    // Run in 1ms slices until the limit is reached
+    const SLICE_CYCLES: u32 = 48_000;
+    for _ in 0..RECORD_MAX_SECS * 1000 {
+        if !emu.is_tracing() || emu.run_cycles(SLICE_CYCLES) == 0 {
+            break;
+        }
+    }
+    match emu.stop_trace() {
+        Some(Ok(records)) => println!("Wrote {} records to {}", records, output),
+        Some(Err(e)) => eprintln!("{}: {}", output, e),
+        None => {}
+    }
+}
+
 // === Boot Test ===
 
 fn cmd_boot() {
@@ -1251,7 +1550,7 @@ fn cmd_calc(expr: &str) {
 
 // === Screen Rendering ===
 
-fn cmd_screen(output: &str) {
+fn cmd_screen(output: &str, term_style: Option<&str>) {
     let mut emu = match create_emu() {
         Some(e) => e,
         None => return,
@@ -1276,6 +1575,17 @@ fn cmd_screen(output: &str) {
     // Render frame
     emu.render_frame();
 
+    // "-" prints the screen to the terminal instead of saving an image
+    if output == "-" {
+        let style = match term_style {
+            Some("braille") => TermStyle::Braille,
+            Some("ascii") => TermStyle::Ascii,
+            _ => TermStyle::Blocks,
+        };
+        print!("{}", emu.screen_to_terminal(80, style));
+        return;
+    }
+
     // Save as PPM first
     let ppm_path = output.replace(".png", ".ppm");
     save_framebuffer_ppm(&emu, &ppm_path);
@@ -2023,7 +2333,7 @@ fn cmd_bakerom(output_path: &str, files: &[String]) {
 
     // Write modified flash to output file
     let flash = emu.flash_data();
-    fs::write(output_path, flash).expect("Failed to write output ROM");
+    fs::write(output_path, &flash).expect("Failed to write output ROM");
 
     println!("Wrote {} to: {} ({} bytes)",
         if total_entries > 0 { "baked ROM" } else { "ROM copy" },
@@ -2216,18 +2526,55 @@ fn dump_os_state(emu: &mut Emu, label: &str) {
     println!();
 }
 
+/// TI-OS program name for a .8xp path (its upper-cased file stem)
+fn program_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("UNKNOWN")
+        .to_uppercase()
+}
+
+/// Boot TI-OS with the ON key held, then release it
+fn boot_os(emu: &mut Emu) {
+    eprintln!("Booting TI-OS...");
+    emu.press_on_key();
+    let mut total = 0u64;
+    while total < 175_000_000 {
+        total += emu.run_cycles(1_000_000) as u64;
+    }
+    emu.release_on_key();
+    emu.run_cycles(2_000_000);
+    eprintln!("Boot complete at {:.1}M cycles, PC={:06X}", total as f64 / 1e6, emu.pc());
+}
+
+/// Launch via sendKey: ENTER → CLEAR → Asm( → prgm → <NAME> → ENTER
+fn launch_asm_program(emu: &mut Emu, prog_name: &str) {
+    eprintln!("Launching Asm(prgm{})...", prog_name);
+    send_os_key_wait(emu, 0x05, "ENTER-init");
+    send_os_key_wait(emu, 0x09, "CLEAR");
+    send_os_key_wait(emu, 0xFC9C, "Asm(");
+    send_os_key_wait(emu, 0xDA, "prgm");
+    for ch in prog_name.chars() {
+        if ch.is_ascii_uppercase() {
+            let key = 0x9A + (ch as u16 - 'A' as u16);
+            send_os_key_wait(emu, key, &format!("'{}'", ch));
+        } else if ch.is_ascii_digit() {
+            let key = 0x80 + (ch as u16 - '0' as u16);
+            send_os_key_wait(emu, key, &format!("'{}'", ch));
+        }
+    }
+    send_os_key_wait(emu, 0x05, "ENTER-exec");
+    eprintln!("Program launched.");
+}
+
 fn cmd_run(files: &[&str], timeout_secs: u64, speed: Option<f64>) {
     if files.is_empty() {
         eprintln!("No program file specified.");
         return;
     }
 
-    let prog_path = files[0];
-    let prog_name = Path::new(prog_path)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("UNKNOWN")
-        .to_uppercase();
+    let prog_name = program_name(files[0]);
 
     // Load ROM
     let rom_data = match load_rom() {
@@ -2257,34 +2604,8 @@ fn cmd_run(files: &[&str], timeout_secs: u64, speed: Option<f64>) {
     // Enable debug port interception
     emu.enable_debug_ports();
 
-    // Boot TI-OS
-    eprintln!("Booting TI-OS...");
-    emu.press_on_key();
-    let mut total = 0u64;
-    while total < 175_000_000 {
-        total += emu.run_cycles(1_000_000) as u64;
-    }
-    emu.release_on_key();
-    emu.run_cycles(2_000_000);
-    eprintln!("Boot complete at {:.1}M cycles, PC={:06X}", total as f64 / 1e6, emu.pc());
-
-    // Launch via sendKey: ENTER → CLEAR → Asm( → prgm → <NAME> → ENTER
-    eprintln!("Launching Asm(prgm{})...", prog_name);
-    send_os_key_wait(&mut emu, 0x05, "ENTER-init");
-    send_os_key_wait(&mut emu, 0x09, "CLEAR");
-    send_os_key_wait(&mut emu, 0xFC9C, "Asm(");
-    send_os_key_wait(&mut emu, 0xDA, "prgm");
-    for ch in prog_name.chars() {
-        if ch.is_ascii_uppercase() {
-            let key = 0x9A + (ch as u16 - 'A' as u16);
-            send_os_key_wait(&mut emu, key, &format!("'{}'", ch));
-        } else if ch.is_ascii_digit() {
-            let key = 0x80 + (ch as u16 - '0' as u16);
-            send_os_key_wait(&mut emu, key, &format!("'{}'", ch));
-        }
-    }
-    send_os_key_wait(&mut emu, 0x05, "ENTER-exec");
-    eprintln!("Program launched.");
+    boot_os(&mut emu);
+    launch_asm_program(&mut emu, &prog_name);
 
     // Run loop with debug output capture
     let timeout_cycles = timeout_secs * 48_000_000;
@@ -2349,6 +2670,197 @@ fn cmd_run(files: &[&str], timeout_secs: u64, speed: Option<f64>) {
     }
 }
 
+/// Modification time of `path`, or None while it is missing (the toolchain
+/// may delete and rewrite it during a build)
+fn modified_time(path: &str) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Read a file once it has stopped changing, so a half-written build output
+/// is never injected
+fn read_settled(path: &str) -> Option<Vec<u8>> {
+    let before = modified_time(path)?;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    if modified_time(path)? != before {
+        return None;
+    }
+    fs::read(path).ok()
+}
+
+fn cmd_watch(files: &[&str], timeout_secs: u64) {
+    const POLL: std::time::Duration = std::time::Duration::from_millis(250);
+
+    if files.is_empty() {
+        eprintln!("No program file specified.");
+        return;
+    }
+    let prog_path = files[0];
+    let prog_name = program_name(prog_path);
+
+    let rom_data = match load_rom() {
+        Some(data) => data,
+        None => return,
+    };
+    let mut emu = Emu::new();
+    emu.load_rom(&rom_data).expect("Failed to load ROM");
+
+    // Libraries are injected once; the warm state below already has them
+    for file_path in &files[1..] {
+        match fs::read(file_path) {
+            Ok(data) => match emu.send_file(&data) {
+                Ok(count) => eprintln!("Injecting: {} ({} entries)", file_path, count),
+                Err(code) => eprintln!("Injecting: {} ERROR: send_file returned {}", file_path, code),
+            },
+            Err(e) => eprintln!("Failed to read {}: {}", file_path, e),
+        }
+    }
+    emu.enable_debug_ports();
+
+    // Warm state: booted, libraries installed, program not yet sent. Every
+    // reload starts from here, so old copies never pile up in the archive.
+    eprintln!("Booting TI-OS...");
+    let warm = match emu.capture_warm_boot_state(175_000_000) {
+        Ok(warm) => warm,
+        Err(code) => {
+            eprintln!("Failed to capture warm boot state: {}", code);
+            return;
+        }
+    };
+
+    eprintln!("Watching {} (Ctrl-C to stop)", prog_path);
+    let timeout_cycles = timeout_secs * 48_000_000;
+    let mut last_seen = None;
+    loop {
+        // Wait for a new build
+        let modified = match modified_time(prog_path) {
+            Some(time) if Some(time) != last_seen => time,
+            _ => {
+                std::thread::sleep(POLL);
+                continue;
+            }
+        };
+        let data = match read_settled(prog_path) {
+            Some(data) => data,
+            None => continue,
+        };
+        last_seen = Some(modified);
+
+        eprintln!("\n=== Reloading {} ({} bytes) ===", prog_path, data.len());
+        if let Err(code) = emu.boot_from_warm_state(&warm) {
+            eprintln!("Failed to restore warm state: {}", code);
+            return;
+        }
+        emu.enable_debug_ports();
+        match emu.send_file_live(&data) {
+            Ok(count) => eprintln!("Sent {} entries, rebooting...", count),
+            Err(code) => {
+                eprintln!("ERROR: send_file_live returned {}; waiting for next build", code);
+                continue;
+            }
+        }
+        if emu.run_until_idle(175_000_000).is_none() {
+            eprintln!("Warning: OS did not reach the homescreen, launching anyway");
+        }
+        launch_asm_program(&mut emu, &prog_name);
+
+        // Run until the program ends or the next build lands
+        let mut exec_cycles = 0u64;
+        let mut last_poll = Instant::now();
+        loop {
+            exec_cycles += emu.run_cycles(1_000_000) as u64;
+            for line in emu.take_debug_stdout() {
+                print!("{}", line);
+            }
+            for line in emu.take_debug_stderr() {
+                eprint!("{}", line);
+            }
+            if emu.debug_terminated() {
+                eprintln!("\n[Terminated via null sentinel after {:.2}M cycles]", exec_cycles as f64 / 1e6);
+                break;
+            }
+            if emu.is_off() {
+                eprintln!("\n[Calculator powered off after {:.2}M cycles]", exec_cycles as f64 / 1e6);
+                break;
+            }
+            if exec_cycles >= timeout_cycles {
+                eprintln!("\n[Timeout after {}s, still watching]", timeout_secs);
+                break;
+            }
+            if last_poll.elapsed() >= POLL {
+                last_poll = Instant::now();
+                if modified_time(prog_path).is_some_and(|time| Some(time) != last_seen) {
+                    eprintln!("\n[Rebuilt, restarting]");
+                    break;
+                }
+            }
+        }
+        let _ = std::io::stdout().flush();
+    }
+}
+
+fn cmd_elf(path: &str, timeout_secs: u64, break_at: Option<&str>) {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return;
+        }
+    };
+
+    // Bare metal: no ROM, no TI-OS
+    let mut emu = Emu::new();
+    match emu.load_elf(&data) {
+        Ok(count) => eprintln!(
+            "Loaded {} sections, {} symbols, entry {}",
+            count,
+            emu.symbols().len(),
+            emu.describe_addr(emu.pc())
+        ),
+        Err(code) => {
+            eprintln!("Failed to load {}: error {}", path, code);
+            return;
+        }
+    }
+    emu.enable_debug_ports();
+    if let Some(name) = break_at {
+        if !emu.set_breakpoint_at_symbol(name) {
+            eprintln!("Unknown symbol '{}'", name);
+            return;
+        }
+    }
+
+    let timeout_cycles = timeout_secs * 48_000_000;
+    let mut exec_cycles = 0u64;
+    let reason = loop {
+        let ran = emu.run_cycles(1_000_000);
+        exec_cycles += ran as u64;
+        for line in emu.take_debug_stdout() {
+            print!("{}", line);
+        }
+        for line in emu.take_debug_stderr() {
+            eprint!("{}", line);
+        }
+        if emu.breakpoint_was_hit() {
+            break "breakpoint";
+        }
+        if emu.debug_terminated() {
+            break "null sentinel";
+        }
+        if ran == 0 || emu.is_off() {
+            break "powered off";
+        }
+        if exec_cycles >= timeout_cycles {
+            break "timeout";
+        }
+    };
+    eprintln!(
+        "\n[Stopped ({}) at {} after {:.2}M cycles]",
+        reason,
+        emu.describe_addr(emu.pc()),
+        exec_cycles as f64 / 1e6
+    );
+}
+
 fn cmd_runprog(files: &[&str], post_launch_cycles: u64) {
     // First file must be the .8xp program
     let prog_path = files[0];
@@ -2567,9 +3079,9 @@ fn cmd_rundoom() {
     // Now enable instruction tracing to capture the loop
     println!("  Enabling instruction trace (1000 instructions)...");
     let _ = fs::remove_file("emu.log"); // clear old log
-    emu_core::enable_inst_trace(1000);
+    emu.enable_inst_trace(1000);
     emu.run_cycles(5_000_000); // run a bit with tracing
-    emu_core::disable_inst_trace();
+    emu.disable_inst_trace();
 
     // Read and analyze the trace
     if let Ok(log_data) = fs::read_to_string("emu.log") {
@@ -2895,10 +3407,13 @@ fn cmd_disasm(addr: u32, count: usize) {
         for i in 0..6 {
             bytes[i] = emu.peek_byte(pc + i as u32);
         }
-        let result = disassemble(&bytes, true); // ADL mode
-        let hex: String = bytes[..result.length].iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
-        println!("  {:06X}: {:<18} {}", pc, hex, result.mnemonic);
-        pc += result.length as u32;
+        let instr = decode(&bytes, true); // ADL mode
+        let hex: String = bytes[..instr.length].iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        match instr.target(pc) {
+            Some(target) => println!("  {:06X}: {:<18} {:<24} ; -> {:06X}", pc, hex, instr.to_string(), target),
+            None => println!("  {:06X}: {:<18} {}", pc, hex, instr),
+        }
+        pc += instr.length.max(1) as u32;
     }
 }
 
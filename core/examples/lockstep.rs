@@ -0,0 +1,36 @@
+//! Run our core and CEmu in lockstep until the first divergence.
+//!
+//! Usage: cargo run --release --features cemu-lockstep --example lockstep -- <rom> [steps] [mem_interval]
+
+use emu_core::lockstep::{CemuCore, Lockstep};
+use emu_core::Emu;
+use std::path::Path;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: lockstep <rom> [steps] [mem_interval]");
+        std::process::exit(1);
+    }
+    let rom_path = Path::new(&args[1]);
+    let steps: u64 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(10_000_000);
+    let mem_interval: u64 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(0);
+
+    let rom = std::fs::read(rom_path).expect("failed to read ROM");
+    let mut emu = Emu::new();
+    emu.load_rom(&rom).expect("ROM rejected");
+    emu.power_on();
+    let cemu = CemuCore::load_rom(rom_path).expect("CEmu failed to load ROM");
+
+    let mut ls = Lockstep::new(emu, cemu);
+    if mem_interval > 0 {
+        ls.set_mem_check(mem_interval, 0xD00000..0xD65800);
+    }
+    match ls.run(steps) {
+        Ok(n) => println!("no divergence in {} steps", n),
+        Err(div) => {
+            print!("{}", div);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Compare a CEmu trace log with one of our structured traces and report the
+//! first divergence.
+//!
+//! Usage: cargo run --release --example trace_diff -- <cemu.log> <ours.trace> [context]
+//!
+//! The CEmu log comes from `tools/cemu-test/trace_gen`; ours from
+//! `cargo run --release --example debug -- record <ours.trace>` (binary or
+//! `--jsonl`).
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use emu_core::trace_diff::{self, DEFAULT_CONTEXT};
+use emu_core::trace_record::{self, TraceRecord, MAGIC};
+
+fn read_ours(path: &str) -> Result<Vec<TraceRecord>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    if data.starts_with(MAGIC) {
+        return trace_record::parse_binary(&data).ok_or_else(|| format!("{}: bad or truncated binary trace", path));
+    }
+    BufReader::new(&data[..])
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| TraceRecord::from_json(&line).ok_or_else(|| format!("{}:{}: bad record", path, i + 1)))
+        .collect()
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: trace_diff <cemu.log> <ours.trace> [context]");
+        std::process::exit(1);
+    }
+    let context = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_CONTEXT);
+
+    let ours = read_ours(&args[2]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let cemu = File::open(&args[1]).unwrap_or_else(|e| {
+        eprintln!("{}: {}", args[1], e);
+        std::process::exit(1);
+    });
+
+    match trace_diff::diff(BufReader::new(cemu), ours, context) {
+        Ok(report) => match report.divergence {
+            None => println!("no divergence in {} instructions", report.compared),
+            Some(div) => {
+                print!("{}", div);
+                std::process::exit(2);
+            }
+        },
+        Err(e) => {
+            eprintln!("{}: {}", args[1], e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Instruction decoder: arbitrary opcode bytes in both ADL and Z80 mode.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for adl in [false, true] {
+        let _ = emu_core::disassemble(data, adl);
+    }
+});
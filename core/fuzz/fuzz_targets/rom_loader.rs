@@ -0,0 +1,14 @@
+//! ROM loading: arbitrary images, then a short run so random code exercises
+//! the CPU and bus from reset.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut emu = emu_core::Emu::new();
+    if emu.load_rom(data).is_ok() {
+        emu.power_on();
+        emu.run_cycles(20_000);
+    }
+});
@@ -0,0 +1,33 @@
+//! Save-state loader: arbitrary buffers, plus mutated valid states so the
+//! fuzzer gets past the header checks quickly.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+/// EI; HALT; JR -3
+const TINY_ROM: [u8; 4] = [0xFB, 0x76, 0x18, 0xFD];
+
+fuzz_target!(|data: &[u8]| {
+    let mut emu = emu_core::Emu::new();
+    emu.load_rom(&TINY_ROM).unwrap();
+
+    if emu.load_state(data).is_ok() {
+        emu.run_cycles(10_000);
+    }
+
+    // XOR the input over the body of a valid state (CPU, scheduler and
+    // peripheral snapshots come first)
+    let mut state = vec![0u8; emu.save_state_size()];
+    if let Ok(len) = emu.save_state(&mut state) {
+        state.truncate(len);
+        // Skip the 20-byte header (magic, version, ROM hash, length)
+        let header = 20.min(state.len());
+        for (dst, src) in state[header..].iter_mut().zip(data) {
+            *dst ^= *src;
+        }
+        if emu.load_state(&state).is_ok() {
+            emu.run_cycles(10_000);
+        }
+    }
+});
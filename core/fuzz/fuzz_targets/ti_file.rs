@@ -0,0 +1,16 @@
+//! Variable-file (.8xp/.8xv) parser and the live/archive injection paths.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+/// EI; HALT; JR -3 - enough ROM for send_file to run against
+const TINY_ROM: [u8; 4] = [0xFB, 0x76, 0x18, 0xFD];
+
+fuzz_target!(|data: &[u8]| {
+    let _ = emu_core::ti_file::TiFile::parse(data);
+
+    let mut emu = emu_core::Emu::new();
+    emu.load_rom(&TINY_ROM).unwrap();
+    let _ = emu.send_file(data);
+});
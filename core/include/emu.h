@@ -6,46 +6,408 @@
 extern "C" {
 #endif
 
+// C ABI version; emu_api_version() returns the version the library was built with.
+// Bumped whenever a declaration below changes incompatibly.
+#define EMU_API_VERSION 1
+
+// Threading: every function taking an Emu* locks that instance, so one Emu may be
+// shared between threads, but calls on it are serialized. Distinct instances are
+// independent. Out-pointers and buffers must stay valid for the duration of the call.
+// Errors: int returns are >= 0 on success and negative on failure (-1 null argument
+// unless noted); pointer returns are NULL on failure.
 typedef struct Emu Emu;
 typedef void (*emu_log_cb_t)(const char* message);
+typedef void (*emu_diag_sink_t)(const uint8_t* data, size_t len, void* user);
+// one completed LCD frame, ARGB8888; stride in pixels; pixels valid only during the call
+typedef void (*emu_frame_cb_t)(const uint32_t* pixels, int width, int height, int stride, void* user);
+// receives one modified 64KB flash sector at byte offset into the 4MB image; return 0 on success
+typedef int (*emu_flash_sink_t)(uint32_t offset, const uint8_t* data, size_t len, void* user);
+// CRC32 of one rendered frame (see emu_set_frame_hashing)
+typedef struct {
+    uint64_t frame;    // frames rendered count
+    uint64_t cycle;    // total_cycles when rendered
+    uint32_t crc;
+} EmuFrameHash;
+
+// CPU activity during one LCD frame (see emu_set_cpu_usage_stats)
+typedef struct {
+    uint64_t frame;          // LCD frame number
+    uint64_t cycles;
+    uint64_t halted_cycles;
+    uint32_t wakes;          // wakes from HALT by an interrupt
+    uint32_t wake_sources;   // OR of the interrupt source bits that woke it
+} EmuFrameUsage;
+
+// result of one expression from emu_eval_batch
+typedef struct {
+    int32_t  kind;       // 0 real, 1 complex, 2 list, 3 matrix, 4 string, 5 other, -1 error
+    int32_t  error;      // kind -1: OS error number (8 = SYNTAX, ...), or -1 unsupported
+                         // character, -2 timeout, -3 no Ans, -4 empty expression
+    double   re, im;     // real / complex value
+    uint32_t rows;       // matrix rows, list or string length
+    uint32_t cols;       // matrix columns (1 for lists)
+    char     text[96];   // value as calculator text ("{1,2}", "1+2i") or "ERR:SYNTAX"
+} EmuEvalResult;
+
+// one key press: time until the first rendered frame that changed
+typedef struct {
+    uint32_t row, col;          // keypad matrix position
+    double   emulated_us;       // emulated time from press to changed frame
+    double   host_us;           // host time for the same, -1 if unavailable
+    uint32_t unchanged_frames;  // frames rendered before the change
+} EmuInputLatency;
+
+// summary of all input latency samples since tracking started
+typedef struct {
+    uint64_t samples;
+    uint64_t dropped;           // presses that changed nothing within 2 emulated seconds
+    double   emulated_mean_us, emulated_min_us, emulated_max_us;
+    double   host_mean_us, host_max_us;  // -1 if unavailable
+} EmuLatencyStats;
+
+// user-program write to OS-owned RAM (see emu_sandbox_enable)
+typedef struct {
+    uint64_t cycle;
+    uint32_t pc;       // writing instruction (in RAM)
+    uint32_t addr;
+    uint8_t  old;
+    uint8_t  value;
+    uint8_t  kind;     // 0 system flags, 1 VAT, 2 caller stack, 3 custom
+} EmuSandboxViolation;
+
+// debugger stop (see emu_take_debug_event)
+typedef struct {
+    uint8_t  kind;     // 0 breakpoint, 1 memory watchpoint, 2 port watchpoint, 3 step
+    uint8_t  access;   // watchpoints: 0 read, 1 write, 2 execute
+    uint8_t  value;    // watchpoints: value read or written
+    uint32_t id;       // breakpoint/watchpoint id, 0 for steps
+    uint32_t pc;       // next instruction; for read/write watchpoints, the accessing one
+    uint32_t addr;     // address or port
+} EmuDebugEvent;
+
+// runaway execution detected: kind 0 = stuck loop (interrupts disabled), 1 = reboot loop;
+// context holds registers and recent history and is only valid during the call
+typedef void (*emu_runaway_cb_t)(int kind, uint32_t pc, const char* context, void* user);
+
+// LCD buffer swap: a frame started scanning out from a different base address
+typedef struct {
+    uint64_t cycle;    // total_cycles when the new base was latched
+    uint64_t frame;    // LCD frame count at that point
+    uint32_t old_base;
+    uint32_t new_base;
+} EmuLcdBaseChange;
 
 // lifecycle
+uint32_t emu_api_version(void);
 Emu* emu_create(void);
 void emu_destroy(Emu*);
 void emu_set_log_callback(emu_log_cb_t cb);
+// per-instance log callback (NULL = use the process-wide one above)
+void emu_set_instance_log_callback(Emu*, emu_log_cb_t cb);
+// per-subsystem log verbosity; subsystem: 0 cpu, 1 bus, 2 keypad, 3 lcd, 4 flash, 5 interrupt,
+// 6 panel, 7 sha256, 8 control, <0 all
+// level: 0 off, 1 error, 2 warn, 3 info (default), 4 debug, 5 trace. Returns 0 ok, -1 invalid
+int  emu_set_log_level(Emu*, int subsystem, int level);
+// the same from a filter string: comma-separated "level" (all subsystems) or
+// "subsystem=level" directives applied in order, e.g. "warn,keypad=debug,lcd=off"
+// 0 ok, -1 null, -2 invalid UTF-8, -3 bad directive (earlier ones still applied)
+int  emu_set_log_filter(Emu*, const char* filter);
 
 // ROM loading (bytes only)
 int  emu_load_rom(Emu*, const uint8_t* data, size_t len); // 0 ok, else error code
+// validating load: rejects anything but a full 4MB dump with boot code; current ROM kept on error
+// 0 ok, -2 empty, -3 too large, -5 truncated, -6 CEmu state image, -7 no boot code
+int  emu_load_rom_checked(Emu*, const uint8_t* data, size_t len);
+// loaded ROM as JSON {size, boot_code_hash, boot_version, os_present, os_version};
+// writes up to cap bytes (no terminator), returns full length; -10 no ROM
+int  emu_rom_info_json(const Emu*, char* out, size_t cap);
+// replace the ROM in place, keeping callbacks/breakpoints/config; re-powers on if running
+int  emu_swap_rom(Emu*, const uint8_t* data, size_t len); // 0 ok, else error code
+// memory-map ROM file read-only (built with the `mmap` feature); 0 ok, else error code
+int  emu_load_rom_mapped(Emu*, const char* path);
 
 // Send .8xp/.8xv file (injects into flash archive before boot)
 // Must be called after load_rom() and before power_on().
 // Returns: entry count (>=0) or negative error code
 int  emu_send_file(Emu*, const uint8_t* data, size_t len);
+// Same, before or after boot: once running, replaces archived copies and restarts TI-OS
+int  emu_send_variable(Emu*, const uint8_t* data, size_t len);
+// Read a program/appvar/etc. back as a .8x* file (RAM or archive); name is the OS name bytes.
+// Returns bytes written or <0 (-1 not found, -2 unsupported type, -3 buffer too small)
+int  emu_receive_variable(Emu*, const uint8_t* name, size_t name_len, uint8_t* out, size_t cap);
+
+// Load a linked ez80-clang ELF for bare-metal tests (no TI-OS, ROM optional):
+// sections at link addresses, PC at entry in ADL mode, symbols registered
+// Returns: section count (>=0) or negative error code (-11 = not an eZ80 ELF)
+int  emu_load_elf(Emu*, const uint8_t* data, size_t len);
+// CEmu ROM image (4MB flash) + optional RAM dump (ram may be NULL)
+// 0 ok, -2 CEmu state image, -3 bad flash size, -4 bad RAM size
+int  emu_import_cemu(Emu*, const uint8_t* flash, size_t flash_len, const uint8_t* ram, size_t ram_len);
 
 void emu_reset(Emu*);
+// reset variants: 0 = warm (reset button, RAM kept), 1 = RAM clear, 2 = power cycle (same as emu_reset)
+int  emu_reset_with(Emu*, int kind);
+// pause/resume: while paused the run calls execute nothing (stepping still works);
+// waits for a concurrent emu_run_cycles slice to finish. Kept across resets.
+void emu_pause(Emu*);
+void emu_resume(Emu*);
+int  emu_is_paused(const Emu*); // 1 paused, 0 running, -1 null
+// why the calculator last restarted: 0-2 host reset (kind as emu_reset_with), 3 power-on,
+// 4 watchdog, 5 protection violation (addr/pc receive the blocked write and the offending
+// code; either may be NULL), -1 null
+int  emu_last_reset_cause(const Emu*, uint32_t* addr, uint32_t* pc);
+
+// deterministic mode: all nondeterministic inputs derived from seed (call before emu_load_rom)
+void emu_set_deterministic(Emu*, int enabled, uint64_t seed);
+
+// OS patches applied on every load (call before emu_load_rom); text lines "ADDR: ORIG -> REPL" in hex.
+// Returns patch count, -2 bad UTF-8, -(100+line) parse error. Nothing is applied unless all originals match.
+int  emu_set_os_patches(Emu*, const char* text);
+int  emu_os_patch_result(const Emu*); // bytes written, -1 none, -2 original mismatch, -3 invalid patch
 
 // Power on (simulate ON key press+release to wake from reset)
 void emu_power_on(Emu*);
 
 // execution
 int  emu_run_cycles(Emu*, int cycles); // returns executed cycles
+// run until halted waiting for key input; returns elapsed cycles or -1 on timeout
+int64_t emu_run_until_idle(Emu*, uint64_t max_cycles);
+// run until the next LCD vsync and render; returns elapsed cycles
+int  emu_run_frame(Emu*);
 
-// framebuffer (owned by core), ARGB8888
+// framebuffer (owned by core), ARGB8888, with the color profile applied
 const uint32_t* emu_framebuffer(const Emu*, int* w, int* h);
+// copy of the current frame as RGBA8888; bytes written, -1 null, -2 cap too small
+int  emu_get_framebuffer(const Emu*, uint8_t* out, size_t cap);
+// frames rendered since reset; unchanged means no new frame to blit
+uint64_t emu_get_frame_counter(const Emu*);
+// render each completed LCD frame and pass it to cb from inside emu_run_cycles,
+// for event-driven drawing instead of polling; NULL cb removes it
+void emu_set_frame_callback(Emu*, emu_frame_cb_t cb, void* user);
+// SPI panel frame memory (pixels drawn with RAMWR), ARGB8888
+const uint32_t* emu_panel_framebuffer(const Emu*, int* w, int* h);
+// color profile applied to presented frames: 0 ideal sRGB (default), 1 CE panel approximation
+int  emu_set_color_profile(Emu*, int profile); // 0 ok, -1 invalid
 
 // input
 void emu_set_key(Emu*, int row, int col, int down);
+// press, then release after ms emulated milliseconds (during emu_run_cycles); -2 no key there
+int  emu_tap_key(Emu*, int row, int col, uint32_t ms);
+// type text on the keypad (2nd/ALPHA added as needed, "\n" = ENTER), running the emulator
+// meanwhile; 0 ok, -2 invalid UTF-8, -3 a character has no key (nothing typed)
+int  emu_type_text(Emu*, const char* text);
+// homescreen text from the OS text buffer as UTF-8, rows joined with '\n';
+// writes up to cap bytes (no terminator), returns full length
+int  emu_read_screen_text(Emu*, char* out, size_t cap);
+// ON key (power button): press powers on, wakes a halted CPU (even with interrupts
+// disabled) and wakes the calculator from off; down non-zero = pressed
+void emu_set_on_key(Emu*, int down);
+
+// cheats: memory pokes re-applied every frame; ids are >= 0, -1 = error
+int  emu_cheat_add(Emu*, uint32_t addr, uint8_t value);
+// only applied while the byte at cond_addr equals cond_value
+int  emu_cheat_add_conditional(Emu*, uint32_t addr, uint8_t value, uint32_t cond_addr, uint8_t cond_value);
+int  emu_cheat_set_enabled(Emu*, int id, int enabled); // 0 ok, -1 unknown id
+int  emu_cheat_remove(Emu*, int id); // 0 ok, -1 unknown id
+void emu_cheat_clear(Emu*);
+// when cheats apply: 0 start of each emu_run_cycles (default), 1 LCD vblank. 0 ok, -1 invalid
+int  emu_cheat_set_point(Emu*, int point);
+
+// USB serial accessory (CDC-ACM device on the emulated USB port, e.g. TI-Innovator Hub)
+void emu_serial_attach(Emu*, int attached);
+int  emu_serial_ready(const Emu*); // 1 once the calculator has configured the accessory
+int  emu_serial_write(Emu*, const uint8_t* data, size_t len); // 0 ok, -1 not attached/null
+int  emu_serial_read(Emu*, uint8_t* out, size_t cap); // bytes the calculator sent: count copied, rest stays queued
 
 // backlight
 uint8_t emu_get_backlight(const Emu*); // 0-255, 0 = off (screen black)
+uint8_t emu_backlight_level(const Emu*); // lit level 0-255: dim the screen by level/255
+
+// battery shown by the OS: percent 0-100 (seen in 20% steps), charging nonzero; kept across resets
+void emu_set_battery(Emu*, int percent, int charging);
 
 // LCD state - 1 if LCD is on (show content), 0 if LCD is off (show black)
 int emu_is_lcd_on(const Emu*);
 
+// execution counters (lifetime, for dashboards / stall detection)
+uint64_t emu_instructions_retired(const Emu*);
+uint64_t emu_interrupts_serviced(const Emu*, int source); // source < 0 = total, 0-31 = source bit
+uint64_t emu_frames_rendered(const Emu*);
+uint64_t emu_reset_count(const Emu*);
+
+// LCD controller frames since reset, and total_cycles of the last one (-1 if none yet)
+uint64_t emu_lcd_frame_count(const Emu*);
+int64_t  emu_last_lcd_frame_cycle(const Emu*);
+// take up to cap queued buffer swaps (oldest first); returns number written
+int emu_take_lcd_base_changes(Emu*, EmuLcdBaseChange* out, size_t cap);
+
+// host-time profiling (disabled by default)
+// sections: 0=cpu, 1=peripherals, 2=scheduler, 3=keypad, 4=lcd_render, 5=trace
+void     emu_set_profiling(Emu*, int enabled);
+uint64_t emu_profile_nanos(const Emu*, int section);
+
+// adaptive performance governor: report each frame's host wall time; it trades
+// timing precision (accuracy 1 = fast) and frame skip to stay at full speed.
+// frame skip drops presented frames only (framebuffer getters, frame callback)
+void   emu_set_governor(Emu*, int enabled);
+// returns 1 if a knob changed this frame, 0 otherwise
+int    emu_governor_report(Emu*, uint64_t host_micros);
+double emu_governor_speed(const Emu*); // last window's speed (1.0 = real time), -1 if none
+int    emu_frame_skip(const Emu*);
+void   emu_set_frame_skip(Emu*, int skip);
+int    emu_accuracy_mode(const Emu*); // 0 full, 1 fast
+void   emu_set_accuracy_mode(Emu*, int mode);
+
+// real-time pacing: call emu_run_paced with the wall time since the previous call
+// (it renders), then sleep emu_pace_sleep_micros. speed in percent of real time,
+// negative = turbo
+void     emu_set_speed(Emu*, int percent);
+int      emu_run_paced(Emu*, uint64_t host_micros); // returns executed cycles
+uint64_t emu_pace_sleep_micros(const Emu*);
+
+// background flash persistence: write modified sectors every interval_ms of emulated
+// time (0 = never) and/or once a burst of flash writes finishes; cb NULL = off
+void emu_set_flash_persistence(Emu*, emu_flash_sink_t cb, void* user, uint32_t interval_ms, int on_write_complete);
+// same, updating an existing image file in place; 0 ok, -4 cannot open
+int  emu_set_flash_persistence_file(Emu*, const char* path, uint32_t interval_ms, int on_write_complete);
+int  emu_flush_flash(Emu*); // sectors written, -2 sink failed
+// pull-based alternative: copy modified 64KB sectors (cap / 65536 of them) into out,
+// their image offsets into offsets[]; exported sectors stop being dirty
+int  emu_flash_dirty_count(const Emu*);
+int  emu_flash_export_dirty(Emu*, uint8_t* out, size_t cap, uint32_t* offsets); // sectors copied
+
+// runaway watchdog: cb is called from inside emu_run_cycles (do not call back into the
+// emulator); stuck_ms = emulated ms at 48MHz before a stuck loop is reported (0 = 5s); cb NULL = off
+void emu_set_runaway_watchdog(Emu*, emu_runaway_cb_t cb, void* user, uint32_t stuck_ms);
+
+// ASM sandbox: report writes by code running from RAM to the system flags, VAT, and the
+// stack above the current SP (enable right before launching the program), plus count extra
+// inclusive (start, end) pairs in ranges
+int  emu_sandbox_enable(Emu*, const uint32_t* ranges, size_t count);
+void emu_sandbox_disable(Emu*);
+int  emu_take_sandbox_violations(Emu*, EmuSandboxViolation* out, size_t cap);
+
+// debugger: runs stop before a breakpoint and after an instruction touching a watchpoint;
+// ids are > 0. access bits: 1 read, 2 write, 4 execute (memory only); -2 empty range/bits.
+// Needs the debugger feature.
+int  emu_add_breakpoint(Emu*, uint32_t pc);
+int  emu_remove_breakpoint(Emu*, uint32_t id); // 0 ok, -1 not found
+int  emu_add_watchpoint(Emu*, uint32_t start, uint32_t end, int access);
+int  emu_add_port_watchpoint(Emu*, uint16_t start, uint16_t end, int access);
+int  emu_remove_watchpoint(Emu*, uint32_t id); // 0 ok, -1 not found
+// 1 and fills out if the last run stopped for the debugger, else 0
+int  emu_take_debug_event(Emu*, EmuDebugEvent* out);
+// steps return 1 and fill out (may be NULL) when stopped, 0 if max_cycles ran out
+int  emu_step_into(Emu*, EmuDebugEvent* out);
+int  emu_step_over(Emu*, uint32_t max_cycles, EmuDebugEvent* out);
+int  emu_step_out(Emu*, uint32_t max_cycles, EmuDebugEvent* out);
+
+// block memory access (addresses wrap at 24 bits); side_effects != 0 goes through the
+// CPU's bus path (read-sensitive MMIO, flash commands, protection), 0 peeks/pokes.
+// Emulated timing is not affected.
+int  emu_read_memory(Emu*, uint32_t addr, uint8_t* buf, size_t len, int side_effects);
+int  emu_write_memory(Emu*, uint32_t addr, const uint8_t* data, size_t len, int side_effects);
+
+// frame hash stream: CRC32 of each rendered frame, or of the w x h rectangle at (x, y)
+// (w or h 0 = whole screen); -2 rectangle off screen
+int  emu_set_frame_hashing(Emu*, int enabled, uint32_t x, uint32_t y, uint32_t w, uint32_t h);
+int  emu_take_frame_hashes(Emu*, EmuFrameHash* out, size_t cap);
+// 64-bit hash of the last rendered frame; equal screens hash equal
+uint64_t emu_frame_hash(const Emu*);
+// PNG of the last rendered frame, written only if it fits in cap; returns its length
+// (call with out NULL, cap 0 to size the buffer). Needs the png feature.
+int  emu_screenshot_png(const Emu*, uint8_t* out, size_t cap);
+
+// Chrome trace-event export (Perfetto / chrome://tracing): scheduler events, interrupts,
+// LCD frames, and optionally bcalls; max_events 0 = default limit
+void emu_chrome_trace_start(Emu*, int bcalls, uint32_t max_events);
+void emu_chrome_trace_stop(Emu*);
+int  emu_chrome_trace_save(const Emu*, const char* path); // bytes, -2 nothing recorded, -4 write failed
+
+// animated screen recording to .gif or .png/.apng (APNG), sampled at fps (max 50) frames per
+// emulated second as frames are rendered; needs the recording feature
+int  emu_start_recording(Emu*, const char* path, uint32_t fps); // -2 unknown extension, -4 create failed
+int  emu_stop_recording(Emu*); // frames written, -2 not recording, -4 write failed
+
+// input recording and deterministic replay: key events stamped with the instructions and
+// cycles since recording started, re-injected at the same points relative to emu_play_replay
+// (start both from the same save state). emu_input_recording copies the replay file if it
+// fits in cap and returns its length, -2 if not recording
+void emu_start_input_recording(Emu*);
+int  emu_input_recording(const Emu*, uint8_t* out, size_t cap);
+void emu_stop_input_recording(Emu*);
+int  emu_play_replay(Emu*, const uint8_t* data, size_t len); // events, -102 malformed, -103 bad version
+
+// virtual link cable: the calculator end of a DUSB silent link. emu_link_send takes the bytes
+// a host writes (raw packets carrying RTS/VAR_REQ etc.); variables are stored or read back
+// immediately and the replies queued for emu_link_recv, which copies up to cap bytes
+int  emu_link_send(Emu*, const uint8_t* data, size_t len);
+int  emu_link_recv(Emu*, uint8_t* out, size_t cap); // bytes copied
+
+// skin-composited screenshot of the last frame: skin is skin_w x skin_h ARGB8888,
+// screen_rect [x, y, w, h] (NULL = none), keys key_count x [row, col, x, y, w, h];
+// held keys tinted with highlight (ARGB, alpha = opacity, 0 = off); out holds skin_w * skin_h
+int  emu_render_skin(const Emu*, const uint32_t* skin, uint32_t skin_w, uint32_t skin_h,
+                     const uint32_t* screen_rect, const uint32_t* keys, size_t key_count,
+                     uint32_t highlight, uint32_t* out);
+
+// per-frame CPU utilization: halted vs executing cycles per LCD frame
+void emu_set_cpu_usage_stats(Emu*, int enabled);
+int  emu_take_frame_usage(Emu*, EmuFrameUsage* out, size_t cap);
+int  emu_cpu_busy_percent(const Emu*); // last frame, -1 none yet
+
+// input-to-photon latency: emu_set_key press -> first changed frame rendered by emu_run_cycles
+void emu_set_latency_tracking(Emu*, int enabled);
+int  emu_input_latency_stats(const Emu*, EmuLatencyStats* out); // 0 ok, -1 not tracking
+int  emu_take_input_latency(Emu*, EmuInputLatency* out, size_t cap);
+
+// evaluate expressions through TI-OS on the homescreen (OS must be booted);
+// "~" is negation, words like sin( sqrt( pi Ans are typed as tokens
+// returns results written (one per expression), -1 on null argument
+int  emu_eval_batch(Emu*, const char* const* exprs, size_t count, EmuEvalResult* out);
+
+// exam mode (Press-to-Test): enter simulates [left]+[right]+[ON] from a power cycle
+void emu_enter_exam_mode(Emu*);
+void emu_exit_exam_mode(Emu*);
+int  emu_exam_mode_status(Emu*); // bit 0 active, bit 1 LED lit
+// where the OS records exam mode (OS-version specific); bit outside 0-7 = unknown
+int  emu_set_exam_flag(Emu*, uint32_t addr, int bit);
+
 // optional save state (buffer-based)
 size_t emu_save_state_size(const Emu*);
 int    emu_save_state(const Emu*, uint8_t* out, size_t cap); // bytes written or <0
 int    emu_load_state(Emu*, const uint8_t* data, size_t len);
+uint64_t emu_state_hash(const Emu*); // cheap divergence check, equal states hash equal
+
+// rewind: a delta-compressed snapshot every interval_frames LCD frames, newest capacity kept
+// (0 for either stops recording); emu_rewind steps back at least frames: frames rewound or <0
+void    emu_set_rewind(Emu*, uint32_t interval_frames, uint32_t capacity);
+int64_t emu_rewind(Emu*, uint32_t frames);
+
+// real-time clock: times are Unix seconds in the time zone the calculator shows.
+// emu_set_rtc_time sets it once (and stops host sync); host sync seeds it from the
+// host clock plus utc_offset_secs and corrects drift each run (0 ok, -1 no host clock)
+void    emu_set_rtc_time(Emu*, int64_t unix_secs);
+int     emu_set_rtc_host_sync(Emu*, int enabled, int32_t utc_offset_secs);
+int64_t emu_rtc_time(const Emu*);
+
+// diagnostic bundles (save state + history + interrupt log + unknown-access report);
+// the sink receives one whole bundle per call. Setting a sink arms one automatic bundle
+// on the next internal error or protection violation. NULL cb removes the sink.
+void   emu_set_diagnostic_sink(Emu*, emu_diag_sink_t cb, void* user);
+int    emu_dump_diagnostics(Emu*); // user-requested bundle: bytes written, -2 no sink, -4 write failed
+
+// warm boot: capture a post-boot snapshot once (buffer >= emu_save_state_size),
+// then start later sessions from it instead of running the ROM boot
+int    emu_capture_warm_boot(Emu*, uint8_t* out, size_t cap, uint64_t max_cycles); // bytes written or <0
+int    emu_boot_warm(Emu*, const uint8_t* data, size_t len); // 0 ok, else error code
+
+// backend selection (not exported in ios_prefixed builds, where the app provides it);
+// this library has the single backend "rust"
+const char* emu_backend_get_available(void); // comma-separated names
+const char* emu_backend_get_current(void);
+int  emu_backend_set(const char* name); // 0 ok, -1 unknown backend
+int  emu_backend_count(void);
 
 #ifdef __cplusplus
 }
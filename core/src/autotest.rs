@@ -0,0 +1,627 @@
+//! Test runner for CEmu autotester JSON files
+//!
+//! Runs the community's existing CEmu test scripts against this core
+//! unchanged (`cargo run --example autotest -- test.json`). A test names a
+//! ROM and files to transfer, a target program, a sequence of steps, and
+//! the CRC32 checks those steps refer to:
+//!
+//! ```text
+//! {
+//!   "rom": "84pce.rom",
+//!   "transfer_files": ["DEMO.8xp"],
+//!   "target": { "name": "DEMO", "isASM": true },
+//!   "sequence": ["action|launch", "delay|500", "hashWait|1", "key|enter", "hash|2"],
+//!   "hashes": {
+//!     "1": { "description": "title", "start": "vram_start", "size": "vram_16_size",
+//!            "expected_CRCs": ["FFAF89BA"], "timeout_ms": 2000 },
+//!     "2": { "description": "score", "start": "0xD031F6", "size": 3, "expected_CRCs": ["26F2FD5C"] }
+//!   }
+//! }
+//! ```
+//!
+//! Steps are `action|launch` (type `[Asm(]prgmNAME` and ENTER on the
+//! homescreen), `action|reset`, `delay|<ms>`, `key|<name>` (press and
+//! release), `hash|<id>` (check now) and `hashWait|<id>` (check until it
+//! matches or `timeout_ms` runs out). Key names are CEmu's (`yequ`, `wind`,
+//! `sq`, `inv`, `comma`, `xton`, `dot`, `lpar`, `rpar`, `chs`, `add`, `sub`,
+//! `mul`, `div`, `pow`) or the `pipe` protocol's. A check passes when the
+//! CRC32 of the memory range is any of the expected values; `start` and
+//! `size` are numbers, hex strings, or CEmu's `vram_*` names.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::emu::Emu;
+use crate::eval::{KEY_CLEAR, KEY_ENTER};
+use crate::frame_hash::Crc32;
+use crate::pipe::{self, PipeKey};
+
+/// `hashWait` limit when a check sets no `timeout_ms`
+pub const DEFAULT_TIMEOUT_MS: u32 = 2000;
+/// How long the OS may take to boot or take a launch key, in emulated seconds
+pub const BOOT_TIMEOUT_SECS: f64 = 10.0;
+/// Interval between `hashWait` checks
+const HASH_POLL_MS: f64 = 10.0;
+
+/// OS key codes for `action|launch` (CEmu's)
+const KEY_ASM: u16 = 0x9CFC;
+const KEY_PRGM: u16 = 0xDA;
+
+/// CEmu key names that differ from the pipe protocol's
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("yequ", "y="), ("wind", "window"), ("sq", "square"), ("inv", "recip"),
+    ("comma", ","), ("xton", "xttn"), ("dot", "."), ("lpar", "("), ("rpar", ")"),
+    ("chs", "neg"), ("add", "+"), ("sub", "-"), ("mul", "*"), ("div", "/"), ("pow", "^"),
+];
+
+/// Named `start` and `size` values
+const NAMED_VALUES: &[(&str, u32)] = &[
+    ("vram_start", 0xD40000),
+    ("vram_16_size", 320 * 240 * 2),
+    ("vram_8_size", 320 * 240),
+    ("vram_4_size", 320 * 240 / 2),
+    ("vram_2_size", 320 * 240 / 4),
+    ("vram_1_size", 320 * 240 / 8),
+];
+
+/// Why a test could not be loaded or run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutotestError {
+    /// Not valid JSON: byte offset and reason
+    Json(usize, &'static str),
+    /// Valid JSON that is not a valid test
+    Config(String),
+    /// A file could not be read or transferred
+    File(String),
+    /// The OS did not boot or take a launch key in time
+    Timeout,
+}
+
+impl fmt::Display for AutotestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutotestError::Json(offset, reason) => write!(f, "invalid JSON at byte {}: {}", offset, reason),
+            AutotestError::Config(reason) => write!(f, "invalid test: {}", reason),
+            AutotestError::File(reason) => f.write_str(reason),
+            AutotestError::Timeout => f.write_str("the OS did not respond in time"),
+        }
+    }
+}
+
+/// The program `action|launch` runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub name: String,
+    /// Run with `Asm(`
+    pub is_asm: bool,
+}
+
+/// One entry of the sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Launch,
+    Reset,
+    Delay(u32),
+    Key(PipeKey),
+    Hash(String),
+    HashWait(String),
+}
+
+/// A CRC32 check of a memory range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashCheck {
+    pub description: String,
+    pub start: u32,
+    pub size: u32,
+    pub expected: Vec<u32>,
+    /// `hashWait` limit
+    pub timeout_ms: u32,
+}
+
+/// A parsed autotester JSON file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Autotest {
+    /// ROM path, relative to the JSON file
+    pub rom: Option<String>,
+    /// Files to transfer before the sequence, relative to the JSON file
+    pub transfer_files: Vec<String>,
+    pub target: Option<Target>,
+    pub sequence: Vec<Step>,
+    pub hashes: BTreeMap<String, HashCheck>,
+}
+
+/// Outcome of one `hash` or `hashWait` step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub id: String,
+    pub description: String,
+    /// CRC32 at the last check
+    pub actual: u32,
+    pub passed: bool,
+}
+
+/// Results of a run, in sequence order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub checks: Vec<CheckResult>,
+}
+
+impl Report {
+    pub fn failed(&self) -> usize {
+        self.checks.iter().filter(|c| !c.passed).count()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+impl Autotest {
+    /// Parse an autotester JSON file
+    pub fn parse(json: &str) -> Result<Self, AutotestError> {
+        let root = Parser::new(json).document()?;
+        let config = AutotestError::Config;
+
+        let rom = match root.get("rom") {
+            Some(value) => Some(value.as_str().ok_or_else(|| config("'rom' must be a string".into()))?.to_string()),
+            None => None,
+        };
+        let transfer_files = match root.get("transfer_files") {
+            Some(Json::Array(files)) => files
+                .iter()
+                .map(|f| f.as_str().map(str::to_string).ok_or_else(|| config("'transfer_files' must hold strings".into())))
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(config("'transfer_files' must be an array".into())),
+            None => Vec::new(),
+        };
+        let target = match root.get("target") {
+            Some(target) => {
+                let name = target.get("name").and_then(Json::as_str).unwrap_or("").to_string();
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+                    return Err(config(format!("target name '{}' must be A-Z and 0-9", name)));
+                }
+                let is_asm = matches!(target.get("isASM"), Some(Json::Bool(true)));
+                Some(Target { name, is_asm })
+            }
+            None => None,
+        };
+
+        let mut hashes = BTreeMap::new();
+        if let Some(entries) = root.get("hashes") {
+            let Json::Object(entries) = entries else {
+                return Err(config("'hashes' must be an object".into()));
+            };
+            for (id, check) in entries {
+                hashes.insert(id.clone(), parse_check(id, check)?);
+            }
+        }
+
+        let Some(Json::Array(steps)) = root.get("sequence") else {
+            return Err(config("'sequence' must be an array".into()));
+        };
+        let mut sequence = Vec::with_capacity(steps.len());
+        for step in steps {
+            let text = step.as_str().ok_or_else(|| config("sequence steps must be strings".into()))?;
+            let step = parse_step(text).ok_or_else(|| config(format!("unknown step '{}'", text)))?;
+            match &step {
+                Step::Hash(id) | Step::HashWait(id) if !hashes.contains_key(id) => {
+                    return Err(config(format!("step '{}' refers to a missing hash", text)));
+                }
+                Step::Launch if target.is_none() => return Err(config("'action|launch' needs a target".into())),
+                _ => {}
+            }
+            sequence.push(step);
+        }
+
+        Ok(Self { rom, transfer_files, target, sequence, hashes })
+    }
+
+    /// Transfer the test's files (relative to `dir`), power on, and wait for
+    /// the OS to boot. Call after loading the ROM.
+    pub fn boot(&self, emu: &mut Emu, dir: &Path) -> Result<(), AutotestError> {
+        for file in &self.transfer_files {
+            let path = dir.join(file);
+            let data = std::fs::read(&path).map_err(|e| AutotestError::File(format!("{}: {}", path.display(), e)))?;
+            emu.send_variable(&data)
+                .map_err(|code| AutotestError::File(format!("{}: transfer failed ({})", path.display(), code)))?;
+        }
+        emu.power_on();
+        wait_for_os(emu)
+    }
+
+    /// Run the sequence, checking hashes as it goes
+    pub fn run(&self, emu: &mut Emu) -> Result<Report, AutotestError> {
+        let mut report = Report::default();
+        for step in &self.sequence {
+            match step {
+                Step::Launch => self.launch(emu)?,
+                Step::Reset => {
+                    emu.reset();
+                    emu.power_on();
+                    wait_for_os(emu)?;
+                }
+                Step::Delay(ms) => {
+                    emu.run_ms(*ms as f64);
+                }
+                Step::Key(key) => pipe::press(emu, *key),
+                Step::Hash(id) => report.checks.push(check(emu, id, &self.hashes[id], 0)),
+                Step::HashWait(id) => {
+                    let hash = &self.hashes[id];
+                    report.checks.push(check(emu, id, hash, hash.timeout_ms));
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Type `[Asm(]prgmNAME` and ENTER on the homescreen
+    fn launch(&self, emu: &mut Emu) -> Result<(), AutotestError> {
+        let target = self.target.as_ref().expect("launch is only parsed with a target");
+        let mut keys = vec![KEY_CLEAR];
+        if target.is_asm {
+            keys.push(KEY_ASM);
+        }
+        keys.push(KEY_PRGM);
+        keys.extend(target.name.chars().map(letter_key));
+        keys.push(KEY_ENTER);
+
+        let limit = (BOOT_TIMEOUT_SECS * emu.cpu_clock_hz()) as u64;
+        for key in keys {
+            if !emu.send_key_wait(key, limit) {
+                return Err(AutotestError::Timeout);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn wait_for_os(emu: &mut Emu) -> Result<(), AutotestError> {
+    let limit = (BOOT_TIMEOUT_SECS * emu.cpu_clock_hz()) as u64;
+    emu.run_until_idle(limit).map(|_| ()).ok_or(AutotestError::Timeout)
+}
+
+/// OS key code for a program name character (A-Z or 0-9)
+fn letter_key(c: char) -> u16 {
+    match c {
+        '0'..='9' => 0x8E + (c as u16 - '0' as u16),
+        _ => 0x9A + (c as u16 - 'A' as u16),
+    }
+}
+
+/// Check `hash` now, then every `HASH_POLL_MS` until it passes or
+/// `timeout_ms` has run
+fn check(emu: &mut Emu, id: &str, hash: &HashCheck, timeout_ms: u32) -> CheckResult {
+    let mut buf = vec![0; hash.size as usize];
+    let mut waited = 0.0;
+    loop {
+        emu.read_memory(hash.start, &mut buf, false);
+        let mut crc = Crc32::new();
+        crc.update(&buf);
+        let actual = crc.finish();
+        let passed = hash.expected.contains(&actual);
+        if passed || waited >= timeout_ms as f64 {
+            return CheckResult { id: id.to_string(), description: hash.description.clone(), actual, passed };
+        }
+        emu.run_ms(HASH_POLL_MS);
+        waited += HASH_POLL_MS;
+    }
+}
+
+fn parse_step(text: &str) -> Option<Step> {
+    let (command, arg) = text.split_once('|')?;
+    Some(match command {
+        "action" => match arg {
+            "launch" => Step::Launch,
+            "reset" => Step::Reset,
+            _ => return None,
+        },
+        "delay" => Step::Delay(arg.parse().ok()?),
+        "key" => Step::Key(key_by_name(arg)?),
+        "hash" => Step::Hash(arg.to_string()),
+        "hashWait" => Step::HashWait(arg.to_string()),
+        _ => return None,
+    })
+}
+
+/// Look up a key by CEmu or pipe name (case-insensitive)
+pub fn key_by_name(name: &str) -> Option<PipeKey> {
+    let name = name.to_ascii_lowercase();
+    let name = KEY_ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name.as_str(), |(_, pipe_name)| pipe_name);
+    pipe::key_by_name(name)
+}
+
+fn parse_check(id: &str, check: &Json) -> Result<HashCheck, AutotestError> {
+    let err = |reason: &str| AutotestError::Config(format!("hash '{}': {}", id, reason));
+    let value = |field: &str| -> Result<u32, AutotestError> {
+        match check.get(field) {
+            Some(Json::Number(n)) if *n >= 0.0 && *n <= u32::MAX as f64 && n.fract() == 0.0 => Ok(*n as u32),
+            Some(Json::String(s)) => NAMED_VALUES
+                .iter()
+                .find(|(name, _)| name == s)
+                .map(|&(_, v)| v)
+                .or_else(|| parse_hex(s))
+                .ok_or_else(|| err(&format!("invalid '{}'", field))),
+            _ => Err(err(&format!("missing or invalid '{}'", field))),
+        }
+    };
+    let expected = match check.get("expected_CRCs") {
+        Some(Json::Array(crcs)) if !crcs.is_empty() => crcs
+            .iter()
+            .map(|crc| crc.as_str().and_then(parse_hex).ok_or_else(|| err("CRCs must be hex strings")))
+            .collect::<Result<_, _>>()?,
+        _ => return Err(err("missing 'expected_CRCs'")),
+    };
+    let timeout_ms = match check.get("timeout_ms") {
+        Some(_) => value("timeout_ms")?,
+        None => DEFAULT_TIMEOUT_MS,
+    };
+    Ok(HashCheck {
+        description: check.get("description").and_then(Json::as_str).unwrap_or("").to_string(),
+        start: value("start")?,
+        size: value("size")?,
+        expected,
+        timeout_ms,
+    })
+}
+
+/// Hex with or without `0x` (CRCs and addresses)
+fn parse_hex(text: &str) -> Option<u32> {
+    let digits = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// A JSON value (objects keep their key order)
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Recursive-descent JSON parser
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    /// A whole document holding one object
+    fn document(mut self) -> Result<Json, AutotestError> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        if self.pos != self.text.len() {
+            return Err(self.error("trailing characters"));
+        }
+        if !matches!(value, Json::Object(_)) {
+            return Err(AutotestError::Config("the top level must be an object".into()));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, reason: &'static str) -> AutotestError {
+        AutotestError::Json(self.pos, reason)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, reason: &'static str) -> Result<(), AutotestError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(reason));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, AutotestError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => {
+                for (word, value) in [("true", Json::Bool(true)), ("false", Json::Bool(false)), ("null", Json::Null)] {
+                    if self.text[self.pos..].starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, AutotestError> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.expect(b':', "expected ':'")?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, AutotestError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, AutotestError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let Some(c) = rest.chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    out.push(match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let hex = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error("bad \\u escape"))?;
+                            let code = u32::from_str_radix(hex, 16).map_err(|_| self.error("bad \\u escape"))?;
+                            self.pos += 4;
+                            char::from_u32(code).unwrap_or('\u{FFFD}')
+                        }
+                        _ => return Err(self.error("bad escape")),
+                    });
+                }
+                _ => out.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, AutotestError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        self.text[start..self.pos].parse().map(Json::Number).map_err(|_| AutotestError::Json(start, "invalid number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = r#"{
+        "rom": "84pce.rom",
+        "transfer_files": ["DEMO.8xp"],
+        "target": { "name": "DEMO", "isASM": true },
+        "sequence": ["action|launch", "delay|20", "key|enter", "key|Yequ", "hash|ram", "hashWait|ram"],
+        "hashes": {
+            "ram": { "description": "A \"byte\"", "start": "0xD00000", "size": 4,
+                     "expected_CRCs": ["2144DF1C", "0xdeadbeef"], "timeout_ms": 50 },
+            "vram": { "start": "vram_start", "size": "vram_16_size", "expected_CRCs": ["0"] }
+        }
+    }"#;
+
+    #[test]
+    fn test_parse() {
+        let test = Autotest::parse(TEST).unwrap();
+        assert_eq!(test.rom.as_deref(), Some("84pce.rom"));
+        assert_eq!(test.transfer_files, ["DEMO.8xp"]);
+        assert_eq!(test.target, Some(Target { name: "DEMO".into(), is_asm: true }));
+        assert_eq!(test.sequence[..4], [Step::Launch, Step::Delay(20), Step::Key(PipeKey::Matrix(6, 0)), Step::Key(PipeKey::Matrix(1, 4))]);
+        let ram = &test.hashes["ram"];
+        assert_eq!((ram.description.as_str(), ram.start, ram.size, ram.timeout_ms), ("A \"byte\"", 0xD00000, 4, 50));
+        assert_eq!(ram.expected, [0x2144DF1C, 0xDEADBEEF]);
+        let vram = &test.hashes["vram"];
+        assert_eq!((vram.start, vram.size, vram.timeout_ms), (0xD40000, 153600, DEFAULT_TIMEOUT_MS));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Autotest::parse("{\"sequence\": [}"), Err(AutotestError::Json(14, "expected a value")));
+        assert!(matches!(Autotest::parse("[]"), Err(AutotestError::Config(_))));
+        let bad = |sequence: &str| Autotest::parse(&format!("{{\"sequence\": [\"{}\"], \"hashes\": {{}}}}", sequence));
+        assert!(bad("delay|20").is_ok());
+        assert!(matches!(bad("key|shift"), Err(AutotestError::Config(_))));
+        assert!(matches!(bad("hash|1"), Err(AutotestError::Config(_))));
+        assert!(matches!(bad("action|launch"), Err(AutotestError::Config(_))));
+    }
+
+    #[test]
+    fn test_run_checks_memory() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let test = Autotest::parse(
+            r#"{"sequence": ["hash|zero", "key|enter", "hashWait|ones"],
+                "hashes": {"zero": {"start": 13631488, "size": 4, "expected_CRCs": ["2144DF1C"]},
+                           "ones": {"start": 13631488, "size": 4, "expected_CRCs": ["FFFFFFFF"], "timeout_ms": 30}}}"#,
+        )
+        .unwrap();
+
+        let start = emu.total_cycles();
+        let report = test.run(&mut emu).unwrap();
+        // CRC32 of four zero bytes
+        assert!(report.checks[0].passed);
+        assert_eq!(report.checks[0].actual, 0x2144DF1C);
+        // Polled until the timeout, after the key's 100ms
+        assert!(!report.checks[1].passed);
+        assert_eq!(report.failed(), 1);
+        let ms = (emu.total_cycles() - start) as f64 * 1000.0 / emu.cpu_clock_hz();
+        assert!((130.0..140.0).contains(&ms), "{}", ms);
+    }
+}
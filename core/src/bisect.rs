@@ -0,0 +1,446 @@
+//! Divergence bisection
+//!
+//! Finds the first instruction where two runs stop agreeing, to speed up
+//! triage of accuracy regressions. Runs are compared by `state_hash()`, so
+//! any difference in CPU, peripheral, RAM, or flash state counts.
+//!
+//! Two workflows are supported:
+//!
+//! - In one process, `bisect()` replays two runs (different configurations
+//!   or input recordings) from a fresh start, narrows the first mismatch to a
+//!   checkpoint interval, and then binary-searches the exact instruction.
+//!   Runs always restart from scratch rather than from save states, because
+//!   save states do not capture every peripheral detail.
+//! - Across builds (before/after a core change), each build writes a
+//!   `StateCapture` of hashes and registers at fixed steps; comparing two
+//!   captures gives the window to re-capture at a finer interval until the
+//!   first differing instruction is found.
+
+use std::fmt;
+
+use crate::disasm::disassemble;
+use crate::lockstep::RegSnapshot;
+use crate::Emu;
+
+/// Default checkpoint spacing for `bisect()`
+pub const DEFAULT_INTERVAL: u64 = 1 << 16;
+
+/// RAM compared for the first differing byte in a report
+const RAM_RANGE: std::ops::Range<u32> = 0xD00000..0xD65800;
+
+/// Why a recording or capture could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// What was wrong with it
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Content lines of a text file with their 1-based numbers, comments removed
+fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// A key change applied before instruction `step` (counted from the start)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    /// Instructions executed before the change
+    pub step: u64,
+    /// Keypad matrix row
+    pub row: usize,
+    /// Keypad matrix column
+    pub col: usize,
+    /// Pressed (true) or released
+    pub down: bool,
+}
+
+/// Key input of a run, keyed to instruction counts so replays are exact
+///
+/// Text form, one event per line: `<step> <row> <col> down|up`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputRecording {
+    events: Vec<InputEvent>,
+}
+
+impl InputRecording {
+    /// Recording of `events` (sorted by step; same-step events keep their order)
+    pub fn new(mut events: Vec<InputEvent>) -> Self {
+        events.sort_by_key(|event| event.step);
+        Self { events }
+    }
+
+    /// Events in replay order
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+
+    /// Parse the text form
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut events = Vec::new();
+        for (line, content) in content_lines(text) {
+            let err = |reason| ParseError { line, reason };
+            let fields: Vec<&str> = content.split_whitespace().collect();
+            let [step, row, col, dir] = fields[..] else {
+                return Err(err("expected '<step> <row> <col> down|up'"));
+            };
+            let down = match dir {
+                "down" => true,
+                "up" => false,
+                _ => return Err(err("direction must be 'down' or 'up'")),
+            };
+            events.push(InputEvent {
+                step: step.parse().map_err(|_| err("invalid step"))?,
+                row: row.parse().map_err(|_| err("invalid row"))?,
+                col: col.parse().map_err(|_| err("invalid column"))?,
+                down,
+            });
+        }
+        Ok(Self::new(events))
+    }
+
+    /// The text form
+    pub fn to_text(&self) -> String {
+        self.events
+            .iter()
+            .map(|e| format!("{} {} {} {}\n", e.step, e.row, e.col, if e.down { "down" } else { "up" }))
+            .collect()
+    }
+}
+
+/// An emulator being replayed against a recording
+struct Replay<'a> {
+    emu: Emu,
+    recording: &'a InputRecording,
+    next_event: usize,
+    step: u64,
+}
+
+impl<'a> Replay<'a> {
+    fn new(emu: Emu, recording: &'a InputRecording) -> Self {
+        Self { emu, recording, next_event: 0, step: 0 }
+    }
+
+    /// Execute instructions until `step` have run
+    fn advance_to(&mut self, step: u64) {
+        let events = self.recording.events();
+        while self.step < step {
+            while let Some(event) = events.get(self.next_event).filter(|e| e.step <= self.step) {
+                self.emu.set_key(event.row, event.col, event.down);
+                self.next_event += 1;
+            }
+            self.emu.step();
+            self.step += 1;
+        }
+    }
+}
+
+/// State hash and registers at one step of a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureEntry {
+    /// Instructions executed
+    pub step: u64,
+    /// `state_hash()` at that point
+    pub hash: u64,
+    /// Registers at that point
+    pub regs: RegSnapshot,
+}
+
+impl CaptureEntry {
+    fn of(step: u64, emu: &Emu) -> Self {
+        Self { step, hash: emu.state_hash(), regs: RegSnapshot::of(emu) }
+    }
+}
+
+/// Hashes of one run at fixed steps, for comparing runs across builds
+///
+/// Text form, one entry per line:
+/// `<step> <hash> <pc> <sp> <af> <bc> <de> <hl> <ix> <iy> <mode>` in hex
+/// (except step), where mode bits are ADL, IFF1, IFF2, HALT, then IM.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateCapture {
+    /// Entries in step order
+    pub entries: Vec<CaptureEntry>,
+}
+
+/// Where two captures first disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureDiff {
+    /// Last step where both agreed, if any
+    pub last_agreed: Option<u64>,
+    /// First differing entry in each capture
+    pub first: (CaptureEntry, CaptureEntry),
+}
+
+impl StateCapture {
+    /// Replay `emu` with `recording` and record an entry every `interval`
+    /// instructions from step `from` up to and including `to`
+    pub fn record(emu: Emu, recording: &InputRecording, from: u64, to: u64, interval: u64) -> Self {
+        let interval = interval.max(1);
+        let mut replay = Replay::new(emu, recording);
+        let mut entries = Vec::new();
+        let mut step = from;
+        while step <= to {
+            replay.advance_to(step);
+            entries.push(CaptureEntry::of(step, &replay.emu));
+            step += interval;
+        }
+        Self { entries }
+    }
+
+    /// First entry at a common step where the hashes differ. Entries at
+    /// steps only one capture has are skipped.
+    pub fn first_difference(&self, other: &StateCapture) -> Option<CaptureDiff> {
+        let mut last_agreed = None;
+        let mut theirs = other.entries.iter().peekable();
+        for ours in &self.entries {
+            while theirs.next_if(|t| t.step < ours.step).is_some() {}
+            let Some(t) = theirs.peek().filter(|t| t.step == ours.step) else {
+                continue;
+            };
+            if t.hash != ours.hash {
+                return Some(CaptureDiff { last_agreed, first: (*ours, **t) });
+            }
+            last_agreed = Some(ours.step);
+        }
+        None
+    }
+
+    /// Parse the text form
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut entries = Vec::new();
+        for (line, content) in content_lines(text) {
+            let err = |reason| ParseError { line, reason };
+            let fields: Vec<&str> = content.split_whitespace().collect();
+            if fields.len() != 11 {
+                return Err(err("expected 11 fields"));
+            }
+            let step = fields[0].parse().map_err(|_| err("invalid step"))?;
+            let hash = u64::from_str_radix(fields[1], 16).map_err(|_| err("invalid hash"))?;
+            let mut words = [0u32; 9];
+            for (word, field) in words.iter_mut().zip(&fields[2..]) {
+                *word = u32::from_str_radix(field, 16).map_err(|_| err("invalid register"))?;
+            }
+            let [pc, sp, af, bc, de, hl, ix, iy, mode] = words;
+            let regs = RegSnapshot {
+                pc,
+                sp,
+                bc,
+                de,
+                hl,
+                ix,
+                iy,
+                a: (af >> 8) as u8,
+                f: af as u8,
+                adl: mode & 1 != 0,
+                iff1: mode & 2 != 0,
+                iff2: mode & 4 != 0,
+                halted: mode & 8 != 0,
+                im: (mode >> 4) as u8 & 3,
+            };
+            entries.push(CaptureEntry { step, hash, regs });
+        }
+        Ok(Self { entries })
+    }
+
+    /// The text form
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# step hash pc sp af bc de hl ix iy mode\n");
+        for e in &self.entries {
+            let r = &e.regs;
+            let mode = r.adl as u32 | (r.iff1 as u32) << 1 | (r.iff2 as u32) << 2 | (r.halted as u32) << 3 | (r.im as u32) << 4;
+            text.push_str(&format!(
+                "{} {:016X} {:06X} {:06X} {:02X}{:02X} {:06X} {:06X} {:06X} {:06X} {:06X} {:X}\n",
+                e.step, e.hash, r.pc, r.sp, r.a, r.f, r.bc, r.de, r.hl, r.ix, r.iy, mode
+            ));
+        }
+        text
+    }
+}
+
+/// The first instruction after which two runs differ
+#[derive(Debug, Clone)]
+pub struct BisectReport {
+    /// Instructions both runs executed before the diverging one
+    /// (0 with no instruction if the runs already differ at the start)
+    pub step: u64,
+    /// Registers before the diverging instruction in each run
+    pub before: (RegSnapshot, RegSnapshot),
+    /// Registers after it in each run
+    pub after: (RegSnapshot, RegSnapshot),
+    /// Disassembly of the diverging instruction in each run
+    pub instruction: Option<(String, String)>,
+    /// First differing RAM byte after it: (address, run A, run B)
+    pub memory: Option<(u32, u8, u8)>,
+}
+
+impl fmt::Display for BisectReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((a, b)) = &self.instruction else {
+            return writeln!(f, "runs differ before the first instruction");
+        };
+        writeln!(f, "divergence at step {}", self.step)?;
+        writeln!(f, "  A: {:06X}  {}", self.before.0.pc, a)?;
+        writeln!(f, "  B: {:06X}  {}", self.before.1.pc, b)?;
+        for line in self.before.0.diff(&self.before.1) {
+            writeln!(f, "  before {}", line.replace("ours=", "A=").replace("ref=", "B="))?;
+        }
+        for line in self.after.0.diff(&self.after.1) {
+            writeln!(f, "  after {}", line.replace("ours=", "A=").replace("ref=", "B="))?;
+        }
+        if let Some((addr, a, b)) = self.memory {
+            writeln!(f, "  mem[{:06X}]: A={:02X} B={:02X}", addr, a, b)?;
+        }
+        Ok(())
+    }
+}
+
+fn disassemble_at(emu: &mut Emu, pc: u32) -> String {
+    let bytes: Vec<u8> = (0..6).map(|i| emu.peek_byte(pc.wrapping_add(i))).collect();
+    let result = disassemble(&bytes, emu.adl());
+    format!("{:<12} {}", result.bytes, result.mnemonic)
+}
+
+/// Find the first instruction within `max_steps` after which two runs' state
+/// hashes differ. Each run starts from a fresh emulator made by its `start`
+/// closure and is fed its recording. Checkpoints are `interval` instructions
+/// apart. Returns the steps compared if the runs never diverge.
+pub fn bisect(
+    mut start_a: impl FnMut() -> Emu,
+    recording_a: &InputRecording,
+    mut start_b: impl FnMut() -> Emu,
+    recording_b: &InputRecording,
+    max_steps: u64,
+    interval: u64,
+) -> Result<u64, Box<BisectReport>> {
+    let interval = interval.max(1);
+    let mut run = |step: u64| {
+        let mut a = Replay::new(start_a(), recording_a);
+        let mut b = Replay::new(start_b(), recording_b);
+        a.advance_to(step);
+        b.advance_to(step);
+        (a, b)
+    };
+
+    let (mut a, mut b) = run(0);
+    if a.emu.state_hash() != b.emu.state_hash() {
+        let regs = (RegSnapshot::of(&a.emu), RegSnapshot::of(&b.emu));
+        return Err(Box::new(BisectReport { step: 0, before: regs, after: regs, instruction: None, memory: None }));
+    }
+
+    // Coarse pass: the first checkpoint where the hashes differ
+    let mut lo = 0;
+    let mut hi = loop {
+        if lo == max_steps {
+            return Ok(max_steps);
+        }
+        let next = (lo + interval).min(max_steps);
+        a.advance_to(next);
+        b.advance_to(next);
+        if a.emu.state_hash() != b.emu.state_hash() {
+            break next;
+        }
+        lo = next;
+    };
+
+    // Binary search within the interval, replaying from the start each time
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        let (a, b) = run(mid);
+        if a.emu.state_hash() == b.emu.state_hash() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    let (mut a, mut b) = run(lo);
+    let before = (RegSnapshot::of(&a.emu), RegSnapshot::of(&b.emu));
+    let instruction = (disassemble_at(&mut a.emu, before.0.pc), disassemble_at(&mut b.emu, before.1.pc));
+    a.advance_to(hi);
+    b.advance_to(hi);
+    let after = (RegSnapshot::of(&a.emu), RegSnapshot::of(&b.emu));
+    let memory = RAM_RANGE.clone().find_map(|addr| {
+        let (x, y) = (a.emu.peek_byte(addr), b.emu.peek_byte(addr));
+        (x != y).then_some((addr, x, y))
+    });
+    Err(Box::new(BisectReport { step: lo, before, after, instruction: Some(instruction), memory }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD A,5; INC A; LD (D00000),A; JR -7
+    const ROM: [u8; 9] = [0x3E, 0x05, 0x3C, 0x32, 0x00, 0x00, 0xD0, 0x18, 0xF7];
+
+    fn start() -> Emu {
+        let mut emu = Emu::new();
+        emu.load_rom(&ROM).unwrap();
+        emu.powered_on = true;
+        emu
+    }
+
+    fn enter_at(step: u64) -> InputRecording {
+        InputRecording::new(vec![InputEvent { step, row: 6, col: 0, down: true }])
+    }
+
+    #[test]
+    fn test_bisect_finds_first_differing_instruction() {
+        let none = InputRecording::default();
+        assert_eq!(bisect(start, &none, start, &none, 500, 64).unwrap(), 500);
+
+        // A key press raises the keypad interrupt before instruction 37
+        let report = bisect(start, &none, start, &enter_at(37), 500, 16).unwrap_err();
+        assert_eq!(report.step, 37);
+        assert_eq!(report.before.0, report.before.1);
+        let (a, b) = report.instruction.clone().unwrap();
+        assert_eq!(a, b);
+        assert!(report.to_string().contains("divergence at step 37"));
+
+        let poked = || {
+            let mut emu = start();
+            emu.poke_byte(0xD00010, 1);
+            emu
+        };
+        let report = bisect(start, &none, poked, &none, 500, 16).unwrap_err();
+        assert_eq!(report.step, 0);
+        assert!(report.instruction.is_none());
+    }
+
+    #[test]
+    fn test_captures_across_builds() {
+        let a = StateCapture::record(start(), &InputRecording::default(), 0, 100, 10);
+        let b = StateCapture::record(start(), &enter_at(37), 0, 100, 10);
+        assert_eq!(a.entries.len(), 11);
+        let diff = a.first_difference(&b).unwrap();
+        assert_eq!(diff.last_agreed, Some(30));
+        assert_eq!(diff.first.0.step, 40);
+        assert_eq!(a.first_difference(&a), None);
+
+        // Refine the window at single-instruction resolution
+        let a = StateCapture::record(start(), &InputRecording::default(), 30, 40, 1);
+        let b = StateCapture::record(start(), &enter_at(37), 30, 40, 1);
+        assert_eq!(a.first_difference(&b).unwrap().last_agreed, Some(37));
+
+        assert_eq!(StateCapture::parse(&a.to_text()).unwrap(), a);
+        assert_eq!(StateCapture::parse("1 2 3").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn test_recording_text_roundtrip() {
+        let rec = InputRecording::parse("# comment\n20 6 0 up\n10 6 0 down\n").unwrap();
+        assert_eq!(rec.events()[0], InputEvent { step: 10, row: 6, col: 0, down: true });
+        assert_eq!(InputRecording::parse(&rec.to_text()).unwrap(), rec);
+        assert_eq!(InputRecording::parse("1 2 3 sideways").unwrap_err().line, 1);
+    }
+}
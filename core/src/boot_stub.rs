@@ -0,0 +1,65 @@
+//! Built-in boot stub for running code without a TI ROM
+//!
+//! `Emu::new_with_stub_boot` puts this shim in flash instead of a TI boot
+//! image, so raw eZ80 binaries can be tested without a copyrighted ROM. At
+//! reset it disables interrupts, switches to ADL mode, sets SP to
+//! `elf::DEFAULT_STACK_TOP` and calls the 24-bit entry point stored at
+//! `ENTRY_PTR` (see `Emu::load_payload`). When the payload returns, the CPU
+//! halts at `RETURNED_PC` with interrupts off and stays there.
+//!
+//! ```text
+//! 000000  F3              DI
+//! 000001  5B C3 06 00 00  JP.LIL 000006
+//! 000006  31 xx xx xx     LD SP, DEFAULT_STACK_TOP
+//! 00000A  2A 20 00 00     LD HL, (ENTRY_PTR)
+//! 00000E  CD 16 00 00     CALL 000016
+//! 000012  76              HALT
+//! 000013  18 FD           JR 000012
+//! 000016  E9              JP (HL)
+//! 000020  xx xx xx        entry point
+//! ```
+
+use crate::elf::DEFAULT_STACK_TOP;
+
+/// Flash address of the 24-bit entry point the stub calls
+pub const ENTRY_PTR: u32 = 0x000020;
+
+/// Where the CPU halts once the payload returns
+pub const RETURNED_PC: u32 = 0x000012;
+
+/// Entry point until a payload is loaded: the start of user program RAM,
+/// where TI-OS runs assembly programs from
+pub const DEFAULT_ENTRY: u32 = 0xD1A881;
+
+/// Stub image calling `entry`, to load as the ROM
+pub fn image(entry: u32) -> Vec<u8> {
+    let [sp0, sp1, sp2, _] = DEFAULT_STACK_TOP.to_le_bytes();
+    let mut rom = vec![
+        0xF3, // DI
+        0x5B, 0xC3, 0x06, 0x00, 0x00, // JP.LIL 000006
+        0x31, sp0, sp1, sp2, // LD SP, DEFAULT_STACK_TOP
+        0x2A, ENTRY_PTR as u8, 0x00, 0x00, // LD HL, (ENTRY_PTR)
+        0xCD, 0x16, 0x00, 0x00, // CALL 000016
+        0x76, // HALT
+        0x18, 0xFD, // JR 000012
+        0x00,
+        0xE9, // JP (HL)
+    ];
+    rom.resize(ENTRY_PTR as usize, 0xFF);
+    rom.extend_from_slice(&entry.to_le_bytes()[..3]);
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_layout() {
+        let rom = image(0xD12345);
+        assert_eq!(rom.len(), ENTRY_PTR as usize + 3);
+        assert_eq!(rom[RETURNED_PC as usize], 0x76);
+        assert_eq!(rom[0x16], 0xE9);
+        assert_eq!(&rom[ENTRY_PTR as usize..], &[0x45, 0x23, 0xD1]);
+    }
+}
@@ -15,8 +15,12 @@
 //! Reference: CEmu (https://github.com/CE-Programming/CEmu)
 
 use crate::memory::{addr, Flash, FlashError, Ports, Ram};
-use crate::peripherals::SpiController;
-use std::collections::BTreeMap;
+use crate::peripherals::usb::UsbDma;
+use crate::peripherals::{BusAccess, PanelStub, SpiController, UsbController};
+use crate::sandbox::Sandbox;
+#[cfg(feature = "debugger")]
+use crate::debug::{AccessKind, Watchpoints};
+use std::collections::{BTreeMap, VecDeque};
 
 /// Bus access type for debugging/tracing
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -346,6 +350,302 @@ impl Default for WriteTracer {
     }
 }
 
+/// Max distinct (address, direction) pairs tracked by the unimplemented-access registry
+const MAX_UNIMPL_ENTRIES: usize = 1024;
+
+/// Unmapped memory accesses are grouped into 4KB regions so a scan
+/// over unmapped space doesn't exhaust the registry
+const UNMAPPED_REGION_MASK: u32 = !0xFFF;
+
+/// A recorded access to hardware the emulator does not implement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnimplAccess {
+    /// Missing peripheral/feature (e.g. "USB", "UART", "MMIO", "Unmapped")
+    pub feature: &'static str,
+    /// Absolute address (0xFFxxxx for CPU ports, 0xE0xxxx-0xFFxxxx for MMIO,
+    /// 4KB region base for unmapped memory and unmapped MMIO)
+    pub addr: u32,
+    /// True for writes, false for reads
+    pub is_write: bool,
+    /// PC of the instruction that made the first access
+    pub first_pc: u32,
+    /// Total number of accesses
+    pub count: u64,
+}
+
+/// Registry of accesses to unimplemented hardware
+///
+/// The first access to each address/direction is logged once with its PC;
+/// later accesses are only counted. This tells users which missing
+/// peripheral a ROM needs without flooding the log.
+pub struct UnimplRegistry {
+    /// Entries keyed by (address, is_write)
+    entries: BTreeMap<(u32, bool), UnimplAccess>,
+    /// Accesses not tracked because the registry was full
+    dropped: u64,
+}
+
+impl UnimplRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Record an access, logging it if this is the first for the address/direction
+    pub fn record(&mut self, feature: &'static str, addr: u32, is_write: bool, pc: u32) {
+        if let Some(entry) = self.entries.get_mut(&(addr, is_write)) {
+            entry.count += 1;
+            return;
+        }
+
+        if self.entries.len() >= MAX_UNIMPL_ENTRIES {
+            self.dropped += 1;
+            return;
+        }
+
+        crate::emu::log_sub!(Bus, Warn,
+            "UNIMPL: {} {} addr={:06X} pc={:06X} (further accesses counted only)",
+            feature,
+            if is_write { "write" } else { "read" },
+            addr, pc
+        );
+        self.entries.insert((addr, is_write), UnimplAccess {
+            feature,
+            addr,
+            is_write,
+            first_pc: pc,
+            count: 1,
+        });
+    }
+
+    /// All recorded accesses, ordered by address
+    pub fn entries(&self) -> Vec<UnimplAccess> {
+        self.entries.values().cloned().collect()
+    }
+
+    /// Number of distinct address/direction pairs recorded
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Accesses dropped because the registry was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Record an access to unmapped memory, keyed by its 4KB region
+    pub fn record_unmapped(&mut self, feature: &'static str, addr: u32, is_write: bool, pc: u32) {
+        self.record(feature, addr & UNMAPPED_REGION_MASK, is_write, pc);
+    }
+
+    /// Snapshot of everything recorded so far
+    pub fn report(&self) -> UnknownAccessReport {
+        UnknownAccessReport {
+            entries: self.entries(),
+            dropped: self.dropped,
+        }
+    }
+
+    /// Human-readable summary, one line per address/direction
+    pub fn summary(&self) -> String {
+        self.report().to_string()
+    }
+
+    /// Clear all recorded accesses
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+}
+
+impl Default for UnimplRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One logged I/O port access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortAccess {
+    pub op_type: IoOpType,
+    /// CpuPort for IN/OUT, MmioPort for memory-mapped accesses
+    pub target: IoTarget,
+    /// Address (IN/OUT ports appear at 0xFFxxxx, as in IoRecord)
+    pub addr: u32,
+    /// Value read or written
+    pub value: u8,
+    /// PC of the accessing instruction
+    pub pc: u32,
+    /// Bus cycle of the access
+    pub cycle: u64,
+}
+
+impl PortAccess {
+    /// Port range (address bits 12-15) the access falls in
+    pub fn range(&self) -> u8 {
+        ((self.addr >> 12) & 0xF) as u8
+    }
+}
+
+/// Ring buffer of I/O port reads and writes
+///
+/// Off by default. Each of the 16 port ranges (0x0 control, 0x1 flash,
+/// ... 0xD SPI, 0xF control via MMIO) has an enable bit; once full, the
+/// oldest accesses are dropped. The log survives resets so a whole boot
+/// can be captured.
+#[derive(Debug, Clone)]
+pub struct PortLog {
+    entries: VecDeque<PortAccess>,
+    capacity: usize,
+    /// Bit n enables port range n
+    range_mask: u16,
+    /// Accesses pushed out of the buffer
+    dropped: u64,
+}
+
+impl PortLog {
+    /// Default number of accesses kept
+    pub const DEFAULT_CAPACITY: usize = 65536;
+    /// Range mask enabling every port range
+    pub const ALL_RANGES: u16 = 0xFFFF;
+
+    /// Create a disabled log
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: Self::DEFAULT_CAPACITY,
+            range_mask: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Log accesses to the ranges set in `range_mask`
+    pub fn enable(&mut self, range_mask: u16) {
+        self.range_mask = range_mask;
+    }
+
+    /// Stop logging (keeps recorded accesses)
+    pub fn disable(&mut self) {
+        self.range_mask = 0;
+    }
+
+    /// Check if any range is being logged
+    pub fn is_enabled(&self) -> bool {
+        self.range_mask != 0
+    }
+
+    /// Currently enabled ranges
+    pub fn range_mask(&self) -> u16 {
+        self.range_mask
+    }
+
+    /// Set how many accesses are kept, dropping the oldest if over
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    /// Record an access if its range is enabled
+    pub fn record(&mut self, access: PortAccess) {
+        if self.range_mask & (1 << access.range()) == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(access);
+    }
+
+    /// Logged accesses, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &PortAccess> + '_ {
+        self.entries.iter()
+    }
+
+    /// Number of accesses held
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if nothing is held
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Accesses dropped because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Clear logged accesses (keeps the range mask)
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+}
+
+impl Default for PortLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every unimplemented port and unmapped memory region touched in a session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownAccessReport {
+    /// Recorded accesses, ordered by address
+    pub entries: Vec<UnimplAccess>,
+    /// Accesses not tracked because the registry was full
+    pub dropped: u64,
+}
+
+impl UnknownAccessReport {
+    /// Check if nothing unknown was touched
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.dropped == 0
+    }
+
+    /// Distinct missing features, in first-seen address order
+    pub fn features(&self) -> Vec<&'static str> {
+        let mut features = Vec::new();
+        for e in &self.entries {
+            if !features.contains(&e.feature) {
+                features.push(e.feature);
+            }
+        }
+        features
+    }
+}
+
+impl std::fmt::Display for UnknownAccessReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for e in &self.entries {
+            writeln!(
+                f,
+                "{} {} {:06X} first_pc={:06X} count={}",
+                e.feature,
+                if e.is_write { "W" } else { "R" },
+                e.addr, e.first_pc, e.count
+            )?;
+        }
+        if self.dropped > 0 {
+            writeln!(f, "(+{} accesses not tracked, registry full)", self.dropped)?;
+        }
+        Ok(())
+    }
+}
+
 /// Flash cache constants matching CEmu (flash.h)
 /// 32-byte cache lines, 128 sets, 2-way set associative
 const FLASH_CACHE_LINE_BITS: u32 = 5;
@@ -445,6 +745,27 @@ impl Default for FlashCache {
     }
 }
 
+/// USB DMA view of memory: RAM only, everything else reads as zero
+struct RamDma<'a>(&'a mut Ram);
+
+impl UsbDma for RamDma<'_> {
+    fn dma_read(&mut self, addr: u32) -> u8 {
+        let addr = addr & addr::ADDR_MASK;
+        if (addr::RAM_START..addr::RAM_END).contains(&addr) {
+            self.0.read(addr - addr::RAM_START)
+        } else {
+            0x00
+        }
+    }
+
+    fn dma_write(&mut self, addr: u32, value: u8) {
+        let addr = addr & addr::ADDR_MASK;
+        if (addr::RAM_START..addr::RAM_END).contains(&addr) {
+            self.0.write(addr - addr::RAM_START, value);
+        }
+    }
+}
+
 /// System bus connecting CPU to memory subsystems
 pub struct Bus {
     /// Flash memory
@@ -455,6 +776,8 @@ pub struct Bus {
     pub ports: Ports,
     /// SPI controller (port range 0xD)
     spi: SpiController,
+    /// USB host controller (port range 0x3)
+    pub usb: UsbController,
     /// RNG for unmapped region reads
     rng: BusRng,
     /// Internal CPU cycle counter (matches CEmu's cpu.cycles)
@@ -469,6 +792,15 @@ pub struct Bus {
     fetch_index: usize,
     /// Write tracer for debugging RAM writes
     pub write_tracer: WriteTracer,
+    /// Reports user-program writes to OS-owned RAM
+    pub sandbox: Sandbox,
+    /// Debugger memory and port watchpoints
+    #[cfg(feature = "debugger")]
+    pub watchpoints: Watchpoints,
+    /// Registry of accesses to unimplemented hardware
+    pub unimpl: UnimplRegistry,
+    /// Optional log of I/O port accesses
+    pub port_log: PortLog,
     /// Serial flash mode (newer TI-84 CE models)
     /// When true, uses flash cache timing; when false, uses parallel flash timing
     serial_flash: bool,
@@ -554,12 +886,18 @@ impl Bus {
             ram: Ram::new(),
             ports: Ports::new(),
             spi: SpiController::new(),
+            usb: UsbController::new(),
             rng: BusRng::new(),
             cycles: 0,
             mem_cycles: 0,
             fetch_buffer: [0; FETCH_BUFFER_SIZE],
             fetch_index: 0,
             write_tracer: WriteTracer::new(),
+            sandbox: Sandbox::new(),
+            #[cfg(feature = "debugger")]
+            watchpoints: Watchpoints::default(),
+            unimpl: UnimplRegistry::new(),
+            port_log: PortLog::new(),
             serial_flash: false,  // Default to parallel flash (10 cycles, more compatible)
             flash_cache: FlashCache::new(),
             // I/O tracing fields
@@ -628,6 +966,11 @@ impl Bus {
         &mut self.spi
     }
 
+    /// Get the SPI-connected LCD panel
+    pub fn panel(&self) -> &PanelStub {
+        self.spi.panel()
+    }
+
     // === Debug port accessors ===
 
     /// Enable or disable debug port interception
@@ -729,10 +1072,14 @@ impl Bus {
                     // SPI lives on bus.spi (not bus.ports), intercept its MMIO range
                     if port_range == 0xD {
                         let offset = (port_offset & 0x7F) as u32;
-                        (self.spi.read(offset, self.cycles, self.ports.control.cpu_speed()), Some(IoTarget::MmioPort))
+                        (self.spi.read(offset, self.cycles, self.ports.control.cpu_speed(), BusAccess::Cpu), Some(IoTarget::MmioPort))
                     } else {
                         let keys = *self.ports.key_state();
-                        (self.ports.read(port_offset, &keys, self.cycles), Some(IoTarget::MmioPort))
+                        let value = self.ports.read(port_offset, &keys, self.cycles, BusAccess::Cpu);
+                        if self.ports.take_fallback_access() == Some(port_offset) {
+                            self.unimpl.record("MMIO", addr, false, self.cpu_pc);
+                        }
+                        (value, Some(IoTarget::MmioPort))
                     }
                 } else {
                     // Unmapped MMIO: CEmu returns random data with cycle penalty
@@ -741,6 +1088,7 @@ impl Bus {
                     } else {
                         self.mem_cycles += Self::UNMAPPED_MMIO_OTHER_CYCLES; // 2
                     }
+                    self.unimpl.record_unmapped("Unmapped MMIO", addr, false, self.cpu_pc);
                     (self.rng.next(), None)
                 }
             }
@@ -751,7 +1099,8 @@ impl Bus {
                 } else {
                     self.mem_cycles += Self::UNMAPPED_PARALLEL_CYCLES;
                 }
-                (self.rng.next(), None)  // Don't record unmapped reads
+                self.unimpl.record_unmapped("Unmapped", addr, false, self.cpu_pc);
+                (self.rng.next(), None)  // Don't record unmapped reads in the I/O trace
             }
         };
 
@@ -759,6 +1108,10 @@ impl Bus {
         if let Some(target) = target {
             self.record_io_op(IoOpType::Read, target, addr, value, value);
         }
+        #[cfg(feature = "debugger")]
+        if self.watchpoints.is_active() {
+            self.watchpoints.check_memory(self.cpu_pc, addr, AccessKind::Read, value);
+        }
 
         value
     }
@@ -798,7 +1151,7 @@ impl Bus {
                 let port_range = (port_offset >> 12) & 0xF;
                 self.mem_cycles += Self::PORT_READ_CYCLES[port_range as usize];
                 let keys = *self.ports.key_state();
-                self.ports.read(port_offset, &keys, self.cycles)
+                self.ports.read(port_offset, &keys, self.cycles, BusAccess::Cpu)
             }
             MemoryRegion::Unmapped => {
                 // CEmu: 258 cycles for parallel mode, 2 for serial mode
@@ -873,17 +1226,24 @@ impl Bus {
     /// * `value` - Byte to write
     pub fn write_byte(&mut self, addr: u32, value: u8) {
         let addr = addr & addr::ADDR_MASK;
+        #[cfg(feature = "debugger")]
+        if self.watchpoints.is_active() {
+            self.watchpoints.check_memory(self.cpu_pc, addr, AccessKind::Write, value);
+        }
+
+        // CEmu uses rawPC = cpu.registers.PC + 1 for the unprivileged check
+        let raw_pc = self.cpu_pc.wrapping_add(1) & 0xFFFFFF;
 
         // CEmu memory protection: check stack limit (always, write still succeeds)
         let stack_limit = self.ports.control.stack_limit();
         if stack_limit != 0 && addr == stack_limit {
             self.ports.control.set_stack_violation();
             self.nmi_requested = true;
+            self.nmi_violation_addr = addr;
+            self.nmi_violation_pc = raw_pc;
         }
 
         // CEmu memory protection: check protected range (unprivileged code only)
-        // CEmu uses rawPC = cpu.registers.PC + 1 for the unprivileged check
-        let raw_pc = self.cpu_pc.wrapping_add(1) & 0xFFFFFF;
         let unprivileged = self.ports.control.is_unprivileged(raw_pc);
         let protected_start = self.ports.control.protected_start();
         let protected_end = self.ports.control.protected_end();
@@ -901,6 +1261,8 @@ impl Bus {
                 if unprivileged {
                     self.ports.control.set_protected_violation();
                     self.nmi_requested = true;
+                    self.nmi_violation_addr = addr;
+                    self.nmi_violation_pc = raw_pc;
                     return; // Block the write
                 }
                 // CEmu mem_write_flash: serial uses cache touch, parallel uses waitStates
@@ -924,9 +1286,13 @@ impl Bus {
                 // Get old value for tracing
                 let old_value = self.ram.read(addr - addr::RAM_START);
                 // Record write for simple tracing (before actually writing)
-                if self.write_tracer.is_enabled() {
+                if cfg!(feature = "trace") && self.write_tracer.is_enabled() {
                     self.write_tracer.record(addr, value, self.cycles);
                 }
+                if self.sandbox.is_enabled() {
+                    let cycle = self.total_cycles();
+                    self.sandbox.check(self.cpu_pc, addr, old_value, value, cycle);
+                }
                 self.ram.write(addr - addr::RAM_START, value);
                 // Record for comprehensive I/O tracing
                 self.record_io_op(IoOpType::Write, IoTarget::Ram, addr, old_value, value);
@@ -1001,6 +1367,10 @@ impl Bus {
                     } else {
                         self.mem_cycles += Self::UNMAPPED_MMIO_OTHER_CYCLES; // 2
                     }
+                    let debug_port = self.debug_ports_enabled && (0xFB0000..=0xFD0000).contains(&addr);
+                    if !debug_port {
+                        self.unimpl.record_unmapped("Unmapped MMIO", addr, true, self.cpu_pc);
+                    }
                 } else {
                     // CEmu's port_write_byte timing:
                     // 1. Add PORT_WRITE_DELAY (4) before processing
@@ -1017,16 +1387,19 @@ impl Bus {
                     let old_value;
                     if port_range == 0xD {
                         let offset = (port_offset & 0x7F) as u32;
-                        old_value = self.spi.read(offset, self.cycles, self.ports.control.cpu_speed());
+                        old_value = self.spi.read(offset, self.cycles, self.ports.control.cpu_speed(), BusAccess::Debug);
                         let needs_schedule = self.spi.write(offset, value, self.cycles, self.ports.control.cpu_speed());
                         if needs_schedule {
                             self.spi_needs_schedule = true;
                         }
                     } else {
-                        // Get old value for tracing (read without side effects if possible)
+                        // Get old value for tracing (read without side effects)
                         let keys = *self.ports.key_state();
-                        old_value = self.ports.read(port_offset, &keys, self.cycles);
+                        old_value = self.ports.read(port_offset, &keys, self.cycles, BusAccess::Debug);
                         self.ports.write(port_offset, value, self.cycles);
+                        if self.ports.take_fallback_access() == Some(port_offset) {
+                            self.unimpl.record("MMIO", addr, true, self.cpu_pc);
+                        }
                     }
                     // Record for comprehensive I/O tracing
                     self.record_io_op(IoOpType::Write, IoTarget::MmioPort, addr, old_value, value);
@@ -1049,6 +1422,7 @@ impl Bus {
                 } else {
                     self.mem_cycles += Self::UNMAPPED_PARALLEL_CYCLES;
                 }
+                self.unimpl.record_unmapped("Unmapped", addr, true, self.cpu_pc);
             }
         }
     }
@@ -1107,11 +1481,7 @@ impl Bus {
             MemoryRegion::Ram | MemoryRegion::Vram => {
                 self.ram.read(addr - addr::RAM_START)
             }
-            MemoryRegion::Ports => {
-                let keys = *self.ports.key_state();
-                // Use 0 for cycles in debug peek (no timing effects)
-                self.ports.read(addr - addr::PORT_START, &keys, 0)
-            }
+            MemoryRegion::Ports => self.peek_port(addr - addr::PORT_START),
             MemoryRegion::Unmapped => 0x00,
         }
     }
@@ -1124,15 +1494,21 @@ impl Bus {
             MemoryRegion::Ram | MemoryRegion::Vram => {
                 self.ram.read(addr - addr::RAM_START)
             }
-            MemoryRegion::Ports => {
-                let keys = *self.ports.key_state();
-                // Use 0 for cycles in debug peek (no timing effects)
-                self.ports.read(addr - addr::PORT_START, &keys, 0)
-            }
+            MemoryRegion::Ports => self.peek_port(addr - addr::PORT_START),
             MemoryRegion::Unmapped => 0x00,
         }
     }
 
+    /// Read an MMIO register the way read_byte routes it, without side effects
+    fn peek_port(&mut self, port_offset: u32) -> u8 {
+        if (port_offset >> 12) & 0xF == 0xD {
+            let offset = port_offset & 0x7F;
+            return self.spi.read(offset, self.cycles, self.ports.control.cpu_speed(), BusAccess::Debug);
+        }
+        let keys = *self.ports.key_state();
+        self.ports.read(port_offset, &keys, self.cycles, BusAccess::Debug)
+    }
+
     /// Poke a byte without affecting cycles (for debugging)
     pub fn poke_byte(&mut self, addr: u32, value: u8) {
         let addr = addr & addr::ADDR_MASK;
@@ -1152,11 +1528,43 @@ impl Bus {
         }
     }
 
+    /// Read a block starting at `addr` (wrapping at 24 bits). With
+    /// `side_effects` each byte goes through `read_byte` like a CPU read (MMIO
+    /// registers that change on read do), otherwise through `peek_byte`.
+    /// Cycle counters are left untouched either way.
+    pub fn read_block(&mut self, addr: u32, buf: &mut [u8], side_effects: bool) {
+        let cycles = (self.cycles, self.mem_cycles);
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let addr = addr.wrapping_add(i as u32);
+            *byte = if side_effects { self.read_byte(addr) } else { self.peek_byte(addr) };
+        }
+        (self.cycles, self.mem_cycles) = cycles;
+    }
+
+    /// Write a block starting at `addr`. With `side_effects` each byte goes
+    /// through `write_byte` like a CPU write (flash commands, memory
+    /// protection), otherwise through `poke_byte`, which writes flash
+    /// directly. Cycle counters are left untouched either way.
+    pub fn write_block(&mut self, addr: u32, data: &[u8], side_effects: bool) {
+        let cycles = (self.cycles, self.mem_cycles);
+        for (i, &byte) in data.iter().enumerate() {
+            let addr = addr.wrapping_add(i as u32);
+            if side_effects { self.write_byte(addr, byte) } else { self.poke_byte(addr, byte) }
+        }
+        (self.cycles, self.mem_cycles) = cycles;
+    }
+
     /// Load ROM into flash
     pub fn load_rom(&mut self, data: &[u8]) -> Result<(), FlashError> {
         self.flash.load_rom(data)
     }
 
+    /// Load ROM by memory-mapping a file (see `Flash::load_rom_mapped`)
+    #[cfg(feature = "mmap")]
+    pub fn load_rom_mapped(&mut self, path: &std::path::Path) -> Result<(), FlashError> {
+        self.flash.load_rom_mapped(path)
+    }
+
     /// Get current CPU cycle count (internal CPU timing only, matches CEmu's cpu.cycles)
     pub fn cycles(&self) -> u64 {
         self.cycles
@@ -1199,6 +1607,16 @@ impl Bus {
         self.ram.vram()
     }
 
+    /// Name of the unimplemented peripheral behind a CPU port range
+    fn unimpl_port_feature(range: u16) -> &'static str {
+        match range {
+            0x9 => "Protected",
+            0xC => "Cxxx",
+            0xE => "UART",
+            _ => "Port",
+        }
+    }
+
     /// Read from I/O port (for IN instructions)
     ///
     /// The eZ80 uses a 16-bit port address space separate from memory.
@@ -1206,7 +1624,7 @@ impl Bus {
     ///   0x0xxx -> Control ports
     ///   0x1xxx -> Flash controller
     ///   0x2xxx -> SHA256 (stub)
-    ///   0x3xxx -> USB (stub)
+    ///   0x3xxx -> USB host controller
     ///   0x4xxx -> LCD controller
     ///   0x5xxx -> Interrupt controller
     ///   0x6xxx -> Watchdog
@@ -1242,6 +1660,10 @@ impl Bus {
                 let offset = (port & 0xFF) as u32;
                 self.ports.sha256.read(offset)
             }
+            0x3 => {
+                // USB host controller - mask with 0xFFF
+                self.usb.read((port & 0xFFF) as u32)
+            }
             0x4 => {
                 // LCD controller - mask with 0xFF
                 let offset = (port & 0xFF) as u32;
@@ -1280,19 +1702,27 @@ impl Bus {
             0xD => {
                 // SPI - mask with 0x7F (CEmu port_mirrors)
                 let offset = (port & 0x7F) as u32;
-                self.spi.read(offset, self.cycles, self.ports.control.cpu_speed())
+                self.spi.read(offset, self.cycles, self.ports.control.cpu_speed(), BusAccess::Cpu)
             }
             // CEmu: port_map[0xF] = fxxx (debug handler), not Control
             // Control ports are only accessible via IN0/OUT0 (port range 0x0)
             // or via MMIO at 0xFF0000 which routes to peripherals/mod.rs
             0xF => 0x00,
-            // Unimplemented: USB(3), Protected(9), Cxxx(C), UART(E)
-            _ => 0x00,
+            // Unimplemented: Protected(9), Cxxx(C), UART(E)
+            _ => {
+                let addr = 0xFF0000 | (port as u32);
+                self.unimpl.record(Self::unimpl_port_feature(range), addr, false, self.cpu_pc);
+                0x00
+            }
         };
 
         // Record for comprehensive I/O tracing (CPU port read)
         let addr = 0xFF0000 | (port as u32);
         self.record_io_op(IoOpType::Read, IoTarget::CpuPort, addr, value, value);
+        #[cfg(feature = "debugger")]
+        if self.watchpoints.is_active() {
+            self.watchpoints.check_port(self.cpu_pc, port, AccessKind::Read, value);
+        }
 
         value
     }
@@ -1303,7 +1733,10 @@ impl Bus {
     /// during the write, as the conversion happens with the +4 already added.
     pub fn port_write(&mut self, port: u16, value: u8) {
         let range = (port >> 12) & 0xF;
-
+        #[cfg(feature = "debugger")]
+        if self.watchpoints.is_active() {
+            self.watchpoints.check_port(self.cpu_pc, port, AccessKind::Write, value);
+        }
         // CEmu: cpu.cycles += PORT_WRITE_DELAY (4) BEFORE the write
         self.mem_cycles += Self::PORT_WRITE_DELAY;
 
@@ -1389,11 +1822,7 @@ impl Bus {
                     let key_state = *self.ports.key_state();
                     let should_interrupt = self.ports.keypad.any_key_check(&key_state);
 
-                    if should_interrupt {
-                        self.ports.interrupt.raise(crate::peripherals::interrupt::sources::KEYPAD);
-                    } else {
-                        self.ports.interrupt.clear_raw(crate::peripherals::interrupt::sources::KEYPAD);
-                    }
+                    self.ports.interrupt.set(crate::peripherals::interrupt::sources::KEYPAD, should_interrupt);
                 }
             }
             0xB => {
@@ -1409,10 +1838,18 @@ impl Bus {
                     self.spi_needs_schedule = true;
                 }
             }
+            0x3 => {
+                // USB host controller - mask with 0xFFF
+                self.usb.write((port & 0xFFF) as u32, value);
+                self.sync_usb_irq();
+            }
             // CEmu: port_map[0xF] = fxxx (debug handler), not Control
             0xF => {}
-            // Unimplemented: USB(3), Protected(9), Cxxx(C), UART(E)
-            _ => {}
+            // Unimplemented: Protected(9), Cxxx(C), UART(E)
+            _ => {
+                let addr = 0xFF0000 | (port as u32);
+                self.unimpl.record(Self::unimpl_port_feature(range), addr, true, self.cpu_pc);
+            }
         }
         // CEmu: sched_rewind_cpu(PORT_WRITE_DELAY - port_write_cycles[port_loc])
         // Rewind excess port write delay cycles
@@ -1443,6 +1880,7 @@ impl Bus {
                 let offset = (port & 0xFF) as u32;
                 self.ports.sha256.read(offset)
             }
+            0x3 => self.usb.read((port & 0xFFF) as u32),
             0x4 => {
                 let offset = (port & 0xFF) as u32;
                 self.ports.lcd.read(offset)
@@ -1460,8 +1898,8 @@ impl Bus {
                 self.ports.timers.read(offset)
             }
             0x8 => {
-                // RTC - can't read without cycle parameter, return 0
-                0x00
+                let offset = (port & 0xFF) as u32;
+                self.ports.rtc.read(offset, self.cycles, self.ports.control.cpu_speed())
             }
             0xA => {
                 let offset = (port & 0x7F) as u32;
@@ -1472,18 +1910,39 @@ impl Bus {
                 self.ports.backlight.read(offset)
             }
             0xD => {
-                // SPI - can't read without cycle parameter, return 0
-                0x00
+                let offset = (port & 0x7F) as u32;
+                self.spi.read(offset, self.cycles, self.ports.control.cpu_speed(), BusAccess::Debug)
             }
             _ => 0x00,
         }
     }
 
+    /// Advance the USB controller (its schedules live in RAM) and update
+    /// its interrupt line
+    pub fn tick_usb(&mut self, cycles: u32) {
+        if self.usb.tick(cycles, &mut RamDma(&mut self.ram)) {
+            self.sync_usb_irq();
+        }
+    }
+
+    /// Drive the USB interrupt source from the controller state
+    pub fn sync_usb_irq(&mut self) {
+        use crate::peripherals::interrupt::sources;
+        self.ports.interrupt.set(sources::USB, self.usb.irq_pending());
+    }
+
     /// Reset bus and all memory to initial state
     pub fn reset(&mut self) {
         self.ram.reset();
+        self.reset_keep_ram();
+    }
+
+    /// Reset peripherals and bus state but keep RAM contents
+    /// (hardware reset button: RAM is not cleared by the reset line)
+    pub fn reset_keep_ram(&mut self) {
         self.ports.reset();
         self.spi.reset();
+        self.usb.reset();
         self.cycles = 0;
         self.mem_cycles = 0;
         self.rng = BusRng::new();
@@ -1523,9 +1982,10 @@ impl Bus {
 
     // === Comprehensive I/O Tracing Methods ===
 
-    /// Enable full I/O tracing (records all memory/port operations with instruction context)
+    /// Enable full I/O tracing (records all memory/port operations with instruction context).
+    /// No-op without the `trace` feature.
     pub fn enable_full_trace(&mut self) {
-        self.full_trace_enabled = true;
+        self.full_trace_enabled = cfg!(feature = "trace");
     }
 
     /// Disable full I/O tracing
@@ -1569,7 +2029,17 @@ impl Bus {
 
     /// Record an I/O operation (internal helper)
     fn record_io_op(&mut self, op_type: IoOpType, target: IoTarget, addr: u32, old_value: u8, new_value: u8) {
-        if self.full_trace_enabled && self.instruction_io_ops.len() < Self::MAX_IO_OPS_PER_INSTRUCTION {
+        if self.port_log.is_enabled() && matches!(target, IoTarget::MmioPort | IoTarget::CpuPort) {
+            self.port_log.record(PortAccess {
+                op_type,
+                target,
+                addr,
+                value: new_value,
+                pc: self.cpu_pc,
+                cycle: self.cycles,
+            });
+        }
+        if cfg!(feature = "trace") && self.full_trace_enabled && self.instruction_io_ops.len() < Self::MAX_IO_OPS_PER_INSTRUCTION {
             self.instruction_io_ops.push(IoRecord {
                 op_type,
                 target,
@@ -1737,6 +2207,76 @@ mod tests {
         assert_eq!(bus2.read_byte(0xC00000), 0x12);
     }
 
+    #[test]
+    fn test_unimpl_port_access_recorded_once() {
+        let mut bus = Bus::new();
+        bus.cpu_pc = 0x001234;
+        bus.port_read(0xE010); // UART
+        bus.cpu_pc = 0x005678;
+        bus.port_read(0xE010);
+        bus.port_write(0xE010, 0x01);
+
+        let entries = bus.unimpl.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].feature, "UART");
+        assert_eq!(entries[0].addr, 0xFFE010);
+        assert!(!entries[0].is_write);
+        assert_eq!(entries[0].first_pc, 0x001234);
+        assert_eq!(entries[0].count, 2);
+        assert!(entries[1].is_write);
+        assert_eq!(entries[1].first_pc, 0x005678);
+
+        // Implemented ports are not recorded
+        bus.port_read(0x5000);
+        assert_eq!(bus.unimpl.len(), 2);
+
+        bus.unimpl.clear();
+        assert!(bus.unimpl.is_empty());
+    }
+
+    #[test]
+    fn test_unimpl_mmio_access_recorded() {
+        let mut bus = Bus::new();
+        // 0xF10000 is inside the mapped MMIO window but has no peripheral
+        bus.write_byte(0xF10000, 0xAA);
+        bus.read_byte(0xF10000);
+        let entries = bus.unimpl.entries();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.feature == "MMIO" && e.addr == 0xF10000));
+        assert!(bus.unimpl.summary().contains("MMIO W F10000"));
+
+        // Mapped peripheral (interrupt controller) is not recorded
+        bus.read_byte(0xF00000);
+        assert_eq!(bus.unimpl.len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_access_report_groups_unmapped_regions() {
+        let mut bus = Bus::new();
+        bus.cpu_pc = 0x000100;
+        bus.read_byte(0xC00010);
+        bus.read_byte(0xC00FFF);
+        bus.write_byte(0xC01000, 0x00);
+        bus.read_byte(0xE40000); // unmapped MMIO
+        bus.port_read(0xE000); // UART
+
+        let report = bus.unimpl.report();
+        assert_eq!(report.entries.len(), 4);
+        assert_eq!(report.entries[0].feature, "Unmapped");
+        assert_eq!(report.entries[0].addr, 0xC00000);
+        assert_eq!(report.entries[0].count, 2);
+        assert_eq!(report.entries[0].first_pc, 0x000100);
+        assert_eq!(report.entries[1].addr, 0xC01000);
+        assert!(report.entries[1].is_write);
+        assert_eq!(report.features(), vec!["Unmapped", "Unmapped MMIO", "UART"]);
+        assert_eq!(report.to_string(), bus.unimpl.summary());
+
+        // Debug console writes are expected, not unknown
+        bus.set_debug_ports(true);
+        bus.write_byte(0xFB0000, b'A');
+        assert_eq!(bus.unimpl.len(), 4);
+    }
+
     #[test]
     fn test_cycle_counting() {
         let mut bus = Bus::new();
@@ -1794,6 +2334,73 @@ mod tests {
         assert_eq!(bus.peek_byte(0xD00000), 0x42);
     }
 
+    #[test]
+    fn test_block_read_write() {
+        let mut bus = Bus::new();
+        bus.load_rom(&[0x11; 0x20]).unwrap();
+        bus.add_cycles(100);
+        let before = (bus.cycles(), bus.mem_cycles());
+
+        bus.write_block(0xD00000, &[1, 2, 3], true);
+        bus.write_block(0xD00003, &[4], false);
+        let mut buf = [0u8; 4];
+        bus.read_block(0xD00000, &mut buf, true);
+        assert_eq!(buf, [1, 2, 3, 4]);
+        assert_eq!((bus.cycles(), bus.mem_cycles()), before);
+
+        // A CPU-path write to flash needs the command sequence; a poke does not
+        bus.write_block(0x000010, &[0x22], true);
+        bus.read_block(0x000010, &mut buf[..1], false);
+        assert_eq!(buf[0], 0x11);
+        bus.write_block(0x000010, &[0x22], false);
+        bus.read_block(0x000010, &mut buf[..1], false);
+        assert_eq!(buf[0], 0x22);
+    }
+
+    #[test]
+    fn test_port_log() {
+        let mut bus = Bus::new();
+        bus.port_write(0x0001, 0x03);
+        assert!(bus.port_log.is_empty());
+
+        // Control ports (range 0x0) only
+        bus.port_log.enable(1 << 0x0);
+        bus.cpu_pc = 0x1234;
+        bus.port_write(0x0001, 0x02);
+        assert_eq!(bus.port_read(0x0001), 0x02);
+        bus.port_read(0x5000);
+        bus.write_byte(0xE00005, 0x11);
+        let log: Vec<_> = bus.port_log.entries().copied().collect();
+        assert_eq!(log.len(), 3);
+        assert_eq!((log[0].op_type, log[0].target, log[0].addr, log[0].value, log[0].pc),
+            (IoOpType::Write, IoTarget::CpuPort, 0xFF0001, 0x02, 0x1234));
+        assert_eq!((log[1].op_type, log[1].value), (IoOpType::Read, 0x02));
+        assert_eq!((log[2].target, log[2].addr), (IoTarget::MmioPort, 0xE00005));
+
+        // Oldest entries fall out of the ring
+        bus.port_log.set_capacity(2);
+        assert_eq!(bus.port_log.dropped(), 1);
+        bus.port_read(0x0001);
+        assert_eq!(bus.port_log.len(), 2);
+        assert_eq!(bus.port_log.dropped(), 2);
+        assert_eq!(bus.port_log.entries().next().unwrap().addr, 0xE00005);
+    }
+
+    #[test]
+    fn test_peek_port_is_side_effect_free() {
+        let mut bus = Bus::new();
+        // Unmapped port: a CPU read is reported to the unimplemented registry
+        bus.peek_byte(0xE00100);
+        assert_eq!(bus.ports.take_fallback_access(), None);
+        let keys = *bus.ports.key_state();
+        bus.ports.read(0x100, &keys, 0, BusAccess::Cpu);
+        assert_eq!(bus.ports.take_fallback_access(), Some(0x100));
+
+        // SPI registers are routed as read_byte routes them
+        assert_eq!(bus.peek_byte(0xE0D01C), bus.spi.read(0x1C, 0, 0, BusAccess::Debug));
+        assert_ne!(bus.peek_byte(0xE0D01C), 0);
+    }
+
     #[test]
     fn test_reset() {
         let mut bus = Bus::new();
@@ -1926,4 +2533,24 @@ mod tests {
         // Read should work too
         assert_eq!(bus.read_word(0xD3FFFF), 0xABCD);
     }
+
+    #[test]
+    fn test_sha256_port_hash() {
+        let mut bus = Bus::new();
+        // SHA-256("abc"), one padded block
+        let mut block = [0u32; 16];
+        block[0] = 0x61626380;
+        block[15] = 0x18;
+        for (i, word) in block.iter().enumerate() {
+            for (b, byte) in word.to_le_bytes().into_iter().enumerate() {
+                bus.port_write(0x2010 + (i * 4 + b) as u16, byte);
+            }
+        }
+        bus.port_write(0x2000, 0x0A);
+
+        let read_word = |bus: &mut Bus, port: u16| u32::from_le_bytes(std::array::from_fn(|b| bus.port_read(port + b as u16)));
+        assert_eq!(read_word(&mut bus, 0x2060), 0xba7816bf);
+        assert_eq!(read_word(&mut bus, 0x207C), 0xf20015ad);
+        assert_eq!(read_word(&mut bus, 0x200C), 0xf20015ad);
+    }
 }
@@ -0,0 +1,102 @@
+//! Import from CEmu
+//!
+//! CEmu can export the calculator's flash ("Save ROM image", the full 4MB,
+//! so the installed OS and every archived variable come along) and its RAM
+//! (a dump of D00000h-D657FFh, VRAM included). `Emu::import_cemu` rebuilds
+//! an emulator from those, which is the way to migrate calculators set up
+//! in CEmu.
+//!
+//! CEmu's `.ce` state images cannot be mapped onto this emulator: after a
+//! version word they are the raw bytes of CEmu's C structs one after another,
+//! so where the memory and each peripheral start depends on the CEmu version
+//! and the compiler that built it. They are recognized by that version word
+//! and rejected with `CemuImportError::StateImage` instead of being guessed
+//! at; export the ROM (and RAM) from CEmu instead.
+
+use std::fmt;
+
+use crate::memory::addr::{FLASH_SIZE, RAM_SIZE};
+
+/// Upper half of the version word that starts every CEmu state image
+/// (0xCECExxxx, little-endian)
+pub const CEMU_IMAGE_TAG: u16 = 0xCECE;
+
+/// Errors that can occur during import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CemuImportError {
+    /// A `.ce` state image (with its version word) instead of a flash export
+    StateImage(u32),
+    /// The flash export is not the full 4MB
+    FlashSize(usize),
+    /// The RAM export is not D00000h-D657FFh
+    RamSize(usize),
+}
+
+impl CemuImportError {
+    /// Code for FFI: -2 state image, -3 flash size, -4 RAM size
+    pub fn code(&self) -> i32 {
+        match self {
+            CemuImportError::StateImage(_) => -2,
+            CemuImportError::FlashSize(_) => -3,
+            CemuImportError::RamSize(_) => -4,
+        }
+    }
+}
+
+impl fmt::Display for CemuImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CemuImportError::StateImage(version) => write!(
+                f,
+                "CEmu state image (version {:08X}) cannot be imported; export the ROM from CEmu instead",
+                version
+            ),
+            CemuImportError::FlashSize(len) => {
+                write!(f, "flash export is {} bytes, expected {}", len, FLASH_SIZE)
+            }
+            CemuImportError::RamSize(len) => write!(f, "RAM export is {} bytes, expected {}", len, RAM_SIZE),
+        }
+    }
+}
+
+impl std::error::Error for CemuImportError {}
+
+/// Version word of a CEmu state image, if `data` looks like one
+pub fn state_image_version(data: &[u8]) -> Option<u32> {
+    let word = u32::from_le_bytes(data.get(..4)?.try_into().unwrap());
+    ((word >> 16) as u16 == CEMU_IMAGE_TAG).then_some(word)
+}
+
+/// Check that `flash` and `ram` are CEmu exports this emulator can load
+pub fn validate(flash: &[u8], ram: Option<&[u8]>) -> Result<(), CemuImportError> {
+    if let Some(version) = state_image_version(flash) {
+        return Err(CemuImportError::StateImage(version));
+    }
+    if flash.len() != FLASH_SIZE {
+        return Err(CemuImportError::FlashSize(flash.len()));
+    }
+    match ram {
+        Some(ram) if ram.len() != RAM_SIZE => Err(CemuImportError::RamSize(ram.len())),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let flash = vec![0xFF; FLASH_SIZE];
+        let ram = vec![0; RAM_SIZE];
+        assert_eq!(validate(&flash, Some(&ram)), Ok(()));
+        assert_eq!(validate(&flash, None), Ok(()));
+        assert_eq!(validate(&flash[..0x100000], None), Err(CemuImportError::FlashSize(0x100000)));
+        assert_eq!(validate(&flash, Some(&ram[..0x40000])), Err(CemuImportError::RamSize(0x40000)));
+
+        let mut image = vec![0x1B, 0x00, 0xCE, 0xCE];
+        image.resize(0x500000, 0);
+        assert_eq!(validate(&image, None), Err(CemuImportError::StateImage(0xCECE001B)));
+        assert_eq!(validate(&image, None).unwrap_err().code(), -2);
+    }
+}
@@ -0,0 +1,404 @@
+//! TI-84 Plus CE character set ↔ Unicode
+//!
+//! The OS draws text with its own 8-bit character set: ASCII in the printable
+//! range, with θ at 0x5B and math glyphs, accented letters, Greek, subscripts
+//! and small caps elsewhere. This is the encoding of the homescreen text buffer
+//! and of the glyph strings produced when tokens are displayed.
+//!
+//! Glyphs without a Unicode equivalent (cursors, graph style icons, unused
+//! codes) map into the Private Use Area at `PRIVATE_BASE + code`, so every
+//! code decodes to something and `encode(&decode(bytes))` returns `bytes` for
+//! any input without duplicate glyphs. Where two codes draw the same glyph
+//! (e.g. 0x2F '/' and 0xD1 per-slash), encoding picks the lower code.
+//!
+//! Programs, equations and strings are stored as tokens instead: one byte,
+//! or a prefix byte (0x5C-0x63, 0x7E, 0xAA, 0xBB, 0xEF) and an index. The
+//! token tables give each token the text the OS shows for it, written with
+//! the same glyphs as the character set. `tokenize` matches the longest
+//! token text, so `tokenize(&detokenize(bytes)?)` gives back `bytes` for any
+//! single token; sequences that display the same (0x06 'A' 0x07 and the
+//! matrix [A]) come back as the longer token.
+
+use std::fmt;
+
+/// Private Use Area base for glyphs with no Unicode equivalent
+pub const PRIVATE_BASE: u32 = 0xF000;
+
+/// Unicode text for each character code. Empty entries are private glyphs.
+const CHARSET: [&str; 256] = [
+    // 0x00
+    "", "𝑛", "𝑢", "𝑣", "𝑤", "►", "⬆", "⬇",
+    "∫", "×", "▫", "﹢", "·", "ᴛ", "³", "𝗙",
+    // 0x10
+    "√", "⁻¹", "²", "∠", "°", "ʳ", "ᵀ", "≤",
+    "≠", "≥", "⁻", "ᴇ", "→", "⏨", "↑", "↓",
+    // 0x20
+    " ", "!", "\"", "#", "$", "%", "&", "'",
+    "(", ")", "*", "+", ",", "-", ".", "/",
+    // 0x30
+    "0", "1", "2", "3", "4", "5", "6", "7",
+    "8", "9", ":", ";", "<", "=", ">", "?",
+    // 0x40
+    "@", "A", "B", "C", "D", "E", "F", "G",
+    "H", "I", "J", "K", "L", "M", "N", "O",
+    // 0x50
+    "P", "Q", "R", "S", "T", "U", "V", "W",
+    "X", "Y", "Z", "θ", "\\", "]", "^", "_",
+    // 0x60
+    "`", "a", "b", "c", "d", "e", "f", "g",
+    "h", "i", "j", "k", "l", "m", "n", "o",
+    // 0x70
+    "p", "q", "r", "s", "t", "u", "v", "w",
+    "x", "y", "z", "{", "|", "}", "~", "⌸",
+    // 0x80
+    "₀", "₁", "₂", "₃", "₄", "₅", "₆", "₇",
+    "₈", "₉", "Á", "À", "Â", "Ä", "á", "à",
+    // 0x90
+    "â", "ä", "É", "È", "Ê", "Ë", "é", "è",
+    "ê", "ë", "Í", "Ì", "Î", "Ï", "í", "ì",
+    // 0xA0
+    "î", "ï", "Ó", "Ò", "Ô", "Ö", "ó", "ò",
+    "ô", "ö", "Ú", "Ù", "Û", "Ü", "ú", "ù",
+    // 0xB0
+    "û", "ü", "Ç", "ç", "Ñ", "ñ", "´", "`",
+    "¨", "¿", "¡", "α", "β", "γ", "Δ", "δ",
+    // 0xC0
+    "ε", "[", "λ", "μ", "π", "ρ", "Σ", "σ",
+    "τ", "φ", "Ω", "x̄", "ȳ", "ˣ", "…", "◄",
+    // 0xD0
+    "█", "/", "‐", "²", "°", "³", "↵", "𝑖",
+    "p̂", "χ", "𝙵", "𝑒", "ʟ", "𝗡", ")", "",
+    // 0xE0: cursors and graph style icons
+    "", "", "", "", "", "", "", "",
+    "", "", "", "", "", "", "", "⇧",
+    // 0xF0
+    "⇩", "█", "", "", "", "", "", "",
+    "", "", "", "", "", "", "", "",
+];
+
+/// Text of each one-byte token. Empty entries are prefixes or unused.
+const TOKENS: [&str; 256] = [
+    // 0x00
+    "", "►DMS", "►Dec", "►Frac", "→", "Boxplot", "[", "]",
+    "{", "}", "ʳ", "°", "⁻¹", "²", "ᵀ", "³",
+    // 0x10
+    "(", ")", "round(", "pxl-Test(", "augment(", "rowSwap(", "row+(", "*row(",
+    "*row+(", "max(", "min(", "R►Pr(", "R►Pθ(", "P►Rx(", "P►Ry(", "median(",
+    // 0x20
+    "randM(", "mean(", "solve(", "seq(", "fnInt(", "nDeriv(", "", "fMin(",
+    "fMax(", " ", "\"", ",", "𝑖", "!", "CubicReg ", "QuartReg ",
+    // 0x30
+    "0", "1", "2", "3", "4", "5", "6", "7",
+    "8", "9", ".", "ᴇ", " or ", " xor ", ":", "\n",
+    // 0x40
+    " and ", "A", "B", "C", "D", "E", "F", "G",
+    "H", "I", "J", "K", "L", "M", "N", "O",
+    // 0x50
+    "P", "Q", "R", "S", "T", "U", "V", "W",
+    "X", "Y", "Z", "θ", "", "", "", "prgm",
+    // 0x60
+    "", "", "", "", "Radian", "Degree", "Normal", "Sci",
+    "Eng", "Float", "=", "<", ">", "≤", "≥", "≠",
+    // 0x70
+    "+", "-", "Ans", "Fix ", "Horiz", "Full", "Func", "Param",
+    "Polar", "Seq", "IndpntAuto", "IndpntAsk", "DependAuto", "DependAsk", "", "▫",
+    // 0x80
+    "﹢", "·", "*", "/", "Trace", "ClrDraw", "ZStandard", "ZTrig",
+    "ZBox", "Zoom In", "Zoom Out", "ZSquare", "ZInteger", "ZPrevious", "ZDecimal", "ZoomStat",
+    // 0x90
+    "ZoomRcl", "PrintScreen", "ZoomSto", "Text(", " nPr ", " nCr ", "FnOn ", "FnOff ",
+    "StorePic ", "RecallPic ", "StoreGDB ", "RecallGDB ", "Line(", "Vertical ", "Pt-On(", "Pt-Off(",
+    // 0xA0
+    "Pt-Change(", "Pxl-On(", "Pxl-Off(", "Pxl-Change(", "Shade(", "Circle(", "Horizontal ", "Tangent(",
+    "DrawInv ", "DrawF ", "", "rand", "π", "getKey", "'", "?",
+    // 0xB0
+    "⁻", "int(", "abs(", "det(", "identity(", "dim(", "sum(", "prod(",
+    "not(", "iPart(", "fPart(", "", "√(", "³√(", "ln(", "𝑒^(",
+    // 0xC0
+    "log(", "₁₀^(", "sin(", "sin⁻¹(", "cos(", "cos⁻¹(", "tan(", "tan⁻¹(",
+    "sinh(", "sinh⁻¹(", "cosh(", "cosh⁻¹(", "tanh(", "tanh⁻¹(", "If ", "Then",
+    // 0xD0
+    "Else", "While ", "Repeat ", "For(", "End", "Return", "Lbl ", "Goto ",
+    "Pause ", "Stop", "IS>(", "DS<(", "Input ", "Prompt ", "Disp ", "DispGraph",
+    // 0xE0
+    "Output(", "ClrHome", "Fill(", "SortA(", "SortD(", "DispTable", "Menu(", "Send(",
+    "Get(", "PlotsOn ", "PlotsOff ", "ʟ", "Plot1(", "Plot2(", "Plot3(", "",
+    // 0xF0
+    "^", "×√", "1-Var Stats ", "2-Var Stats ", "LinReg(a+bx) ", "ExpReg ", "LnReg ", "PwrReg ",
+    "Med-Med ", "QuadReg ", "ClrList ", "ClrTable", "Histogram", "xyLine", "Scatter", "LinReg(ax+b) ",
+];
+
+/// Text of the common two-byte tokens: variables behind the 0x5C-0x63 and
+/// 0xAA prefixes, and the 0xBB and 0xEF extended tokens
+const TWO_BYTE_TOKENS: &[([u8; 2], &str)] = &[
+    // Matrices
+    ([0x5C, 0x00], "[A]"), ([0x5C, 0x01], "[B]"), ([0x5C, 0x02], "[C]"), ([0x5C, 0x03], "[D]"),
+    ([0x5C, 0x04], "[E]"), ([0x5C, 0x05], "[F]"), ([0x5C, 0x06], "[G]"), ([0x5C, 0x07], "[H]"),
+    ([0x5C, 0x08], "[I]"), ([0x5C, 0x09], "[J]"),
+    // Lists
+    ([0x5D, 0x00], "L₁"), ([0x5D, 0x01], "L₂"), ([0x5D, 0x02], "L₃"),
+    ([0x5D, 0x03], "L₄"), ([0x5D, 0x04], "L₅"), ([0x5D, 0x05], "L₆"),
+    // Equations
+    ([0x5E, 0x10], "Y₁"), ([0x5E, 0x11], "Y₂"), ([0x5E, 0x12], "Y₃"), ([0x5E, 0x13], "Y₄"),
+    ([0x5E, 0x14], "Y₅"), ([0x5E, 0x15], "Y₆"), ([0x5E, 0x16], "Y₇"), ([0x5E, 0x17], "Y₈"),
+    ([0x5E, 0x18], "Y₉"), ([0x5E, 0x19], "Y₀"),
+    ([0x5E, 0x40], "r₁"), ([0x5E, 0x41], "r₂"), ([0x5E, 0x42], "r₃"),
+    ([0x5E, 0x43], "r₄"), ([0x5E, 0x44], "r₅"), ([0x5E, 0x45], "r₆"),
+    ([0x5E, 0x80], "𝑢"), ([0x5E, 0x81], "𝑣"), ([0x5E, 0x82], "𝑤"),
+    // Pictures and graph databases
+    ([0x60, 0x00], "Pic1"), ([0x60, 0x01], "Pic2"), ([0x60, 0x02], "Pic3"), ([0x60, 0x03], "Pic4"),
+    ([0x60, 0x04], "Pic5"), ([0x60, 0x05], "Pic6"), ([0x60, 0x06], "Pic7"), ([0x60, 0x07], "Pic8"),
+    ([0x60, 0x08], "Pic9"), ([0x60, 0x09], "Pic0"),
+    ([0x61, 0x00], "GDB1"), ([0x61, 0x01], "GDB2"), ([0x61, 0x02], "GDB3"), ([0x61, 0x03], "GDB4"),
+    ([0x61, 0x04], "GDB5"), ([0x61, 0x05], "GDB6"), ([0x61, 0x06], "GDB7"), ([0x61, 0x07], "GDB8"),
+    ([0x61, 0x08], "GDB9"), ([0x61, 0x09], "GDB0"),
+    // Window variables
+    ([0x63, 0x02], "Xscl"), ([0x63, 0x03], "Yscl"),
+    ([0x63, 0x0A], "Xmin"), ([0x63, 0x0B], "Xmax"), ([0x63, 0x0C], "Ymin"), ([0x63, 0x0D], "Ymax"),
+    // Strings
+    ([0xAA, 0x00], "Str1"), ([0xAA, 0x01], "Str2"), ([0xAA, 0x02], "Str3"), ([0xAA, 0x03], "Str4"),
+    ([0xAA, 0x04], "Str5"), ([0xAA, 0x05], "Str6"), ([0xAA, 0x06], "Str7"), ([0xAA, 0x07], "Str8"),
+    ([0xAA, 0x08], "Str9"), ([0xAA, 0x09], "Str0"),
+    // Extended functions
+    ([0xBB, 0x00], "npv("), ([0xBB, 0x01], "irr("), ([0xBB, 0x02], "bal("), ([0xBB, 0x03], "ΣPrn("),
+    ([0xBB, 0x04], "ΣInt("), ([0xBB, 0x05], "►Nom("), ([0xBB, 0x06], "►Eff("), ([0xBB, 0x07], "dbd("),
+    ([0xBB, 0x08], "lcm("), ([0xBB, 0x09], "gcd("), ([0xBB, 0x0A], "randInt("), ([0xBB, 0x0B], "randBin("),
+    ([0xBB, 0x0C], "sub("), ([0xBB, 0x0D], "stdDev("), ([0xBB, 0x0E], "variance("), ([0xBB, 0x0F], "inString("),
+    ([0xBB, 0x10], "normalcdf("), ([0xBB, 0x11], "invNorm("), ([0xBB, 0x12], "tcdf("), ([0xBB, 0x13], "χ²cdf("),
+    ([0xBB, 0x14], "Fcdf("), ([0xBB, 0x15], "binompdf("), ([0xBB, 0x16], "binomcdf("), ([0xBB, 0x17], "poissonpdf("),
+    ([0xBB, 0x18], "poissoncdf("), ([0xBB, 0x19], "geometpdf("), ([0xBB, 0x1A], "geometcdf("), ([0xBB, 0x1B], "normalpdf("),
+    ([0xBB, 0x1C], "tpdf("), ([0xBB, 0x1D], "χ²pdf("), ([0xBB, 0x1E], "Fpdf("), ([0xBB, 0x1F], "randNorm("),
+    ([0xBB, 0x25], "conj("), ([0xBB, 0x26], "real("), ([0xBB, 0x27], "imag("), ([0xBB, 0x28], "angle("),
+    ([0xBB, 0x29], "cumSum("), ([0xBB, 0x2A], "expr("), ([0xBB, 0x2B], "length("), ([0xBB, 0x2C], "ΔList("),
+    ([0xBB, 0x2D], "ref("), ([0xBB, 0x2E], "rref("), ([0xBB, 0x2F], "►Rect"), ([0xBB, 0x30], "►Polar"),
+    ([0xBB, 0x31], "𝑒"),
+    ([0xBB, 0x4D], "Real"), ([0xBB, 0x4E], "r𝑒^θ𝑖"), ([0xBB, 0x4F], "a+b𝑖"),
+    ([0xBB, 0x52], "ClrAllLists"), ([0xBB, 0x53], "GetCalc("), ([0xBB, 0x54], "DelVar "),
+    ([0xBB, 0x55], "Equ►String("), ([0xBB, 0x56], "String►Equ("),
+    ([0xBB, 0x68], "Archive "), ([0xBB, 0x69], "UnArchive "),
+    ([0xBB, 0x6A], "Asm("), ([0xBB, 0x6B], "AsmComp("), ([0xBB, 0x6C], "AsmPrgm"),
+    // Lowercase letters (0xBB 0xBB is skipped)
+    ([0xBB, 0xB0], "a"), ([0xBB, 0xB1], "b"), ([0xBB, 0xB2], "c"), ([0xBB, 0xB3], "d"),
+    ([0xBB, 0xB4], "e"), ([0xBB, 0xB5], "f"), ([0xBB, 0xB6], "g"), ([0xBB, 0xB7], "h"),
+    ([0xBB, 0xB8], "i"), ([0xBB, 0xB9], "j"), ([0xBB, 0xBA], "k"), ([0xBB, 0xBC], "l"),
+    ([0xBB, 0xBD], "m"), ([0xBB, 0xBE], "n"), ([0xBB, 0xBF], "o"), ([0xBB, 0xC0], "p"),
+    ([0xBB, 0xC1], "q"), ([0xBB, 0xC2], "r"), ([0xBB, 0xC3], "s"), ([0xBB, 0xC4], "t"),
+    ([0xBB, 0xC5], "u"), ([0xBB, 0xC6], "v"), ([0xBB, 0xC7], "w"), ([0xBB, 0xC8], "x"),
+    ([0xBB, 0xC9], "y"), ([0xBB, 0xCA], "z"),
+    // Clock (84 Plus and later)
+    ([0xEF, 0x00], "setDate("), ([0xEF, 0x01], "setTime("), ([0xEF, 0x02], "checkTmr("),
+    ([0xEF, 0x03], "setDtFmt("), ([0xEF, 0x04], "setTmFmt("), ([0xEF, 0x05], "timeCnv("),
+    ([0xEF, 0x06], "dayOfWk("), ([0xEF, 0x07], "getDtStr("), ([0xEF, 0x08], "getTmStr("),
+    ([0xEF, 0x09], "getDate"), ([0xEF, 0x0A], "getTime"), ([0xEF, 0x0B], "startTmr"),
+    ([0xEF, 0x0C], "getDtFmt"), ([0xEF, 0x0D], "getTmFmt"), ([0xEF, 0x0E], "isClockOn"),
+    ([0xEF, 0x0F], "ClockOff"), ([0xEF, 0x10], "ClockOn"),
+];
+
+/// A character with no equivalent in the calculator's character set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeError {
+    /// The character that could not be encoded
+    pub ch: char,
+    /// Byte offset of the character in the input string
+    pub offset: usize,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no calculator character for {:?} (U+{:04X}) at offset {}", self.ch, self.ch as u32, self.offset)
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// A token missing from the token tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownToken {
+    /// Byte offset of the token in the input
+    pub offset: usize,
+}
+
+impl fmt::Display for UnknownToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown token at offset {}", self.offset)
+    }
+}
+
+impl std::error::Error for UnknownToken {}
+
+/// Unicode text for one character code
+pub fn to_unicode(code: u8) -> String {
+    match CHARSET[code as usize] {
+        "" => char::from_u32(PRIVATE_BASE + code as u32).unwrap().to_string(),
+        s => s.to_string(),
+    }
+}
+
+/// Decode calculator text to a Unicode string
+pub fn decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| to_unicode(b)).collect()
+}
+
+/// Match the longest character code at the start of `text`.
+/// Returns the code and the number of bytes of `text` it covers.
+pub fn match_prefix(text: &str) -> Option<(u8, usize)> {
+    let first = text.chars().next()?;
+    let private = first as u32;
+    if (PRIVATE_BASE..PRIVATE_BASE + 256).contains(&private) {
+        return Some(((private - PRIVATE_BASE) as u8, first.len_utf8()));
+    }
+    let mut best: Option<(u8, usize)> = None;
+    for (code, glyph) in CHARSET.iter().enumerate() {
+        if glyph.is_empty() || !text.starts_with(glyph) {
+            continue;
+        }
+        // Longest match wins ("⁻¹" over "⁻"); ties keep the lowest code
+        if best.is_none_or(|(_, len)| glyph.len() > len) {
+            best = Some((code as u8, glyph.len()));
+        }
+    }
+    best
+}
+
+/// Encode a Unicode string as calculator text
+pub fn encode(text: &str) -> Result<Vec<u8>, EncodeError> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut offset = 0;
+    while offset < text.len() {
+        let rest = &text[offset..];
+        let (code, len) = match_prefix(rest).ok_or_else(|| EncodeError {
+            ch: rest.chars().next().unwrap(),
+            offset,
+        })?;
+        out.push(code);
+        offset += len;
+    }
+    Ok(out)
+}
+
+/// Text of the token at the start of `bytes` and its length in bytes
+pub fn token_text(bytes: &[u8]) -> Option<(&'static str, usize)> {
+    let &first = bytes.first()?;
+    match TOKENS[first as usize] {
+        "" => {
+            let pair = [first, *bytes.get(1)?];
+            TWO_BYTE_TOKENS.iter().find(|(code, _)| *code == pair).map(|&(_, text)| (text, 2))
+        }
+        text => Some((text, 1)),
+    }
+}
+
+/// Decode tokenized data (a program, equation or string) to Unicode text
+pub fn detokenize(bytes: &[u8]) -> Result<String, UnknownToken> {
+    let mut out = String::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let (text, len) = token_text(&bytes[offset..]).ok_or(UnknownToken { offset })?;
+        out.push_str(text);
+        offset += len;
+    }
+    Ok(out)
+}
+
+/// Match the longest token text at the start of `text`.
+/// Returns the token bytes and the number of bytes of `text` they cover.
+pub fn match_token(text: &str) -> Option<(Vec<u8>, usize)> {
+    let one_byte = TOKENS.iter().enumerate().map(|(code, &glyph)| (vec![code as u8], glyph));
+    let two_byte = TWO_BYTE_TOKENS.iter().map(|(code, glyph)| (code.to_vec(), *glyph));
+    let mut best: Option<(Vec<u8>, usize)> = None;
+    for (code, glyph) in one_byte.chain(two_byte) {
+        if glyph.is_empty() || !text.starts_with(glyph) {
+            continue;
+        }
+        if best.as_ref().is_none_or(|(_, len)| glyph.len() > *len) {
+            best = Some((code, glyph.len()));
+        }
+    }
+    best
+}
+
+/// Tokenize Unicode text, always taking the longest matching token
+pub fn tokenize(text: &str) -> Result<Vec<u8>, EncodeError> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut offset = 0;
+    while offset < text.len() {
+        let rest = &text[offset..];
+        let (code, len) = match_token(rest).ok_or_else(|| EncodeError {
+            ch: rest.chars().next().unwrap(),
+            offset,
+        })?;
+        out.extend_from_slice(&code);
+        offset += len;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_special_glyphs() {
+        assert_eq!(decode(b"HELLO"), "HELLO");
+        assert_eq!(decode(&[0x41, 0x1C, 0x5B]), "A→θ");
+        assert_eq!(decode(&[0x32, 0x11]), "2⁻¹");
+        assert_eq!(decode(&[0xDC, 0x31, 0x1B, 0x33]), "ʟ1ᴇ3");
+        assert_eq!(decode(&[0xE0]), "\u{F0E0}");
+    }
+
+    #[test]
+    fn test_encode_prefers_longest_and_lowest() {
+        assert_eq!(encode("X⁻¹").unwrap(), [0x58, 0x11]);
+        assert_eq!(encode("⁻5").unwrap(), [0x1A, 0x35]);
+        assert_eq!(encode("1/2").unwrap(), [0x31, 0x2F, 0x32]);
+        assert_eq!(encode("[A]").unwrap(), [0xC1, 0x41, 0x5D]);
+        assert_eq!(encode("x̄").unwrap(), [0xCB]);
+
+        let err = encode("A€").unwrap_err();
+        assert_eq!((err.ch, err.offset), ('€', 1));
+    }
+
+    #[test]
+    fn test_round_trip_all_canonical_codes() {
+        for code in 0..=255u8 {
+            let text = to_unicode(code);
+            let back = encode(&text).unwrap();
+            assert_eq!(back.len(), 1, "code {:02X}", code);
+            // Duplicate glyphs encode to their canonical (lowest) code
+            assert_eq!(to_unicode(back[0]), text, "code {:02X}", code);
+            assert!(back[0] <= code);
+        }
+    }
+
+    #[test]
+    fn test_detokenize_program() {
+        // "Disp "HI":2→A"
+        let prog = [0xDE, 0x2A, 0x48, 0x49, 0x2A, 0x3F, 0x32, 0x04, 0x41];
+        assert_eq!(detokenize(&prog).unwrap(), "Disp \"HI\"\n2→A");
+        assert_eq!(detokenize(&[0x5E, 0x10, 0x6A, 0xC2, 0x5B, 0x11]).unwrap(), "Y₁=sin(θ)");
+        assert_eq!(detokenize(&[0xBB, 0xB0, 0xBB, 0xBC, 0xEB, 0x41]).unwrap(), "alʟA");
+        assert_eq!(detokenize(&[0x41, 0xBB]), Err(UnknownToken { offset: 1 }));
+        assert_eq!(detokenize(&[0x26]), Err(UnknownToken { offset: 0 }));
+    }
+
+    #[test]
+    fn test_tokenize_prefers_longest() {
+        assert_eq!(tokenize("If A≥1:Then").unwrap(), [0xCE, 0x41, 0x6E, 0x31, 0x3E, 0xCF]);
+        assert_eq!(tokenize("sin⁻¹(X").unwrap(), [0xC3, 0x58]);
+        assert_eq!(tokenize("[A]⁻¹").unwrap(), [0x5C, 0x00, 0x0C]);
+        assert_eq!(tokenize("Str1→Str2").unwrap(), [0xAA, 0x00, 0x04, 0xAA, 0x01]);
+
+        let err = tokenize("A€").unwrap_err();
+        assert_eq!((err.ch, err.offset), ('€', 1));
+    }
+
+    #[test]
+    fn test_round_trip_all_tokens() {
+        let one_byte = (0..=255u8).filter(|&code| !TOKENS[code as usize].is_empty()).map(|code| vec![code]);
+        let two_byte = TWO_BYTE_TOKENS.iter().map(|(code, _)| code.to_vec());
+        for code in one_byte.chain(two_byte) {
+            let text = detokenize(&code).unwrap();
+            assert_eq!(tokenize(&text).unwrap(), code, "token {:02X?} {:?}", code, text);
+        }
+    }
+}
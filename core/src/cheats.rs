@@ -0,0 +1,185 @@
+//! Cheat/poke patches
+//!
+//! A cheat is a memory poke re-applied every frame, optionally only while
+//! another byte holds a given value (e.g. "lives = 9 while in level mode").
+//! Pokes go through `Bus::poke_byte`, so they skip bus timing and memory
+//! protection and can target RAM, VRAM, or flash.
+
+use crate::bus::Bus;
+
+/// When cheats are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheatPoint {
+    /// At the start of every `run_cycles()` call (once per host frame)
+    #[default]
+    RunStart,
+    /// Each time the LCD controller completes a frame (vblank)
+    Vblank,
+}
+
+impl CheatPoint {
+    /// Point from its FFI id (0 = RunStart, 1 = Vblank)
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(CheatPoint::RunStart),
+            1 => Some(CheatPoint::Vblank),
+            _ => None,
+        }
+    }
+}
+
+/// Byte that must hold a value for a cheat to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheatCondition {
+    /// Address to check
+    pub addr: u32,
+    /// Value the byte must equal
+    pub value: u8,
+}
+
+/// A single memory poke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cheat {
+    /// Address to write
+    pub addr: u32,
+    /// Value written
+    pub value: u8,
+    /// Only apply while this condition holds
+    pub condition: Option<CheatCondition>,
+    /// Disabled cheats are kept but not applied
+    pub enabled: bool,
+}
+
+impl Cheat {
+    /// Unconditional, enabled poke
+    pub fn new(addr: u32, value: u8) -> Self {
+        Self { addr, value, condition: None, enabled: true }
+    }
+
+    /// Only apply while the byte at `addr` equals `value`
+    pub fn when(mut self, addr: u32, value: u8) -> Self {
+        self.condition = Some(CheatCondition { addr, value });
+        self
+    }
+}
+
+/// Set of cheats keyed by id
+#[derive(Debug, Clone, Default)]
+pub struct CheatManager {
+    cheats: Vec<(u32, Cheat)>,
+    next_id: u32,
+    point: CheatPoint,
+}
+
+impl CheatManager {
+    /// Create an empty manager applying at `CheatPoint::RunStart`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a cheat, returning its id
+    pub fn add(&mut self, cheat: Cheat) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.cheats.push((id, cheat));
+        id
+    }
+
+    /// Remove a cheat. Returns false if the id is unknown.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let len = self.cheats.len();
+        self.cheats.retain(|(i, _)| *i != id);
+        self.cheats.len() != len
+    }
+
+    /// Enable or disable a cheat. Returns false if the id is unknown.
+    pub fn set_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        match self.cheats.iter_mut().find(|(i, _)| *i == id) {
+            Some((_, cheat)) => {
+                cheat.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look up a cheat by id
+    pub fn get(&self, id: u32) -> Option<&Cheat> {
+        self.cheats.iter().find(|(i, _)| *i == id).map(|(_, cheat)| cheat)
+    }
+
+    /// All cheats with their ids, in the order they were added
+    pub fn list(&self) -> &[(u32, Cheat)] {
+        &self.cheats
+    }
+
+    /// Remove every cheat
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+    }
+
+    /// True if no cheats are registered
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    /// When cheats are applied
+    pub fn point(&self) -> CheatPoint {
+        self.point
+    }
+
+    /// Choose when cheats are applied
+    pub fn set_point(&mut self, point: CheatPoint) {
+        self.point = point;
+    }
+
+    /// Apply enabled cheats whose conditions hold, in the order they were added.
+    /// Returns the number of pokes written.
+    pub fn apply(&self, bus: &mut Bus) -> usize {
+        let mut applied = 0;
+        for (_, cheat) in &self.cheats {
+            if !cheat.enabled {
+                continue;
+            }
+            if let Some(cond) = cheat.condition {
+                if bus.peek_byte(cond.addr) != cond.value {
+                    continue;
+                }
+            }
+            bus.poke_byte(cheat.addr, cheat.value);
+            applied += 1;
+        }
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_respects_enable_and_condition() {
+        let mut bus = Bus::new();
+        let mut cheats = CheatManager::new();
+        let lives = cheats.add(Cheat::new(0xD01000, 9));
+        let gated = cheats.add(Cheat::new(0xD01001, 0x55).when(0xD01002, 1));
+
+        assert_eq!(cheats.apply(&mut bus), 1);
+        assert_eq!(bus.peek_byte(0xD01000), 9);
+        assert_eq!(bus.peek_byte(0xD01001), 0);
+
+        bus.poke_byte(0xD01002, 1);
+        assert_eq!(cheats.apply(&mut bus), 2);
+        assert_eq!(bus.peek_byte(0xD01001), 0x55);
+
+        assert!(cheats.set_enabled(lives, false));
+        bus.poke_byte(0xD01000, 3);
+        assert_eq!(cheats.apply(&mut bus), 1);
+        assert_eq!(bus.peek_byte(0xD01000), 3);
+
+        assert!(cheats.remove(gated));
+        assert!(!cheats.remove(gated));
+        assert!(!cheats.set_enabled(gated, true));
+        assert_eq!(cheats.list().len(), 1);
+    }
+}
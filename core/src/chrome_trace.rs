@@ -0,0 +1,201 @@
+//! Chrome trace-event export
+//!
+//! Records scheduler events, interrupt services, LCD frame boundaries, and
+//! optionally OS bcalls, then writes them in the Chrome trace-event JSON
+//! format so a run can be inspected on a timeline in Perfetto or
+//! chrome://tracing.
+//!
+//! Timestamps are emulated microseconds since recording started. They are
+//! accumulated from CPU cycles at the CPU speed in effect when each event is
+//! recorded, so they stay monotonic across speed changes and resets.
+
+use std::io::{self, Write};
+
+use crate::scheduler::EventId;
+
+/// Start of the OS jump table; bcalls are calls to its 4-byte entries
+pub const JUMP_TABLE_START: u32 = 0x020104;
+/// End of the OS jump table (exclusive)
+pub const JUMP_TABLE_END: u32 = 0x022400;
+
+/// Recording options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChromeTraceConfig {
+    /// Also record calls into the OS jump table
+    pub bcalls: bool,
+    /// Events kept; later ones are counted but dropped
+    pub max_events: usize,
+}
+
+impl Default for ChromeTraceConfig {
+    fn default() -> Self {
+        Self { bcalls: false, max_events: 1_000_000 }
+    }
+}
+
+/// What happened at a traced instant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceKind {
+    /// A scheduler event fired
+    Scheduler(EventId),
+    /// An interrupt was serviced at `pc`; `sources` are the pending source bits
+    Interrupt { pc: u32, sources: u32 },
+    /// A non-maskable interrupt was serviced at `pc`
+    Nmi { pc: u32 },
+    /// The LCD controller completed frame number `frame`
+    Frame { frame: u64 },
+    /// Execution entered the jump table entry at `entry`
+    Bcall { entry: u32 },
+}
+
+/// A recorded event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceRecord {
+    /// Emulated microseconds since recording started
+    pub time_us: f64,
+    /// What happened
+    pub kind: TraceKind,
+}
+
+/// Event recorder
+#[derive(Debug, Clone)]
+pub struct ChromeTracer {
+    config: ChromeTraceConfig,
+    recording: bool,
+    records: Vec<TraceRecord>,
+    dropped: u64,
+    time_us: f64,
+    last_cycles: u64,
+}
+
+impl ChromeTracer {
+    /// Start recording; `total_cycles` is the emulator's current cycle count
+    pub fn new(config: ChromeTraceConfig, total_cycles: u64) -> Self {
+        Self {
+            config,
+            recording: true,
+            records: Vec::new(),
+            dropped: 0,
+            time_us: 0.0,
+            last_cycles: total_cycles,
+        }
+    }
+
+    /// Recording options
+    pub fn config(&self) -> ChromeTraceConfig {
+        self.config
+    }
+
+    /// Whether events are still being recorded
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Stop recording (recorded events are kept for export)
+    pub fn stop(&mut self) {
+        self.recording = false;
+    }
+
+    /// Recorded events, oldest first
+    pub fn records(&self) -> &[TraceRecord] {
+        &self.records
+    }
+
+    /// Events dropped after `max_events` was reached
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Record an event at `total_cycles`, running at `cpu_hz`
+    pub fn record(&mut self, kind: TraceKind, total_cycles: u64, cpu_hz: f64) {
+        if !self.recording {
+            return;
+        }
+        // total_cycles restarts at reset; count only cycles since then
+        let cycles = total_cycles.checked_sub(self.last_cycles).unwrap_or(total_cycles);
+        self.last_cycles = total_cycles;
+        self.time_us += cycles as f64 * 1e6 / cpu_hz;
+        if self.records.len() >= self.config.max_events {
+            self.dropped += 1;
+            return;
+        }
+        self.records.push(TraceRecord { time_us: self.time_us, kind });
+    }
+
+    /// Write the recording as Chrome trace-event JSON. Returns bytes written.
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<usize> {
+        let mut json = String::from("{\"displayTimeUnit\":\"ns\",\"traceEvents\":[\n");
+        for (tid, name) in [(1, "CPU"), (2, "Scheduler"), (3, "LCD")] {
+            json.push_str(&format!(
+                "{{\"ph\":\"M\",\"name\":\"thread_name\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}},\n",
+                tid, name
+            ));
+        }
+        for record in &self.records {
+            let (tid, name, args) = match record.kind {
+                TraceKind::Scheduler(event) => (2, format!("{:?}", event), String::new()),
+                TraceKind::Interrupt { pc, sources } => {
+                    (1, "IRQ".to_string(), format!("\"pc\":\"{:06X}\",\"sources\":\"{:08X}\"", pc, sources))
+                }
+                TraceKind::Nmi { pc } => (1, "NMI".to_string(), format!("\"pc\":\"{:06X}\"", pc)),
+                TraceKind::Frame { frame } => (3, "frame".to_string(), format!("\"frame\":{}", frame)),
+                TraceKind::Bcall { entry } => (1, format!("bcall {:06X}", entry), String::new()),
+            };
+            json.push_str(&format!(
+                "{{\"ph\":\"i\",\"s\":\"t\",\"name\":\"{}\",\"pid\":1,\"tid\":{},\"ts\":{:.3},\"args\":{{{}}}}},\n",
+                name, tid, record.time_us, args
+            ));
+        }
+        json.push_str(&format!(
+            "{{\"ph\":\"M\",\"name\":\"process_name\",\"pid\":1,\"args\":{{\"name\":\"TI-84 Plus CE\",\"dropped_events\":{}}}}}\n]}}\n",
+            self.dropped
+        ));
+        out.write_all(json.as_bytes())?;
+        Ok(json.len())
+    }
+}
+
+/// True if `pc` is the start of a jump table entry (a bcall target)
+pub fn is_bcall_entry(pc: u32) -> bool {
+    (JUMP_TABLE_START..JUMP_TABLE_END).contains(&pc) && (pc - JUMP_TABLE_START).is_multiple_of(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps_and_json() {
+        let mut tracer = ChromeTracer::new(ChromeTraceConfig { bcalls: true, max_events: 3 }, 1000);
+        tracer.record(TraceKind::Scheduler(EventId::Lcd), 49_000, 48e6);
+        // A reset restarts total_cycles; time keeps moving forward
+        tracer.record(TraceKind::Interrupt { pc: 0x1234, sources: 0x10 }, 6_000, 6e6);
+        tracer.record(TraceKind::Bcall { entry: 0x020108 }, 12_000, 6e6);
+        tracer.record(TraceKind::Frame { frame: 1 }, 13_000, 6e6);
+        let times: Vec<_> = tracer.records().iter().map(|r| r.time_us).collect();
+        assert_eq!(times, [1000.0, 2000.0, 3000.0]);
+        assert_eq!(tracer.dropped(), 1);
+
+        let mut out = Vec::new();
+        let len = tracer.write_json(&mut out).unwrap();
+        assert_eq!(len, out.len());
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.starts_with("{\"displayTimeUnit\""));
+        assert!(json.contains("\"name\":\"Lcd\",\"pid\":1,\"tid\":2,\"ts\":1000.000"));
+        assert!(json.contains("\"name\":\"IRQ\""));
+        assert!(json.contains("\"name\":\"bcall 020108\""));
+        assert!(json.trim_end().ends_with("]}"));
+
+        tracer.stop();
+        tracer.record(TraceKind::Frame { frame: 2 }, 20_000, 6e6);
+        assert_eq!(tracer.records().len(), 3);
+    }
+
+    #[test]
+    fn test_bcall_entries() {
+        assert!(is_bcall_entry(0x020104));
+        assert!(is_bcall_entry(0x020108));
+        assert!(!is_bcall_entry(0x020106));
+        assert!(!is_bcall_entry(0x000104));
+    }
+}
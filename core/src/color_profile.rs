@@ -0,0 +1,170 @@
+//! LCD color profile simulation
+//!
+//! The emulator outputs the ideal sRGB value of every VRAM color. The real
+//! CE panel is a backlit TFT with a narrower gamut, a cooler white point, and
+//! a raised black level, so sprites look noticeably less saturated on
+//! hardware. `ColorProfile::CePanel` approximates that in linear light:
+//!
+//! 1. decode sRGB to linear
+//! 2. mix channels toward each other (narrower gamut)
+//! 3. scale channels (white point) and lift the black level
+//! 4. re-encode to sRGB
+//!
+//! The coefficients are eyeballed from photos of the panel next to an sRGB
+//! reference, not measured with a colorimeter; treat the result as a preview.
+
+/// Output color transform applied to rendered frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorProfile {
+    /// Ideal sRGB output (no transform)
+    #[default]
+    Ideal,
+    /// Approximation of the TI-84 Plus CE panel
+    CePanel,
+}
+
+impl ColorProfile {
+    /// Profile from its FFI id (0 = Ideal, 1 = CePanel)
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(ColorProfile::Ideal),
+            1 => Some(ColorProfile::CePanel),
+            _ => None,
+        }
+    }
+
+    /// FFI id of this profile
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Linear-light channel mixing for the CE panel (rows: output R, G, B)
+const CE_PANEL_MIX: [[f32; 3]; 3] = [
+    [0.82, 0.13, 0.05],
+    [0.08, 0.84, 0.08],
+    [0.05, 0.12, 0.83],
+];
+/// Per-channel white point scale (slightly blue)
+const CE_PANEL_WHITE: [f32; 3] = [0.93, 0.97, 1.0];
+/// Linear-light black level of the backlit panel
+const CE_PANEL_BLACK: f32 = 0.012;
+
+/// Resolution of the linear → sRGB encode table
+const ENCODE_STEPS: usize = 4096;
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Precomputed tables for applying a profile to ARGB8888 pixels
+pub struct ColorTransform {
+    profile: ColorProfile,
+    decode: [f32; 256],
+    encode: Vec<u8>,
+}
+
+impl ColorTransform {
+    /// Build the tables for `profile`
+    pub fn new(profile: ColorProfile) -> Self {
+        let mut decode = [0.0; 256];
+        for (i, v) in decode.iter_mut().enumerate() {
+            *v = srgb_to_linear(i as f32 / 255.0);
+        }
+        let encode = (0..ENCODE_STEPS)
+            .map(|i| {
+                let v = linear_to_srgb(i as f32 / (ENCODE_STEPS - 1) as f32);
+                (v * 255.0 + 0.5) as u8
+            })
+            .collect();
+        Self { profile, decode, encode }
+    }
+
+    /// Profile these tables were built for
+    pub fn profile(&self) -> ColorProfile {
+        self.profile
+    }
+
+    /// Transform one ARGB8888 pixel (alpha is kept)
+    pub fn apply_pixel(&self, argb: u32) -> u32 {
+        if self.profile == ColorProfile::Ideal {
+            return argb;
+        }
+        let rgb = [
+            self.decode[((argb >> 16) & 0xFF) as usize],
+            self.decode[((argb >> 8) & 0xFF) as usize],
+            self.decode[(argb & 0xFF) as usize],
+        ];
+        let mut out = argb & 0xFF000000;
+        for (c, row) in CE_PANEL_MIX.iter().enumerate() {
+            let mixed = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+            let lit = CE_PANEL_BLACK + (1.0 - CE_PANEL_BLACK) * mixed * CE_PANEL_WHITE[c];
+            let index = (lit.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32 + 0.5) as usize;
+            out |= (self.encode[index] as u32) << (16 - 8 * c);
+        }
+        out
+    }
+
+    /// Transform a buffer of ARGB8888 pixels in place
+    pub fn apply(&self, pixels: &mut [u32]) {
+        if self.profile == ColorProfile::Ideal {
+            return;
+        }
+        for pixel in pixels {
+            *pixel = self.apply_pixel(*pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(argb: u32) -> (u32, u32, u32) {
+        ((argb >> 16) & 0xFF, (argb >> 8) & 0xFF, argb & 0xFF)
+    }
+
+    #[test]
+    fn test_ideal_is_identity() {
+        let t = ColorTransform::new(ColorProfile::Ideal);
+        let mut px = [0xFF123456, 0xFF000000, 0xFFFFFFFF];
+        t.apply(&mut px);
+        assert_eq!(px, [0xFF123456, 0xFF000000, 0xFFFFFFFF]);
+    }
+
+    #[test]
+    fn test_ce_panel_desaturates_and_lifts_black() {
+        let t = ColorTransform::new(ColorProfile::CePanel);
+
+        let (r, g, b) = channels(t.apply_pixel(0xFFFF0000));
+        assert!(r < 255 && g > 0 && b > 0, "pure red is desaturated: {} {} {}", r, g, b);
+
+        let (r, g, b) = channels(t.apply_pixel(0xFF000000));
+        assert!(r > 0 && g > 0 && b > 0, "black is lifted");
+
+        let (r, _, b) = channels(t.apply_pixel(0xFFFFFFFF));
+        assert!(b == 255 && r < b, "white is cool");
+
+        assert_eq!(t.apply_pixel(0x80FFFFFF) >> 24, 0x80);
+    }
+
+    #[test]
+    fn test_profile_ids() {
+        for profile in [ColorProfile::Ideal, ColorProfile::CePanel] {
+            assert_eq!(ColorProfile::from_id(profile.id()), Some(profile));
+        }
+        assert_eq!(ColorProfile::from_id(2), None);
+    }
+}
@@ -731,26 +731,7 @@ impl Cpu {
         match x {
             0 => self.execute_ed_x0(bus, y, z),
             1 => self.execute_ed_x1(bus, y, z, p, q),
-            2 => {
-                // Block instructions
-                // Standard Z80: y >= 4, z <= 3 (LDI/CPI/INI/OUTI and variants)
-                // eZ80 extended: y < 4, z = 2 or 3 (INIM/OTIM and variants)
-                // z=4: INI2/IND2/OUTI2/OUTD2 and repeat variants (both y<4 and y>=4)
-                if z <= 3 {
-                    if y >= 4 {
-                        // Standard Z80 block instructions
-                        self.execute_bli(bus, y, z)
-                    } else {
-                        // eZ80 extended block I/O instructions
-                        self.execute_bli_ez80(bus, y, z, p, q)
-                    }
-                } else if z == 4 {
-                    // z=4: INI2/IND2/OUTI2/OUTD2 + repeat variants
-                    self.execute_bli_z4(bus, y, z, p, q)
-                } else {
-                    8 // NOP for invalid
-                }
-            }
+            2 => self.execute_block(bus, opcode),
             3 => {
                 // x=3: a few valid instructions, rest are NONI/OPCODETRAP
                 let opcode = (x << 6) | (y << 3) | z;
@@ -770,7 +751,7 @@ impl Cpu {
                     // INIRX (C2), INDRX (CA), OTIRX (C3), OTDRX (CB)
                     // These route to the block instruction handler (execute_bli)
                     0xC2 | 0xCA | 0xC3 | 0xCB => {
-                        self.execute_bli_x3(bus, opcode)
+                        self.execute_block(bus, opcode)
                     }
                     _ => 8, // NONI (no operation, no interrupt)
                 }
@@ -779,6 +760,40 @@ impl Cpu {
         }
     }
 
+    /// Execute an ED-prefixed block instruction, given its second opcode byte.
+    /// Also continues one that yielded (see `block_yield`).
+    pub(super) fn execute_block(&mut self, bus: &mut Bus, opcode: u8) -> u32 {
+        self.block_opcode = opcode;
+        let x = (opcode >> 6) & 0x03;
+        let y = (opcode >> 3) & 0x07;
+        let z = opcode & 0x07;
+        let p = (y >> 1) & 0x03;
+        let q = y & 0x01;
+
+        if x == 3 {
+            // INIRX/INDRX/OTIRX/OTDRX
+            return self.execute_bli_x3(bus, opcode);
+        }
+        // Block instructions
+        // Standard Z80: y >= 4, z <= 3 (LDI/CPI/INI/OUTI and variants)
+        // eZ80 extended: y < 4, z = 2 or 3 (INIM/OTIM and variants)
+        // z=4: INI2/IND2/OUTI2/OUTD2 and repeat variants (both y<4 and y>=4)
+        if z <= 3 {
+            if y >= 4 {
+                // Standard Z80 block instructions
+                self.execute_bli(bus, y, z)
+            } else {
+                // eZ80 extended block I/O instructions
+                self.execute_bli_ez80(bus, y, z, p, q)
+            }
+        } else if z == 4 {
+            // z=4: INI2/IND2/OUTI2/OUTD2 + repeat variants
+            self.execute_bli_z4(bus, y, z, p, q)
+        } else {
+            8 // NOP for invalid
+        }
+    }
+
     /// Execute ED prefix x=0 opcodes (eZ80-specific I/O instructions)
     pub fn execute_ed_x0(&mut self, bus: &mut Bus, y: u8, z: u8) -> u32 {
         let p = y >> 1;
@@ -1281,6 +1296,21 @@ impl Cpu {
         }
     }
 
+    /// End an iteration of a repeating block instruction early once the next
+    /// event is due (`block_cycle_limit`), so events and interrupts are
+    /// handled. PC goes back to the instruction, which an interrupt returns
+    /// to; otherwise the next step continues it (`in_block`) without a new
+    /// fetch. CEmu's block loop runs while cpu.cycles < cpu.next and resumes
+    /// through cpu.inBlock the same way.
+    fn block_yield(&mut self, bus: &mut Bus) -> bool {
+        if bus.total_cycles() < self.block_cycle_limit {
+            return false;
+        }
+        self.pc = self.wrap_pc(self.pc.wrapping_sub(2 + self.suffix as u32));
+        self.in_block = true;
+        true
+    }
+
     /// Execute block instructions (ED prefix, x=2)
     pub fn execute_bli(&mut self, bus: &mut Bus, y: u8, z: u8) -> u32 {
         match (y, z) {
@@ -1321,7 +1351,7 @@ impl Cpu {
                 16
             }
             // LDIR - Load, increment, repeat
-            // Executes all iterations in a single instruction to match CEmu behavior
+            // Iterates until done or block_yield() rewinds it at the next event
             // CEmu preserves F3/F5 flags
             // CEmu only adds internalCycles (1) per iteration; memory ops add their own cycles
             (6, 0) => {
@@ -1340,14 +1370,14 @@ impl Cpu {
                     // CEmu: cpu.cycles += internalCycles (1 for LDIR)
                     bus.add_cycles(1);
 
-                    if self.bc == 0 {
+                    if self.bc == 0 || self.block_yield(bus) {
                         break;
                     }
                 }
                 0
             }
             // LDDR - Load, decrement, repeat
-            // Executes all iterations in a single instruction to match CEmu behavior
+            // Iterates until done or block_yield() rewinds it at the next event
             // CEmu preserves F3/F5 flags
             // CEmu only adds internalCycles (1) per iteration; memory ops add their own cycles
             (7, 0) => {
@@ -1366,7 +1396,7 @@ impl Cpu {
                     // CEmu: cpu.cycles += internalCycles (1 for LDDR)
                     bus.add_cycles(1);
 
-                    if self.bc == 0 {
+                    if self.bc == 0 || self.block_yield(bus) {
                         break;
                     }
                 }
@@ -1410,7 +1440,7 @@ impl Cpu {
                 16
             }
             // CPIR - Compare, increment, repeat
-            // Executes all iterations in a single instruction to match CEmu behavior
+            // Iterates until done or block_yield() rewinds it at the next event
             // Stops when BC=0 or when A matches the memory byte (result=0)
             // CEmu: internalCycles=2 when repeating, 1 when terminating
             (6, 1) => {
@@ -1431,6 +1461,9 @@ impl Cpu {
                     if self.bc != 0 && result != 0 {
                         // CEmu: cpu.cycles += internalCycles (2 for CPIR when repeating)
                         bus.add_cycles(2);
+                        if self.block_yield(bus) {
+                            break;
+                        }
                     } else {
                         // CEmu: internalCycles-- when not repeating, so 1 cycle
                         bus.add_cycles(1);
@@ -1440,7 +1473,7 @@ impl Cpu {
                 0
             }
             // CPDR - Compare, decrement, repeat
-            // Executes all iterations in a single instruction to match CEmu behavior
+            // Iterates until done or block_yield() rewinds it at the next event
             // Stops when BC=0 or when A matches the memory byte (result=0)
             // CEmu: internalCycles=2 when repeating, 1 when terminating
             (7, 1) => {
@@ -1461,6 +1494,9 @@ impl Cpu {
                     if self.bc != 0 && result != 0 {
                         // CEmu: cpu.cycles += internalCycles (2 for CPDR when repeating)
                         bus.add_cycles(2);
+                        if self.block_yield(bus) {
+                            break;
+                        }
                     } else {
                         // CEmu: internalCycles-- when not repeating, so 1 cycle
                         bus.add_cycles(1);
@@ -1494,7 +1530,7 @@ impl Cpu {
                     self.set_flag_z(new_b == 0);
                     self.set_flag_n(val & 0x80 != 0);
                     bus.add_cycles(1);
-                    if new_b == 0 {
+                    if new_b == 0 || self.block_yield(bus) {
                         break;
                     }
                 }
@@ -1525,7 +1561,7 @@ impl Cpu {
                     self.set_flag_z(new_b == 0);
                     self.set_flag_n(val & 0x80 != 0);
                     bus.add_cycles(1);
-                    if new_b == 0 {
+                    if new_b == 0 || self.block_yield(bus) {
                         break;
                     }
                 }
@@ -1539,9 +1575,8 @@ impl Cpu {
     /// These include OTIM, OTDM, OTIMR, OTDMR, INIM, INDM, INIMR, INDMR
     /// I/O is blocked on TI-84 CE, but registers still update
     ///
-    /// Note: Repeat variants (INIMR, INDMR, OTIMR, OTDMR) execute all iterations
-    /// in a single instruction, matching CEmu behavior. This is different from the
-    /// standard Z80 block instructions which use PC rewind.
+    /// Repeat variants (INIMR, INDMR, OTIMR, OTDMR) iterate like the standard
+    /// Z80 block instructions, up to the next event (see `block_yield`).
     pub fn execute_bli_ez80(&mut self, bus: &mut Bus, _y: u8, z: u8, p: u8, q: u8) -> u32 {
         // delta = q ? -1 : 1
         let delta: i32 = if q != 0 { -1 } else { 1 };
@@ -1576,7 +1611,7 @@ impl Cpu {
                     // CEmu: cpu.cycles += internalCycles (1 per iteration)
                     bus.add_cycles(1);
 
-                    if !(is_repeat && new_b != 0) {
+                    if !(is_repeat && new_b != 0) || self.block_yield(bus) {
                         break;
                     }
                 }
@@ -1610,7 +1645,7 @@ impl Cpu {
                     // CEmu: cpu.cycles += internalCycles (1 per iteration)
                     bus.add_cycles(1);
 
-                    if !(is_repeat && new_b != 0) {
+                    if !(is_repeat && new_b != 0) || self.block_yield(bus) {
                         break;
                     }
                 }
@@ -1653,7 +1688,7 @@ impl Cpu {
 
             bus.add_cycles(1);
 
-            if bc_val == 0 {
+            if bc_val == 0 || self.block_yield(bus) {
                 break;
             }
         }
@@ -1711,7 +1746,7 @@ impl Cpu {
             bus.add_cycles(1);
 
             // Repeat variants loop while Z flag is not set
-            if !is_repeat || self.flag_z() {
+            if !is_repeat || self.flag_z() || self.block_yield(bus) {
                 break;
             }
 
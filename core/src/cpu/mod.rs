@@ -8,6 +8,7 @@
 //! - `flags`: Flag bit constants for the F register
 //! - `helpers`: Helper functions (register access, fetch, push/pop, ALU, flags)
 //! - `execute`: Instruction execution functions (execute_x0, execute_cb, execute_ed, etc.)
+//! - `verify`: Single-instruction checks against reference register/flag vectors
 //!
 //! # Register Set
 //!
@@ -24,6 +25,7 @@ use crate::bus::Bus;
 mod execute;
 pub mod flags;
 mod helpers;
+pub mod verify;
 
 #[cfg(test)]
 mod tests;
@@ -113,6 +115,19 @@ pub struct Cpu {
     /// Any key wake signal - wakes CPU from HALT when any key is pressed
     /// Like CEmu's CPU_SIGNAL_ANY_KEY, this allows keys to wake the CPU
     pub any_key_wake: bool,
+    /// Bus cycle count at which a repeating block instruction (LDIR, CPIR,
+    /// OTIR, ...) stops iterating and rewinds PC to run again, so events and
+    /// interrupts are handled in between (CEmu's cpu.next). The emulator sets
+    /// it to the next scheduler event before each step; u64::MAX runs the
+    /// whole block in one step.
+    pub block_cycle_limit: u64,
+    /// A block instruction yielded at `block_cycle_limit` and the next step
+    /// continues it without fetching it again (CEmu's cpu.inBlock). PC
+    /// points at the instruction meanwhile, so an interrupt returns to it.
+    in_block: bool,
+    /// Second opcode byte of the block instruction being executed, for
+    /// continuing it after a yield
+    block_opcode: u8,
     /// EI delay counter - EI enables interrupts after the NEXT instruction
     /// When EI is executed, this is set to 2. It decrements each step, and when
     /// it reaches 0, IFF1/IFF2 are set to true.
@@ -143,6 +158,14 @@ pub struct Cpu {
     /// the next instruction's first byte as part of the current instruction.
     /// This is essential for cycle parity with CEmu.
     pub prefetch: u8,
+
+    // Lifetime execution counters (not cleared by reset)
+    /// Instructions executed (excludes HALT idle steps and interrupt dispatch)
+    pub instructions_retired: u64,
+    /// Maskable interrupts accepted
+    pub irqs_serviced: u64,
+    /// Non-maskable interrupts accepted
+    pub nmis_serviced: u64,
 }
 
 impl Cpu {
@@ -187,6 +210,9 @@ impl Cpu {
             nmi_pending: false,
             on_key_wake: false,
             any_key_wake: false,
+            block_cycle_limit: u64::MAX,
+            in_block: false,
+            block_opcode: 0,
             ei_delay: 0,
 
             // Per-instruction modes (reset to ADL at start of each instruction)
@@ -197,9 +223,21 @@ impl Cpu {
             prefix: 0,
             // Prefetch starts at 0 - will be initialized by reset() with bus access
             prefetch: 0,
+
+            instructions_retired: 0,
+            irqs_serviced: 0,
+            nmis_serviced: 0,
         }
     }
 
+    /// True while a block instruction that yielded at an event is waiting to
+    /// continue. The next step is part of the same instruction, so it isn't
+    /// a new instruction boundary for breakpoints or traces.
+    #[inline]
+    pub fn in_block(&self) -> bool {
+        self.in_block
+    }
+
     /// Get the active stack pointer based on L mode (CEmu: stack[cpu.L])
     #[inline(always)]
     pub fn sp(&self) -> u32 {
@@ -260,6 +298,9 @@ impl Cpu {
         self.nmi_pending = false;
         self.on_key_wake = false;
         self.any_key_wake = false;
+        self.block_cycle_limit = u64::MAX;
+        self.in_block = false;
+        self.block_opcode = 0;
         self.ei_delay = 0;
         self.l = false;
         self.il = false;
@@ -274,6 +315,8 @@ impl Cpu {
     /// Must be called after reset() when the bus is available.
     /// CEmu prefetches the first byte at PC during cpu_prefetch(0, cpu.ADL) at init.
     pub fn init_prefetch(&mut self, bus: &mut Bus) {
+        // A new PC abandons any block instruction in progress
+        self.in_block = false;
         // Prefetch the first byte at PC=0 (with MBASE applied if in Z80 mode)
         let effective_pc = self.mask_addr_instr(self.pc);
         // Read the byte and store it in prefetch buffer
@@ -317,6 +360,7 @@ impl Cpu {
         // Check for NMI first (highest priority)
         if self.nmi_pending {
             self.nmi_pending = false;
+            self.nmis_serviced += 1;
             self.handle_nmi(bus);
             return cycle_delta(start_cycles, bus.total_cycles());
         }
@@ -324,6 +368,7 @@ impl Cpu {
         // Check for maskable interrupt
         if self.irq_pending && self.iff1 {
             self.irq_pending = false;
+            self.irqs_serviced += 1;
             self.handle_irq(bus);
             return cycle_delta(start_cycles, bus.total_cycles());
         }
@@ -368,6 +413,18 @@ impl Cpu {
         // Set CPU PC on bus for memory protection checks
         bus.cpu_pc = self.pc;
 
+        // Continue a block instruction that yielded at an event. It is the
+        // same instruction: no fetch, no retire, and L/IL/suffix are kept.
+        // The prefetch buffer still holds the byte after it.
+        if self.in_block {
+            self.in_block = false;
+            self.pc = self.wrap_pc(self.pc.wrapping_add(2 + self.suffix as u32));
+            self.execute_block(bus, self.block_opcode);
+            return cycle_delta(start_cycles, bus.total_cycles());
+        }
+
+        self.instructions_retired += 1;
+
         // eZ80 per-instruction mode handling:
         // L and IL are always reset to ADL at the start of each instruction.
         // Suffix opcodes (.SIS/.LIS/.SIL/.LIL) override L/IL within the
@@ -470,6 +527,8 @@ impl Cpu {
         // Disable interrupts and clear state
         self.iff1 = false;
         self.halted = false;
+        // The handler returns to the start of a yielded block instruction
+        self.in_block = false;
 
         // CEmu: cpu_interrupt(0x38) → cpu_rst(0x38, cpu.ADL, cpu.ADL|cpu.MADL, cpu.MADL)
         // rst_impl handles both normal and mixed-mode (MADL) interrupt entry
@@ -497,6 +556,7 @@ impl Cpu {
         self.iff2 = self.iff1;
         self.iff1 = false;
         self.halted = false;
+        self.in_block = false;
 
         // Jump to NMI handler at 0x0066
         self.push_addr(bus, self.pc);
@@ -569,9 +629,11 @@ impl Cpu {
         if self.il { mode_flags |= 1 << 1; }
         if self.suffix { mode_flags |= 1 << 2; }
         if self.madl { mode_flags |= 1 << 3; }
+        if self.in_block { mode_flags |= 1 << 4; }
         buf[pos] = mode_flags; pos += 1;
         buf[pos] = self.prefix; pos += 1;
         buf[pos] = self.prefetch; pos += 1;
+        buf[pos] = self.block_opcode; pos += 1;
 
         // Padding to SNAPSHOT_SIZE
         let _ = pos; // Unused beyond here
@@ -638,8 +700,10 @@ impl Cpu {
         self.il = mode_flags & (1 << 1) != 0;
         self.suffix = mode_flags & (1 << 2) != 0;
         self.madl = mode_flags & (1 << 3) != 0;
+        self.in_block = mode_flags & (1 << 4) != 0;
         self.prefix = buf[pos]; pos += 1;
-        self.prefetch = buf[pos];
+        self.prefetch = buf[pos]; pos += 1;
+        self.block_opcode = buf[pos];
 
         Ok(())
     }
@@ -1116,7 +1116,7 @@ fn test_ldir() {
     bus.poke_byte(0, 0xED);
     bus.poke_byte(1, 0xB0);
 
-    // LDIR executes all iterations in a single step (matches CEmu behavior)
+    // With no event due, LDIR executes all iterations in a single step
     let cycles = cpu.step(&mut bus);
 
     // All 3 bytes copied in one step
@@ -1136,6 +1136,86 @@ fn test_ldir() {
     assert_eq!(bus.peek_byte(0xD00202), 0x33);
 }
 
+#[test]
+fn test_ldir_yields_at_block_cycle_limit() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    cpu.adl = true;
+    cpu.pc = 0xD00000;
+    cpu.hl = 0xD00100;
+    cpu.de = 0xD00200;
+    cpu.bc = 0x000003;
+    bus.poke_byte(0xD00100, 0x11);
+    bus.poke_byte(0xD00101, 0x22);
+    bus.poke_byte(0xD00102, 0x33);
+    // LDIR (ED B0), then .SIS LDIR (40 ED B0)
+    for (i, b) in [0xED, 0xB0, 0x40, 0xED, 0xB0].into_iter().enumerate() {
+        bus.poke_byte(0xD00000 + i as u32, b);
+    }
+    cpu.init_prefetch(&mut bus);
+
+    // An event is due after the first iteration: stop there and rewind
+    cpu.block_cycle_limit = bus.total_cycles() + 1;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.bc, 0x000002);
+    assert_eq!(cpu.pc, 0xD00000, "PC rewound to the LDIR");
+    assert!(cpu.in_block());
+    assert!(cpu.flag_pv());
+    assert_eq!(bus.peek_byte(0xD00200), 0x11);
+    assert_eq!(bus.peek_byte(0xD00201), 0x00);
+
+    // Running it again picks up where it left off, as the same instruction
+    let retired = cpu.instructions_retired;
+    let r = cpu.r;
+    cpu.block_cycle_limit = u64::MAX;
+    cpu.step(&mut bus);
+    assert!(!cpu.in_block());
+    assert_eq!(cpu.instructions_retired, retired);
+    assert_eq!(cpu.r, r, "no opcode fetch on resume");
+    assert_eq!(cpu.bc, 0x000000);
+    assert_eq!(cpu.pc, 0xD00002);
+    assert_eq!(cpu.hl, 0xD00103);
+    assert_eq!(bus.peek_byte(0xD00202), 0x33);
+
+    // A suffixed block instruction rewinds to its suffix
+    cpu.bc = 0x000002;
+    cpu.block_cycle_limit = 0;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.bc, 0x000001);
+    assert_eq!(cpu.pc, 0xD00002);
+}
+
+#[test]
+fn test_cpir_yields_at_block_cycle_limit() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    cpu.adl = true;
+    cpu.pc = 0xD00000;
+    cpu.hl = 0xD00100;
+    cpu.bc = 0x000004;
+    cpu.a = 0x33;
+    for (i, b) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    // CPIR (ED B1)
+    bus.poke_byte(0xD00000, 0xED);
+    bus.poke_byte(0xD00001, 0xB1);
+    cpu.init_prefetch(&mut bus);
+
+    cpu.block_cycle_limit = 0;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0xD00000);
+    assert_eq!(cpu.hl, 0xD00101);
+    assert!(!cpu.flag_z());
+
+    cpu.block_cycle_limit = u64::MAX;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0xD00002);
+    assert_eq!(cpu.hl, 0xD00103);
+    assert_eq!(cpu.bc, 0x000001);
+    assert!(cpu.flag_z(), "stopped on the match");
+}
+
 #[test]
 fn test_cpi() {
     let mut cpu = Cpu::new();
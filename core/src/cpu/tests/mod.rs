@@ -4,6 +4,8 @@
 //! - instructions.rs: Tests for individual instructions and instruction families
 //! - modes.rs: Tests for ADL mode and Z80 mode specific behavior
 //! - parity.rs: Comprehensive CEmu parity tests for flag and register behavior
+//! - test_roms.rs: Runner for bare-metal exerciser binaries (zexdoc-style)
+//! - flag_props.rs: Property-based ALU flag tests against a reference model
 //!
 //! # References
 //! - eZ80 CPU User Manual (Zilog UM0077)
@@ -14,7 +16,9 @@ use crate::bus::Bus;
 
 mod instructions;
 mod modes;
+mod flag_props;
 mod parity;
+mod test_roms;
 
 // ========== Test Helpers ==========
 
@@ -3,6 +3,7 @@
 //! Tests for:
 //! - ADL mode (24-bit addressing) specific behavior
 //! - Z80 mode (16-bit + MBASE) backward compatibility  
+//! - Mixed-memory mode: suffixed CALL/RET/RST and MADL interrupts
 //! - TI-84 CE memory map verification
 //!
 //! # References
@@ -993,3 +994,249 @@ fn test_z80_mode_ex_sp_ix_uses_mbase() {
     assert_eq!(bus.peek_byte(0xD04000), 0x34, "Stack low byte should be old IX low");
     assert_eq!(bus.peek_byte(0xD04001), 0x12, "Stack high byte should be old IX high");
 }
+
+// ========== Mixed-Memory Mode (suffixes, MADL) ==========
+// A suffixed CALL/RST (or an IRQ with MADL set) also pushes a flag byte,
+// (MADL << 1) | ADL, onto SPL, and a suffixed RET pops it to return to the
+// caller's mode. A CALL puts the 16-bit return address on the callee's
+// stack, an IRQ on the interrupted code's. An NMI pushes no flag byte.
+
+/// Z80 mode at 0x0100 with MBASE 0xD0, SPS 0x8000 and SPL 0xD1F000
+fn setup_mixed(cpu: &mut Cpu) {
+    setup_z80_mode(cpu);
+    cpu.sps = 0x8000;
+    cpu.spl = 0xD1F000;
+}
+
+#[test]
+fn test_mixed_call_lil_from_z80_and_ret_l() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    setup_mixed(&mut cpu);
+
+    // CALL.LIL 0xD02000 (5B CD 00 20 D0)
+    for (i, b) in [0x5B, 0xCD, 0x00, 0x20, 0xD0].into_iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    // RET.L (5B C9) back to Z80 mode
+    bus.poke_byte(0xD02000, 0x5B);
+    bus.poke_byte(0xD02001, 0xC9);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(cpu.adl, "CALL.LIL enters ADL mode");
+    assert_eq!(cpu.pc, 0xD02000);
+    // Return address and flag byte (ADL=0) all on SPL
+    assert_eq!(cpu.spl, 0xD1EFFD);
+    assert_eq!(cpu.sps, 0x8000);
+    assert_eq!(bus.peek_byte(0xD1EFFF), 0x01, "PCH");
+    assert_eq!(bus.peek_byte(0xD1EFFE), 0x05, "PCL");
+    assert_eq!(bus.peek_byte(0xD1EFFD), 0x00, "flag byte");
+
+    cpu.step(&mut bus);
+    assert!(!cpu.adl, "RET.L restores Z80 mode");
+    assert_eq!(cpu.pc, 0x0105);
+    assert_eq!(cpu.spl, 0xD1F000);
+}
+
+#[test]
+fn test_mixed_call_is_from_adl_and_ret_l() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    setup_mixed(&mut cpu);
+    cpu.adl = true;
+    cpu.pc = 0xD00100;
+
+    // CALL.SIS 0x3000 (40 CD 00 30) into Z80 code at MBASE:3000
+    for (i, b) in [0x40, 0xCD, 0x00, 0x30].into_iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    // RET.L (49 C9), fetched through MBASE
+    bus.poke_byte(0xD03000, 0x49);
+    bus.poke_byte(0xD03001, 0xC9);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(!cpu.adl, "CALL.SIS enters Z80 mode");
+    assert_eq!(cpu.pc, 0x3000);
+    // PCU and flag byte (ADL=1) on SPL, PC[15:0] on SPS
+    assert_eq!(cpu.spl, 0xD1EFFE);
+    assert_eq!(bus.peek_byte(0xD1EFFF), 0xD0, "PCU");
+    assert_eq!(bus.peek_byte(0xD1EFFE), 0x01, "flag byte");
+    assert_eq!(cpu.sps, 0x7FFE);
+    assert_eq!(bus.peek_byte(0xD07FFF), 0x01, "PCH");
+    assert_eq!(bus.peek_byte(0xD07FFE), 0x04, "PCL");
+
+    cpu.step(&mut bus);
+    assert!(cpu.adl, "RET.L restores ADL mode");
+    assert_eq!(cpu.pc, 0xD00104);
+    assert_eq!(cpu.spl, 0xD1F000);
+    assert_eq!(cpu.sps, 0x8000);
+}
+
+#[test]
+fn test_mixed_rst_lil_from_z80() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    setup_mixed(&mut cpu);
+
+    // RST.LIL 28h (5B EF)
+    bus.poke_byte(0xD00100, 0x5B);
+    bus.poke_byte(0xD00101, 0xEF);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(cpu.adl);
+    assert_eq!(cpu.pc, 0x000028);
+    assert_eq!(cpu.spl, 0xD1EFFD);
+    assert_eq!(bus.peek_byte(0xD1EFFF), 0x01, "PCH");
+    assert_eq!(bus.peek_byte(0xD1EFFE), 0x02, "PCL");
+    assert_eq!(bus.peek_byte(0xD1EFFD), 0x00, "flag byte");
+}
+
+#[test]
+fn test_madl_interrupt_from_z80_and_reti_l() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    setup_mixed(&mut cpu);
+
+    // STMIX (ED 7D) then an interrupt
+    bus.poke_byte(0xD00100, 0xED);
+    bus.poke_byte(0xD00101, 0x7D);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(cpu.madl);
+    cpu.iff1 = true;
+    cpu.irq_pending = true;
+    cpu.step(&mut bus);
+    assert!(cpu.adl, "the handler runs in ADL mode");
+    assert_eq!(cpu.pc, 0x000038);
+    // CEmu's cpu_rst(0x38, ADL, ...): PC[15:0] goes on the interrupted
+    // code's stack (SPS), the flag byte (MADL=1, ADL=0) on SPL
+    assert_eq!(cpu.sps, 0x7FFE);
+    assert_eq!(bus.peek_byte(0xD07FFF), 0x01, "PCH");
+    assert_eq!(bus.peek_byte(0xD07FFE), 0x02, "PCL");
+    assert_eq!(cpu.spl, 0xD1EFFF);
+    assert_eq!(bus.peek_byte(0xD1EFFF), 0x02, "flag byte");
+
+    // RETI.L (5B ED 4D) pops the flag byte and returns to Z80 mode
+    // Run the handler from RAM
+    cpu.pc = 0xD02000;
+    for (i, b) in [0x5B, 0xED, 0x4D].into_iter().enumerate() {
+        bus.poke_byte(0xD02000 + i as u32, b);
+    }
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(!cpu.adl, "RETI.L restores Z80 mode");
+    assert!(cpu.madl);
+}
+
+#[test]
+fn test_madl_interrupt_from_adl_push_layout() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    cpu.adl = true;
+    cpu.madl = true;
+    cpu.pc = 0xD00100;
+    cpu.spl = 0xD1F000;
+    cpu.sps = 0x8000;
+    cpu.iff1 = true;
+    cpu.irq_pending = true;
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(cpu.adl);
+    assert_eq!(cpu.pc, 0x000038);
+    // Everything on SPL: PCU and the flag byte (MADL=1, ADL=1), then PC[15:0]
+    assert_eq!(cpu.spl, 0xD1EFFC);
+    assert_eq!(bus.peek_byte(0xD1EFFF), 0xD0, "PCU");
+    assert_eq!(bus.peek_byte(0xD1EFFE), 0x03, "flag byte");
+    assert_eq!(bus.peek_byte(0xD1EFFD), 0x01, "PCH");
+    assert_eq!(bus.peek_byte(0xD1EFFC), 0x00, "PCL");
+    assert_eq!(cpu.sps, 0x8000);
+}
+
+#[test]
+fn test_madl_nmi_from_adl_and_retn() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    cpu.adl = true;
+    cpu.madl = true;
+    cpu.pc = 0xD00100;
+    cpu.spl = 0xD1F000;
+
+    cpu.nmi_pending = true;
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x000066);
+    // A plain 24-bit push on SPL, no flag byte
+    assert_eq!(cpu.spl, 0xD1EFFD);
+    assert_eq!(bus.peek_byte(0xD1EFFF), 0xD0);
+    assert_eq!(bus.peek_byte(0xD1EFFE), 0x01);
+    assert_eq!(bus.peek_byte(0xD1EFFD), 0x00);
+
+    // RETN (ED 45) back to the interrupted code
+    cpu.pc = 0xD02000;
+    bus.poke_byte(0xD02000, 0xED);
+    bus.poke_byte(0xD02001, 0x45);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert!(cpu.adl);
+    assert_eq!(cpu.pc, 0xD00100);
+    assert_eq!(cpu.spl, 0xD1F000);
+}
+
+#[test]
+fn test_suffix_data_addressing_and_mbase() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    setup_mixed(&mut cpu);
+    cpu.a = 0x42;
+
+    // Z80 mode: .LIL LD (0xD12345),A bypasses MBASE (5B 32 45 23 D1)
+    for (i, b) in [0x5B, 0x32, 0x45, 0x23, 0xD1].into_iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(bus.peek_byte(0xD12345), 0x42);
+    assert!(!cpu.adl, "a suffix does not change the mode");
+    assert_eq!(cpu.pc, 0x0105);
+
+    // ADL mode: .SIS LD A,(0x2345) reads MBASE:2345 (40 3A 45 23)
+    cpu.adl = true;
+    cpu.pc = 0xD00200;
+    for (i, b) in [0x40, 0x3A, 0x45, 0x23].into_iter().enumerate() {
+        bus.poke_byte(0xD00200 + i as u32, b);
+    }
+    bus.poke_byte(0xD02345, 0x99);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 0x99);
+    assert!(cpu.adl);
+    assert_eq!(cpu.pc, 0xD00204);
+
+    // LD MB,A (ED 6D) is ignored outside ADL mode
+    cpu.adl = false;
+    cpu.pc = 0x0300;
+    bus.poke_byte(0xD00300, 0xED);
+    bus.poke_byte(0xD00301, 0x6D);
+    cpu.init_prefetch(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.mbase, 0xD0);
+}
+
+#[test]
+fn test_suffix_with_index_prefix() {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    setup_mixed(&mut cpu);
+
+    // Z80 mode: .LIL LD IX,(0xD12345) loads 24 bits (5B DD 2A 45 23 D1)
+    for (i, b) in [0x5B, 0xDD, 0x2A, 0x45, 0x23, 0xD1].into_iter().enumerate() {
+        bus.poke_byte(0xD00100 + i as u32, b);
+    }
+    bus.poke_byte(0xD12345, 0x11);
+    bus.poke_byte(0xD12346, 0x22);
+    bus.poke_byte(0xD12347, 0x33);
+    cpu.init_prefetch(&mut bus);
+    step_full(&mut cpu, &mut bus);
+    assert_eq!(cpu.ix, 0x332211);
+    assert_eq!(cpu.pc, 0x0106);
+}
@@ -0,0 +1,150 @@
+//! Bare-metal CPU test-ROM runner
+//!
+//! Runs CP/M-style exerciser binaries (zexdoc/zexall and similar eZ80 ports)
+//! in Z80 mode with MBASE pointing at RAM, so the program's 64KB address
+//! space maps to 0xD00000. A minimal vector table is installed:
+//! - 0x0000: HALT (warm boot / program exit)
+//! - 0x0005: RET, with BDOS console calls intercepted by the harness
+//!   (C=2 prints E, C=9 prints the '$'-terminated string at DE)
+//!
+//! Binaries are loaded at 0x0100. External binaries are taken from the
+//! directory in `EZ80_TEST_ROMS` (every `*.com` / `*.bin` file); the test is
+//! skipped when the variable is unset.
+
+use super::*;
+
+/// Load address of test programs (CP/M TPA)
+const LOAD_ADDR: u16 = 0x0100;
+/// BDOS entry point
+const BDOS_ADDR: u16 = 0x0005;
+/// Env var naming a directory of test binaries
+const TEST_ROMS_ENV: &str = "EZ80_TEST_ROMS";
+
+/// Outcome of running a test binary
+#[derive(Debug)]
+struct TestRomResult {
+    /// Console output written through BDOS
+    output: String,
+    /// True if the program exited through 0x0000 before the cycle limit
+    exited: bool,
+    cycles: u64,
+}
+
+impl TestRomResult {
+    /// zexdoc-style verdict: the program exited and reported no errors
+    fn passed(&self) -> bool {
+        self.exited && !self.output.contains("ERROR")
+    }
+}
+
+fn ram_addr(addr: u16) -> u32 {
+    0xD00000 | addr as u32
+}
+
+/// Run a test binary until it exits or `max_cycles` elapse
+fn run_test_rom(program: &[u8], max_cycles: u64) -> TestRomResult {
+    assert!(program.len() <= 0x10000 - LOAD_ADDR as usize - 0x100, "program too large");
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+
+    bus.poke_byte(ram_addr(0x0000), 0x76); // HALT
+    bus.poke_byte(ram_addr(BDOS_ADDR), 0xC9); // RET
+    for (i, &b) in program.iter().enumerate() {
+        bus.poke_byte(ram_addr(LOAD_ADDR + i as u16), b);
+    }
+
+    setup_z80_mode_with_prefetch(&mut cpu, &mut bus);
+    cpu.iff1 = false;
+    cpu.iff2 = false;
+    // Return address for programs that exit with RET instead of JP 0
+    cpu.set_sp_both(0xFFFD);
+    bus.poke_byte(ram_addr(0xFFFD), 0x00);
+    bus.poke_byte(ram_addr(0xFFFE), 0x00);
+
+    let mut output = String::new();
+    let mut cycles = 0u64;
+    while cycles < max_cycles && !cpu.halted {
+        if cpu.prefix == 0 && cpu.pc == BDOS_ADDR as u32 {
+            match cpu.c() {
+                2 => output.push(cpu.e() as char),
+                9 => {
+                    let mut addr = (cpu.de & 0xFFFF) as u16;
+                    loop {
+                        let ch = bus.peek_byte(ram_addr(addr));
+                        if ch == b'$' {
+                            break;
+                        }
+                        output.push(ch as char);
+                        addr = addr.wrapping_add(1);
+                    }
+                }
+                _ => {}
+            }
+        }
+        cycles += cpu.step(&mut bus) as u64;
+    }
+
+    TestRomResult { output, exited: cpu.halted, cycles }
+}
+
+/// Prints "OK" with C=9, then "!" with C=2, then exits via JP 0
+const SELF_TEST_PASS: [u8; 21] = [
+    0x0E, 0x09, // LD C,9
+    0x11, 0x12, 0x01, // LD DE,0x0112
+    0xCD, 0x05, 0x00, // CALL 0x0005
+    0x0E, 0x02, // LD C,2
+    0x1E, b'!', // LD E,'!'
+    0xCD, 0x05, 0x00, // CALL 0x0005
+    0xC3, 0x00, 0x00, // JP 0
+    b'O', b'K', b'$',
+];
+
+#[test]
+fn test_rom_runner_console_output() {
+    let result = run_test_rom(&SELF_TEST_PASS, 10_000);
+    assert!(result.exited, "program did not exit: {:?}", result);
+    assert_eq!(result.output, "OK!");
+    assert!(result.passed());
+    assert!(result.cycles > 0);
+}
+
+#[test]
+fn test_rom_runner_detects_failure() {
+    // LD C,9; LD DE,0x0109; CALL 5; RET; "ERROR$"
+    let program = [
+        0x0E, 0x09, 0x11, 0x09, 0x01, 0xCD, 0x05, 0x00, 0xC9,
+        b'E', b'R', b'R', b'O', b'R', b'$',
+    ];
+    let result = run_test_rom(&program, 10_000);
+    assert!(result.exited);
+    assert!(!result.passed());
+
+    // Program that never exits
+    let spin = [0x18, 0xFE]; // JR $
+    let result = run_test_rom(&spin, 10_000);
+    assert!(!result.exited);
+    assert!(!result.passed());
+}
+
+#[test]
+fn test_rom_suite_from_env() {
+    let Some(dir) = std::env::var_os(TEST_ROMS_ENV) else { return };
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("{}={:?}: {}", TEST_ROMS_ENV, dir, e))
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("com" | "bin")))
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let program = std::fs::read(path).unwrap();
+        // zexall takes tens of billions of cycles; cap generously
+        let result = run_test_rom(&program, 200_000_000_000);
+        println!("{}:\n{}", path.display(), result.output);
+        if !result.passed() {
+            failures.push(path.display().to_string());
+        }
+    }
+    assert!(failures.is_empty(), "failing test ROMs: {:?}", failures);
+}
@@ -0,0 +1,468 @@
+//! Single-instruction verification against reference vectors
+//!
+//! A vector gives a starting CPU and memory state, the bytes of one
+//! instruction (suffixes such as .SIS/.LIL and DD/FD prefixes included),
+//! and the state expected after it executes. `run_vector` executes it on a
+//! fresh CPU and bus and reports every register, flag or memory byte that
+//! differs, so vectors exported from CEmu or converted from zexall-style
+//! tables can be checked in bulk.
+//!
+//! Vectors are written as text, one block per vector:
+//!
+//! ```text
+//! # ADD A,B with signed overflow
+//! test add_a_b_overflow
+//! code 80
+//! in   a=7F bc=000100 f=00 adl=1 pc=D00000
+//! out  a=80 f=94 pc=D00001
+//! end
+//! ```
+//!
+//! - `in` sets registers; anything not given keeps its reset value
+//!   (`Cpu::reset`, so Z80 mode with PC 0 unless `adl`/`pc` are set).
+//! - `out` lists only the registers to check.
+//! - `mem ADDR: BYTES` writes memory before the instruction runs.
+//! - `expect ADDR: BYTES` checks memory afterwards.
+//! - `flags MASK` compares only the F bits in MASK (default FF), e.g. D7 to
+//!   ignore the undocumented F3/F5.
+//! - `cycles N` checks the cycles the instruction took, bus wait states
+//!   included: code fetched from flash pays the flash controller's wait
+//!   states (10 cycles per byte at reset) where RAM fetches take 4.
+//!
+//! The code is placed at the effective PC (MBASE applied in Z80 mode).
+//! Register names: a f bc de hl ix iy sps spl pc mbase i r adl madl iff1
+//! iff2, and a' f' bc' de' hl' for the shadow set. Values are hex; the mode
+//! flags take 0 or 1.
+
+use super::Cpu;
+use crate::bus::Bus;
+
+/// A register a vector can set or check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    A,
+    F,
+    BC,
+    DE,
+    HL,
+    IX,
+    IY,
+    SPS,
+    SPL,
+    PC,
+    MBASE,
+    I,
+    R,
+    ADL,
+    MADL,
+    IFF1,
+    IFF2,
+    APrime,
+    FPrime,
+    BCPrime,
+    DEPrime,
+    HLPrime,
+}
+
+impl Reg {
+    /// Name used in vector text
+    pub fn name(self) -> &'static str {
+        match self {
+            Reg::A => "a",
+            Reg::F => "f",
+            Reg::BC => "bc",
+            Reg::DE => "de",
+            Reg::HL => "hl",
+            Reg::IX => "ix",
+            Reg::IY => "iy",
+            Reg::SPS => "sps",
+            Reg::SPL => "spl",
+            Reg::PC => "pc",
+            Reg::MBASE => "mbase",
+            Reg::I => "i",
+            Reg::R => "r",
+            Reg::ADL => "adl",
+            Reg::MADL => "madl",
+            Reg::IFF1 => "iff1",
+            Reg::IFF2 => "iff2",
+            Reg::APrime => "a'",
+            Reg::FPrime => "f'",
+            Reg::BCPrime => "bc'",
+            Reg::DEPrime => "de'",
+            Reg::HLPrime => "hl'",
+        }
+    }
+
+    const ALL: [Reg; 22] = [
+        Reg::A,
+        Reg::F,
+        Reg::BC,
+        Reg::DE,
+        Reg::HL,
+        Reg::IX,
+        Reg::IY,
+        Reg::SPS,
+        Reg::SPL,
+        Reg::PC,
+        Reg::MBASE,
+        Reg::I,
+        Reg::R,
+        Reg::ADL,
+        Reg::MADL,
+        Reg::IFF1,
+        Reg::IFF2,
+        Reg::APrime,
+        Reg::FPrime,
+        Reg::BCPrime,
+        Reg::DEPrime,
+        Reg::HLPrime,
+    ];
+
+    fn from_name(name: &str) -> Option<Reg> {
+        Reg::ALL.into_iter().find(|r| r.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Largest value the register holds
+    fn max(self) -> u32 {
+        match self {
+            Reg::A | Reg::F | Reg::MBASE | Reg::R | Reg::APrime | Reg::FPrime => 0xFF,
+            Reg::I => 0xFFFF,
+            Reg::ADL | Reg::MADL | Reg::IFF1 | Reg::IFF2 => 1,
+            _ => 0xFFFFFF,
+        }
+    }
+
+    fn get(self, cpu: &Cpu) -> u32 {
+        match self {
+            Reg::A => cpu.a as u32,
+            Reg::F => cpu.f as u32,
+            Reg::BC => cpu.bc,
+            Reg::DE => cpu.de,
+            Reg::HL => cpu.hl,
+            Reg::IX => cpu.ix,
+            Reg::IY => cpu.iy,
+            Reg::SPS => cpu.sps,
+            Reg::SPL => cpu.spl,
+            Reg::PC => cpu.pc,
+            Reg::MBASE => cpu.mbase as u32,
+            Reg::I => cpu.i as u32,
+            Reg::R => cpu.r as u32,
+            Reg::ADL => cpu.adl as u32,
+            Reg::MADL => cpu.madl as u32,
+            Reg::IFF1 => cpu.iff1 as u32,
+            Reg::IFF2 => cpu.iff2 as u32,
+            Reg::APrime => cpu.a_prime as u32,
+            Reg::FPrime => cpu.f_prime as u32,
+            Reg::BCPrime => cpu.bc_prime,
+            Reg::DEPrime => cpu.de_prime,
+            Reg::HLPrime => cpu.hl_prime,
+        }
+    }
+
+    fn set(self, cpu: &mut Cpu, value: u32) {
+        match self {
+            Reg::A => cpu.a = value as u8,
+            Reg::F => cpu.f = value as u8,
+            Reg::BC => cpu.bc = value,
+            Reg::DE => cpu.de = value,
+            Reg::HL => cpu.hl = value,
+            Reg::IX => cpu.ix = value,
+            Reg::IY => cpu.iy = value,
+            Reg::SPS => cpu.sps = value,
+            Reg::SPL => cpu.spl = value,
+            Reg::PC => cpu.pc = value,
+            Reg::MBASE => cpu.mbase = value as u8,
+            Reg::I => cpu.i = value as u16,
+            Reg::R => cpu.r = value as u8,
+            Reg::ADL => cpu.adl = value != 0,
+            Reg::MADL => cpu.madl = value != 0,
+            Reg::IFF1 => cpu.iff1 = value != 0,
+            Reg::IFF2 => cpu.iff2 = value != 0,
+            Reg::APrime => cpu.a_prime = value as u8,
+            Reg::FPrime => cpu.f_prime = value as u8,
+            Reg::BCPrime => cpu.bc_prime = value,
+            Reg::DEPrime => cpu.de_prime = value,
+            Reg::HLPrime => cpu.hl_prime = value,
+        }
+    }
+}
+
+/// One instruction with its starting and expected state
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Vector {
+    /// Name from the `test` line
+    pub name: String,
+    /// Instruction bytes, placed at the starting PC
+    pub code: Vec<u8>,
+    /// Registers set before running
+    pub input: Vec<(Reg, u32)>,
+    /// Memory written before running
+    pub memory: Vec<(u32, Vec<u8>)>,
+    /// Registers checked afterwards
+    pub output: Vec<(Reg, u32)>,
+    /// Memory checked afterwards
+    pub expect_memory: Vec<(u32, Vec<u8>)>,
+    /// F bits compared (for `f` and `f'`)
+    pub flag_mask: u8,
+    /// Cycles the instruction should take, if checked
+    pub cycles: Option<u32>,
+}
+
+/// A difference between the expected and actual state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mismatch {
+    /// Register value (flags already masked)
+    Reg { reg: Reg, expected: u32, actual: u32 },
+    /// Memory byte
+    Mem { addr: u32, expected: u8, actual: u8 },
+    /// Cycles taken
+    Cycles { expected: u32, actual: u32 },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Mismatch::Reg { reg: reg @ (Reg::F | Reg::FPrime), expected, actual } => {
+                write!(f, "{}: expected {:08b}, got {:08b} (SZ5H3PNC)", reg.name(), expected, actual)
+            }
+            Mismatch::Reg { reg, expected, actual } => {
+                write!(f, "{}: expected {:X}, got {:X}", reg.name(), expected, actual)
+            }
+            Mismatch::Mem { addr, expected, actual } => {
+                write!(f, "[{:06X}]: expected {:02X}, got {:02X}", addr, expected, actual)
+            }
+            Mismatch::Cycles { expected, actual } => {
+                write!(f, "cycles: expected {}, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+/// Why vector text could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// What was wrong with it
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for VectorParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Parse vector text (see the module docs for the format)
+pub fn parse_vectors(text: &str) -> Result<Vec<Vector>, VectorParseError> {
+    let mut vectors = Vec::new();
+    let mut current: Option<Vector> = None;
+    for (index, line) in text.lines().enumerate() {
+        let err = |reason| VectorParseError { line: index + 1, reason };
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        if keyword == "test" {
+            if current.is_some() {
+                return Err(err("'test' before 'end' of the previous vector"));
+            }
+            if rest.is_empty() {
+                return Err(err("missing test name"));
+            }
+            current = Some(Vector { name: rest.to_string(), flag_mask: 0xFF, ..Default::default() });
+            continue;
+        }
+        let vector = current.as_mut().ok_or(err("expected 'test'"))?;
+        match keyword {
+            "code" => vector.code = parse_hex_bytes(rest).filter(|b| !b.is_empty()).ok_or(err("invalid code bytes"))?,
+            "in" => vector.input.extend(parse_regs(rest).ok_or(err("invalid register list"))?),
+            "out" => vector.output.extend(parse_regs(rest).ok_or(err("invalid register list"))?),
+            "mem" => vector.memory.push(parse_mem(rest).ok_or(err("invalid memory line"))?),
+            "expect" => vector.expect_memory.push(parse_mem(rest).ok_or(err("invalid memory line"))?),
+            "flags" => vector.flag_mask = u8::from_str_radix(rest, 16).map_err(|_| err("invalid flag mask"))?,
+            "cycles" => vector.cycles = Some(rest.parse().map_err(|_| err("invalid cycle count"))?),
+            "end" => {
+                let vector = current.take().unwrap();
+                if vector.code.is_empty() {
+                    return Err(err("vector has no code"));
+                }
+                vectors.push(vector);
+            }
+            _ => return Err(err("unknown keyword")),
+        }
+    }
+    if current.is_some() {
+        return Err(VectorParseError { line: text.lines().count(), reason: "missing 'end'" });
+    }
+    Ok(vectors)
+}
+
+/// `name=value` pairs
+fn parse_regs(text: &str) -> Option<Vec<(Reg, u32)>> {
+    text.split_whitespace()
+        .map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let reg = Reg::from_name(name)?;
+            let value = u32::from_str_radix(value, 16).ok().filter(|&v| v <= reg.max())?;
+            Some((reg, value))
+        })
+        .collect()
+}
+
+/// `ADDR: BYTES`
+fn parse_mem(text: &str) -> Option<(u32, Vec<u8>)> {
+    let (addr, bytes) = text.split_once(':')?;
+    let addr = u32::from_str_radix(addr.trim(), 16).ok().filter(|&a| a <= 0xFFFFFF)?;
+    let bytes = parse_hex_bytes(bytes).filter(|b| !b.is_empty())?;
+    Some((addr, bytes))
+}
+
+/// Hex bytes, optionally separated by whitespace ("3E 01" or "3E01")
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Execute `vector`'s instruction on a fresh CPU and bus and list what
+/// differs from its expected state (empty if it passes)
+pub fn run_vector(vector: &Vector) -> Vec<Mismatch> {
+    let mut cpu = Cpu::new();
+    cpu.reset();
+    let mut bus = Bus::new();
+    for &(reg, value) in &vector.input {
+        reg.set(&mut cpu, value);
+    }
+    for (addr, bytes) in &vector.memory {
+        for (a, &b) in (*addr..).zip(bytes) {
+            bus.poke_byte(a, b);
+        }
+    }
+    let code_addr = cpu.mask_addr_instr(cpu.pc);
+    for (i, &b) in vector.code.iter().enumerate() {
+        bus.poke_byte(cpu.mask_addr_instr(code_addr.wrapping_add(i as u32)), b);
+    }
+    cpu.init_prefetch(&mut bus);
+
+    let mut cycles = cpu.step(&mut bus);
+    // DD/FD prefixes can count as a step of their own
+    while cpu.prefix != 0 {
+        cycles += cpu.step(&mut bus);
+    }
+
+    let mut mismatches = Vec::new();
+    for &(reg, expected) in &vector.output {
+        let mut actual = reg.get(&cpu);
+        let mut expected = expected;
+        if matches!(reg, Reg::F | Reg::FPrime) {
+            actual &= vector.flag_mask as u32;
+            expected &= vector.flag_mask as u32;
+        }
+        if actual != expected {
+            mismatches.push(Mismatch::Reg { reg, expected, actual });
+        }
+    }
+    if let Some(expected) = vector.cycles.filter(|&c| c != cycles) {
+        mismatches.push(Mismatch::Cycles { expected, actual: cycles });
+    }
+    for (addr, bytes) in &vector.expect_memory {
+        for (a, &expected) in (*addr..).zip(bytes) {
+            let actual = bus.peek_byte(a);
+            if actual != expected {
+                mismatches.push(Mismatch::Mem { addr: a, expected, actual });
+            }
+        }
+    }
+    mismatches
+}
+
+/// Run every vector, returning the names and mismatches of those that fail
+pub fn run_vectors(vectors: &[Vector]) -> Vec<(String, Vec<Mismatch>)> {
+    vectors
+        .iter()
+        .filter_map(|v| {
+            let mismatches = run_vector(v);
+            (!mismatches.is_empty()).then(|| (v.name.clone(), mismatches))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Env var naming a directory of vector files (`*.txt`) to run as well
+    const VECTORS_ENV: &str = "EZ80_VECTORS";
+
+    fn report(failures: &[(String, Vec<Mismatch>)]) -> String {
+        failures
+            .iter()
+            .map(|(name, m)| format!("{}: {}", name, m.iter().map(|m| m.to_string()).collect::<Vec<_>>().join("; ")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_bundled_vectors() {
+        let vectors = parse_vectors(include_str!("tests/vectors.txt")).unwrap();
+        assert!(vectors.len() >= 10);
+        let failures = run_vectors(&vectors);
+        assert!(failures.is_empty(), "\n{}", report(&failures));
+    }
+
+    #[test]
+    fn test_external_vectors() {
+        let Some(dir) = std::env::var_os(VECTORS_ENV) else {
+            return;
+        };
+        let mut failures = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "txt") {
+                let text = std::fs::read_to_string(&path).unwrap();
+                let vectors = parse_vectors(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+                failures.extend(run_vectors(&vectors));
+            }
+        }
+        assert!(failures.is_empty(), "\n{}", report(&failures));
+    }
+
+    #[test]
+    fn test_mismatches_are_reported() {
+        let text = "test wrong\ncode 3C\nin a=01 f=00 adl=1 pc=D00000\nmem D10000: 55\nout a=03 f=00\nexpect D10000: 56\nend\n";
+        let vectors = parse_vectors(text).unwrap();
+        let mismatches = run_vector(&vectors[0]);
+        assert_eq!(mismatches, [
+            Mismatch::Reg { reg: Reg::A, expected: 3, actual: 2 },
+            Mismatch::Mem { addr: 0xD10000, expected: 0x56, actual: 0x55 },
+        ]);
+        assert_eq!(mismatches[1].to_string(), "[D10000]: expected 56, got 55");
+
+        let vectors = parse_vectors("test slow\ncode 00\nin adl=1 pc=D00000\ncycles 10\nend\n").unwrap();
+        assert_eq!(run_vector(&vectors[0]), [Mismatch::Cycles { expected: 10, actual: 4 }]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_vectors("code 00").unwrap_err().line, 1);
+        assert_eq!(parse_vectors("test x\ncode 00\n").unwrap_err().reason, "missing 'end'");
+        assert_eq!(parse_vectors("test x\nend").unwrap_err().reason, "vector has no code");
+        assert_eq!(parse_vectors("test x\ncode 00\nin a=100\nend").unwrap_err().line, 3);
+        assert_eq!(parse_vectors("test x\ncode 00\nin q=1\nend").unwrap_err().line, 3);
+        assert_eq!(parse_vectors("test x\ncode 00\nflags D7\nbogus\nend").unwrap_err().line, 4);
+        assert_eq!(parse_vectors("test x\ncode 00\ncycles x\nend").unwrap_err().reason, "invalid cycle count");
+
+        let vectors = parse_vectors("# c\ntest x y\ncode 00\nin hl'=123456 adl=1\nflags d7\nend\n").unwrap();
+        assert_eq!(vectors[0].name, "x y");
+        assert_eq!(vectors[0].input, [(Reg::HLPrime, 0x123456), (Reg::ADL, 1)]);
+        assert_eq!(vectors[0].flag_mask, 0xD7);
+    }
+}
@@ -0,0 +1,158 @@
+//! Per-frame CPU utilization
+//!
+//! Splits each LCD frame into cycles the CPU spent halted and cycles it spent
+//! executing, and records which interrupt sources woke it from HALT. Frontends
+//! can show an activity meter from this; program authors can see how much of
+//! each frame their code leaves idle.
+//!
+//! Time is sampled after every instruction, so the cycles of an instruction
+//! that wakes the CPU count as halted. That is a few cycles per wake, well
+//! below what a meter can show.
+
+use std::collections::VecDeque;
+
+/// Unread frames kept for the host; older ones are dropped when it falls behind
+pub const CPU_USAGE_QUEUE_SIZE: usize = 256;
+
+/// CPU activity during one LCD frame
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameUsage {
+    /// LCD frame number that ended this period
+    pub frame: u64,
+    /// CPU cycles in the frame
+    pub cycles: u64,
+    /// Of those, cycles spent halted
+    pub halted_cycles: u64,
+    /// Number of times an interrupt woke the CPU from HALT
+    pub wakes: u32,
+    /// Interrupt source bits that woke it (OR of all wakes)
+    pub wake_sources: u32,
+}
+
+impl FrameUsage {
+    /// Fraction of the frame spent executing, 0.0 to 1.0
+    pub fn busy_fraction(&self) -> f64 {
+        if self.cycles == 0 {
+            return 0.0;
+        }
+        (self.cycles - self.halted_cycles.min(self.cycles)) as f64 / self.cycles as f64
+    }
+}
+
+/// Accumulates usage for the frame in progress
+#[derive(Debug, Clone)]
+pub struct CpuUsage {
+    last_cycle: u64,
+    last_halted: bool,
+    cycles: u64,
+    halted_cycles: u64,
+    wakes: u32,
+    wake_sources: u32,
+    frames: VecDeque<FrameUsage>,
+    last: Option<FrameUsage>,
+}
+
+impl CpuUsage {
+    /// Start measuring at `total_cycles` with the CPU `halted` or not
+    pub fn new(total_cycles: u64, halted: bool) -> Self {
+        Self {
+            last_cycle: total_cycles,
+            last_halted: halted,
+            cycles: 0,
+            halted_cycles: 0,
+            wakes: 0,
+            wake_sources: 0,
+            frames: VecDeque::new(),
+            last: None,
+        }
+    }
+
+    /// Charge the cycles since the last sample to the previous state, then
+    /// note whether the CPU is now `halted`
+    #[inline]
+    pub fn sample(&mut self, total_cycles: u64, halted: bool) {
+        // total_cycles restarts at reset; count only cycles since then
+        let elapsed = total_cycles.checked_sub(self.last_cycle).unwrap_or(total_cycles);
+        self.cycles += elapsed;
+        if self.last_halted {
+            self.halted_cycles += elapsed;
+        }
+        self.last_cycle = total_cycles;
+        self.last_halted = halted;
+    }
+
+    /// Note an interrupt with `sources` pending; it is a wake if the CPU was
+    /// halted at the last sample
+    pub fn interrupt(&mut self, sources: u32) {
+        if self.last_halted {
+            self.wakes += 1;
+            self.wake_sources |= sources;
+        }
+    }
+
+    /// Rebase after the bus cycle counter was rescaled for a CPU speed change
+    pub fn rebase(&mut self, total_cycles: u64) {
+        self.last_cycle = total_cycles;
+    }
+
+    /// Close the frame numbered `frame` at `total_cycles`
+    pub fn end_frame(&mut self, frame: u64, total_cycles: u64, halted: bool) {
+        self.sample(total_cycles, halted);
+        let usage = FrameUsage {
+            frame,
+            cycles: self.cycles,
+            halted_cycles: self.halted_cycles,
+            wakes: self.wakes,
+            wake_sources: self.wake_sources,
+        };
+        if self.frames.len() == CPU_USAGE_QUEUE_SIZE {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(usage);
+        self.last = Some(usage);
+        self.cycles = 0;
+        self.halted_cycles = 0;
+        self.wakes = 0;
+        self.wake_sources = 0;
+    }
+
+    /// Most recently completed frame
+    pub fn last_frame(&self) -> Option<FrameUsage> {
+        self.last
+    }
+
+    /// Take up to `max` unread frames, oldest first
+    pub fn take_frames(&mut self, max: usize) -> Vec<FrameUsage> {
+        let n = max.min(self.frames.len());
+        self.frames.drain(..n).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halted_and_wakes() {
+        let mut usage = CpuUsage::new(1000, false);
+        // Run 300 cycles, HALT, sleep until 1900, then an interrupt wakes it
+        usage.sample(1300, true);
+        usage.interrupt(0x10);
+        usage.sample(1900, false);
+        // Interrupts while running are not wakes
+        usage.interrupt(0x01);
+        usage.end_frame(1, 2000, false);
+
+        let frame = usage.last_frame().unwrap();
+        assert_eq!(frame, FrameUsage { frame: 1, cycles: 1000, halted_cycles: 600, wakes: 1, wake_sources: 0x10 });
+        assert!((frame.busy_fraction() - 0.4).abs() < 1e-9);
+
+        // A reset restarts the cycle counter
+        usage.end_frame(2, 500, true);
+        assert_eq!(usage.last_frame().unwrap().cycles, 500);
+        assert_eq!(usage.last_frame().unwrap().halted_cycles, 0);
+        assert_eq!(usage.take_frames(usize::MAX).len(), 2);
+        assert!(usage.take_frames(usize::MAX).is_empty());
+    }
+}
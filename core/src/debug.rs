@@ -0,0 +1,471 @@
+//! Debugger core: breakpoints, watchpoints and stepping
+//!
+//! Breakpoints and the step-over/step-out targets live on `Emu` (`Debugger`)
+//! and are checked before each instruction. Memory and port watchpoints live
+//! on the bus (`Watchpoints`) and are checked on each access, like the
+//! sandbox; the first hit is held and the run loop stops once the accessing
+//! instruction has finished. Either way the run stops with a `DebugEvent`.
+//!
+//! Execute watchpoints are checked with the breakpoints. A breakpoint or
+//! execute watchpoint does not fire again at the PC the emulator stopped at,
+//! so running again resumes past it.
+
+use std::fmt;
+
+/// Access bits for watchpoints
+pub mod access {
+    /// Data read (memory reads, IN)
+    pub const READ: u8 = 1 << 0;
+    /// Data write (memory writes, OUT)
+    pub const WRITE: u8 = 1 << 1;
+    /// Instruction start inside the range (memory watchpoints only)
+    pub const EXECUTE: u8 = 1 << 2;
+}
+
+/// How a watched address was accessed
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read = 0,
+    Write = 1,
+    Execute = 2,
+}
+
+impl AccessKind {
+    fn bit(self) -> u8 {
+        match self {
+            AccessKind::Read => access::READ,
+            AccessKind::Write => access::WRITE,
+            AccessKind::Execute => access::EXECUTE,
+        }
+    }
+}
+
+/// Why the emulator stopped
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugEventKind {
+    /// Execution reached a breakpoint
+    Breakpoint = 0,
+    /// A memory watchpoint was accessed
+    Watchpoint = 1,
+    /// A port watchpoint was accessed (IN/OUT)
+    Port = 2,
+    /// A step finished
+    Step = 3,
+}
+
+/// A debugger stop
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugEvent {
+    /// Why the emulator stopped
+    pub kind: DebugEventKind,
+    /// How the watched address was accessed (watchpoints only)
+    pub access: AccessKind,
+    /// Value read or written (watchpoints only)
+    pub value: u8,
+    /// Breakpoint or watchpoint ID (0 for steps)
+    pub id: u32,
+    /// PC of the next instruction for breakpoints and steps; PC of the
+    /// accessing instruction for watchpoints
+    pub pc: u32,
+    /// Address or port accessed (watchpoints only)
+    pub addr: u32,
+}
+
+impl DebugEvent {
+    fn at_pc(kind: DebugEventKind, id: u32, pc: u32) -> Self {
+        Self { kind, access: AccessKind::Execute, value: 0, id, pc, addr: pc }
+    }
+}
+
+impl fmt::Display for DebugEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DebugEventKind::Breakpoint => write!(f, "breakpoint {} at {:06X}", self.id, self.pc),
+            DebugEventKind::Step => write!(f, "step to {:06X}", self.pc),
+            DebugEventKind::Watchpoint | DebugEventKind::Port if self.access == AccessKind::Execute => {
+                write!(f, "watchpoint {}: execute {:06X}", self.id, self.addr)
+            }
+            DebugEventKind::Watchpoint | DebugEventKind::Port => {
+                let verb = if self.access == AccessKind::Read { "read" } else { "write" };
+                write!(f, "watchpoint {}: {} ", self.id, verb)?;
+                if self.kind == DebugEventKind::Port {
+                    write!(f, "port {:04X}", self.addr)?;
+                } else {
+                    write!(f, "{:06X}", self.addr)?;
+                }
+                write!(f, " = {:02X} (PC={:06X})", self.value, self.pc)
+            }
+        }
+    }
+}
+
+/// An inclusive address or port range and the accesses that hit it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watch {
+    id: u32,
+    start: u32,
+    end: u32,
+    access: u8,
+}
+
+impl Watch {
+    fn hits(&self, addr: u32, kind: AccessKind) -> bool {
+        self.access & kind.bit() != 0 && (self.start..=self.end).contains(&addr)
+    }
+}
+
+/// Memory and port watchpoints, checked by the bus on every access
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    memory: Vec<Watch>,
+    ports: Vec<Watch>,
+    next_id: u32,
+    /// Cached `!memory.is_empty() || !ports.is_empty()`
+    active: bool,
+    hit: Option<DebugEvent>,
+}
+
+impl Watchpoints {
+    /// True if any watchpoint is set
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Watch memory `start..=end` for the `access` bits. Returns the ID, or
+    /// None if the range or bits are empty.
+    pub fn add_memory(&mut self, start: u32, end: u32, access: u8) -> Option<u32> {
+        let watch = self.new_watch(start, end, access & (access::READ | access::WRITE | access::EXECUTE))?;
+        self.memory.push(watch);
+        Some(watch.id)
+    }
+
+    /// Watch ports `start..=end` (IN/OUT) for reads and/or writes. Returns
+    /// the ID, or None if the range or bits are empty.
+    pub fn add_port(&mut self, start: u16, end: u16, access: u8) -> Option<u32> {
+        let watch = self.new_watch(start as u32, end as u32, access & (access::READ | access::WRITE))?;
+        self.ports.push(watch);
+        Some(watch.id)
+    }
+
+    fn new_watch(&mut self, start: u32, end: u32, access: u8) -> Option<Watch> {
+        if access == 0 || start > end {
+            return None;
+        }
+        self.next_id += 1;
+        self.active = true;
+        Some(Watch { id: self.next_id, start, end, access })
+    }
+
+    /// Remove a watchpoint. Returns false if there is none with that ID.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.memory.len() + self.ports.len();
+        self.memory.retain(|w| w.id != id);
+        self.ports.retain(|w| w.id != id);
+        self.active = !self.memory.is_empty() || !self.ports.is_empty();
+        self.memory.len() + self.ports.len() != before
+    }
+
+    /// Check a memory read or write
+    #[inline]
+    pub fn check_memory(&mut self, pc: u32, addr: u32, kind: AccessKind, value: u8) {
+        if self.hit.is_none() {
+            if let Some(w) = self.memory.iter().find(|w| w.hits(addr, kind)) {
+                self.hit = Some(DebugEvent { kind: DebugEventKind::Watchpoint, access: kind, value, id: w.id, pc, addr });
+            }
+        }
+    }
+
+    /// Check an IN or OUT
+    #[inline]
+    pub fn check_port(&mut self, pc: u32, port: u16, kind: AccessKind, value: u8) {
+        if self.hit.is_none() {
+            if let Some(w) = self.ports.iter().find(|w| w.hits(port as u32, kind)) {
+                let addr = port as u32;
+                self.hit = Some(DebugEvent { kind: DebugEventKind::Port, access: kind, value, id: w.id, pc, addr });
+            }
+        }
+    }
+
+    /// Execute watchpoint covering `pc`, if any
+    fn execute_hit(&self, pc: u32) -> Option<DebugEvent> {
+        let w = self.memory.iter().find(|w| w.hits(pc, AccessKind::Execute))?;
+        Some(DebugEvent::at_pc(DebugEventKind::Watchpoint, w.id, pc))
+    }
+
+    /// True if an access has hit a watchpoint since the last `take_hit`
+    #[inline]
+    pub fn has_hit(&self) -> bool {
+        self.hit.is_some()
+    }
+
+    /// Take the pending hit
+    pub fn take_hit(&mut self) -> Option<DebugEvent> {
+        self.hit.take()
+    }
+}
+
+/// Where a step-over or step-out stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepTarget {
+    /// At `pc` with the stack back at `sp` or above (after a CALL/RST)
+    Over { pc: u32, sp: u32 },
+    /// After a return taken with SP at `min_sp` or above, i.e. from the
+    /// current function rather than one it calls. `ret_sp` is the SP at a
+    /// return just executed.
+    Out { min_sp: u32, ret_sp: Option<u32> },
+}
+
+/// Breakpoints and step state, checked by the run loop before each instruction
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    /// (ID, PC)
+    breakpoints: Vec<(u32, u32)>,
+    next_id: u32,
+    step: Option<StepTarget>,
+    /// Breakpoints do not fire here on the next check (where we stopped)
+    resume_pc: Option<u32>,
+}
+
+impl Debugger {
+    /// True if there is anything to check before each instruction
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        !self.breakpoints.is_empty() || self.step.is_some()
+    }
+
+    /// Add a breakpoint at `pc`; returns its ID
+    pub fn add_breakpoint(&mut self, pc: u32) -> u32 {
+        self.next_id += 1;
+        self.breakpoints.push((self.next_id, pc));
+        self.next_id
+    }
+
+    /// Remove a breakpoint. Returns false if there is none with that ID.
+    pub fn remove_breakpoint(&mut self, id: u32) -> bool {
+        let before = self.breakpoints.len();
+        self.breakpoints.retain(|&(bp, _)| bp != id);
+        self.breakpoints.len() != before
+    }
+
+    /// Breakpoints as (ID, PC)
+    pub fn breakpoints(&self) -> &[(u32, u32)] {
+        &self.breakpoints
+    }
+
+    /// Stop at `pc` once the stack is back at `sp` (step over a call)
+    pub(crate) fn set_step_over(&mut self, pc: u32, sp: u32) {
+        self.step = Some(StepTarget::Over { pc, sp });
+    }
+
+    /// Stop after the current function returns
+    pub(crate) fn set_step_out(&mut self, sp: u32) {
+        self.step = Some(StepTarget::Out { min_sp: sp, ret_sp: None });
+    }
+
+    pub(crate) fn clear_step(&mut self) {
+        self.step = None;
+    }
+
+    /// True if `check` needs to know whether the next instruction returns
+    pub(crate) fn wants_return_check(&self) -> bool {
+        matches!(self.step, Some(StepTarget::Out { .. }))
+    }
+
+    /// Record where the emulator stopped, so resuming doesn't break there again
+    pub(crate) fn stopped_at(&mut self, pc: u32) {
+        self.resume_pc = Some(pc);
+    }
+
+    /// Check before the instruction at `pc` runs. `is_return` is only
+    /// meaningful when `wants_return_check()`.
+    pub(crate) fn check(&mut self, pc: u32, sp: u32, is_return: bool, watches: &Watchpoints) -> Option<DebugEvent> {
+        match &mut self.step {
+            Some(StepTarget::Over { pc: target, sp: target_sp }) if pc == *target && sp >= *target_sp => {
+                return Some(DebugEvent::at_pc(DebugEventKind::Step, 0, pc));
+            }
+            Some(StepTarget::Out { min_sp, ret_sp }) => {
+                if ret_sp.take().is_some_and(|ret| sp > ret) {
+                    return Some(DebugEvent::at_pc(DebugEventKind::Step, 0, pc));
+                }
+                if is_return && sp >= *min_sp {
+                    *ret_sp = Some(sp);
+                }
+            }
+            _ => {}
+        }
+
+        if self.resume_pc.take() == Some(pc) {
+            return None;
+        }
+        if let Some(&(id, _)) = self.breakpoints.iter().find(|&&(_, bp)| bp == pc) {
+            return Some(DebugEvent::at_pc(DebugEventKind::Breakpoint, id, pc));
+        }
+        watches.execute_hit(pc)
+    }
+}
+
+/// A CPU register, for debuggers that read and write registers by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Af,
+    Bc,
+    De,
+    Hl,
+    Ix,
+    Iy,
+    /// The active stack pointer (SPL in ADL mode, SPS otherwise)
+    Sp,
+    Pc,
+    AfPrime,
+    BcPrime,
+    DePrime,
+    HlPrime,
+    Spl,
+    Sps,
+    I,
+    R,
+    Mbase,
+    /// ADL mode flag (0 or 1)
+    Adl,
+}
+
+impl Register {
+    /// Every register, in the order debuggers list them
+    pub const ALL: [Register; 18] = [
+        Register::Af, Register::Bc, Register::De, Register::Hl, Register::Ix, Register::Iy,
+        Register::Sp, Register::Pc, Register::AfPrime, Register::BcPrime, Register::DePrime,
+        Register::HlPrime, Register::Spl, Register::Sps, Register::I, Register::R,
+        Register::Mbase, Register::Adl,
+    ];
+
+    /// Lower-case name (`af'` for the shadow registers)
+    pub fn name(self) -> &'static str {
+        match self {
+            Register::Af => "af",
+            Register::Bc => "bc",
+            Register::De => "de",
+            Register::Hl => "hl",
+            Register::Ix => "ix",
+            Register::Iy => "iy",
+            Register::Sp => "sp",
+            Register::Pc => "pc",
+            Register::AfPrime => "af'",
+            Register::BcPrime => "bc'",
+            Register::DePrime => "de'",
+            Register::HlPrime => "hl'",
+            Register::Spl => "spl",
+            Register::Sps => "sps",
+            Register::I => "i",
+            Register::R => "r",
+            Register::Mbase => "mbase",
+            Register::Adl => "adl",
+        }
+    }
+
+    /// Width in bits
+    pub fn bits(self) -> u32 {
+        match self {
+            Register::Af | Register::AfPrime | Register::Sps | Register::I => 16,
+            Register::R | Register::Mbase | Register::Adl => 8,
+            _ => 24,
+        }
+    }
+}
+
+/// Strip a .SIS/.LIS/.SIL/.LIL suffix
+fn strip_suffix(bytes: &[u8]) -> &[u8] {
+    match bytes {
+        [0x40 | 0x49 | 0x52 | 0x5B, rest @ ..] => rest,
+        _ => bytes,
+    }
+}
+
+/// True if the instruction starting with `bytes` is a RET, RET cc, RETI or RETN
+pub fn is_return(bytes: &[u8]) -> bool {
+    match strip_suffix(bytes) {
+        [0xC9, ..] => true,
+        [op, ..] if op & 0xC7 == 0xC0 => true,
+        [0xED, 0x4D | 0x45, ..] => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchpoints() {
+        let mut watches = Watchpoints::default();
+        assert!(!watches.is_active());
+        assert_eq!(watches.add_memory(0xD00010, 0xD0000F, access::READ), None);
+        assert_eq!(watches.add_memory(0xD00000, 0xD0000F, 0), None);
+        let mem = watches.add_memory(0xD00000, 0xD0000F, access::WRITE | access::EXECUTE).unwrap();
+        let port = watches.add_port(0x5000, 0x5000, access::READ).unwrap();
+        assert!(watches.is_active());
+
+        watches.check_memory(0xD10000, 0xD00004, AccessKind::Read, 1);
+        watches.check_port(0xD10000, 0x5000, AccessKind::Write, 1);
+        assert!(!watches.has_hit());
+
+        watches.check_memory(0xD10000, 0xD00004, AccessKind::Write, 0x42);
+        // Only the first hit is kept
+        watches.check_port(0xD10001, 0x5000, AccessKind::Read, 7);
+        let hit = watches.take_hit().unwrap();
+        assert_eq!((hit.kind, hit.id, hit.addr, hit.value), (DebugEventKind::Watchpoint, mem, 0xD00004, 0x42));
+        assert_eq!(hit.to_string(), "watchpoint 1: write D00004 = 42 (PC=D10000)");
+        watches.check_port(0xD10001, 0x5000, AccessKind::Read, 7);
+        assert_eq!(watches.take_hit().unwrap().to_string(), "watchpoint 2: read port 5000 = 07 (PC=D10001)");
+        assert_eq!(watches.execute_hit(0xD0000F).unwrap().id, mem);
+
+        assert!(watches.remove(mem));
+        assert!(watches.remove(port));
+        assert!(!watches.remove(port));
+        assert!(!watches.is_active());
+    }
+
+    #[test]
+    fn test_breakpoints_and_resume() {
+        let watches = Watchpoints::default();
+        let mut debugger = Debugger::default();
+        let id = debugger.add_breakpoint(0x100);
+        let hit = debugger.check(0x100, 0, false, &watches).unwrap();
+        assert_eq!((hit.kind, hit.id, hit.pc), (DebugEventKind::Breakpoint, id, 0x100));
+
+        // Resuming from the breakpoint runs past it once
+        debugger.stopped_at(0x100);
+        assert_eq!(debugger.check(0x100, 0, false, &watches), None);
+        assert!(debugger.check(0x100, 0, false, &watches).is_some());
+
+        assert!(debugger.remove_breakpoint(id));
+        assert!(!debugger.is_active());
+    }
+
+    #[test]
+    fn test_step_out_ignores_nested_returns() {
+        let watches = Watchpoints::default();
+        let mut debugger = Debugger::default();
+        debugger.set_step_out(0xD1A870);
+        // A callee's RET below our frame
+        assert_eq!(debugger.check(0x200, 0xD1A86D, true, &watches), None);
+        assert_eq!(debugger.check(0x110, 0xD1A870, false, &watches), None);
+        // A RET cc not taken: SP unchanged
+        assert_eq!(debugger.check(0x120, 0xD1A870, true, &watches), None);
+        assert_eq!(debugger.check(0x121, 0xD1A870, true, &watches), None);
+        let hit = debugger.check(0x400, 0xD1A873, false, &watches).unwrap();
+        assert_eq!((hit.kind, hit.pc), (DebugEventKind::Step, 0x400));
+    }
+
+    #[test]
+    fn test_is_return() {
+        assert!(is_return(&[0xC9]));
+        assert!(is_return(&[0xD8]));
+        assert!(is_return(&[0x5B, 0xC9]));
+        assert!(is_return(&[0xED, 0x4D]));
+        assert!(!is_return(&[0xCD, 0, 0]));
+    }
+}
@@ -0,0 +1,189 @@
+//! Crash diagnostic bundles
+//!
+//! A bundle packs everything needed to reproduce a failure into one blob:
+//! the save state, the execution history tail, recent interrupts, and the
+//! unknown-access report. Bundles are written on internal errors, memory
+//! protection violations, or on request (see `Emu::write_diagnostic_bundle`).
+//!
+//! # Format
+//!
+//! ```text
+//! magic "CEDG" | version u32 | section count u32 | sections...
+//! section: tag [u8; 4] | length u32 | data
+//! ```
+//!
+//! All integers are little-endian. Sections: `INFO` (text: trigger, PC,
+//! cycles), `STAT` (save state, loadable with `Emu::load_state`), `HIST`
+//! (text: execution history), `IRQS` (text: interrupt log), `UNKN` (text:
+//! unknown-access report).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Bundle magic bytes
+pub const BUNDLE_MAGIC: [u8; 4] = *b"CEDG";
+/// Bundle format version
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Number of serviced interrupts kept in the interrupt log
+const IRQ_LOG_SIZE: usize = 32;
+
+/// Why a diagnostic bundle was written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticTrigger {
+    /// The core detected an inconsistency in its own state
+    InternalError(String),
+    /// A memory protection violation raised an NMI
+    ProtectionViolation {
+        /// Address of the offending access
+        addr: u32,
+        /// PC of the offending instruction
+        pc: u32,
+    },
+    /// Requested by the host
+    UserRequest,
+}
+
+impl fmt::Display for DiagnosticTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticTrigger::InternalError(msg) => write!(f, "internal error: {}", msg),
+            DiagnosticTrigger::ProtectionViolation { addr, pc } => {
+                write!(f, "protection violation: addr={:06X} pc={:06X}", addr, pc)
+            }
+            DiagnosticTrigger::UserRequest => write!(f, "user request"),
+        }
+    }
+}
+
+/// One serviced interrupt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqLogEntry {
+    /// Bus cycle count when the interrupt was taken
+    pub cycle: u64,
+    /// PC that was interrupted
+    pub pc: u32,
+    /// Pending and enabled interrupt controller sources (0 for NMI)
+    pub sources: u32,
+    /// True for NMI, false for maskable interrupts
+    pub nmi: bool,
+}
+
+/// Ring buffer of the most recently serviced interrupts
+#[derive(Debug, Default)]
+pub struct IrqLog {
+    entries: VecDeque<IrqLogEntry>,
+}
+
+impl IrqLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an interrupt, dropping the oldest entry when full
+    pub fn record(&mut self, entry: IrqLogEntry) {
+        if self.entries.len() == IRQ_LOG_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Entries from oldest to newest
+    pub fn entries(&self) -> Vec<IrqLogEntry> {
+        self.entries.iter().copied().collect()
+    }
+
+    /// Clear the log
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Human-readable log, one line per interrupt
+    pub fn summary(&self) -> String {
+        let mut out = String::from("Interrupt log (oldest to newest):\n");
+        for e in &self.entries {
+            if e.nmi {
+                out.push_str(&format!("  cycle={} pc={:06X} NMI\n", e.cycle, e.pc));
+            } else {
+                out.push_str(&format!("  cycle={} pc={:06X} sources={:08X}\n", e.cycle, e.pc, e.sources));
+            }
+        }
+        out
+    }
+}
+
+/// Write a bundle from its sections, returning the number of bytes written
+pub fn write_bundle<W: Write>(sink: &mut W, sections: &[([u8; 4], &[u8])]) -> io::Result<usize> {
+    let mut written = 0;
+    sink.write_all(&BUNDLE_MAGIC)?;
+    sink.write_all(&BUNDLE_VERSION.to_le_bytes())?;
+    sink.write_all(&(sections.len() as u32).to_le_bytes())?;
+    written += 12;
+    for (tag, data) in sections {
+        sink.write_all(tag)?;
+        sink.write_all(&(data.len() as u32).to_le_bytes())?;
+        sink.write_all(data)?;
+        written += 8 + data.len();
+    }
+    sink.flush()?;
+    Ok(written)
+}
+
+/// Split a bundle into (tag, data) sections.
+/// Returns None if the magic, version, or section lengths are invalid.
+pub fn parse_bundle(data: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+    if data.len() < 12 || data[0..4] != BUNDLE_MAGIC {
+        return None;
+    }
+    let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    if version != BUNDLE_VERSION {
+        return None;
+    }
+    let count = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+    let mut pos = 12;
+    let mut sections = Vec::with_capacity(count);
+    for _ in 0..count {
+        let header = data.get(pos..pos + 8)?;
+        let tag: [u8; 4] = header[0..4].try_into().unwrap();
+        let len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        pos += 8;
+        sections.push((tag, data.get(pos..pos + len)?));
+        pos += len;
+    }
+    Some(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let mut out = Vec::new();
+        let n = write_bundle(&mut out, &[(*b"INFO", b"hello"), (*b"STAT", &[1, 2, 3])]).unwrap();
+        assert_eq!(n, out.len());
+
+        let sections = parse_bundle(&out).unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0], (*b"INFO", &b"hello"[..]));
+        assert_eq!(sections[1], (*b"STAT", &[1u8, 2, 3][..]));
+
+        // Truncated data is rejected
+        assert!(parse_bundle(&out[..out.len() - 1]).is_none());
+        assert!(parse_bundle(b"XXXX").is_none());
+    }
+
+    #[test]
+    fn test_irq_log_keeps_newest() {
+        let mut log = IrqLog::new();
+        for i in 0..(IRQ_LOG_SIZE as u64 + 5) {
+            log.record(IrqLogEntry { cycle: i, pc: 0, sources: 1, nmi: false });
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), IRQ_LOG_SIZE);
+        assert_eq!(entries[0].cycle, 5);
+        assert!(log.summary().contains("sources=00000001"));
+    }
+}
@@ -45,6 +45,129 @@ pub fn disassemble(opcode: &[u8], adl: bool) -> DisasmResult {
     }
 }
 
+/// What kind of control transfer an instruction makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchKind {
+    /// JP, JR, DJNZ
+    Jump,
+    /// CALL, RST
+    Call,
+    /// RET, RETI, RETN
+    Return,
+}
+
+/// Where a branch goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchTarget {
+    /// Fixed address (JP nn, CALL nn, RST)
+    Absolute(u32),
+    /// Displacement from the end of the instruction (JR, DJNZ)
+    Relative(i8),
+    /// Register or stack: JP (HL), JP (IX), JP (IY), returns
+    Indirect,
+}
+
+/// A branch made by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Branch {
+    pub kind: BranchKind,
+    pub target: BranchTarget,
+    /// Only taken if a condition (or DJNZ's B != 0) holds
+    pub conditional: bool,
+}
+
+/// A decoded instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instr {
+    /// Mnemonic including any suffix (e.g. "JP.LIL")
+    pub mnemonic: String,
+    /// Operands as written (e.g. ["(IX-3)", "0x42"])
+    pub operands: Vec<String>,
+    /// Length in bytes, suffix included
+    pub length: usize,
+    /// Control transfer, if the instruction makes one
+    pub branch: Option<Branch>,
+    /// Immediate addresses are 24-bit (ADL mode, or a .SIL/.LIL suffix)
+    pub long_imm: bool,
+}
+
+impl Instr {
+    /// Branch target as an address, for the instruction at `pc`. None if it
+    /// does not branch or the target is only known at run time.
+    pub fn target(&self, pc: u32) -> Option<u32> {
+        let mask = if self.long_imm { 0xFFFFFF } else { 0xFFFF };
+        match self.branch?.target {
+            BranchTarget::Absolute(addr) => Some(addr),
+            BranchTarget::Relative(e) => Some(pc.wrapping_add(self.length as u32).wrapping_add(e as i32 as u32) & mask),
+            BranchTarget::Indirect => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Instr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mnemonic)?;
+        if !self.operands.is_empty() {
+            write!(f, " {}", self.operands.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode an eZ80 instruction into mnemonic, operands, length and branch.
+/// `bytes` should hold up to 6 bytes (the longest instruction with a
+/// suffix); `adl` is the current mode.
+pub fn decode(bytes: &[u8], adl: bool) -> Instr {
+    let DisasmResult { mnemonic: text, length, .. } = disassemble(bytes, adl);
+
+    // The disassembler appends the suffix to the whole instruction
+    let (suffix, long_imm) = match bytes.first() {
+        Some(0x40) => (".SIS", false),
+        Some(0x49) => (".LIS", false),
+        Some(0x52) => (".SIL", true),
+        Some(0x5B) => (".LIL", true),
+        _ => ("", adl),
+    };
+    let text = text.strip_suffix(suffix).unwrap_or(&text);
+    let (name, operands) = text.split_once(' ').unwrap_or((text, ""));
+    let operands = if operands.is_empty() { Vec::new() } else { operands.split(',').map(str::to_string).collect() };
+    let body = if suffix.is_empty() { bytes } else { &bytes[1..] };
+
+    Instr {
+        mnemonic: format!("{}{}", name, suffix),
+        operands,
+        length,
+        branch: decode_branch(body, long_imm),
+        long_imm,
+    }
+}
+
+/// Branch made by the instruction in `op` (suffix already removed)
+fn decode_branch(op: &[u8], long_imm: bool) -> Option<Branch> {
+    use BranchKind::*;
+
+    let imm = |i: usize| {
+        let byte = |n: usize| op.get(i + n).copied().unwrap_or(0) as u32;
+        byte(0) | byte(1) << 8 | if long_imm { byte(2) << 16 } else { 0 }
+    };
+    let rel = || BranchTarget::Relative(op.get(1).copied().unwrap_or(0) as i8);
+    let branch = |kind, target, conditional| Some(Branch { kind, target, conditional });
+
+    match *op {
+        [0xC3, ..] => branch(Jump, BranchTarget::Absolute(imm(1)), false),
+        [o, ..] if o & 0xC7 == 0xC2 => branch(Jump, BranchTarget::Absolute(imm(1)), true),
+        [0x18, ..] => branch(Jump, rel(), false),
+        [0x20 | 0x28 | 0x30 | 0x38 | 0x10, ..] => branch(Jump, rel(), true),
+        [0xE9, ..] | [0xDD | 0xFD, 0xE9, ..] => branch(Jump, BranchTarget::Indirect, false),
+        [0xCD, ..] => branch(Call, BranchTarget::Absolute(imm(1)), false),
+        [o, ..] if o & 0xC7 == 0xC4 => branch(Call, BranchTarget::Absolute(imm(1)), true),
+        [o, ..] if o & 0xC7 == 0xC7 => branch(Call, BranchTarget::Absolute((o & 0x38) as u32), false),
+        [0xC9, ..] | [0xED, 0x4D | 0x45, ..] => branch(Return, BranchTarget::Indirect, false),
+        [o, ..] if o & 0xC7 == 0xC0 => branch(Return, BranchTarget::Indirect, true),
+        _ => None,
+    }
+}
+
 /// Main disassembly dispatcher
 fn disasm_main(opcode: &[u8], adl: bool) -> (String, usize) {
     let op = opcode[0];
@@ -1527,6 +1650,47 @@ mod tests {
         assert!(result.mnemonic.contains(".SIS"));
     }
 
+    #[test]
+    fn test_decode_operands_and_suffix() {
+        let instr = decode(&[0xDD, 0x36, 0xFD, 0x42], true);
+        assert_eq!(instr.mnemonic, "LD");
+        assert_eq!(instr.operands, ["(IX-3)", "0x42"]);
+        assert_eq!(instr.length, 4);
+        assert_eq!(instr.branch, None);
+
+        let instr = decode(&[0x40, 0x21, 0x34, 0x12], true);
+        assert_eq!(instr.mnemonic, "LD.SIS");
+        assert_eq!(instr.operands, ["HL", "0x1234"]);
+        assert_eq!(instr.to_string(), "LD.SIS HL,0x1234");
+
+        let instr = decode(&[0x52, 0xED, 0xB0], true);
+        assert_eq!((instr.mnemonic.as_str(), instr.operands.len(), instr.length), ("LDIR.SIL", 0, 3));
+    }
+
+    #[test]
+    fn test_decode_branches() {
+        let jr = decode(&[0x20, 0xFB], true);
+        assert_eq!(jr.branch, Some(Branch { kind: BranchKind::Jump, target: BranchTarget::Relative(-5), conditional: true }));
+        assert_eq!(jr.target(0xD00010), Some(0xD0000D));
+        // Z80 mode wraps within 64K
+        assert_eq!(decode(&[0x18, 0x10], false).target(0xFFF8), Some(0x000A));
+
+        let call = decode(&[0xCD, 0x56, 0x34, 0x12], true);
+        assert_eq!(call.branch.unwrap().kind, BranchKind::Call);
+        assert_eq!(call.target(0), Some(0x123456));
+        assert_eq!(decode(&[0xDC, 0x34, 0x12], false).target(0), Some(0x1234));
+        assert_eq!(decode(&[0xEF], true).target(0x1000), Some(0x28));
+
+        // JP.LIL from Z80 mode takes a 24-bit address
+        let jp = decode(&[0x5B, 0xC3, 0x00, 0x10, 0xD1], false);
+        assert_eq!((jp.mnemonic.as_str(), jp.length, jp.target(0)), ("JP.LIL", 5, Some(0xD11000)));
+
+        assert_eq!(decode(&[0xDD, 0xE9], true).branch.unwrap().target, BranchTarget::Indirect);
+        assert_eq!(decode(&[0xD8], true).branch.unwrap().kind, BranchKind::Return);
+        assert!(decode(&[0xED, 0x4D], true).branch.is_some());
+        assert_eq!(decode(&[0xED, 0x38, 0x05], true).branch, None);
+    }
+
     #[test]
     fn test_cb_prefix() {
         // RLC B
@@ -0,0 +1,288 @@
+//! ELF loading for bare-metal tests
+//!
+//! Reads the linked ELF files that ez80-clang produces so firmware-style
+//! code can be run against the CPU and peripherals without building a ROM
+//! or booting TI-OS (see `Emu::load_elf`). Only what loading needs is
+//! parsed: allocated sections at their link addresses, the entry point,
+//! and the symbol table.
+
+use crate::symbols::Symbol;
+
+/// `e_machine` for Z80-family targets, which ez80-clang uses for eZ80 code
+pub const EM_Z80: u16 = 220;
+
+/// Stack pointer set by `Emu::load_elf`: the top of the stack TI-OS uses,
+/// so code written for the calculator finds its stack where it expects
+pub const DEFAULT_STACK_TOP: u32 = 0xD1A87E;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u32 = 0x2;
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+const SHN_UNDEF: u16 = 0;
+const SHN_LORESERVE: u16 = 0xFF00;
+const SHN_ABS: u16 = 0xFFF1;
+
+/// A section to place in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSection {
+    /// Section name (e.g. `.text`)
+    pub name: String,
+    /// Link address
+    pub addr: u32,
+    /// Contents; zeros for `.bss`-style sections
+    pub data: Vec<u8>,
+}
+
+/// Errors that can occur during parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElfError {
+    /// A header or table runs past the end of the file
+    Truncated,
+    /// Not an ELF file
+    BadMagic,
+    /// Not a 32-bit little-endian Z80-family executable
+    Unsupported(&'static str),
+    /// A section does not fit in the 24-bit address space
+    OutOfRange(String),
+}
+
+impl std::fmt::Display for ElfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ElfError::Truncated => write!(f, "file truncated"),
+            ElfError::BadMagic => write!(f, "bad magic (not an ELF file)"),
+            ElfError::Unsupported(what) => write!(f, "unsupported ELF: {}", what),
+            ElfError::OutOfRange(name) => write!(f, "section {} lies outside the 24-bit address space", name),
+        }
+    }
+}
+
+/// A parsed ELF executable
+#[derive(Debug, Clone)]
+pub struct ElfImage {
+    /// Entry point
+    pub entry: u32,
+    /// Allocated sections, in file order
+    pub sections: Vec<ElfSection>,
+    /// Defined function, object and label symbols
+    pub symbols: Vec<Symbol>,
+}
+
+/// Little-endian reads that fail with `Truncated` past the end of the file
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8], ElfError> {
+        let end = offset.checked_add(len).ok_or(ElfError::Truncated)?;
+        self.0.get(offset..end).ok_or(ElfError::Truncated)
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, ElfError> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ElfError> {
+        let b = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ElfError> {
+        let b = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// NUL-terminated string at `offset` in the string table at `table`
+    fn str(&self, table: usize, offset: u32) -> Result<String, ElfError> {
+        let start = table.checked_add(offset as usize).ok_or(ElfError::Truncated)?;
+        let tail = self.0.get(start..).ok_or(ElfError::Truncated)?;
+        let len = tail.iter().position(|&b| b == 0).ok_or(ElfError::Truncated)?;
+        Ok(String::from_utf8_lossy(&tail[..len]).into_owned())
+    }
+}
+
+/// Fields of a section header that loading uses
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    addr: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+}
+
+impl ElfImage {
+    /// Parse a linked ELF file
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        let r = Reader(data);
+        if r.bytes(0, 4).map_err(|_| ElfError::BadMagic)? != b"\x7FELF" {
+            return Err(ElfError::BadMagic);
+        }
+        if r.u8(4)? != 1 {
+            return Err(ElfError::Unsupported("not 32-bit"));
+        }
+        if r.u8(5)? != 1 {
+            return Err(ElfError::Unsupported("not little-endian"));
+        }
+        if r.u16(18)? != EM_Z80 {
+            return Err(ElfError::Unsupported("not a Z80/eZ80 machine"));
+        }
+        let entry = r.u32(24)?;
+        let shoff = r.u32(32)? as usize;
+        let shentsize = r.u16(46)? as usize;
+        let shnum = r.u16(48)? as usize;
+        let shstrndx = r.u16(50)? as usize;
+        if shnum > 0 && shentsize < 40 {
+            return Err(ElfError::Unsupported("section header size"));
+        }
+
+        let headers = (0..shnum)
+            .map(|i| {
+                let at = shoff + i * shentsize;
+                Ok(SectionHeader {
+                    name: r.u32(at)?,
+                    kind: r.u32(at + 4)?,
+                    flags: r.u32(at + 8)?,
+                    addr: r.u32(at + 12)?,
+                    offset: r.u32(at + 16)?,
+                    size: r.u32(at + 20)?,
+                    link: r.u32(at + 24)?,
+                })
+            })
+            .collect::<Result<Vec<_>, ElfError>>()?;
+        let names = headers.get(shstrndx).map(|h| h.offset as usize);
+        let section_name = |h: &SectionHeader| match names {
+            Some(table) => r.str(table, h.name),
+            None => Ok(String::new()),
+        };
+
+        let mut sections = Vec::new();
+        for h in headers.iter().filter(|h| h.flags & SHF_ALLOC != 0 && h.size > 0) {
+            let name = section_name(h)?;
+            if h.addr as u64 + h.size as u64 > 1 << 24 {
+                return Err(ElfError::OutOfRange(name));
+            }
+            let data = if h.kind == SHT_NOBITS {
+                vec![0; h.size as usize]
+            } else {
+                r.bytes(h.offset as usize, h.size as usize)?.to_vec()
+            };
+            sections.push(ElfSection { name, addr: h.addr, data });
+        }
+
+        let mut symbols = Vec::new();
+        for h in headers.iter().filter(|h| h.kind == SHT_SYMTAB) {
+            let strings = headers.get(h.link as usize).ok_or(ElfError::Truncated)?.offset as usize;
+            // Entry 0 is the reserved null symbol
+            for i in 1..h.size as usize / 16 {
+                let at = h.offset as usize + i * 16;
+                let kind = r.u8(at + 12)? & 0xF;
+                let shndx = r.u16(at + 14)?;
+                let defined = shndx != SHN_UNDEF && (shndx < SHN_LORESERVE || shndx == SHN_ABS);
+                if !defined || kind == STT_SECTION || kind == STT_FILE {
+                    continue;
+                }
+                let name = r.str(strings, r.u32(at)?)?;
+                if !name.is_empty() {
+                    symbols.push(Symbol { name, addr: r.u32(at + 4)? & 0xFFFFFF, size: r.u32(at + 8)? });
+                }
+            }
+        }
+
+        Ok(Self { entry: entry & 0xFFFFFF, sections, symbols })
+    }
+}
+
+/// Build a minimal ELF with one `.text` section at `addr` holding `code`, a
+/// `.bss` of `bss_size` bytes after it, and a symbol per `(name, addr, size)`
+#[cfg(test)]
+pub(crate) fn test_elf(addr: u32, code: &[u8], bss_size: u32, symbols: &[(&str, u32, u32)]) -> Vec<u8> {
+    fn push_u16(out: &mut Vec<u8>, v: u16) {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    fn push_u32(out: &mut Vec<u8>, v: u32) {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let shstrtab = b"\0.text\0.bss\0.shstrtab\0.strtab\0.symtab\0";
+    let mut strtab = vec![0u8];
+    let mut symtab = vec![0u8; 16];
+    for &(name, value, size) in symbols {
+        let name_off = strtab.len() as u32;
+        strtab.extend_from_slice(name.as_bytes());
+        strtab.push(0);
+        push_u32(&mut symtab, name_off);
+        push_u32(&mut symtab, value);
+        push_u32(&mut symtab, size);
+        symtab.extend_from_slice(&[0x12, 0]); // global function
+        push_u16(&mut symtab, 1);
+    }
+
+    let text_off = 52u32;
+    let shstr_off = text_off + code.len() as u32;
+    let str_off = shstr_off + shstrtab.len() as u32;
+    let sym_off = str_off + strtab.len() as u32;
+    let sh_off = sym_off + symtab.len() as u32;
+
+    let mut out = b"\x7FELF\x01\x01\x01".to_vec();
+    out.resize(16, 0);
+    push_u16(&mut out, 2); // ET_EXEC
+    push_u16(&mut out, EM_Z80);
+    push_u32(&mut out, 1);
+    push_u32(&mut out, addr);
+    push_u32(&mut out, 0);
+    push_u32(&mut out, sh_off);
+    push_u32(&mut out, 0);
+    for v in [52, 0, 0, 40, 6, 3] {
+        push_u16(&mut out, v);
+    }
+    out.extend_from_slice(code);
+    out.extend_from_slice(shstrtab);
+    out.extend_from_slice(&strtab);
+    out.extend_from_slice(&symtab);
+
+    // name, type, flags, addr, offset, size, link
+    let sections = [
+        [0, 0, 0, 0, 0, 0, 0],
+        [1, 1, 0x6, addr, text_off, code.len() as u32, 0],
+        [7, SHT_NOBITS, 0x3, addr + code.len() as u32, sh_off, bss_size, 0],
+        [12, 3, 0, 0, shstr_off, shstrtab.len() as u32, 0],
+        [22, 3, 0, 0, str_off, strtab.len() as u32, 0],
+        [30, SHT_SYMTAB, 0, 0, sym_off, symtab.len() as u32, 4],
+    ];
+    for [name, kind, flags, addr, offset, size, link] in sections {
+        for v in [name, kind, flags, addr, offset, size, link, 0, 1, 0] {
+            push_u32(&mut out, v);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sections_and_symbols() {
+        let elf = test_elf(0xD1A881, &[0x3E, 0x42, 0x18, 0xFE], 8, &[("_start", 0xD1A881, 0), ("loop", 0xD1A883, 2)]);
+        let image = ElfImage::parse(&elf).unwrap();
+        assert_eq!(image.entry, 0xD1A881);
+        assert_eq!(image.sections.len(), 2);
+        assert_eq!(image.sections[0], ElfSection { name: ".text".into(), addr: 0xD1A881, data: vec![0x3E, 0x42, 0x18, 0xFE] });
+        assert_eq!(image.sections[1], ElfSection { name: ".bss".into(), addr: 0xD1A885, data: vec![0; 8] });
+        assert_eq!(image.symbols[1], Symbol { name: "loop".into(), addr: 0xD1A883, size: 2 });
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(ElfImage::parse(b"MZ").unwrap_err(), ElfError::BadMagic);
+        let mut elf = test_elf(0xD1A881, &[0xC9], 0, &[]);
+        assert_eq!(ElfImage::parse(&elf[..100]).unwrap_err(), ElfError::Truncated);
+        elf[18] = 3; // EM_386
+        assert!(matches!(ElfImage::parse(&elf), Err(ElfError::Unsupported(_))));
+        let elf = test_elf(0xFFFFFF, &[0xC9, 0xC9], 0, &[]);
+        assert_eq!(ElfImage::parse(&elf).unwrap_err(), ElfError::OutOfRange(".text".into()));
+    }
+}
@@ -2,15 +2,58 @@
 //!
 //! Coordinates the CPU, bus, and peripherals to run the TI-84 Plus CE.
 
-use crate::bus::{Bus, IoRecord};
+use crate::bus::{Bus, IoRecord, PortLog, UnimplAccess, UnknownAccessReport};
 use crate::cpu::{Cpu, InterruptMode};
-use crate::peripherals::rtc::LATCH_TICK_OFFSET;
+use crate::diagnostics::{self, DiagnosticTrigger, IrqLog, IrqLogEntry};
+use crate::fnv::Fnv1a;
+use crate::peripherals::lcd::LcdCompare;
+use crate::peripherals::rtc::{HostClockSync, EPOCH_UNIX_SECS, LATCH_TICK_OFFSET};
+use crate::peripherals::{ControlPorts, KeypadController, PanelStub, Sha256Controller, UsbController, WatchdogController};
+use crate::logging::{LogLevel, LogLevels, LogSubsystem};
+use crate::os_patch::{self, BootPatch};
+use crate::profile::{ProfileEntry, ProfileSection, Profiler};
 use crate::scheduler::{EventId, Scheduler};
+use crate::serial::{SerialAccessoryConfig, SerialPeer};
+use crate::color_profile::{ColorProfile, ColorTransform};
+use crate::cheats::{Cheat, CheatManager, CheatPoint};
+use crate::governor::{AccuracyMode, Governor, GovernorConfig, GovernorDecision};
+use crate::throttle::{SpeedMode, Throttle};
+use crate::flash_persist::{FlashPersistence, FlashSink, PersistPolicy};
+use crate::memory::FLASH_DIRTY_SECTOR_SIZE;
+use crate::exam_mode::{self, ExamFlag, ExamMode};
+use crate::frame_hash::{self, FrameHash, HashRect};
+use crate::chrome_trace::{self, ChromeTraceConfig, ChromeTracer, TraceKind};
+use crate::trace_record::{TraceConfig, Tracer};
+#[cfg(feature = "recording")]
+use crate::recorder::{self, Recorder};
+#[cfg(feature = "trace")]
+use crate::trace_record::{self, TraceRecord};
+use crate::cpu_usage::{CpuUsage, FrameUsage};
+use crate::latency::{InputLatency, LatencyStats, LatencyTracker};
+use crate::rewind::{RewindBuffer, RewindConfig};
+use crate::replay::{self, InputRecorder, ReplayPlayer, RunPoint};
+use crate::link::{self, LinkPort, LinkRequest};
+use crate::key::{self, Key};
+use crate::ti_file::TiFile;
+use crate::skin::Skin;
+use crate::elf::{self, ElfImage};
+use crate::boot_stub;
+#[cfg(feature = "debugger")]
+use crate::debug::{self, DebugEvent, DebugEventKind, Debugger, Register};
+use crate::symbols::SymbolTable;
+use crate::eval::{self, EvalError, EvalResult};
+use crate::vat;
+use crate::cemu_import::{self, CemuImportError};
+use crate::rom_info::{RomError, RomInfo};
+use crate::sandbox::{self, GuardKind, GuardRegion, SandboxViolation};
+use crate::runaway::{RunawayCallback, RunawayConfig, RunawayDetector, RunawayReport};
 use std::os::raw::c_char;
+use std::cell::Cell;
+use std::collections::VecDeque;
 use std::ptr;
-use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicPtr, Ordering};
 
-/// Zero-cost logging macro — compiles to nothing in WASM builds.
+/// Zero-cost logging macro — compiles to dead code in WASM builds.
 /// Use this instead of `log_event(&format!(...))` to avoid format string
 /// allocation overhead in WASM where logging is a no-op.
 #[cfg(not(target_arch = "wasm32"))]
@@ -22,64 +65,73 @@ macro_rules! log_evt {
 
 #[cfg(target_arch = "wasm32")]
 macro_rules! log_evt {
-    ($($arg:tt)*) => { /* no-op in WASM */ };
-}
-
-pub(crate) use log_evt;
-
-/// Instruction trace flag - when enabled, logs every instruction
-static INST_TRACE_ENABLED: AtomicBool = AtomicBool::new(false);
-/// Number of instructions traced (resets when trace is enabled)
-static INST_TRACE_COUNT: AtomicU32 = AtomicU32::new(0);
-/// Maximum instructions to trace before auto-disable (0 = unlimited)
-static INST_TRACE_LIMIT: AtomicU32 = AtomicU32::new(0);
-/// Armed trace - will enable when CPU wakes from HALT
-static INST_TRACE_ARMED: AtomicBool = AtomicBool::new(false);
-/// Limit for armed trace
-static INST_TRACE_ARMED_LIMIT: AtomicU32 = AtomicU32::new(0);
-
-/// Enable instruction tracing (logs every instruction to log callback)
-#[allow(dead_code)]
-pub fn enable_inst_trace(limit: u32) {
-    INST_TRACE_COUNT.store(0, Ordering::SeqCst);
-    INST_TRACE_LIMIT.store(limit, Ordering::SeqCst);
-    INST_TRACE_ENABLED.store(true, Ordering::SeqCst);
-    log_evt!("INST_TRACE: enabled, limit={}", limit);
+    // Never formats, but still an expression that uses its arguments
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
 }
 
-/// Arm instruction tracing to start when CPU wakes from HALT
-/// This avoids tracing HALT loops - only traces after wake event
-#[allow(dead_code)]
-pub fn arm_inst_trace_on_wake(limit: u32) {
-    INST_TRACE_ARMED_LIMIT.store(limit, Ordering::SeqCst);
-    INST_TRACE_ARMED.store(true, Ordering::SeqCst);
-    log_evt!("INST_TRACE: armed for wake, limit={}", limit);
+/// Subsystem-tagged logging: dropped before formatting when `$level` is above
+/// the running instance's configured level for `$sub`.
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! log_sub {
+    ($sub:ident, $level:ident, $($arg:tt)*) => {
+        if $crate::emu::log_enabled(
+            $crate::logging::LogSubsystem::$sub,
+            $crate::logging::LogLevel::$level,
+        ) {
+            $crate::emu::log_event(&format!($($arg)*))
+        }
+    };
 }
 
-/// Disable instruction tracing
-#[allow(dead_code)]
-pub fn disable_inst_trace() {
-    INST_TRACE_ENABLED.store(false, Ordering::SeqCst);
-    INST_TRACE_ARMED.store(false, Ordering::SeqCst);
-    log_evt!("INST_TRACE: disabled");
+#[cfg(target_arch = "wasm32")]
+macro_rules! log_sub {
+    ($sub:ident, $level:ident, $($arg:tt)*) => {
+        if false && $crate::emu::log_enabled(
+            $crate::logging::LogSubsystem::$sub,
+            $crate::logging::LogLevel::$level,
+        ) {
+            let _ = format_args!($($arg)*);
+        }
+    };
 }
 
-/// Check if instruction tracing is enabled
-#[allow(dead_code)]
-pub fn is_inst_trace_enabled() -> bool {
-    INST_TRACE_ENABLED.load(Ordering::Relaxed)
+pub(crate) use log_sub;
+
+/// Per-instance instruction trace state
+#[derive(Debug, Clone, Default)]
+struct InstTrace {
+    /// Logs every instruction when set
+    enabled: bool,
+    /// Number of instructions traced (resets when trace is enabled)
+    count: u32,
+    /// Maximum instructions to trace before auto-disable (0 = unlimited)
+    limit: u32,
+    /// Armed trace - will enable when CPU wakes from HALT
+    armed: bool,
+    /// Limit for armed trace
+    armed_limit: u32,
 }
 
-/// Check and trigger armed trace on wake
-fn check_armed_trace_on_wake(was_halted: bool, is_halted: bool) {
-    // If we were halted and now we're not, trigger the armed trace
-    if was_halted && !is_halted && INST_TRACE_ARMED.load(Ordering::SeqCst) {
-        let limit = INST_TRACE_ARMED_LIMIT.load(Ordering::SeqCst);
-        INST_TRACE_ARMED.store(false, Ordering::SeqCst);
-        INST_TRACE_COUNT.store(0, Ordering::SeqCst);
-        INST_TRACE_LIMIT.store(limit, Ordering::SeqCst);
-        INST_TRACE_ENABLED.store(true, Ordering::SeqCst);
-        log_evt!("INST_TRACE: triggered on wake, limit={}", limit);
+impl InstTrace {
+    fn enable(&mut self, limit: u32) {
+        self.count = 0;
+        self.limit = limit;
+        // The per-instruction trace hook is compiled out without the feature
+        self.enabled = cfg!(feature = "trace");
+    }
+
+    /// Check and trigger armed trace on wake
+    fn check_armed_on_wake(&mut self, was_halted: bool, is_halted: bool) {
+        // If we were halted and now we're not, trigger the armed trace
+        if was_halted && !is_halted && self.armed {
+            self.armed = false;
+            self.enable(self.armed_limit);
+            log_sub!(Cpu, Info, "INST_TRACE: triggered on wake, limit={}", self.armed_limit);
+        }
     }
 }
 
@@ -134,6 +186,19 @@ const BOOT_COMPLETE_CYCLES: u64 = 65_000_000;
 const APD_FLAGS_ADDR: u32 = 0xD00088;
 const APD_ABLE_BIT: u8 = 2;
 
+/// OS homescreen text buffer (`textShadow`): one character code per cell,
+/// rows of `HOMESCREEN_COLS`
+const TEXT_SHADOW_ADDR: u32 = 0xD006C0;
+const HOMESCREEN_COLS: usize = 26;
+const HOMESCREEN_ROWS: usize = 10;
+
+/// How long `type_text` holds each key, and then waits after releasing it
+/// (the OS scans the keypad and debounces well within this)
+const TYPE_KEY_HOLD_MS: u32 = 50;
+
+/// Number of unread LCD base-address changes kept before the oldest is dropped
+const LCD_BASE_CHANGE_QUEUE_SIZE: usize = 64;
+
 /// Number of entries in the PC/opcode history ring buffer
 const HISTORY_SIZE: usize = 64;
 
@@ -203,20 +268,71 @@ impl ExecutionHistory {
     }
 }
 
+/// Host log callback, receives one NUL-terminated message per call
+pub type LogCallback = extern "C" fn(*const c_char);
+
+/// Frame-ready callback: the rendered ARGB8888 framebuffer and its stride
+/// in pixels, called each time the LCD controller completes a frame
+pub type FrameCallback = Box<dyn FnMut(&[u32], usize) + Send>;
+
+/// Process-wide default log callback, used when an instance has none
 static LOG_CALLBACK: AtomicPtr<std::ffi::c_void> = AtomicPtr::new(ptr::null_mut());
 
-pub(crate) fn set_log_callback(cb: Option<extern "C" fn(*const c_char)>) {
+/// Log routing of the Emu currently executing on a thread
+#[derive(Clone, Copy)]
+struct LogConfig {
+    // WASM builds never call back
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    callback: Option<LogCallback>,
+    levels: LogLevels,
+}
+
+thread_local! {
+    /// Log config of the Emu currently executing on this thread (see `LogScope`)
+    static ACTIVE_LOG: Cell<LogConfig> = const {
+        Cell::new(LogConfig { callback: None, levels: LogLevels::new() })
+    };
+}
+
+pub(crate) fn set_log_callback(cb: Option<LogCallback>) {
     let ptr = cb.map(|f| f as *mut std::ffi::c_void).unwrap_or(ptr::null_mut());
     LOG_CALLBACK.store(ptr, Ordering::SeqCst);
 }
 
+/// Routes `log_event` to an instance's callback and levels for the duration of
+/// an Emu call. The previous config is restored on drop, so instances on
+/// different threads (or nested on one thread) never see each other's logs.
+struct LogScope {
+    prev: LogConfig,
+}
+
+impl LogScope {
+    fn enter(config: LogConfig) -> Self {
+        Self { prev: ACTIVE_LOG.with(|c| c.replace(config)) }
+    }
+}
+
+impl Drop for LogScope {
+    fn drop(&mut self) {
+        ACTIVE_LOG.with(|c| c.set(self.prev));
+    }
+}
+
+/// Check if the running instance logs `level` messages from `sub`
+#[inline]
+pub fn log_enabled(sub: LogSubsystem, level: LogLevel) -> bool {
+    ACTIVE_LOG.with(|c| c.get().levels.enabled(sub, level))
+}
+
 /// Public logging function for use by other modules.
 /// In WASM builds this is a no-op (callback is never set).
 #[cfg(not(target_arch = "wasm32"))]
 pub fn log_event(message: &str) {
-    let cb_ptr = LOG_CALLBACK.load(Ordering::SeqCst);
-    if !cb_ptr.is_null() {
-        let cb: extern "C" fn(*const c_char) = unsafe { std::mem::transmute(cb_ptr) };
+    let cb = ACTIVE_LOG.with(|c| c.get().callback).or_else(|| {
+        let cb_ptr = LOG_CALLBACK.load(Ordering::SeqCst);
+        (!cb_ptr.is_null()).then(|| unsafe { std::mem::transmute::<*mut std::ffi::c_void, LogCallback>(cb_ptr) })
+    });
+    if let Some(cb) = cb {
         if let Ok(cstr) = std::ffi::CString::new(message) {
             cb(cstr.as_ptr());
         }
@@ -249,6 +365,73 @@ pub enum StopReason {
     // TODO: Wire up BusFault when Bus reports invalid memory access (Milestone 5+)
     /// Bus fault (invalid memory access)
     BusFault(u32),
+    /// Breakpoint, watchpoint or step (see `Emu::take_debug_event`)
+    Debugger,
+}
+
+/// Host wall-clock time in Unix seconds, where the host has a clock
+#[cfg(not(target_arch = "wasm32"))]
+fn host_unix_secs() -> Option<i64> {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(now.as_secs() as i64)
+}
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn host_unix_secs() -> Option<i64> {
+    Some((js_sys::Date::now() / 1000.0) as i64)
+}
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+fn host_unix_secs() -> Option<i64> {
+    None
+}
+
+/// SplitMix64 step, used to expand the deterministic mode seed
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Kind of reset, matching the different behaviors on hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Reset button: CPU and peripherals reset, RAM contents preserved
+    Warm,
+    /// Reset with RAM forced to zero (the OS boots with "RAM Cleared")
+    RamClear,
+    /// Full power-on reset of all peripherals and RAM; requires the ON key to start
+    PowerCycle,
+}
+
+/// Why the calculator last restarted (`Emu::last_reset_cause`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetCause {
+    /// Fresh instance or loaded ROM
+    PowerOn,
+    /// Reset requested by the host (`Emu::reset_with`)
+    Host(ResetKind),
+    /// Watchdog expired with reset enabled
+    Watchdog,
+    /// Memory protection violation: a write by unprivileged code to the
+    /// protected range or flash (status bit 1), or to the stack limit (bit 0).
+    /// `status` is control port 0x3D, `pc` the offending code.
+    Protection { status: u8, addr: u32, pc: u32 },
+}
+
+impl ResetCause {
+    /// Numeric code for the C and wasm APIs: 0-2 host reset by kind (as
+    /// `emu_reset_with`), 3 power-on, 4 watchdog, 5 protection violation
+    pub fn code(self) -> i32 {
+        match self {
+            ResetCause::Host(ResetKind::Warm) => 0,
+            ResetCause::Host(ResetKind::RamClear) => 1,
+            ResetCause::Host(ResetKind::PowerCycle) => 2,
+            ResetCause::PowerOn => 3,
+            ResetCause::Watchdog => 4,
+            ResetCause::Protection { .. } => 5,
+        }
+    }
 }
 
 /// Information about a single instruction step (for trace comparison)
@@ -312,10 +495,22 @@ pub struct Emu {
 
     /// Calculator is powered on (ON key was pressed)
     /// CPU won't execute until this is true
-    powered_on: bool,
+    pub(crate) powered_on: bool,
+
+    /// Host pause: run_cycles does nothing until resumed
+    paused: bool,
 
     /// Execution history for crash diagnostics
     history: ExecutionHistory,
+    /// Recently serviced interrupts for crash diagnostics
+    irq_log: IrqLog,
+    /// Sink for automatically written diagnostic bundles
+    diagnostic_sink: Option<Box<dyn std::io::Write + Send>>,
+    /// Whether the next internal error/protection violation writes a bundle
+    diagnostic_armed: bool,
+
+    /// Answers bytes the calculator sends to the serial accessory
+    serial_peer: Option<Box<dyn SerialPeer + Send>>,
 
     /// Last stop reason
     last_stop: StopReason,
@@ -335,11 +530,118 @@ pub struct Emu {
     breakpoint_pc: Option<u32>,
     /// Whether a breakpoint was hit during the last run_cycles call
     breakpoint_hit: bool,
+    /// Breakpoints and step targets (watchpoints are on the bus)
+    #[cfg(feature = "debugger")]
+    debugger: Debugger,
+    /// Why the last run stopped for the debugger, until taken
+    #[cfg(feature = "debugger")]
+    debug_event: Option<DebugEvent>,
 
     /// NMI debug logging (for WASM where log_evt is no-op)
     nmi_log_count: u32,
     nmi_log_pc: u32,
     nmi_log_sp: u32,
+
+    /// Interrupts serviced, per interrupt controller source bit
+    irq_source_counts: [u64; 32],
+    /// Frames rendered by render_frame()
+    frames_rendered: u64,
+    /// Frames completed by the LCD controller since reset
+    lcd_frames: u64,
+    /// `total_cycles` when the LCD controller last completed a frame
+    last_lcd_frame_cycle: Option<u64>,
+    /// run_cycles returns as soon as the LCD controller completes a frame
+    stop_on_frame: bool,
+    /// Renders and hands over each completed LCD frame
+    frame_callback: Option<FrameCallback>,
+    /// Color transform applied to presented frames
+    color_transform: ColorTransform,
+    /// Frame handed to frontends: the framebuffer with the color profile applied
+    presented: Vec<u32>,
+    /// `frames_rendered` when `presented` was last updated (None = stale)
+    presented_frame: Option<u64>,
+    /// Region hashed after each rendered frame (None = hashing off)
+    frame_hash_rect: Option<HashRect>,
+    /// Unread frame hashes, oldest first
+    frame_hashes: VecDeque<FrameHash>,
+    /// Memory pokes re-applied every frame
+    cheats: CheatManager,
+    /// Rendered frames present_frame() skips between presented frames
+    frame_skip: u32,
+    /// Rendered frames skipped since the last presented frame
+    frames_skipped: u32,
+    /// Run loop fidelity/speed trade-off
+    accuracy: AccuracyMode,
+    /// Adaptive performance governor (None = knobs are set manually)
+    governor: Option<Governor>,
+    /// `total_cycles` at the last governor report
+    governor_last_cycles: u64,
+    /// Real-time pacing for `run_paced`
+    throttle: Throttle,
+    /// Background flash persistence (None = only explicit saves)
+    flash_persistence: Option<FlashPersistence>,
+    /// Unread LCD base-address changes, oldest first
+    lcd_base_changes: VecDeque<LcdBaseChange>,
+    /// Runaway-execution watchdog (None = disabled)
+    runaway: Option<RunawayDetector>,
+    /// Host callback for runaway reports
+    runaway_callback: Option<RunawayCallback>,
+    /// Most recent runaway report
+    last_runaway: Option<RunawayReport>,
+    /// Chrome trace-event recorder (None = not tracing)
+    chrome_trace: Option<ChromeTracer>,
+    /// Structured instruction trace, if one is running
+    tracer: Option<Tracer>,
+    /// GIF/APNG screen recording, if one is running
+    #[cfg(feature = "recording")]
+    recorder: Option<Recorder>,
+    /// Per-frame halted/executing split (None = off)
+    cpu_usage: Option<CpuUsage>,
+    latency: Option<LatencyTracker>,
+    /// Key events captured for replay, if recording input
+    input_recorder: Option<InputRecorder>,
+    /// Replay being fed into the run loop
+    replay: Option<ReplayPlayer>,
+    /// Keys pressed by `tap_key`, with the cycles left until each is released
+    key_releases: Vec<(Key, u64)>,
+    /// Calculator end of the virtual link cable
+    link: LinkPort,
+    /// Rewind history (None = off)
+    rewind: Option<RewindBuffer>,
+    /// What the loaded ROM image contains (None before a ROM is loaded)
+    rom_info: Option<RomInfo>,
+    /// Flash holds the built-in boot stub rather than a TI ROM
+    stub_boot: bool,
+    /// Keeps the RTC on host wall-clock time (None = free-running)
+    rtc_host_sync: Option<HostClockSync>,
+    /// Debugger symbols (from `load_elf` or the host)
+    symbols: SymbolTable,
+    /// Exam mode (Press-to-Test) state
+    exam: ExamMode,
+    /// Number of reset() calls (including the one done by load_rom)
+    reset_count: u64,
+    /// Why the calculator last restarted
+    last_reset_cause: ResetCause,
+    /// Protection violation whose NMI has not yet restarted the calculator
+    pending_violation: Option<ResetCause>,
+
+    /// Optional host-time profiler (disabled by default)
+    profiler: Profiler,
+
+    /// Deterministic mode seed (None = normal mode)
+    deterministic_seed: Option<u64>,
+
+    /// Patches applied to flash on every ROM load
+    os_patches: Vec<BootPatch>,
+    /// Outcome of applying `os_patches` on the last ROM load
+    os_patch_result: Option<Result<usize, os_patch::PatchError>>,
+
+    /// Instruction trace state
+    inst_trace: InstTrace,
+    /// Instance log callback (None = process-wide default)
+    log_callback: Option<LogCallback>,
+    /// Per-subsystem log verbosity
+    log_levels: LogLevels,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -362,6 +664,38 @@ pub struct LcdSnapshot {
     pub compare_state: u8,
 }
 
+/// The LCD started scanning out from a different frame buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LcdBaseChange {
+    /// `total_cycles()` when the new base was latched
+    pub cycle: u64,
+    /// `lcd_frame_count()` at that point (the new buffer's frame index)
+    pub frame: u64,
+    /// Previous scanout base address
+    pub old_base: u32,
+    /// New scanout base address
+    pub new_base: u32,
+}
+
+/// Lifetime execution counters, for dashboards and detecting silent stalls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExecCounters {
+    /// Instructions executed
+    pub instructions: u64,
+    /// Maskable interrupts serviced
+    pub interrupts: u64,
+    /// Non-maskable interrupts serviced
+    pub nmis: u64,
+    /// Maskable interrupts serviced per source bit (see `interrupt::sources`).
+    /// A source is counted if it was pending and enabled when the CPU took the interrupt.
+    pub interrupts_by_source: [u64; 32],
+    /// Frames rendered
+    pub frames: u64,
+    /// Emulator resets
+    pub resets: u64,
+}
+
 impl Emu {
     /// Create a new emulator instance
     pub fn new() -> Self {
@@ -372,7 +706,12 @@ impl Emu {
             framebuffer: vec![0xFF000000; SCREEN_WIDTH * SCREEN_HEIGHT],
             rom_loaded: false,
             powered_on: false,
+            paused: false,
             history: ExecutionHistory::new(),
+            irq_log: IrqLog::new(),
+            diagnostic_sink: None,
+            diagnostic_armed: false,
+            serial_peer: None,
             last_stop: StopReason::CyclesComplete,
             total_cycles: 0,
             halt_logged: false,
@@ -381,22 +720,192 @@ impl Emu {
             frame_count: 0,
             breakpoint_pc: None,
             breakpoint_hit: false,
+            #[cfg(feature = "debugger")]
+            debugger: Debugger::default(),
+            #[cfg(feature = "debugger")]
+            debug_event: None,
             nmi_log_count: 0,
             nmi_log_pc: 0,
             nmi_log_sp: 0,
+            irq_source_counts: [0; 32],
+            frames_rendered: 0,
+            lcd_frames: 0,
+            last_lcd_frame_cycle: None,
+            stop_on_frame: false,
+            frame_callback: None,
+            color_transform: ColorTransform::new(ColorProfile::Ideal),
+            presented: vec![0xFF000000; SCREEN_WIDTH * SCREEN_HEIGHT],
+            presented_frame: None,
+            frame_hash_rect: None,
+            frame_hashes: VecDeque::new(),
+            cheats: CheatManager::new(),
+            frame_skip: 0,
+            frames_skipped: 0,
+            accuracy: AccuracyMode::Full,
+            governor: None,
+            governor_last_cycles: 0,
+            throttle: Throttle::default(),
+            flash_persistence: None,
+            lcd_base_changes: VecDeque::new(),
+            runaway: None,
+            runaway_callback: None,
+            last_runaway: None,
+            chrome_trace: None,
+            tracer: None,
+            #[cfg(feature = "recording")]
+            recorder: None,
+            cpu_usage: None,
+            latency: None,
+            input_recorder: None,
+            replay: None,
+            key_releases: Vec::new(),
+            link: LinkPort::default(),
+            rewind: None,
+            rom_info: None,
+            stub_boot: false,
+            rtc_host_sync: None,
+            symbols: SymbolTable::new(),
+            exam: ExamMode::new(),
+            reset_count: 0,
+            last_reset_cause: ResetCause::PowerOn,
+            pending_violation: None,
+            profiler: Profiler::new(),
+            deterministic_seed: None,
+            os_patches: Vec::new(),
+            os_patch_result: None,
+            inst_trace: InstTrace::default(),
+            log_callback: None,
+            log_levels: LogLevels::new(),
+        }
+    }
+
+    /// Create an emulator that boots the built-in stub instead of a TI ROM
+    /// (see `boot_stub`), for running raw eZ80 code loaded with
+    /// `load_payload`. Without a payload the stub calls into empty RAM.
+    pub fn new_with_stub_boot() -> Self {
+        let mut emu = Self::new();
+        emu.load_rom(&boot_stub::image(boot_stub::DEFAULT_ENTRY))
+            .expect("boot stub fits in flash");
+        emu.stub_boot = true;
+        emu
+    }
+
+    /// Load a raw eZ80 binary at `addr` and restart the boot stub, which
+    /// calls it in ADL mode with interrupts disabled. The machine is
+    /// power-cycled first, so RAM holds only the payload.
+    /// `payload_returned` reports when it returns.
+    ///
+    /// Error codes: -10 = not in stub boot mode (`new_with_stub_boot`),
+    /// -3 = payload runs past the end of the address space.
+    pub fn load_payload(&mut self, addr: u32, data: &[u8]) -> Result<(), i32> {
+        let _log = self.log_scope();
+        if !self.stub_boot {
+            return Err(-10);
+        }
+        if addr as usize + data.len() > 0x1000000 {
+            return Err(-3);
+        }
+        for (i, &byte) in addr.to_le_bytes()[..3].iter().enumerate() {
+            self.bus.flash.write_direct(boot_stub::ENTRY_PTR + i as u32, byte);
+        }
+        self.reset();
+        for (a, &byte) in (addr..).zip(data) {
+            self.bus.poke_byte(a, byte);
         }
+        self.bus.flash.take_dirty_sectors();
+        log_sub!(Flash, Info, "LOAD_PAYLOAD addr=0x{:06X} size={}", addr, data.len());
+        self.powered_on = true;
+        Ok(())
+    }
+
+    /// True once a payload started by the boot stub has returned to it
+    pub fn payload_returned(&self) -> bool {
+        self.stub_boot && self.cpu.halted && self.cpu.pc == boot_stub::RETURNED_PC + 1
     }
 
     /// Load ROM data into flash
     pub fn load_rom(&mut self, data: &[u8]) -> Result<(), i32> {
+        let _log = self.log_scope();
         if data.is_empty() {
             return Err(-2); // Empty ROM
         }
 
         self.bus.load_rom(data).map_err(|_| -3)?; // -3 = ROM too large
         self.rom_loaded = true;
-        log_evt!("ROM_LOADED bytes={}", data.len());
-        self.reset();
+        self.rom_info = Some(RomInfo::describe(data));
+        self.stub_boot = false;
+        // States from another ROM cannot be loaded
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+        log_sub!(Flash, Info, "ROM_LOADED bytes={}", data.len());
+        self.apply_load_patches();
+        self.reset_for(ResetKind::PowerCycle, ResetCause::PowerOn);
+        Ok(())
+    }
+
+    /// Load a ROM dump after checking that it is one: the full 4MB with boot
+    /// code, not a truncated dump or a CEmu state image. Returns what it
+    /// contains; on error the current ROM is left untouched.
+    /// Use `load_rom` for partial images (tests, hand-built ROMs).
+    pub fn load_rom_checked(&mut self, data: &[u8]) -> Result<RomInfo, RomError> {
+        let info = RomInfo::parse(data)?;
+        // parse() checked everything load_rom can reject
+        self.load_rom(data).map_err(|_| RomError::TooLarge(data.len()))?;
+        log_sub!(
+            Flash,
+            Info,
+            "ROM_INFO boot={} os={}",
+            info.boot_version.as_deref().unwrap_or("?"),
+            info.os_version.as_deref().unwrap_or(if info.os_present { "?" } else { "none" })
+        );
+        Ok(info)
+    }
+
+    /// What the loaded ROM image contains: versions and boot code hash.
+    /// Reflects the image as loaded, before any patches.
+    pub fn rom_info(&self) -> Option<&RomInfo> {
+        self.rom_info.as_ref()
+    }
+
+    /// Load ROM by memory-mapping a file read-only instead of copying it to the heap.
+    /// Flash writes go to a copy-on-write overlay; the file is never modified.
+    /// Returns -3 if the file is too large, -4 if it cannot be opened or mapped.
+    #[cfg(feature = "mmap")]
+    pub fn load_rom_mapped(&mut self, path: &std::path::Path) -> Result<(), i32> {
+        let _log = self.log_scope();
+        use crate::memory::FlashError;
+
+        self.bus.load_rom_mapped(path).map_err(|e| match e {
+            FlashError::RomTooLarge => -3,
+            _ => -4, // Open/map failed
+        })?;
+        self.rom_loaded = true;
+        self.rom_info = Some(RomInfo::describe(&self.bus.flash.data()));
+        self.stub_boot = false;
+        log_sub!(Flash, Info, "ROM_MAPPED path={}", path.display());
+        self.apply_load_patches();
+        self.reset_for(ResetKind::PowerCycle, ResetCause::PowerOn);
+        Ok(())
+    }
+
+    /// Replace the ROM without recreating the emulator.
+    ///
+    /// Callbacks, breakpoints, tracing, deterministic mode, OS bypass settings,
+    /// and attached accessories are kept. The machine is power-cycled (RAM
+    /// cleared, since it belongs to the old OS) and powered back on if it was
+    /// running. Accesses recorded against the old ROM are dropped. On error the
+    /// current ROM and machine state are left untouched.
+    pub fn swap_rom(&mut self, data: &[u8]) -> Result<(), i32> {
+        let _log = self.log_scope();
+        let was_powered_on = self.powered_on;
+        self.load_rom(data)?;
+        self.bus.unimpl.clear();
+        self.breakpoint_hit = false;
+        log_sub!(Flash, Info, "ROM_SWAPPED bytes={} powered_on={}", data.len(), was_powered_on);
+        if was_powered_on {
+            self.power_on();
+        }
         Ok(())
     }
 
@@ -408,6 +917,7 @@ impl Emu {
     ///
     /// Returns Ok(count) with the number of entries injected, or an error code.
     pub fn send_file(&mut self, file_data: &[u8]) -> Result<usize, i32> {
+        let _log = self.log_scope();
         use crate::ti_file::TiFile;
 
         if !self.rom_loaded {
@@ -418,7 +928,7 @@ impl Emu {
         }
 
         let ti_file = TiFile::parse(file_data).map_err(|e| {
-            log_evt!("SEND_FILE_PARSE_ERROR: {}", e);
+            log_sub!(Flash, Error, "SEND_FILE_PARSE_ERROR: {}", e);
             -11 // Parse error
         })?;
 
@@ -602,7 +1112,7 @@ impl Emu {
             offset += 1;
         }
 
-        log_evt!(
+        log_sub!(Flash, Info,
             "ARCHIVE_INJECT name={} type=0x{:02X} addr=0x{:06X} total={} payload={}",
             entry.name_str(),
             entry.var_type.as_u8(),
@@ -702,6 +1212,7 @@ impl Emu {
     ///
     /// Returns Ok(count) with entries injected, or an error code.
     pub fn send_file_live(&mut self, file_data: &[u8]) -> Result<usize, i32> {
+        let _log = self.log_scope();
         use crate::ti_file::TiFile;
 
         if !self.rom_loaded {
@@ -709,7 +1220,7 @@ impl Emu {
         }
 
         let ti_file = TiFile::parse(file_data).map_err(|e| {
-            log_evt!("SEND_FILE_LIVE_PARSE_ERROR: {}", e);
+            log_sub!(Flash, Error, "SEND_FILE_LIVE_PARSE_ERROR: {}", e);
             -11 // Parse error
         })?;
 
@@ -721,7 +1232,7 @@ impl Emu {
                 entry.name_len(),
                 entry.var_type.as_u8(),
             ) {
-                log_evt!(
+                log_sub!(Flash, Info,
                     "ARCHIVE_INVALIDATE name={} addr=0x{:06X}",
                     entry.name_str(),
                     flag_addr
@@ -734,13 +1245,156 @@ impl Emu {
         }
 
         // Soft reset (preserves flash) + power on
-        log_evt!("SEND_FILE_LIVE: soft reset after injecting {} entries", count);
+        log_sub!(Flash, Info, "SEND_FILE_LIVE: soft reset after injecting {} entries", count);
         self.reset();
         self.power_on();
 
         Ok(count)
     }
 
+    /// Send a .8xp/.8xv/.8xg file whether or not the calculator has booted.
+    ///
+    /// Before `power_on()` this is `send_file()`: the variables go into the
+    /// archive and TI-OS finds them when it boots. Once running it is
+    /// `send_file_live()`, which replaces any archived copies and restarts
+    /// the OS (RAM is cleared) so it picks them up. Variables are always
+    /// archived, whatever the file's archived flag says.
+    ///
+    /// Returns Ok(count) with entries injected, or an error code.
+    pub fn send_variable(&mut self, file_data: &[u8]) -> Result<usize, i32> {
+        if self.powered_on {
+            self.send_file_live(file_data)
+        } else {
+            self.send_file(file_data)
+        }
+    }
+
+    /// Read a variable back out as a .8x* file (with checksum), from RAM or
+    /// the archive. `name` is the variable name's bytes as the OS stores them
+    /// (e.g. `b"HELLO"` for a program or appvar). If names repeat across
+    /// types, the symbol table comes first, then programs and appvars in VAT
+    /// order.
+    ///
+    /// Returns the file bytes, or an error code.
+    /// Error codes: -1 = no such variable (or no VAT yet), -2 = unknown type or too large for a file
+    pub fn receive_variable(&mut self, name: &[u8]) -> Result<Vec<u8>, i32> {
+        use crate::ti_file::{TiFile, TiVarEntry, VarType};
+
+        let read_ptr = |bus: &mut Bus, addr: u32| (0..3).fold(0u32, |acc, i| acc | (bus.peek_byte(addr + i) as u32) << (8 * i));
+        let prog_ptr = read_ptr(&mut self.bus, eval::PROG_PTR_ADDR);
+        let p_temp = read_ptr(&mut self.bus, sandbox::PTEMP_ADDR);
+        let bus = &mut self.bus;
+        let entry = vat::walk(|addr| bus.peek_byte(addr), prog_ptr, p_temp)
+            .into_iter()
+            .find(|entry| entry.name == name && name.len() <= 8)
+            .ok_or(-1)?;
+
+        // A single entry's size fields are 16-bit
+        let len = vat::data_len(|addr| bus.peek_byte(addr), entry.var_type, entry.data_addr)
+            .filter(|&len| len <= 0xFFFF - 17)
+            .ok_or(-2)?;
+        let data = (0..len).map(|i| bus.peek_byte(entry.data_addr + i)).collect();
+        let mut padded = [0u8; 8];
+        padded[..name.len()].copy_from_slice(name);
+        log_sub!(Flash, Info, "RECEIVE_VARIABLE name={} type=0x{:02X} len={} archived={}",
+            String::from_utf8_lossy(name), entry.var_type, len, entry.archived);
+
+        let file = TiFile {
+            entries: vec![TiVarEntry {
+                var_type: VarType::from(entry.var_type),
+                name: padded,
+                version: entry.version,
+                archived: entry.archived,
+                data,
+            }],
+        };
+        Ok(file.to_bytes())
+    }
+
+    /// Write bytes into the virtual link cable, as the host end would
+    /// (see `link`). Variables sent are stored with `send_variable` and
+    /// requested ones read with `receive_variable`; replies are queued for
+    /// `link_recv`.
+    pub fn link_send(&mut self, bytes: &[u8]) {
+        for request in self.link.receive(bytes) {
+            match request {
+                LinkRequest::Store(entry) => {
+                    log_sub!(Flash, Info, "LINK: store {}", entry.name_str());
+                    let ok = self.send_variable(&link::entry_file(entry)).is_ok();
+                    self.link.store_done(ok);
+                }
+                LinkRequest::Fetch(name) => {
+                    log_sub!(Flash, Info, "LINK: request {}", String::from_utf8_lossy(&name));
+                    let file = self.receive_variable(&name).ok().and_then(|data| TiFile::parse(&data).ok());
+                    self.link.reply_variable(file.as_ref().and_then(|file| file.entries.first()));
+                }
+            }
+        }
+    }
+
+    /// Take up to `max` bytes the calculator end has written to the cable
+    pub fn link_recv(&mut self, max: usize) -> Vec<u8> {
+        self.link.take_output(max)
+    }
+
+    /// Rebuild the calculator from CEmu exports: `flash` is CEmu's ROM image
+    /// (the full 4MB flash, OS and archive included) and `ram` optionally its
+    /// RAM dump. The flash becomes the loaded ROM and the machine is reset,
+    /// then RAM is restored as it was, so it can be inspected; booting the
+    /// OS reinitializes it. CEmu `.ce` state images are rejected (see
+    /// `cemu_import`).
+    pub fn import_cemu(&mut self, flash: &[u8], ram: Option<&[u8]>) -> Result<(), CemuImportError> {
+        cemu_import::validate(flash, ram)?;
+        // validate() checked the size, which is all load_rom can reject
+        self.load_rom(flash).map_err(|_| CemuImportError::FlashSize(flash.len()))?;
+        if let Some(ram) = ram {
+            self.bus.ram.load_data(ram);
+        }
+        log_sub!(Flash, Info, "CEMU_IMPORT ram={}", ram.is_some());
+        Ok(())
+    }
+
+    /// Load a linked ez80-clang ELF for bare-metal testing, without TI-OS.
+    ///
+    /// Power-cycles the machine, writes each allocated section at its link
+    /// address (flash sections overlay the loaded ROM, or blank flash if none
+    /// was loaded), and starts the CPU at the entry point in ADL mode with SP
+    /// at `elf::DEFAULT_STACK_TOP`. The ELF's symbols replace the debugger
+    /// symbol table. Nothing else is initialized; the code runs against the
+    /// peripherals in their reset state.
+    ///
+    /// Returns Ok(count) with the number of sections loaded.
+    /// Error codes: -11 = not a valid eZ80 ELF.
+    pub fn load_elf(&mut self, data: &[u8]) -> Result<usize, i32> {
+        let _log = self.log_scope();
+        let image = ElfImage::parse(data).map_err(|e| {
+            log_sub!(Flash, Error, "LOAD_ELF_PARSE_ERROR: {}", e);
+            -11 // Parse error
+        })?;
+
+        if !self.rom_loaded {
+            self.bus.load_rom(&[]).map_err(|_| -3)?;
+            self.rom_loaded = true;
+        }
+        self.reset();
+        for section in &image.sections {
+            log_sub!(Flash, Info, "LOAD_ELF section={} addr=0x{:06X} size={}", section.name, section.addr, section.data.len());
+            for (addr, &byte) in (section.addr..).zip(&section.data) {
+                self.bus.poke_byte(addr, byte);
+            }
+        }
+
+        self.cpu.adl = true;
+        self.cpu.madl = true;
+        self.cpu.pc = image.entry;
+        self.cpu.set_sp_both(elf::DEFAULT_STACK_TOP);
+        // Refill the prefetch reset() took from address 0
+        self.cpu.prefetch = self.bus.peek_byte(image.entry);
+        self.symbols = image.symbols.into_iter().collect();
+        self.powered_on = true;
+        Ok(image.sections.len())
+    }
+
     /// Set serial flash mode
     /// - true: Serial flash (newer TI-84 CE models) - uses cache timing
     /// - false: Parallel flash (older models) - uses constant 10 cycle timing
@@ -753,18 +1407,58 @@ impl Emu {
         self.bus.is_serial_flash()
     }
 
-    /// Reset emulator to initial state
+    /// Reset emulator to initial state (full power cycle, see `ResetKind::PowerCycle`)
     pub fn reset(&mut self) {
-        log_evt!("RESET");
+        self.reset_with(ResetKind::PowerCycle);
+    }
+
+    /// Perform a specific kind of reset
+    pub fn reset_with(&mut self, kind: ResetKind) {
+        self.reset_for(kind, ResetCause::Host(kind));
+    }
+
+    /// Why the calculator last restarted
+    pub fn last_reset_cause(&self) -> ResetCause {
+        self.last_reset_cause
+    }
+
+    fn reset_for(&mut self, kind: ResetKind, cause: ResetCause) {
+        let _log = self.log_scope();
+        log_evt!("RESET kind={:?} cause={:?}", kind, cause);
+        self.reset_count += 1;
+        self.last_reset_cause = cause;
+        self.pending_violation = None;
+        let was_powered_on = self.powered_on;
         self.cpu.reset();
-        self.bus.reset();
+        match kind {
+            ResetKind::Warm => self.bus.reset_keep_ram(),
+            ResetKind::RamClear | ResetKind::PowerCycle => self.bus.reset(),
+        }
+        self.serial_peer_disconnected();
+        if let Some(seed) = self.deterministic_seed {
+            self.apply_deterministic_seed(seed, kind == ResetKind::PowerCycle);
+        }
         self.scheduler.reset();
         self.history.clear();
+        self.irq_log.clear();
         self.last_stop = StopReason::CyclesComplete;
         self.total_cycles = 0;
         self.halt_logged = false;
         self.boot_init_done = false;
-        self.powered_on = false; // Require ON key press to power on again
+        self.lcd_frames = 0;
+        self.last_lcd_frame_cycle = None;
+        self.lcd_base_changes.clear();
+        // The keypad was reset, so pending tap releases refer to nothing
+        self.key_releases.clear();
+        if let Some(runaway) = &mut self.runaway {
+            runaway.note_reset();
+        }
+        if let Some(latency) = &mut self.latency {
+            latency.clear_pending();
+        }
+        // A power cycle requires an ON key press to power on again; the reset
+        // button reboots straight into the OS
+        self.powered_on = kind != ResetKind::PowerCycle && was_powered_on;
         // Initialize CPU prefetch buffer - charges cycles for first instruction's first byte
         // This matches CEmu's cpu_inst_start() call at the beginning of cpu_execute()
         self.cpu.init_prefetch(&mut self.bus);
@@ -781,11 +1475,160 @@ impl Emu {
         // This schedules the RTC LATCH event to fire at LATCH_TICK_OFFSET (16429) ticks after
         // each second boundary. We start from time 0, so first LATCH is at LATCH_TICK_OFFSET.
         self.scheduler.set(EventId::Rtc, LATCH_TICK_OFFSET);
+        // CEmu's gpt_reset(): sched_set(SCHED_OSTIMER, 0)
+        self.scheduler.set(EventId::OsTimer, 0);
 
         // Clear framebuffer to black
         for pixel in &mut self.framebuffer {
             *pixel = 0xFF000000;
         }
+        self.presented_frame = None;
+    }
+
+    /// Enable instruction tracing (logs every instruction to the log callback)
+    pub fn enable_inst_trace(&mut self, limit: u32) {
+        let _log = self.log_scope();
+        self.inst_trace.enable(limit);
+        log_sub!(Cpu, Info, "INST_TRACE: enabled, limit={}", limit);
+    }
+
+    /// Arm instruction tracing to start when CPU wakes from HALT
+    /// This avoids tracing HALT loops - only traces after wake event
+    pub fn arm_inst_trace_on_wake(&mut self, limit: u32) {
+        let _log = self.log_scope();
+        self.inst_trace.armed_limit = limit;
+        self.inst_trace.armed = true;
+        log_sub!(Cpu, Info, "INST_TRACE: armed for wake, limit={}", limit);
+    }
+
+    /// Disable instruction tracing
+    pub fn disable_inst_trace(&mut self) {
+        let _log = self.log_scope();
+        self.inst_trace.enabled = false;
+        self.inst_trace.armed = false;
+        log_sub!(Cpu, Info, "INST_TRACE: disabled");
+    }
+
+    /// Check if instruction tracing is enabled
+    pub fn is_inst_trace_enabled(&self) -> bool {
+        self.inst_trace.enabled
+    }
+
+    /// Set this instance's log callback (None = use the process-wide default).
+    /// Lets hosts running many instances concurrently keep their logs apart.
+    pub fn set_log_callback(&mut self, cb: Option<LogCallback>) {
+        self.log_callback = cb;
+    }
+
+    /// This instance's log callback (None = the process-wide default)
+    pub fn log_callback(&self) -> Option<LogCallback> {
+        self.log_callback
+    }
+
+    /// Set the log verbosity for one subsystem
+    pub fn set_log_level(&mut self, sub: LogSubsystem, level: LogLevel) {
+        self.log_levels.set(sub, level);
+    }
+
+    /// Set the log verbosity for every subsystem
+    pub fn set_all_log_levels(&mut self, level: LogLevel) {
+        self.log_levels.set_all(level);
+    }
+
+    /// Set log verbosity from a filter string such as
+    /// `"warn,keypad=debug"` (see `logging`). Returns the first directive
+    /// that doesn't parse; the ones before it are still applied.
+    pub fn set_log_filter(&mut self, filter: &str) -> Result<(), String> {
+        self.log_levels.apply_filter(filter)
+    }
+
+    /// Get the log verbosity for one subsystem
+    pub fn log_level(&self, sub: LogSubsystem) -> LogLevel {
+        self.log_levels.get(sub)
+    }
+
+    /// Route log output to this instance's callback and levels until the guard drops
+    fn log_scope(&self) -> LogScope {
+        LogScope::enter(LogConfig { callback: self.log_callback, levels: self.log_levels })
+    }
+
+    /// Enable deterministic mode with the given seed, or disable it with None.
+    ///
+    /// In deterministic mode every nondeterministic input is derived from the
+    /// seed: the unmapped-read RNG is seeded, RAM powers up with a seeded
+    /// pattern instead of zeros, and the RTC starts at day 0 00:00:00 without
+    /// tracking the host clock. Two runs with the same seed and inputs produce
+    /// bit-identical traces and frames. Call before `load_rom()` (which resets)
+    /// or follow with a power-cycle reset for the RAM pattern to apply.
+    pub fn set_deterministic(&mut self, seed: Option<u64>) {
+        self.deterministic_seed = seed;
+        if let Some(seed) = seed {
+            self.rtc_host_sync = None;
+            self.apply_deterministic_seed(seed, false);
+        }
+    }
+
+    /// Get the deterministic mode seed (None if deterministic mode is off)
+    pub fn deterministic_seed(&self) -> Option<u64> {
+        self.deterministic_seed
+    }
+
+    /// Set the patches applied to the flash image on every ROM load (takes
+    /// effect on the next `load_rom()`). Original bytes are verified first;
+    /// if any patch does not match, none are applied. See `os_patch` for a
+    /// text format.
+    pub fn set_os_patches(&mut self, patches: Vec<BootPatch>) {
+        self.os_patches = patches;
+    }
+
+    /// Patches applied on every ROM load
+    pub fn os_patches(&self) -> &[BootPatch] {
+        &self.os_patches
+    }
+
+    /// Outcome of applying the OS patches on the last ROM load: bytes
+    /// written, or why nothing was. None if there were no patches.
+    pub fn os_patch_result(&self) -> Option<&Result<usize, os_patch::PatchError>> {
+        self.os_patch_result.as_ref()
+    }
+
+    /// Apply the OS patches to a freshly loaded image.
+    /// These are not modifications by the emulated system, so they are kept
+    /// out of the dirty sectors that flash persistence writes back.
+    fn apply_load_patches(&mut self) {
+        self.os_patch_result = None;
+        if !self.os_patches.is_empty() {
+            let result = os_patch::apply_patches(&mut self.bus.flash, &self.os_patches);
+            match &result {
+                Ok(bytes) => log_sub!(Flash, Info, "OS_PATCHES applied patches={} bytes={}", self.os_patches.len(), bytes),
+                Err(e) => log_sub!(Flash, Warn, "OS_PATCHES not applied: {}", e),
+            }
+            self.os_patch_result = Some(result);
+        }
+        self.bus.flash.take_dirty_sectors();
+    }
+
+    /// Seed the bus RNG and optionally fill RAM with the seeded power-on pattern
+    fn apply_deterministic_seed(&mut self, seed: u64, fill_ram: bool) {
+        use crate::memory::addr::RAM_SIZE;
+
+        let mut state = seed;
+        let rng = splitmix64(&mut state).to_le_bytes();
+        // The LFSR locks up on an all-zero state
+        if rng[..3] == [0, 0, 0] {
+            self.bus.seed_rng(0x9A, 0x59, 0xC6);
+        } else {
+            self.bus.seed_rng(rng[0], rng[1], rng[2]);
+        }
+
+        if fill_ram {
+            let mut pattern = Vec::with_capacity(RAM_SIZE);
+            while pattern.len() < RAM_SIZE {
+                pattern.extend_from_slice(&splitmix64(&mut state).to_le_bytes());
+            }
+            pattern.truncate(RAM_SIZE);
+            self.bus.ram.load_data(&pattern);
+        }
     }
 
     /// Run for specified cycles, returns cycles actually executed
@@ -797,47 +1640,96 @@ impl Emu {
     /// screen ("TI-84 Plus CE", OS version, "RAM Cleared") to remain visible until the user
     /// presses their first key. See `set_key()` documentation for details.
     pub fn run_cycles(&mut self, cycles: u32) -> u32 {
+        if self.paused {
+            return 0;
+        }
+        if let Some(player) = self.replay.take() {
+            return self.run_replay_cycles(player, cycles);
+        }
+        if !self.key_releases.is_empty() {
+            return self.run_tap_cycles(cycles);
+        }
+        let _log = self.log_scope();
         if !self.rom_loaded || !self.powered_on || self.is_off() {
             return 0;
         }
 
         // Sync check: bus.cycles should match total_cycles
         if self.total_cycles != self.bus.total_cycles() {
-            log_evt!(
+            log_sub!(Cpu, Error,
                 "DESYNC at run_cycles entry: emu_total={} bus_total={} bus_mem={}",
                 self.total_cycles, self.bus.total_cycles(), self.bus.mem_cycles()
             );
+            self.write_auto_diagnostics(DiagnosticTrigger::InternalError(format!(
+                "cycle desync: emu_total={} bus_total={}",
+                self.total_cycles, self.bus.total_cycles()
+            )));
             // Force resync to prevent runaway execution
             self.total_cycles = self.bus.total_cycles();
         }
 
+        if self.bus.sandbox.is_enabled() {
+            self.refresh_sandbox_vat();
+        }
+
+        if self.cheats.point() == CheatPoint::RunStart {
+            self.cheats.apply(&mut self.bus);
+        }
+        self.persist_flash_if_due();
+        self.capture_rewind_if_due();
+        self.sync_rtc_to_host();
+
         let mut cycles_remaining = cycles as i32;
         let mut start_cycles = self.total_cycles;
+        let start_frames = self.lcd_frames;
+        // Fast mode ticks polled peripherals in batches of this many cycles
+        const FAST_TICK_BATCH: u32 = 1024;
+        let mut tick_debt: u32 = 0;
+        #[cfg(feature = "debugger")]
+        {
+            self.debug_event = None;
+            self.bus.watchpoints.take_hit();
+        }
 
         while cycles_remaining > 0 {
+            if self.stop_on_frame && self.lcd_frames != start_frames {
+                break;
+            }
+
             // Sync scheduler with CPU speed setting
             let cpu_speed = self.bus.ports.control.cpu_speed();
             self.scheduler.set_cpu_speed(cpu_speed);
 
-            // Check breakpoint BEFORE executing
+            // Check breakpoint BEFORE executing (not when continuing a
+            // block instruction that already passed this check)
             if let Some(bp) = self.breakpoint_pc {
-                if self.cpu.pc == bp && !self.cpu.halted {
+                if self.cpu.pc == bp && !self.cpu.halted && !self.cpu.in_block() {
                     self.breakpoint_hit = true;
+                    self.tick_peripheral_debt(&mut tick_debt);
                     self.total_cycles = self.bus.total_cycles();
                     return (self.total_cycles - start_cycles) as u32;
                 }
             }
+            #[cfg(feature = "debugger")]
+            if self.debug_active() && !self.cpu.halted && !self.cpu.in_block() && self.check_debug_break() {
+                break;
+            }
 
             // Record PC and peek at opcode before execution
             let pc = self.cpu.pc;
-            let (opcode, opcode_len) = self.peek_opcode(pc);
+            // Continuing a yielded block instruction is not a new instruction
+            let boundary = !self.cpu.in_block();
+            let record_history = boundary && (self.accuracy == AccuracyMode::Full || self.inst_trace.enabled);
+            let (opcode, opcode_len) = if record_history { self.peek_opcode(pc) } else { ([0; 4], 0) };
             let was_halted = self.cpu.halted;
 
             // Instruction tracing (when enabled via FFI, not in WASM)
-            #[cfg(not(target_arch = "wasm32"))]
-            if INST_TRACE_ENABLED.load(Ordering::Relaxed) && !self.cpu.halted {
-                let count = INST_TRACE_COUNT.fetch_add(1, Ordering::Relaxed);
-                let limit = INST_TRACE_LIMIT.load(Ordering::Relaxed);
+            #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+            if self.inst_trace.enabled && boundary && !self.cpu.halted {
+                let probe = self.profiler.start();
+                let count = self.inst_trace.count;
+                self.inst_trace.count = count.wrapping_add(1);
+                let limit = self.inst_trace.limit;
 
                 let opcode_str: String = opcode[..opcode_len]
                     .iter()
@@ -845,7 +1737,7 @@ impl Emu {
                     .collect::<Vec<_>>()
                     .join(" ");
 
-                log_evt!(
+                log_sub!(Cpu, Info,
                     "INST[{}]: PC={:06X} OP={} A={:02X} F={:02X} BC={:06X} DE={:06X} HL={:06X} SP={:06X} halted={} wake={}",
                     count, pc, opcode_str,
                     self.cpu.a, self.cpu.f,
@@ -855,29 +1747,48 @@ impl Emu {
                 );
 
                 if limit > 0 && count >= limit {
-                    INST_TRACE_ENABLED.store(false, Ordering::SeqCst);
-                    log_evt!("INST_TRACE: auto-disabled after limit reached");
+                    self.inst_trace.enabled = false;
+                    log_sub!(Cpu, Info, "INST_TRACE: auto-disabled after limit reached");
                 }
+                self.profiler.stop(ProfileSection::Trace, probe);
             }
+            #[cfg(feature = "trace")]
+            let trace = if boundary && !self.cpu.halted { self.trace_begin(pc) } else { None };
 
             // Handle CPU_SIGNAL_ANY_KEY equivalent - call any_key_check before CPU executes
             if self.cpu.any_key_wake {
+                let probe = self.profiler.start();
                 let key_state = self.bus.key_state().clone();
                 let should_interrupt = self.bus.ports.keypad.any_key_check(&key_state);
                 if should_interrupt {
                     use crate::peripherals::interrupt::sources;
                     self.bus.ports.interrupt.raise(sources::KEYPAD);
                 }
+                self.profiler.stop(ProfileSection::Keypad, probe);
             }
 
             // Execute one instruction
+            let irqs_before = self.cpu.irqs_serviced;
+            let nmis_before = self.cpu.nmis_serviced;
+            self.set_block_limit();
+            let probe = self.profiler.start();
             let cycles_used = self.cpu.step(&mut self.bus);
+            self.profiler.stop(ProfileSection::Cpu, probe);
+            self.log_serviced_interrupts(pc, irqs_before, nmis_before);
+            #[cfg(feature = "trace")]
+            self.trace_end(trace, cycles_used, irqs_before, nmis_before);
+            self.check_runaway(cycles_used);
+            self.check_violation_restart();
+            self.trace_bcall();
+            self.sample_cpu_usage();
 
             // Check for wake event - triggers armed trace if CPU woke from HALT
-            check_armed_trace_on_wake(was_halted, self.cpu.halted);
+            self.inst_trace.check_armed_on_wake(was_halted, self.cpu.halted);
 
             // Record in history
-            self.history.record(pc, &opcode[..opcode_len]);
+            if record_history {
+                self.history.record(pc, &opcode[..opcode_len]);
+            }
 
             // Advance scheduler with cycles used at current speed, THEN handle speed change
             cycles_remaining -= cycles_used as i32;
@@ -900,12 +1811,16 @@ impl Emu {
                 let converted = total * new_mhz as u64 / old_mhz as u64;
                 self.bus.set_total_cycles(converted);
                 self.total_cycles = converted;
+                if let Some(usage) = &mut self.cpu_usage {
+                    usage.rebase(converted);
+                }
                 // Also rescale start_cycles so the returned delta reflects actual work,
                 // not the speed conversion artifact
                 start_cycles = start_cycles * new_mhz as u64 / old_mhz as u64;
             }
 
             // Process pending scheduler events
+            let probe = self.profiler.start();
             self.process_scheduler_events();
 
             // DMA cycle stealing: if LCD DMA consumed bus time, steal CPU cycles
@@ -913,6 +1828,7 @@ impl Emu {
             if dma_stolen > 0 {
                 cycles_remaining -= dma_stolen as i32;
             }
+            self.profiler.stop(ProfileSection::Scheduler, probe);
 
             // Check if SPI needs initial scheduling (state changed via port write)
             if self.bus.take_spi_schedule_flag() && !self.scheduler.is_active(EventId::Spi) {
@@ -925,15 +1841,30 @@ impl Emu {
             if self.bus.take_nmi_flag() {
                 self.cpu.nmi_pending = true;
                 self.log_nmi();
+                self.note_violation();
+                self.write_auto_diagnostics(DiagnosticTrigger::ProtectionViolation {
+                    addr: self.bus.nmi_violation_addr(),
+                    pc: self.bus.nmi_violation_pc(),
+                });
             }
 
-            // Tick peripherals and check for interrupts
-            if self.tick_peripherals(cycles_used) {
-                self.cpu.irq_pending = true;
+            // Tick peripherals and check for interrupts (batched in fast mode)
+            let probe = self.profiler.start();
+            tick_debt += cycles_used;
+            if self.accuracy == AccuracyMode::Full || tick_debt >= FAST_TICK_BATCH || self.cpu.halted {
+                self.tick_peripheral_debt(&mut tick_debt);
             }
+            self.profiler.stop(ProfileSection::Peripherals, probe);
 
-            // Stop if device went off (OS wrote POWER bit 6 during this instruction)
-            if self.is_off() {
+            // Stop if device went off (OS wrote POWER bit 6 during this instruction),
+            // or the watchdog is about to reset it
+            if self.is_off() || self.bus.ports.watchdog.needs_reset {
+                break;
+            }
+
+            // A watchpoint was hit during this instruction
+            #[cfg(feature = "debugger")]
+            if self.bus.watchpoints.has_hit() {
                 break;
             }
 
@@ -944,21 +1875,26 @@ impl Emu {
             // Performance: LCD DMA fires every ~19 cycles at Clock48M, creating thousands
             // of events per frame. Instead of returning to the outer loop for each event
             // (which requires cpu.step + tick_peripherals overhead), we use a tight inner
-            // loop that only processes scheduler events and DMA stealing. Peripheral ticks
-            // (OS Timer, keypad, etc.) are batched every HALT_TICK_BATCH cycles.
+            // loop that only processes scheduler events and DMA stealing. Interrupt sources
+            // on the scheduler (OS Timer, RTC, keypad scans, LCD) wake the CPU on time;
+            // the polled ones (general-purpose timers, USB) are batched every
+            // HALT_TICK_BATCH cycles.
             if self.cpu.halted {
                 self.last_stop = StopReason::Halted;
                 const HALT_TICK_BATCH: u64 = 10_000;
                 let mut peripheral_debt: u64 = 0;
+                // HALT fast-forward is dominated by event processing; charge it to the scheduler
+                let probe = self.profiler.start();
 
                 loop {
                     // Stop if device went off during this frame (OS wrote POWER bit 6)
-                    if self.is_off() { break; }
+                    if self.is_off() || self.bus.ports.watchdog.needs_reset { break; }
+                    if self.stop_on_frame && self.lcd_frames != start_frames { break; }
 
                     let skip = self.scheduler.cycles_until_next_event();
                     if skip == 0 {
                         if !self.cpu.iff1 && !self.cpu.nmi_pending {
-                            log_evt!(
+                            log_sub!(Cpu, Warn,
                                 "HALT_STUCK: pc={:06X} iff1={} iff2={} irq={} nmi={} cycles_left={} total={}",
                                 self.cpu.pc, self.cpu.iff1, self.cpu.iff2,
                                 self.cpu.irq_pending, self.cpu.nmi_pending,
@@ -986,7 +1922,7 @@ impl Emu {
                         }
 
                         // Genuinely no events — batch advance so tick_peripherals
-                        // can generate an OS Timer interrupt to wake the CPU.
+                        // can generate a timer interrupt to wake the CPU.
                         // Cap at SCHED_SECOND boundary to prevent process_second()
                         // from saturating event timestamps to 0 (causes DMA catch-up storm).
                         let to_sched_second = self.scheduler.cycles_until_sched_second();
@@ -1025,7 +1961,7 @@ impl Emu {
 
                     peripheral_debt += skip + dma_stolen;
 
-                    // Periodically tick peripherals (OS Timer, keypad, etc.)
+                    // Periodically tick polled peripherals (timers, USB, etc.)
                     if peripheral_debt >= HALT_TICK_BATCH {
                         if self.tick_peripherals(peripheral_debt as u32) {
                             self.cpu.irq_pending = true;
@@ -1045,10 +1981,16 @@ impl Emu {
                         self.cpu.irq_pending = true;
                     }
                 }
+                self.profiler.stop(ProfileSection::Scheduler, probe);
             }
         }
+        self.tick_peripheral_debt(&mut tick_debt);
 
-        self.last_stop = StopReason::CyclesComplete;
+        #[cfg(feature = "debugger")]
+        let debug_stop = self.finish_debug_stop();
+        #[cfg(not(feature = "debugger"))]
+        let debug_stop = false;
+        self.last_stop = if debug_stop { StopReason::Debugger } else { StopReason::CyclesComplete };
         let executed = (self.total_cycles - start_cycles) as u32;
 
         // Periodic frame diagnostic logging (non-WASM only)
@@ -1066,7 +2008,7 @@ impl Emu {
                 let raw_irqs = self.bus.ports.interrupt.raw();
                 let status_irqs = self.bus.ports.interrupt.status();
                 let enabled_irqs = self.bus.ports.interrupt.enabled();
-                log_evt!(
+                log_sub!(Lcd, Info,
                     "FRAME[{}]: pc={:06X} halted={} iff1={} iff2={} irq={} nmi={} executed={}/{} total={} events=[{}] pending=[{}] raw={:05X} status={:05X} enabled={:05X} SP={:06X}",
                     self.frame_count, self.cpu.pc,
                     self.cpu.halted, self.cpu.iff1, self.cpu.iff2,
@@ -1082,15 +2024,88 @@ impl Emu {
                 // Only dump once (use halt_logged as a one-shot flag)
                 if !self.halt_logged {
                     self.halt_logged = true;
-                    log_evt!("STUCK_ISR_HISTORY: {}", self.dump_history());
-                    log_evt!("STUCK_ISR_REGS: {}", self.dump_registers());
+                    log_sub!(Interrupt, Warn, "STUCK_ISR_HISTORY: {}", self.dump_history());
+                    log_sub!(Interrupt, Warn, "STUCK_ISR_REGS: {}", self.dump_registers());
                 }
             }
         }
 
+        // Watchdog expired with reset enabled: the ASIC resets like the reset button
+        if self.bus.ports.watchdog.needs_reset {
+            log_sub!(Cpu, Warn, "WATCHDOG: expired, resetting (PC={:06X})", self.cpu.pc);
+            self.reset_for(ResetKind::Warm, ResetCause::Watchdog);
+        }
+
         executed
     }
 
+    /// Check if the CPU is idle: halted with interrupts enabled and nothing pending.
+    /// This is the state TI-OS sits in while waiting in GetKey.
+    pub fn is_idle(&self) -> bool {
+        self.cpu.halted
+            && self.cpu.iff1
+            && !self.cpu.irq_pending
+            && !self.cpu.nmi_pending
+            && !self.bus.ports.interrupt.irq_pending()
+    }
+
+    /// Run until the CPU is idle (see `is_idle()`), waiting for key input.
+    ///
+    /// The OS wakes periodically from HALT to service timer interrupts, so idle
+    /// must be observed at IDLE_CONFIRM_SLICES consecutive slice boundaries.
+    /// Returns elapsed cycles, or None if `max_cycles` ran out first (or the
+    /// emulator cannot run).
+    pub fn run_until_idle(&mut self, max_cycles: u64) -> Option<u64> {
+        const IDLE_SLICE_CYCLES: u32 = 100_000;
+        const IDLE_CONFIRM_SLICES: u32 = 3;
+
+        let mut elapsed: u64 = 0;
+        let mut idle_slices = 0;
+
+        while elapsed < max_cycles {
+            let slice = (max_cycles - elapsed).min(IDLE_SLICE_CYCLES as u64) as u32;
+            let executed = self.run_cycles(slice);
+            if executed == 0 {
+                return None;
+            }
+            elapsed += executed as u64;
+
+            if self.is_idle() {
+                idle_slices += 1;
+                if idle_slices >= IDLE_CONFIRM_SLICES {
+                    return Some(elapsed);
+                }
+            } else {
+                idle_slices = 0;
+            }
+        }
+
+        None
+    }
+
+    /// Run until the LCD controller completes its next frame (vsync), so a
+    /// frontend can pace itself on real frames rather than a guessed cycle
+    /// count. Returns elapsed cycles, or 0 if the emulator cannot run.
+    ///
+    /// With the LCD controller off there are no frames to wait for, so one
+    /// 60 Hz frame's worth of cycles is run instead. With it on, the wait is
+    /// bounded at FRAME_WAIT_LIMIT of those (e.g. very slow custom timings).
+    /// Breakpoints and power-off still stop the run early.
+    pub fn run_frame(&mut self) -> u32 {
+        const FALLBACK_HZ: f64 = 60.0;
+        const FRAME_WAIT_LIMIT: u32 = 4;
+
+        let frame_cycles = (self.cpu_clock_hz() / FALLBACK_HZ) as u32;
+        if !self.bus.ports.lcd.is_enabled() {
+            return self.run_cycles(frame_cycles);
+        }
+
+        self.stop_on_frame = true;
+        let elapsed = self.run_cycles(frame_cycles * FRAME_WAIT_LIMIT);
+        self.stop_on_frame = false;
+        elapsed
+    }
+
     /// Internal run_cycles without boot initialization check (to avoid recursion)
     fn run_cycles_internal(&mut self, cycles: u32) -> u32 {
         if !self.rom_loaded || !self.powered_on {
@@ -1114,9 +2129,10 @@ impl Emu {
                 }
             }
 
-            // Check breakpoint BEFORE executing
+            // Check breakpoint BEFORE executing (not when continuing a
+            // block instruction that already passed this check)
             if let Some(bp) = self.breakpoint_pc {
-                if self.cpu.pc == bp && !self.cpu.halted {
+                if self.cpu.pc == bp && !self.cpu.halted && !self.cpu.in_block() {
                     self.breakpoint_hit = true;
                     self.total_cycles = self.bus.total_cycles();
                     return (self.total_cycles - start_cycles) as u32;
@@ -1124,8 +2140,17 @@ impl Emu {
             }
 
             let was_halted = self.cpu.halted;
+            let pc = self.cpu.pc;
+            let irqs_before = self.cpu.irqs_serviced;
+            let nmis_before = self.cpu.nmis_serviced;
+            self.set_block_limit();
             let cycles_used = self.cpu.step(&mut self.bus);
-            check_armed_trace_on_wake(was_halted, self.cpu.halted);
+            self.log_serviced_interrupts(pc, irqs_before, nmis_before);
+            self.check_runaway(cycles_used);
+            self.check_violation_restart();
+            self.trace_bcall();
+            self.sample_cpu_usage();
+            self.inst_trace.check_armed_on_wake(was_halted, self.cpu.halted);
 
             // Advance scheduler with cycles used at current speed, then handle speed change
             cycles_remaining -= cycles_used as i32;
@@ -1143,6 +2168,9 @@ impl Emu {
                 let converted = total * new_mhz as u64 / old_mhz as u64;
                 self.bus.set_total_cycles(converted);
                 self.total_cycles = converted;
+                if let Some(usage) = &mut self.cpu_usage {
+                    usage.rebase(converted);
+                }
                 start_cycles = start_cycles * new_mhz as u64 / old_mhz as u64;
             }
             self.process_scheduler_events();
@@ -1163,6 +2191,7 @@ impl Emu {
             // Check for NMI from memory protection violations
             if self.bus.take_nmi_flag() {
                 self.cpu.nmi_pending = true;
+                self.note_violation();
             }
 
             if self.tick_peripherals(cycles_used) {
@@ -1240,6 +2269,7 @@ impl Emu {
     /// This captures state BEFORE execution to match CEmu's trace format.
     /// Use this for accurate trace comparison with CEmu.
     pub fn step(&mut self) -> Option<StepInfo> {
+        let _log = self.log_scope();
         if !self.rom_loaded || !self.powered_on {
             return None;
         }
@@ -1267,31 +2297,49 @@ impl Emu {
         // Read opcode bytes at PC
         let (opcode, opcode_len) = self.peek_opcode(pc);
 
+        // Continuing a yielded block instruction is not a new instruction
+        let boundary = !self.cpu.in_block();
+
+        #[cfg(feature = "trace")]
+        let trace = if was_halted || !boundary { None } else { self.trace_begin(pc) };
+
         // Clear I/O ops buffer and set instruction context for tracing
         self.bus.clear_instruction_io_ops();
         self.bus.set_instruction_context(pc, &opcode[..opcode_len]);
 
         // Handle CPU_SIGNAL_ANY_KEY equivalent
         if self.cpu.any_key_wake {
-            log_evt!("ANY_KEY_CHECK: mode={} halted={} iff1={}",
+            log_sub!(Keypad, Info, "ANY_KEY_CHECK: mode={} halted={} iff1={}",
                 self.bus.ports.keypad.mode(), self.cpu.halted, self.cpu.iff1);
             let key_state = self.bus.key_state().clone();
             let should_interrupt = self.bus.ports.keypad.any_key_check(&key_state);
             if should_interrupt {
-                log_evt!("ANY_KEY_CHECK: raising keypad interrupt");
+                log_sub!(Keypad, Info, "ANY_KEY_CHECK: raising keypad interrupt");
                 use crate::peripherals::interrupt::sources;
                 self.bus.ports.interrupt.raise(sources::KEYPAD);
             }
         }
 
         // Execute one instruction
+        let irqs_before = self.cpu.irqs_serviced;
+        let nmis_before = self.cpu.nmis_serviced;
+        self.set_block_limit();
         let cycles_used = self.cpu.step(&mut self.bus);
+        self.log_serviced_interrupts(pc, irqs_before, nmis_before);
+        #[cfg(feature = "trace")]
+        self.trace_end(trace, cycles_used, irqs_before, nmis_before);
+        self.check_runaway(cycles_used);
+        self.check_violation_restart();
+        self.trace_bcall();
+        self.sample_cpu_usage();
 
         // Check for wake event
-        check_armed_trace_on_wake(was_halted, self.cpu.halted);
+        self.inst_trace.check_armed_on_wake(was_halted, self.cpu.halted);
 
         // Record in history
-        self.history.record(pc, &opcode[..opcode_len]);
+        if boundary {
+            self.history.record(pc, &opcode[..opcode_len]);
+        }
 
         // Advance scheduler with cycles used at current speed, then handle speed change
         self.scheduler.advance(cycles_used as u64);
@@ -1322,6 +2370,11 @@ impl Emu {
         // Check for NMI from memory protection violations
         if self.bus.take_nmi_flag() {
             self.cpu.nmi_pending = true;
+            self.note_violation();
+            self.write_auto_diagnostics(DiagnosticTrigger::ProtectionViolation {
+                addr: self.bus.nmi_violation_addr(),
+                pc: self.bus.nmi_violation_pc(),
+            });
         }
 
         // Tick peripherals and check for interrupts
@@ -1412,11 +2465,27 @@ impl Emu {
 
     /// Tick peripherals and handle timer delay pipeline scheduling.
     /// Returns true if any interrupt is pending.
+    /// Tick polled peripherals for the cycles `AccuracyMode::Fast` held back
+    fn tick_peripheral_debt(&mut self, debt: &mut u32) {
+        if *debt > 0 && self.tick_peripherals(std::mem::take(debt)) {
+            self.cpu.irq_pending = true;
+        }
+    }
+
     fn tick_peripherals(&mut self, cycles: u32) -> bool {
         // Get timer delay remaining for the delay pipeline packing
         let delay_remaining = self.scheduler.ticks_remaining(EventId::TimerDelay);
+        // USB first so its interrupt line is included in the pending check below
+        self.bus.tick_usb(cycles);
         let irq = self.bus.ports.tick(cycles, delay_remaining);
 
+        if let Some(peer) = self.serial_peer.as_mut() {
+            if self.bus.usb.has_output() {
+                let reply = peer.receive(&self.bus.usb.take_from_calc(usize::MAX));
+                self.bus.usb.push_to_calc(&reply);
+            }
+        }
+
         // If timer tick generated new delay pipeline data, schedule the TimerDelay event
         if self.bus.ports.timers.needs_delay_event {
             self.bus.ports.timers.needs_delay_event = false;
@@ -1425,6 +2494,13 @@ impl Emu {
             }
         }
 
+        // Watchdog expired with its interrupt enabled
+        if self.bus.ports.watchdog.needs_nmi {
+            self.bus.ports.watchdog.needs_nmi = false;
+            log_sub!(Interrupt, Warn, "WATCHDOG: expired, NMI");
+            self.cpu.nmi_pending = true;
+        }
+
         // Check LCD scheduling flags (set by control register writes)
         if self.bus.ports.lcd.needs_lcd_event {
             self.bus.ports.lcd.needs_lcd_event = false;
@@ -1438,6 +2514,17 @@ impl Emu {
             self.scheduler.clear(EventId::LcdDma);
         }
 
+        // Keypad scan started or stopped by a control register write
+        if self.bus.ports.keypad.needs_scan_clear {
+            self.bus.ports.keypad.needs_scan_clear = false;
+            self.scheduler.clear(EventId::Keypad);
+        }
+        if self.bus.ports.keypad.needs_scan_event {
+            self.bus.ports.keypad.needs_scan_event = false;
+            let row_wait = self.bus.ports.keypad.row_wait();
+            self.scheduler.set(EventId::Keypad, row_wait as u64);
+        }
+
         irq
     }
 
@@ -1447,13 +2534,14 @@ impl Emu {
 
         // Process all pending events
         while let Some(event) = self.scheduler.next_pending_event() {
+            self.trace_event(TraceKind::Scheduler(event));
             match event {
                 EventId::Rtc => {
                     // Process RTC event using 3-state machine (TICK/LATCH/LOAD_LATCH)
                     let (next_delay, raise_interrupt) = self.bus.ports.rtc.process_event();
                     if raise_interrupt {
-                        // TODO: Wire RTC interrupt to interrupt controller
-                        // CEmu: intrpt_set(INT_RTC, true) — INT_RTC is a dedicated line
+                        // CEmu: intrpt_set(INT_RTC, true)
+                        self.raise_irq(sources::RTC);
                     }
                     // Schedule next RTC event
                     self.scheduler.repeat(EventId::Rtc, next_delay);
@@ -1468,6 +2556,18 @@ impl Emu {
                         self.scheduler.clear(EventId::Spi);
                     }
                 }
+                EventId::Keypad => {
+                    // CEmu keypad_scan_event: scan one row, then the next row
+                    // or the next scan (or idle), then keypad_intrpt_check()
+                    let key_state = *self.bus.ports.key_state();
+                    match self.bus.ports.keypad.scan_event(&key_state) {
+                        Some(ticks) => self.scheduler.repeat(EventId::Keypad, ticks as u64),
+                        None => self.scheduler.clear(EventId::Keypad),
+                    }
+                    if self.bus.ports.keypad.interrupt_pending() {
+                        self.raise_irq(sources::KEYPAD);
+                    }
+                }
                 EventId::TimerDelay => {
                     // Timer 2-cycle delay pipeline: process one tier of deferred interrupts
                     let (_status, _intrpt, has_more) = self.bus.ports.timers.process_delay();
@@ -1476,11 +2576,7 @@ impl Emu {
                     let int_state = self.bus.ports.timers.interrupt_state();
                     let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
                     for (i, &src) in timer_sources.iter().enumerate() {
-                        if int_state & (1 << i) != 0 {
-                            self.bus.ports.interrupt.raise(src);
-                        } else {
-                            self.bus.ports.interrupt.clear_raw(src);
-                        }
+                        self.bus.ports.interrupt.set(src, int_state & (1 << i) != 0);
                     }
                     self.cpu.irq_pending = self.bus.ports.interrupt.irq_pending();
                     if has_more {
@@ -1495,34 +2591,43 @@ impl Emu {
                     let int_state = self.bus.ports.timers.interrupt_state();
                     let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
                     for (i, &src) in timer_sources.iter().enumerate() {
-                        if int_state & (1 << i) != 0 {
-                            self.bus.ports.interrupt.raise(src);
-                        } else {
-                            self.bus.ports.interrupt.clear_raw(src);
-                        }
+                        self.bus.ports.interrupt.set(src, int_state & (1 << i) != 0);
                     }
                     self.cpu.irq_pending = self.bus.ports.interrupt.irq_pending();
                     self.scheduler.clear(event);
                 }
                 EventId::OsTimer => {
-                    // OS Timer fired - raise OSTIMER interrupt
-                    self.bus.ports.interrupt.raise(sources::OSTIMER);
+                    // OS Timer edge: set the line to the old state, then toggle
+                    let ticks = self.bus.ports.os_timer_event();
                     self.cpu.irq_pending = self.bus.ports.interrupt.irq_pending();
-                    // OS Timer auto-repeats, reschedule it
-                    // Note: the actual period is set by the timer peripheral
-                    self.scheduler.clear(EventId::OsTimer);
+                    self.scheduler.repeat(EventId::OsTimer, ticks);
                 }
                 EventId::Lcd => {
                     // LCD event state machine — matches CEmu's lcd_event()
+                    // Reaching front porch means the previous active video period finished
+                    let frame_done = self.bus.ports.lcd.compare_state() == LcdCompare::FrontPorch as u8;
                     let result = self.bus.ports.lcd.process_event();
+                    if frame_done {
+                        self.lcd_frames += 1;
+                        self.last_lcd_frame_cycle = Some(self.total_cycles);
+                        if let Some(rewind) = &mut self.rewind {
+                            rewind.frame_done();
+                        }
+                        self.trace_event(TraceKind::Frame { frame: self.lcd_frames });
+                        if self.frame_callback.is_some() {
+                            self.frame_ready();
+                        }
+                        if let Some(usage) = &mut self.cpu_usage {
+                            usage.end_frame(self.lcd_frames, self.bus.total_cycles(), self.cpu.halted);
+                        }
+                        if self.cheats.point() == CheatPoint::Vblank {
+                            self.cheats.apply(&mut self.bus);
+                        }
+                    }
                     // Update interrupt controller based on lcd.ris & lcd.imsc
                     if result.interrupt_changed {
-                        if self.bus.ports.lcd.check_interrupt() {
-                            self.bus.ports.interrupt.raise(sources::LCD);
-                        } else {
-                            self.bus.ports.interrupt.clear_raw(sources::LCD);
-                        }
-                        self.cpu.irq_pending = self.bus.ports.interrupt.irq_pending();
+                        let active = self.bus.ports.lcd.check_interrupt();
+                        self.set_irq(sources::LCD, active);
                     }
                     // Schedule DMA if needed (relative to this LCD event)
                     if let Some(offset) = result.schedule_dma_offset {
@@ -1538,6 +2643,9 @@ impl Emu {
                     // DMA consumes bus time tracked via dma_last_mem_timestamp.
                     // CEmu: last_mem_timestamp += callback.dma(id) * tick_unit
                     let result = self.bus.ports.lcd.process_dma();
+                    if let Some((old_base, new_base)) = self.bus.ports.lcd.take_base_swap() {
+                        self.record_lcd_base_change(old_base, new_base);
+                    }
                     let tick_unit = crate::scheduler::ClockId::Clock48M
                         .base_ticks_per_tick(self.scheduler.cpu_speed());
                     if let Some(ticks) = result.repeat_ticks {
@@ -1651,6 +2759,117 @@ impl Emu {
         &self.framebuffer
     }
 
+    /// 64-bit hash of the framebuffer as the last `render_frame()` left it.
+    /// Equal screens hash equal, so tests can assert what was drawn
+    /// without storing golden images.
+    pub fn frame_hash(&self) -> u64 {
+        frame_hash::hash_frame(&self.framebuffer)
+    }
+
+    /// The framebuffer as the last `render_frame()` left it, as a PNG file
+    #[cfg(feature = "png")]
+    pub fn screenshot_png(&self) -> Vec<u8> {
+        crate::png::encode_argb(&self.framebuffer, SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+
+    /// Update the presented frame, the copy frontends draw, from the
+    /// framebuffer with the color profile applied. The framebuffer itself
+    /// stays exactly what the LCD controller produced. Returns false if
+    /// nothing was rendered since the last call or frame skip dropped it.
+    pub fn present_frame(&mut self) -> bool {
+        let stale = self.presented_frame.is_none();
+        if self.presented_frame == Some(self.frames_rendered) {
+            return false;
+        }
+        self.presented_frame = Some(self.frames_rendered);
+        if self.frames_skipped < self.frame_skip && !stale {
+            self.frames_skipped += 1;
+            return false;
+        }
+        self.frames_skipped = 0;
+        self.presented.copy_from_slice(&self.framebuffer);
+        self.color_transform.apply(&mut self.presented);
+        true
+    }
+
+    /// The frame as of the last `present_frame()`, ARGB8888
+    pub fn presented_data(&self) -> &[u32] {
+        &self.presented
+    }
+
+    /// Raw pointer to the presented frame
+    pub fn presented_ptr(&self) -> *const u32 {
+        self.presented.as_ptr()
+    }
+
+    /// Copy the presented frame into `out` as RGBA8888 bytes, row-major.
+    /// Returns the bytes written, or None if `out` is shorter than a frame.
+    pub fn copy_presented_rgba(&self, out: &mut [u8]) -> Option<usize> {
+        let len = self.presented.len() * 4;
+        let out = out.get_mut(..len)?;
+        for (rgba, &argb) in out.chunks_exact_mut(4).zip(&self.presented) {
+            let [a, r, g, b] = argb.to_be_bytes();
+            rgba.copy_from_slice(&[r, g, b, a]);
+        }
+        Some(len)
+    }
+
+    /// Frames rendered into the framebuffer since the last reset; unchanged
+    /// means the framebuffer has not been redrawn since it was last read
+    pub fn frame_counter(&self) -> u64 {
+        self.frames_rendered
+    }
+
+    /// The SPI panel's frame memory: what was drawn with RAMWR, as
+    /// ARGB8888 at `SCREEN_WIDTH` x `SCREEN_HEIGHT`. Unlike
+    /// `framebuffer_data`, this is not fed by the LCD controller.
+    pub fn panel_framebuffer(&self) -> &[u32] {
+        self.bus.panel().framebuffer()
+    }
+
+    /// Render the last frame as terminal text about `cols` characters wide
+    pub fn screen_to_terminal(&self, cols: usize, style: crate::term_render::TermStyle) -> String {
+        crate::term_render::render_terminal(&self.framebuffer, SCREEN_WIDTH, SCREEN_HEIGHT, cols, style)
+    }
+
+    /// Composite the last frame into `skin`, tinting held keys with
+    /// `highlight` (ARGB, alpha = opacity) if given. Returns the skin-sized
+    /// ARGB8888 image.
+    pub fn render_skin_screenshot(&self, skin: &Skin, highlight: Option<u32>) -> Vec<u32> {
+        let keys = self.bus.key_state();
+        let pressed = |row: usize, col: usize| keys.get(row).and_then(|r| r.get(col)).copied().unwrap_or(false);
+        skin.compose(&self.framebuffer, SCREEN_WIDTH, pressed, highlight)
+    }
+
+    /// Read the homescreen text from the OS text buffer, one string per row
+    /// (trailing blanks trimmed). Only meaningful once the OS has booted.
+    pub fn homescreen_text(&mut self) -> Vec<String> {
+        (0..HOMESCREEN_ROWS)
+            .map(|row| {
+                let start = TEXT_SHADOW_ADDR + (row * HOMESCREEN_COLS) as u32;
+                let mut bytes: Vec<u8> = (0..HOMESCREEN_COLS as u32)
+                    .map(|col| self.bus.peek_byte(start + col))
+                    .collect();
+                while bytes.last().is_some_and(|&b| b == b' ' || b == 0) {
+                    bytes.pop();
+                }
+                crate::charset::decode(&bytes)
+            })
+            .collect()
+    }
+
+    /// The homescreen text as one string: the rows of `homescreen_text`
+    /// joined with newlines, trailing blank rows dropped. OS glyphs decode
+    /// to Unicode (θ, →, ², and so on) as in `charset`, so a test can assert
+    /// on what the user would read.
+    pub fn read_screen_text(&mut self) -> String {
+        let mut rows = self.homescreen_text();
+        while rows.last().is_some_and(String::is_empty) {
+            rows.pop();
+        }
+        rows.join("\n")
+    }
+
     /// Set key state
     /// Special handling for ON key (row 2, col 0) which has dedicated interrupt
     /// Set key state in the keypad matrix.
@@ -1677,18 +2896,29 @@ impl Emu {
     ///
     /// See docs/findings.md "TI-OS Expression Parser Requires Initialization After Boot"
     pub fn set_key(&mut self, row: usize, col: usize, down: bool) {
+        let _log = self.log_scope();
+        // ON is recorded by press_on_key/release_on_key, which it routes to
+        if !(row == 2 && col == 0) {
+            self.record_input(row, col, down);
+        }
+        if down {
+            let secs = self.emulated_secs();
+            if let Some(latency) = &mut self.latency {
+                latency.key_pressed(row, col, secs);
+            }
+        }
         // Auto-initialize TI-OS parser on first key press after boot
         // Skip ON key (row 2, col 0) - it's for power management, not normal input
         if down && !self.boot_init_done && self.total_cycles > BOOT_COMPLETE_CYCLES && !(row == 2 && col == 0) {
             // If user's first key IS ENTER, just let it through (don't inject another ENTER)
             // Otherwise, inject ENTER before processing their key
             if row == 6 && col == 0 {
-                log_evt!("BOOT_INIT: first key is ENTER, using it to dismiss boot screen");
+                log_sub!(Keypad, Info, "BOOT_INIT: first key is ENTER, using it to dismiss boot screen");
                 self.boot_init_done = true;
                 self.disable_apd();
                 // Continue to process user's ENTER press below
             } else {
-                log_evt!("BOOT_INIT: first key press detected, auto-dismissing boot screen with ENTER");
+                log_sub!(Keypad, Info, "BOOT_INIT: first key press detected, auto-dismissing boot screen with ENTER");
                 // Press ENTER (row 6, col 0) to dismiss boot screen
                 self.bus.set_key(6, 0, true);
                 self.cpu.any_key_wake = true;
@@ -1698,7 +2928,7 @@ impl Emu {
                 self.run_cycles_internal(3_000_000);
                 self.boot_init_done = true;
                 self.disable_apd();
-                log_evt!("BOOT_INIT: boot screen dismissed, processing user key");
+                log_sub!(Keypad, Info, "BOOT_INIT: boot screen dismissed, processing user key");
                 // Continue to process the original key press below
             }
         }
@@ -1724,6 +2954,61 @@ impl Emu {
         }
     }
 
+    /// Press `key` and hold it until `release_key`
+    pub fn press_key(&mut self, key: Key) {
+        self.key_releases.retain(|&(held, _)| held != key);
+        let (row, col) = key.position();
+        self.set_key(row, col, true);
+    }
+
+    /// Release `key`
+    pub fn release_key(&mut self, key: Key) {
+        self.key_releases.retain(|&(held, _)| held != key);
+        let (row, col) = key.position();
+        self.set_key(row, col, false);
+    }
+
+    /// Press `key` and release it once `ms` emulated milliseconds have run
+    /// (at the current CPU speed). The release happens inside `run_cycles`,
+    /// so the caller keeps running the emulator as usual.
+    pub fn tap_key(&mut self, key: Key, ms: u32) {
+        self.press_key(key);
+        let cycles = (ms as f64 * self.cpu_clock_hz() / 1000.0) as u64;
+        self.key_releases.push((key, cycles));
+    }
+
+    /// Type `text` on the keypad, tapping 2nd or ALPHA before keys that need
+    /// them (see `key::keystroke` for the characters understood). Each key
+    /// is held for `TYPE_KEY_HOLD_MS` and followed by as long a pause, with
+    /// the emulator running throughout. Nothing is typed if a character has
+    /// no key; it is returned as the error.
+    pub fn type_text(&mut self, text: &str) -> Result<(), char> {
+        let strokes = text.chars().map(|c| key::keystroke(c).ok_or(c)).collect::<Result<Vec<_>, _>>()?;
+        for (modifier, key) in strokes {
+            for key in modifier.into_iter().chain([key]) {
+                self.tap_key(key, TYPE_KEY_HOLD_MS);
+                self.run_ms(2.0 * TYPE_KEY_HOLD_MS as f64);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run for `ms` emulated milliseconds at the current CPU speed, stopping
+    /// early if the CPU can't run; returns cycles executed
+    pub(crate) fn run_ms(&mut self, ms: f64) -> u64 {
+        let mut remaining = (ms * self.cpu_clock_hz() / 1000.0) as u64;
+        let mut executed = 0u64;
+        while remaining > 0 {
+            let ran = self.run_cycles(remaining.min(1_000_000) as u32);
+            if ran == 0 {
+                break;
+            }
+            executed += ran as u64;
+            remaining = remaining.saturating_sub(ran as u64);
+        }
+        executed
+    }
+
     /// Disable TI-OS Automatic Power Down (APD) by clearing the `apdAble` flag
     /// in the OS system flags area. See APD_FLAGS_ADDR constant for details.
     fn disable_apd(&mut self) {
@@ -1738,6 +3023,24 @@ impl Emu {
         self.bus.ports.backlight.brightness()
     }
 
+    /// Set the simulated battery: `percent` charge (0-100) and whether a
+    /// charger is connected. The boot code and OS see it through the control
+    /// port battery probe in steps of 20% (0 reads as discharged); the next
+    /// probe picks it up. Kept across resets, like a real battery.
+    pub fn set_battery(&mut self, percent: u8, charging: bool) {
+        let status = match percent.min(100) {
+            0 => 0,
+            percent => 1 + (percent - 1) / 20,
+        };
+        self.bus.ports.control.set_battery(status, charging);
+    }
+
+    /// Level the screen is lit at (0-255), 0 when the backlight is too dim
+    /// to see. Frontends dim the rendered screen by `level / 255`.
+    pub fn backlight_level(&self) -> u8 {
+        self.bus.ports.backlight.level()
+    }
+
     /// Check if LCD is on (should display content).
     /// Returns true when both conditions are met:
     /// 1. Control port 0x05 bit 4 is set (lcd_flag_enabled)
@@ -1762,9 +3065,11 @@ impl Emu {
     /// the OS has configured WAKE as inverted — the clear step sets the status bit.
     /// wake() also sets readBatteryStatus = 0xFE so the OS WAKE ISR sees a valid battery.
     pub fn press_on_key(&mut self) {
+        let _log = self.log_scope();
         use crate::peripherals::interrupt::sources;
 
-        log_evt!("ON_KEY pressed");
+        log_sub!(Keypad, Info, "ON_KEY pressed");
+        self.record_input(2, 0, true);
         // Power on the calculator
         self.powered_on = true;
         // Set the one-shot wake signal — consumed on first cpu.step() call.
@@ -1780,7 +3085,7 @@ impl Emu {
         // CEmu's keypad_on_check(): if (control.off && onState) { control.off=false; intrpt_pulse(INT_WAKE); }
         // wake() clears off and sets readBatteryStatus=0xFE so the OS ISR sees valid battery.
         if self.bus.ports.control.is_off() {
-            log_evt!("WAKE: device off, clearing off + pulsing WAKE");
+            log_sub!(Interrupt, Info, "WAKE: device off, clearing off + pulsing WAKE");
             self.bus.ports.control.wake();
             self.bus.ports.interrupt.pulse(sources::WAKE);
             // Disable APD on every wake — if the OS put the device to sleep via APD,
@@ -1800,12 +3105,32 @@ impl Emu {
     /// WAKE is NOT touched on release — CEmu only pulses WAKE on press when off.
     /// on_key_wake is one-shot (consumed in step()), no need to clear here.
     pub fn release_on_key(&mut self) {
+        let _log = self.log_scope();
         use crate::peripherals::interrupt::sources;
-        log_evt!("ON_KEY released");
+        log_sub!(Keypad, Info, "ON_KEY released");
+        self.record_input(2, 0, false);
         self.bus.set_key(2, 0, false);
         self.bus.ports.interrupt.clear_raw(sources::ON_KEY);
     }
 
+    /// Pause emulation: `run_cycles` (and everything built on it) runs
+    /// nothing until `resume()`. Stepping still works, and the pause
+    /// survives resets and state loads, which are host state, not the
+    /// calculator's.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume after `pause()`
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Whether emulation is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     /// Simulate initial power-on sequence
     /// Call this after loading ROM but before run_cycles to simulate
     /// the calculator being turned on via the ON key
@@ -1815,6 +3140,66 @@ impl Emu {
         self.release_on_key();
     }
 
+    /// Enter exam mode with the real key combination: from a power cycle,
+    /// hold [←] and [→] while pressing [ON], then release them after
+    /// `EXAM_HOLD_SECS`. The OS then shows its Press-to-Test screen, which
+    /// the user (or host) confirms with the usual keys.
+    pub fn enter_exam_mode(&mut self) {
+        let _log = self.log_scope();
+        log_sub!(Keypad, Info, "EXAM_MODE: entering via [left]+[right]+[ON]");
+        self.reset_with(ResetKind::PowerCycle);
+        for (row, col) in exam_mode::EXAM_KEYS {
+            self.bus.set_key(row, col, true);
+        }
+        self.press_on_key();
+        let hold = exam_mode::EXAM_HOLD_SECS * Self::cpu_hz(self.bus.ports.control.cpu_speed());
+        self.run_cycles_internal(hold as u32);
+        self.release_on_key();
+        for (row, col) in exam_mode::EXAM_KEYS {
+            self.bus.set_key(row, col, false);
+        }
+        self.exam.enter();
+        if let Some(flag) = self.exam.flag() {
+            let value = self.bus.peek_byte(flag.addr);
+            self.bus.poke_byte(flag.addr, value | flag.mask());
+        }
+    }
+
+    /// Leave exam mode. On hardware this happens when a file is received
+    /// over the link port; here the emulator's state is cleared, along with
+    /// the OS's flag bit if its location was set with `set_exam_flag()`.
+    pub fn exit_exam_mode(&mut self) {
+        let _log = self.log_scope();
+        log_sub!(Keypad, Info, "EXAM_MODE: exit");
+        self.exam.exit();
+        if let Some(flag) = self.exam.flag() {
+            let value = self.bus.peek_byte(flag.addr);
+            self.bus.poke_byte(flag.addr, value & !flag.mask());
+        }
+    }
+
+    /// Whether exam mode is active. With an OS flag location set this reads
+    /// the OS's own state, so it also reflects exam mode entered or left by
+    /// the emulated OS.
+    pub fn exam_mode_active(&mut self) -> bool {
+        match self.exam.flag() {
+            Some(flag) => self.bus.peek_byte(flag.addr) & flag.mask() != 0,
+            None => self.exam.is_active(),
+        }
+    }
+
+    /// Set where the OS records exam mode (OS-version specific), or None to
+    /// track it in the emulator only
+    pub fn set_exam_flag(&mut self, flag: Option<ExamFlag>) {
+        self.exam.set_flag(flag);
+    }
+
+    /// Simulated exam LED: blinks while exam mode is active
+    pub fn exam_led_on(&mut self) -> bool {
+        let secs = self.total_cycles as f64 / Self::cpu_hz(self.bus.ports.control.cpu_speed());
+        self.exam_mode_active() && ExamMode::led_on(secs)
+    }
+
     /// Get current keypad mode (for debugging)
     pub fn keypad_mode(&self) -> u8 {
         self.bus.ports.keypad.mode()
@@ -1823,38 +3208,383 @@ impl Emu {
     /// Render the current VRAM contents to the framebuffer.
     /// Supports both 8bpp indexed color (used by graphx games) and 16bpp RGB565 (used by TI-OS).
     pub fn render_frame(&mut self) {
+        self.frames_rendered += 1;
+        let probe = self.profiler.start();
         let upbase = self.bus.ports.lcd.upbase();
         let bpp_mode = self.bus.ports.lcd.bpp_mode();
 
         match bpp_mode {
-            3 => self.render_frame_8bpp(upbase),
+            0..=3 => self.render_frame_indexed(upbase, 1 << bpp_mode),
             _ => self.render_frame_16bpp(upbase),
         }
+        if let Some(rect) = self.frame_hash_rect {
+            self.record_frame_hash(rect);
+        }
+        if self.latency.is_some() {
+            let hash = self.full_frame_hash();
+            let secs = self.emulated_secs();
+            if let Some(latency) = &mut self.latency {
+                latency.frame(hash, secs);
+            }
+        }
+        #[cfg(feature = "recording")]
+        if self.recorder.is_some() {
+            let secs = self.emulated_secs();
+            if let Some(recorder) = &mut self.recorder {
+                recorder.capture(&self.framebuffer, secs);
+            }
+        }
+        self.profiler.stop(ProfileSection::LcdRender, probe);
+    }
+
+    /// Hash every rendered frame (or only `rect` of it), or stop with
+    /// `enabled = false`. Skipped frames are not hashed. Returns false if
+    /// `rect` lies entirely off screen.
+    pub fn set_frame_hashing(&mut self, enabled: bool, rect: Option<HashRect>) -> bool {
+        if !enabled {
+            self.frame_hash_rect = None;
+            return true;
+        }
+        let full = HashRect { x: 0, y: 0, width: SCREEN_WIDTH, height: SCREEN_HEIGHT };
+        match rect.unwrap_or(full).clip(SCREEN_WIDTH, SCREEN_HEIGHT) {
+            Some(rect) => {
+                self.frame_hash_rect = Some(rect);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take up to `max` unread frame hashes, oldest first. Only the newest
+    /// `FRAME_HASH_QUEUE_SIZE` are kept if the host falls behind.
+    pub fn take_frame_hashes(&mut self, max: usize) -> Vec<FrameHash> {
+        let n = max.min(self.frame_hashes.len());
+        self.frame_hashes.drain(..n).collect()
+    }
+
+    fn record_frame_hash(&mut self, rect: HashRect) {
+        let crc = frame_hash::hash_pixels(&self.framebuffer, SCREEN_WIDTH, rect);
+        if self.frame_hashes.len() == frame_hash::FRAME_HASH_QUEUE_SIZE {
+            self.frame_hashes.pop_front();
+        }
+        self.frame_hashes.push_back(FrameHash { frame: self.frames_rendered, cycle: self.total_cycles, crc });
+    }
+
+    /// Render every frame the LCD controller completes and pass it to
+    /// `callback`, so a frontend can draw on vsync instead of polling
+    /// `render_frame()`. Frames dropped by frame skip are not passed on.
+    /// None removes it.
+    pub fn set_frame_callback(&mut self, callback: Option<FrameCallback>) {
+        self.frame_callback = callback;
+    }
+
+    /// Remove the frame callback and return it
+    pub fn take_frame_callback(&mut self) -> Option<FrameCallback> {
+        self.frame_callback.take()
+    }
+
+    /// Whether a frame callback is set (completed frames are already rendered)
+    pub fn has_frame_callback(&self) -> bool {
+        self.frame_callback.is_some()
+    }
+
+    fn frame_ready(&mut self) {
+        let rendered = self.frames_rendered;
+        self.render_frame();
+        if self.frames_rendered == rendered || !self.present_frame() {
+            return;
+        }
+        if let Some(callback) = &mut self.frame_callback {
+            callback(&self.presented, SCREEN_WIDTH);
+        }
+    }
+
+    // ========== Performance Governor ==========
+
+    /// Present only one of every `skip + 1` rendered frames; the presented
+    /// frame keeps the last one in between. Rendering, hashes, screenshots
+    /// and recordings still see every frame.
+    pub fn set_frame_skip(&mut self, skip: u32) {
+        self.frame_skip = skip;
+        self.frames_skipped = 0;
+    }
+
+    /// Current frame skip
+    pub fn frame_skip(&self) -> u32 {
+        self.frame_skip
+    }
+
+    /// Set the run loop fidelity/speed trade-off
+    pub fn set_accuracy_mode(&mut self, mode: AccuracyMode) {
+        self.accuracy = mode;
+    }
+
+    /// Current run loop fidelity/speed trade-off
+    pub fn accuracy_mode(&self) -> AccuracyMode {
+        self.accuracy
+    }
+
+    /// Enable the adaptive performance governor, or disable it with None.
+    /// While enabled the governor owns frame skip and accuracy mode; disabling
+    /// it returns them to full fidelity.
+    pub fn set_governor(&mut self, config: Option<GovernorConfig>) {
+        self.governor = config.map(Governor::new);
+        self.governor_last_cycles = self.total_cycles;
+        self.set_frame_skip(0);
+        self.accuracy = AccuracyMode::Full;
+    }
+
+    /// Governor state (None when disabled)
+    pub fn governor(&self) -> Option<&Governor> {
+        self.governor.as_ref()
+    }
+
+    /// Report the wall-clock time the host spent on the frame just finished
+    /// (running, rendering, and presenting). The emulated time is taken from
+    /// the cycles run since the previous report. Returns the governor's
+    /// decision if it changed a knob; no-op while the governor is disabled.
+    pub fn governor_report(&mut self, host_secs: f64) -> Option<GovernorDecision> {
+        let governor = self.governor.as_mut()?;
+        // total_cycles restarts at reset; count only cycles since then
+        let cycles = self.total_cycles.checked_sub(self.governor_last_cycles).unwrap_or(self.total_cycles);
+        self.governor_last_cycles = self.total_cycles;
+        let hz = Self::cpu_hz(self.bus.ports.control.cpu_speed());
+        let decision = governor.record_frame(cycles as f64 / hz, host_secs)?;
+
+        self.frame_skip = governor.frame_skip();
+        self.accuracy = governor.accuracy();
+        log_sub!(Cpu, Info, "GOVERNOR: speed={:.0}% -> {:?}", decision.speed * 100.0, decision.action);
+        Some(decision)
+    }
+
+    /// CPU clock in Hz for a CPU speed setting
+    fn cpu_hz(cpu_speed: u8) -> f64 {
+        match cpu_speed { 0 => 6e6, 1 => 12e6, 2 => 24e6, _ => 48e6 }
+    }
+
+    /// Take the governor's recorded decisions, oldest first
+    pub fn take_governor_decisions(&mut self) -> Vec<GovernorDecision> {
+        self.governor.as_mut().map_or_else(Vec::new, |g| g.take_decisions())
+    }
+
+    // ========== Speed Control ==========
+
+    /// Set how fast `run_paced` runs relative to the host clock
+    pub fn set_speed(&mut self, mode: SpeedMode) {
+        self.throttle.set_mode(mode);
+    }
+
+    /// Current `run_paced` speed
+    pub fn speed(&self) -> SpeedMode {
+        self.throttle.mode()
+    }
+
+    /// Run the emulated time due `host_secs` of wall-clock time after the
+    /// previous call, at the speed set with `set_speed`. Returns the cycles
+    /// executed. Call in a loop, sleeping `pace_sleep_secs()` in between;
+    /// see `throttle` for how jitter is corrected.
+    pub fn run_paced(&mut self, host_secs: f64) -> u32 {
+        let hz = self.cpu_clock_hz();
+        let budget = self.throttle.budget(host_secs);
+        let cycles = (budget * hz) as u32;
+        if cycles == 0 {
+            return 0;
+        }
+        let executed = self.run_cycles(cycles);
+        // Charge the whole budget when nothing can run (off, breakpoint),
+        // so the owed time doesn't burst out once it can
+        let ran = if executed == 0 { budget } else { executed as f64 / hz };
+        self.throttle.ran(ran);
+        executed
+    }
+
+    /// Host seconds to sleep before the next `run_paced` call
+    pub fn pace_sleep_secs(&self) -> f64 {
+        self.throttle.sleep_secs()
+    }
+
+    // ========== Flash Persistence ==========
+
+    /// Write modified flash sectors to `sink` automatically according to
+    /// `policy`, or stop with None. Sectors already modified are written on
+    /// the first flush; see `flash_persist` for when flushes happen.
+    pub fn set_flash_persistence(&mut self, sink: Option<Box<dyn FlashSink + Send>>, policy: PersistPolicy) {
+        self.flash_persistence = sink.map(|sink| FlashPersistence {
+            sink,
+            policy,
+            elapsed_secs: 0.0,
+            last_generation: self.bus.flash.write_generation(),
+            last_cycles: self.total_cycles,
+        });
+    }
+
+    /// Write modified flash sectors to the persistence sink now.
+    /// Returns the number of sectors written (0 if persistence is off).
+    /// On error the sectors stay dirty and are retried on the next flush.
+    pub fn flush_flash(&mut self) -> std::io::Result<usize> {
+        let Some(persist) = self.flash_persistence.as_mut() else {
+            return Ok(0);
+        };
+        let dirty = self.bus.flash.take_dirty_sectors();
+        let mut written = 0;
+        let result = (0..64).filter(|i| dirty & (1 << i) != 0).try_for_each(|sector| {
+            let data = self.bus.flash.sector(sector);
+            persist.sink.write_sector((sector * FLASH_DIRTY_SECTOR_SIZE) as u32, &data)?;
+            written += 1;
+            Ok(())
+        });
+        if let Err(e) = result.and_then(|()| persist.sink.finish()) {
+            self.bus.flash.restore_dirty_sectors(dirty);
+            log_sub!(Flash, Error, "FLASH_PERSIST: flush failed: {}", e);
+            return Err(e);
+        }
+        persist.elapsed_secs = 0.0;
+        if written > 0 {
+            log_sub!(Flash, Info, "FLASH_PERSIST: wrote {} sector(s)", written);
+        }
+        Ok(written)
+    }
+
+    /// Full 4MB flash image, for hosts that save it themselves
+    pub fn export_flash(&self) -> Vec<u8> {
+        self.bus.flash.data().into_owned()
+    }
+
+    /// Number of 64KB flash sectors modified since they were last exported
+    /// or flushed
+    pub fn flash_dirty_count(&self) -> usize {
+        self.bus.flash.dirty_sectors().count_ones() as usize
+    }
+
+    /// Take up to `max_sectors` modified 64KB sectors, lowest first, as
+    /// (byte offset into the image, contents). The rest stay dirty for the
+    /// next call. This shares dirty tracking with `set_flash_persistence`:
+    /// a sector exported here is not flushed to the sink, and vice versa.
+    pub fn export_dirty_flash(&mut self, max_sectors: usize) -> Vec<(u32, Vec<u8>)> {
+        let dirty = self.bus.flash.take_dirty_sectors();
+        let mut sectors = Vec::new();
+        let mut remaining = 0u64;
+        for sector in (0..64).filter(|i| dirty & (1 << i) != 0) {
+            if sectors.len() < max_sectors {
+                sectors.push(((sector * FLASH_DIRTY_SECTOR_SIZE) as u32, self.bus.flash.sector(sector)));
+            } else {
+                remaining |= 1 << sector;
+            }
+        }
+        self.bus.flash.restore_dirty_sectors(remaining);
+        sectors
     }
 
-    /// Render 8bpp indexed color mode (BPP=3).
-    /// Each VRAM byte is a palette index. The palette at LCD 0xE30200 maps indices to colors.
-    /// This is what the graphx library and all CE games use.
-    fn render_frame_8bpp(&mut self, upbase: u32) {
+    /// Flush dirty flash if the persistence policy says it is time
+    fn persist_flash_if_due(&mut self) {
+        let hz = Self::cpu_hz(self.bus.ports.control.cpu_speed());
+        let Some(persist) = self.flash_persistence.as_mut() else {
+            return;
+        };
+        // total_cycles restarts at reset; count only cycles since then
+        let cycles = self.total_cycles.checked_sub(persist.last_cycles).unwrap_or(self.total_cycles);
+        persist.last_cycles = self.total_cycles;
+        let generation = self.bus.flash.write_generation();
+        let quiet = generation == persist.last_generation;
+        persist.last_generation = generation;
+
+        if self.bus.flash.dirty_sectors() == 0 {
+            persist.elapsed_secs = 0.0;
+            return;
+        }
+        persist.elapsed_secs += cycles as f64 / hz;
+        let due = persist.policy.interval_secs.is_some_and(|secs| persist.elapsed_secs >= secs)
+            || (persist.policy.on_write_complete && quiet);
+        if due {
+            // Failures are logged and retried on the next check
+            let _ = self.flush_flash();
+        }
+    }
+
+    // ========== Cheats ==========
+
+    /// Add a memory poke applied every frame, returning its id
+    pub fn add_cheat(&mut self, cheat: Cheat) -> u32 {
+        self.cheats.add(cheat)
+    }
+
+    /// Remove a cheat. Returns false if the id is unknown.
+    pub fn remove_cheat(&mut self, id: u32) -> bool {
+        self.cheats.remove(id)
+    }
+
+    /// Enable or disable a cheat. Returns false if the id is unknown.
+    pub fn set_cheat_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        self.cheats.set_enabled(id, enabled)
+    }
+
+    /// Remove every cheat
+    pub fn clear_cheats(&mut self) {
+        self.cheats.clear();
+    }
+
+    /// All cheats with their ids, in the order they were added
+    pub fn cheats(&self) -> &[(u32, Cheat)] {
+        self.cheats.list()
+    }
+
+    /// Choose when cheats are applied (default: start of each `run_cycles()`)
+    pub fn set_cheat_point(&mut self, point: CheatPoint) {
+        self.cheats.set_point(point);
+    }
+
+    /// Apply cheats now, regardless of the chosen point.
+    /// Returns the number of pokes written.
+    pub fn apply_cheats(&mut self) -> usize {
+        self.cheats.apply(&mut self.bus)
+    }
+
+    /// Set the color profile applied to presented frames (takes effect on the
+    /// next `present_frame()`). `ColorProfile::CePanel` previews colors roughly
+    /// as the real panel shows them; the default is ideal sRGB.
+    pub fn set_color_profile(&mut self, profile: ColorProfile) {
+        if profile != self.color_transform.profile() {
+            self.color_transform = ColorTransform::new(profile);
+            self.presented_frame = None;
+        }
+    }
+
+    /// Get the color profile applied to presented frames
+    pub fn color_profile(&self) -> ColorProfile {
+        self.color_transform.profile()
+    }
+
+    /// Render palette-indexed modes (BPP=0-3: 1, 2, 4 or 8 bits per pixel).
+    /// Each VRAM byte holds `8 / bits` indices into the palette at LCD
+    /// 0xE30200, first pixel in the low bits unless BEPO is set. 8bpp is what
+    /// the graphx library and all CE games use.
+    fn render_frame_indexed(&mut self, upbase: u32, bits: usize) {
         let ram_offset = upbase.wrapping_sub(crate::memory::addr::RAM_START) as usize;
-        let needed = SCREEN_WIDTH * SCREEN_HEIGHT; // 1 byte per pixel
+        let needed = SCREEN_WIDTH * SCREEN_HEIGHT * bits / 8;
         let ram_data = self.bus.ram.data();
         // Copy palette to avoid borrow conflict in fallback path
         let palette = *self.bus.ports.lcd.palette_for_mode();
+        let msb_first = self.bus.ports.lcd.big_endian_pixels();
+        let per_byte = 8 / bits;
+        let mask = ((1u16 << bits) - 1) as u8;
+        let expand = |framebuffer: &mut [u32], i: usize, byte: u8| {
+            for p in 0..per_byte {
+                let shift = if msb_first { 8 - bits * (p + 1) } else { bits * p };
+                let index = (byte >> shift) & mask;
+                framebuffer[i * per_byte + p] = bgr565_to_argb8888(palette[index as usize]);
+            }
+        };
 
         if ram_offset < ram_data.len() && ram_offset + needed <= ram_data.len() {
             let vram = &ram_data[ram_offset..ram_offset + needed];
-            for (i, &index) in vram.iter().enumerate() {
-                let bgr565 = palette[index as usize];
-                self.framebuffer[i] = bgr565_to_argb8888(bgr565);
+            for (i, &byte) in vram.iter().enumerate() {
+                expand(&mut self.framebuffer, i, byte);
             }
         } else {
             // Fallback for out-of-range UPBASE
-            for i in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-                let vram_addr = upbase + i as u32;
-                let index = self.bus.peek_byte(vram_addr);
-                let bgr565 = palette[index as usize];
-                self.framebuffer[i] = bgr565_to_argb8888(bgr565);
+            for i in 0..needed {
+                let byte = self.bus.peek_byte(upbase + i as u32);
+                expand(&mut self.framebuffer, i, byte);
             }
         }
     }
@@ -1889,31 +3619,82 @@ impl Emu {
 
     // ========== State Persistence ==========
 
-    /// State format version (v9: LCD palette + cursor state in peripheral snapshot)
-    const STATE_VERSION: u32 = 10;
+    /// State format version (v11: tagged sections after flash)
+    const STATE_VERSION: u32 = 11;
+    /// Oldest version still loaded; v10 states just have no sections
+    const MIN_STATE_VERSION: u32 = 10;
     /// Magic bytes for state file identification
     const STATE_MAGIC: [u8; 4] = *b"CE84";
     /// Header size: magic(4) + version(4) + rom_hash(8) + data_len(4) = 20
     const STATE_HEADER_SIZE: usize = 20;
-    /// Metadata size: powered_on(1) + total_cycles(8) + boot_init_done(1) + padding(6) = 16
+    /// Metadata size: powered_on(1) + total_cycles(8) + boot_init_done(1) + flags(1) + padding(5) = 16
     const STATE_META_SIZE: usize = 16;
+    /// Metadata flag: state is a warm-boot snapshot (captured once the OS finished booting)
+    const STATE_FLAG_WARM_BOOT: u8 = 1 << 0;
+    /// Section header after flash: tag(4) + len(4). Loaders skip tags they do
+    /// not know and leave state without a section at its reset value, so new
+    /// sections can be added without a version bump.
+    const STATE_SECTION_HEADER_SIZE: usize = 8;
+    /// Section tag: SPI panel registers and frame memory
+    const STATE_SECTION_PANEL: [u8; 4] = *b"PANL";
+    /// Section tag: SHA256 accelerator
+    const STATE_SECTION_SHA256: [u8; 4] = *b"SHA2";
+    /// Section tag: keypad controller registers and its scan event timestamp
+    const STATE_SECTION_KEYPAD: [u8; 4] = *b"KPAD";
+    /// Section tag: watchdog timer
+    const STATE_SECTION_WATCHDOG: [u8; 4] = *b"WDOG";
+    /// Section tag: every control port register (the base peripheral state
+    /// only has the ones the OS cannot boot without)
+    const STATE_SECTION_CONTROL: [u8; 4] = *b"CTRL";
+    /// Section tag: USB host, OTG and device controller registers
+    const STATE_SECTION_USB: [u8; 4] = *b"USB0";
 
     /// Compute a simple hash of the ROM for state validation
     fn compute_rom_hash(&self) -> u64 {
         // FNV-1a hash of first 64KB of ROM (fast, good distribution)
-        let mut hash: u64 = 0xcbf29ce484222325;
         let rom_data = self.bus.flash.data();
-        let len = rom_data.len().min(65536);
-        for &byte in &rom_data[..len] {
-            hash ^= byte as u64;
-            hash = hash.wrapping_mul(0x100000001b3);
+        crate::fnv::fnv1a(&rom_data[..rom_data.len().min(65536)])
+    }
+
+    /// Let a repeating block instruction iterate only up to the next
+    /// scheduler event, so the event (and any interrupt it raises) is
+    /// handled mid-block as on hardware. Fast mode runs the whole block
+    /// and handles the events after it.
+    fn set_block_limit(&mut self) {
+        self.cpu.block_cycle_limit = match self.accuracy {
+            AccuracyMode::Full => self.bus.total_cycles() + self.scheduler.cycles_until_next_event(),
+            AccuracyMode::Fast => u64::MAX,
+        };
+    }
+
+    /// Remember a protection violation NMI so the restart it causes can be
+    /// attributed to it
+    fn note_violation(&mut self) {
+        let status = self.bus.ports.control.read(0x3D);
+        if status & 3 != 0 {
+            self.pending_violation = Some(ResetCause::Protection {
+                status,
+                addr: self.bus.nmi_violation_addr(),
+                pc: self.bus.nmi_violation_pc(),
+            });
+        }
+    }
+
+    /// The boot code answers a violation NMI by restarting through the reset
+    /// vector, which is the hardware reset the violation stands for
+    fn check_violation_restart(&mut self) {
+        if self.cpu.pc == 0 {
+            if let Some(cause) = self.pending_violation.take() {
+                log_sub!(Cpu, Warn, "RESET: {:?}", cause);
+                self.reset_count += 1;
+                self.last_reset_cause = cause;
+            }
         }
-        hash
     }
 
     /// Log NMI trigger details
     fn log_nmi(&mut self) {
-        log_evt!(
+        log_sub!(Interrupt, Warn,
             "NMI triggered: pc={:06X} sp={:06X} stack_limit={:06X} prot_start={:06X} prot_end={:06X} privileged={:06X} write_addr={:06X} raw_pc={:06X}",
             self.cpu.pc, self.cpu.sp(),
             self.bus.ports.control.stack_limit(),
@@ -1953,11 +3734,86 @@ impl Emu {
             + Self::STATE_META_SIZE
             + RAM_SIZE
             + FLASH_SIZE
+            + Self::STATE_SECTION_HEADER_SIZE * 6
+            + PanelStub::SNAPSHOT_SIZE
+            + Sha256Controller::SNAPSHOT_SIZE
+            + KeypadController::SNAPSHOT_SIZE + 8
+            + WatchdogController::SNAPSHOT_SIZE
+            + ControlPorts::SNAPSHOT_SIZE
+            + UsbController::SNAPSHOT_SIZE
+    }
+
+    /// Sections saved after flash, in order
+    fn state_sections(&self) -> [([u8; 4], Vec<u8>); 6] {
+        let mut keypad = self.bus.ports.keypad.to_bytes().to_vec();
+        keypad.extend_from_slice(&self.scheduler.raw_timestamp(EventId::Keypad).to_le_bytes());
+        [
+            (Self::STATE_SECTION_PANEL, self.bus.panel().to_bytes()),
+            (Self::STATE_SECTION_SHA256, self.bus.ports.sha256.to_bytes().to_vec()),
+            (Self::STATE_SECTION_KEYPAD, keypad),
+            (Self::STATE_SECTION_WATCHDOG, self.bus.ports.watchdog.to_bytes().to_vec()),
+            (Self::STATE_SECTION_CONTROL, self.bus.ports.control.to_bytes()),
+            (Self::STATE_SECTION_USB, self.bus.usb.to_bytes()),
+        ]
+    }
+
+    /// Smallest valid length of a section this build knows, None for tags
+    /// from newer builds
+    fn state_section_size(tag: [u8; 4]) -> Option<usize> {
+        Some(match tag {
+            Self::STATE_SECTION_PANEL => PanelStub::SNAPSHOT_SIZE,
+            Self::STATE_SECTION_SHA256 => Sha256Controller::SNAPSHOT_SIZE,
+            Self::STATE_SECTION_KEYPAD => KeypadController::SNAPSHOT_SIZE + 8,
+            Self::STATE_SECTION_WATCHDOG => WatchdogController::SNAPSHOT_SIZE,
+            Self::STATE_SECTION_CONTROL => ControlPorts::SNAPSHOT_SIZE,
+            Self::STATE_SECTION_USB => UsbController::SNAPSHOT_SIZE,
+            _ => return None,
+        })
+    }
+
+    /// Split the sections after flash into (tag, data). Fails on a header or
+    /// length that runs past the end, or a known section that is too short.
+    fn parse_state_sections(mut data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>, i32> {
+        let mut sections = Vec::new();
+        while !data.is_empty() {
+            let header = data.get(..Self::STATE_SECTION_HEADER_SIZE).ok_or(-105)?;
+            let tag: [u8; 4] = header[..4].try_into().unwrap();
+            let len = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+            data = &data[Self::STATE_SECTION_HEADER_SIZE..];
+            if len > data.len() || Self::state_section_size(tag).is_some_and(|size| len < size) {
+                return Err(-105); // Data corruption
+            }
+            sections.push((tag, &data[..len]));
+            data = &data[len..];
+        }
+        Ok(sections)
+    }
+
+    /// Restore the keypad section: controller, then its scan event
+    fn load_keypad_section(&mut self, data: &[u8]) -> Result<(), i32> {
+        let timestamp = data.get(KeypadController::SNAPSHOT_SIZE..KeypadController::SNAPSHOT_SIZE + 8).ok_or(-105)?;
+        self.bus.ports.keypad.from_bytes(data)?;
+        self.scheduler.set_raw_timestamp(EventId::Keypad, u64::from_le_bytes(timestamp.try_into().unwrap()));
+        Ok(())
+    }
+
+    /// Save the full emulator state into a new buffer
+    pub fn save_state_vec(&self) -> Result<Vec<u8>, i32> {
+        let mut buffer = vec![0u8; self.save_state_size()];
+        let written = self.save_state(&mut buffer)?;
+        buffer.truncate(written);
+        Ok(buffer)
     }
 
     /// Save emulator state to buffer
     /// Returns number of bytes written on success
     pub fn save_state(&self, buffer: &mut [u8]) -> Result<usize, i32> {
+        let _log = self.log_scope();
+        self.save_state_with_flags(buffer, 0)
+    }
+
+    /// Save emulator state with metadata flags (STATE_FLAG_*)
+    fn save_state_with_flags(&self, buffer: &mut [u8], flags: u8) -> Result<usize, i32> {
         use crate::cpu::Cpu;
         use crate::memory::addr::{FLASH_SIZE, RAM_SIZE};
         use crate::peripherals::Peripherals;
@@ -2000,24 +3856,73 @@ impl Emu {
         buffer[pos] = if self.powered_on { 1 } else { 0 }; pos += 1;
         buffer[pos..pos+8].copy_from_slice(&self.total_cycles.to_le_bytes()); pos += 8;
         buffer[pos] = if self.boot_init_done { 1 } else { 0 }; pos += 1;
-        pos += 6; // Padding to 16 bytes
+        buffer[pos] = flags; pos += 1;
+        buffer[pos..pos+5].fill(0); pos += 5; // Padding to 16 bytes
 
         // Write RAM
+        // RAM is allocated lazily on first write; unallocated RAM reads as zero
         let ram_data = self.bus.ram.data();
-        buffer[pos..pos+RAM_SIZE].copy_from_slice(ram_data);
+        if ram_data.is_empty() {
+            buffer[pos..pos+RAM_SIZE].fill(0);
+        } else {
+            buffer[pos..pos+RAM_SIZE].copy_from_slice(ram_data);
+        }
         pos += RAM_SIZE;
 
         // Write Flash
         let flash_data = self.bus.flash.data();
-        buffer[pos..pos+FLASH_SIZE].copy_from_slice(flash_data);
+        buffer[pos..pos+FLASH_SIZE].copy_from_slice(&flash_data);
         pos += FLASH_SIZE;
 
+        // Write sections
+        for (tag, data) in self.state_sections() {
+            buffer[pos..pos+4].copy_from_slice(&tag); pos += 4;
+            buffer[pos..pos+4].copy_from_slice(&(data.len() as u32).to_le_bytes()); pos += 4;
+            buffer[pos..pos+data.len()].copy_from_slice(&data);
+            pos += data.len();
+        }
+
         log_evt!("STATE_SAVED: {} bytes", pos);
         Ok(pos)
     }
 
+    /// Hash of everything a save state captures (CPU, scheduler, peripherals,
+    /// metadata, RAM and flash), without allocating a state buffer.
+    /// Two emulators with equal hashes would produce identical save states.
+    pub fn state_hash(&self) -> u64 {
+        use crate::memory::addr::RAM_SIZE;
+
+        let mut hasher = Fnv1a::new();
+        // Length after each section so shifting bytes between them changes the hash
+        let mut section = |data: &[u8]| {
+            hasher.write(data);
+            hasher.write(&(data.len() as u64).to_le_bytes());
+        };
+        section(&self.cpu.to_bytes());
+        section(&self.scheduler.to_bytes());
+        section(&self.bus.ports.to_bytes());
+        section(&[self.powered_on as u8, self.boot_init_done as u8]);
+        section(&self.total_cycles.to_le_bytes());
+        section(&self.bus.flash.data());
+        for (tag, data) in self.state_sections() {
+            section(&tag);
+            section(&data);
+        }
+        // Unallocated RAM reads as zero, same as in save_state
+        let ram_data = self.bus.ram.data();
+        if ram_data.is_empty() {
+            hasher.write_zeros(RAM_SIZE);
+            hasher.write(&(RAM_SIZE as u64).to_le_bytes());
+        } else {
+            hasher.write(ram_data);
+            hasher.write(&(ram_data.len() as u64).to_le_bytes());
+        }
+        hasher.finish()
+    }
+
     /// Load emulator state from buffer
     pub fn load_state(&mut self, buffer: &[u8]) -> Result<(), i32> {
+        let _log = self.log_scope();
         use crate::cpu::Cpu;
         use crate::memory::addr::{FLASH_SIZE, RAM_SIZE};
         use crate::peripherals::Peripherals;
@@ -2038,7 +3943,7 @@ impl Emu {
 
         // Check version
         let version = u32::from_le_bytes(buffer[pos..pos+4].try_into().unwrap());
-        if version != Self::STATE_VERSION {
+        if !(Self::MIN_STATE_VERSION..=Self::STATE_VERSION).contains(&version) {
             return Err(-103); // Version mismatch
         }
         pos += 4;
@@ -2060,6 +3965,10 @@ impl Emu {
         if data_len < expected_data || buffer.len() < pos + data_len {
             return Err(-105); // Data corruption
         }
+        // Check every section before anything is overwritten, so a corrupt
+        // state leaves the emulator as it was
+        let sections_start = pos + expected_data;
+        let sections = Self::parse_state_sections(&buffer[sections_start..pos + data_len])?;
 
         // Load CPU state
         self.cpu.from_bytes(&buffer[pos..pos+Cpu::SNAPSHOT_SIZE])?;
@@ -2069,15 +3978,22 @@ impl Emu {
         self.scheduler.from_bytes(&buffer[pos..pos+Scheduler::SNAPSHOT_SIZE])?;
         pos += Scheduler::SNAPSHOT_SIZE;
 
-        // Load peripheral state
+        // Load peripheral state. It only has some control ports; the rest
+        // start from reset unless the CTRL section has them.
+        self.bus.ports.control.reset();
         self.bus.ports.from_bytes(&buffer[pos..pos+Peripherals::SNAPSHOT_SIZE])?;
         pos += Peripherals::SNAPSHOT_SIZE;
+        // States saved while the OS timer was polled have no event for it
+        if !self.scheduler.is_active(EventId::OsTimer) {
+            self.scheduler.set(EventId::OsTimer, 0);
+        }
 
         // Load Emu metadata
         self.powered_on = buffer[pos] != 0; pos += 1;
         self.total_cycles = u64::from_le_bytes(buffer[pos..pos+8].try_into().unwrap()); pos += 8;
         self.boot_init_done = buffer[pos] != 0; pos += 1;
-        pos += 6; // Skip padding
+        pos += 1; // Flags (see state_flags)
+        pos += 5; // Skip padding
 
         // Load RAM
         self.bus.ram.load_data(&buffer[pos..pos+RAM_SIZE]);
@@ -2085,6 +4001,28 @@ impl Emu {
 
         // Load Flash
         self.bus.flash.load_data(&buffer[pos..pos+FLASH_SIZE]);
+        pos += FLASH_SIZE;
+
+        debug_assert_eq!(pos, sections_start);
+
+        // Load sections; anything not in the state starts from reset
+        self.bus.spi().panel_mut().reset();
+        self.bus.ports.sha256.reset();
+        self.bus.ports.keypad.reset();
+        self.bus.ports.watchdog.reset();
+        self.bus.usb.reset();
+        self.serial_peer_disconnected();
+        for (tag, data) in sections {
+            match tag {
+                Self::STATE_SECTION_PANEL => self.bus.spi().panel_mut().from_bytes(data)?,
+                Self::STATE_SECTION_SHA256 => self.bus.ports.sha256.from_bytes(data)?,
+                Self::STATE_SECTION_KEYPAD => self.load_keypad_section(data)?,
+                Self::STATE_SECTION_WATCHDOG => self.bus.ports.watchdog.from_bytes(data)?,
+                Self::STATE_SECTION_CONTROL => self.bus.ports.control.from_bytes(data)?,
+                Self::STATE_SECTION_USB => self.bus.usb.from_bytes(data)?,
+                _ => {} // From a newer build
+            }
+        }
 
         // Sync bus cycle counter with restored total_cycles.
         // load_rom() → reset() zeroed bus.cycles, but total_cycles was restored
@@ -2092,6 +4030,9 @@ impl Emu {
         // self.bus.total_cycles() in run_cycles() would overwrite the restored
         // value, causing the `executed` return to wrap.
         self.bus.set_total_cycles(self.total_cycles);
+        if let Some(usage) = &mut self.cpu_usage {
+            usage.rebase(self.total_cycles);
+        }
 
         // Clear stale cpu_speed_written flag left by from_bytes calling
         // control.write(0x01, ...).  Without this, the next control-port
@@ -2117,6 +4058,75 @@ impl Emu {
         Ok(())
     }
 
+    /// Read the metadata flags (STATE_FLAG_*) of a save state without loading it.
+    /// Returns None if the buffer is not a valid state for this format version.
+    fn state_flags(buffer: &[u8]) -> Option<u8> {
+        use crate::cpu::Cpu;
+        use crate::peripherals::Peripherals;
+        use crate::scheduler::Scheduler;
+
+        let flags_pos = Self::STATE_HEADER_SIZE
+            + Cpu::SNAPSHOT_SIZE
+            + Scheduler::SNAPSHOT_SIZE
+            + Peripherals::SNAPSHOT_SIZE
+            + 10; // powered_on(1) + total_cycles(8) + boot_init_done(1)
+        if buffer.len() <= flags_pos || buffer[0..4] != Self::STATE_MAGIC {
+            return None;
+        }
+        let version = u32::from_le_bytes(buffer[4..8].try_into().unwrap());
+        if !(Self::MIN_STATE_VERSION..=Self::STATE_VERSION).contains(&version) {
+            return None;
+        }
+        Some(buffer[flags_pos])
+    }
+
+    /// Check if a save state is a warm-boot snapshot
+    pub fn is_warm_boot_state(buffer: &[u8]) -> bool {
+        Self::state_flags(buffer).is_some_and(|f| f & Self::STATE_FLAG_WARM_BOOT != 0)
+    }
+
+    /// Boot the loaded ROM to the home screen and capture a warm-boot snapshot.
+    ///
+    /// Powers on (if needed) and runs until the OS is idle in GetKey, then saves
+    /// the state tagged as a warm-boot snapshot. Frontends store this once per
+    /// ROM and pass it to `boot_from_warm_state()` on later launches to skip the
+    /// boot sequence. Files should be sent before capturing, since `send_file()`
+    /// only works before power-on.
+    ///
+    /// Error codes: -10 = ROM not loaded, -106 = boot did not reach idle within max_cycles,
+    /// plus save_state errors.
+    pub fn capture_warm_boot_state(&mut self, max_cycles: u64) -> Result<Vec<u8>, i32> {
+        let _log = self.log_scope();
+        if !self.rom_loaded {
+            return Err(-10);
+        }
+        if !self.powered_on {
+            self.power_on();
+        }
+        let elapsed = self.run_until_idle(max_cycles).ok_or(-106)?;
+        log_evt!("WARM_BOOT_CAPTURED after {} cycles", elapsed);
+
+        let mut buffer = vec![0u8; self.save_state_size()];
+        let written = self.save_state_with_flags(&mut buffer, Self::STATE_FLAG_WARM_BOOT)?;
+        buffer.truncate(written);
+        Ok(buffer)
+    }
+
+    /// Start a session from a warm-boot snapshot instead of running the ROM boot.
+    ///
+    /// The ROM must already be loaded (the snapshot is checked against its hash).
+    /// Error codes: -107 = not a warm-boot snapshot, plus load_state errors.
+    pub fn boot_from_warm_state(&mut self, buffer: &[u8]) -> Result<(), i32> {
+        match Self::state_flags(buffer) {
+            None => return Err(-102), // Invalid magic / version
+            Some(flags) if flags & Self::STATE_FLAG_WARM_BOOT == 0 => return Err(-107),
+            Some(_) => {}
+        }
+        self.load_state(buffer)?;
+        log_evt!("WARM_BOOT pc={:06X}", self.cpu.pc);
+        Ok(())
+    }
+
     /// Get the last stop reason
     pub fn last_stop_reason(&self) -> StopReason {
         self.last_stop
@@ -2199,10 +4209,70 @@ impl Emu {
     }
 
     /// Get raw flash data (4MB) — useful for baking programs into a ROM file
-    pub fn flash_data(&self) -> &[u8] {
+    /// Borrowed for heap-backed flash; a copy when the ROM is memory-mapped.
+    pub fn flash_data(&self) -> std::borrow::Cow<'_, [u8]> {
         self.bus.flash.data()
     }
 
+    /// Start reporting writes by user programs (code running from RAM) to
+    /// OS-owned RAM: the system flags, the VAT, and the stack above the
+    /// current SP, plus any `extra` regions. Enable it just before launching
+    /// the program so the stack frames of its caller are the ones guarded.
+    /// Earlier reports are cleared.
+    pub fn enable_sandbox(&mut self, extra: Vec<GuardRegion>) {
+        let mut regions = vec![GuardRegion::new(
+            sandbox::SYSTEM_FLAGS_START,
+            sandbox::SYSTEM_FLAGS_END,
+            GuardKind::SystemFlags,
+        )];
+        let sp = self.cpu.sp();
+        if (crate::memory::addr::RAM_START..=sandbox::STACK_TOP).contains(&sp) {
+            regions.push(GuardRegion::new(sp, sandbox::STACK_TOP, GuardKind::CallerStack));
+        }
+        regions.extend(extra);
+        self.bus.sandbox.enable(regions);
+        self.refresh_sandbox_vat();
+    }
+
+    /// Stop reporting; unread violations can still be taken
+    pub fn disable_sandbox(&mut self) {
+        self.bus.sandbox.disable();
+    }
+
+    /// Take up to `max` unread sandbox violations, oldest first
+    pub fn take_sandbox_violations(&mut self, max: usize) -> Vec<SandboxViolation> {
+        self.bus.sandbox.take_violations(max)
+    }
+
+    /// Sandbox violations dropped because the host did not take them in time
+    pub fn sandbox_dropped(&self) -> u64 {
+        self.bus.sandbox.dropped()
+    }
+
+    /// Take all unread sandbox violations as readable text, one per line
+    pub fn sandbox_report(&mut self) -> String {
+        let mut report = String::new();
+        for v in self.take_sandbox_violations(usize::MAX) {
+            report.push_str(&format!(
+                "PC={:06X} wrote {:06X} {:02X}->{:02X} ({:?}) at cycle {}\n",
+                v.pc, v.addr, v.old, v.value, v.kind, v.cycle
+            ));
+        }
+        let dropped = self.sandbox_dropped();
+        if dropped > 0 {
+            report.push_str(&format!("{} more dropped\n", dropped));
+        }
+        report
+    }
+
+    /// Track the VAT, whose bottom (`pTemp`) moves as variables are created
+    fn refresh_sandbox_vat(&mut self) {
+        let ptemp = (0..3).fold(0u32, |acc, i| acc | (self.bus.peek_byte(sandbox::PTEMP_ADDR + i) as u32) << (8 * i));
+        if ptemp > sandbox::STACK_TOP && ptemp < sandbox::SYM_TABLE_END {
+            self.bus.sandbox.set_region(GuardRegion::new(ptemp + 1, sandbox::SYM_TABLE_END, GuardKind::Vat));
+        }
+    }
+
     /// Peek at a memory byte without affecting emulation state
     pub fn peek_byte(&mut self, addr: u32) -> u8 {
         self.bus.peek_byte(addr)
@@ -2213,6 +4283,19 @@ impl Emu {
         self.bus.write_byte(addr, value);
     }
 
+    /// Read `buf.len()` bytes from `addr`, as the CPU would (`side_effects`)
+    /// or as a side-effect-free peek. Emulated timing is not affected.
+    pub fn read_memory(&mut self, addr: u32, buf: &mut [u8], side_effects: bool) {
+        self.bus.read_block(addr, buf, side_effects);
+    }
+
+    /// Write `data` to `addr`, as the CPU would (`side_effects`) or as a
+    /// direct poke that bypasses flash commands and memory protection.
+    /// Emulated timing is not affected.
+    pub fn write_memory(&mut self, addr: u32, data: &[u8], side_effects: bool) {
+        self.bus.write_block(addr, data, side_effects);
+    }
+
     // === Breakpoint API ===
 
     /// Set a PC breakpoint. run_cycles will return early when PC hits this address.
@@ -2232,6 +4315,242 @@ impl Emu {
         self.breakpoint_hit
     }
 
+}
+
+#[cfg(feature = "debugger")]
+impl Emu {
+    // === Debugger API ===
+
+    /// Add a breakpoint; runs stop before executing the instruction at `pc`
+    /// with a `DebugEvent`. Returns the breakpoint's ID.
+    pub fn add_breakpoint(&mut self, pc: u32) -> u32 {
+        self.debugger.add_breakpoint(pc)
+    }
+
+    /// Remove a breakpoint by ID. Returns false if there is none.
+    pub fn remove_breakpoint(&mut self, id: u32) -> bool {
+        self.debugger.remove_breakpoint(id)
+    }
+
+    /// Breakpoints as (ID, PC)
+    pub fn breakpoints(&self) -> &[(u32, u32)] {
+        self.debugger.breakpoints()
+    }
+
+    /// Watch memory `start..=end` for the `debug::access` bits; runs stop
+    /// after the accessing instruction. Returns the ID, or None if the range
+    /// or bits are empty.
+    pub fn add_watchpoint(&mut self, start: u32, end: u32, access: u8) -> Option<u32> {
+        self.bus.watchpoints.add_memory(start, end, access)
+    }
+
+    /// Watch I/O ports `start..=end` (IN/OUT) for reads and/or writes
+    pub fn add_port_watchpoint(&mut self, start: u16, end: u16, access: u8) -> Option<u32> {
+        self.bus.watchpoints.add_port(start, end, access)
+    }
+
+    /// Remove a memory or port watchpoint by ID. Returns false if there is none.
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        self.bus.watchpoints.remove(id)
+    }
+
+    /// Why the last run stopped for the debugger, if it did
+    pub fn take_debug_event(&mut self) -> Option<DebugEvent> {
+        self.debug_event.take()
+    }
+
+    /// Run up to `max_cycles`, returning the debugger event that stopped the
+    /// run (None if the cycles ran out first)
+    pub fn run_until_break(&mut self, max_cycles: u32) -> Option<DebugEvent> {
+        self.run_cycles(max_cycles);
+        self.take_debug_event()
+    }
+
+    /// Execute one instruction. Returns a step event, or the watchpoint the
+    /// instruction hit; None if the emulator cannot run.
+    pub fn step_into(&mut self) -> Option<DebugEvent> {
+        self.bus.watchpoints.take_hit();
+        self.step()?;
+        // A block instruction that yielded at an event is still the same
+        // instruction; finish it
+        while self.cpu.in_block() {
+            self.step()?;
+        }
+        let pc = self.cpu.pc;
+        self.debugger.stopped_at(pc);
+        Some(self.bus.watchpoints.take_hit().unwrap_or(Self::step_event(pc)))
+    }
+
+    /// Execute one instruction, running a CALL or RST through to its return.
+    /// Breakpoints and watchpoints inside the call still stop it; None if
+    /// `max_cycles` ran out first.
+    pub fn step_over(&mut self, max_cycles: u32) -> Option<DebugEvent> {
+        let pc = self.cpu.pc;
+        let sp = self.cpu.sp();
+        let addr = self.cpu.mask_addr_instr(pc);
+        let bytes: [u8; 6] = std::array::from_fn(|i| self.bus.peek_byte(addr.wrapping_add(i as u32)));
+        let instr = crate::disasm::decode(&bytes, self.cpu.adl);
+        if instr.branch.is_none_or(|b| b.kind != crate::disasm::BranchKind::Call) {
+            return self.step_into();
+        }
+
+        let mask = if self.cpu.adl { 0xFFFFFF } else { 0xFFFF };
+        let next = pc.wrapping_add(instr.length as u32) & mask;
+        let event = self.step_into()?;
+        // Condition false, or a watchpoint hit by the CALL itself
+        if event.kind != DebugEventKind::Step || self.cpu.pc == next {
+            return Some(event);
+        }
+        self.debugger.set_step_over(next, sp);
+        let event = self.run_until_break(max_cycles);
+        self.debugger.clear_step();
+        event
+    }
+
+    /// Run until the current function returns, stopping at the instruction
+    /// after the call. Returns from functions it calls (and interrupt
+    /// handlers) don't count. None if `max_cycles` ran out first.
+    pub fn step_out(&mut self, max_cycles: u32) -> Option<DebugEvent> {
+        self.debugger.set_step_out(self.cpu.sp());
+        let event = self.run_until_break(max_cycles);
+        self.debugger.clear_step();
+        event
+    }
+
+    /// Read a register by name
+    pub fn register(&self, reg: Register) -> u32 {
+        let cpu = &self.cpu;
+        let pair = |hi: u8, lo: u8| (hi as u32) << 8 | lo as u32;
+        match reg {
+            Register::Af => pair(cpu.a, cpu.f),
+            Register::Bc => cpu.bc,
+            Register::De => cpu.de,
+            Register::Hl => cpu.hl,
+            Register::Ix => cpu.ix,
+            Register::Iy => cpu.iy,
+            Register::Sp => cpu.sp(),
+            Register::Pc => cpu.pc,
+            Register::AfPrime => pair(cpu.a_prime, cpu.f_prime),
+            Register::BcPrime => cpu.bc_prime,
+            Register::DePrime => cpu.de_prime,
+            Register::HlPrime => cpu.hl_prime,
+            Register::Spl => cpu.spl,
+            Register::Sps => cpu.sps,
+            Register::I => cpu.i as u32,
+            Register::R => cpu.r as u32,
+            Register::Mbase => cpu.mbase as u32,
+            Register::Adl => cpu.adl as u32,
+        }
+    }
+
+    /// Write a register by name, truncating `value` to its width. Writing PC
+    /// refills the prefetch so the next instruction comes from the new PC.
+    pub fn set_register(&mut self, reg: Register, value: u32) {
+        let value = value & ((1u64 << reg.bits()) - 1) as u32;
+        let cpu = &mut self.cpu;
+        match reg {
+            Register::Af => (cpu.a, cpu.f) = ((value >> 8) as u8, value as u8),
+            Register::Bc => cpu.bc = value,
+            Register::De => cpu.de = value,
+            Register::Hl => cpu.hl = value,
+            Register::Ix => cpu.ix = value,
+            Register::Iy => cpu.iy = value,
+            Register::Sp => cpu.set_sp(value),
+            Register::Pc => {
+                cpu.pc = value;
+                self.cpu.init_prefetch(&mut self.bus);
+            }
+            Register::AfPrime => (cpu.a_prime, cpu.f_prime) = ((value >> 8) as u8, value as u8),
+            Register::BcPrime => cpu.bc_prime = value,
+            Register::DePrime => cpu.de_prime = value,
+            Register::HlPrime => cpu.hl_prime = value,
+            Register::Spl => cpu.spl = value,
+            Register::Sps => cpu.sps = value,
+            Register::I => cpu.i = value as u16,
+            Register::R => cpu.r = value as u8,
+            Register::Mbase => cpu.mbase = value as u8,
+            Register::Adl => {
+                cpu.adl = value != 0;
+                cpu.l = cpu.adl;
+                cpu.il = cpu.adl;
+            }
+        }
+    }
+
+    fn step_event(pc: u32) -> DebugEvent {
+        DebugEvent {
+            kind: DebugEventKind::Step,
+            access: debug::AccessKind::Execute,
+            value: 0,
+            id: 0,
+            pc,
+            addr: pc,
+        }
+    }
+
+    /// True if the run loop has breakpoints, step targets or watchpoints to check
+    #[inline]
+    fn debug_active(&self) -> bool {
+        self.debugger.is_active() || self.bus.watchpoints.is_active()
+    }
+
+    /// Check breakpoints and step targets before the next instruction.
+    /// Returns true (with `debug_event` set) if the run should stop.
+    fn check_debug_break(&mut self) -> bool {
+        let pc = self.cpu.pc;
+        let is_return = self.debugger.wants_return_check() && {
+            let addr = self.cpu.mask_addr_instr(pc);
+            let bytes: [u8; 3] = std::array::from_fn(|i| self.bus.peek_byte(addr.wrapping_add(i as u32)));
+            debug::is_return(&bytes)
+        };
+        let Some(event) = self.debugger.check(pc, self.cpu.sp(), is_return, &self.bus.watchpoints) else {
+            return false;
+        };
+        self.debugger.stopped_at(pc);
+        self.debug_event = Some(event);
+        true
+    }
+
+    /// After a run: pick up a watchpoint hit. Returns true if the run
+    /// stopped for the debugger.
+    fn finish_debug_stop(&mut self) -> bool {
+        if let Some(event) = self.bus.watchpoints.take_hit() {
+            self.debugger.stopped_at(self.cpu.pc);
+            self.debug_event = Some(event);
+        }
+        self.debug_event.is_some()
+    }
+}
+
+impl Emu {
+    /// Set the PC breakpoint on a symbol. Returns false if it is not defined.
+    pub fn set_breakpoint_at_symbol(&mut self, name: &str) -> bool {
+        match self.symbols.addr_of(name) {
+            Some(addr) => {
+                self.set_breakpoint(addr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // === Symbol API ===
+
+    /// Debugger symbols
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Debugger symbols, for hosts that load their own
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    /// `addr` as `symbol+offset` if a symbol covers it, else hex
+    pub fn describe_addr(&self, addr: u32) -> String {
+        self.symbols.describe(addr)
+    }
+
     // === Debug port API ===
 
     /// Enable debug port interception (CE toolchain: 0xFB0000=stdout, 0xFC0000=stderr)
@@ -2278,6 +4597,7 @@ impl Emu {
     /// - CLEAR = 0x09
     /// - Numbers: '0' = 0x8E, '1' = 0x8F, ... '9' = 0x97
     pub fn send_key(&mut self, key: u16) -> bool {
+        let _log = self.log_scope();
         const CE_KBD_KEY: u32 = 0xD0058C;
         const CE_KEY_EXTEND: u32 = 0xD0058E;
         const CE_GRAPH_FLAGS2: u32 = 0xD0009F;
@@ -2302,7 +4622,7 @@ impl Emu {
             let verify_key = self.peek_byte(CE_KBD_KEY);
             let verify_extend = self.peek_byte(CE_KEY_EXTEND);
             let verify_flags = self.peek_byte(CE_GRAPH_FLAGS2);
-            log_evt!("SEND_KEY: key=0x{:04X} wrote kbdKey=0x{:02X} keyExtend=0x{:02X} flags=0x{:02X}",
+            log_sub!(Keypad, Info, "SEND_KEY: key=0x{:04X} wrote kbdKey=0x{:02X} keyExtend=0x{:02X} flags=0x{:02X}",
                 key, verify_key, verify_extend, verify_flags);
         }
         true
@@ -2320,6 +4640,73 @@ impl Emu {
         self.send_key(key)
     }
 
+    /// `send_key`, first waiting for the OS to take the previous key, then
+    /// for it to take this one. False if it did not within `max_cycles`.
+    pub(crate) fn send_key_wait(&mut self, key: u16, max_cycles: u64) -> bool {
+        const CE_GRAPH_FLAGS2: u32 = 0xD0009F;
+        const CE_KEY_READY: u8 = 1 << 5;
+        const POLL_CYCLES: u32 = 48_000;
+
+        let mut waited = 0u64;
+        let mut sent = false;
+        while waited < max_cycles {
+            if self.peek_byte(CE_GRAPH_FLAGS2) & CE_KEY_READY == 0 {
+                if sent {
+                    return true;
+                }
+                sent = self.send_key(key);
+            }
+            let ran = self.run_cycles(POLL_CYCLES);
+            if ran == 0 {
+                return false;
+            }
+            waited += ran as u64;
+        }
+        false
+    }
+
+    /// Evaluate `expr` on the homescreen through TI-OS and read back `Ans`.
+    ///
+    /// The OS must be booted and waiting on the homescreen. The entry line is
+    /// cleared, the expression typed (see `eval` for the syntax) and ENTER
+    /// pressed; an OS error is dismissed with Quit so the next call starts on
+    /// the homescreen again. Each step may take up to `EVAL_TIMEOUT_SECS`.
+    pub fn evaluate(&mut self, expr: &str) -> EvalResult {
+        let _log = self.log_scope();
+        let keys = eval::expression_keys(expr)?;
+        let limit = (eval::EVAL_TIMEOUT_SECS * self.cpu_clock_hz()) as u64;
+        self.run_until_idle(limit).ok_or(EvalError::Timeout)?;
+
+        for key in std::iter::once(eval::KEY_CLEAR).chain(keys) {
+            if !self.send_key_wait(key, limit) {
+                return Err(EvalError::Timeout);
+            }
+        }
+        // The OS only writes errNo on error
+        self.bus.poke_byte(eval::ERR_NO_ADDR, 0);
+        if !self.send_key_wait(eval::KEY_ENTER, limit) {
+            return Err(EvalError::Timeout);
+        }
+        self.run_until_idle(limit).ok_or(EvalError::Timeout)?;
+
+        let err = self.bus.peek_byte(eval::ERR_NO_ADDR) & 0x7F;
+        if err != 0 {
+            log_evt!("EVAL error={} expr={:?}", err, expr);
+            self.send_key_wait(eval::KEY_QUIT_ERROR, limit);
+            self.run_until_idle(limit);
+            return Err(EvalError::Os(err));
+        }
+        let prog_ptr = (0..3).fold(0u32, |acc, i| acc | (self.bus.peek_byte(eval::PROG_PTR_ADDR + i) as u32) << (8 * i));
+        let bus = &mut self.bus;
+        eval::read_ans(|addr| bus.peek_byte(addr), sandbox::SYM_TABLE_END, prog_ptr).ok_or(EvalError::NoAnswer)
+    }
+
+    /// Evaluate each expression in order (see `evaluate`). A failed
+    /// expression does not stop the rest.
+    pub fn evaluate_batch<S: AsRef<str>>(&mut self, exprs: &[S]) -> Vec<EvalResult> {
+        exprs.iter().map(|expr| self.evaluate(expr.as_ref())).collect()
+    }
+
     /// Get the CPU's A register value
     pub fn reg_a(&self) -> u8 {
         self.cpu.a
@@ -2439,6 +4826,11 @@ impl Emu {
         self.bus.ports.control.cpu_speed()
     }
 
+    /// Current CPU clock rate in Hz
+    pub fn cpu_clock_hz(&self) -> f64 {
+        Self::cpu_hz(self.bus.ports.control.cpu_speed())
+    }
+
     /// Get scheduler base_ticks
     pub fn scheduler_base_ticks(&self) -> u64 {
         self.scheduler.base_ticks
@@ -2454,6 +4846,26 @@ impl Emu {
         self.cpu.any_key_wake
     }
 
+    /// Raise interrupt lines (`peripherals::interrupt::sources` bits), as a
+    /// peripheral asserting its output. The CPU sees the interrupt if it is
+    /// enabled in the controller.
+    pub fn raise_irq(&mut self, source: u32) {
+        self.set_irq(source, true);
+    }
+
+    /// Lower interrupt lines. Sources in latched mode stay pending until the
+    /// OS acknowledges them.
+    pub fn clear_irq(&mut self, source: u32) {
+        self.set_irq(source, false);
+    }
+
+    /// Drive interrupt lines high or low (CEmu's intrpt_set) and update the
+    /// CPU's pending IRQ
+    pub fn set_irq(&mut self, source: u32, active: bool) {
+        self.bus.ports.interrupt.set(source, active);
+        self.cpu.irq_pending = self.bus.ports.interrupt.irq_pending();
+    }
+
     /// Read full interrupt status mask
     pub fn interrupt_status(&self) -> u32 {
         let lo = self.bus.ports.interrupt.read(0x00) as u32;
@@ -2481,6 +4893,17 @@ impl Emu {
         lo | b1 | b2 | b3
     }
 
+    /// Read full interrupt latched-mode mask (sources that stay pending
+    /// until acknowledged)
+    pub fn interrupt_latched(&self) -> u32 {
+        self.bus.ports.interrupt.latched_word(0)
+    }
+
+    /// Read full interrupt inversion mask (active-low sources)
+    pub fn interrupt_inverted(&self) -> u32 {
+        self.bus.ports.interrupt.inverted_word(0)
+    }
+
     /// Read a control port byte (offset from 0xE00000)
     pub fn control_read(&self, offset: u32) -> u8 {
         self.bus.ports.control.read(offset)
@@ -2696,167 +5119,2739 @@ impl Emu {
         self.bus.write_tracer.clear_filter_range();
     }
 
-    /// Get detailed write log (Vec of (addr, value, cycle))
-    pub fn get_write_log(&self) -> Vec<(u32, u8, u64)> {
-        self.bus.write_tracer.detailed_log()
-            .iter()
-            .map(|rec| (rec.addr, rec.value, rec.cycle))
-            .collect()
+    // ========== Port Access Log ==========
+
+    /// Log I/O port reads and writes in the ranges set in `range_mask`
+    /// (bit n = port range n, e.g. `1 << 0x0` for control ports)
+    pub fn enable_port_log(&mut self, range_mask: u16) {
+        self.bus.port_log.enable(range_mask);
     }
 
-    /// Get CPU register dump for debugging
-    pub fn dump_registers(&self) -> String {
-        format!(
-            "AF={:02X}{:02X} BC={:06X} DE={:06X} HL={:06X}\n\
-             IX={:06X} IY={:06X} SP={:06X} PC={:06X}\n\
-             Flags: S={} Z={} H={} PV={} N={} C={}\n\
-             ADL={} IFF1={} IFF2={} IM={:?} MBASE={:02X}",
-            self.cpu.a,
-            self.cpu.f,
-            self.cpu.bc,
-            self.cpu.de,
-            self.cpu.hl,
-            self.cpu.ix,
-            self.cpu.iy,
-            self.cpu.sp(),
-            self.cpu.pc,
-            (self.cpu.f >> 7) & 1,
-            (self.cpu.f >> 6) & 1,
-            (self.cpu.f >> 4) & 1,
-            (self.cpu.f >> 2) & 1,
-            (self.cpu.f >> 1) & 1,
-            self.cpu.f & 1,
-            self.cpu.adl,
-            self.cpu.iff1,
-            self.cpu.iff2,
-            self.cpu.im,
-            self.cpu.mbase,
-        )
+    /// Stop logging port accesses (keeps what was recorded)
+    pub fn disable_port_log(&mut self) {
+        self.bus.port_log.disable();
     }
 
-    // === Full I/O Trace Methods ===
+    /// Clear the port access log
+    pub fn clear_port_log(&mut self) {
+        self.bus.port_log.clear();
+    }
 
-    /// Enable full I/O tracing (records all memory/port operations per instruction)
-    pub fn enable_full_trace(&mut self) {
-        self.bus.enable_full_trace();
+    /// Set how many port accesses the log keeps
+    pub fn set_port_log_capacity(&mut self, capacity: usize) {
+        self.bus.port_log.set_capacity(capacity);
     }
 
-    /// Disable full I/O tracing
-    pub fn disable_full_trace(&mut self) {
-        self.bus.disable_full_trace();
+    /// Logged port accesses, oldest first
+    pub fn port_log(&self) -> &PortLog {
+        &self.bus.port_log
     }
 
-    /// Check if full I/O tracing is enabled
-    pub fn is_full_trace_enabled(&self) -> bool {
-        self.bus.is_full_trace_enabled()
+    // ========== Unimplemented Hardware Access Registry ==========
+
+    /// Get all recorded accesses to unimplemented hardware, ordered by address
+    pub fn unimpl_accesses(&self) -> Vec<UnimplAccess> {
+        self.bus.unimpl.entries()
     }
-}
 
-impl Default for Emu {
-    fn default() -> Self {
-        Self::new()
+    /// Get a summary of unimplemented hardware accesses (one line per address)
+    pub fn unimpl_summary(&self) -> String {
+        self.bus.unimpl.summary()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Every unimplemented port and unmapped memory region touched since the
+    /// registry was last cleared, with access counts and the first PC
+    pub fn unknown_access_report(&self) -> UnknownAccessReport {
+        self.bus.unimpl.report()
+    }
 
-    #[test]
-    fn test_new_emu() {
-        let emu = Emu::new();
-        assert_eq!(emu.framebuffer.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
-        assert!(!emu.rom_loaded);
+    /// Clear the unimplemented hardware access registry
+    pub fn clear_unimpl_accesses(&mut self) {
+        self.bus.unimpl.clear();
     }
 
-    #[test]
-    fn test_load_rom() {
-        let mut emu = Emu::new();
-        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
-        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
-        assert!(emu.load_rom(&rom).is_ok());
-        assert!(emu.rom_loaded);
+    // ========== Serial Accessory ==========
+
+    /// Plug a USB CDC serial accessory (e.g. a TI-Innovator Hub stand-in)
+    /// into the emulated USB port, replacing any attached one
+    pub fn attach_serial_accessory(&mut self, config: SerialAccessoryConfig) {
+        self.bus.usb.attach(config);
+        self.bus.sync_usb_irq();
     }
 
-    #[test]
-    fn test_empty_rom_fails() {
-        let mut emu = Emu::new();
-        let rom: Vec<u8> = vec![];
-        assert!(emu.load_rom(&rom).is_err());
+    /// Unplug the serial accessory
+    pub fn detach_serial_accessory(&mut self) {
+        self.bus.usb.detach();
+        self.bus.sync_usb_irq();
+        self.serial_peer_disconnected();
     }
 
-    #[test]
-    fn test_key_state() {
-        let mut emu = Emu::new();
-        emu.set_key(0, 0, true);
-        assert!(emu.bus.key_state()[0][0]);
-        emu.set_key(0, 0, false);
-        assert!(!emu.bus.key_state()[0][0]);
+    /// True once the calculator has enumerated and configured the accessory
+    pub fn serial_accessory_ready(&self) -> bool {
+        self.bus.usb.device().is_some_and(|d| d.is_configured())
     }
 
-    #[test]
-    fn test_run_cycles() {
-        let mut emu = Emu::new();
-        // Without ROM loaded, should return 0
-        let executed = emu.run_cycles(1000);
-        assert_eq!(executed, 0);
+    /// Queue bytes for the calculator to receive.
+    /// Returns false if no accessory is attached.
+    pub fn serial_write(&mut self, data: &[u8]) -> bool {
+        self.bus.usb.push_to_calc(data)
     }
 
-    #[test]
-    fn test_run_with_rom() {
-        let mut emu = Emu::new();
-        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
-        let rom = vec![0x00, 0x00, 0x00, 0x76]; // NOP, NOP, NOP, HALT
-        emu.load_rom(&rom).unwrap();
-        emu.powered_on = true; // Power on for test
-        let executed = emu.run_cycles(1000);
+    /// Take up to `max` bytes the calculator has sent (empty while a peer is
+    /// installed, since the peer consumes them)
+    pub fn serial_read(&mut self, max: usize) -> Vec<u8> {
+        self.bus.usb.take_from_calc(max)
+    }
 
-        // Should have executed some cycles and halted
-        // Note: Since we don't return early on HALT (to keep peripherals ticking),
-        // the stop reason is CyclesComplete, but the CPU IS halted.
-        assert!(executed > 0);
-        assert_eq!(emu.last_stop_reason(), StopReason::CyclesComplete);
-        assert!(emu.cpu.halted);
+    /// Install a peer that answers the calculator automatically, returning
+    /// the one it replaces. The peer is told when the accessory is unplugged
+    /// or the calculator resets.
+    pub fn set_serial_peer(&mut self, peer: Option<Box<dyn SerialPeer + Send>>) -> Option<Box<dyn SerialPeer + Send>> {
+        std::mem::replace(&mut self.serial_peer, peer)
     }
 
-    #[test]
-    fn test_reset() {
-        let mut emu = Emu::new();
-        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
-        let rom = vec![0x00, 0x76]; // NOP, HALT
-        emu.load_rom(&rom).unwrap();
-        emu.powered_on = true; // Power on for test
-        emu.run_cycles(100);
-        emu.set_key(1, 1, true);
-        emu.reset();
+    /// Tell the serial peer its USB session ended
+    fn serial_peer_disconnected(&mut self) {
+        if let Some(peer) = self.serial_peer.as_mut() {
+            peer.disconnected();
+        }
+    }
 
-        assert_eq!(emu.cpu.pc, 0);
-        assert!(!emu.bus.key_state()[1][1]);
-        // After reset, total_cycles includes init_prefetch cost for CEmu parity.
-        // The prefetch fetches the first byte at PC=0, which adds flash timing cycles.
-        // With serial flash enabled by default, this is typically 10 cycles (cache miss).
-        // We just verify it's small and consistent, rather than exactly 0.
-        assert!(emu.total_cycles <= 20, "total_cycles after reset should be small prefetch cost");
-        assert!(!emu.powered_on); // Reset should power off the calculator
+    // ========== Execution Counters ==========
+
+    /// Get lifetime execution counters (instructions, interrupts, frames, resets)
+    pub fn exec_counters(&self) -> ExecCounters {
+        ExecCounters {
+            instructions: self.cpu.instructions_retired,
+            interrupts: self.cpu.irqs_serviced,
+            nmis: self.cpu.nmis_serviced,
+            interrupts_by_source: self.irq_source_counts,
+            frames: self.frames_rendered,
+            resets: self.reset_count,
+        }
     }
 
-    #[test]
-    fn test_history() {
-        let mut emu = Emu::new();
-        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
-        let rom = vec![0x00, 0x00, 0x00, 0x76]; // NOP, NOP, NOP, HALT
-        emu.load_rom(&rom).unwrap();
-        emu.powered_on = true; // Power on for test
-        emu.run_cycles(100);
+    /// Frames completed by the LCD controller since the last reset.
+    /// Unlike `exec_counters().frames`, this advances with emulated time
+    /// whether or not the frontend calls `render_frame()`.
+    pub fn lcd_frame_count(&self) -> u64 {
+        self.lcd_frames
+    }
 
-        let history = emu.dump_history();
-        assert!(history.contains("NOP"));
-        assert!(history.contains("HALT"));
+    /// `total_cycles()` at which the LCD controller last completed a frame
+    /// (None if no frame has completed since reset)
+    pub fn last_lcd_frame_cycle(&self) -> Option<u64> {
+        self.last_lcd_frame_cycle
     }
 
-    #[test]
-    fn test_on_key_wakes_from_halt_with_di() {
-        let mut emu = Emu::new();
+    /// Take up to `max` LCD base-address changes (buffer swaps), oldest first.
+    ///
+    /// A change is reported when a frame starts scanning out from a different
+    /// UPBASE than the previous frame, so double-buffered programs produce one
+    /// entry per presented buffer however UPBASE was written. Only the newest
+    /// changes are kept if the host falls behind.
+    pub fn take_lcd_base_changes(&mut self, max: usize) -> Vec<LcdBaseChange> {
+        let n = max.min(self.lcd_base_changes.len());
+        self.lcd_base_changes.drain(..n).collect()
+    }
+
+    /// Queue a buffer swap reported by the LCD controller
+    fn record_lcd_base_change(&mut self, old_base: u32, new_base: u32) {
+        log_sub!(Lcd, Debug, "LCD_BASE_CHANGE {:06X} -> {:06X} frame={}", old_base, new_base, self.lcd_frames);
+        if self.lcd_base_changes.len() == LCD_BASE_CHANGE_QUEUE_SIZE {
+            self.lcd_base_changes.pop_front();
+        }
+        self.lcd_base_changes.push_back(LcdBaseChange {
+            cycle: self.total_cycles,
+            frame: self.lcd_frames,
+            old_base,
+            new_base,
+        });
+    }
+
+    /// Zero all execution counters
+    pub fn reset_exec_counters(&mut self) {
+        self.cpu.instructions_retired = 0;
+        self.cpu.irqs_serviced = 0;
+        self.cpu.nmis_serviced = 0;
+        self.irq_source_counts = [0; 32];
+        self.frames_rendered = 0;
+        self.reset_count = 0;
+    }
+
+    // ========== Host-Time Profiling ==========
+
+    /// Enable or disable per-subsystem host-time profiling (no-op on WASM)
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler.set_enabled(enabled);
+    }
+
+    /// Check if profiling is enabled
+    pub fn is_profiling(&self) -> bool {
+        self.profiler.is_enabled()
+    }
+
+    /// Get accumulated host time per subsystem
+    pub fn profile_breakdown(&self) -> Vec<ProfileEntry> {
+        self.profiler.breakdown()
+    }
+
+    /// Get a human-readable profiling breakdown
+    pub fn profile_summary(&self) -> String {
+        self.profiler.summary()
+    }
+
+    /// Clear accumulated profiling data (keeps enabled state)
+    pub fn reset_profile(&mut self) {
+        self.profiler.reset();
+    }
+
+    /// Attribute a just-serviced interrupt to the sources that were pending and enabled.
+    /// Returns the attributed source mask.
+    fn count_irq_sources(&mut self) -> u32 {
+        let interrupt = &self.bus.ports.interrupt;
+        let active = interrupt.status() & interrupt.enabled();
+        for (bit, count) in self.irq_source_counts.iter_mut().enumerate() {
+            if active & (1 << bit) != 0 {
+                *count += 1;
+            }
+        }
+        active
+    }
+
+    /// Count and log interrupts taken by the last `cpu.step()` (which started at `pc`)
+    fn log_serviced_interrupts(&mut self, pc: u32, irqs_before: u64, nmis_before: u64) {
+        let cycle = self.bus.total_cycles();
+        if self.cpu.irqs_serviced != irqs_before {
+            let sources = self.count_irq_sources();
+            self.irq_log.record(IrqLogEntry { cycle, pc, sources, nmi: false });
+            self.trace_event(TraceKind::Interrupt { pc, sources });
+            if let Some(usage) = &mut self.cpu_usage {
+                usage.interrupt(sources);
+            }
+        }
+        if self.cpu.nmis_serviced != nmis_before {
+            self.irq_log.record(IrqLogEntry { cycle, pc, sources: 0, nmi: true });
+            self.trace_event(TraceKind::Nmi { pc });
+        }
+    }
+
+    // ========== Screen Recording ==========
+
+    /// Record rendered frames to `path` as a GIF (.gif) or APNG (.png,
+    /// .apng), sampled at `fps` frames per emulated second (at most
+    /// `recorder::MAX_FPS`). Replaces any recording in progress without
+    /// finishing it.
+    #[cfg(feature = "recording")]
+    pub fn start_recording(&mut self, path: impl AsRef<std::path::Path>, fps: u32) -> std::io::Result<()> {
+        self.recorder = Some(recorder::create(path.as_ref(), fps, SCREEN_WIDTH, SCREEN_HEIGHT)?);
+        Ok(())
+    }
+
+    /// Finish the recording. Returns the number of frames written or the
+    /// first write error; None if nothing was recording.
+    #[cfg(feature = "recording")]
+    pub fn stop_recording(&mut self) -> Option<std::io::Result<u64>> {
+        let secs = self.emulated_secs();
+        self.recorder.take().map(|recorder| recorder.finish(secs))
+    }
+
+    /// True while a screen recording is running
+    #[cfg(feature = "recording")]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // ========== Input Recording and Replay ==========
+
+    /// Start recording key events for `play_replay`, stamped relative to
+    /// now. Replaces any input recording in progress.
+    pub fn start_input_recording(&mut self) {
+        self.input_recorder = Some(InputRecorder::new(self.run_point()));
+    }
+
+    /// The key events recorded so far as a replay file (None if not recording)
+    pub fn input_recording(&self) -> Option<Vec<u8>> {
+        self.input_recorder.as_ref().map(|recorder| replay::encode(recorder.events()))
+    }
+
+    /// Stop recording key events and return them as a replay file (None if
+    /// not recording)
+    pub fn stop_input_recording(&mut self) -> Option<Vec<u8>> {
+        let data = self.input_recording();
+        self.input_recorder = None;
+        data
+    }
+
+    /// True while key events are being recorded
+    pub fn is_recording_input(&self) -> bool {
+        self.input_recorder.is_some()
+    }
+
+    /// Replay a recording made by `start_input_recording`. Events are
+    /// injected by `run_cycles` at the instruction and cycle they were
+    /// recorded at, relative to now, so a replay started from the state the
+    /// recording started from reproduces the run exactly (with RTC host
+    /// sync off). Replaces any replay in progress; returns the number of
+    /// events, or `replay::ERR_INVALID` / `replay::ERR_VERSION`.
+    pub fn play_replay(&mut self, data: &[u8]) -> Result<usize, i32> {
+        let events = replay::parse(data)?;
+        let count = events.len();
+        self.replay = Some(ReplayPlayer::new(events, self.run_point()));
+        Ok(count)
+    }
+
+    /// Abandon the replay in progress (keys it pressed stay pressed)
+    pub fn stop_replay(&mut self) {
+        self.replay = None;
+    }
+
+    /// True while replay events remain to be injected
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    fn run_point(&self) -> RunPoint {
+        RunPoint { instructions: self.cpu.instructions_retired, cycles: self.total_cycles }
+    }
+
+    fn record_input(&mut self, row: usize, col: usize, down: bool) {
+        let at = self.run_point();
+        if let Some(recorder) = &mut self.input_recorder {
+            recorder.record(at, row, col, down);
+        }
+    }
+
+    /// `run_cycles` during a replay: run up to each event's cycle, then
+    /// inject it once the instruction count has caught up too
+    fn run_replay_cycles(&mut self, mut player: ReplayPlayer, cycles: u32) -> u32 {
+        let mut executed = 0u32;
+        loop {
+            while let Some(event) = player.take_due(self.run_point()) {
+                log_sub!(Keypad, Debug, "REPLAY: key ({}, {}) {}", event.row, event.col, if event.down { "down" } else { "up" });
+                self.set_key(event.row, event.col, event.down);
+            }
+            let Some(until_next) = player.cycles_until_next(self.run_point()) else {
+                // Replay finished: run the rest normally
+                if executed < cycles {
+                    executed += self.run_cycles(cycles - executed);
+                }
+                return executed;
+            };
+            if executed >= cycles {
+                break;
+            }
+            let chunk = (cycles - executed).min(until_next.min(u32::MAX as u64) as u32);
+            let ran = self.run_cycles(chunk);
+            executed += ran;
+            // Stopped early (breakpoint, frame end, powered off)
+            if ran < chunk {
+                break;
+            }
+        }
+        self.replay = Some(player);
+        executed
+    }
+
+    /// `run_cycles` while `tap_key` releases are pending: run up to the
+    /// next one, release its key, and carry on
+    fn run_tap_cycles(&mut self, cycles: u32) -> u32 {
+        let mut releases = std::mem::take(&mut self.key_releases);
+        let mut executed = 0u32;
+        loop {
+            for &(key, _) in releases.iter().filter(|&&(_, left)| left == 0) {
+                let (row, col) = key.position();
+                self.set_key(row, col, false);
+            }
+            releases.retain(|&(_, left)| left > 0);
+            let Some(until_next) = releases.iter().map(|&(_, left)| left).min() else {
+                if executed < cycles {
+                    executed += self.run_cycles(cycles - executed);
+                }
+                return executed;
+            };
+            if executed >= cycles {
+                break;
+            }
+            let chunk = (cycles - executed).min(until_next.min(u32::MAX as u64) as u32);
+            let ran = self.run_cycles(chunk);
+            executed += ran;
+            for (_, left) in &mut releases {
+                *left = left.saturating_sub(ran as u64);
+            }
+            // Stopped early (breakpoint, frame end, powered off)
+            if ran < chunk {
+                break;
+            }
+        }
+        self.key_releases = releases;
+        executed
+    }
+
+    // ========== Structured Instruction Trace ==========
+
+    /// Start streaming a record per instruction to `sink` (see
+    /// `trace_record`), replacing any trace in progress. Records are only
+    /// produced with the `trace` feature.
+    pub fn set_trace(&mut self, config: TraceConfig, sink: Box<dyn std::io::Write + Send>) {
+        self.tracer = Some(Tracer::new(config, sink));
+    }
+
+    /// Stop the trace and flush its sink. Returns the number of records
+    /// written or the first write error; None if no trace was running.
+    pub fn stop_trace(&mut self) -> Option<std::io::Result<u64>> {
+        self.tracer.take().map(Tracer::finish)
+    }
+
+    /// True while a trace is recording (false once it reaches its limit or
+    /// its sink fails)
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.as_ref().is_some_and(|tracer| !tracer.is_done())
+    }
+
+    /// Capture the state before the instruction at `pc`, if the trace wants it
+    #[cfg(feature = "trace")]
+    fn trace_begin(&mut self, pc: u32) -> Option<TraceRecord> {
+        let index = self.tracer.as_mut()?.wants(pc)?;
+        let (opcode, opcode_len) = self.peek_opcode(pc);
+        let cpu = &self.cpu;
+        let flags = [
+            (cpu.adl, trace_record::flags::ADL),
+            (cpu.iff1, trace_record::flags::IFF1),
+            (cpu.iff2, trace_record::flags::IFF2),
+            (cpu.irq_pending, trace_record::flags::IRQ_PENDING),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, bit)| flags | bit);
+        Some(TraceRecord {
+            index,
+            total_cycles: self.total_cycles,
+            cycles: 0,
+            pc,
+            sp: cpu.sp(),
+            bc: cpu.bc,
+            de: cpu.de,
+            hl: cpu.hl,
+            ix: cpu.ix,
+            iy: cpu.iy,
+            a: cpu.a,
+            f: cpu.f,
+            flags,
+            opcode_len: opcode_len as u8,
+            opcode,
+        })
+    }
+
+    /// Finish and write a record from `trace_begin`
+    #[cfg(feature = "trace")]
+    fn trace_end(&mut self, record: Option<TraceRecord>, cycles: u32, irqs_before: u64, nmis_before: u64) {
+        let (Some(mut record), Some(tracer)) = (record, self.tracer.as_mut()) else {
+            return;
+        };
+        record.cycles = cycles;
+        if self.cpu.irqs_serviced != irqs_before || self.cpu.nmis_serviced != nmis_before {
+            record.flags |= trace_record::flags::INTERRUPTED;
+        }
+        tracer.record(&record);
+    }
+
+    // ========== Chrome Trace Export ==========
+
+    /// Start recording scheduler events, interrupts, LCD frames, and
+    /// optionally bcalls for `write_chrome_trace()`. Discards any earlier
+    /// recording.
+    pub fn start_chrome_trace(&mut self, config: ChromeTraceConfig) {
+        self.chrome_trace = Some(ChromeTracer::new(config, self.bus.total_cycles()));
+    }
+
+    /// Stop recording; the recording is kept until the next start
+    pub fn stop_chrome_trace(&mut self) {
+        if let Some(tracer) = &mut self.chrome_trace {
+            tracer.stop();
+        }
+    }
+
+    /// The current or last recording, if any
+    pub fn chrome_trace(&self) -> Option<&ChromeTracer> {
+        self.chrome_trace.as_ref()
+    }
+
+    /// Write the recording as Chrome trace-event JSON (for Perfetto or
+    /// chrome://tracing). Returns bytes written.
+    pub fn write_chrome_trace<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<usize> {
+        match &self.chrome_trace {
+            Some(tracer) => tracer.write_json(out),
+            None => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no chrome trace recorded")),
+        }
+    }
+
+    fn trace_event(&mut self, kind: TraceKind) {
+        if let Some(tracer) = &mut self.chrome_trace {
+            let hz = Self::cpu_hz(self.bus.ports.control.cpu_speed());
+            tracer.record(kind, self.bus.total_cycles(), hz);
+        }
+    }
+
+    /// Record a bcall if the last instruction entered the OS jump table
+    fn trace_bcall(&mut self) {
+        let Some(tracer) = &self.chrome_trace else {
+            return;
+        };
+        if tracer.is_recording() && tracer.config().bcalls && chrome_trace::is_bcall_entry(self.cpu.pc) {
+            self.trace_event(TraceKind::Bcall { entry: self.cpu.pc });
+        }
+    }
+
+    // ========== CPU Utilization ==========
+
+    /// Start or stop per-frame CPU utilization statistics. Frames are LCD
+    /// frames, so nothing is recorded while the LCD is off.
+    pub fn set_cpu_usage_stats(&mut self, enabled: bool) {
+        self.cpu_usage = enabled.then(|| CpuUsage::new(self.bus.total_cycles(), self.cpu.halted));
+    }
+
+    /// Whether CPU utilization statistics are being collected
+    pub fn cpu_usage_stats_enabled(&self) -> bool {
+        self.cpu_usage.is_some()
+    }
+
+    /// Usage of the most recently completed frame (for an activity meter)
+    pub fn last_frame_usage(&self) -> Option<FrameUsage> {
+        self.cpu_usage.as_ref().and_then(|usage| usage.last_frame())
+    }
+
+    /// Take up to `max` unread per-frame statistics, oldest first. Only the
+    /// newest `CPU_USAGE_QUEUE_SIZE` are kept if the host falls behind.
+    pub fn take_frame_usage(&mut self, max: usize) -> Vec<FrameUsage> {
+        match &mut self.cpu_usage {
+            Some(usage) => usage.take_frames(max),
+            None => Vec::new(),
+        }
+    }
+
+    // ========== Input Latency ==========
+
+    /// Start or stop measuring input-to-photon latency: the time from each
+    /// `set_key()` press to the first `render_frame()` whose pixels changed.
+    /// Restarting discards earlier samples.
+    pub fn set_latency_tracking(&mut self, enabled: bool) {
+        self.latency = enabled.then(|| LatencyTracker::new(self.full_frame_hash()));
+    }
+
+    /// Whether input latency is being measured
+    pub fn latency_tracking_enabled(&self) -> bool {
+        self.latency.is_some()
+    }
+
+    /// Summary of input latency samples so far (None if not tracking)
+    pub fn input_latency_stats(&self) -> Option<LatencyStats> {
+        self.latency.as_ref().map(LatencyTracker::stats)
+    }
+
+    /// Take up to `max` unread latency samples, oldest first. Only the newest
+    /// `LATENCY_QUEUE_SIZE` are kept if the host falls behind.
+    pub fn take_input_latencies(&mut self, max: usize) -> Vec<InputLatency> {
+        match &mut self.latency {
+            Some(latency) => latency.take_samples(max),
+            None => Vec::new(),
+        }
+    }
+
+    fn full_frame_hash(&self) -> u32 {
+        let full = HashRect { x: 0, y: 0, width: SCREEN_WIDTH, height: SCREEN_HEIGHT };
+        frame_hash::hash_pixels(&self.framebuffer, SCREEN_WIDTH, full)
+    }
+
+    /// Emulated seconds since reset, continuous across CPU speed changes
+    fn emulated_secs(&self) -> f64 {
+        self.total_cycles as f64 / self.cpu_clock_hz()
+    }
+
+    // ========== Rewind ==========
+
+    /// Start recording rewind history with `config`, or stop and discard it
+    /// with None. Snapshots are taken at the start of `run_cycles()` once
+    /// `interval_frames` LCD frames have passed, so the history reaches
+    /// back across resets and crashes.
+    pub fn set_rewind(&mut self, config: Option<RewindConfig>) {
+        self.rewind = config.map(RewindBuffer::new);
+    }
+
+    /// Rewind configuration, if recording
+    pub fn rewind_config(&self) -> Option<RewindConfig> {
+        self.rewind.as_ref().map(RewindBuffer::config)
+    }
+
+    /// Snapshots held and the bytes they use
+    pub fn rewind_usage(&self) -> (usize, usize) {
+        self.rewind.as_ref().map_or((0, 0), |r| (r.len(), r.memory_usage()))
+    }
+
+    /// Step back to the newest snapshot at least `frames` LCD frames old (or
+    /// the oldest one kept). Newer snapshots are dropped, and recording
+    /// continues from the restored state.
+    ///
+    /// Returns Ok(frames) with how far back the restored state is.
+    /// Error codes: -1 = not recording or no snapshot yet, plus load_state errors.
+    pub fn rewind(&mut self, frames: u32) -> Result<u64, i32> {
+        let (rewound, state) = self.rewind.as_mut().and_then(|r| r.rewind(frames as u64)).ok_or(-1)?;
+        self.load_state(&state)?;
+        log_evt!("REWIND: {} frames", rewound);
+        Ok(rewound)
+    }
+
+    fn capture_rewind_if_due(&mut self) {
+        if !self.rewind.as_ref().is_some_and(RewindBuffer::is_due) {
+            return;
+        }
+        if let Ok(state) = self.save_state_vec() {
+            if let Some(rewind) = &mut self.rewind {
+                rewind.push(state);
+            }
+        }
+    }
+
+    // ========== Real-time clock ==========
+
+    /// Set the RTC to `unix_secs` (in the time zone the calculator should
+    /// show), as if the user had set the clock. Stops host clock tracking.
+    pub fn set_rtc_time(&mut self, unix_secs: i64) {
+        self.rtc_host_sync = None;
+        self.bus.ports.rtc.set_unix_time(unix_secs);
+    }
+
+    /// Seed the RTC from the host clock plus `utc_offset_secs` and keep it
+    /// there, or stop with None. While tracking, the clock is corrected at
+    /// the start of `run_cycles()` whenever it drifts more than a second,
+    /// e.g. after a pause or fast-forward; a time set from the OS is kept as
+    /// an offset from the host. Returns false if the host has no clock or
+    /// deterministic mode is on, which keeps the RTC off the host clock.
+    pub fn set_rtc_host_sync(&mut self, utc_offset_secs: Option<i32>) -> bool {
+        self.rtc_host_sync = None;
+        let Some(offset) = utc_offset_secs else {
+            return true;
+        };
+        if self.deterministic_seed.is_some() {
+            return false;
+        }
+        let Some(now) = host_unix_secs() else {
+            return false;
+        };
+        self.rtc_host_sync = Some(HostClockSync::new(&mut self.bus.ports.rtc, now, offset));
+        true
+    }
+
+    /// RTC counter as Unix seconds
+    pub fn rtc_time(&self) -> i64 {
+        self.bus.ports.rtc.time_secs() as i64 + EPOCH_UNIX_SECS
+    }
+
+    fn sync_rtc_to_host(&mut self) {
+        let (Some(sync), Some(now)) = (&mut self.rtc_host_sync, host_unix_secs()) else {
+            return;
+        };
+        if sync.check(&mut self.bus.ports.rtc, now) {
+            log_evt!("RTC: resynced to host clock");
+        }
+    }
+
+    fn sample_cpu_usage(&mut self) {
+        if let Some(usage) = &mut self.cpu_usage {
+            usage.sample(self.bus.total_cycles(), self.cpu.halted);
+        }
+    }
+
+    /// Feed the runaway watchdog after a `cpu.step()` that took `cycles`
+    fn check_runaway(&mut self, cycles: u32) {
+        let Some(runaway) = &mut self.runaway else {
+            return;
+        };
+        let Some(kind) = runaway.observe(self.cpu.pc, self.cpu.iff1, self.cpu.halted, cycles) else {
+            return;
+        };
+        let _log = self.log_scope();
+        log_sub!(Cpu, Warn, "RUNAWAY: {} (PC={:06X})", kind, self.cpu.pc);
+        let report = RunawayReport {
+            kind,
+            cycle: self.bus.total_cycles(),
+            pc: self.cpu.pc,
+            context: format!("{}\n{}\n{}", kind, self.dump_registers(), self.dump_history()),
+        };
+        if let Some(callback) = &mut self.runaway_callback {
+            callback(&report);
+        }
+        self.last_runaway = Some(report);
+    }
+
+    /// Enable the runaway-execution watchdog, or disable it with `None`.
+    /// `callback` (optional) is called from inside `run_cycles()`/`step()`
+    /// when a stuck loop or reboot loop is detected; the report is also kept
+    /// for `take_runaway_report()`.
+    pub fn set_runaway_watchdog(&mut self, config: Option<RunawayConfig>, callback: Option<RunawayCallback>) {
+        self.runaway = config.map(RunawayDetector::new);
+        self.runaway_callback = callback;
+        self.last_runaway = None;
+    }
+
+    /// Watchdog thresholds in use (None = disabled)
+    pub fn runaway_watchdog(&self) -> Option<RunawayConfig> {
+        self.runaway.as_ref().map(RunawayDetector::config)
+    }
+
+    /// Take the most recent runaway report, if one is pending
+    pub fn take_runaway_report(&mut self) -> Option<RunawayReport> {
+        self.last_runaway.take()
+    }
+
+    // ========== Diagnostic Bundles ==========
+
+    /// Set the sink for automatic diagnostic bundles and arm it.
+    /// The next internal error or protection violation writes one bundle,
+    /// then the sink is disarmed until set again.
+    pub fn set_diagnostic_sink(&mut self, sink: Option<Box<dyn std::io::Write + Send>>) {
+        self.diagnostic_armed = sink.is_some();
+        self.diagnostic_sink = sink;
+    }
+
+    /// Recently serviced interrupts, oldest first
+    pub fn irq_log(&self) -> Vec<IrqLogEntry> {
+        self.irq_log.entries()
+    }
+
+    /// Write a diagnostic bundle (see `diagnostics` for the format).
+    /// Returns the number of bytes written.
+    pub fn write_diagnostic_bundle<W: std::io::Write>(
+        &self,
+        trigger: &DiagnosticTrigger,
+        sink: &mut W,
+    ) -> std::io::Result<usize> {
+        let info = format!(
+            "trigger: {}\npc: {:06X}\ntotal_cycles: {}\nstop_reason: {:?}\nrom_hash: {:016X}\n",
+            trigger, self.cpu.pc, self.total_cycles, self.last_stop, self.compute_rom_hash()
+        );
+        let mut state = vec![0u8; self.save_state_size()];
+        let state_len = self.save_state(&mut state).unwrap_or(0);
+        let history = self.dump_history();
+        let irqs = self.irq_log.summary();
+        let unknown = self.unknown_access_report().to_string();
+        diagnostics::write_bundle(sink, &[
+            (*b"INFO", info.as_bytes()),
+            (*b"STAT", &state[..state_len]),
+            (*b"HIST", history.as_bytes()),
+            (*b"IRQS", irqs.as_bytes()),
+            (*b"UNKN", unknown.as_bytes()),
+        ])
+    }
+
+    /// Write a user-requested bundle to the sink set by `set_diagnostic_sink()`.
+    /// Does not disarm automatic bundles.
+    pub fn dump_diagnostics(&mut self) -> std::io::Result<usize> {
+        let Some(mut sink) = self.diagnostic_sink.take() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no diagnostic sink"));
+        };
+        let result = self.write_diagnostic_bundle(&DiagnosticTrigger::UserRequest, &mut sink);
+        self.diagnostic_sink = Some(sink);
+        result
+    }
+
+    /// Write an automatic bundle if the sink is armed
+    fn write_auto_diagnostics(&mut self, trigger: DiagnosticTrigger) {
+        if !self.diagnostic_armed {
+            return;
+        }
+        let Some(mut sink) = self.diagnostic_sink.take() else {
+            return;
+        };
+        self.diagnostic_armed = false;
+        match self.write_diagnostic_bundle(&trigger, &mut sink) {
+            Ok(bytes) => log_sub!(Cpu, Warn, "DIAGNOSTIC_BUNDLE: {} ({} bytes)", trigger, bytes),
+            Err(e) => log_sub!(Cpu, Error, "DIAGNOSTIC_BUNDLE failed: {}", e),
+        }
+        self.diagnostic_sink = Some(sink);
+    }
+
+    /// Get detailed write log (Vec of (addr, value, cycle))
+    pub fn get_write_log(&self) -> Vec<(u32, u8, u64)> {
+        self.bus.write_tracer.detailed_log()
+            .iter()
+            .map(|rec| (rec.addr, rec.value, rec.cycle))
+            .collect()
+    }
+
+    /// Get CPU register dump for debugging
+    pub fn dump_registers(&self) -> String {
+        format!(
+            "AF={:02X}{:02X} BC={:06X} DE={:06X} HL={:06X}\n\
+             IX={:06X} IY={:06X} SP={:06X} PC={:06X}\n\
+             Flags: S={} Z={} H={} PV={} N={} C={}\n\
+             ADL={} IFF1={} IFF2={} IM={:?} MBASE={:02X}",
+            self.cpu.a,
+            self.cpu.f,
+            self.cpu.bc,
+            self.cpu.de,
+            self.cpu.hl,
+            self.cpu.ix,
+            self.cpu.iy,
+            self.cpu.sp(),
+            self.cpu.pc,
+            (self.cpu.f >> 7) & 1,
+            (self.cpu.f >> 6) & 1,
+            (self.cpu.f >> 4) & 1,
+            (self.cpu.f >> 2) & 1,
+            (self.cpu.f >> 1) & 1,
+            self.cpu.f & 1,
+            self.cpu.adl,
+            self.cpu.iff1,
+            self.cpu.iff2,
+            self.cpu.im,
+            self.cpu.mbase,
+        )
+    }
+
+    // === Full I/O Trace Methods ===
+
+    /// Enable full I/O tracing (records all memory/port operations per instruction)
+    pub fn enable_full_trace(&mut self) {
+        self.bus.enable_full_trace();
+    }
+
+    /// Disable full I/O tracing
+    pub fn disable_full_trace(&mut self) {
+        self.bus.disable_full_trace();
+    }
+
+    /// Check if full I/O tracing is enabled
+    pub fn is_full_trace_enabled(&self) -> bool {
+        self.bus.is_full_trace_enabled()
+    }
+}
+
+impl Default for Emu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_emu() {
+        let emu = Emu::new();
+        assert_eq!(emu.framebuffer.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert!(!emu.rom_loaded);
+    }
+
+    #[test]
+    fn test_load_rom() {
+        let mut emu = Emu::new();
+        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
+        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        assert!(emu.load_rom(&rom).is_ok());
+        assert!(emu.rom_loaded);
+    }
+
+    #[test]
+    fn test_empty_rom_fails() {
+        let mut emu = Emu::new();
+        let rom: Vec<u8> = vec![];
+        assert!(emu.load_rom(&rom).is_err());
+    }
+
+    #[test]
+    fn test_key_state() {
+        let mut emu = Emu::new();
+        emu.set_key(0, 0, true);
+        assert!(emu.bus.key_state()[0][0]);
+        emu.set_key(0, 0, false);
+        assert!(!emu.bus.key_state()[0][0]);
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        let mut emu = Emu::new();
+        // Without ROM loaded, should return 0
+        let executed = emu.run_cycles(1000);
+        assert_eq!(executed, 0);
+    }
+
+    #[test]
+    fn test_run_with_rom() {
+        let mut emu = Emu::new();
+        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
+        let rom = vec![0x00, 0x00, 0x00, 0x76]; // NOP, NOP, NOP, HALT
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true; // Power on for test
+        let executed = emu.run_cycles(1000);
+
+        // Should have executed some cycles and halted
+        // Note: Since we don't return early on HALT (to keep peripherals ticking),
+        // the stop reason is CyclesComplete, but the CPU IS halted.
+        assert!(executed > 0);
+        assert_eq!(emu.last_stop_reason(), StopReason::CyclesComplete);
+        assert!(emu.cpu.halted);
+    }
+
+    #[test]
+    fn test_run_until_idle() {
+        let mut emu = Emu::new();
+        // EI; HALT; JR -3 - sits halted with interrupts enabled
+        let rom = vec![0xFB, 0x76, 0x18, 0xFD];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        let elapsed = emu.run_until_idle(10_000_000).expect("should reach idle");
+        assert!(elapsed > 0 && elapsed <= 10_000_000);
+        assert!(emu.is_idle());
+    }
+
+    #[test]
+    fn test_run_until_idle_times_out() {
+        let mut emu = Emu::new();
+        // DI; HALT - halted but interrupts disabled, never idle
+        let rom = vec![0xF3, 0x76];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        assert_eq!(emu.run_until_idle(1_000_000), None);
+        assert!(!emu.is_idle());
+
+        // Without a ROM nothing runs
+        let mut emu = Emu::new();
+        assert_eq!(emu.run_until_idle(1_000_000), None);
+    }
+
+    #[test]
+    fn test_exec_counters() {
+        use crate::peripherals::interrupt::sources;
+
+        let mut emu = Emu::new();
+        // EI; NOP; NOP; HALT
+        let rom = vec![0xFB, 0x00, 0x00, 0x76];
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        assert_eq!(emu.exec_counters().resets, 1);
+
+        // Enable and raise the ON key interrupt so it is taken once EI completes
+        emu.bus.ports.interrupt.write(0x04, 0x01);
+        emu.bus.ports.interrupt.raise(sources::ON_KEY);
+        emu.cpu.irq_pending = true;
+        for _ in 0..4 {
+            emu.step();
+        }
+        emu.render_frame();
+
+        let counters = emu.exec_counters();
+        assert_eq!(counters.interrupts, 1);
+        assert_eq!(counters.interrupts_by_source[0], 1);
+        assert_eq!(counters.interrupts_by_source[15], 0); // PWR raised but not enabled
+        assert_eq!(counters.instructions, 3);
+        assert_eq!(counters.frames, 1);
+
+        emu.reset();
+        assert_eq!(emu.exec_counters().resets, 2);
+        // Lifetime counters survive reset
+        assert_eq!(emu.exec_counters().instructions, 3);
+
+        emu.reset_exec_counters();
+        assert_eq!(emu.exec_counters(), ExecCounters::default());
+    }
+
+    #[test]
+    fn test_lcd_frame_counter() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.run_cycles(100_000);
+        // LCD disabled: no frames
+        assert_eq!(emu.lcd_frame_count(), 0);
+        assert_eq!(emu.last_lcd_frame_cycle(), None);
+
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.run_cycles(2_000_000);
+        let frames = emu.lcd_frame_count();
+        let last = emu.last_lcd_frame_cycle().unwrap();
+        assert!(frames > 0);
+        assert!(last <= emu.total_cycles());
+
+        emu.run_cycles(2_000_000);
+        assert!(emu.lcd_frame_count() > frames);
+        assert!(emu.last_lcd_frame_cycle().unwrap() > last);
+
+        emu.reset();
+        assert_eq!(emu.lcd_frame_count(), 0);
+        assert_eq!(emu.last_lcd_frame_cycle(), None);
+    }
+
+    #[test]
+    fn test_frame_callback_on_vsync() {
+        use std::sync::{Arc, Mutex};
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let seen = frames.clone();
+        emu.set_frame_callback(Some(Box::new(move |pixels, stride| {
+            seen.lock().unwrap().push((pixels.len(), stride));
+        })));
+        emu.bus.write_byte(0xE30000, 0x4C); // 320 pixels per line
+        emu.bus.write_byte(0xE30004, 0xEF); // 240 lines
+        emu.bus.write_byte(0xE3000A, 0x3F); // 320 clocks per line
+        emu.bus.write_byte(0xE3000B, 0x01);
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        for _ in 0..4 {
+            emu.run_frame();
+        }
+        assert_eq!(frames.lock().unwrap().len(), 4);
+        assert_eq!(emu.exec_counters().frames, 4);
+        assert_eq!(frames.lock().unwrap()[0], (SCREEN_WIDTH * SCREEN_HEIGHT, SCREEN_WIDTH));
+
+        // Skipped frames are rendered but not reported
+        emu.set_frame_skip(1);
+        for _ in 0..4 {
+            emu.run_frame();
+        }
+        assert_eq!(frames.lock().unwrap().len(), 6);
+
+        emu.set_frame_callback(None);
+        emu.run_frame();
+        assert_eq!(frames.lock().unwrap().len(), 6);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn test_debugger_breakpoints_watchpoints_and_stepping() {
+        use crate::debug::{access, AccessKind};
+
+        let mut rom = vec![0u8; 0x40];
+        let code: &[(usize, &[u8])] = &[
+            (0x00, &[0x5B, 0xC3, 0x05, 0x00, 0x00]), // JP.LIL 000005
+            (0x05, &[0x31, 0x00, 0x00, 0xD4]),       // LD SP,D40000
+            (0x09, &[0x21, 0x00, 0x00, 0xD0]),       // LD HL,D00000
+            (0x0D, &[0x3E, 0x42]),                   // LD A,42
+            (0x0F, &[0xCD, 0x20, 0x00, 0x00]),       // CALL 000020
+            (0x13, &[0x77]),                         // LD (HL),A
+            (0x14, &[0xED, 0x38, 0x05]),             // IN0 A,(05)
+            (0x17, &[0x18, 0xFE]),                   // JR $
+            (0x20, &[0x3C, 0xCD, 0x30, 0x00, 0x00, 0xC9]), // INC A; CALL 000030; RET
+            (0x30, &[0x3C, 0xC9]),                   // INC A; RET
+        ];
+        for &(addr, bytes) in code {
+            rom[addr..addr + bytes.len()].copy_from_slice(bytes);
+        }
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+
+        let bp = emu.add_breakpoint(0x0F);
+        let event = emu.run_until_break(10_000).unwrap();
+        assert_eq!((event.kind, event.id, event.pc), (DebugEventKind::Breakpoint, bp, 0x0F));
+        assert_eq!(emu.last_stop_reason(), StopReason::Debugger);
+
+        // Over the call, which runs both functions
+        let event = emu.step_over(10_000).unwrap();
+        assert_eq!((event.kind, event.pc), (DebugEventKind::Step, 0x13));
+        assert_eq!(emu.cpu.a, 0x44);
+
+        let watch = emu.add_watchpoint(0xD00000, 0xD00000, access::WRITE).unwrap();
+        emu.add_port_watchpoint(0x05, 0x05, access::READ).unwrap();
+        let event = emu.run_until_break(10_000).unwrap();
+        assert_eq!((event.kind, event.id, event.access), (DebugEventKind::Watchpoint, watch, AccessKind::Write));
+        assert_eq!((event.pc, event.addr, event.value), (0x13, 0xD00000, 0x44));
+        assert_eq!(emu.cpu.pc, 0x14);
+        let event = emu.step_into().unwrap();
+        assert_eq!((event.kind, event.addr, event.pc), (DebugEventKind::Port, 0x05, 0x14));
+        assert_eq!(emu.run_until_break(10_000), None);
+
+        // Into the call, then out of it past the nested one
+        emu.cpu.pc = 0x0F;
+        emu.cpu.init_prefetch(&mut emu.bus);
+        emu.cpu.a = 0;
+        assert!(emu.remove_breakpoint(bp));
+        assert_eq!(emu.step_into().unwrap().pc, 0x20);
+        assert_eq!(emu.step_into().unwrap().pc, 0x21);
+        let event = emu.step_out(10_000).unwrap();
+        assert_eq!((event.kind, event.pc), (DebugEventKind::Step, 0x13));
+        assert_eq!(emu.cpu.a, 2);
+        assert_eq!(emu.cpu.sp(), 0xD40000);
+    }
+
+    #[test]
+    fn test_run_frame_stops_at_vsync() {
+        // Spinning and halted CPUs take different run_cycles paths
+        for rom in [&[0x18, 0xFE][..], &[0xF3, 0x76][..]] { // JR $ / DI; HALT
+            let mut emu = Emu::new();
+            emu.load_rom(rom).unwrap();
+            emu.powered_on = true;
+            // LCD off: one 60 Hz frame of cycles
+            let fallback = (emu.cpu_clock_hz() / 60.0) as u32;
+            let elapsed = emu.run_frame();
+            assert!(elapsed >= fallback && elapsed < fallback + 100, "{}", elapsed);
+            assert_eq!(emu.lcd_frame_count(), 0);
+
+            emu.bus.write_byte(0xE30000, 0x4C); // 320 pixels per line
+            emu.bus.write_byte(0xE30004, 0xEF); // 240 lines
+            emu.bus.write_byte(0xE3000A, 0x3F); // 320 clocks per line
+            emu.bus.write_byte(0xE3000B, 0x01);
+            emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+            emu.run_frame();
+            assert_eq!(emu.lcd_frame_count(), 1);
+            let mut prev = emu.last_lcd_frame_cycle().unwrap();
+            for frame in 2..5 {
+                let elapsed = emu.run_frame();
+                assert_eq!(emu.lcd_frame_count(), frame);
+                let last = emu.last_lcd_frame_cycle().unwrap();
+                assert!(emu.total_cycles() - last < 100, "stopped {} cycles late", emu.total_cycles() - last);
+                assert!((elapsed as u64).abs_diff(last - prev) < 100);
+                prev = last;
+            }
+        }
+    }
+
+    #[test]
+    fn test_color_profile_applies_to_presented_frames() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00]).unwrap();
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        // First pixel pure red (RGB565 0xF800)
+        emu.poke_byte(0xD40000, 0x00);
+        emu.poke_byte(0xD40001, 0xF8);
+
+        emu.render_frame();
+        assert!(emu.present_frame());
+        assert!(!emu.present_frame());
+        assert_eq!(emu.presented_data()[0], 0xFFFF0000);
+
+        // A new profile re-presents the same frame; the framebuffer stays exact
+        emu.set_color_profile(ColorProfile::CePanel);
+        assert_eq!(emu.color_profile(), ColorProfile::CePanel);
+        assert!(emu.present_frame());
+        assert_ne!(emu.presented_data()[0], 0xFFFF0000);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer_data()[0], 0xFFFF0000);
+
+        // The profile is configuration and survives reset
+        emu.reset();
+        assert_eq!(emu.color_profile(), ColorProfile::CePanel);
+    }
+
+    #[test]
+    fn test_render_low_bpp_palette_modes() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00]).unwrap();
+        // Palette entry 0 black, entries 1-15 white
+        for entry in 1..16u32 {
+            emu.bus.write_byte(0xE30200 + entry * 2, 0xFF);
+            emu.bus.write_byte(0xE30201 + entry * 2, 0xFF);
+        }
+        let white = 0xFFFFFFFF;
+        let black = 0xFF000000;
+
+        // 1bpp: pixels 0 and 2 set, first pixel in the low bit
+        emu.bus.write_byte(0xE30018, 0x01);
+        emu.poke_byte(0xD40000, 0b0000_0101);
+        emu.render_frame();
+        let row: Vec<u32> = emu.framebuffer_data()[..8].to_vec();
+        assert_eq!(row, [white, black, white, black, black, black, black, black]);
+
+        // Big-endian pixel order flips that
+        emu.bus.write_byte(0xE30019, 0x04);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer_data()[5], white);
+        assert_eq!(emu.framebuffer_data()[0], black);
+        emu.bus.write_byte(0xE30019, 0x00);
+
+        // 2bpp and 4bpp: index 0 then a nonzero index
+        emu.bus.write_byte(0xE30018, 0x03);
+        emu.poke_byte(0xD40000, 0b0000_0100);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer_data()[..2], [black, white]);
+        emu.bus.write_byte(0xE30018, 0x05);
+        emu.poke_byte(0xD40000, 0x30);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer_data()[..2], [black, white]);
+        // A 4bpp frame is 38400 bytes; the last byte fills the last two pixels
+        emu.poke_byte(0xD40000 + 38399, 0x11);
+        emu.render_frame();
+        assert_eq!(emu.framebuffer_data()[SCREEN_WIDTH * SCREEN_HEIGHT - 1], white);
+    }
+
+    #[test]
+    fn test_copy_presented_rgba_and_frame_counter() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00]).unwrap();
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        // First pixel pure red (RGB565 0xF800)
+        emu.poke_byte(0xD40000, 0x00);
+        emu.poke_byte(0xD40001, 0xF8);
+        assert_eq!(emu.frame_counter(), 0);
+
+        emu.render_frame();
+        assert_eq!(emu.frame_counter(), 1);
+        emu.present_frame();
+        let mut rgba = vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
+        assert_eq!(emu.copy_presented_rgba(&mut rgba), Some(rgba.len()));
+        assert_eq!(rgba[..8], [0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(emu.copy_presented_rgba(&mut rgba[..100]), None);
+
+        // Skipped frames are rendered but not presented
+        emu.set_frame_skip(1);
+        emu.render_frame();
+        assert!(!emu.present_frame());
+        emu.render_frame();
+        assert!(emu.present_frame());
+        assert_eq!(emu.frame_counter(), 3);
+    }
+
+    #[test]
+    fn test_governor_drives_knobs() {
+        use crate::governor::GovernorAction;
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        assert_eq!(emu.governor_report(1.0), None);
+
+        emu.set_governor(Some(GovernorConfig { window: 2, ..Default::default() }));
+        // 800k cycles is at most 133ms (6MHz); taking 500ms per frame is too slow
+        let mut decisions = Vec::new();
+        for _ in 0..4 {
+            emu.run_cycles(800_000);
+            decisions.extend(emu.governor_report(0.5));
+        }
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(emu.accuracy_mode(), AccuracyMode::Fast);
+        assert_eq!(emu.frame_skip(), 1);
+        assert_eq!(decisions[1].action, GovernorAction::FrameSkip(1));
+        assert!(decisions[1].speed < 0.5);
+        assert_eq!(emu.take_governor_decisions().len(), 2);
+
+        // Frame skip presents every other frame; all of them are rendered
+        let presented: Vec<bool> = (0..3).map(|_| {
+            emu.render_frame();
+            emu.present_frame()
+        }).collect();
+        assert_eq!(presented, [true, false, true]);
+        assert_eq!(emu.exec_counters().frames, 3);
+
+        emu.set_governor(None);
+        assert_eq!(emu.accuracy_mode(), AccuracyMode::Full);
+        assert_eq!(emu.frame_skip(), 0);
+    }
+
+    #[test]
+    fn test_run_paced_tracks_host_time() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let hz = emu.cpu_clock_hz();
+        assert_eq!(emu.speed(), SpeedMode::RealTime);
+
+        // Over many uneven host steps, emulated time matches host time
+        let start = emu.total_cycles();
+        let mut host = 0.0;
+        for i in 0..60 {
+            let step = if i % 2 == 0 { 0.010 } else { 0.023 };
+            host += step;
+            emu.run_paced(step);
+        }
+        let emulated = (emu.total_cycles() - start) as f64 / hz;
+        assert!((emulated - host).abs() < 0.001, "{} vs {}", emulated, host);
+        assert!(emu.pace_sleep_secs() > 0.0);
+
+        emu.set_speed(SpeedMode::Percent(50));
+        let start = emu.total_cycles();
+        for _ in 0..10 {
+            emu.run_paced(0.02);
+        }
+        let emulated = (emu.total_cycles() - start) as f64 / hz;
+        assert!((emulated - 0.1).abs() < 0.001, "{}", emulated);
+
+        // Turbo runs a frame per call whatever the host time
+        emu.set_speed(SpeedMode::Turbo);
+        assert!(emu.run_paced(0.0) > 0);
+        assert_eq!(emu.pace_sleep_secs(), 0.0);
+    }
+
+    #[test]
+    fn test_flash_persistence_on_write_complete() {
+        use std::sync::{Arc, Mutex};
+
+        struct Recorder(Arc<Mutex<Vec<u32>>>);
+        impl FlashSink for Recorder {
+            fn write_sector(&mut self, offset: u32, data: &[u8]) -> std::io::Result<()> {
+                assert_eq!(data.len(), FLASH_DIRTY_SECTOR_SIZE);
+                self.0.lock().unwrap().push(offset);
+                Ok(())
+            }
+        }
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let policy = PersistPolicy { interval_secs: None, on_write_complete: true };
+        emu.set_flash_persistence(Some(Box::new(Recorder(written.clone()))), policy);
+
+        emu.bus.flash.write_direct(0x0C0010, 0x00);
+        // First check sees the write still in progress, the next one flushes
+        emu.run_cycles(1_000);
+        assert!(written.lock().unwrap().is_empty());
+        emu.run_cycles(1_000);
+        assert_eq!(*written.lock().unwrap(), [0x0C0000]);
+
+        // Nothing dirty: nothing more written
+        emu.run_cycles(1_000);
+        emu.run_cycles(1_000);
+        assert_eq!(written.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_flash_persistence_interval() {
+        use std::sync::{Arc, Mutex};
+
+        struct Counter(Arc<Mutex<usize>>);
+        impl FlashSink for Counter {
+            fn write_sector(&mut self, _offset: u32, _data: &[u8]) -> std::io::Result<()> {
+                *self.0.lock().unwrap() += 1;
+                Ok(())
+            }
+        }
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let count = Arc::new(Mutex::new(0));
+        let policy = PersistPolicy { interval_secs: Some(0.01), on_write_complete: false };
+        emu.set_flash_persistence(Some(Box::new(Counter(count.clone()))), policy);
+
+        emu.bus.flash.write_direct(0x0C0010, 0x00);
+        emu.run_cycles(1_000);
+        emu.run_cycles(1_000);
+        assert_eq!(*count.lock().unwrap(), 0);
+        emu.run_cycles(1_000_000); // >= 10ms at any CPU speed
+        emu.run_cycles(1_000);
+        assert_eq!(*count.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_load_rom_checked() {
+        let mut emu = Emu::new();
+        assert_eq!(emu.rom_info(), None);
+        assert_eq!(emu.load_rom_checked(&[0x18, 0xFE]), Err(RomError::Truncated(2)));
+        assert!(!emu.rom_loaded);
+
+        let mut rom = vec![0xFF; crate::memory::addr::FLASH_SIZE];
+        rom[..2].copy_from_slice(&[0x18, 0xFE]);
+        rom[0x030000..0x03000A].copy_from_slice(b"5.8.1.0012");
+        let info = emu.load_rom_checked(&rom).unwrap();
+        assert_eq!(info.os_version.as_deref(), Some("5.8.1.0012"));
+        assert_eq!(emu.rom_info(), Some(&info));
+
+        // Unchecked loads are still described
+        emu.load_rom(&[0x18, 0xFE]).unwrap();
+        assert_eq!(emu.rom_info().unwrap().size, 2);
+        assert!(!emu.rom_info().unwrap().os_present);
+    }
+
+    #[test]
+    fn test_stub_boot_runs_payload() {
+        let mut emu = Emu::new_with_stub_boot();
+        // A real ROM cannot take a payload
+        assert_eq!(Emu::new().load_payload(0xD1A881, &[0xC9]), Err(-10));
+
+        // LD A,42; LD (D10000),A; LD HL,(D10000); RET
+        let code = [0x3E, 0x2A, 0x32, 0x00, 0x00, 0xD1, 0x2A, 0x00, 0x00, 0xD1, 0xC9];
+        emu.load_payload(0xD1A881, &code).unwrap();
+        assert!(!emu.payload_returned());
+        emu.run_cycles(1_000);
+        assert!(emu.payload_returned());
+        assert!(emu.cpu.adl);
+        assert_eq!(emu.cpu.a, 0x2A);
+        assert_eq!(emu.bus.peek_byte(0xD10000), 0x2A);
+        // The return address was popped off the stub's stack
+        assert_eq!(emu.cpu.sp(), elf::DEFAULT_STACK_TOP);
+
+        // Loading another payload restarts with fresh RAM
+        emu.load_payload(0xD20000, &[0xC9]).unwrap();
+        assert_eq!(emu.bus.peek_byte(0xD10000), 0x00);
+        emu.run_cycles(1_000);
+        assert!(emu.payload_returned());
+        assert_eq!(emu.bus.flash.dirty_sectors(), 0);
+
+        // Loading a real ROM leaves stub mode
+        emu.load_rom(&[0x18, 0xFE]).unwrap();
+        assert_eq!(emu.load_payload(0xD1A881, &[0xC9]), Err(-10));
+    }
+
+    #[test]
+    fn test_export_dirty_flash() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        assert_eq!(emu.flash_dirty_count(), 0);
+        assert_eq!(emu.export_flash().len(), crate::memory::addr::FLASH_SIZE);
+
+        emu.bus.flash.write_direct(0x0C0010, 0x00);
+        emu.bus.flash.write_direct(0x0D0000, 0x12);
+        emu.bus.flash.write_direct(0x3F0001, 0x34);
+        assert_eq!(emu.flash_dirty_count(), 3);
+
+        let first = emu.export_dirty_flash(2);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].0, 0x0C0000);
+        assert_eq!(first[0].1.len(), FLASH_DIRTY_SECTOR_SIZE);
+        assert_eq!(first[0].1[0x10], 0x00);
+        assert_eq!(first[1].0, 0x0D0000);
+        assert_eq!(first[1].1[0], 0x12);
+        assert_eq!(emu.flash_dirty_count(), 1);
+
+        let rest = emu.export_dirty_flash(usize::MAX);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].0, 0x3F0000);
+        assert_eq!(rest[0].1[1], 0x34);
+        assert_eq!(emu.flash_dirty_count(), 0);
+        assert!(emu.export_dirty_flash(usize::MAX).is_empty());
+        assert_eq!(emu.export_flash()[0x3F0001], 0x34);
+    }
+
+    #[test]
+    fn test_chrome_trace_records_activity() {
+        use crate::chrome_trace::{ChromeTraceConfig, TraceKind};
+
+        let mut emu = Emu::new();
+        // CALL 020108; JR $ -- jump table entry 020108: JP 000004
+        let mut rom = vec![0xCD, 0x08, 0x01, 0x02, 0x18, 0xFE];
+        rom.resize(0x020108, 0x00);
+        rom.extend_from_slice(&[0xC3, 0x04, 0x00, 0x00]);
+        emu.load_rom(&rom).unwrap();
+        emu.cpu.adl = true;
+        emu.powered_on = true;
+        emu.bus.write_byte(0xE30018, 0x2D); // enable LCD, 16bpp
+        emu.start_chrome_trace(ChromeTraceConfig { bcalls: true, ..Default::default() });
+        emu.run_cycles(2_000_000);
+        emu.stop_chrome_trace();
+
+        let tracer = emu.chrome_trace().unwrap();
+        let kinds: Vec<_> = tracer.records().iter().map(|r| r.kind).collect();
+        assert_eq!(kinds.iter().filter(|k| matches!(k, TraceKind::Bcall { entry: 0x020108 })).count(), 1);
+        assert!(kinds.iter().any(|k| matches!(k, TraceKind::Frame { .. })));
+        assert!(kinds.iter().any(|k| matches!(k, TraceKind::Scheduler(EventId::Lcd))));
+        assert!(tracer.records().windows(2).all(|w| w[0].time_us <= w[1].time_us));
+
+        let mut json = Vec::new();
+        assert!(emu.write_chrome_trace(&mut json).unwrap() > 0);
+        assert!(String::from_utf8(json).unwrap().contains("\"name\":\"frame\""));
+    }
+
+    #[test]
+    fn test_cpu_usage_per_frame() {
+        let mut emu = Emu::new();
+        let start_lcd = |emu: &mut Emu| {
+            emu.powered_on = true;
+            emu.bus.write_byte(0xE30000, 0x4C); // 320 pixels per line
+            emu.bus.write_byte(0xE30004, 0xEF); // 240 lines
+            emu.bus.write_byte(0xE3000A, 0x3F); // 320 clocks per line
+            emu.bus.write_byte(0xE3000B, 0x01);
+            emu.bus.write_byte(0xE30018, 0x2D); // enable LCD, 16bpp
+        };
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        start_lcd(&mut emu);
+        emu.run_cycles(1_000_000);
+        assert!(emu.take_frame_usage(8).is_empty());
+
+        emu.set_cpu_usage_stats(true);
+        emu.run_cycles(2_000_000);
+        let frames = emu.take_frame_usage(usize::MAX);
+        assert!(!frames.is_empty());
+        assert!(frames.iter().all(|f| f.cycles > 0 && f.halted_cycles == 0 && f.wakes == 0));
+        assert_eq!(emu.last_frame_usage(), frames.last().copied());
+
+        // EI; HALT - sleeps with no interrupt source enabled
+        emu.load_rom(&[0xFB, 0x76]).unwrap();
+        start_lcd(&mut emu);
+        emu.set_cpu_usage_stats(true);
+        emu.run_cycles(2_000_000);
+        let frames = emu.take_frame_usage(usize::MAX);
+        assert!(frames.len() > 1);
+        assert!(frames[1..].iter().all(|f| f.busy_fraction() == 0.0));
+
+        emu.set_cpu_usage_stats(false);
+        assert!(!emu.cpu_usage_stats_enabled());
+        assert_eq!(emu.last_frame_usage(), None);
+    }
+
+    #[test]
+    fn test_load_elf_runs_at_entry() {
+        // LD A,42h; LD (D00000h),A; JR $
+        let code = [0x3E, 0x42, 0x32, 0x00, 0x00, 0xD0, 0x18, 0xFE];
+        let elf = crate::elf::test_elf(0xD1A881, &code, 4, &[("_start", 0xD1A881, 6), ("spin", 0xD1A887, 2)]);
+
+        // No ROM needed
+        let mut emu = Emu::new();
+        emu.poke_byte(0xD1A889, 0x55);
+        assert_eq!(emu.load_elf(&elf), Ok(2));
+        assert_eq!(emu.pc(), 0xD1A881);
+        assert_eq!(emu.peek_byte(0xD1A889), 0x00); // .bss cleared
+
+        assert!(emu.set_breakpoint_at_symbol("spin"));
+        assert!(!emu.set_breakpoint_at_symbol("missing"));
+        emu.run_cycles(1000);
+        assert!(emu.breakpoint_was_hit());
+        assert_eq!(emu.describe_addr(emu.pc()), "spin");
+        assert_eq!(emu.describe_addr(0xD1A883), "_start+0x2");
+        assert_eq!(emu.peek_byte(0xD00000), 0x42);
+
+        assert_eq!(emu.load_elf(b"not an elf"), Err(-11));
+    }
+
+    #[test]
+    fn test_evaluate_needs_booted_os() {
+        use crate::eval::EvalError;
+
+        let mut emu = Emu::new();
+        // Bad input is rejected before touching the machine
+        assert_eq!(emu.evaluate("2x"), Err(EvalError::UnsupportedChar('x')));
+        // Nothing to run, so the OS never becomes idle
+        let results = emu.evaluate_batch(&["1+1", ""]);
+        assert_eq!(results, [Err(EvalError::Timeout), Err(EvalError::Empty)]);
+    }
+
+    #[test]
+    fn test_input_latency_from_key_to_changed_frame() {
+        let mut emu = Emu::new();
+        // Spin until the byte at D00000h is set (standing in for the OS
+        // reading the key), then paint VRAM
+        let code = [
+            0x3A, 0x00, 0x00, 0xD0, // LD A,(D00000h)
+            0xB7, // OR A
+            0x28, 0xF9, // JR Z,-7
+            0x21, 0x00, 0x00, 0xD4, // LD HL,D40000h
+            0x36, 0xFF, // LD (HL),FFh
+            0x23, // INC HL
+            0x18, 0xFB, // JR -5
+        ];
+        let elf = crate::elf::test_elf(0xD1A881, &code, 0, &[]);
+        emu.load_elf(&elf).unwrap();
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.render_frame();
+
+        emu.set_latency_tracking(true);
+        emu.run_cycles(100_000);
+        emu.render_frame();
+        emu.set_key(6, 0, true);
+        emu.bus.poke_byte(0xD00000, 1); // the "OS" sees the key
+        emu.render_frame();
+        emu.run_cycles(100_000);
+        emu.render_frame();
+
+        let samples = emu.take_input_latencies(usize::MAX);
+        assert_eq!(samples.len(), 1);
+        assert_eq!((samples[0].row, samples[0].col, samples[0].unchanged_frames), (6, 0, 1));
+        let expected_us = 100_000.0 / emu.cpu_clock_hz() * 1e6;
+        assert!((samples[0].emulated_us - expected_us).abs() < 50.0);
+        assert_eq!(emu.input_latency_stats().unwrap().samples, 1);
+
+        emu.set_latency_tracking(false);
+        assert_eq!(emu.input_latency_stats(), None);
+    }
+
+    #[test]
+    fn test_skin_screenshot_highlights_held_keys() {
+        use crate::skin::{Skin, SkinKey, SkinLayout, SkinRect};
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00]).unwrap();
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.bus.write_byte(0xD40000, 0xFF); // top-left pixel white
+        emu.bus.write_byte(0xD40001, 0xFF);
+        emu.render_frame();
+
+        let layout = SkinLayout {
+            screen: Some(SkinRect { x: 0, y: 0, width: 32, height: 24 }),
+            keys: vec![SkinKey { row: 6, col: 0, rect: SkinRect { x: 0, y: 30, width: 8, height: 8 } }],
+        };
+        let skin = Skin::new(32, 40, vec![0xFF000000; 32 * 40], layout).unwrap();
+
+        let shot = emu.render_skin_screenshot(&skin, Some(0xFFFF0000));
+        assert_eq!(shot.len(), 32 * 40);
+        assert_eq!(shot[0], emu.framebuffer_data()[0]);
+        assert_eq!(shot[30 * 32], 0xFF000000);
+
+        emu.set_key(6, 0, true);
+        let shot = emu.render_skin_screenshot(&skin, Some(0xFFFF0000));
+        assert_eq!(shot[30 * 32], 0xFFFF0000);
+        assert_eq!(emu.render_skin_screenshot(&skin, None)[30 * 32], 0xFF000000);
+    }
+
+    #[test]
+    fn test_frame_hash_and_screenshot() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.render_frame();
+        let blank = emu.frame_hash();
+        emu.render_frame();
+        assert_eq!(emu.frame_hash(), blank);
+        emu.poke_byte(0xD40000, 0xFF);
+        emu.render_frame();
+        assert_ne!(emu.frame_hash(), blank);
+
+        #[cfg(feature = "png")]
+        {
+            let png = emu.screenshot_png();
+            assert!(png.starts_with(b"\x89PNG"));
+            assert_eq!(&png[16..24], &[0, 0, 1, 64, 0, 0, 0, 240]);
+        }
+    }
+
+    #[cfg(feature = "recording")]
+    #[test]
+    fn test_screen_recording() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+
+        let dir = std::env::temp_dir();
+        assert!(emu.start_recording(dir.join("emu_test_recording.txt"), 10).is_err());
+        let path = dir.join(format!("emu_test_recording_{}.gif", std::process::id()));
+        emu.start_recording(&path, 50).unwrap();
+        assert!(emu.is_recording());
+        let frame_cycles = (emu.cpu_clock_hz() / 50.0) as u32;
+        for i in 0..6u8 {
+            emu.poke_byte(0xD40000, i / 2);
+            emu.render_frame();
+            emu.run_cycles(frame_cycles);
+        }
+        // Three distinct screens
+        assert_eq!(emu.stop_recording().unwrap().unwrap(), 3);
+        assert!(!emu.is_recording());
+        assert!(emu.stop_recording().is_none());
+
+        let gif = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(gif.starts_with(b"GIF89a\x40\x01\xF0\x00"));
+        assert_eq!(gif.last(), Some(&0x3B));
+    }
+
+    #[test]
+    fn test_input_replay() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let start = emu.save_state_vec().unwrap();
+
+        emu.start_input_recording();
+        emu.run_cycles(1000);
+        emu.set_key(6, 0, true);
+        emu.run_cycles(777);
+        emu.set_key(6, 0, false);
+        emu.press_on_key();
+        emu.run_cycles(500);
+        let data = emu.stop_input_recording().unwrap();
+        assert!(!emu.is_recording_input());
+        let end_cycles = emu.total_cycles();
+        let end_hash = emu.state_hash();
+
+        // One call, re-recording what the replay injects
+        emu.load_state(&start).unwrap();
+        assert_eq!(emu.play_replay(&data), Ok(3));
+        emu.start_input_recording();
+        emu.run_cycles((end_cycles - emu.total_cycles()) as u32);
+        assert!(!emu.is_replaying());
+        assert_eq!(emu.stop_input_recording().unwrap(), data);
+        assert_eq!(emu.total_cycles(), end_cycles);
+        assert_eq!(emu.state_hash(), end_hash);
+
+        assert_eq!(emu.play_replay(&data[..5]), Err(replay::ERR_INVALID));
+    }
+
+    #[test]
+    fn test_frame_hash_stream() {
+        use crate::frame_hash::HashRect;
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.render_frame();
+        assert!(emu.take_frame_hashes(8).is_empty());
+
+        assert!(emu.set_frame_hashing(true, None));
+        emu.render_frame();
+        emu.render_frame();
+        // A pixel at the bottom-right corner of VRAM changes the full-frame hash
+        emu.poke_byte(0xD40000 + (320 * 240 - 1) * 2, 0xFF);
+        emu.render_frame();
+        let hashes = emu.take_frame_hashes(8);
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(hashes[0].crc, hashes[1].crc);
+        assert_ne!(hashes[1].crc, hashes[2].crc);
+        assert_eq!(hashes[2].frame, hashes[0].frame + 2);
+
+        // A sub-rectangle that excludes the pixel is unaffected
+        assert!(emu.set_frame_hashing(true, Some(HashRect { x: 0, y: 0, width: 320, height: 239 })));
+        emu.render_frame();
+        emu.poke_byte(0xD40000 + (320 * 240 - 1) * 2, 0x00);
+        emu.render_frame();
+        let hashes = emu.take_frame_hashes(8);
+        assert_eq!(hashes[0].crc, hashes[1].crc);
+
+        assert!(!emu.set_frame_hashing(true, Some(HashRect { x: 320, y: 0, width: 1, height: 1 })));
+        assert!(emu.set_frame_hashing(false, None));
+        emu.render_frame();
+        assert!(emu.take_frame_hashes(8).is_empty());
+    }
+
+    #[test]
+    fn test_sandbox_reports_user_program_writes() {
+        use crate::sandbox::{GuardKind, GuardRegion, PTEMP_ADDR};
+
+        let mut emu = Emu::new();
+        // Boot code jumps to a "program" in RAM
+        emu.load_rom(&[0xC3, 0x81, 0xA8, 0xD1]).unwrap(); // JP D1A881
+        emu.cpu.adl = true;
+        emu.powered_on = true;
+        // LD A,5; LD (D00090),A; LD (D3F800),A; LD (D20000),A; JR $
+        let program = [0x3E, 0x05, 0x32, 0x90, 0x00, 0xD0, 0x32, 0x00, 0xF8, 0xD3, 0x32, 0x00, 0x00, 0xD2, 0x18, 0xFE];
+        for (i, &b) in program.iter().enumerate() {
+            emu.poke_byte(0xD1A881 + i as u32, b);
+        }
+        // pTemp: VAT occupies D3F000..=D3FFFF
+        for (i, b) in [0xFF, 0xEF, 0xD3].into_iter().enumerate() {
+            emu.poke_byte(PTEMP_ADDR + i as u32, b);
+        }
+        emu.enable_sandbox(vec![GuardRegion::new(0xD20000, 0xD20000, GuardKind::Custom)]);
+        emu.run_cycles(1_000);
+
+        let found = emu.take_sandbox_violations(16);
+        let summary: Vec<_> = found.iter().map(|v| (v.pc, v.addr, v.kind)).collect();
+        assert_eq!(summary, [
+            (0xD1A883, 0xD00090, GuardKind::SystemFlags),
+            (0xD1A887, 0xD3F800, GuardKind::Vat),
+            (0xD1A88B, 0xD20000, GuardKind::Custom),
+        ]);
+        assert_eq!(found[0].value, 5);
+        assert!(emu.sandbox_report().is_empty());
+    }
+
+    #[test]
+    fn test_exam_mode_key_combination() {
+        use crate::exam_mode::ExamFlag;
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.enter_exam_mode();
+        assert!(emu.exam_mode_active());
+        assert!(emu.powered_on);
+        // The combination is released again
+        assert!(!emu.bus.key_state()[7][1] && !emu.bus.key_state()[7][2]);
+
+        // The LED blinks
+        let mut lit = [false; 2];
+        for _ in 0..8 {
+            emu.run_cycles(1_000_000);
+            lit[emu.exam_led_on() as usize] = true;
+        }
+        assert_eq!(lit, [true, true]);
+
+        emu.exit_exam_mode();
+        assert!(!emu.exam_mode_active());
+        assert!(!emu.exam_led_on());
+
+        // With the OS flag location known, the OS state is set and cleared
+        emu.set_exam_flag(ExamFlag::new(0xD01000, 2));
+        emu.enter_exam_mode();
+        assert_eq!(emu.peek_byte(0xD01000), 0x04);
+        emu.poke_byte(0xD01000, 0x00); // the OS leaves exam mode itself
+        assert!(!emu.exam_mode_active());
+        emu.poke_byte(0xD01000, 0x05);
+        emu.exit_exam_mode();
+        assert_eq!(emu.peek_byte(0xD01000), 0x01);
+    }
+
+    #[test]
+    fn test_runaway_watchdog_reports_stuck_loop() {
+        use crate::runaway::{RunawayConfig, RunawayKind};
+        use std::sync::{Arc, Mutex};
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0xF3, 0x18, 0xFE]).unwrap(); // DI; JR $
+        emu.powered_on = true;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let config = RunawayConfig { stuck_cycles: 10_000, ..Default::default() };
+        emu.set_runaway_watchdog(Some(config), Some(Box::new(move |r| sink.lock().unwrap().push(r.kind))));
+
+        emu.run_cycles(5_000);
+        assert!(seen.lock().unwrap().is_empty());
+        emu.run_cycles(50_000);
+        assert_eq!(*seen.lock().unwrap(), [RunawayKind::StuckLoop { start: 1, end: 1 }]);
+        let report = emu.take_runaway_report().unwrap();
+        assert_eq!(report.pc, 1);
+        assert!(report.context.contains("IFF1=false"));
+        assert!(emu.take_runaway_report().is_none());
+
+        // Reported once per loop
+        emu.run_cycles(50_000);
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cheats_applied_each_frame() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let id = emu.add_cheat(Cheat::new(0xD01000, 42));
+
+        emu.run_cycles(1_000);
+        assert_eq!(emu.peek_byte(0xD01000), 42);
+        emu.poke_byte(0xD01000, 0);
+        emu.run_cycles(1_000);
+        assert_eq!(emu.peek_byte(0xD01000), 42);
+
+        // At vblank, nothing is applied until the LCD completes a frame
+        emu.set_cheat_point(CheatPoint::Vblank);
+        emu.poke_byte(0xD01000, 0);
+        emu.run_cycles(1_000);
+        assert_eq!(emu.peek_byte(0xD01000), 0);
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.run_cycles(2_000_000);
+        assert!(emu.lcd_frame_count() > 0);
+        assert_eq!(emu.peek_byte(0xD01000), 42);
+
+        assert!(emu.set_cheat_enabled(id, false));
+        emu.poke_byte(0xD01000, 0);
+        emu.run_cycles(2_000_000);
+        assert_eq!(emu.peek_byte(0xD01000), 0);
+    }
+
+    #[test]
+    fn test_lcd_base_changes() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        emu.run_cycles(2_000_000);
+        // Same buffer every frame: no changes
+        assert!(emu.take_lcd_base_changes(usize::MAX).is_empty());
+
+        // Flip to the second half of VRAM and back, one frame each
+        for &base in &[0xD52C00u32, 0xD40000] {
+            emu.bus.write_byte(0xE30011, (base >> 8) as u8);
+            emu.bus.write_byte(0xE30012, (base >> 16) as u8);
+            emu.run_cycles(2_000_000);
+        }
+        let changes = emu.take_lcd_base_changes(usize::MAX);
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].old_base, changes[0].new_base), (0xD40000, 0xD52C00));
+        assert_eq!((changes[1].old_base, changes[1].new_base), (0xD52C00, 0xD40000));
+        assert!(changes[1].frame > changes[0].frame);
+        assert!(changes[1].cycle > changes[0].cycle);
+        assert!(emu.take_lcd_base_changes(usize::MAX).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "profiler")]
+    fn test_profiling() {
+        let mut emu = Emu::new();
+        let rom = vec![0x00, 0x00, 0x00, 0x76]; // NOP, NOP, NOP, HALT
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+
+        // Disabled by default: nothing recorded
+        emu.run_cycles(100);
+        assert!(emu.profile_breakdown().iter().all(|e| e.calls == 0));
+
+        emu.set_profiling(true);
+        emu.run_cycles(1000);
+        emu.render_frame();
+        let cpu = emu.profile_breakdown()[ProfileSection::Cpu as usize];
+        let lcd = emu.profile_breakdown()[ProfileSection::LcdRender as usize];
+        assert!(cpu.calls > 0);
+        assert_eq!(lcd.calls, 1);
+
+        emu.reset_profile();
+        assert!(emu.profile_breakdown().iter().all(|e| e.calls == 0 && e.nanos == 0));
+    }
+
+    #[test]
+    fn test_warm_boot_state() {
+        // EI; HALT; JR -3 - reaches idle immediately
+        let rom = vec![0xFB, 0x76, 0x18, 0xFD];
+        let mut emu = Emu::new();
+        assert_eq!(emu.capture_warm_boot_state(1_000_000).unwrap_err(), -10);
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true; // Skip the ON key interrupt this test ROM can't service
+        let snapshot = emu.capture_warm_boot_state(10_000_000).unwrap();
+        assert!(Emu::is_warm_boot_state(&snapshot));
+
+        let mut fresh = Emu::new();
+        fresh.load_rom(&rom).unwrap();
+        assert!(fresh.boot_from_warm_state(&snapshot).is_ok());
+        assert!(fresh.powered_on);
+        assert_eq!(fresh.cpu.pc, emu.cpu.pc);
+        assert!(fresh.is_idle());
+
+        // A regular save state is not accepted as a warm-boot snapshot
+        let mut regular = vec![0u8; emu.save_state_size()];
+        emu.save_state(&mut regular).unwrap();
+        assert!(!Emu::is_warm_boot_state(&regular));
+        assert_eq!(fresh.boot_from_warm_state(&regular), Err(-107));
+        // ...but the warm-boot snapshot still loads as a regular state
+        assert!(fresh.load_state(&snapshot).is_ok());
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let rom = vec![0x3C, 0x18, 0xFD]; // INC A; JR -3
+        let mut a = Emu::new();
+        let mut b = Emu::new();
+        a.load_rom(&rom).unwrap();
+        b.load_rom(&rom).unwrap();
+        a.powered_on = true;
+        b.powered_on = true;
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        a.run_cycles(10_000);
+        assert_ne!(a.state_hash(), b.state_hash());
+        b.run_cycles(10_000);
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        // Save/load round trip preserves the hash
+        let mut state = vec![0u8; a.save_state_size()];
+        a.save_state(&mut state).unwrap();
+        let mut c = Emu::new();
+        c.load_rom(&rom).unwrap();
+        c.load_state(&state).unwrap();
+        assert_eq!(c.state_hash(), a.state_hash());
+
+        // A single RAM byte changes it
+        c.bus.ram.write(0x1234, c.bus.ram.read(0x1234) ^ 1);
+        assert_ne!(c.state_hash(), a.state_hash());
+
+        // So does a USB register
+        c.load_state(&state).unwrap();
+        c.bus.port_write(0x3018, 0x01);
+        assert_ne!(c.state_hash(), a.state_hash());
+    }
+
+    #[test]
+    fn test_keypad_scan_runs_on_scheduler() {
+        use crate::peripherals::interrupt::sources;
+
+        let rom = [0x18, 0xFE]; // JR $
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        emu.set_key(3, 5, true);
+
+        // Scan-done interrupt; single scan with rowWait = 600 (100us at 6MHz)
+        emu.bus.write_byte(0xF5000C, 0x01);
+        emu.bus.write_byte(0xF50001, (600u32 >> 6) as u8);
+        emu.bus.write_byte(0xF50000, ((600 << 2) & 0xFF) as u8 | 2);
+        emu.run_cycles(100);
+        assert!(emu.scheduler.is_active(EventId::Keypad));
+        assert!(emu.bus.ports.keypad.is_scanning());
+
+        // Save mid-scan; the copy finishes the scan too
+        emu.run_cycles(2_000);
+        let state = emu.save_state_vec().unwrap();
+        let mut copy = Emu::new();
+        copy.load_rom(&rom).unwrap();
+        copy.load_state(&state).unwrap();
+        assert!(copy.scheduler.is_active(EventId::Keypad));
+
+        // 8 rows x 100us at 6MHz CPU = 4800 cycles
+        for emu in [&mut emu, &mut copy] {
+            emu.run_cycles(4_000);
+            assert_eq!(emu.keypad_mode(), 0);
+            assert!(!emu.scheduler.is_active(EventId::Keypad));
+            assert_ne!(emu.bus.ports.interrupt.status_word(0) & sources::KEYPAD, 0);
+            assert_eq!(emu.bus.read_byte(0xF50016), 1 << 5);
+        }
+    }
+
+    #[test]
+    fn test_state_sections_round_trip() {
+        let rom = [0x18, 0xFE]; // JR $
+        let mut a = Emu::new();
+        a.load_rom(&rom).unwrap();
+        // SHA-256 of a zero block, and a pixel drawn straight to the panel
+        a.bus.port_write(0x2000, 0x0A);
+        for frame in [0x2A, 0x100, 0x100, 0x100, 0x100, 0x2C, 0x1F8, 0x100] {
+            a.bus.spi().panel_mut().transfer(frame);
+        }
+        assert_eq!(a.panel_framebuffer()[0], 0xFFFF0000);
+        // Control registers the base peripheral state leaves out
+        a.bus.port_write(0x0029, 0x01);
+        a.bus.port_write(0x0030, 0x5A);
+        // USB host and OTG interrupt enables
+        a.bus.port_write(0x3018, 0x05);
+        a.bus.port_write(0x3089, 0x02);
+
+        let state = a.save_state_vec().unwrap();
+        assert_eq!(state.len(), a.save_state_size());
+        let mut b = Emu::new();
+        b.load_rom(&rom).unwrap();
+        b.load_state(&state).unwrap();
+        assert_eq!(b.panel_framebuffer()[0], 0xFFFF0000);
+        assert_eq!(b.bus.port_read(0x200C), a.bus.port_read(0x200C));
+        assert_eq!((b.bus.port_read(0x0029), b.bus.port_read(0x0030)), (0x01, 0x5A));
+        assert_eq!((b.bus.port_read(0x3018), b.bus.port_read(0x3089)), (0x05, 0x02));
+        assert_eq!(b.state_hash(), a.state_hash());
+
+        // A section from a newer build is skipped
+        let mut newer = state.clone();
+        newer.extend_from_slice(b"NEW!\x02\x00\x00\x00\xAA\xBB");
+        let data_len = (newer.len() - Emu::STATE_HEADER_SIZE) as u32;
+        newer[16..20].copy_from_slice(&data_len.to_le_bytes());
+        b.load_state(&newer).unwrap();
+        assert_eq!(b.state_hash(), a.state_hash());
+
+        // A v10 state has no sections: the panel and SHA256 start from reset
+        b.load_state(&v10_state(&state)).unwrap();
+        assert_eq!(b.panel_framebuffer()[0], 0xFF000000);
+        assert_eq!(b.bus.port_read(0x200C), 0);
+    }
+
+    /// `state` cut down to a v10 state, which ends after flash
+    fn v10_state(state: &[u8]) -> Vec<u8> {
+        let base = state.len() - 48 - PanelStub::SNAPSHOT_SIZE - Sha256Controller::SNAPSHOT_SIZE
+            - KeypadController::SNAPSHOT_SIZE - 8 - WatchdogController::SNAPSHOT_SIZE
+            - ControlPorts::SNAPSHOT_SIZE - UsbController::SNAPSHOT_SIZE;
+        let mut v10 = state[..base].to_vec();
+        v10[4..8].copy_from_slice(&10u32.to_le_bytes());
+        v10[16..20].copy_from_slice(&((base - Emu::STATE_HEADER_SIZE) as u32).to_le_bytes());
+        v10
+    }
+
+    #[test]
+    fn test_v10_state_resets_usb_and_control_ports() {
+        let rom = [0x18, 0xFE]; // JR $
+        let mut a = Emu::new();
+        a.load_rom(&rom).unwrap();
+        let v10 = v10_state(&a.save_state_vec().unwrap());
+
+        // A live session with an accessory, USB registers and control
+        // registers the base peripheral state leaves out
+        let mut b = Emu::new();
+        b.load_rom(&rom).unwrap();
+        b.attach_serial_accessory(SerialAccessoryConfig::default());
+        usb_write32(&mut b, 0x18, 0x05);
+        usb_write32(&mut b, 0x28, 0xD01000);
+        usb_write32(&mut b, 0x10, 0x21);
+        b.bus.port_write(0x0029, 0x01);
+        b.bus.port_write(0x0030, 0x5A);
+
+        b.load_state(&v10).unwrap();
+        for port in [0x3010, 0x3018, 0x3028, 0x0029, 0x0030] {
+            assert_eq!(b.bus.port_read(port), a.bus.port_read(port), "port {:04X}", port);
+        }
+        // The accessory stays plugged in, as after a reset
+        assert!(b.bus.usb.device().is_some());
+    }
+
+    #[test]
+    fn test_corrupt_state_leaves_emulator_unchanged() {
+        let rom = [0x18, 0xFE]; // JR $
+        let mut a = Emu::new();
+        a.load_rom(&rom).unwrap();
+        let state = a.save_state_vec().unwrap();
+        let with_tail = |tail: &[u8]| {
+            let mut state = state.clone();
+            state.extend_from_slice(tail);
+            let data_len = (state.len() - Emu::STATE_HEADER_SIZE) as u32;
+            state[16..20].copy_from_slice(&data_len.to_le_bytes());
+            state
+        };
+
+        let mut b = Emu::new();
+        b.load_rom(&rom).unwrap();
+        b.cpu.a = 0x42;
+        b.bus.ram.write(0x100, 0x99);
+        b.bus.port_write(0x3018, 0x05);
+        let hash = b.state_hash();
+
+        for bad in [
+            // A trailing section running past the end
+            with_tail(b"NEW!\xFF\xFF\xFF\xFF\xAA"),
+            // A partial section header
+            with_tail(b"NEW"),
+            // A known section too short for its contents
+            with_tail(b"USB0\x04\x00\x00\x00\x00\x00\x00\x00"),
+        ] {
+            assert_eq!(b.load_state(&bad), Err(-105));
+            assert_eq!(b.state_hash(), hash);
+        }
+        assert_eq!(b.cpu.a, 0x42);
+    }
+
+    #[test]
+    fn test_import_cemu_exports() {
+        use crate::memory::addr::{FLASH_SIZE, RAM_SIZE};
+
+        let mut flash = vec![0xFF; FLASH_SIZE];
+        flash[..2].copy_from_slice(&[0x18, 0xFE]); // JR $
+        flash[0x3B0000] = 0x42; // archive
+        let mut ram = vec![0; RAM_SIZE];
+        ram[0x0595] = 0x99; // D00595h
+
+        let mut emu = Emu::new();
+        emu.import_cemu(&flash, Some(&ram)).unwrap();
+        assert_eq!(emu.peek_byte(0x3B0000), 0x42);
+        assert_eq!(emu.peek_byte(0xD00595), 0x99);
+        assert_eq!(emu.pc(), 0);
+
+        assert_eq!(emu.import_cemu(&flash, Some(&ram[..16])), Err(CemuImportError::RamSize(16)));
+    }
+
+    #[test]
+    fn test_rewind_restores_earlier_state() {
+        use crate::rewind::RewindConfig;
+
+        let mut emu = Emu::new();
+        // Count up in D00000h forever
+        let code = [
+            0x21, 0x00, 0x00, 0xD0, // LD HL,D00000h
+            0x34, // INC (HL)
+            0x18, 0xFD, // JR -3
+        ];
+        let elf = crate::elf::test_elf(0xD1A881, &code, 0, &[]);
+        emu.load_elf(&elf).unwrap();
+        emu.bus.write_byte(0xE30018, 0x2D); // enable, 16bpp
+        assert_eq!(emu.rewind(1), Err(-1));
+
+        emu.set_rewind(Some(RewindConfig { interval_frames: 2, capacity: 4 }));
+        // A snapshot is due at the start of every run (frames are short here)
+        let mut counts = Vec::new();
+        for _ in 0..6 {
+            counts.push(emu.peek_byte(0xD00000));
+            emu.run_cycles(50_000);
+        }
+        let (snapshots, bytes) = emu.rewind_usage();
+        assert_eq!(snapshots, 4);
+        // Three deltas cost far less than three more full states
+        assert!(bytes < emu.save_state_size() * 2);
+
+        // Back to the newest snapshot, then one further
+        assert!(emu.rewind(1).unwrap() > 0);
+        assert_eq!(emu.peek_byte(0xD00000), counts[5]);
+        assert_eq!(emu.rewind_usage().0, 4);
+        assert!(emu.rewind(1).unwrap() > 0);
+        assert_eq!(emu.peek_byte(0xD00000), counts[4]);
+        assert_eq!(emu.rewind_usage().0, 3);
+
+        // Past the history stops at the oldest snapshot kept
+        emu.rewind(u32::MAX).unwrap();
+        assert_eq!(emu.peek_byte(0xD00000), counts[2]);
+        assert_eq!(emu.rewind_usage().0, 1);
+
+        // The restored machine keeps running from there
+        emu.run_cycles(50_000);
+        assert_ne!(emu.peek_byte(0xD00000), counts[2]);
+
+        emu.set_rewind(None);
+        assert_eq!(emu.rewind(1), Err(-1));
+    }
+
+    #[test]
+    fn test_raise_and_clear_irq() {
+        use crate::peripherals::interrupt::sources;
+
+        let mut emu = Emu::new();
+        emu.raise_irq(sources::USB);
+        assert!(!emu.irq_pending()); // not enabled
+        assert_ne!(emu.interrupt_raw() & sources::USB, 0);
+
+        emu.bus.write_byte(0xF00005, (sources::USB >> 8) as u8);
+        emu.raise_irq(sources::USB);
+        assert!(emu.irq_pending());
+        emu.clear_irq(sources::USB);
+        assert!(!emu.irq_pending());
+
+        // Latched: stays pending after the line drops, until acknowledged
+        emu.bus.write_byte(0xF0000D, (sources::USB >> 8) as u8);
+        emu.set_irq(sources::USB, true);
+        emu.set_irq(sources::USB, false);
+        assert!(emu.irq_pending());
+        assert_eq!(emu.interrupt_latched(), sources::USB);
+        emu.bus.write_byte(0xF00009, (sources::USB >> 8) as u8);
+        assert_eq!(emu.interrupt_status() & sources::USB, 0);
+    }
+
+    #[test]
+    fn test_os_timer_runs_on_scheduler_while_halted() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0xF3, 0x76]).unwrap(); // DI; HALT
+        emu.powered_on = true;
+        assert!(emu.scheduler.is_active(EventId::OsTimer));
+
+        // At 6MHz: low for 73 32kHz ticks, then high for 1
+        let tick = 6_000_000.0 / 32768.0;
+        emu.run_cycles((tick * 72.5) as u32);
+        assert!(emu.is_halted());
+        assert_eq!(emu.bus.read_byte(0xF00008) & 0x10, 0);
+        emu.run_cycles(tick as u32);
+        assert_ne!(emu.bus.read_byte(0xF00008) & 0x10, 0);
+        emu.run_cycles(tick as u32);
+        assert_eq!(emu.bus.read_byte(0xF00008) & 0x10, 0);
+
+        // Restored states keep the event
+        let state = emu.save_state_vec().unwrap();
+        emu.load_state(&state).unwrap();
+        assert!(emu.scheduler.is_active(EventId::OsTimer));
+    }
+
+    #[test]
+    fn test_fast_mode_still_delivers_peripheral_interrupts() {
+        for mode in [AccuracyMode::Full, AccuracyMode::Fast] {
+            let mut emu = Emu::new();
+            emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+            emu.powered_on = true;
+            emu.set_accuracy_mode(mode);
+            // Watchdog NMI after 10,000 cycles; fast mode may be up to a batch late
+            for (i, b) in 10_000u32.to_le_bytes().iter().enumerate() {
+                emu.bus.write_byte(0xF60004 + i as u32, *b);
+            }
+            emu.bus.write_byte(0xF60008, 0xB9);
+            emu.bus.write_byte(0xF6000C, 0x05);
+            emu.run_cycles(9_000);
+            assert!(!emu.cpu.nmi_pending && emu.cpu.nmis_serviced == 0, "{:?}", mode);
+            emu.run_cycles(3_000);
+            assert!(emu.cpu.nmi_pending || emu.cpu.nmis_serviced > 0, "{:?}", mode);
+
+            // Block instructions only stop at scheduler events in full mode
+            emu.set_block_limit();
+            assert_eq!(emu.cpu.block_cycle_limit == u64::MAX, mode == AccuracyMode::Fast);
+        }
+    }
+
+    #[test]
+    fn test_watchdog_expiry_resets_machine() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.bus.write_byte(0xD00000, 0x42);
+        // Load 10,000 cycles, kick, enable with interrupt (NMI) only
+        for (i, b) in 10_000u32.to_le_bytes().iter().enumerate() {
+            emu.bus.write_byte(0xF60004 + i as u32, *b);
+        }
+        emu.bus.write_byte(0xF60008, 0xB9);
+        emu.bus.write_byte(0xF6000C, 0x05);
+        emu.run_cycles(12_000);
+        assert!(emu.cpu.nmi_pending || emu.cpu.nmis_serviced > 0);
+        assert_eq!(emu.bus.read_byte(0xF60010), 1);
+
+        // Saved and restored with the state
+        let state = emu.save_state_vec().unwrap();
+        let count = emu.bus.read_byte(0xF60000);
+        emu.bus.write_byte(0xF6000C, 0);
+        emu.load_state(&state).unwrap();
+        assert_eq!(emu.bus.read_byte(0xF6000C), 0x05);
+        assert_eq!(emu.bus.read_byte(0xF60000), count);
+
+        // With reset enabled, expiry reboots and keeps RAM
+        emu.bus.write_byte(0xF60008, 0xB9);
+        emu.bus.write_byte(0xF6000C, 0x03);
+        let resets = emu.reset_count;
+        emu.run_cycles(12_000);
+        assert_eq!(emu.reset_count, resets + 1);
+        assert_eq!(emu.pc(), 0);
+        assert_eq!(emu.peek_byte(0xD00000), 0x42);
+        assert_eq!(emu.bus.read_byte(0xF6000C), 0); // watchdog back to reset state
+    }
+
+    #[test]
+    fn test_rtc_time_and_host_sync() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        // 2024-03-01 13:45:30
+        emu.set_rtc_time(1_709_300_730);
+        assert_eq!(emu.rtc_time(), 1_709_300_730);
+        // Latched registers read back the new time: seconds, minutes, hours
+        assert_eq!(emu.bus.read_byte(0xF80000), 30);
+        assert_eq!(emu.bus.read_byte(0xF80004), 45);
+        assert_eq!(emu.bus.read_byte(0xF80008), 13);
+
+        let now = host_unix_secs().unwrap();
+        assert!(emu.set_rtc_host_sync(Some(3600)));
+        assert!((emu.rtc_time() - (now + 3600)).abs() <= 1);
+
+        // Drift is corrected at the next run
+        emu.bus.ports.rtc.set_unix_time(0);
+        emu.run_cycles(1000);
+        assert!((emu.rtc_time() - (now + 3600)).abs() <= 2);
+
+        // Setting a time by hand stops tracking
+        emu.set_rtc_time(1_709_300_730);
+        emu.run_cycles(1000);
+        assert_eq!(emu.rtc_time(), 1_709_300_730);
+    }
+
+    #[test]
+    fn test_rtc_alarm_raises_interrupt() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.set_rtc_time(1_709_300_730); // 13:45:30
+        emu.bus.write_byte(0xF80010, 32); // alarm 13:45:32
+        emu.bus.write_byte(0xF80014, 45);
+        emu.bus.write_byte(0xF80018, 13);
+        emu.bus.write_byte(0xF80020, 0x21); // enable, alarm interrupt
+
+        let second = emu.cpu_clock_hz() as u32;
+        emu.run_cycles(second);
+        assert_eq!(emu.bus.read_byte(0xF80034), 0);
+        assert_eq!(emu.bus.read_byte(0xF00009) & 0x10, 0);
+
+        emu.run_cycles(second);
+        assert_eq!(emu.bus.read_byte(0xF80034), 0x10);
+        assert_ne!(emu.bus.read_byte(0xF00009) & 0x10, 0); // raw bit 12
+
+        // Acknowledging the alarm drops the line
+        emu.bus.write_byte(0xF80034, 0x10);
+        emu.run_cycles(100);
+        assert_eq!(emu.bus.read_byte(0xF00009) & 0x10, 0);
+    }
+
+    #[test]
+    fn test_homescreen_text() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00]).unwrap();
+        let line = crate::charset::encode("2→A      ").unwrap();
+        for (i, &b) in line.iter().enumerate() {
+            emu.poke_byte(TEXT_SHADOW_ADDR + HOMESCREEN_COLS as u32 + i as u32, b);
+        }
+        let text = emu.homescreen_text();
+        assert_eq!(text.len(), HOMESCREEN_ROWS);
+        assert_eq!(text[1], "2→A");
+
+        // Rows 2 onward cleared to blanks, as the OS does; θ² ends row 2
+        let blank = " ".repeat(HOMESCREEN_COLS);
+        for row in 2..HOMESCREEN_ROWS {
+            for (i, &b) in blank.as_bytes().iter().enumerate() {
+                emu.poke_byte(TEXT_SHADOW_ADDR + (row * HOMESCREEN_COLS + i) as u32, b);
+            }
+        }
+        let line = crate::charset::encode("θ²").unwrap();
+        for (i, &b) in line.iter().enumerate() {
+            emu.poke_byte(TEXT_SHADOW_ADDR + (3 * HOMESCREEN_COLS - 2 + i) as u32, b);
+        }
+        let expected = format!("{}\n2→A\n{}θ²", text[0], " ".repeat(HOMESCREEN_COLS - 2));
+        assert_eq!(emu.read_screen_text(), expected);
+    }
+
+    #[test]
+    fn test_swap_rom_keeps_configuration() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x3C, 0x18, 0xFD]).unwrap(); // INC A; JR -3
+        emu.set_deterministic(Some(7));
+        emu.set_breakpoint(0x000010);
+        emu.power_on();
+        emu.run_cycles(1_000);
+        let resets = emu.reset_count;
+
+        emu.swap_rom(&[0x00, 0x18, 0xFD]).unwrap(); // NOP; JR -3
+        assert_eq!(emu.peek_byte(0), 0x00);
+        assert_eq!(emu.reset_count, resets + 1);
+        assert_eq!(emu.deterministic_seed(), Some(7));
+        assert_eq!(emu.breakpoint_pc, Some(0x000010));
+        assert!(emu.powered_on);
+
+        // A failed swap leaves the current ROM in place
+        assert_eq!(emu.swap_rom(&[]), Err(-2));
+        assert_eq!(emu.peek_byte(0), 0x00);
+    }
+
+    #[test]
+    fn test_diagnostic_bundle() {
+        use crate::peripherals::interrupt::sources;
+        use std::sync::{Arc, Mutex};
+
+        /// Sink that collects bundles into shared storage
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Shared {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut emu = Emu::new();
+        // EI; NOP; NOP; HALT
+        emu.load_rom(&[0xFB, 0x00, 0x00, 0x76]).unwrap();
+        emu.powered_on = true;
+        emu.bus.ports.interrupt.write(0x04, 0x01);
+        emu.bus.ports.interrupt.raise(sources::ON_KEY);
+        emu.cpu.irq_pending = true;
+        for _ in 0..4 {
+            emu.step();
+        }
+        let irqs = emu.irq_log();
+        assert_eq!(irqs.len(), 1);
+        assert_eq!(irqs[0].sources & 1, 1);
+        assert!(!irqs[0].nmi);
+
+        let mut out = Vec::new();
+        emu.write_diagnostic_bundle(&DiagnosticTrigger::UserRequest, &mut out).unwrap();
+        let sections = diagnostics::parse_bundle(&out).unwrap();
+        let tags: Vec<&[u8; 4]> = sections.iter().map(|(tag, _)| tag).collect();
+        assert_eq!(tags, [b"INFO", b"STAT", b"HIST", b"IRQS", b"UNKN"]);
+        assert!(String::from_utf8_lossy(sections[0].1).contains("trigger: user request"));
+        // The embedded save state loads
+        let mut restored = Emu::new();
+        restored.load_rom(&[0xFB, 0x00, 0x00, 0x76]).unwrap();
+        assert!(restored.load_state(sections[1].1).is_ok());
+
+        // Automatic bundles fire once per arming
+        let shared = Arc::new(Mutex::new(Vec::new()));
+        emu.set_diagnostic_sink(Some(Box::new(Shared(shared.clone()))));
+        emu.write_auto_diagnostics(DiagnosticTrigger::InternalError("test".into()));
+        let first_len = shared.lock().unwrap().len();
+        assert!(first_len > 0);
+        emu.write_auto_diagnostics(DiagnosticTrigger::InternalError("again".into()));
+        assert_eq!(shared.lock().unwrap().len(), first_len);
+        // ...but user requests always write
+        assert!(emu.dump_diagnostics().is_ok());
+        assert!(shared.lock().unwrap().len() > first_len);
+    }
+
+    // USB host schedule helpers: QH at D01000, qTD at D01100, buffer at D01200
+    fn put32(emu: &mut Emu, addr: u32, value: u32) {
+        for i in 0..4 {
+            emu.bus.ram.write(addr - 0xD00000 + i, (value >> (i * 8)) as u8);
+        }
+    }
+    fn usb_write32(emu: &mut Emu, offset: u16, value: u32) {
+        for i in 0..4 {
+            emu.bus.port_write(0x3000 + offset + i, (value >> (i * 8)) as u8);
+        }
+    }
+    fn queue(emu: &mut Emu, ep: u32, pid: u32, data: &[u8], len: u32) {
+        for (i, &byte) in data.iter().enumerate() {
+            emu.bus.ram.write(0x1200 + i as u32, byte);
+        }
+        put32(emu, 0xD01000, 0xD01000 | 2);
+        put32(emu, 0xD01004, ep << 8 | 64 << 16);
+        put32(emu, 0xD01010, 0xD01100);
+        put32(emu, 0xD01018, 0);
+        put32(emu, 0xD01100, 1);
+        put32(emu, 0xD01104, 1);
+        put32(emu, 0xD01108, 0x80 | pid << 8 | len << 16);
+        put32(emu, 0xD0110C, 0xD01200);
+    }
+
+    #[test]
+    fn test_serial_accessory_peer() {
+        use crate::serial::ScriptedPeer;
+        use std::sync::{Arc, Mutex};
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        assert!(!emu.serial_write(b"x"));
+        emu.attach_serial_accessory(SerialAccessoryConfig::default());
+        assert_eq!(emu.bus.port_read(0x3030) & 1, 1); // connected
+        let peer = Arc::new(Mutex::new(ScriptedPeer::new().on("PING", b"PONG\n")));
+        emu.set_serial_peer(Some(Box::new(peer.clone())));
+
+        // Port reset, then start the async schedule
+        usb_write32(&mut emu, 0x30, 1 << 8);
+        usb_write32(&mut emu, 0x30, 0);
+        usb_write32(&mut emu, 0x28, 0xD01000);
+        usb_write32(&mut emu, 0x10, 0x21);
+
+        // SET_CONFIGURATION(1) at the default address
+        queue(&mut emu, 0, 2, &[0x00, 0x09, 1, 0, 0, 0, 0, 0], 8);
+        emu.run_cycles(10_000);
+        queue(&mut emu, 0, 1, &[], 0);
+        emu.run_cycles(10_000);
+        assert!(emu.serial_accessory_ready());
+
+        // Calculator sends a line; the peer's reply comes back on bulk IN
+        queue(&mut emu, 2, 0, b"PING\n", 5);
+        emu.run_cycles(10_000);
+        queue(&mut emu, 1, 1, &[], 64);
+        emu.run_cycles(10_000);
+        assert_eq!(emu.peek_byte(0xD01108) & 0x80, 0); // retired
+        assert_eq!(&emu.bus.ram.data()[0x1200..0x1205], b"PONG\n");
+        assert_eq!(peer.lock().unwrap().lines(), ["PING"]);
+
+        // Without a peer, the host reads calculator output directly
+        assert!(emu.set_serial_peer(None).is_some());
+        queue(&mut emu, 2, 0, b"HI", 2);
+        emu.run_cycles(10_000);
+        assert_eq!(emu.serial_read(1), b"H");
+        assert_eq!(emu.serial_read(usize::MAX), b"I");
+
+        // A reset ends the session: a half-sent line is not carried over
+        emu.set_serial_peer(Some(Box::new(peer.clone())));
+        queue(&mut emu, 2, 0, b"HAL", 3);
+        emu.run_cycles(10_000);
+        emu.reset_with(ResetKind::Warm);
+        peer.lock().unwrap().receive(b"T\n");
+        assert_eq!(peer.lock().unwrap().lines(), ["PING", "T"]);
+    }
+
+    #[test]
+    fn test_link_cable_connects_serial_links() {
+        let mut emus = [Emu::new(), Emu::new()];
+        for emu in &mut emus {
+            emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+            emu.powered_on = true;
+        }
+        let [a, b] = &mut emus;
+        let mut cable = link::connect(a, b);
+        cable.set_slice_cycles(5_000);
+
+        // Each side resets its port and starts the schedule; only A configures
+        for emu in [&mut *a, &mut *b] {
+            usb_write32(emu, 0x30, 1 << 8);
+            usb_write32(emu, 0x30, 0);
+            usb_write32(emu, 0x28, 0xD01000);
+            usb_write32(emu, 0x10, 0x21);
+        }
+        queue(a, 0, 2, &[0x00, 0x09, 1, 0, 0, 0, 0, 0], 8);
+        cable.run_cycles(a, b, 10_000);
+        queue(a, 0, 1, &[], 0);
+        cable.run_cycles(a, b, 10_000);
+        assert!(a.serial_accessory_ready() && !b.serial_accessory_ready());
+
+        // B has not configured its end yet, so A's bytes wait in the cable
+        queue(a, 2, 0, b"HELLO", 5);
+        cable.run_cycles(a, b, 10_000);
+        assert_eq!(cable.pending(), (5, 0));
+        queue(b, 0, 2, &[0x00, 0x09, 1, 0, 0, 0, 0, 0], 8);
+        cable.run_cycles(a, b, 10_000);
+        queue(b, 0, 1, &[], 0);
+        cable.run_cycles(a, b, 10_000);
+        assert_eq!(cable.pending(), (0, 0));
+        queue(b, 1, 1, &[], 64);
+        cable.run_cycles(a, b, 10_000);
+        assert_eq!(&b.bus.ram.data()[0x1200..0x1205], b"HELLO");
+        assert_eq!(a.total_cycles(), b.total_cycles());
+
+        cable.disconnect(a, b);
+        assert!(!a.serial_write(b"x") && !b.serial_write(b"x"));
+    }
+
+    #[test]
+    fn test_reset_variants() {
+        let mut emu = Emu::new();
+        let rom = vec![0x00, 0x76]; // NOP, HALT
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        emu.run_cycles(100);
+
+        // Warm reset keeps RAM and power state
+        emu.bus.ram.write(0x100, 0xAB);
+        emu.reset_with(ResetKind::Warm);
+        assert_eq!(emu.cpu.pc, 0);
+        assert_eq!(emu.bus.ram.read(0x100), 0xAB);
+        assert!(emu.powered_on);
+
+        // RAM clear wipes RAM but reboots without needing ON
+        emu.reset_with(ResetKind::RamClear);
+        assert_eq!(emu.bus.ram.read(0x100), 0x00);
+        assert!(emu.powered_on);
+
+        // Power cycle wipes RAM and requires ON
+        emu.bus.ram.write(0x100, 0xCD);
+        emu.reset_with(ResetKind::PowerCycle);
+        assert_eq!(emu.bus.ram.read(0x100), 0x00);
+        assert!(!emu.powered_on);
+
+        // ROM is preserved by all reset kinds
+        assert_eq!(emu.bus.flash.peek(1), 0x76);
+    }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn test_per_instance_trace_and_log() {
+        use std::sync::atomic::AtomicU32;
+        static A_LOGS: AtomicU32 = AtomicU32::new(0);
+        static B_LOGS: AtomicU32 = AtomicU32::new(0);
+        extern "C" fn log_a(_: *const c_char) {
+            A_LOGS.fetch_add(1, Ordering::SeqCst);
+        }
+        extern "C" fn log_b(_: *const c_char) {
+            B_LOGS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let mut a = Emu::new();
+        let mut b = Emu::new();
+        a.set_log_callback(Some(log_a));
+        b.set_log_callback(Some(log_b));
+
+        a.enable_inst_trace(10);
+        assert!(a.is_inst_trace_enabled());
+        assert!(!b.is_inst_trace_enabled());
+        assert_eq!(A_LOGS.load(Ordering::SeqCst), 1);
+        assert_eq!(B_LOGS.load(Ordering::SeqCst), 0);
+
+        let handle = std::thread::spawn(move || {
+            b.disable_inst_trace();
+            b
+        });
+        let b = handle.join().unwrap();
+        assert!(!b.is_inst_trace_enabled());
+        assert_eq!(A_LOGS.load(Ordering::SeqCst), 1);
+        assert_eq!(B_LOGS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_log_levels_per_instance() {
+        use std::sync::atomic::AtomicU32;
+        static LOGS: AtomicU32 = AtomicU32::new(0);
+        extern "C" fn count_log(_: *const c_char) {
+            LOGS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let mut emu = Emu::new();
+        emu.set_log_callback(Some(count_log));
+        emu.set_log_level(LogSubsystem::Cpu, LogLevel::Off);
+        assert_eq!(emu.log_level(LogSubsystem::Cpu), LogLevel::Off);
+        emu.enable_inst_trace(1);
+        assert_eq!(LOGS.load(Ordering::SeqCst), 0);
+
+        emu.set_all_log_levels(LogLevel::Info);
+        emu.disable_inst_trace();
+        assert_eq!(LOGS.load(Ordering::SeqCst), 1);
+
+        // Levels only apply inside the instance's own calls
+        assert!(!log_enabled(LogSubsystem::Keypad, LogLevel::Debug));
+        emu.set_log_level(LogSubsystem::Keypad, LogLevel::Debug);
+        assert!(!log_enabled(LogSubsystem::Keypad, LogLevel::Debug));
+        let _log = emu.log_scope();
+        assert!(log_enabled(LogSubsystem::Keypad, LogLevel::Debug));
+        drop(_log);
+
+        assert_eq!(emu.set_log_filter("error,sha256=trace"), Ok(()));
+        assert_eq!(emu.log_level(LogSubsystem::Keypad), LogLevel::Error);
+        assert_eq!(emu.log_level(LogSubsystem::Sha256), LogLevel::Trace);
+        assert_eq!(emu.set_log_filter("spi=debug"), Err("spi=debug".to_string()));
+    }
+
+    #[test]
+    fn test_os_patches_applied_on_load() {
+        use crate::os_patch::PatchError;
+
+        let rom = [0xFB, 0x76, 0x18, 0xFD];
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        assert!(emu.os_patch_result().is_none());
+
+        let patches = crate::os_patch::parse_patch_list("1: 76 -> 00
+2: 18 FD -> 18 FE").unwrap();
+        emu.set_os_patches(patches);
+        emu.load_rom(&rom).unwrap();
+        assert_eq!(emu.os_patch_result(), Some(&Ok(3)));
+        assert_eq!(emu.peek_byte(1), 0x00);
+        assert_eq!(emu.peek_byte(3), 0xFE);
+        // Load-time patches are not flash writes to persist
+        assert_eq!(emu.bus.flash.dirty_sectors(), 0);
+
+        // A mismatch leaves the image untouched
+        emu.set_os_patches(vec![
+            BootPatch { addr: 1, original: vec![0x76], replacement: vec![0x00] },
+            BootPatch { addr: 2, original: vec![0x00], replacement: vec![0x01] },
+        ]);
+        emu.load_rom(&rom).unwrap();
+        assert_eq!(emu.os_patch_result(), Some(&Err(PatchError::Mismatch { addr: 2 })));
+        assert_eq!(emu.peek_byte(1), 0x76);
+    }
+
+    #[test]
+    fn test_deterministic_mode() {
+        fn run(seed: u64) -> (Vec<u8>, u8, u64) {
+            let mut emu = Emu::new();
+            emu.set_deterministic(Some(seed));
+            // LD A,(0xC00000) (unmapped read); HALT
+            let rom = vec![0x3A, 0x00, 0x00, 0xC0, 0x76];
+            emu.load_rom(&rom).unwrap();
+            emu.cpu.adl = true;
+            emu.powered_on = true;
+            emu.run_cycles(10_000);
+            (emu.bus.ram.data().to_vec(), emu.cpu.a, emu.total_cycles())
+        }
+
+        let a = run(42);
+        let b = run(42);
+        assert_eq!(a, b);
+        assert_eq!(a.0.len(), crate::memory::addr::RAM_SIZE);
+        let c = run(43);
+        assert_ne!(a.0, c.0);
+
+        let mut emu = Emu::new();
+        assert_eq!(emu.deterministic_seed(), None);
+        emu.set_deterministic(Some(7));
+        assert_eq!(emu.deterministic_seed(), Some(7));
+    }
+
+    #[test]
+    fn test_deterministic_mode_pins_rtc() {
+        fn run() -> Vec<u8> {
+            let mut emu = Emu::new();
+            emu.set_rtc_host_sync(Some(0));
+            emu.set_deterministic(Some(42));
+            assert!(!emu.set_rtc_host_sync(Some(3600)));
+            emu.load_rom(&[0x18, 0xFE]).unwrap();
+            emu.powered_on = true;
+            emu.run_cycles(1_000_000);
+            (0..0x50).map(|addr| emu.bus.ports.rtc.read(addr, 0, 0)).collect()
+        }
+
+        let a = run();
+        assert_eq!(a, run());
+        // Day 0 00:00:00
+        assert_eq!([a[0x00], a[0x04], a[0x08], a[0x0C], a[0x0D]], [0; 5]);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut emu = Emu::new();
+        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
+        let rom = vec![0x00, 0x76]; // NOP, HALT
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true; // Power on for test
+        emu.run_cycles(100);
+        emu.set_key(1, 1, true);
+        emu.reset();
+
+        assert_eq!(emu.cpu.pc, 0);
+        assert!(!emu.bus.key_state()[1][1]);
+        // After reset, total_cycles includes init_prefetch cost for CEmu parity.
+        // The prefetch fetches the first byte at PC=0, which adds flash timing cycles.
+        // With serial flash enabled by default, this is typically 10 cycles (cache miss).
+        // We just verify it's small and consistent, rather than exactly 0.
+        assert!(emu.total_cycles <= 20, "total_cycles after reset should be small prefetch cost");
+        assert!(!emu.powered_on); // Reset should power off the calculator
+    }
+
+    #[test]
+    fn test_history() {
+        let mut emu = Emu::new();
+        // Minimal ROM - flash defaults to 0xFF so we only need the bytes we use
+        let rom = vec![0x00, 0x00, 0x00, 0x76]; // NOP, NOP, NOP, HALT
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true; // Power on for test
+        emu.run_cycles(100);
+
+        let history = emu.dump_history();
+        assert!(history.contains("NOP"));
+        assert!(history.contains("HALT"));
+    }
+
+    #[test]
+    fn test_on_key_wakes_from_halt_with_di() {
+        let mut emu = Emu::new();
         // ROM: DI (F3), HALT (76), NOP (00), NOP (00)
         // After DI + HALT, interrupts are disabled but ON key should still wake
         let rom = vec![0xF3, 0x76, 0x00, 0x00];
@@ -3099,6 +8094,279 @@ mod tests {
         assert_eq!(emu.send_file(&file), Err(-13));
     }
 
+    #[test]
+    fn test_send_variable_before_and_after_boot() {
+        let mut emu = Emu::new();
+        let file = make_test_8xp(0x15, b"APPVAR\0\0", 0, 0x80, &[0x01, 0x00, 0xBB]);
+        assert_eq!(emu.send_variable(&file), Err(-10));
+
+        emu.load_rom(&[0x18, 0xFE]).unwrap();
+        assert_eq!(emu.send_variable(&file), Ok(1));
+        let first = emu.find_archive_entry_by_name(b"APPVAR\0\0", 6, 0x15).unwrap();
+
+        // Once running, the archived copy is replaced and the OS restarted
+        emu.power_on();
+        emu.run_cycles(1000);
+        assert_eq!(emu.send_variable(&file), Ok(1));
+        assert_eq!(emu.bus.flash.peek(first), 0xF0);
+        let second = emu.find_archive_entry_by_name(b"APPVAR\0\0", 6, 0x15).unwrap();
+        assert!(second > first);
+        assert!(emu.powered_on);
+        assert_eq!(emu.pc(), 0);
+
+        assert_eq!(emu.send_variable(&file[..40]), Err(-11));
+    }
+
+    #[test]
+    fn test_receive_variable_round_trip() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap();
+        assert_eq!(emu.receive_variable(b"HELLO"), Err(-1));
+
+        // Archived appvar, sent the usual way
+        let appvar = make_test_8xp(0x15, b"GRAPHX\0\0", 0, 0x80, &[0x03, 0x00, 0x11, 0x22, 0x33]);
+        emu.send_file(&appvar).unwrap();
+        let flag_addr = emu.find_archive_entry_by_name(b"GRAPHX\0\0", 6, 0x15).unwrap();
+
+        // Program in RAM
+        let program = make_test_8xp(0x05, b"HELLO\0\0\0", 0, 0, &[0x02, 0x00, 0xEF, 0x7B]);
+        for (i, &b) in [0x02, 0x00, 0xEF, 0x7B].iter().enumerate() {
+            emu.poke_byte(0xD10000 + i as u32, b);
+        }
+
+        // VAT with no symbol table entries, then the two programs
+        let mut top = sandbox::SYM_TABLE_END;
+        for (kind, addr, name) in [(0x05u8, 0xD10000u32, &b"HELLO"[..]), (0x15, flag_addr, &b"GRAPHX"[..])] {
+            let mut bytes = vec![kind, 0, 0, addr as u8, (addr >> 8) as u8, (addr >> 16) as u8, name.len() as u8];
+            bytes.extend_from_slice(name);
+            for (i, &b) in bytes.iter().enumerate() {
+                emu.poke_byte(top - i as u32, b);
+            }
+            top -= bytes.len() as u32;
+        }
+        for i in 0..3 {
+            emu.poke_byte(eval::PROG_PTR_ADDR + i, (sandbox::SYM_TABLE_END >> (8 * i)) as u8);
+            emu.poke_byte(sandbox::PTEMP_ADDR + i, (top >> (8 * i)) as u8);
+        }
+
+        // Same entries and checksum as the files they came from
+        assert_eq!(emu.receive_variable(b"HELLO").unwrap()[55..], program[55..]);
+        assert_eq!(emu.receive_variable(b"GRAPHX").unwrap()[55..], appvar[55..]);
+        assert_eq!(emu.receive_variable(b"MISSING"), Err(-1));
+    }
+
+    #[test]
+    fn test_tap_key_releases_after_duration() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let ms = (emu.cpu_clock_hz() / 1000.0) as u32;
+
+        emu.tap_key(Key::Enter, 2);
+        emu.tap_key(Key::Up, 1);
+        assert!(emu.bus.key_state()[6][0] && emu.bus.key_state()[7][3]);
+        // One run spanning the first release stops nowhere short
+        assert_eq!(emu.run_cycles(ms + ms / 2), ms + ms / 2);
+        assert!(emu.bus.key_state()[6][0] && !emu.bus.key_state()[7][3]);
+        emu.run_cycles(ms);
+        assert!(!emu.bus.key_state()[6][0]);
+        assert!(emu.key_releases.is_empty());
+
+        // An explicit release cancels the scheduled one
+        emu.tap_key(Key::Clear, 1);
+        emu.release_key(Key::Clear);
+        emu.press_key(Key::Clear);
+        emu.run_cycles(2 * ms);
+        assert!(emu.bus.key_state()[6][6]);
+    }
+
+    #[test]
+    fn test_pause_resume_and_reset() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.pause();
+        let cycles = emu.total_cycles();
+        assert_eq!(emu.run_cycles(10_000), 0);
+        assert_eq!(emu.run_frame(), 0);
+        assert_eq!(emu.total_cycles(), cycles);
+        assert!(emu.step().is_some());
+
+        // A warm reset leaves no stale peripheral or input state behind
+        emu.bus.port_write(0xB024, 0x40);
+        assert_eq!(emu.get_backlight(), 0x40);
+        emu.tap_key(Key::Enter, 100);
+        emu.reset_with(ResetKind::Warm);
+        assert!(emu.is_paused());
+        assert_eq!(emu.get_backlight(), 0xFF);
+        assert!(!emu.bus.key_state()[6][0]);
+        assert!(emu.key_releases.is_empty());
+
+        emu.resume();
+        assert!(emu.run_cycles(10_000) >= 10_000);
+    }
+
+    #[test]
+    fn test_set_battery() {
+        let mut emu = Emu::new();
+        assert_eq!(emu.bus.ports.control.battery(), (5, false));
+        emu.set_battery(30, true);
+        assert_eq!(emu.bus.ports.control.battery(), (2, true));
+        emu.set_battery(0, false);
+        assert_eq!(emu.bus.ports.control.battery(), (0, false));
+        emu.set_battery(200, false);
+        assert_eq!(emu.bus.ports.control.battery(), (5, false));
+        emu.set_battery(81, true);
+        emu.reset_with(ResetKind::PowerCycle);
+        assert_eq!(emu.bus.ports.control.battery(), (5, true));
+    }
+
+    #[test]
+    fn test_block_instruction_yields_to_events() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0xED, 0xB0, 0x18, 0xFE]).unwrap(); // LDIR; JR $
+        emu.powered_on = true;
+        emu.cpu.adl = true;
+        emu.cpu.hl = 0xD00000;
+        emu.cpu.de = 0xD20000;
+        emu.cpu.bc = 0x010000;
+        emu.bus.poke_byte(0xD0FFFF, 0x5A);
+
+        // A 64K copy does not run past the next scheduler event
+        let retired = emu.cpu.instructions_retired;
+        emu.step();
+        assert_eq!(emu.cpu.pc, 0);
+        assert!(emu.cpu.in_block());
+        assert!(emu.cpu.bc > 0 && emu.cpu.bc < 0x010000);
+
+        // Continuing it is not a new instruction: the breakpoint on it
+        // doesn't stop the run and it retires once
+        emu.set_breakpoint(0);
+        emu.run_cycles(1_000_000);
+        assert!(!emu.breakpoint_was_hit());
+        assert_eq!(emu.cpu.bc, 0);
+        assert!(emu.cpu.pc >= 2);
+        assert_eq!(emu.peek_byte(0xD2FFFF), 0x5A);
+        // The LDIR once, then the JR loop
+        let jrs = emu.cpu.instructions_retired - retired - 1;
+        assert!(jrs > 0 && jrs < 1_000_000 / 4);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn test_breakpoint_on_block_instruction_fires_once() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0xED, 0xB0, 0x18, 0xFE]).unwrap(); // LDIR; JR $
+        emu.powered_on = true;
+        emu.cpu.adl = true;
+        emu.cpu.hl = 0xD00000;
+        emu.cpu.de = 0xD20000;
+        emu.cpu.bc = 0x010000;
+
+        let bp = emu.add_breakpoint(0);
+        let event = emu.run_until_break(1_000_000).unwrap();
+        assert_eq!((event.kind, event.id, event.pc), (DebugEventKind::Breakpoint, bp, 0));
+        assert_eq!(emu.cpu.bc, 0x010000);
+
+        // The copy yields at many events but stops only the once
+        assert_eq!(emu.run_until_break(1_000_000), None);
+        assert_eq!(emu.cpu.bc, 0);
+
+        // Stepping into a block instruction runs all of it
+        emu.cpu.pc = 0;
+        emu.cpu.init_prefetch(&mut emu.bus);
+        emu.cpu.bc = 0x010000;
+        let retired = emu.cpu.instructions_retired;
+        let event = emu.step_into().unwrap();
+        assert_eq!((event.kind, event.pc), (DebugEventKind::Step, 2));
+        assert_eq!(emu.cpu.bc, 0);
+        assert_eq!(emu.cpu.instructions_retired, retired + 1);
+    }
+
+    #[test]
+    fn test_protection_violation_reset_cause() {
+        let mut rom = vec![0xFF; 0x108];
+        rom[0..4].copy_from_slice(&[0xC3, 0x00, 0x01, 0x00]); // JP 0x000100
+        rom[0x66..0x6A].copy_from_slice(&[0xC3, 0x00, 0x00, 0x00]); // NMI: JP 0
+        // LD A,0x55; LD (0xD00050),A; JR $
+        rom[0x100..0x108].copy_from_slice(&[0x3E, 0x55, 0x32, 0x50, 0x00, 0xD0, 0x18, 0xFE]);
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        assert_eq!(emu.last_reset_cause(), ResetCause::PowerOn);
+        emu.cpu.adl = true;
+        emu.cpu.spl = 0xD08000;
+
+        // Code above 0x80 is unprivileged and may not write 0xD00000-0xD000FF
+        for (port, value) in [(0x1D, 0x80), (0x1E, 0), (0x1F, 0), (0x20, 0), (0x21, 0), (0x22, 0xD0), (0x23, 0xFF), (0x24, 0), (0x25, 0xD0)] {
+            emu.bus.port_write(port, value);
+        }
+        let resets = emu.reset_count;
+        for _ in 0..10 {
+            emu.step();
+            if emu.last_reset_cause() != ResetCause::PowerOn {
+                break;
+            }
+        }
+        assert_eq!(
+            emu.last_reset_cause(),
+            ResetCause::Protection { status: 2, addr: 0xD00050, pc: 0x103 }
+        );
+        assert_eq!(emu.reset_count, resets + 1);
+        assert_eq!(emu.peek_byte(0xD00050), 0); // write blocked
+
+        emu.reset_with(ResetKind::Warm);
+        assert_eq!(emu.last_reset_cause(), ResetCause::Host(ResetKind::Warm));
+    }
+
+    #[test]
+    fn test_type_text_taps_modifiers_and_keys() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        let start = emu.total_cycles();
+        assert_eq!(emu.type_text("2=2"), Err('='));
+        assert_eq!(emu.total_cycles(), start);
+
+        emu.start_input_recording();
+        assert_eq!(emu.type_text("A1\n"), Ok(()));
+        let events = replay::parse(&emu.stop_input_recording().unwrap()).unwrap();
+        let keys: Vec<_> = events.iter().map(|e| (Key::at(e.row, e.col).unwrap(), e.down)).collect();
+        assert_eq!(keys, [
+            (Key::Alpha, true), (Key::Alpha, false), (Key::Math, true), (Key::Math, false),
+            (Key::Num1, true), (Key::Num1, false), (Key::Enter, true), (Key::Enter, false),
+        ]);
+        let hold = (TYPE_KEY_HOLD_MS as f64 * emu.cpu_clock_hz() / 1000.0) as i64;
+        assert!(events.windows(2).all(|w| w[1].cycles - w[0].cycles == hold));
+    }
+
+    #[test]
+    fn test_link_transfer_between_emulators() {
+        let mut source = Emu::new();
+        source.load_rom(&[0x18, 0xFE]).unwrap();
+        for (i, &b) in [0x02, 0x00, 0xEF, 0x7B].iter().enumerate() {
+            source.poke_byte(0xD10000 + i as u32, b);
+        }
+        let entry = [0x05, 0, 0, 0x00, 0x00, 0xD1, 5, b'H', b'E', b'L', b'L', b'O'];
+        for (i, &b) in entry.iter().enumerate() {
+            source.poke_byte(sandbox::SYM_TABLE_END - i as u32, b);
+        }
+        let top = sandbox::SYM_TABLE_END - entry.len() as u32;
+        for i in 0..3 {
+            source.poke_byte(eval::PROG_PTR_ADDR + i, (sandbox::SYM_TABLE_END >> (8 * i)) as u8);
+            source.poke_byte(sandbox::PTEMP_ADDR + i, (top >> (8 * i)) as u8);
+        }
+
+        let mut dest = Emu::new();
+        dest.load_rom(&[0x18, 0xFE]).unwrap();
+        assert_eq!(link::transfer(&mut source, &mut dest, b"MISSING"), Err(link::LinkError::NotFound));
+        assert_eq!(link::transfer(&mut source, &mut dest, b"HELLO"), Ok(()));
+        let addr = dest.find_archive_entry_by_name(b"HELLO\0\0\0", 5, 0x05).unwrap();
+        let data: Vec<u8> = (0..4).map(|i| dest.bus.flash.peek(addr + 15 + i)).collect();
+        assert_eq!(data, [0x02, 0x00, 0xEF, 0x7B]);
+        assert!(source.link_recv(usize::MAX).is_empty() && dest.link_recv(usize::MAX).is_empty());
+    }
+
     #[test]
     fn test_send_real_doom_8xp() {
         let path = "/tmp/DOOM.8xp";
@@ -3121,4 +8389,65 @@ mod tests {
             assert_eq!(emu.bus.flash.peek(0x0C000A), b'M');
         }
     }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn test_port_log() {
+        use crate::bus::IoOpType;
+
+        let mut emu = Emu::new();
+        // LD A,0x03; OUT0 (0x01),A; IN0 A,(0x01)
+        emu.load_rom(&[0x3E, 0x03, 0xED, 0x39, 0x01, 0xED, 0x38, 0x01]).unwrap();
+        emu.powered_on = true;
+        emu.enable_port_log(PortLog::ALL_RANGES);
+        for _ in 0..3 {
+            emu.step();
+        }
+        let log: Vec<_> = emu.port_log().entries().map(|a| (a.op_type, a.addr, a.value, a.pc)).collect();
+        assert_eq!(log, [
+            (IoOpType::Write, 0xFF0001, 0x03, 2),
+            (IoOpType::Read, 0xFF0001, 0x03, 5),
+        ]);
+        emu.clear_port_log();
+        assert!(emu.port_log().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn test_structured_trace() {
+        use crate::trace_record::{parse_binary, TraceConfig};
+        use std::sync::{Arc, Mutex};
+
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Shared {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x00, 0x00, 0x18, 0xFC]).unwrap(); // NOP; NOP; JR -4
+        emu.powered_on = true;
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let config = TraceConfig { pc_range: Some((0, 1)), limit: 4, ..Default::default() };
+        emu.set_trace(config, Box::new(Shared(out.clone())));
+        assert!(emu.is_tracing());
+        emu.step();
+        emu.run_cycles(1_000);
+        assert!(!emu.is_tracing());
+        assert_eq!(emu.stop_trace().unwrap().unwrap(), 4);
+        assert!(emu.stop_trace().is_none());
+
+        let records = parse_binary(&out.lock().unwrap()).unwrap();
+        // The JRs at 2 are counted but filtered out
+        let seen: Vec<_> = records.iter().map(|r| (r.index, r.pc, r.opcode().to_vec())).collect();
+        assert_eq!(seen, [(0, 0, vec![0x00]), (1, 1, vec![0x00]), (3, 0, vec![0x00]), (4, 1, vec![0x00])]);
+        for pair in records.windows(2) {
+            assert!(pair[0].cycles > 0 && pair[1].total_cycles > pair[0].total_cycles);
+        }
+    }
 }
@@ -0,0 +1,484 @@
+//! Expression evaluation through TI-OS
+//!
+//! Lets a host use the calculator as a math service: each expression is
+//! typed on the homescreen with OS key codes, evaluated by the real OS on
+//! ENTER, and the result read back from `Ans` (or the error from `errNo`).
+//! The emulator must be booted to the homescreen (see `Emu::evaluate`).
+//!
+//! Expressions are plain text. Digits, `.`, `+ - * / ^ ( ) , [ ]`, the
+//! letters `A`-`Z`, `π`, `²`, `√` and `→` map to their keys; `~` or `⁻` is
+//! the negation key, since `-` is always subtraction as on the calculator.
+//! The words `sin(`, `cos(`, `tan(`, `ln(`, `log(`, `e^(`, `sqrt(`, `pi` and
+//! `Ans` enter the matching token. Whitespace is ignored.
+
+use std::fmt;
+
+/// errNo: OS error number, bit 7 set if the error offers Goto
+pub const ERR_NO_ADDR: u32 = 0xD008DF;
+/// progPtr: bottom of the symbol table, where the program table starts
+pub const PROG_PTR_ADDR: u32 = 0xD0259D;
+/// How long the OS may take to take a key or finish evaluating, in
+/// emulated seconds
+pub const EVAL_TIMEOUT_SECS: f64 = 10.0;
+
+/// Symbol table entry type for each `Ans` value the decoder understands
+const REAL_OBJ: u8 = 0x00;
+const LIST_OBJ: u8 = 0x01;
+const MAT_OBJ: u8 = 0x02;
+const STRNG_OBJ: u8 = 0x04;
+const CPLX_OBJ: u8 = 0x0C;
+const CLIST_OBJ: u8 = 0x0D;
+
+/// First byte of the `Ans` variable name
+const ANS_NAME: u8 = 0x72;
+
+/// Size of a symbol table entry (type, T2, version, 3 address bytes, 3 name bytes)
+const SYM_ENTRY_SIZE: u32 = 9;
+
+/// OS key codes for typing
+pub const KEY_ENTER: u16 = 0x05;
+pub const KEY_CLEAR: u16 = 0x09;
+/// `1`, which picks Quit on the error screen
+pub const KEY_QUIT_ERROR: u16 = 0x8F;
+
+/// Multi-character words and their keys; checked before single characters
+const WORDS: &[(&str, u16)] = &[
+    ("sqrt(", 0xBE),
+    ("sin(", 0xB7),
+    ("cos(", 0xB9),
+    ("tan(", 0xBB),
+    ("log(", 0xC1),
+    ("ln(", 0xBF),
+    ("e^(", 0xC0),
+    ("Ans", 0xC5),
+    ("pi", 0xB5),
+];
+
+/// OS error names by error number, starting at 1
+const ERROR_NAMES: &[&str] = &[
+    "OVERFLOW", "DIVIDE BY 0", "SINGULAR MAT", "DOMAIN", "INCREMENT", "BREAK",
+    "NONREAL ANS", "SYNTAX", "DATA TYPE", "ARGUMENT", "DIM MISMATCH", "INVALID DIM",
+    "UNDEFINED", "MEMORY", "INVALID", "ILLEGAL NEST", "BOUND", "WINDOW RANGE",
+    "ZOOM", "LABEL", "STAT", "SOLVER", "SINGULARITY", "SIGN CHANGE", "ITERATIONS",
+    "BAD GUESS", "STAT PLOT", "TOL NOT MET",
+];
+
+/// A TI floating-point number: sign, power of ten and 14 BCD digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TiFloat {
+    /// True if negative
+    pub negative: bool,
+    /// Exponent of the first digit
+    pub exponent: i32,
+    /// Mantissa digits, most significant first
+    pub digits: [u8; 14],
+}
+
+impl TiFloat {
+    /// Decode the 9-byte OS format (type/sign, biased exponent, 7 BCD bytes)
+    pub fn from_bytes(bytes: &[u8; 9]) -> Self {
+        let mut digits = [0u8; 14];
+        for (i, byte) in bytes[2..].iter().enumerate() {
+            digits[i * 2] = byte >> 4;
+            digits[i * 2 + 1] = byte & 0x0F;
+        }
+        Self { negative: bytes[0] & 0x80 != 0, exponent: bytes[1] as i32 - 0x80, digits }
+    }
+
+    /// Nearest f64
+    pub fn to_f64(&self) -> f64 {
+        // Through the decimal text, so the result is correctly rounded
+        self.to_string().replace('E', "e").parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl fmt::Display for TiFloat {
+    /// Shortest exact text: positional for exponents -3..10, else `1.5E12`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.digits.iter().rposition(|&d| d != 0).map_or(0, |i| i + 1);
+        if len == 0 {
+            return write!(f, "0");
+        }
+        let digits: String = self.digits[..len].iter().map(|d| char::from(b'0' + d)).collect();
+        let sign = if self.negative { "-" } else { "" };
+        let exp = self.exponent;
+        if (0..10).contains(&exp) {
+            let int_len = exp as usize + 1;
+            if len <= int_len {
+                write!(f, "{}{}{}", sign, digits, "0".repeat(int_len - len))
+            } else {
+                write!(f, "{}{}.{}", sign, &digits[..int_len], &digits[int_len..])
+            }
+        } else if (-3..0).contains(&exp) {
+            write!(f, "{}0.{}{}", sign, "0".repeat((-exp - 1) as usize), digits)
+        } else if len == 1 {
+            write!(f, "{}{}E{}", sign, digits, exp)
+        } else {
+            write!(f, "{}{}.{}E{}", sign, &digits[..1], &digits[1..], exp)
+        }
+    }
+}
+
+/// A value read back from `Ans`
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalValue {
+    /// Real number
+    Real(TiFloat),
+    /// Complex number (real, imaginary)
+    Complex(TiFloat, TiFloat),
+    /// Real list
+    List(Vec<TiFloat>),
+    /// Complex list
+    ComplexList(Vec<(TiFloat, TiFloat)>),
+    /// Real matrix, row by row
+    Matrix { rows: usize, cols: usize, values: Vec<TiFloat> },
+    /// String, as OS tokens (displayed as text for the common one-byte ones)
+    String(Vec<u8>),
+    /// A type the decoder does not read, by its symbol table type byte
+    Other(u8),
+}
+
+impl fmt::Display for EvalValue {
+    /// Calculator-style text: `{1,2}`, `[[1,2][3,4]]`, `1+2i`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn complex(f: &mut fmt::Formatter<'_>, re: &TiFloat, im: &TiFloat) -> fmt::Result {
+            let zero = |v: &TiFloat| v.digits == [0; 14];
+            let sign = if im.negative { "-" } else { "+" };
+            let im_abs = TiFloat { negative: false, ..*im };
+            if zero(im) {
+                write!(f, "{}", re)
+            } else if zero(re) {
+                write!(f, "{}{}i", sign.trim_start_matches('+'), im_abs)
+            } else {
+                write!(f, "{}{}{}i", re, sign, im_abs)
+            }
+        }
+        fn list<T>(f: &mut fmt::Formatter<'_>, items: &[T], mut item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result) -> fmt::Result {
+            write!(f, "{{")?;
+            for (i, value) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                item(f, value)?;
+            }
+            write!(f, "}}")
+        }
+        match self {
+            EvalValue::Real(value) => write!(f, "{}", value),
+            EvalValue::Complex(re, im) => complex(f, re, im),
+            EvalValue::List(values) => list(f, values, |f, v| write!(f, "{}", v)),
+            EvalValue::ComplexList(values) => list(f, values, |f, (re, im)| complex(f, re, im)),
+            EvalValue::Matrix { cols, values, .. } => {
+                write!(f, "[")?;
+                for row in values.chunks((*cols).max(1)) {
+                    write!(f, "[")?;
+                    for (i, value) in row.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ",")?;
+                        }
+                        write!(f, "{}", value)?;
+                    }
+                    write!(f, "]")?;
+                }
+                write!(f, "]")
+            }
+            EvalValue::String(tokens) => write!(f, "\"{}\"", tokens.iter().map(|&t| token_char(t)).collect::<String>()),
+            EvalValue::Other(kind) => write!(f, "<type {:02X}>", kind),
+        }
+    }
+}
+
+/// Text of a one-byte string token: digits, capitals, space and common
+/// punctuation; anything else (including two-byte tokens) is `?`
+fn token_char(token: u8) -> char {
+    match token {
+        b'0'..=b'9' | b'A'..=b'Z' => token as char,
+        0x29 => ' ',
+        0x2B => ',',
+        0x3A => '.',
+        0x70 => '+',
+        0x71 => '-',
+        0x82 => '*',
+        0x83 => '/',
+        _ => '?',
+    }
+}
+
+/// Why an expression produced no value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// The OS reported an error (errNo without the Goto bit)
+    Os(u8),
+    /// A character with no key mapping
+    UnsupportedChar(char),
+    /// Nothing to evaluate
+    Empty,
+    /// The OS did not take a key or finish evaluating in time
+    Timeout,
+    /// Evaluation succeeded but `Ans` could not be found
+    NoAnswer,
+}
+
+impl EvalError {
+    /// OS error name, e.g. "SYNTAX", for `Os` errors
+    pub fn os_name(&self) -> Option<&'static str> {
+        match self {
+            EvalError::Os(code) => ERROR_NAMES.get((*code as usize).wrapping_sub(1)).copied(),
+            _ => None,
+        }
+    }
+
+    /// Code for FFI: the OS error number, or -1 unsupported character,
+    /// -2 timeout, -3 no answer, -4 empty expression
+    pub fn code(&self) -> i32 {
+        match self {
+            EvalError::Os(code) => *code as i32,
+            EvalError::UnsupportedChar(_) => -1,
+            EvalError::Timeout => -2,
+            EvalError::NoAnswer => -3,
+            EvalError::Empty => -4,
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Os(code) => match self.os_name() {
+                Some(name) => write!(f, "ERR:{}", name),
+                None => write!(f, "ERR:{}", code),
+            },
+            EvalError::UnsupportedChar(c) => write!(f, "no key for '{}'", c),
+            EvalError::Timeout => write!(f, "timed out"),
+            EvalError::NoAnswer => write!(f, "no Ans"),
+            EvalError::Empty => write!(f, "empty expression"),
+        }
+    }
+}
+
+/// Result of evaluating one expression
+pub type EvalResult = Result<EvalValue, EvalError>;
+
+/// Key codes that type `expr`
+pub fn expression_keys(expr: &str) -> Result<Vec<u16>, EvalError> {
+    let mut keys = Vec::new();
+    let mut rest = expr;
+    while let Some(c) = rest.chars().next() {
+        if let Some(&(word, key)) = WORDS.iter().find(|(word, _)| rest.starts_with(word)) {
+            keys.push(key);
+            rest = &rest[word.len()..];
+            continue;
+        }
+        let key = match c {
+            c if c.is_whitespace() => None,
+            '0'..='9' => Some(0x8E + (c as u16 - '0' as u16)),
+            'A'..='Z' => Some(0x9A + (c as u16 - 'A' as u16)),
+            '+' => Some(0x80),
+            '-' => Some(0x81),
+            '*' => Some(0x82),
+            '/' => Some(0x83),
+            '^' => Some(0x84),
+            '(' => Some(0x85),
+            ')' => Some(0x86),
+            '[' => Some(0x87),
+            ']' => Some(0x88),
+            '→' => Some(0x8A),
+            ',' => Some(0x8B),
+            '~' | '⁻' => Some(0x8C),
+            '.' => Some(0x8D),
+            'π' => Some(0xB5),
+            '²' => Some(0xBD),
+            '√' => Some(0xBE),
+            _ => return Err(EvalError::UnsupportedChar(c)),
+        };
+        keys.extend(key);
+        rest = &rest[c.len_utf8()..];
+    }
+    if keys.is_empty() {
+        return Err(EvalError::Empty);
+    }
+    Ok(keys)
+}
+
+/// Find `Ans` in the symbol table and decode it. `peek` reads memory;
+/// `sym_table_end` and `prog_ptr` bound the table (it grows downward).
+pub fn read_ans(mut peek: impl FnMut(u32) -> u8, sym_table_end: u32, prog_ptr: u32) -> Option<EvalValue> {
+    let mut entry = sym_table_end;
+    while entry > prog_ptr && entry - prog_ptr >= SYM_ENTRY_SIZE {
+        if peek(entry - 6) == ANS_NAME {
+            let kind = peek(entry) & 0x1F;
+            let data = peek(entry - 3) as u32 | (peek(entry - 4) as u32) << 8 | (peek(entry - 5) as u32) << 16;
+            return Some(decode_value(&mut peek, kind, data));
+        }
+        entry -= SYM_ENTRY_SIZE;
+    }
+    None
+}
+
+fn read_float(peek: &mut impl FnMut(u32) -> u8, addr: u32) -> TiFloat {
+    let mut bytes = [0u8; 9];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = peek(addr + i as u32);
+    }
+    TiFloat::from_bytes(&bytes)
+}
+
+fn decode_value(peek: &mut impl FnMut(u32) -> u8, kind: u8, data: u32) -> EvalValue {
+    let (lo, hi) = (peek(data), peek(data + 1));
+    let len = u16::from_le_bytes([lo, hi]) as u32;
+    match kind {
+        REAL_OBJ => EvalValue::Real(read_float(peek, data)),
+        CPLX_OBJ => EvalValue::Complex(read_float(peek, data), read_float(peek, data + 9)),
+        LIST_OBJ => EvalValue::List((0..len).map(|i| read_float(peek, data + 2 + i * 9)).collect()),
+        CLIST_OBJ => EvalValue::ComplexList(
+            (0..len).map(|i| (read_float(peek, data + 2 + i * 18), read_float(peek, data + 11 + i * 18))).collect(),
+        ),
+        STRNG_OBJ => EvalValue::String((0..len).map(|i| peek(data + 2 + i)).collect()),
+        MAT_OBJ => {
+            // Columns, then rows, each one byte
+            let (cols, rows) = (lo as usize, hi as usize);
+            let values = (0..(rows * cols) as u32).map(|i| read_float(peek, data + 2 + i * 9)).collect();
+            EvalValue::Matrix { rows, cols, values }
+        }
+        _ => EvalValue::Other(kind),
+    }
+}
+
+/// One evaluation result for FFI
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EvalRecord {
+    /// 0 real, 1 complex, 2 list, 3 matrix, 4 string, 5 other, -1 error
+    pub kind: i32,
+    /// For errors, `EvalError::code()`
+    pub error: i32,
+    /// Real and imaginary part of a real or complex value
+    pub re: f64,
+    pub im: f64,
+    /// Matrix rows, or list/string length
+    pub rows: u32,
+    /// Matrix columns (1 for lists)
+    pub cols: u32,
+    /// Value as text (see `EvalValue`'s Display) or the error, NUL-terminated
+    /// and truncated to fit
+    pub text: [u8; 96],
+}
+
+impl EvalRecord {
+    /// Flatten a result for FFI
+    pub fn from_result(result: &EvalResult) -> Self {
+        let mut record = Self { kind: -1, error: 0, re: 0.0, im: 0.0, rows: 0, cols: 0, text: [0; 96] };
+        let text = match result {
+            Ok(value) => value.to_string(),
+            Err(e) => e.to_string(),
+        };
+        // Truncate on a character boundary, keeping the NUL
+        let mut len = text.len().min(record.text.len() - 1);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        record.text[..len].copy_from_slice(&text.as_bytes()[..len]);
+
+        match result {
+            Err(e) => record.error = e.code(),
+            Ok(EvalValue::Real(v)) => {
+                record.kind = 0;
+                record.re = v.to_f64();
+            }
+            Ok(EvalValue::Complex(re, im)) => {
+                record.kind = 1;
+                record.re = re.to_f64();
+                record.im = im.to_f64();
+            }
+            Ok(EvalValue::List(values)) => {
+                record.kind = 2;
+                (record.rows, record.cols) = (values.len() as u32, 1);
+            }
+            Ok(EvalValue::ComplexList(values)) => {
+                record.kind = 2;
+                (record.rows, record.cols) = (values.len() as u32, 1);
+            }
+            Ok(EvalValue::Matrix { rows, cols, .. }) => {
+                record.kind = 3;
+                (record.rows, record.cols) = (*rows as u32, *cols as u32);
+            }
+            Ok(EvalValue::String(tokens)) => {
+                record.kind = 4;
+                record.rows = tokens.len() as u32;
+            }
+            Ok(EvalValue::Other(_)) => record.kind = 5,
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ti(negative: bool, exponent: i32, digits: &[u8]) -> TiFloat {
+        let mut d = [0u8; 14];
+        d[..digits.len()].copy_from_slice(digits);
+        TiFloat { negative, exponent, digits: d }
+    }
+
+    #[test]
+    fn test_float_decode_and_text() {
+        // 42 = 4.2E1
+        let bytes = [0x00, 0x81, 0x42, 0, 0, 0, 0, 0, 0];
+        let value = TiFloat::from_bytes(&bytes);
+        assert_eq!(value.to_string(), "42");
+        assert_eq!(value.to_f64(), 42.0);
+
+        assert_eq!(ti(true, -1, &[1, 2, 5]).to_string(), "-0.125");
+        assert_eq!(ti(false, 0, &[3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3]).to_string(), "3.3333333333333");
+        assert_eq!(ti(false, 12, &[1, 5]).to_string(), "1.5E12");
+        assert_eq!(ti(false, -5, &[2]).to_string(), "2E-5");
+        assert_eq!(ti(false, 0, &[]).to_string(), "0");
+        assert_eq!(ti(false, -1, &[3]).to_f64(), 0.3);
+    }
+
+    #[test]
+    fn test_expression_keys() {
+        assert_eq!(expression_keys("12+sin(pi)").unwrap(), [0x8F, 0x90, 0x80, 0xB7, 0xB5, 0x86]);
+        assert_eq!(expression_keys("~3 * Ans").unwrap(), [0x8C, 0x91, 0x82, 0xC5]);
+        assert_eq!(expression_keys("2x"), Err(EvalError::UnsupportedChar('x')));
+        assert_eq!(expression_keys(" "), Err(EvalError::Empty));
+    }
+
+    #[test]
+    fn test_read_ans_from_symbol_table() {
+        let mut mem = vec![0u8; 0x100];
+        let base = 0xD3FF00u32;
+        let end = 0xD3FFFFu32;
+        // Another variable first, then Ans as a two-element list at 0xD3FF10
+        mem[0xFF - 6] = 0x41;
+        let ans = end - SYM_ENTRY_SIZE;
+        let at = |addr: u32| (addr - base) as usize;
+        mem[at(ans)] = LIST_OBJ;
+        mem[at(ans) - 3] = 0x10;
+        mem[at(ans) - 4] = 0xFF;
+        mem[at(ans) - 5] = 0xD3;
+        mem[at(ans) - 6] = ANS_NAME;
+        mem[0x10..0x12].copy_from_slice(&[2, 0]);
+        mem[0x12..0x1B].copy_from_slice(&[0x00, 0x80, 0x10, 0, 0, 0, 0, 0, 0]);
+        mem[0x1B..0x24].copy_from_slice(&[0x80, 0x81, 0x25, 0, 0, 0, 0, 0, 0]);
+
+        let value = read_ans(|addr| mem[(addr - base) as usize], end, base).unwrap();
+        assert_eq!(value.to_string(), "{1,-25}");
+        let record = EvalRecord::from_result(&Ok(value));
+        assert_eq!((record.kind, record.rows, record.cols), (2, 2, 1));
+        assert_eq!(&record.text[..8], b"{1,-25}\0");
+
+        assert_eq!(read_ans(|_| 0, end, end - 4), None);
+    }
+
+    #[test]
+    fn test_error_text() {
+        assert_eq!(EvalError::Os(8).to_string(), "ERR:SYNTAX");
+        assert_eq!(EvalError::Os(2).os_name(), Some("DIVIDE BY 0"));
+        assert_eq!(EvalError::Os(99).to_string(), "ERR:99");
+        let record = EvalRecord::from_result(&Err(EvalError::Timeout));
+        assert_eq!((record.kind, record.error), (-1, -2));
+    }
+}
@@ -0,0 +1,116 @@
+//! Exam mode (Press-to-Test)
+//!
+//! On hardware, exam mode is entered by holding [←] and [→] while pressing
+//! [ON]. The OS reboots into its Press-to-Test options screen and, once
+//! confirmed, disables apps and programs until exam mode is cleared by a
+//! link transfer. The exam LED blinks for as long as exam mode is active.
+//!
+//! The emulator drives the same key combination and tracks exam mode
+//! itself. Where the OS records the state depends on the OS version, so it
+//! is only read (and cleared on exit) when the host supplies its location
+//! as an `ExamFlag`.
+
+/// Keys held together with [ON] to enter exam mode: [←] and [→] (row, col)
+pub const EXAM_KEYS: [(usize, usize); 2] = [(7, 1), (7, 2)];
+
+/// How long the key combination is held after [ON], in emulated seconds
+pub const EXAM_HOLD_SECS: f64 = 1.0;
+
+/// Simulated LED blink period, in emulated seconds
+pub const LED_PERIOD_SECS: f64 = 1.0;
+
+/// Part of each blink period the simulated LED is lit
+pub const LED_ON_SECS: f64 = 0.25;
+
+/// Location of the OS's exam-mode flag bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExamFlag {
+    /// Address of the flag byte
+    pub addr: u32,
+    /// Bit set while exam mode is active (0-7)
+    pub bit: u8,
+}
+
+impl ExamFlag {
+    /// Flag at `addr`, or None if `bit` is out of range
+    pub fn new(addr: u32, bit: u8) -> Option<Self> {
+        (bit < 8).then_some(Self { addr, bit })
+    }
+
+    /// Mask for the flag bit
+    pub fn mask(&self) -> u8 {
+        1 << self.bit
+    }
+}
+
+/// Exam mode state kept by the emulator
+#[derive(Debug, Clone, Default)]
+pub struct ExamMode {
+    active: bool,
+    flag: Option<ExamFlag>,
+    entries: u32,
+}
+
+impl ExamMode {
+    /// Inactive, with no OS flag location
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True after `enter()` until `exit()`
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Number of times exam mode was entered
+    pub fn entries(&self) -> u32 {
+        self.entries
+    }
+
+    /// Where the OS records exam mode, if known
+    pub fn flag(&self) -> Option<ExamFlag> {
+        self.flag
+    }
+
+    /// Set where the OS records exam mode
+    pub fn set_flag(&mut self, flag: Option<ExamFlag>) {
+        self.flag = flag;
+    }
+
+    /// Record that exam mode was entered
+    pub fn enter(&mut self) {
+        self.active = true;
+        self.entries += 1;
+    }
+
+    /// Record that exam mode was left
+    pub fn exit(&mut self) {
+        self.active = false;
+    }
+
+    /// Simulated LED state `elapsed_secs` into a blink sequence
+    pub fn led_on(elapsed_secs: f64) -> bool {
+        elapsed_secs.rem_euclid(LED_PERIOD_SECS) < LED_ON_SECS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_led_blinks_and_flag_validation() {
+        assert!(ExamMode::led_on(0.0));
+        assert!(ExamMode::led_on(LED_PERIOD_SECS + 0.1));
+        assert!(!ExamMode::led_on(0.5));
+        assert_eq!(ExamFlag::new(0xD00080, 3).unwrap().mask(), 0x08);
+        assert_eq!(ExamFlag::new(0xD00080, 8), None);
+
+        let mut exam = ExamMode::new();
+        exam.enter();
+        exam.exit();
+        exam.enter();
+        assert!(exam.is_active());
+        assert_eq!(exam.entries(), 2);
+    }
+}
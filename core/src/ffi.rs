@@ -0,0 +1,2658 @@
+//! C ABI
+//!
+//! Every `emu_*` function here is exported unmangled (or as `rust_emu_*`
+//! with the `ios_prefixed` feature, for builds that link both backends) and
+//! declared in `include/emu.h`; `test_header_matches_exports` fails when the
+//! two drift apart.
+//!
+//! Conventions:
+//! - An `Emu*` is an opaque `SyncEmu`: every call locks its mutex, so one
+//!   instance may be used from several threads (e.g. keys from the UI thread
+//!   while the emulation thread runs it). Calls on one instance serialize;
+//!   separate instances never share state. Callbacks run on the calling
+//!   thread with the lock held and must not call back into the same instance.
+//! - Fallible calls return `int`: 0 or a count on success, -1 for a null
+//!   pointer or invalid argument. Codes below -1 mean something specific to
+//!   each function and are documented on it. Only `emu_create` and the
+//!   framebuffer/backend getters return pointers.
+//! - Getters that cannot fail return the value itself, and 0 for a null
+//!   pointer (-1 where 0 is a meaningful value).
+//! - Out buffers for a whole value (a state, a file, a string) take a
+//!   capacity and report the full length, so a short buffer can be retried.
+//!   Queue reads (`emu_take_*`, `emu_link_recv`, `emu_serial_read`) instead
+//!   copy what fits, return the count copied, and leave the rest queued.
+//! - `include/emu.h` is written by hand; the build fails when it no longer
+//!   matches the exports here (see `ffi_header`).
+//! - `emu_api_version` returns `EMU_API_VERSION`, bumped on any change that
+//!   breaks existing callers (removed functions, changed signatures or
+//!   codes). Additions keep the version.
+
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
+
+use crate::memory::FLASH_DIRTY_SECTOR_SIZE;
+
+use super::*;
+
+/// Version of the C ABI, bumped on incompatible changes
+pub const EMU_API_VERSION: u32 = 1;
+
+/// Thread-safe wrapper for the emulator.
+/// All FFI calls go through this mutex to prevent data races between
+/// the UI thread (key events) and emulation thread (run_cycles).
+/// This is an opaque type from C's perspective (used via void*).
+pub struct SyncEmu {
+    inner: Mutex<Emu>,
+}
+
+impl SyncEmu {
+    fn new() -> Self {
+        Self {
+            inner: Mutex::new(Emu::new()),
+        }
+    }
+}
+
+/// The C ABI version this library implements (`EMU_API_VERSION` in emu.h).
+/// Callers built against a different version should not use the library.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_api_version")]
+pub extern "C" fn emu_api_version() -> u32 {
+    EMU_API_VERSION
+}
+
+/// Create a new emulator instance.
+/// Returns null on allocation failure.
+/// The returned pointer is thread-safe - all operations are synchronized.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_create")]
+pub extern "C" fn emu_create() -> *mut SyncEmu {
+    let emu = Box::new(SyncEmu::new());
+    Box::into_raw(emu)
+}
+
+/// Destroy an emulator instance.
+/// Safe to call with null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_destroy")]
+pub extern "C" fn emu_destroy(emu: *mut SyncEmu) {
+    if !emu.is_null() {
+        unsafe {
+            drop(Box::from_raw(emu));
+        }
+    }
+}
+
+/// Set an optional log callback for emulator events.
+/// The callback is called with a null-terminated C string.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_log_callback")]
+pub extern "C" fn emu_set_log_callback(cb: Option<extern "C" fn(*const c_char)>) {
+    emu::set_log_callback(cb);
+}
+
+/// Set a log callback for one emulator instance, overriding the process-wide
+/// callback. Pass NULL to fall back to the process-wide callback.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_instance_log_callback")]
+pub extern "C" fn emu_set_instance_log_callback(emu: *mut SyncEmu, cb: Option<extern "C" fn(*const c_char)>) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_log_callback(cb);
+}
+
+/// Set the log verbosity for one subsystem, or all of them when subsystem < 0.
+/// subsystem: 0 = cpu, 1 = bus, 2 = keypad, 3 = lcd, 4 = flash, 5 = interrupt,
+/// 6 = panel, 7 = sha256, 8 = control.
+/// level: 0 = off, 1 = error, 2 = warn, 3 = info (default), 4 = debug, 5 = trace.
+/// Returns 0 on success, -1 on invalid arguments.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_log_level")]
+pub extern "C" fn emu_set_log_level(emu: *mut SyncEmu, subsystem: i32, level: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(level) = logging::LogLevel::from_id(level) else {
+        return -1;
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if subsystem < 0 {
+        emu.set_all_log_levels(level);
+        return 0;
+    }
+    match logging::LogSubsystem::from_id(subsystem) {
+        Some(sub) => {
+            emu.set_log_level(sub, level);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Set log verbosity from a null-terminated filter string, e.g.
+/// "warn,keypad=debug,lcd=off" (see `logging`). Returns 0, -1 on null
+/// pointer, -2 on invalid UTF-8, or -3 on a bad directive (the directives
+/// before it are still applied).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_log_filter")]
+pub extern "C" fn emu_set_log_filter(emu: *mut SyncEmu, filter: *const c_char) -> i32 {
+    if emu.is_null() || filter.is_null() {
+        return -1;
+    }
+    let filter = match unsafe { std::ffi::CStr::from_ptr(filter) }.to_str() {
+        Ok(filter) => filter,
+        Err(_) => return -2,
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.set_log_filter(filter) {
+        Ok(()) => 0,
+        Err(_) => -3,
+    }
+}
+
+/// Load ROM data into the emulator.
+/// Returns 0 on success, negative error code on failure.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_rom")]
+pub extern "C" fn emu_load_rom(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let rom_data = unsafe { slice::from_raw_parts(data, len) };
+
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.load_rom(rom_data) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// Load a ROM dump after validating it: the full 4MB with boot code, not a
+/// truncated dump or a CEmu state image. On error the current ROM is kept.
+/// Returns 0 on success, -1 on null pointer, -2 empty, -3 too large,
+/// -5 truncated, -6 CEmu state image, -7 no boot code.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_rom_checked")]
+pub extern "C" fn emu_load_rom_checked(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let rom_data = unsafe { slice::from_raw_parts(data, len) };
+
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.load_rom_checked(rom_data) {
+        Ok(_) => 0,
+        Err(e) => e.code(),
+    }
+}
+
+/// Describe the loaded ROM as a JSON object: size, boot_code_hash,
+/// boot_version, os_present, os_version (versions null if not found).
+/// Writes up to `cap` bytes (not null-terminated) and returns the full
+/// length, so a short buffer can be retried; -1 on null pointer, -10 if no
+/// ROM is loaded.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_rom_info_json")]
+pub extern "C" fn emu_rom_info_json(emu: *const SyncEmu, out: *mut c_char, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let Some(info) = emu.rom_info() else {
+        return -10;
+    };
+    let json = info.to_json();
+    let len = json.len().min(cap);
+    if len > 0 {
+        let out = unsafe { slice::from_raw_parts_mut(out as *mut u8, len) };
+        out.copy_from_slice(&json.as_bytes()[..len]);
+    }
+    json.len() as i32
+}
+
+/// The homescreen text as UTF-8, rows separated by newlines (see
+/// `Emu::read_screen_text`). Writes up to `cap` bytes (not null-terminated)
+/// and returns the full length; -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_read_screen_text")]
+pub extern "C" fn emu_read_screen_text(emu: *mut SyncEmu, out: *mut c_char, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let text = emu.read_screen_text();
+    let len = text.len().min(cap);
+    if len > 0 {
+        let out = unsafe { slice::from_raw_parts_mut(out as *mut u8, len) };
+        out.copy_from_slice(&text.as_bytes()[..len]);
+    }
+    text.len() as i32
+}
+
+/// Replace the ROM in place, keeping callbacks, breakpoints, and configuration.
+/// Returns 0 on success, negative error code on failure (same codes as emu_load_rom).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_swap_rom")]
+pub extern "C" fn emu_swap_rom(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let rom_data = unsafe { slice::from_raw_parts(data, len) };
+
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.swap_rom(rom_data) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// Load ROM by memory-mapping a file (requires the `mmap` feature).
+/// The path is a null-terminated UTF-8 string. The file must not be modified while mapped.
+/// Returns 0 on success, negative error code on failure (-3 = too large, -4 = open/map failed).
+#[cfg(feature = "mmap")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_rom_mapped")]
+pub extern "C" fn emu_load_rom_mapped(emu: *mut SyncEmu, path: *const c_char) -> i32 {
+    if emu.is_null() || path.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let path = match unsafe { std::ffi::CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path,
+        Err(_) => return -4,
+    };
+
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.load_rom_mapped(std::path::Path::new(path)) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// Send a .8xp/.8xv file to the emulator.
+/// Injects the file into the flash archive so TI-OS discovers it on boot.
+/// Must be called after load_rom() and before power_on().
+/// Returns: number of entries injected (>=0), or negative error code.
+/// Error codes: -10 = ROM not loaded, -11 = parse error, -12 = no flash space, -13 = already booted
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_send_file")]
+pub extern "C" fn emu_send_file(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() || len == 0 {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let file_data = unsafe { slice::from_raw_parts(data, len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.send_file(file_data) {
+        Ok(count) => count as i32,
+        Err(code) => code,
+    }
+}
+
+/// Send a .8xp/.8xv/.8xg file before or after boot. Before power_on() this
+/// is emu_send_file(); once running, archived copies are replaced and TI-OS
+/// restarts to discover them (RAM is cleared).
+/// Returns: number of entries injected (>=0), or negative error code.
+/// Error codes: -1 = null pointer, -10 = ROM not loaded, -11 = parse error, -12 = no flash space
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_send_variable")]
+pub extern "C" fn emu_send_variable(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() || len == 0 {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let file_data = unsafe { slice::from_raw_parts(data, len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.send_variable(file_data) {
+        Ok(count) => count as i32,
+        Err(code) => code,
+    }
+}
+
+/// Read a variable back out as a .8x* file. `name` is the OS name bytes
+/// (e.g. "HELLO"); a file is at most 65592 bytes.
+/// Returns: bytes written (>0), or negative error code
+/// Error codes: -1 = null pointer or no such variable, -2 = unknown type or too large,
+/// -3 = buffer too small
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_receive_variable")]
+pub extern "C" fn emu_receive_variable(
+    emu: *mut SyncEmu,
+    name: *const u8,
+    name_len: usize,
+    out: *mut u8,
+    cap: usize,
+) -> i32 {
+    if emu.is_null() || name.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let name = unsafe { slice::from_raw_parts(name, name_len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.receive_variable(name) {
+        Ok(file) if file.len() <= cap => {
+            let buffer = unsafe { slice::from_raw_parts_mut(out, file.len()) };
+            buffer.copy_from_slice(&file);
+            file.len() as i32
+        }
+        Ok(_) => -3,
+        Err(code) => code,
+    }
+}
+
+/// Load a linked ez80-clang ELF and start at its entry point, without TI-OS.
+/// Sections go to their link addresses and the ELF's symbols become the
+/// debugger symbols; a ROM is optional.
+/// Returns: number of sections loaded (>=0), or negative error code
+/// Error codes: -11 = not a valid eZ80 ELF
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_elf")]
+pub extern "C" fn emu_load_elf(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let elf_data = unsafe { slice::from_raw_parts(data, len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.load_elf(elf_data) {
+        Ok(count) => count as i32,
+        Err(code) => code,
+    }
+}
+
+/// Rebuild the calculator from CEmu exports: its ROM image (full 4MB flash)
+/// and, optionally, its RAM dump (`ram` may be null).
+/// Returns: 0 on success, or negative error code
+/// Error codes: -1 = null pointer, -2 = CEmu state image (not importable),
+/// -3 = flash is not 4MB, -4 = RAM dump has the wrong size
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_import_cemu")]
+pub extern "C" fn emu_import_cemu(
+    emu: *mut SyncEmu,
+    flash: *const u8,
+    flash_len: usize,
+    ram: *const u8,
+    ram_len: usize,
+) -> i32 {
+    if emu.is_null() || flash.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let flash = unsafe { slice::from_raw_parts(flash, flash_len) };
+    let ram = (!ram.is_null()).then(|| unsafe { slice::from_raw_parts(ram, ram_len) });
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.import_cemu(flash, ram) {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Reset the emulator to initial state.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset")]
+pub extern "C" fn emu_reset(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.reset();
+}
+
+/// Perform a specific kind of reset.
+/// kind: 0 = warm (reset button, RAM preserved), 1 = RAM clear, 2 = full power cycle.
+/// Returns 0 on success, -1 on null pointer or invalid kind.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset_with")]
+pub extern "C" fn emu_reset_with(emu: *mut SyncEmu, kind: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let kind = match kind {
+        0 => ResetKind::Warm,
+        1 => ResetKind::RamClear,
+        2 => ResetKind::PowerCycle,
+        _ => return -1,
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.reset_with(kind);
+    0
+}
+
+/// Pause emulation: `emu_run_cycles` and the other run calls execute
+/// nothing until `emu_resume`. Called while another thread is inside
+/// `emu_run_cycles`, it waits for that slice to finish, so the core is
+/// never stopped mid-instruction.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_pause")]
+pub extern "C" fn emu_pause(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.pause();
+}
+
+/// Resume after `emu_pause`.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_resume")]
+pub extern "C" fn emu_resume(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.resume();
+}
+
+/// Whether emulation is paused: 1 paused, 0 running, -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_is_paused")]
+pub extern "C" fn emu_is_paused(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.is_paused() as i32
+}
+
+/// Why the calculator last restarted (see `ResetCause::code`), -1 on null
+/// pointer. For a protection violation, `addr` and `pc` (either may be null)
+/// receive the blocked write address and the offending code.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_last_reset_cause")]
+pub extern "C" fn emu_last_reset_cause(emu: *const SyncEmu, addr: *mut u32, pc: *mut u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let cause = emu.last_reset_cause();
+    if let ResetCause::Protection { addr: violation_addr, pc: violation_pc, .. } = cause {
+        if !addr.is_null() {
+            unsafe { *addr = violation_addr };
+        }
+        if !pc.is_null() {
+            unsafe { *pc = violation_pc };
+        }
+    }
+    cause.code()
+}
+
+/// Enable (nonzero) or disable (0) deterministic mode with the given seed.
+/// Call before load_rom() so the seeded RAM pattern applies at reset.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_deterministic")]
+pub extern "C" fn emu_set_deterministic(emu: *mut SyncEmu, enabled: i32, seed: u64) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_deterministic(if enabled != 0 { Some(seed) } else { None });
+}
+
+/// Set the OS patches applied on every ROM load from a null-terminated text
+/// patch list (see `os_patch`); an empty list clears them.
+/// Returns the number of patches, -1 on null pointer, -2 on invalid UTF-8,
+/// or -(100 + line) for the first line that failed to parse.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_os_patches")]
+pub extern "C" fn emu_set_os_patches(emu: *mut SyncEmu, text: *const c_char) -> i32 {
+    if emu.is_null() || text.is_null() {
+        return -1;
+    }
+    let text = match unsafe { std::ffi::CStr::from_ptr(text) }.to_str() {
+        Ok(text) => text,
+        Err(_) => return -2,
+    };
+    let patches = match os_patch::parse_patch_list(text) {
+        Ok(patches) => patches,
+        Err(e) => return -(100 + e.line.min(i32::MAX as usize - 100) as i32),
+    };
+    let count = patches.len() as i32;
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_os_patches(patches);
+    count
+}
+
+/// Outcome of the OS patches on the last ROM load: bytes written (>= 0),
+/// -1 on null pointer or no patches, -2 if original bytes did not match,
+/// -3 if a patch was invalid.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_os_patch_result")]
+pub extern "C" fn emu_os_patch_result(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.os_patch_result() {
+        None => -1,
+        Some(Ok(bytes)) => *bytes as i32,
+        Some(Err(os_patch::PatchError::Mismatch { .. })) => -2,
+        Some(Err(_)) => -3,
+    }
+}
+
+/// Power on the emulator (simulate ON key press+release).
+/// Must be called after load_rom() to start execution.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_power_on")]
+pub extern "C" fn emu_power_on(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.power_on();
+}
+
+/// Run the emulator for the specified number of cycles.
+/// Returns the number of cycles actually executed.
+/// Also updates the framebuffer with current VRAM contents.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_cycles")]
+pub extern "C" fn emu_run_cycles(emu: *mut SyncEmu, cycles: i32) -> i32 {
+    if emu.is_null() || cycles <= 0 {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let executed = emu.run_cycles(cycles as u32) as i32;
+    emu.render_frame();
+    executed
+}
+
+/// Run until the CPU is idle waiting for key input (halted, interrupts enabled,
+/// nothing pending), up to max_cycles. Renders a frame afterwards.
+/// Returns elapsed cycles, or -1 if idle was not reached.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_until_idle")]
+pub extern "C" fn emu_run_until_idle(emu: *mut SyncEmu, max_cycles: u64) -> i64 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let result = emu.run_until_idle(max_cycles);
+    emu.render_frame();
+    match result {
+        Some(elapsed) => elapsed as i64,
+        None => -1,
+    }
+}
+
+/// Run until the LCD controller completes its next frame (vsync), then
+/// render it. Returns elapsed cycles (see `Emu::run_frame` for the LCD-off
+/// fallback), or 0 if the emulator cannot run.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_frame")]
+pub extern "C" fn emu_run_frame(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let executed = emu.run_frame() as i32;
+    // With a frame callback the completed frame was rendered at vsync
+    if !emu.has_frame_callback() {
+        emu.render_frame();
+    }
+    executed
+}
+
+/// Get a pointer to the presented framebuffer (with the color profile
+/// applied). The framebuffer is ARGB8888 format, owned by the emulator.
+/// Writes width and height to the provided pointers if non-null.
+/// Returns null if emulator pointer is null.
+///
+/// WARNING: The returned pointer is only valid while the mutex is held.
+/// The caller should copy the framebuffer data immediately.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_framebuffer")]
+pub extern "C" fn emu_framebuffer(emu: *const SyncEmu, w: *mut i32, h: *mut i32) -> *const u32 {
+    if emu.is_null() {
+        return ptr::null();
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let (width, height) = emu.framebuffer_size();
+
+    if !w.is_null() {
+        unsafe { *w = width as i32 };
+    }
+    if !h.is_null() {
+        unsafe { *h = height as i32 };
+    }
+
+    emu.present_frame();
+    emu.presented_ptr()
+}
+
+/// Copy the current frame into `out` as RGBA8888 (320x240x4 bytes), with
+/// the color profile applied like `emu_framebuffer`.
+/// Unlike `emu_framebuffer`, the copy is made under the emulator lock.
+/// Returns bytes written, -1 on null pointer, -2 if `cap` is too small.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_framebuffer")]
+pub extern "C" fn emu_get_framebuffer(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
+
+    emu.present_frame();
+    match emu.copy_presented_rgba(buffer) {
+        Some(len) => len as i32,
+        None => -2,
+    }
+}
+
+/// Frames rendered since the last reset. A frontend can skip its blit
+/// while this has not changed. Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_frame_counter")]
+pub extern "C" fn emu_get_frame_counter(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.frame_counter()
+}
+
+/// C frame-ready callback and its user pointer
+struct CallbackFrameSink {
+    cb: extern "C" fn(*const u32, i32, i32, i32, *mut std::ffi::c_void),
+    user: *mut std::ffi::c_void,
+}
+
+// The user pointer is only passed back to the caller's callback
+unsafe impl Send for CallbackFrameSink {}
+
+impl CallbackFrameSink {
+    fn frame(&self, pixels: &[u32], stride: usize) {
+        let height = pixels.len() / stride;
+        (self.cb)(pixels.as_ptr(), stride as i32, height as i32, stride as i32, self.user);
+    }
+}
+
+/// Call `cb` with the rendered framebuffer (ARGB8888, width, height,
+/// stride in pixels) each time the LCD completes a frame, from inside
+/// `emu_run_cycles`/`emu_run_frame` on the emulation thread. The pixels
+/// are only valid during the call. Pass NULL to remove it.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_frame_callback")]
+pub extern "C" fn emu_set_frame_callback(
+    emu: *mut SyncEmu,
+    cb: Option<extern "C" fn(*const u32, i32, i32, i32, *mut std::ffi::c_void)>,
+    user: *mut std::ffi::c_void,
+) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let callback = cb.map(|cb| {
+        let sink = CallbackFrameSink { cb, user };
+        Box::new(move |pixels: &[u32], stride: usize| sink.frame(pixels, stride)) as FrameCallback
+    });
+    emu.set_frame_callback(callback);
+}
+
+/// Get a pointer to the SPI panel's frame memory (pixels drawn with RAMWR).
+/// Same format, size reporting and lifetime caveat as `emu_framebuffer`.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_panel_framebuffer")]
+pub extern "C" fn emu_panel_framebuffer(emu: *const SyncEmu, w: *mut i32, h: *mut i32) -> *const u32 {
+    if emu.is_null() {
+        return ptr::null();
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+
+    if !w.is_null() {
+        unsafe { *w = peripherals::PANEL_WIDTH as i32 };
+    }
+    if !h.is_null() {
+        unsafe { *h = peripherals::PANEL_HEIGHT as i32 };
+    }
+
+    emu.panel_framebuffer().as_ptr()
+}
+
+/// Set the color profile applied to presented frames (0 = ideal sRGB, 1 = CE panel).
+/// It only changes what the framebuffer getters and frame callback hand out;
+/// hashes, screenshots and recordings see the exact LCD output.
+/// Returns 0 on success, -1 on null pointer or unknown profile.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_color_profile")]
+pub extern "C" fn emu_set_color_profile(emu: *mut SyncEmu, profile: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(profile) = u8::try_from(profile).ok().and_then(ColorProfile::from_id) else {
+        return -1;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_color_profile(profile);
+    0
+}
+
+/// Set key state.
+/// row: 0-7, col: 0-7
+/// down: non-zero for pressed, zero for released
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_key")]
+pub extern "C" fn emu_set_key(emu: *mut SyncEmu, row: i32, col: i32, down: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_key(row as usize, col as usize, down != 0);
+}
+
+/// Press the key at (row, col) and release it after `ms` emulated
+/// milliseconds, during later emu_run_cycles calls.
+/// Returns 0, -1 on null pointer, or -2 if no key is wired there.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_tap_key")]
+pub extern "C" fn emu_tap_key(emu: *mut SyncEmu, row: i32, col: i32, ms: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(key) = Key::at(row as usize, col as usize) else {
+        return -2;
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.tap_key(key, ms);
+    0
+}
+
+/// Type null-terminated UTF-8 `text` on the keypad (see `Emu::type_text`),
+/// running the emulator while it types. Returns 0, -1 on null pointer, -2
+/// on invalid UTF-8, or -3 if a character has no key (nothing is typed).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_type_text")]
+pub extern "C" fn emu_type_text(emu: *mut SyncEmu, text: *const c_char) -> i32 {
+    if emu.is_null() || text.is_null() {
+        return -1;
+    }
+    let text = match unsafe { std::ffi::CStr::from_ptr(text) }.to_str() {
+        Ok(text) => text,
+        Err(_) => return -2,
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.type_text(text) {
+        Ok(()) => 0,
+        Err(_) => -3,
+    }
+}
+
+/// Press (down non-zero) or release the ON key: the power button.
+/// A press powers on and wakes a halted CPU even with interrupts disabled,
+/// raises the ON interrupt and, if the calculator is off, the WAKE
+/// interrupt. Hold it as long as the user does and release it after.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_on_key")]
+pub extern "C" fn emu_set_on_key(emu: *mut SyncEmu, down: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if down != 0 {
+        emu.press_on_key();
+    } else {
+        emu.release_on_key();
+    }
+}
+
+/// Add a memory poke applied every frame.
+/// Returns the cheat id (>= 0), or -1 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cheat_add")]
+pub extern "C" fn emu_cheat_add(emu: *mut SyncEmu, addr: u32, value: u8) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_cheat(Cheat::new(addr, value)) as i32
+}
+
+/// Add a memory poke applied every frame while the byte at `cond_addr` equals `cond_value`.
+/// Returns the cheat id (>= 0), or -1 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cheat_add_conditional")]
+pub extern "C" fn emu_cheat_add_conditional(
+    emu: *mut SyncEmu,
+    addr: u32,
+    value: u8,
+    cond_addr: u32,
+    cond_value: u8,
+) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_cheat(Cheat::new(addr, value).when(cond_addr, cond_value)) as i32
+}
+
+/// Enable (non-zero) or disable (zero) a cheat.
+/// Returns 0 on success, -1 on null pointer or unknown id.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cheat_set_enabled")]
+pub extern "C" fn emu_cheat_set_enabled(emu: *mut SyncEmu, id: i32, enabled: i32) -> i32 {
+    if emu.is_null() || id < 0 {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.set_cheat_enabled(id as u32, enabled != 0) { 0 } else { -1 }
+}
+
+/// Remove a cheat.
+/// Returns 0 on success, -1 on null pointer or unknown id.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cheat_remove")]
+pub extern "C" fn emu_cheat_remove(emu: *mut SyncEmu, id: i32) -> i32 {
+    if emu.is_null() || id < 0 {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.remove_cheat(id as u32) { 0 } else { -1 }
+}
+
+/// Remove every cheat.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cheat_clear")]
+pub extern "C" fn emu_cheat_clear(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.clear_cheats();
+}
+
+/// Choose when cheats are applied (0 = start of each run_cycles, 1 = LCD vblank).
+/// Returns 0 on success, -1 on null pointer or unknown point.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cheat_set_point")]
+pub extern "C" fn emu_cheat_set_point(emu: *mut SyncEmu, point: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let Some(point) = u8::try_from(point).ok().and_then(CheatPoint::from_id) else {
+        return -1;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_cheat_point(point);
+    0
+}
+
+/// Plug (non-zero) or unplug (zero) the USB serial accessory.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_serial_attach")]
+pub extern "C" fn emu_serial_attach(emu: *mut SyncEmu, attached: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if attached != 0 {
+        emu.attach_serial_accessory(SerialAccessoryConfig::default());
+    } else {
+        emu.detach_serial_accessory();
+    }
+}
+
+/// Returns 1 once the calculator has configured the serial accessory.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_serial_ready")]
+pub extern "C" fn emu_serial_ready(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.serial_accessory_ready() as i32
+}
+
+/// Queue bytes for the calculator to receive from the serial accessory.
+/// Returns 0 on success, -1 on null pointer or if no accessory is attached.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_serial_write")]
+pub extern "C" fn emu_serial_write(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    if emu.serial_write(data) { 0 } else { -1 }
+}
+
+/// Copy up to `cap` bytes the calculator sent to the serial accessory.
+/// Returns the number of bytes copied, or -1 on null pointer; bytes beyond
+/// `cap` stay queued.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_serial_read")]
+pub extern "C" fn emu_serial_read(emu: *mut SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let data = emu.serial_read(cap);
+    let out = unsafe { slice::from_raw_parts_mut(out, data.len()) };
+    out.copy_from_slice(&data);
+    data.len() as i32
+}
+
+/// Get the backlight brightness level (0-255).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_get_backlight")]
+pub extern "C" fn emu_get_backlight(emu: *const SyncEmu) -> u8 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.get_backlight()
+}
+
+/// Get the level the screen is lit at (0-255, 0 when too dim to see).
+/// Frontends dim the rendered screen by level / 255.
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_backlight_level")]
+pub extern "C" fn emu_backlight_level(emu: *const SyncEmu) -> u8 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.backlight_level()
+}
+
+/// Set the simulated battery: percent charge (0-100) and charging (nonzero),
+/// e.g. to mirror the host device. Kept across resets.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_battery")]
+pub extern "C" fn emu_set_battery(emu: *mut SyncEmu, percent: i32, charging: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_battery(percent.clamp(0, 100) as u8, charging != 0);
+}
+
+/// Check if LCD is on (should display content).
+/// Returns 1 if LCD is on, 0 if LCD is off.
+/// LCD is off when either control port 0x05 bit 4 is clear OR lcd.control bit 11 is clear.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_is_lcd_on")]
+pub extern "C" fn emu_is_lcd_on(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    if emu.is_lcd_on() { 1 } else { 0 }
+}
+
+/// Get the number of instructions executed since creation (or counter reset).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_instructions_retired")]
+pub extern "C" fn emu_instructions_retired(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.exec_counters().instructions
+}
+
+/// Get the number of maskable interrupts serviced.
+/// source < 0 returns the total; 0-31 returns the count for that interrupt source bit.
+/// Returns 0 if emulator pointer is null or source is out of range.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_interrupts_serviced")]
+pub extern "C" fn emu_interrupts_serviced(emu: *const SyncEmu, source: i32) -> u64 {
+    if emu.is_null() || source >= 32 {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let counters = emu.exec_counters();
+    if source < 0 {
+        counters.interrupts
+    } else {
+        counters.interrupts_by_source[source as usize]
+    }
+}
+
+/// Get the number of frames rendered.
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_frames_rendered")]
+pub extern "C" fn emu_frames_rendered(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.exec_counters().frames
+}
+
+/// Get the number of frames completed by the LCD controller since reset.
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_lcd_frame_count")]
+pub extern "C" fn emu_lcd_frame_count(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.lcd_frame_count()
+}
+
+/// Get the emulated cycle count at which the LCD controller last completed a frame.
+/// Returns -1 if no frame has completed since reset or emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_last_lcd_frame_cycle")]
+pub extern "C" fn emu_last_lcd_frame_cycle(emu: *const SyncEmu) -> i64 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.last_lcd_frame_cycle().map_or(-1, |cycle| cycle as i64)
+}
+
+/// Take up to `cap` queued LCD base-address changes (buffer swaps), oldest first.
+/// Returns the number written to `out`, or -1 if a pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_lcd_base_changes")]
+pub extern "C" fn emu_take_lcd_base_changes(emu: *mut SyncEmu, out: *mut LcdBaseChange, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let changes = emu.take_lcd_base_changes(cap);
+    let out = unsafe { slice::from_raw_parts_mut(out, changes.len()) };
+    out.copy_from_slice(&changes);
+    changes.len() as i32
+}
+
+/// Start reporting user-program writes to OS-owned RAM (system flags, VAT,
+/// caller stack). `ranges` holds `count` extra (start, end) pairs, inclusive;
+/// it may be NULL when `count` is 0. Returns 0, or -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_sandbox_enable")]
+pub extern "C" fn emu_sandbox_enable(emu: *mut SyncEmu, ranges: *const u32, count: usize) -> i32 {
+    if emu.is_null() || (ranges.is_null() && count > 0) {
+        return -1;
+    }
+    let extra = if count == 0 {
+        Vec::new()
+    } else {
+        let ranges = unsafe { slice::from_raw_parts(ranges, count * 2) };
+        ranges.chunks(2).map(|r| GuardRegion::new(r[0], r[1], GuardKind::Custom)).collect()
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.enable_sandbox(extra);
+    0
+}
+
+/// Stop reporting sandbox violations (unread ones can still be taken).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_sandbox_disable")]
+pub extern "C" fn emu_sandbox_disable(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.disable_sandbox();
+}
+
+/// Take up to `cap` sandbox violations, oldest first.
+/// Returns the number written to `out`, or -1 if a pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_sandbox_violations")]
+pub extern "C" fn emu_take_sandbox_violations(emu: *mut SyncEmu, out: *mut SandboxViolation, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let violations = emu.take_sandbox_violations(cap);
+    let out = unsafe { slice::from_raw_parts_mut(out, violations.len()) };
+    out.copy_from_slice(&violations);
+    violations.len() as i32
+}
+
+/// Add a breakpoint. Returns its ID (> 0), or -1 on null pointer.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_add_breakpoint")]
+pub extern "C" fn emu_add_breakpoint(emu: *mut SyncEmu, pc: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_breakpoint(pc) as i32
+}
+
+/// Remove a breakpoint. Returns 0, or -1 on null pointer or unknown ID.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_remove_breakpoint")]
+pub extern "C" fn emu_remove_breakpoint(emu: *mut SyncEmu, id: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.remove_breakpoint(id) { 0 } else { -1 }
+}
+
+/// Watch memory `start..=end` for the `debug::access` bits.
+/// Returns the ID (> 0), -1 on null pointer, or -2 if the range or bits are empty.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_add_watchpoint")]
+pub extern "C" fn emu_add_watchpoint(emu: *mut SyncEmu, start: u32, end: u32, access: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_watchpoint(start, end, access as u8).map_or(-2, |id| id as i32)
+}
+
+/// Watch I/O ports `start..=end` for reads (1) and/or writes (2).
+/// Returns the ID (> 0), -1 on null pointer, or -2 if the range or bits are empty.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_add_port_watchpoint")]
+pub extern "C" fn emu_add_port_watchpoint(emu: *mut SyncEmu, start: u16, end: u16, access: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.add_port_watchpoint(start, end, access as u8).map_or(-2, |id| id as i32)
+}
+
+/// Remove a memory or port watchpoint. Returns 0, or -1 on null pointer or unknown ID.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_remove_watchpoint")]
+pub extern "C" fn emu_remove_watchpoint(emu: *mut SyncEmu, id: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.remove_watchpoint(id) { 0 } else { -1 }
+}
+
+/// Write a debugger event to `out` if it is non-null; returns 1 if there was one
+#[cfg(feature = "debugger")]
+fn write_debug_event(event: Option<debug::DebugEvent>, out: *mut debug::DebugEvent) -> i32 {
+    match event {
+        Some(event) => {
+            if !out.is_null() {
+                unsafe { *out = event };
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Take why the last run stopped for the debugger.
+/// Returns 1 and fills `out`, 0 if it did not, or -1 on null pointer.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_debug_event")]
+pub extern "C" fn emu_take_debug_event(emu: *mut SyncEmu, out: *mut debug::DebugEvent) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    write_debug_event(emu.take_debug_event(), out)
+}
+
+/// Execute one instruction. Returns 1 and fills `out` (may be null),
+/// 0 if the emulator cannot run, or -1 on null pointer.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_step_into")]
+pub extern "C" fn emu_step_into(emu: *mut SyncEmu, out: *mut debug::DebugEvent) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    write_debug_event(emu.step_into(), out)
+}
+
+/// Step over a CALL/RST. Returns 1 and fills `out` (may be null) when
+/// stopped, 0 if `max_cycles` ran out, or -1 on null pointer.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_step_over")]
+pub extern "C" fn emu_step_over(emu: *mut SyncEmu, max_cycles: u32, out: *mut debug::DebugEvent) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    write_debug_event(emu.step_over(max_cycles), out)
+}
+
+/// Run until the current function returns. Returns 1 and fills `out` (may
+/// be null) when stopped, 0 if `max_cycles` ran out, or -1 on null pointer.
+#[cfg(feature = "debugger")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_step_out")]
+pub extern "C" fn emu_step_out(emu: *mut SyncEmu, max_cycles: u32, out: *mut debug::DebugEvent) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    write_debug_event(emu.step_out(max_cycles), out)
+}
+
+/// Read `len` bytes from `addr` into `buf`. `side_effects` nonzero reads
+/// through the CPU's bus path (MMIO registers that change on read do);
+/// zero peeks. Emulated timing is not affected. Returns 0, or -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_read_memory")]
+pub extern "C" fn emu_read_memory(emu: *mut SyncEmu, addr: u32, buf: *mut u8, len: usize, side_effects: i32) -> i32 {
+    if emu.is_null() || (buf.is_null() && len > 0) {
+        return -1;
+    }
+    if len == 0 {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let buf = unsafe { slice::from_raw_parts_mut(buf, len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.read_memory(addr, buf, side_effects != 0);
+    0
+}
+
+/// Write `len` bytes from `data` to `addr`. `side_effects` nonzero writes
+/// through the CPU's bus path (flash commands, memory protection); zero
+/// pokes memory directly. Returns 0, or -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_write_memory")]
+pub extern "C" fn emu_write_memory(emu: *mut SyncEmu, addr: u32, data: *const u8, len: usize, side_effects: i32) -> i32 {
+    if emu.is_null() || (data.is_null() && len > 0) {
+        return -1;
+    }
+    if len == 0 {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.write_memory(addr, data, side_effects != 0);
+    0
+}
+
+/// Hash each rendered frame (CRC32 of the ARGB framebuffer), optionally only
+/// the `w` x `h` rectangle at (`x`, `y`); `w` or `h` of 0 hashes the whole
+/// screen. Returns 0, -1 on null pointer, or -2 if the rectangle is off screen.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_frame_hashing")]
+pub extern "C" fn emu_set_frame_hashing(emu: *mut SyncEmu, enabled: i32, x: u32, y: u32, w: u32, h: u32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let rect = (w > 0 && h > 0).then_some(HashRect {
+        x: x as usize,
+        y: y as usize,
+        width: w as usize,
+        height: h as usize,
+    });
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.set_frame_hashing(enabled != 0, rect) { 0 } else { -2 }
+}
+
+/// Take up to `cap` queued frame hashes, oldest first.
+/// Returns the number written to `out`, or -1 if a pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_frame_hashes")]
+pub extern "C" fn emu_take_frame_hashes(emu: *mut SyncEmu, out: *mut FrameHash, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let hashes = emu.take_frame_hashes(cap);
+    let out = unsafe { slice::from_raw_parts_mut(out, hashes.len()) };
+    out.copy_from_slice(&hashes);
+    hashes.len() as i32
+}
+
+/// 64-bit hash of the last rendered frame (see Emu::frame_hash).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_frame_hash")]
+pub extern "C" fn emu_frame_hash(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.frame_hash()
+}
+
+/// Encode the last rendered frame as a PNG file. Writes it to `out` only if
+/// it fits in `cap` bytes and returns its length either way, so a short (or
+/// null, with `cap` 0) buffer can be retried; -1 on null emulator pointer.
+#[cfg(feature = "png")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_screenshot_png")]
+pub extern "C" fn emu_screenshot_png(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let png = emu.screenshot_png();
+    if png.len() <= cap {
+        let out = unsafe { slice::from_raw_parts_mut(out, png.len()) };
+        out.copy_from_slice(&png);
+    }
+    png.len() as i32
+}
+
+/// Start recording scheduler events, interrupts, and LCD frames (plus bcalls
+/// if `bcalls` is non-zero) for Chrome trace-event export. `max_events` of 0
+/// uses the default limit.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_chrome_trace_start")]
+pub extern "C" fn emu_chrome_trace_start(emu: *mut SyncEmu, bcalls: i32, max_events: u32) {
+    if emu.is_null() {
+        return;
+    }
+    let mut config = ChromeTraceConfig { bcalls: bcalls != 0, ..Default::default() };
+    if max_events > 0 {
+        config.max_events = max_events as usize;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.start_chrome_trace(config);
+}
+
+/// Stop recording (the recording is kept for emu_chrome_trace_save).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_chrome_trace_stop")]
+pub extern "C" fn emu_chrome_trace_stop(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.stop_chrome_trace();
+}
+
+/// Write the recording as Chrome trace-event JSON to a file.
+/// The path is a null-terminated UTF-8 string.
+/// Returns bytes written, -1 on null pointer, -2 if nothing was recorded,
+/// -4 if the file cannot be written.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_chrome_trace_save")]
+pub extern "C" fn emu_chrome_trace_save(emu: *const SyncEmu, path: *const c_char) -> i32 {
+    if emu.is_null() || path.is_null() {
+        return -1;
+    }
+    let path = match unsafe { std::ffi::CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path,
+        Err(_) => return -4,
+    };
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    if emu.chrome_trace().is_none() {
+        return -2;
+    }
+    let mut file = match std::fs::File::create(path) {
+        Ok(file) => std::io::BufWriter::new(file),
+        Err(_) => return -4,
+    };
+    match emu.write_chrome_trace(&mut file).and_then(|n| std::io::Write::flush(&mut file).map(|_| n)) {
+        Ok(bytes) => bytes.min(i32::MAX as usize) as i32,
+        Err(_) => -4,
+    }
+}
+
+/// Start recording rendered frames to a GIF (.gif) or APNG (.png, .apng)
+/// file at `fps` frames per emulated second. The path is a null-terminated
+/// UTF-8 string. Returns 0, -1 on null pointer, -2 for an unknown
+/// extension, -4 if the file cannot be created.
+#[cfg(feature = "recording")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_start_recording")]
+pub extern "C" fn emu_start_recording(emu: *mut SyncEmu, path: *const c_char, fps: u32) -> i32 {
+    if emu.is_null() || path.is_null() {
+        return -1;
+    }
+    let path = match unsafe { std::ffi::CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path,
+        Err(_) => return -4,
+    };
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.start_recording(path, fps) {
+        Ok(()) => 0,
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => -2,
+        Err(_) => -4,
+    }
+}
+
+/// Finish the screen recording. Returns the number of frames written,
+/// -1 on null pointer, -2 if nothing was recording, -4 if a write failed.
+#[cfg(feature = "recording")]
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_stop_recording")]
+pub extern "C" fn emu_stop_recording(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.stop_recording() {
+        Some(Ok(frames)) => frames.min(i32::MAX as u64) as i32,
+        Some(Err(_)) => -4,
+        None => -2,
+    }
+}
+
+/// Start recording key events for replay, stamped relative to now.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_start_input_recording")]
+pub extern "C" fn emu_start_input_recording(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.start_input_recording();
+}
+
+/// Copy the key events recorded so far, as a replay file, into `out` if it
+/// fits in `cap`. Returns the file length, -1 on null pointer, or -2 if not
+/// recording.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_input_recording")]
+pub extern "C" fn emu_input_recording(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let Some(data) = emu.input_recording() else {
+        return -2;
+    };
+    if data.len() <= cap {
+        let out = unsafe { slice::from_raw_parts_mut(out, data.len()) };
+        out.copy_from_slice(&data);
+    }
+    data.len().min(i32::MAX as usize) as i32
+}
+
+/// Stop recording key events, discarding them.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_stop_input_recording")]
+pub extern "C" fn emu_stop_input_recording(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.stop_input_recording();
+}
+
+/// Replay a recording from `emu_input_recording`, starting now.
+/// Returns the number of events, -1 on null pointer, or -102/-103 for
+/// malformed data or an unsupported version.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_play_replay")]
+pub extern "C" fn emu_play_replay(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    match emu.play_replay(data) {
+        Ok(events) => events.min(i32::MAX as usize) as i32,
+        Err(code) => code,
+    }
+}
+
+/// Write `len` bytes into the virtual link cable (DUSB silent link), as the
+/// host end would. Returns 0 or -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_link_send")]
+pub extern "C" fn emu_link_send(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let data = unsafe { slice::from_raw_parts(data, len) };
+    emu.link_send(data);
+    0
+}
+
+/// Read up to `cap` bytes the calculator wrote to the virtual link cable.
+/// Returns the number of bytes copied, or -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_link_recv")]
+pub extern "C" fn emu_link_recv(emu: *mut SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || (out.is_null() && cap > 0) {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let data = emu.link_recv(cap.min(i32::MAX as usize));
+    if !data.is_empty() {
+        let out = unsafe { slice::from_raw_parts_mut(out, data.len()) };
+        out.copy_from_slice(&data);
+    }
+    data.len() as i32
+}
+
+/// Render the last frame composited into a skin image.
+/// `skin` is `skin_w` x `skin_h` ARGB8888 pixels; `screen_rect` is the LCD
+/// window as [x, y, w, h] (null for none); `keys` holds `key_count` entries of
+/// [row, col, x, y, w, h]. Held keys are tinted with `highlight` (ARGB, alpha
+/// = opacity; 0 = no highlight). `out` must hold `skin_w * skin_h` pixels.
+/// Returns 0, -1 on null pointer, or -2 if the skin size is invalid.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_render_skin")]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn emu_render_skin(
+    emu: *const SyncEmu,
+    skin: *const u32,
+    skin_w: u32,
+    skin_h: u32,
+    screen_rect: *const u32,
+    keys: *const u32,
+    key_count: usize,
+    highlight: u32,
+    out: *mut u32,
+) -> i32 {
+    if emu.is_null() || skin.is_null() || out.is_null() || (keys.is_null() && key_count > 0) {
+        return -1;
+    }
+    let Some(len) = (skin_w as usize).checked_mul(skin_h as usize).filter(|&len| len > 0) else {
+        return -2;
+    };
+    let screen = if screen_rect.is_null() { &[][..] } else { unsafe { slice::from_raw_parts(screen_rect, 4) } };
+    let keys = if key_count == 0 { &[][..] } else { unsafe { slice::from_raw_parts(keys, key_count * 6) } };
+    let Some(layout) = SkinLayout::from_words(screen, keys) else {
+        return -2;
+    };
+    let pixels = unsafe { slice::from_raw_parts(skin, len) }.to_vec();
+    let Some(skin) = Skin::new(skin_w as usize, skin_h as usize, pixels, layout) else {
+        return -2;
+    };
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let image = emu.render_skin_screenshot(&skin, (highlight != 0).then_some(highlight));
+    let out = unsafe { slice::from_raw_parts_mut(out, len) };
+    out.copy_from_slice(&image);
+    0
+}
+
+/// Start (non-zero) or stop per-frame CPU utilization statistics.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_cpu_usage_stats")]
+pub extern "C" fn emu_set_cpu_usage_stats(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_cpu_usage_stats(enabled != 0);
+}
+
+/// Take up to `cap` queued per-frame CPU statistics, oldest first.
+/// Returns the number written to `out`, or -1 if a pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_frame_usage")]
+pub extern "C" fn emu_take_frame_usage(emu: *mut SyncEmu, out: *mut FrameUsage, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let frames = emu.take_frame_usage(cap);
+    let out = unsafe { slice::from_raw_parts_mut(out, frames.len()) };
+    out.copy_from_slice(&frames);
+    frames.len() as i32
+}
+
+/// Evaluate `count` NUL-terminated expressions in order on the homescreen
+/// through TI-OS, writing one result per expression to `out`. The OS must
+/// be booted to the homescreen. Returns the number of results written, or
+/// -1 on a null argument.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_eval_batch")]
+pub extern "C" fn emu_eval_batch(
+    emu: *mut SyncEmu,
+    exprs: *const *const c_char,
+    count: usize,
+    out: *mut EvalRecord,
+) -> i32 {
+    if emu.is_null() || exprs.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let exprs = unsafe { slice::from_raw_parts(exprs, count) };
+    let out = unsafe { slice::from_raw_parts_mut(out, count) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    for (expr, record) in exprs.iter().zip(out.iter_mut()) {
+        let result = if expr.is_null() {
+            Err(EvalError::Empty)
+        } else {
+            emu.evaluate(&unsafe { std::ffi::CStr::from_ptr(*expr) }.to_string_lossy())
+        };
+        *record = EvalRecord::from_result(&result);
+    }
+    count as i32
+}
+
+/// Percentage (0-100) of the last completed frame the CPU spent executing,
+/// for an activity meter. Returns -1 if there is no completed frame yet.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_cpu_busy_percent")]
+pub extern "C" fn emu_cpu_busy_percent(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.last_frame_usage() {
+        Some(usage) => (usage.busy_fraction() * 100.0).round() as i32,
+        None => -1,
+    }
+}
+
+/// Start (nonzero) or stop (0) measuring input-to-photon latency: from each
+/// emu_set_key press to the first frame rendered (by emu_run_cycles) whose pixels changed.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_latency_tracking")]
+pub extern "C" fn emu_set_latency_tracking(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_latency_tracking(enabled != 0);
+}
+
+/// Copy the input latency summary to `out`.
+/// Returns 0 on success, -1 if a pointer is null or latency is not being tracked.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_input_latency_stats")]
+pub extern "C" fn emu_input_latency_stats(emu: *const SyncEmu, out: *mut LatencyStats) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    match emu.input_latency_stats() {
+        Some(stats) => {
+            unsafe { *out = stats };
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Take up to `cap` queued latency samples, oldest first.
+/// Returns the number written to `out`, or -1 if a pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_take_input_latency")]
+pub extern "C" fn emu_take_input_latency(emu: *mut SyncEmu, out: *mut InputLatency, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let samples = emu.take_input_latencies(cap);
+    let out = unsafe { slice::from_raw_parts_mut(out, samples.len()) };
+    out.copy_from_slice(&samples);
+    samples.len() as i32
+}
+
+/// Get the number of emulator resets (including the one done by load_rom).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_reset_count")]
+pub extern "C" fn emu_reset_count(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.exec_counters().resets
+}
+
+/// Enable (1) or disable (0) per-subsystem host-time profiling.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_profiling")]
+pub extern "C" fn emu_set_profiling(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_profiling(enabled != 0);
+}
+
+/// Get accumulated host time in nanoseconds for a profiling section.
+/// Sections: 0=cpu, 1=peripherals, 2=scheduler, 3=keypad, 4=lcd_render, 5=trace.
+/// Returns 0 if emulator pointer is null or section is invalid.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_profile_nanos")]
+pub extern "C" fn emu_profile_nanos(emu: *const SyncEmu, section: i32) -> u64 {
+    let section = match ProfileSection::from_id(section) {
+        Some(section) => section,
+        None => return 0,
+    };
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.profile_breakdown()[section as usize].nanos
+}
+
+/// Enable (non-zero) or disable (zero) the adaptive performance governor.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_governor")]
+pub extern "C" fn emu_set_governor(emu: *mut SyncEmu, enabled: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_governor((enabled != 0).then(GovernorConfig::default));
+}
+
+/// Report the host wall-clock time spent on the last frame.
+/// Returns 1 if the governor changed a knob, 0 otherwise.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_governor_report")]
+pub extern "C" fn emu_governor_report(emu: *mut SyncEmu, host_micros: u64) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.governor_report(host_micros as f64 / 1_000_000.0).is_some() as i32
+}
+
+/// Get the speed measured over the governor's last window (1.0 = real time).
+/// Returns -1 if the governor is disabled, has no measurement yet, or pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_governor_speed")]
+pub extern "C" fn emu_governor_speed(emu: *const SyncEmu) -> f64 {
+    if emu.is_null() {
+        return -1.0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.governor().and_then(|g| g.speed()).unwrap_or(-1.0)
+}
+
+/// Get the current frame skip.
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_frame_skip")]
+pub extern "C" fn emu_frame_skip(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.frame_skip() as i32
+}
+
+/// Set the frame skip (negative values are treated as 0).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_frame_skip")]
+pub extern "C" fn emu_set_frame_skip(emu: *mut SyncEmu, skip: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_frame_skip(skip.max(0) as u32);
+}
+
+/// Get the accuracy mode (0 = full, 1 = fast).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_accuracy_mode")]
+pub extern "C" fn emu_accuracy_mode(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    (emu.accuracy_mode() == AccuracyMode::Fast) as i32
+}
+
+/// Set the accuracy mode (0 = full, non-zero = fast).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_accuracy_mode")]
+pub extern "C" fn emu_set_accuracy_mode(emu: *mut SyncEmu, mode: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_accuracy_mode(if mode != 0 { AccuracyMode::Fast } else { AccuracyMode::Full });
+}
+
+/// Set the emu_run_paced speed as a percentage of real time (100 = real
+/// time, 0 = paused); negative means turbo (as fast as possible).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_speed")]
+pub extern "C" fn emu_set_speed(emu: *mut SyncEmu, percent: i32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_speed(match percent {
+        p if p < 0 => SpeedMode::Turbo,
+        100 => SpeedMode::RealTime,
+        p => SpeedMode::Percent(p as u32),
+    });
+}
+
+/// Run the emulated time due host_micros of wall-clock time after the
+/// previous call, then render. Returns executed cycles (0 if pointer is null).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_run_paced")]
+pub extern "C" fn emu_run_paced(emu: *mut SyncEmu, host_micros: u64) -> i32 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let executed = emu.run_paced(host_micros as f64 / 1_000_000.0) as i32;
+    emu.render_frame();
+    executed
+}
+
+/// Microseconds the host should sleep before the next emu_run_paced call.
+/// Returns 0 if pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_pace_sleep_micros")]
+pub extern "C" fn emu_pace_sleep_micros(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    (emu.pace_sleep_secs() * 1_000_000.0) as u64
+}
+
+/// Get the size needed for a save state buffer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_state_size")]
+pub extern "C" fn emu_save_state_size(emu: *const SyncEmu) -> usize {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.save_state_size()
+}
+
+/// Save emulator state to a buffer.
+/// Returns bytes written on success, negative error code on failure.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_save_state")]
+pub extern "C" fn emu_save_state(emu: *const SyncEmu, out: *mut u8, cap: usize) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
+
+    match emu.save_state(buffer) {
+        Ok(size) => size as i32,
+        Err(code) => code,
+    }
+}
+
+/// Hash of the full machine state (everything a save state captures).
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_state_hash")]
+pub extern "C" fn emu_state_hash(emu: *const SyncEmu) -> u64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.state_hash()
+}
+
+/// Diagnostic sink that buffers a bundle and hands it to a C callback on flush
+struct CallbackSink {
+    cb: extern "C" fn(*const u8, usize, *mut std::ffi::c_void),
+    user: *mut std::ffi::c_void,
+    buf: Vec<u8>,
+}
+
+// The user pointer is only passed back to the caller's callback
+unsafe impl Send for CallbackSink {}
+
+impl std::io::Write for CallbackSink {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buf.is_empty() {
+            (self.cb)(self.buf.as_ptr(), self.buf.len(), self.user);
+            self.buf.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Set the diagnostic bundle sink; each call receives one complete bundle.
+/// Arms one automatic bundle on the next internal error or protection violation.
+/// Pass NULL to remove the sink.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_diagnostic_sink")]
+pub extern "C" fn emu_set_diagnostic_sink(
+    emu: *mut SyncEmu,
+    cb: Option<extern "C" fn(*const u8, usize, *mut std::ffi::c_void)>,
+    user: *mut std::ffi::c_void,
+) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let sink = cb.map(|cb| Box::new(CallbackSink { cb, user, buf: Vec::new() }) as Box<dyn std::io::Write + Send>);
+    emu.set_diagnostic_sink(sink);
+}
+
+/// Write a user-requested diagnostic bundle to the sink.
+/// Returns bytes written, -1 on null pointer, -2 if no sink is set, -4 if
+/// writing the bundle failed.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_dump_diagnostics")]
+pub extern "C" fn emu_dump_diagnostics(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.dump_diagnostics() {
+        Ok(bytes) => bytes as i32,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => -2,
+        Err(_) => -4,
+    }
+}
+
+/// Flash persistence sink that hands each dirty sector to a C callback
+struct CallbackFlashSink {
+    cb: extern "C" fn(u32, *const u8, usize, *mut std::ffi::c_void) -> i32,
+    user: *mut std::ffi::c_void,
+}
+
+// The user pointer is only passed back to the caller's callback
+unsafe impl Send for CallbackFlashSink {}
+
+impl FlashSink for CallbackFlashSink {
+    fn write_sector(&mut self, offset: u32, data: &[u8]) -> std::io::Result<()> {
+        match (self.cb)(offset, data.as_ptr(), data.len(), self.user) {
+            0 => Ok(()),
+            code => Err(std::io::Error::other(format!("flash sink callback returned {}", code))),
+        }
+    }
+}
+
+/// Flash persistence policy from FFI arguments (interval 0 = no periodic flush)
+fn persist_policy(interval_ms: u32, on_write_complete: i32) -> PersistPolicy {
+    PersistPolicy {
+        interval_secs: (interval_ms > 0).then(|| interval_ms as f64 / 1000.0),
+        on_write_complete: on_write_complete != 0,
+    }
+}
+
+/// Flush modified flash sectors to a callback automatically.
+/// The callback returns 0 on success; on failure sectors are retried later.
+/// Pass NULL to turn persistence off.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_flash_persistence")]
+pub extern "C" fn emu_set_flash_persistence(
+    emu: *mut SyncEmu,
+    cb: Option<extern "C" fn(u32, *const u8, usize, *mut std::ffi::c_void) -> i32>,
+    user: *mut std::ffi::c_void,
+    interval_ms: u32,
+    on_write_complete: i32,
+) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let sink = cb.map(|cb| Box::new(CallbackFlashSink { cb, user }) as Box<dyn FlashSink + Send>);
+    emu.set_flash_persistence(sink, persist_policy(interval_ms, on_write_complete));
+}
+
+/// Flush modified flash sectors in place into an image file automatically.
+/// The path is a null-terminated UTF-8 string naming an existing 4MB image.
+/// Returns 0 on success, -1 on null pointer, -4 if the file cannot be opened.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_flash_persistence_file")]
+pub extern "C" fn emu_set_flash_persistence_file(
+    emu: *mut SyncEmu,
+    path: *const c_char,
+    interval_ms: u32,
+    on_write_complete: i32,
+) -> i32 {
+    if emu.is_null() || path.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let path = match unsafe { std::ffi::CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path,
+        Err(_) => return -4,
+    };
+    let sink = match FileFlashSink::open(std::path::Path::new(path)) {
+        Ok(sink) => sink,
+        Err(_) => return -4,
+    };
+
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_flash_persistence(Some(Box::new(sink)), persist_policy(interval_ms, on_write_complete));
+    0
+}
+
+/// Write modified flash sectors to the persistence sink now.
+/// Returns sectors written, -1 on null pointer, -2 if the sink failed.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_flush_flash")]
+pub extern "C" fn emu_flush_flash(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.flush_flash() {
+        Ok(sectors) => sectors as i32,
+        Err(_) => -2,
+    }
+}
+
+/// Number of 64KB flash sectors modified since they were last exported
+/// or flushed. Returns -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_flash_dirty_count")]
+pub extern "C" fn emu_flash_dirty_count(emu: *const SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.flash_dirty_count() as i32
+}
+
+/// Copy modified 64KB flash sectors into `out` (cap / 65536 of them, lowest
+/// first) and their byte offsets into the image into `offsets`, which must
+/// hold as many entries. Exported sectors are no longer dirty; the rest are
+/// left for the next call. Returns sectors copied, -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_flash_export_dirty")]
+pub extern "C" fn emu_flash_export_dirty(emu: *mut SyncEmu, out: *mut u8, cap: usize, offsets: *mut u32) -> i32 {
+    if emu.is_null() || out.is_null() || offsets.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let sectors = emu.export_dirty_flash(cap / FLASH_DIRTY_SECTOR_SIZE);
+    let out = unsafe { slice::from_raw_parts_mut(out, sectors.len() * FLASH_DIRTY_SECTOR_SIZE) };
+    let offsets = unsafe { slice::from_raw_parts_mut(offsets, sectors.len()) };
+    for (i, (offset, data)) in sectors.iter().enumerate() {
+        offsets[i] = *offset;
+        out[i * FLASH_DIRTY_SECTOR_SIZE..(i + 1) * FLASH_DIRTY_SECTOR_SIZE].copy_from_slice(data);
+    }
+    sectors.len() as i32
+}
+
+/// Callback pointer and user data for runaway reports
+struct RunawayNotifier {
+    cb: extern "C" fn(i32, u32, *const c_char, *mut std::ffi::c_void),
+    user: *mut std::ffi::c_void,
+}
+
+// The user pointer is only passed back to the caller's callback
+unsafe impl Send for RunawayNotifier {}
+
+impl RunawayNotifier {
+    fn notify(&self, report: &RunawayReport) {
+        let context = std::ffi::CString::new(report.context.replace('\0', "")).unwrap_or_default();
+        (self.cb)(report.kind.id(), report.pc, context.as_ptr(), self.user);
+    }
+}
+
+/// Enable the runaway-execution watchdog (stuck loop with interrupts
+/// disabled, or repeated reboots). `stuck_ms` is emulated time at 48MHz
+/// before a stuck loop is reported (0 = default); pass NULL to disable.
+/// The callback runs inside emu_run_cycles with the emulator locked and must
+/// not call back into it; the context string is only valid during the call.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_runaway_watchdog")]
+pub extern "C" fn emu_set_runaway_watchdog(
+    emu: *mut SyncEmu,
+    cb: Option<extern "C" fn(i32, u32, *const c_char, *mut std::ffi::c_void)>,
+    user: *mut std::ffi::c_void,
+    stuck_ms: u32,
+) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let Some(cb) = cb else {
+        emu.set_runaway_watchdog(None, None);
+        return;
+    };
+    let mut config = RunawayConfig::default();
+    if stuck_ms > 0 {
+        config.stuck_cycles = stuck_ms as u64 * 48_000;
+    }
+    let notifier = RunawayNotifier { cb, user };
+    let callback = move |report: &RunawayReport| notifier.notify(report);
+    emu.set_runaway_watchdog(Some(config), Some(Box::new(callback)));
+}
+
+/// Enter exam mode (Press-to-Test) by simulating [left]+[right]+[ON] from a power cycle.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_enter_exam_mode")]
+pub extern "C" fn emu_enter_exam_mode(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.enter_exam_mode();
+}
+
+/// Leave exam mode (clears the OS flag too, if its location was set).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_exit_exam_mode")]
+pub extern "C" fn emu_exit_exam_mode(emu: *mut SyncEmu) {
+    if emu.is_null() {
+        return;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.exit_exam_mode();
+}
+
+/// Exam mode status: bit 0 = active, bit 1 = simulated LED lit. -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_exam_mode_status")]
+pub extern "C" fn emu_exam_mode_status(emu: *mut SyncEmu) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.exam_mode_active() as i32 | (emu.exam_led_on() as i32) << 1
+}
+
+/// Set where the OS records exam mode (flag byte address and bit).
+/// A bit outside 0-7 clears the location. Returns 0, or -1 on null pointer.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_exam_flag")]
+pub extern "C" fn emu_set_exam_flag(emu: *mut SyncEmu, addr: u32, bit: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let flag = u8::try_from(bit).ok().and_then(|bit| ExamFlag::new(addr, bit));
+    emu.set_exam_flag(flag);
+    0
+}
+
+/// Load emulator state from a buffer.
+/// Returns 0 on success, negative error code on failure.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_load_state")]
+pub extern "C" fn emu_load_state(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let buffer = unsafe { slice::from_raw_parts(data, len) };
+
+    match emu.load_state(buffer) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// Record rewind history: a snapshot every `interval_frames` LCD frames,
+/// keeping the newest `capacity`. Either one 0 stops and discards it.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_rewind")]
+pub extern "C" fn emu_set_rewind(emu: *mut SyncEmu, interval_frames: u32, capacity: u32) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    let config = (interval_frames > 0 && capacity > 0)
+        .then_some(RewindConfig { interval_frames, capacity: capacity as usize });
+    emu.set_rewind(config);
+}
+
+/// Step back at least `frames` LCD frames (or as far as the history goes).
+/// Returns: frames rewound (>=0), or negative error code
+/// Error codes: -1 = null pointer, not recording or no snapshot yet
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_rewind")]
+pub extern "C" fn emu_rewind(emu: *mut SyncEmu, frames: u32) -> i64 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.rewind(frames) {
+        Ok(rewound) => rewound as i64,
+        Err(code) => code as i64,
+    }
+}
+
+/// Set the calculator clock to `unix_secs` (local time, as it should be
+/// shown). Stops host clock tracking.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_rtc_time")]
+pub extern "C" fn emu_set_rtc_time(emu: *mut SyncEmu, unix_secs: i64) {
+    if emu.is_null() {
+        return;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    emu.set_rtc_time(unix_secs);
+}
+
+/// Keep the calculator clock on the host clock plus `utc_offset_secs`, or
+/// stop with `enabled` = 0.
+/// Returns 0 on success, -1 if emulator pointer is null, the host has no clock
+/// or deterministic mode is on.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_set_rtc_host_sync")]
+pub extern "C" fn emu_set_rtc_host_sync(emu: *mut SyncEmu, enabled: i32, utc_offset_secs: i32) -> i32 {
+    if emu.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if emu.set_rtc_host_sync((enabled != 0).then_some(utc_offset_secs)) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Calculator clock as Unix seconds.
+/// Returns 0 if emulator pointer is null.
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_rtc_time")]
+pub extern "C" fn emu_rtc_time(emu: *const SyncEmu) -> i64 {
+    if emu.is_null() {
+        return 0;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let emu = sync_emu.inner.lock().unwrap();
+    emu.rtc_time()
+}
+
+// ============================================================
+// Backend API (for single-backend builds without bridge)
+// ============================================================
+
+/// Get available backends (comma-separated list).
+/// For Rust-only builds, returns "rust".
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_get_available() -> *const c_char {
+    static BACKENDS: &[u8] = b"rust\0";
+    BACKENDS.as_ptr() as *const c_char
+}
+
+/// Get current backend name.
+/// For Rust-only builds, returns "rust".
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_get_current() -> *const c_char {
+    static RUST: &[u8] = b"rust\0";
+    RUST.as_ptr() as *const c_char
+}
+
+/// Set backend by name.
+/// For Rust-only builds, only "rust" is valid.
+/// Returns 0 on success, -1 on failure.
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_set(name: *const c_char) -> i32 {
+    if name.is_null() {
+        return -1;
+    }
+    let name_str = unsafe { std::ffi::CStr::from_ptr(name) };
+    if name_str.to_bytes() == b"rust" {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Get number of available backends.
+/// For Rust-only builds, returns 1.
+#[no_mangle]
+#[cfg(not(feature = "ios_prefixed"))]
+pub extern "C" fn emu_backend_count() -> i32 {
+    1
+}
+
+/// Boot to the home screen and capture a warm-boot snapshot into the buffer.
+/// Runs at most max_cycles waiting for the OS to become idle.
+/// Returns bytes written, or negative error code
+/// (-10 = ROM not loaded, -101 = buffer too small, -106 = boot did not reach idle).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_capture_warm_boot")]
+pub extern "C" fn emu_capture_warm_boot(emu: *mut SyncEmu, out: *mut u8, cap: usize, max_cycles: u64) -> i32 {
+    if emu.is_null() || out.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    if cap < emu.save_state_size() {
+        return -101;
+    }
+    match emu.capture_warm_boot_state(max_cycles) {
+        Ok(snapshot) => {
+            let buffer = unsafe { slice::from_raw_parts_mut(out, cap) };
+            buffer[..snapshot.len()].copy_from_slice(&snapshot);
+            snapshot.len() as i32
+        }
+        Err(code) => code,
+    }
+}
+
+/// Start from a warm-boot snapshot, skipping the ROM boot sequence.
+/// The ROM must be loaded first. Returns 0 on success, negative error code on failure
+/// (-107 = not a warm-boot snapshot, plus load_state errors).
+#[cfg_attr(not(feature = "ios_prefixed"), no_mangle)]
+#[cfg_attr(feature = "ios_prefixed", export_name = "rust_emu_boot_warm")]
+pub extern "C" fn emu_boot_warm(emu: *mut SyncEmu, data: *const u8, len: usize) -> i32 {
+    if emu.is_null() || data.is_null() {
+        return -1;
+    }
+
+    let sync_emu = unsafe { &*emu };
+    let snapshot = unsafe { slice::from_raw_parts(data, len) };
+    let mut emu = sync_emu.inner.lock().unwrap();
+    match emu.boot_from_warm_state(snapshot) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_destroy() {
+        let emu = emu_create();
+        assert!(!emu.is_null());
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        let emu = emu_create();
+        let mut w: i32 = 0;
+        let mut h: i32 = 0;
+        let fb = emu_framebuffer(emu, &mut w, &mut h);
+
+        assert!(!fb.is_null());
+        assert_eq!(w, 320);
+        assert_eq!(h, 240);
+
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        let emu = emu_create();
+        // Without ROM, should return 0
+        let executed = emu_run_cycles(emu, 1000);
+        assert_eq!(executed, 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_key_input() {
+        let emu = emu_create();
+        emu_set_key(emu, 0, 0, 1);
+        emu_set_key(emu, 0, 0, 0);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_on_key_wakes_halted_cpu() {
+        let emu = emu_create();
+        // DI, HALT, NOP, NOP
+        let rom = [0xF3, 0x76, 0x00, 0x00];
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+        emu_set_on_key(emu, 1);
+        emu_set_on_key(emu, 0);
+        emu_run_cycles(emu, 100);
+        assert!(unsafe { &*emu }.inner.lock().unwrap().is_halted());
+
+        emu_set_on_key(emu, 1);
+        emu_run_cycles(emu, 20);
+        emu_set_on_key(emu, 0);
+        let pc = unsafe { &*emu }.inner.lock().unwrap().pc();
+        assert!(pc > 2);
+        emu_set_on_key(ptr::null_mut(), 1);
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_thread_safety() {
+        use std::thread;
+
+        let emu = emu_create();
+
+        // Load a minimal ROM so we can run cycles
+        let rom = vec![0x00, 0x00, 0x76]; // NOP, NOP, HALT
+        emu_load_rom(emu, rom.as_ptr(), rom.len());
+
+        // Wrap in Arc for sharing across threads
+        let emu_ptr = emu as usize; // Convert to usize for Send
+
+        // Spawn threads that access the emulator concurrently
+        let handles: Vec<_> = (0..4).map(|i| {
+            thread::spawn(move || {
+                let emu = emu_ptr as *mut SyncEmu;
+                for _ in 0..100 {
+                    if i % 2 == 0 {
+                        emu_set_key(emu, (i % 8) as i32, 0, 1);
+                        emu_set_key(emu, (i % 8) as i32, 0, 0);
+                    } else {
+                        emu_run_cycles(emu, 10);
+                    }
+                }
+            })
+        }).collect();
+
+        // Wait for all threads
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        emu_destroy(emu);
+    }
+
+    #[test]
+    fn test_header_matches_exports() {
+        let problems = crate::ffi_header::check(include_str!("ffi.rs"), include_str!("../include/emu.h"));
+        assert!(problems.is_empty(), "emu.h out of date:\n{}", problems.join("\n"));
+    }
+}
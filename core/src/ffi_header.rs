@@ -0,0 +1,166 @@
+//! Check that `include/emu.h` declares exactly what `ffi.rs` exports
+//!
+//! Shared by the build script, which fails the build when the two drift
+//! apart, and by `ffi::tests`. Signatures are compared on C base types and
+//! pointer depth, so `const` and parameter names may differ.
+
+/// An exported function as (name, parameter types, return type), each
+/// type reduced to its C base type and pointer depth
+type Signature = (String, Vec<(String, usize)>, (String, usize));
+
+/// Split at top-level commas (not inside parentheses or angle brackets)
+fn split_params(params: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' if !params[..i].ends_with('-') => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(params[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(params[start..].trim());
+    out.retain(|p| !p.is_empty());
+    out
+}
+
+fn rust_type(ty: &str) -> (String, usize) {
+    let ty = ty.trim();
+    if ty.starts_with("Option<extern") {
+        return ("callback".to_string(), 0);
+    }
+    if let Some(inner) = ty.strip_prefix("*const ").or_else(|| ty.strip_prefix("*mut ")) {
+        let (base, depth) = rust_type(inner);
+        return (base, depth + 1);
+    }
+    let base = match ty {
+        "SyncEmu" => "Emu",
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "usize" => "size_t",
+        "f64" => "double",
+        "f32" => "float",
+        "c_char" => "char",
+        "c_void" | "std::ffi::c_void" | "()" => "void",
+        // emu.h calls the eval record EmuEvalResult
+        "EvalRecord" => "EvalResult",
+        other => other.rsplit("::").next().unwrap(),
+    };
+    (base.to_string(), 0)
+}
+
+fn c_type(decl: &str, named: bool) -> (String, usize) {
+    let depth = decl.matches('*').count();
+    let mut words: Vec<&str> = decl
+        .split(|c: char| c == '*' || c.is_whitespace())
+        .filter(|w| !w.is_empty() && *w != "const")
+        .collect();
+    if named && words.len() > 1 {
+        words.pop();
+    }
+    let base = match words.join(" ").as_str() {
+        "int" => "int32_t".to_string(),
+        // header structs are the Rust #[repr(C)] types with an Emu prefix
+        t if t.len() > 3 && t.starts_with("Emu") && t[3..].starts_with(|c: char| c.is_ascii_uppercase()) => {
+            t[3..].to_string()
+        }
+        t if t.starts_with("emu_") && t.ends_with("_t") => "callback".to_string(),
+        t => t.to_string(),
+    };
+    (base, depth)
+}
+
+fn rust_exports(source: &str) -> Vec<(Signature, String)> {
+    let mut out = Vec::new();
+    let mut rest = source;
+    while let Some(at) = rest.find("pub extern \"C\" fn ") {
+        let attrs = rest[..at].rsplit("\n\n").next().unwrap_or("").to_string();
+        let decl = &rest[at + "pub extern \"C\" fn ".len()..];
+        let open = decl.find('(').unwrap();
+        let name = decl[..open].to_string();
+        let mut depth = 0;
+        let close = open + decl[open..].char_indices().find(|&(_, c)| {
+            depth += match c { '(' => 1, ')' => -1, _ => 0 };
+            depth == 0
+        }).unwrap().0;
+        let params = split_params(&decl[open + 1..close])
+            .into_iter()
+            .map(|p| rust_type(p.split_once(':').unwrap().1))
+            .collect();
+        let tail = &decl[close + 1..decl.find('{').unwrap()];
+        let ret = rust_type(tail.trim().strip_prefix("->").unwrap_or("()"));
+        out.push(((name, params, ret), attrs));
+        rest = &decl[close..];
+    }
+    out
+}
+
+fn header_decls(header: &str) -> Vec<Signature> {
+    let code: String = header.lines().map(|l| l.split("//").next().unwrap()).collect::<Vec<_>>().join(" ");
+    code.split(';')
+        .filter_map(|stmt| {
+            let stmt = stmt.trim();
+            let open = stmt.find("emu_").filter(|&i| stmt[i..].contains('('))?;
+            let paren = open + stmt[open..].find('(')?;
+            let name = stmt[open..paren].trim();
+            if stmt.starts_with("typedef") || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return None;
+            }
+            let params = stmt[paren + 1..stmt.rfind(')')?].trim();
+            let params = if params == "void" { Vec::new() } else {
+                split_params(params).into_iter().map(|p| c_type(p, true)).collect()
+            };
+            let ret_start = stmt[..open].rfind(['}', '{']).map_or(0, |i| i + 1);
+            Some((name.to_string(), params, c_type(&stmt[ret_start..open], false)))
+        })
+        .collect()
+}
+
+/// Everything wrong with `header` as a declaration of the exports in
+/// `ffi_source`; empty when they match
+pub fn check(ffi_source: &str, header: &str) -> Vec<String> {
+    let exports = rust_exports(ffi_source);
+    let decls = header_decls(header);
+    let mut problems = Vec::new();
+    for ((name, params, ret), attrs) in &exports {
+        let prefixed = attrs.contains(&format!("export_name = \"rust_{}\"", name))
+            && attrs.contains("#[cfg_attr(not(feature = \"ios_prefixed\"), no_mangle)]");
+        let unprefixed_only = attrs.contains("#[cfg(not(feature = \"ios_prefixed\"))]") && attrs.contains("#[no_mangle]");
+        if !prefixed && !unprefixed_only {
+            problems.push(format!("{}: export attributes", name));
+        }
+        match decls.iter().find(|(decl, _, _)| decl == name) {
+            None => problems.push(format!("{}: not in emu.h", name)),
+            Some((_, decl_params, decl_ret)) => {
+                if decl_params != params || decl_ret != ret {
+                    problems.push(format!("{}: emu.h has {:?} -> {:?}, Rust {:?} -> {:?}", name, decl_params, decl_ret, params, ret));
+                }
+            }
+        }
+    }
+    for (name, _, _) in &decls {
+        if !exports.iter().any(|((export, _, _), _)| export == name) {
+            problems.push(format!("{}: declared but not exported", name));
+        }
+    }
+    let version = ffi_source
+        .split("pub const EMU_API_VERSION: u32 = ")
+        .nth(1)
+        .and_then(|rest| rest.split(';').next());
+    match version {
+        Some(version) if header.contains(&format!("#define EMU_API_VERSION {}", version)) => {}
+        _ => problems.push("EMU_API_VERSION differs".to_string()),
+    }
+    if exports.len() < 100 || decls.len() < 100 {
+        problems.push(format!("parsed only {} exports and {} declarations", exports.len(), decls.len()));
+    }
+    problems
+}
@@ -0,0 +1,94 @@
+//! Background flash persistence
+//!
+//! Archived variables live in flash, which normally only reaches disk when
+//! the host saves explicitly. With persistence enabled the emulator writes
+//! modified 64KB sectors to a `FlashSink` on its own: every N emulated
+//! seconds, and/or as soon as a burst of flash writes (an archive operation)
+//! has finished. Checks run at the start of each `Emu::run_cycles()` call.
+//!
+//! A write burst counts as finished when a whole `run_cycles()` call passes
+//! without any flash modification.
+
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Destination for dirty flash sectors
+pub trait FlashSink {
+    /// Store `data` at byte `offset` of the 4MB flash image
+    fn write_sector(&mut self, offset: u32, data: &[u8]) -> io::Result<()>;
+
+    /// Called once after each batch of sectors
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes sectors in place into a flash image file.
+///
+/// The file should hold the image the emulator was loaded from (a copy, if
+/// the ROM is memory-mapped: a mapped file must not be modified).
+pub struct FileFlashSink {
+    file: File,
+}
+
+impl FileFlashSink {
+    /// Open an existing image file for in-place updates
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = std::fs::OpenOptions::new().write(true).open(path)?;
+        Ok(Self { file })
+    }
+}
+
+impl FlashSink for FileFlashSink {
+    fn write_sector(&mut self, offset: u32, data: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(data)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+/// When dirty sectors are flushed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PersistPolicy {
+    /// Flush at most this many emulated seconds after a modification
+    pub interval_secs: Option<f64>,
+    /// Flush as soon as a burst of flash writes has finished
+    pub on_write_complete: bool,
+}
+
+/// Persistence state kept by the emulator
+pub(crate) struct FlashPersistence {
+    pub(crate) sink: Box<dyn FlashSink + Send>,
+    pub(crate) policy: PersistPolicy,
+    /// Emulated seconds since the last flush
+    pub(crate) elapsed_secs: f64,
+    /// Flash write generation seen at the previous check
+    pub(crate) last_generation: u64,
+    /// `total_cycles` at the previous check
+    pub(crate) last_cycles: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_sink_writes_in_place() {
+        let path = std::env::temp_dir()
+            .join(format!("emu_core_flash_sink_test_{}.rom", std::process::id()));
+        std::fs::write(&path, [0u8; 16]).unwrap();
+
+        let mut sink = FileFlashSink::open(&path).unwrap();
+        sink.write_sector(4, &[1, 2, 3]).unwrap();
+        sink.finish().unwrap();
+        drop(sink);
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(&data[..8], &[0, 0, 0, 0, 1, 2, 3, 0]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+//! 64-bit FNV-1a hashing
+//!
+//! The one FNV-1a implementation behind `Emu::state_hash`, the save state
+//! ROM hash, the frame hash and the boot code hash. Not collision resistant;
+//! it only tells states and images apart quickly, with values that are
+//! stable across runs and platforms.
+
+const OFFSET: u64 = 0xcbf29ce484222325;
+const PRIME: u64 = 0x100000001b3;
+
+/// Incremental 64-bit FNV-1a
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Self(OFFSET)
+    }
+
+    /// Add bytes
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(PRIME);
+        }
+    }
+
+    /// Add `len` zero bytes without materializing them
+    pub fn write_zeros(&mut self, len: usize) {
+        for _ in 0..len {
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    /// Hash of the bytes added so far
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// FNV-1a hash of `bytes`
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_write_zeros_matches_zero_bytes() {
+        let mut a = Fnv1a::new();
+        a.write(b"x");
+        a.write_zeros(100);
+        let mut b = Fnv1a::new();
+        b.write(b"x");
+        b.write(&[0; 100]);
+        assert_eq!(a.finish(), b.finish());
+    }
+}
@@ -0,0 +1,148 @@
+//! Continuous frame hashing
+//!
+//! Long automated runs can compare a CRC32 per rendered frame against a
+//! reference run instead of storing images. Hashes cover the framebuffer as
+//! `render_frame()` leaves it (ARGB8888, little-endian bytes, row by row),
+//! optionally restricted to a sub-rectangle to ignore parts of the screen
+//! that legitimately differ, such as a clock in the status bar.
+
+use crate::fnv::Fnv1a;
+
+/// Queued hashes kept for the host; older ones are dropped when it falls behind
+pub const FRAME_HASH_QUEUE_SIZE: usize = 256;
+
+/// Region of the screen to hash, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashRect {
+    /// Left edge
+    pub x: usize,
+    /// Top edge
+    pub y: usize,
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+}
+
+impl HashRect {
+    /// Clip to a `width` x `height` screen. None if nothing is left.
+    pub fn clip(&self, width: usize, height: usize) -> Option<HashRect> {
+        let x_end = (self.x.saturating_add(self.width)).min(width);
+        let y_end = (self.y.saturating_add(self.height)).min(height);
+        (self.x < x_end && self.y < y_end).then_some(HashRect {
+            x: self.x,
+            y: self.y,
+            width: x_end - self.x,
+            height: y_end - self.y,
+        })
+    }
+}
+
+/// Hash of one rendered frame
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHash {
+    /// `frames_rendered()` count of the hashed frame
+    pub frame: u64,
+    /// `total_cycles()` when it was rendered
+    pub cycle: u64,
+    /// CRC32 (IEEE) of the hashed pixels
+    pub crc: u32,
+}
+
+const fn make_crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC_TABLE: [u32; 256] = make_crc_table();
+
+/// Incremental CRC32 (IEEE 802.3, as used by zlib and PNG)
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    /// Start a new checksum
+    pub fn new() -> Self {
+        Crc32(0xFFFFFFFF)
+    }
+
+    /// Add bytes to the checksum
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = CRC_TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    /// Final CRC of the bytes added so far
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 64-bit FNV-1a hash of a whole ARGB framebuffer (little-endian bytes).
+/// Stable across runs and platforms, so tests can pin expected screens.
+pub fn hash_frame(pixels: &[u32]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    for pixel in pixels {
+        hasher.write(&pixel.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+/// CRC32 of `rect` within a `width`-pixel-wide ARGB framebuffer.
+/// The rectangle must already be clipped to the framebuffer.
+pub fn hash_pixels(pixels: &[u32], width: usize, rect: HashRect) -> u32 {
+    let mut crc = Crc32::new();
+    for row in rect.y..rect.y + rect.height {
+        let start = row * width + rect.x;
+        for &pixel in &pixels[start..start + rect.width] {
+            crc.update(&pixel.to_le_bytes());
+        }
+    }
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_check_value() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_rect_hash_ignores_outside_pixels() {
+        let mut pixels = vec![0xFF000000u32; 4 * 3];
+        let rect = HashRect { x: 1, y: 1, width: 2, height: 5 }.clip(4, 3).unwrap();
+        assert_eq!(rect, HashRect { x: 1, y: 1, width: 2, height: 2 });
+
+        let before = hash_pixels(&pixels, 4, rect);
+        pixels[0] = 0xFFFFFFFF;
+        assert_eq!(hash_pixels(&pixels, 4, rect), before);
+        pixels[4 + 2] = 0xFFFFFFFF;
+        assert_ne!(hash_pixels(&pixels, 4, rect), before);
+
+        assert_eq!(HashRect { x: 4, y: 0, width: 1, height: 1 }.clip(4, 3), None);
+    }
+}
@@ -0,0 +1,563 @@
+//! GDB remote serial protocol stub
+//!
+//! Lets GDB attach to the emulator over TCP (`cargo run --example debug --
+//! gdb 1234`, then `target remote :1234`), set breakpoints and watchpoints,
+//! read and write memory and registers, and step or continue the CPU. It is
+//! built on the debugger core (`crate::debug`), so breakpoints set from GDB
+//! behave like ones set through the FFI.
+//!
+//! Registers are described to GDB with a target description (`target.xml`)
+//! in `debug::Register::ALL` order. 24-bit registers are sent as 32 bits
+//! and the rest at their own width, all little-endian. The description has
+//! no `<architecture>`, so a generic GDB needs `set architecture` for
+//! disassembly; `ez80-none-elf-gdb` picks it up from the ELF.
+//!
+//! Supported packets: `?`, `g`/`G`, `p`/`P`, `m`/`M`, `c`, `s`, `Z0`-`Z4`
+//! and `z0`-`z4` (software and hardware breakpoints are the same thing here;
+//! `Z2`/`Z3`/`Z4` are write/read/access watchpoints), `qSupported`,
+//! `qXfer:features:read`, `QStartNoAckMode`, thread queries for a single
+//! thread, `D` and `k`. Ctrl-C interrupts a continue. Anything else gets
+//! the empty "unsupported" reply.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::debug::{access, AccessKind, DebugEvent, DebugEventKind, Register};
+use crate::emu::Emu;
+
+/// Cycles run between checks for Ctrl-C while continuing
+const CONTINUE_SLICE_CYCLES: u32 = 100_000;
+/// Largest packet we accept, advertised in `qSupported`
+const PACKET_SIZE: usize = 0x1000;
+/// Ctrl-C from GDB while the target runs
+const INTERRUPT: u8 = 0x03;
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+/// Byte stream to GDB
+pub trait Connection {
+    /// Block for the next byte; None at end of stream
+    fn read_byte(&mut self) -> io::Result<Option<u8>>;
+    /// Send bytes
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()>;
+    /// Without blocking, check whether GDB sent Ctrl-C (or hung up)
+    fn poll_interrupt(&mut self) -> io::Result<bool>;
+}
+
+impl Connection for TcpStream {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        Ok((self.read(&mut byte)? == 1).then_some(byte[0]))
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_all(bytes)
+    }
+
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.set_nonblocking(true)?;
+        let mut byte = [0];
+        let result = match self.read(&mut byte) {
+            Ok(0) => Ok(true),
+            Ok(_) => Ok(byte[0] == INTERRUPT),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        };
+        self.set_nonblocking(false)?;
+        result
+    }
+}
+
+/// Listen on `addr` and serve GDB sessions one at a time, until one kills
+/// the target
+pub fn serve(emu: &mut Emu, addr: impl ToSocketAddrs) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        stream.set_nodelay(true)?;
+        if GdbStub::new().run(emu, &mut stream)? == SessionEnd::Killed {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// How a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// GDB detached (`D`)
+    Detached,
+    /// GDB killed the target (`k`)
+    Killed,
+    /// The connection closed
+    Disconnected,
+}
+
+/// One GDB session's state
+#[derive(Debug, Default)]
+pub struct GdbStub {
+    no_ack: bool,
+    /// GDB's (Z type, address, kind) -> breakpoint or watchpoint ID
+    points: HashMap<(u8, u32, u32), u32>,
+    end: Option<SessionEnd>,
+}
+
+impl GdbStub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve packets until GDB detaches, kills or disconnects. Breakpoints
+    /// and watchpoints GDB left behind are removed.
+    pub fn run(&mut self, emu: &mut Emu, conn: &mut impl Connection) -> io::Result<SessionEnd> {
+        let end = loop {
+            let Some(packet) = self.read_packet(conn)? else {
+                break SessionEnd::Disconnected;
+            };
+            if let Some(reply) = self.handle(emu, &packet, &mut || conn.poll_interrupt().unwrap_or(true)) {
+                self.send_packet(conn, &reply)?;
+            }
+            // Both sides still ack the OK to QStartNoAckMode
+            if packet == "QStartNoAckMode" {
+                self.no_ack = true;
+            }
+            if let Some(end) = self.end {
+                break end;
+            }
+        };
+        self.clear_points(emu);
+        Ok(end)
+    }
+
+    /// Handle one packet's payload, returning the reply payload (None for
+    /// packets that get no reply). `interrupted` is polled while continuing.
+    pub fn handle(&mut self, emu: &mut Emu, packet: &str, interrupted: &mut dyn FnMut() -> bool) -> Option<String> {
+        let (cmd, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+        let reply = match cmd {
+            "?" => format!("S{:02x}", SIGTRAP),
+            "g" => Register::ALL.iter().map(|&reg| encode_register(reg, emu.register(reg))).collect(),
+            "G" => status(write_registers(emu, args)),
+            "p" => match register_at(args) {
+                Some(reg) => encode_register(reg, emu.register(reg)),
+                None => error(),
+            },
+            "P" => status(args.split_once('=').and_then(|(n, v)| {
+                emu.set_register(register_at(n)?, decode_le(v)?);
+                Some(())
+            })),
+            "m" => match parse_range(args) {
+                Some((addr, len)) => (0..len.min(PACKET_SIZE as u32 / 2))
+                    .map(|i| format!("{:02x}", emu.peek_byte(addr.wrapping_add(i) & 0xFFFFFF)))
+                    .collect(),
+                None => error(),
+            },
+            "M" => status(write_memory(emu, args)),
+            "c" | "s" => {
+                if let Some(addr) = parse_hex(args) {
+                    emu.set_register(Register::Pc, addr);
+                }
+                if cmd == "c" {
+                    self.resume(emu, interrupted)
+                } else {
+                    let event = emu.step_into();
+                    self.stop_reply(event.as_ref())
+                }
+            }
+            "Z" => status(self.insert_point(emu, args)),
+            "z" => status(self.remove_point(emu, args)),
+            "q" => query(args),
+            "Q" if args == "StartNoAckMode" => ok(),
+            "H" | "T" => ok(),
+            "D" => {
+                self.end = Some(SessionEnd::Detached);
+                ok()
+            }
+            "k" => {
+                self.end = Some(SessionEnd::Killed);
+                return None;
+            }
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    /// Run until a breakpoint or watchpoint, or until GDB interrupts
+    fn resume(&mut self, emu: &mut Emu, interrupted: &mut dyn FnMut() -> bool) -> String {
+        loop {
+            let ran = emu.run_cycles(CONTINUE_SLICE_CYCLES);
+            if let Some(event) = emu.take_debug_event() {
+                return self.stop_reply(Some(&event));
+            }
+            if ran == 0 || interrupted() {
+                return format!("S{:02x}", SIGINT);
+            }
+        }
+    }
+
+    /// `T05` with the watchpoint address for data watchpoints, else `S05`
+    fn stop_reply(&self, event: Option<&DebugEvent>) -> String {
+        let Some(event) = event.filter(|e| e.kind == DebugEventKind::Watchpoint && e.access != AccessKind::Execute) else {
+            return format!("S{:02x}", SIGTRAP);
+        };
+        let z_type = self.points.iter().find(|&(_, &id)| id == event.id).map(|(&(z, _, _), _)| z);
+        let kind = match (z_type, event.access) {
+            (Some(4), _) => "awatch",
+            (Some(3), _) | (None, AccessKind::Read) => "rwatch",
+            _ => "watch",
+        };
+        format!("T{:02x}{}:{:x};", SIGTRAP, kind, event.addr)
+    }
+
+    /// `Z type,addr,kind`: kind is the length for watchpoints
+    fn insert_point(&mut self, emu: &mut Emu, args: &str) -> Option<()> {
+        let key = parse_point(args)?;
+        if self.points.contains_key(&key) {
+            return Some(());
+        }
+        let (z_type, addr, kind) = key;
+        let end = addr.wrapping_add(kind.max(1) - 1);
+        let id = match z_type {
+            0 | 1 => emu.add_breakpoint(addr),
+            2 => emu.add_watchpoint(addr, end, access::WRITE)?,
+            3 => emu.add_watchpoint(addr, end, access::READ)?,
+            4 => emu.add_watchpoint(addr, end, access::READ | access::WRITE)?,
+            _ => return None,
+        };
+        self.points.insert(key, id);
+        Some(())
+    }
+
+    fn remove_point(&mut self, emu: &mut Emu, args: &str) -> Option<()> {
+        let key = parse_point(args)?;
+        if let Some(id) = self.points.remove(&key) {
+            remove(emu, key.0, id);
+        }
+        Some(())
+    }
+
+    fn clear_points(&mut self, emu: &mut Emu) {
+        for ((z_type, _, _), id) in self.points.drain() {
+            remove(emu, z_type, id);
+        }
+    }
+
+    /// Next valid packet's payload, acking it. None at end of stream.
+    fn read_packet(&self, conn: &mut impl Connection) -> io::Result<Option<String>> {
+        loop {
+            // Skip acks and stray Ctrl-C until the start of a packet
+            loop {
+                match conn.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'$') => break,
+                    Some(_) => {}
+                }
+            }
+            let mut payload = Vec::new();
+            loop {
+                match conn.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => payload.push(byte),
+                }
+            }
+            let (Some(hi), Some(lo)) = (conn.read_byte()?, conn.read_byte()?) else {
+                return Ok(None);
+            };
+            let valid = std::str::from_utf8(&[hi, lo]).ok().and_then(|s| u8::from_str_radix(s, 16).ok())
+                == Some(checksum(&payload));
+            if !self.no_ack {
+                conn.send(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&payload).into_owned()));
+            }
+        }
+    }
+
+    /// Send a packet, resending until GDB acks it
+    fn send_packet(&self, conn: &mut impl Connection, payload: &str) -> io::Result<()> {
+        let frame = format!("${}#{:02x}", payload, checksum(payload.as_bytes()));
+        loop {
+            conn.send(frame.as_bytes())?;
+            if self.no_ack {
+                return Ok(());
+            }
+            loop {
+                match conn.read_byte()? {
+                    None | Some(b'+') => return Ok(()),
+                    Some(b'-') => break,
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+}
+
+fn remove(emu: &mut Emu, z_type: u8, id: u32) {
+    if z_type <= 1 {
+        emu.remove_breakpoint(id);
+    } else {
+        emu.remove_watchpoint(id);
+    }
+}
+
+fn checksum(payload: &[u8]) -> u8 {
+    payload.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+fn ok() -> String {
+    "OK".to_string()
+}
+
+fn error() -> String {
+    "E01".to_string()
+}
+
+fn status(result: Option<()>) -> String {
+    result.map_or_else(error, |_| ok())
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+/// `addr,len`
+fn parse_range(s: &str) -> Option<(u32, u32)> {
+    let (addr, len) = s.split_once(',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+/// `type,addr,kind` (any `;cond` list is ignored)
+fn parse_point(s: &str) -> Option<(u8, u32, u32)> {
+    let s = s.split(';').next()?;
+    let mut fields = s.split(',');
+    let z_type = fields.next()?.parse().ok()?;
+    let addr = parse_hex(fields.next()?)? & 0xFFFFFF;
+    let kind = parse_hex(fields.next()?)?;
+    Some((z_type, addr, kind))
+}
+
+fn register_at(s: &str) -> Option<Register> {
+    Register::ALL.get(usize::from_str_radix(s, 16).ok()?).copied()
+}
+
+/// Bytes GDB uses for a register (24-bit registers are widened to 32)
+fn register_bytes(reg: Register) -> usize {
+    reg.bits().next_power_of_two() as usize / 8
+}
+
+fn encode_register(reg: Register, value: u32) -> String {
+    (0..register_bytes(reg)).map(|i| format!("{:02x}", (value >> (i * 8)) as u8)).collect()
+}
+
+/// Little-endian hex bytes to a value
+fn decode_le(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.len().is_multiple_of(2) || s.len() > 8 {
+        return None;
+    }
+    (0..s.len() / 2).try_fold(0u32, |value, i| {
+        let byte = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        Some(value | (byte as u32) << (i * 8))
+    })
+}
+
+fn write_registers(emu: &mut Emu, mut hex: &str) -> Option<()> {
+    for reg in Register::ALL {
+        let len = register_bytes(reg) * 2;
+        let value = decode_le(hex.get(..len)?)?;
+        emu.set_register(reg, value);
+        hex = &hex[len..];
+    }
+    Some(())
+}
+
+/// `addr,len:bytes`
+fn write_memory(emu: &mut Emu, args: &str) -> Option<()> {
+    let (range, data) = args.split_once(':')?;
+    let (addr, len) = parse_range(range)?;
+    if data.len() != len as usize * 2 {
+        return None;
+    }
+    for i in 0..len {
+        let byte = u8::from_str_radix(data.get(i as usize * 2..i as usize * 2 + 2)?, 16).ok()?;
+        emu.poke_byte(addr.wrapping_add(i) & 0xFFFFFF, byte);
+    }
+    Some(())
+}
+
+fn query(args: &str) -> String {
+    if args.starts_with("Supported") {
+        return format!("PacketSize={:x};qXfer:features:read+;QStartNoAckMode+", PACKET_SIZE);
+    }
+    if let Some(rest) = args.strip_prefix("Xfer:features:read:target.xml:") {
+        let Some((offset, len)) = parse_range(rest) else {
+            return error();
+        };
+        let xml = target_xml();
+        let start = (offset as usize).min(xml.len());
+        let end = start.saturating_add(len as usize).min(xml.len());
+        let more = if end < xml.len() { 'm' } else { 'l' };
+        return format!("{}{}", more, &xml[start..end]);
+    }
+    match args {
+        "Attached" => "1".to_string(),
+        "C" => "QC1".to_string(),
+        "fThreadInfo" => "m1".to_string(),
+        "sThreadInfo" => "l".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Target description listing `Register::ALL`
+fn target_xml() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n<feature name=\"org.calc.ez80.core\">\n",
+    );
+    for reg in Register::ALL {
+        let kind = match reg {
+            Register::Pc => " type=\"code_ptr\"",
+            Register::Sp => " type=\"data_ptr\"",
+            _ => "",
+        };
+        xml += &format!("  <reg name=\"{}\" bitsize=\"{}\"{}/>\n", reg.name(), register_bytes(reg) * 8, kind);
+    }
+    xml + "</feature>\n</target>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// GDB's side of a session, scripted
+    struct Scripted {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Scripted {
+        fn new(packets: &[&str]) -> Self {
+            let mut input = VecDeque::new();
+            for packet in packets {
+                let frame = format!("${}#{:02x}+", packet, checksum(packet.as_bytes()));
+                input.extend(frame.bytes());
+            }
+            Self { input, output: Vec::new() }
+        }
+
+        /// Reply payloads, in order
+        fn replies(&self) -> Vec<String> {
+            let text = String::from_utf8_lossy(&self.output);
+            text.split('$').skip(1).map(|frame| frame.split('#').next().unwrap().to_string()).collect()
+        }
+    }
+
+    impl Connection for Scripted {
+        fn read_byte(&mut self) -> io::Result<Option<u8>> {
+            Ok(self.input.pop_front())
+        }
+
+        fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.output.extend_from_slice(bytes);
+            Ok(())
+        }
+
+        fn poll_interrupt(&mut self) -> io::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    fn test_emu() -> Emu {
+        let mut rom = vec![0u8; 0x20];
+        let code: &[(usize, &[u8])] = &[
+            (0x00, &[0x5B, 0xC3, 0x05, 0x00, 0x00]), // JP.LIL 000005
+            (0x05, &[0x21, 0x00, 0x00, 0xD0]),       // LD HL,D00000
+            (0x09, &[0x3E, 0x42]),                   // LD A,42
+            (0x0B, &[0x77]),                         // LD (HL),A
+            (0x0C, &[0x18, 0xFE]),                   // JR $
+        ];
+        for &(addr, bytes) in code {
+            rom[addr..addr + bytes.len()].copy_from_slice(bytes);
+        }
+        let mut emu = Emu::new();
+        emu.load_rom(&rom).unwrap();
+        emu.powered_on = true;
+        emu
+    }
+
+    #[test]
+    fn test_session() {
+        let mut emu = test_emu();
+        let mut gdb = Scripted::new(&[
+            "qSupported:multiprocess+",
+            "?",
+            "Z0,9,1",
+            "c",
+            "p7",
+            "z0,9,1",
+            "Z2,d00000,1",
+            "c",
+            "s",
+            "md00000,2",
+            "Md00001,2:beef",
+            "md00000,3",
+            "P1=563412",
+            "p1",
+            "QStartNoAckMode",
+            "k",
+        ]);
+        let end = GdbStub::new().run(&mut emu, &mut gdb).unwrap();
+        assert_eq!(end, SessionEnd::Killed);
+
+        let replies = gdb.replies();
+        assert!(replies[0].starts_with("PacketSize="));
+        assert_eq!(replies[1], "S05");
+        assert_eq!(replies[2], "OK");
+        assert_eq!(replies[3], "S05");
+        assert_eq!(replies[4], "09000000");
+        assert_eq!(replies[5], "OK");
+        assert_eq!(replies[6], "OK");
+        assert_eq!(replies[7], "T05watch:d00000;");
+        assert_eq!(emu.pc(), 0x0C);
+        assert_eq!(replies[8], "S05");
+        assert_eq!(replies[9], "4200");
+        assert_eq!(replies[10], "OK");
+        assert_eq!(replies[11], "42beef");
+        assert_eq!(replies[12], "OK");
+        assert_eq!(replies[13], "56341200");
+        assert_eq!(replies[14], "OK");
+        assert_eq!(replies.len(), 15);
+        // Every packet was acked until no-ack mode (the qSupported reply has two '+' of its own)
+        assert_eq!(gdb.output.iter().filter(|&&b| b == b'+').count() - 2, 15);
+        // The watchpoint GDB left behind is gone
+        assert!(emu.run_until_break(10_000).is_none());
+    }
+
+    #[test]
+    fn test_registers_and_target_description() {
+        let mut emu = test_emu();
+        let mut stub = GdbStub::new();
+        let mut handle = |packet: &str| stub.handle(&mut emu, packet, &mut || false).unwrap();
+
+        let regs = handle("g");
+        let widths: usize = Register::ALL.iter().map(|&r| register_bytes(r) * 2).sum();
+        assert_eq!(regs.len(), widths);
+        // Write them back with PC moved to the LD A,42
+        let pc_at: usize = Register::ALL[..7].iter().map(|&r| register_bytes(r) * 2).sum();
+        let mut new_regs = regs.clone();
+        new_regs.replace_range(pc_at..pc_at + 8, "09000000");
+        assert_eq!(handle(&format!("G{}", new_regs)), "OK");
+        assert_eq!(handle("G00"), "E01");
+        assert_eq!(handle("s"), "S05");
+        assert_eq!(handle("p0")[2..4], *"42");
+
+        let first = handle("qXfer:features:read:target.xml:0,20");
+        assert!(first.starts_with("m<?xml"));
+        let all = handle("qXfer:features:read:target.xml:0,1000");
+        assert!(all.starts_with('l') && all.contains("<reg name=\"pc\" bitsize=\"32\" type=\"code_ptr\"/>"));
+        assert_eq!(handle("vMustReplyEmpty"), "");
+        assert_eq!(handle("pff"), "E01");
+    }
+}
@@ -0,0 +1,246 @@
+//! Golden-screenshot regression tests
+//!
+//! Boots the ROM named by `EMU_TEST_ROM`, runs a scripted input sequence and
+//! compares the rendered screen against a stored golden image in
+//! `tests/golden/<name>.ppm`. Tests are skipped when `EMU_TEST_ROM` is unset,
+//! so `cargo test` stays green on machines without a ROM.
+//!
+//! Goldens are plain binary PPM files (viewable in most image tools). They
+//! are screens of the OS in the ROM, so they are not committed: record them
+//! from your own ROM dump with `EMU_UPDATE_GOLDENS=1` (see
+//! `tests/golden/README.md`). A missing golden fails the test rather than
+//! being recorded silently. On mismatch the actual frame is written next to
+//! the golden as `<name>.actual.ppm`.
+//!
+//! The boot test also asserts the OS reaches the idle home screen within
+//! `BOOT_CYCLE_BUDGET`, catching regressions that silently slow down boot.
+
+#[cfg(test)]
+mod tests {
+    use crate::emu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::frame_hash::hash_frame;
+    use crate::Emu;
+    use std::path::PathBuf;
+
+    /// Env var holding the ROM path
+    const ROM_ENV: &str = "EMU_TEST_ROM";
+    /// Env var that forces goldens to be rewritten
+    const UPDATE_ENV: &str = "EMU_UPDATE_GOLDENS";
+    /// Fixed seed so runs render identical frames
+    const GOLDEN_SEED: u64 = 0x84CE;
+    /// Cycles allowed from power-on to the idle home screen (boot takes ~65M)
+    const BOOT_CYCLE_BUDGET: u64 = 120_000_000;
+
+    /// One scripted input step
+    #[derive(Debug, Clone, Copy)]
+    enum Step {
+        /// Run for a number of cycles
+        Run(u32),
+        /// Press and release a key (row, col), then let the OS process it
+        Key(usize, usize),
+    }
+
+    /// Allowed difference between a frame and its golden
+    #[derive(Debug, Clone, Copy)]
+    struct Tolerance {
+        /// Largest per-channel difference that still counts as equal
+        max_channel_delta: u8,
+        /// Number of pixels allowed to exceed `max_channel_delta`
+        max_diff_pixels: usize,
+    }
+
+    impl Tolerance {
+        const EXACT: Tolerance = Tolerance { max_channel_delta: 0, max_diff_pixels: 0 };
+    }
+
+    fn golden_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+    }
+
+    /// Load the ROM from `EMU_TEST_ROM`, or None to skip
+    fn rom_from_env() -> Option<Vec<u8>> {
+        let path = std::env::var_os(ROM_ENV)?;
+        Some(std::fs::read(&path).unwrap_or_else(|e| {
+            panic!("{}={:?} could not be read: {}", ROM_ENV, path, e)
+        }))
+    }
+
+    /// Boot to the home screen in deterministic mode
+    fn boot(rom: &[u8]) -> Emu {
+        let mut emu = Emu::new();
+        emu.set_deterministic(Some(GOLDEN_SEED));
+        emu.load_rom(rom).expect("ROM rejected");
+        emu.press_on_key();
+        emu.run_cycles(10_000_000);
+        emu.release_on_key();
+        for _ in 0..60 {
+            emu.run_cycles(1_000_000);
+        }
+        emu
+    }
+
+    fn run_script(emu: &mut Emu, script: &[Step]) {
+        for step in script {
+            match *step {
+                Step::Run(cycles) => {
+                    emu.run_cycles(cycles);
+                }
+                Step::Key(row, col) => {
+                    emu.set_key(row, col, true);
+                    emu.run_cycles(1_500_000);
+                    emu.set_key(row, col, false);
+                    emu.run_cycles(3_000_000);
+                }
+            }
+        }
+    }
+
+    fn encode_ppm(pixels: &[u32], width: usize, height: usize) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+        for p in pixels {
+            out.extend_from_slice(&[(p >> 16) as u8, (p >> 8) as u8, *p as u8]);
+        }
+        out
+    }
+
+    /// Decode a binary PPM written by `encode_ppm` into ARGB8888 pixels
+    fn decode_ppm(data: &[u8]) -> Option<(Vec<u32>, usize, usize)> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while fields.len() < 4 {
+            while data.get(pos)?.is_ascii_whitespace() {
+                pos += 1;
+            }
+            let start = pos;
+            while !data.get(pos)?.is_ascii_whitespace() {
+                pos += 1;
+            }
+            fields.push(std::str::from_utf8(&data[start..pos]).ok()?);
+        }
+        pos += 1; // single whitespace before the raster
+        if fields[0] != "P6" || fields[3] != "255" {
+            return None;
+        }
+        let width: usize = fields[1].parse().ok()?;
+        let height: usize = fields[2].parse().ok()?;
+        let raster = data.get(pos..pos + width * height * 3)?;
+        let pixels = raster
+            .chunks_exact(3)
+            .map(|c| 0xFF00_0000 | (c[0] as u32) << 16 | (c[1] as u32) << 8 | c[2] as u32)
+            .collect();
+        Some((pixels, width, height))
+    }
+
+    /// Number of pixels whose largest channel difference exceeds the tolerance
+    fn count_diff_pixels(a: &[u32], b: &[u32], tol: Tolerance) -> usize {
+        a.iter()
+            .zip(b)
+            .filter(|(&x, &y)| {
+                (0..3).any(|i| {
+                    let cx = (x >> (i * 8)) as u8;
+                    let cy = (y >> (i * 8)) as u8;
+                    cx.abs_diff(cy) > tol.max_channel_delta
+                })
+            })
+            .count()
+    }
+
+    /// Compare the current frame against golden `name`
+    fn assert_matches_golden(emu: &mut Emu, name: &str, tol: Tolerance) {
+        emu.render_frame();
+        let pixels = emu.framebuffer_data();
+        let path = golden_dir().join(format!("{name}.ppm"));
+
+        if std::env::var_os(UPDATE_ENV).is_some() {
+            std::fs::create_dir_all(golden_dir()).unwrap();
+            std::fs::write(&path, encode_ppm(pixels, SCREEN_WIDTH, SCREEN_HEIGHT)).unwrap();
+            println!("recorded golden {} (hash {:016X})", path.display(), hash_frame(pixels));
+            return;
+        }
+        if !path.exists() {
+            panic!("missing golden {}, rerun with {}=1", path.display(), UPDATE_ENV);
+        }
+
+        let data = std::fs::read(&path).unwrap();
+        let (golden, w, h) = decode_ppm(&data)
+            .unwrap_or_else(|| panic!("{} is not a valid golden", path.display()));
+        assert_eq!((w, h), (SCREEN_WIDTH, SCREEN_HEIGHT), "golden {} has wrong size", name);
+
+        let diff = count_diff_pixels(pixels, &golden, tol);
+        if diff > tol.max_diff_pixels {
+            let actual = golden_dir().join(format!("{name}.actual.ppm"));
+            std::fs::write(&actual, encode_ppm(pixels, SCREEN_WIDTH, SCREEN_HEIGHT)).unwrap();
+            panic!(
+                "golden {} mismatch: {} pixels differ (allowed {}), hash {:016X} vs {:016X}; actual frame written to {}",
+                name, diff, tol.max_diff_pixels,
+                hash_frame(pixels), hash_frame(&golden), actual.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_ppm_round_trip() {
+        let pixels = vec![0xFF123456, 0xFF000000, 0xFFFFFFFF, 0xFFABCDEF];
+        let (decoded, w, h) = decode_ppm(&encode_ppm(&pixels, 2, 2)).unwrap();
+        assert_eq!((w, h), (2, 2));
+        assert_eq!(decoded, pixels);
+        assert!(decode_ppm(b"P5\n2 2\n255\n").is_none());
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let a = [0xFF101010, 0xFF202020, 0xFF303030];
+        let b = [0xFF101012, 0xFF202020, 0xFF903030];
+        assert_eq!(count_diff_pixels(&a, &b, Tolerance::EXACT), 2);
+        let loose = Tolerance { max_channel_delta: 2, max_diff_pixels: 0 };
+        assert_eq!(count_diff_pixels(&a, &b, loose), 1);
+    }
+
+    #[test]
+    fn golden_home_screen() {
+        let Some(rom) = rom_from_env() else { return };
+        let mut emu = boot(&rom);
+        // Dismiss the boot screen
+        run_script(&mut emu, &[Step::Key(6, 0), Step::Run(5_000_000)]);
+        assert_matches_golden(&mut emu, "home_screen", Tolerance::EXACT);
+    }
+
+    #[test]
+    fn golden_addition() {
+        let Some(rom) = rom_from_env() else { return };
+        let mut emu = boot(&rom);
+        run_script(&mut emu, &[
+            Step::Key(6, 0),      // dismiss boot screen
+            Step::Run(5_000_000),
+            Step::Key(4, 1),      // 2
+            Step::Key(6, 1),      // +
+            Step::Key(5, 1),      // 3
+            Step::Key(6, 0),      // ENTER
+            Step::Run(20_000_000),
+        ]);
+        assert_matches_golden(&mut emu, "addition", Tolerance::EXACT);
+    }
+
+    #[test]
+    fn boot_reaches_home_screen_within_budget() {
+        let Some(rom) = rom_from_env() else { return };
+        let mut emu = Emu::new();
+        emu.set_deterministic(Some(GOLDEN_SEED));
+        emu.load_rom(&rom).expect("ROM rejected");
+        emu.power_on();
+
+        // First idle point: boot screen waiting for a key
+        emu.run_until_idle(BOOT_CYCLE_BUDGET).unwrap_or_else(|| {
+            panic!("OS did not go idle within {} cycles (pc={:06X})", BOOT_CYCLE_BUDGET, emu.pc())
+        });
+        assert_matches_golden(&mut emu, "boot_screen", Tolerance::EXACT);
+
+        run_script(&mut emu, &[Step::Key(6, 0)]);
+        let remaining = BOOT_CYCLE_BUDGET.saturating_sub(emu.total_cycles());
+        emu.run_until_idle(remaining).unwrap_or_else(|| {
+            panic!("home screen not reached within {} cycles (pc={:06X})", BOOT_CYCLE_BUDGET, emu.pc())
+        });
+        println!("home screen after {} cycles", emu.total_cycles());
+        assert_matches_golden(&mut emu, "boot_home_screen", Tolerance::EXACT);
+    }
+}
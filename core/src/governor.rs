@@ -0,0 +1,235 @@
+//! Adaptive performance governor
+//!
+//! Keeps emulation at full speed on slow hosts by trading fidelity for time.
+//! The frontend reports how much wall-clock time each frame took
+//! (`Emu::governor_report`); the governor compares that with the emulated
+//! time that elapsed and, once per window of frames, steps its knobs:
+//!
+//! - too slow: switch to `AccuracyMode::Fast`, then raise frame skip
+//! - plenty of headroom: lower frame skip, then return to `AccuracyMode::Full`
+//!
+//! The gap between the two thresholds keeps it from oscillating. Wall time is
+//! measured by the host because `std::time::Instant` is unavailable on wasm.
+
+use std::collections::VecDeque;
+
+/// Number of governor decisions kept for the host to read
+const DECISION_LOG_SIZE: usize = 32;
+
+/// Fidelity/speed trade-off for the run loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccuracyMode {
+    /// Cycle-exact: polled peripherals tick after every instruction, block
+    /// instructions yield at scheduler events, and per-instruction execution
+    /// history is recorded (needed by `dump_history`, crash diagnostics, and
+    /// the debugger)
+    #[default]
+    Full,
+    /// Trade timing precision for speed: polled peripherals (timers, USB,
+    /// watchdog) tick in batches of 1024 cycles, so their interrupts can be
+    /// that late; block instructions run to completion before pending
+    /// scheduler events; and no execution history is recorded.
+    Fast,
+}
+
+/// Governor tuning
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GovernorConfig {
+    /// Frames per evaluation window
+    pub window: u32,
+    /// Speed (emulated time / host time) below which fidelity is reduced
+    pub slow_threshold: f64,
+    /// Speed above which fidelity is restored
+    pub fast_threshold: f64,
+    /// Highest frame skip the governor will choose
+    pub max_frame_skip: u32,
+}
+
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            window: 30,
+            slow_threshold: 0.95,
+            fast_threshold: 1.5,
+            max_frame_skip: 3,
+        }
+    }
+}
+
+/// A knob change made by the governor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GovernorAction {
+    /// Accuracy mode changed
+    Accuracy(AccuracyMode),
+    /// Frame skip changed to this value
+    FrameSkip(u32),
+}
+
+/// One governor decision, with the measurement that caused it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GovernorDecision {
+    /// Number of frames reported when the decision was made
+    pub frame: u64,
+    /// Measured speed over the window (1.0 = real time)
+    pub speed: f64,
+    /// What changed
+    pub action: GovernorAction,
+}
+
+/// Governor state
+#[derive(Debug, Clone)]
+pub struct Governor {
+    config: GovernorConfig,
+    frames: u64,
+    window_frames: u32,
+    window_emulated: f64,
+    window_host: f64,
+    last_speed: Option<f64>,
+    frame_skip: u32,
+    accuracy: AccuracyMode,
+    decisions: VecDeque<GovernorDecision>,
+}
+
+impl Governor {
+    /// Create a governor starting at full fidelity
+    pub fn new(config: GovernorConfig) -> Self {
+        Self {
+            config,
+            frames: 0,
+            window_frames: 0,
+            window_emulated: 0.0,
+            window_host: 0.0,
+            last_speed: None,
+            frame_skip: 0,
+            accuracy: AccuracyMode::Full,
+            decisions: VecDeque::new(),
+        }
+    }
+
+    /// Tuning in use
+    pub fn config(&self) -> GovernorConfig {
+        self.config
+    }
+
+    /// Frame skip currently chosen
+    pub fn frame_skip(&self) -> u32 {
+        self.frame_skip
+    }
+
+    /// Accuracy mode currently chosen
+    pub fn accuracy(&self) -> AccuracyMode {
+        self.accuracy
+    }
+
+    /// Speed measured over the last complete window (None before the first)
+    pub fn speed(&self) -> Option<f64> {
+        self.last_speed
+    }
+
+    /// Record one frame. Returns the decision made, if this frame completed a
+    /// window and a knob changed.
+    pub fn record_frame(&mut self, emulated_secs: f64, host_secs: f64) -> Option<GovernorDecision> {
+        self.frames += 1;
+        self.window_frames += 1;
+        self.window_emulated += emulated_secs;
+        self.window_host += host_secs;
+        if self.window_frames < self.config.window.max(1) {
+            return None;
+        }
+
+        let speed = if self.window_host > 0.0 {
+            self.window_emulated / self.window_host
+        } else {
+            f64::INFINITY
+        };
+        self.window_frames = 0;
+        self.window_emulated = 0.0;
+        self.window_host = 0.0;
+        self.last_speed = Some(speed);
+
+        let action = if speed < self.config.slow_threshold {
+            self.degrade()
+        } else if speed > self.config.fast_threshold {
+            self.restore()
+        } else {
+            None
+        }?;
+        let decision = GovernorDecision { frame: self.frames, speed, action };
+        if self.decisions.len() == DECISION_LOG_SIZE {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(decision);
+        Some(decision)
+    }
+
+    /// Take recorded decisions, oldest first
+    pub fn take_decisions(&mut self) -> Vec<GovernorDecision> {
+        self.decisions.drain(..).collect()
+    }
+
+    fn degrade(&mut self) -> Option<GovernorAction> {
+        if self.accuracy == AccuracyMode::Full {
+            self.accuracy = AccuracyMode::Fast;
+            Some(GovernorAction::Accuracy(self.accuracy))
+        } else if self.frame_skip < self.config.max_frame_skip {
+            self.frame_skip += 1;
+            Some(GovernorAction::FrameSkip(self.frame_skip))
+        } else {
+            None
+        }
+    }
+
+    fn restore(&mut self) -> Option<GovernorAction> {
+        if self.frame_skip > 0 {
+            self.frame_skip -= 1;
+            Some(GovernorAction::FrameSkip(self.frame_skip))
+        } else if self.accuracy == AccuracyMode::Fast {
+            self.accuracy = AccuracyMode::Full;
+            Some(GovernorAction::Accuracy(self.accuracy))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_window(gov: &mut Governor, speed: f64) -> Option<GovernorDecision> {
+        let mut last = None;
+        for _ in 0..gov.config().window {
+            last = gov.record_frame(1.0 / 60.0, 1.0 / 60.0 / speed);
+        }
+        last
+    }
+
+    #[test]
+    fn test_degrades_then_restores_in_reverse() {
+        let mut gov = Governor::new(GovernorConfig { max_frame_skip: 2, ..Default::default() });
+        assert_eq!(run_window(&mut gov, 1.0), None);
+
+        let actions: Vec<_> = (0..4).filter_map(|_| run_window(&mut gov, 0.5)).map(|d| d.action).collect();
+        assert_eq!(actions, [
+            GovernorAction::Accuracy(AccuracyMode::Fast),
+            GovernorAction::FrameSkip(1),
+            GovernorAction::FrameSkip(2),
+        ]);
+        assert!((gov.speed().unwrap() - 0.5).abs() < 1e-9);
+
+        // Within the hysteresis band nothing changes
+        assert_eq!(run_window(&mut gov, 1.2), None);
+
+        let actions: Vec<_> = (0..4).filter_map(|_| run_window(&mut gov, 3.0)).map(|d| d.action).collect();
+        assert_eq!(actions, [
+            GovernorAction::FrameSkip(1),
+            GovernorAction::FrameSkip(0),
+            GovernorAction::Accuracy(AccuracyMode::Full),
+        ]);
+
+        let log = gov.take_decisions();
+        assert_eq!(log.len(), 6);
+        assert_eq!(log[0].frame, 2 * gov.config().window as u64);
+        assert!(gov.take_decisions().is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+//! Calculator keys by name
+//!
+//! The keypad is an 8x8 matrix, of which the CE wires 50 positions (49 keys
+//! plus ON at row 2, column 0, which the emulator routes to the ON line).
+//! `Key` names every one of them so frontends and scripts need not carry
+//! the matrix layout; `Emu::press_key`, `Emu::release_key` and
+//! `Emu::tap_key` take it directly, and `keystroke` maps text characters to
+//! the keys that type them for `Emu::type_text`.
+
+/// A key on the TI-84 Plus CE keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Graph,
+    Trace,
+    Zoom,
+    Window,
+    YEquals,
+    Second,
+    Mode,
+    Del,
+    On,
+    Sto,
+    Ln,
+    Log,
+    Square,
+    Recip,
+    Math,
+    Alpha,
+    Num0,
+    Num1,
+    Num4,
+    Num7,
+    Comma,
+    Sin,
+    Apps,
+    XTThetaN,
+    Decimal,
+    Num2,
+    Num5,
+    Num8,
+    LParen,
+    Cos,
+    Prgm,
+    Stat,
+    Neg,
+    Num3,
+    Num6,
+    Num9,
+    RParen,
+    Tan,
+    Vars,
+    Enter,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Clear,
+    Down,
+    Left,
+    Right,
+    Up,
+}
+
+/// Every key with its name and matrix position, in `Key` declaration order
+const KEYS: [(Key, &str, usize, usize); 50] = [
+    (Key::Graph, "graph", 1, 0), (Key::Trace, "trace", 1, 1), (Key::Zoom, "zoom", 1, 2),
+    (Key::Window, "window", 1, 3), (Key::YEquals, "y=", 1, 4), (Key::Second, "2nd", 1, 5),
+    (Key::Mode, "mode", 1, 6), (Key::Del, "del", 1, 7),
+    (Key::On, "on", 2, 0), (Key::Sto, "sto", 2, 1), (Key::Ln, "ln", 2, 2), (Key::Log, "log", 2, 3),
+    (Key::Square, "square", 2, 4), (Key::Recip, "recip", 2, 5), (Key::Math, "math", 2, 6),
+    (Key::Alpha, "alpha", 2, 7),
+    (Key::Num0, "0", 3, 0), (Key::Num1, "1", 3, 1), (Key::Num4, "4", 3, 2), (Key::Num7, "7", 3, 3),
+    (Key::Comma, ",", 3, 4), (Key::Sin, "sin", 3, 5), (Key::Apps, "apps", 3, 6), (Key::XTThetaN, "xttn", 3, 7),
+    (Key::Decimal, ".", 4, 0), (Key::Num2, "2", 4, 1), (Key::Num5, "5", 4, 2), (Key::Num8, "8", 4, 3),
+    (Key::LParen, "(", 4, 4), (Key::Cos, "cos", 4, 5), (Key::Prgm, "prgm", 4, 6), (Key::Stat, "stat", 4, 7),
+    (Key::Neg, "neg", 5, 0), (Key::Num3, "3", 5, 1), (Key::Num6, "6", 5, 2), (Key::Num9, "9", 5, 3),
+    (Key::RParen, ")", 5, 4), (Key::Tan, "tan", 5, 5), (Key::Vars, "vars", 5, 6),
+    (Key::Enter, "enter", 6, 0), (Key::Add, "+", 6, 1), (Key::Sub, "-", 6, 2), (Key::Mul, "*", 6, 3),
+    (Key::Div, "/", 6, 4), (Key::Pow, "^", 6, 5), (Key::Clear, "clear", 6, 6),
+    (Key::Down, "down", 7, 0), (Key::Left, "left", 7, 1), (Key::Right, "right", 7, 2), (Key::Up, "up", 7, 3),
+];
+
+impl Key {
+    /// Every key, in matrix order
+    pub fn all() -> impl Iterator<Item = Key> {
+        KEYS.iter().map(|&(key, _, _, _)| key)
+    }
+
+    /// Keypad matrix (row, col)
+    pub fn position(self) -> (usize, usize) {
+        let (_, _, row, col) = KEYS[self as usize];
+        (row, col)
+    }
+
+    /// Keypad label in lower case, as the pipe protocol spells it
+    pub fn name(self) -> &'static str {
+        KEYS[self as usize].1
+    }
+
+    /// Key at matrix position (`row`, `col`), if one is wired there
+    pub fn at(row: usize, col: usize) -> Option<Key> {
+        KEYS.iter().find(|&&(_, _, r, c)| (r, c) == (row, col)).map(|&(key, _, _, _)| key)
+    }
+
+    /// Key with label `name` (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Key> {
+        KEYS.iter().find(|(_, label, _, _)| label.eq_ignore_ascii_case(name)).map(|&(key, _, _, _)| key)
+    }
+}
+
+/// Letters A-Z then θ, as typed after ALPHA
+const ALPHA_LETTERS: [Key; 27] = [
+    Key::Math, Key::Apps, Key::Prgm, Key::Recip, Key::Sin, Key::Cos, Key::Tan, Key::Pow, Key::Square,
+    Key::Comma, Key::LParen, Key::RParen, Key::Div, Key::Log, Key::Num7, Key::Num8, Key::Num9, Key::Mul,
+    Key::Ln, Key::Num4, Key::Num5, Key::Num6, Key::Sub, Key::Sto, Key::Num1, Key::Num2, Key::Num3,
+];
+
+/// The keys that type `c` on the homescreen with a normal cursor: an
+/// optional `Key::Second` or `Key::Alpha` modifier, then the key. Letters
+/// are upper case (the OS has no lower case without a setting), `~` is
+/// negation, `→` is STO and `\n` is ENTER. None if no key types `c`.
+pub fn keystroke(c: char) -> Option<(Option<Key>, Key)> {
+    let plain = |key| Some((None, key));
+    let second = |key| Some((Some(Key::Second), key));
+    let alpha = |key| Some((Some(Key::Alpha), key));
+    match c {
+        '0'..='9' => plain(Key::from_name(c.encode_utf8(&mut [0; 4]))?),
+        'a'..='z' | 'A'..='Z' => alpha(ALPHA_LETTERS[(c.to_ascii_uppercase() as u8 - b'A') as usize]),
+        'θ' => alpha(ALPHA_LETTERS[26]),
+        '+' => plain(Key::Add),
+        '-' => plain(Key::Sub),
+        '*' => plain(Key::Mul),
+        '/' => plain(Key::Div),
+        '^' => plain(Key::Pow),
+        '(' => plain(Key::LParen),
+        ')' => plain(Key::RParen),
+        ',' => plain(Key::Comma),
+        '.' => plain(Key::Decimal),
+        '~' | '⁻' => plain(Key::Neg),
+        '→' => plain(Key::Sto),
+        '²' => plain(Key::Square),
+        '\n' => plain(Key::Enter),
+        '[' => second(Key::Mul),
+        ']' => second(Key::Sub),
+        '{' => second(Key::LParen),
+        '}' => second(Key::RParen),
+        'π' => second(Key::Pow),
+        '√' => second(Key::Square),
+        'ᴇ' => second(Key::Comma),
+        ' ' => alpha(Key::Num0),
+        ':' => alpha(Key::Decimal),
+        '?' => alpha(Key::Neg),
+        '"' => alpha(Key::Add),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_matches_enum() {
+        for (i, &(key, name, row, col)) in KEYS.iter().enumerate() {
+            assert_eq!(key as usize, i, "{:?}", key);
+            assert_eq!(Key::at(row, col), Some(key));
+            assert_eq!(Key::from_name(name), Some(key));
+        }
+        assert_eq!(Key::Enter.position(), (6, 0));
+        assert_eq!(Key::from_name("ENTER"), Some(Key::Enter));
+        assert_eq!(Key::at(0, 0), None);
+        assert_eq!(Key::at(7, 4), None);
+        assert_eq!(Key::all().count(), 50);
+    }
+
+    #[test]
+    fn test_keystroke() {
+        assert_eq!(keystroke('7'), Some((None, Key::Num7)));
+        assert_eq!(keystroke('A'), Some((Some(Key::Alpha), Key::Math)));
+        assert_eq!(keystroke('z'), Some((Some(Key::Alpha), Key::Num2)));
+        assert_eq!(keystroke('θ'), Some((Some(Key::Alpha), Key::Num3)));
+        assert_eq!(keystroke('{'), Some((Some(Key::Second), Key::LParen)));
+        assert_eq!(keystroke('\n'), Some((None, Key::Enter)));
+        assert_eq!(keystroke('='), None);
+    }
+}
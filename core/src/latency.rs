@@ -0,0 +1,229 @@
+//! Input-to-photon latency
+//!
+//! Measures how long a key press takes to show up on screen: from the
+//! moment the host hands the key to the emulator (`Emu::set_key`) to the
+//! first rendered frame whose pixels differ from the frame before the press.
+//! Each sample has the latency in emulated time, which is what the calculator
+//! itself contributes, and in host time, which adds the frontend's own
+//! scheduling and rendering cadence.
+//!
+//! Presses that change nothing on screen within `MAX_WAIT_SECS` of emulated
+//! time are dropped rather than matched with some later, unrelated change
+//! such as the blinking cursor. Host time is unavailable on wasm, where
+//! `std::time::Instant` does not exist.
+
+use std::collections::VecDeque;
+
+/// Unread samples kept for the host; older ones are dropped when it falls behind
+pub const LATENCY_QUEUE_SIZE: usize = 256;
+
+/// Presses still waiting for a changed frame after this long are dropped
+pub const MAX_WAIT_SECS: f64 = 2.0;
+
+/// Presses waiting at once; more are ignored until a frame resolves them
+const MAX_PENDING: usize = 64;
+
+#[cfg(not(target_arch = "wasm32"))]
+type HostInstant = std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+type HostInstant = ();
+
+#[cfg(not(target_arch = "wasm32"))]
+fn host_now() -> Option<HostInstant> {
+    Some(std::time::Instant::now())
+}
+#[cfg(target_arch = "wasm32")]
+fn host_now() -> Option<HostInstant> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn host_us_since(start: HostInstant) -> f64 {
+    start.elapsed().as_secs_f64() * 1e6
+}
+#[cfg(target_arch = "wasm32")]
+fn host_us_since(_start: HostInstant) -> f64 {
+    -1.0
+}
+
+/// Latency of one key press
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputLatency {
+    /// Keypad matrix row of the press
+    pub row: u32,
+    /// Keypad matrix column
+    pub col: u32,
+    /// Emulated time from the press to the changed frame, in microseconds
+    pub emulated_us: f64,
+    /// Host time for the same, in microseconds (-1 if unavailable)
+    pub host_us: f64,
+    /// Frames rendered in between that did not change yet
+    pub unchanged_frames: u32,
+}
+
+/// Running summary of all samples since tracking started
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LatencyStats {
+    /// Samples measured
+    pub samples: u64,
+    /// Presses dropped for not changing the screen in time
+    pub dropped: u64,
+    /// Emulated latency, microseconds
+    pub emulated_mean_us: f64,
+    pub emulated_min_us: f64,
+    pub emulated_max_us: f64,
+    /// Host latency, microseconds (-1 if unavailable)
+    pub host_mean_us: f64,
+    pub host_max_us: f64,
+}
+
+/// A press waiting for the screen to change
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    row: u32,
+    col: u32,
+    secs: f64,
+    host: Option<HostInstant>,
+    unchanged_frames: u32,
+}
+
+/// Matches key presses with the frames that show their effect
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    baseline: u32,
+    pending: Vec<Pending>,
+    samples: VecDeque<InputLatency>,
+    stats: LatencyStats,
+    emulated_sum: f64,
+    host_sum: f64,
+    host_samples: u64,
+}
+
+impl LatencyTracker {
+    /// Start tracking with `baseline` the hash of the frame on screen now
+    pub fn new(baseline: u32) -> Self {
+        Self {
+            baseline,
+            pending: Vec::new(),
+            samples: VecDeque::new(),
+            stats: LatencyStats { host_mean_us: -1.0, host_max_us: -1.0, ..LatencyStats::default() },
+            emulated_sum: 0.0,
+            host_sum: 0.0,
+            host_samples: 0,
+        }
+    }
+
+    /// A key at (`row`, `col`) was pressed at emulated time `secs`
+    pub fn key_pressed(&mut self, row: usize, col: usize, secs: f64) {
+        if self.pending.len() < MAX_PENDING {
+            self.pending.push(Pending { row: row as u32, col: col as u32, secs, host: host_now(), unchanged_frames: 0 });
+        }
+    }
+
+    /// A frame hashing to `hash` was rendered at emulated time `secs`
+    pub fn frame(&mut self, hash: u32, secs: f64) {
+        if hash == self.baseline {
+            let before = self.pending.len();
+            self.pending.retain(|p| secs - p.secs <= MAX_WAIT_SECS);
+            self.stats.dropped += (before - self.pending.len()) as u64;
+            for pending in &mut self.pending {
+                pending.unchanged_frames += 1;
+            }
+            return;
+        }
+        self.baseline = hash;
+        for pending in std::mem::take(&mut self.pending) {
+            let host_us = pending.host.map_or(-1.0, host_us_since);
+            self.record(InputLatency {
+                row: pending.row,
+                col: pending.col,
+                emulated_us: (secs - pending.secs) * 1e6,
+                host_us,
+                unchanged_frames: pending.unchanged_frames,
+            });
+        }
+    }
+
+    /// Forget presses in flight (emulated time restarts at reset)
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    fn record(&mut self, sample: InputLatency) {
+        let stats = &mut self.stats;
+        if stats.samples == 0 {
+            stats.emulated_min_us = sample.emulated_us;
+            stats.emulated_max_us = sample.emulated_us;
+        }
+        stats.samples += 1;
+        stats.emulated_min_us = stats.emulated_min_us.min(sample.emulated_us);
+        stats.emulated_max_us = stats.emulated_max_us.max(sample.emulated_us);
+        self.emulated_sum += sample.emulated_us;
+        stats.emulated_mean_us = self.emulated_sum / stats.samples as f64;
+        if sample.host_us >= 0.0 {
+            self.host_samples += 1;
+            self.host_sum += sample.host_us;
+            stats.host_mean_us = self.host_sum / self.host_samples as f64;
+            stats.host_max_us = stats.host_max_us.max(sample.host_us);
+        }
+
+        if self.samples.len() == LATENCY_QUEUE_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Summary of all samples so far
+    pub fn stats(&self) -> LatencyStats {
+        self.stats
+    }
+
+    /// Take up to `max` unread samples, oldest first
+    pub fn take_samples(&mut self, max: usize) -> Vec<InputLatency> {
+        let n = max.min(self.samples.len());
+        self.samples.drain(..n).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_resolved_by_changed_frame() {
+        let mut tracker = LatencyTracker::new(0xAAAA);
+        tracker.key_pressed(6, 0, 1.0);
+        tracker.frame(0xAAAA, 1.016);
+        tracker.key_pressed(4, 1, 1.020);
+        tracker.frame(0xBBBB, 1.033);
+
+        let samples = tracker.take_samples(usize::MAX);
+        assert_eq!(samples.len(), 2);
+        assert_eq!((samples[0].row, samples[0].col, samples[0].unchanged_frames), (6, 0, 1));
+        assert!((samples[0].emulated_us - 33_000.0).abs() < 1e-6);
+        assert!((samples[1].emulated_us - 13_000.0).abs() < 1e-6);
+        assert!(samples[0].host_us >= 0.0);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.samples, 2);
+        assert!((stats.emulated_mean_us - 23_000.0).abs() < 1e-6);
+        assert!((stats.emulated_min_us - 13_000.0).abs() < 1e-6);
+        assert!((stats.emulated_max_us - 33_000.0).abs() < 1e-6);
+        assert!(tracker.take_samples(8).is_empty());
+    }
+
+    #[test]
+    fn test_press_without_visible_effect_is_dropped() {
+        let mut tracker = LatencyTracker::new(1);
+        tracker.key_pressed(1, 1, 0.0);
+        tracker.frame(1, 1.0);
+        tracker.frame(1, MAX_WAIT_SECS + 0.5);
+        // The cursor blinking later must not count as this press's effect
+        tracker.frame(2, MAX_WAIT_SECS + 1.0);
+        assert!(tracker.take_samples(8).is_empty());
+        assert_eq!(tracker.stats().dropped, 1);
+        assert_eq!(tracker.stats().host_mean_us, -1.0);
+    }
+}
@@ -0,0 +1,683 @@
+//! Virtual link cable (DUSB silent link)
+//!
+//! The TI-84 Plus CE links over USB with TI's DUSB protocol; its silent
+//! link lets a computer, or another calculator acting as host, send and
+//! fetch variables without anyone pressing keys on the receiving end. The
+//! calculator end is emulated at the protocol level: `Emu::link_send` takes
+//! the bytes the other end of the cable writes and `Emu::link_recv` returns
+//! the replies, with variables stored and looked up through
+//! `Emu::send_variable` and `Emu::receive_variable` (so a received variable
+//! goes to the archive and restarts a running OS). `LinkHost` is the
+//! matching host end, and `transfer` copies a variable between emulators.
+//!
+//! Framing follows DUSB. Raw packets are `[size u32 BE][type][payload]`.
+//! Virtual packets, `[size u32 BE][type u16 BE][data]`, are split across raw
+//! data packets (type 3, the last one type 4) that are each acknowledged
+//! with a raw type 5. The calculator end answers buffer size requests,
+//! `PING` and `MODE_SET` (with `DATA_ACK`), `RTS` + `VAR_CNTS` + `EOT`
+//! (stores the variable), and `VAR_REQ` (`VAR_HDR`, `VAR_CNTS` and `EOT`,
+//! or `ERROR` if there is no such variable). It replies immediately: the
+//! protocol runs outside emulated time and bypasses the USB controller.
+//!
+//! Calculator programs that talk to each other (chat, multiplayer games)
+//! use the USB serial link instead. `connect` wires two emulators' serial
+//! accessories together with a `LinkCable`, which runs them in lockstep;
+//! `link_hub` does the same for three or more.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::emu::Emu;
+use crate::link_hub::DEFAULT_SLICE_CYCLES;
+use crate::serial::SerialAccessoryConfig;
+use crate::ti_file::{TiFile, TiVarEntry, VarType};
+
+/// Raw packet types
+pub mod raw {
+    pub const BUF_SIZE_REQ: u8 = 1;
+    pub const BUF_SIZE_ALLOC: u8 = 2;
+    pub const VIRT_DATA: u8 = 3;
+    pub const VIRT_DATA_LAST: u8 = 4;
+    pub const VIRT_DATA_ACK: u8 = 5;
+}
+
+/// Virtual packet types
+pub mod vpkt {
+    pub const PING: u16 = 0x0001;
+    pub const VAR_HDR: u16 = 0x000A;
+    pub const RTS: u16 = 0x000B;
+    pub const VAR_REQ: u16 = 0x000C;
+    pub const VAR_CNTS: u16 = 0x000D;
+    pub const MODE_SET: u16 = 0x0012;
+    pub const DATA_ACK: u16 = 0xAA00;
+    pub const EOT: u16 = 0xDD00;
+    pub const ERROR: u16 = 0xEE00;
+}
+
+/// Variable attribute ids
+mod attr {
+    pub const SIZE: u16 = 0x0001;
+    pub const TYPE: u16 = 0x0002;
+    pub const ARCHIVED: u16 = 0x0003;
+}
+
+/// `ERROR` codes sent by the calculator end
+pub mod error {
+    /// Unexpected or malformed packet
+    pub const PROTOCOL: u16 = 0x0001;
+    /// `VAR_REQ` for a variable that does not exist
+    pub const NOT_FOUND: u16 = 0x0004;
+    /// The variable could not be stored
+    pub const STORE_FAILED: u16 = 0x0005;
+}
+
+/// Largest raw payload either end accepts
+pub const MAX_RAW_PAYLOAD: usize = 1023;
+/// Raw payload size used until the other end allocates a buffer size
+pub const DEFAULT_RAW_PAYLOAD: usize = 250;
+/// Raw header: size (4) and type
+const RAW_HEADER: usize = 5;
+/// Virtual header: size (4) and type (2)
+const VIRT_HEADER: usize = 6;
+/// Virtual packets larger than this are dropped (a variable is under 64K)
+const MAX_VIRT_SIZE: usize = 0x20000;
+
+/// Why a `transfer` failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// The sending calculator has no such variable
+    NotFound,
+    /// The receiving calculator answered with this `ERROR` code
+    Rejected(u16),
+    /// An end answered with something unexpected
+    Protocol,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::NotFound => f.write_str("no such variable"),
+            LinkError::Rejected(code) => write!(f, "receiver rejected the variable (error {:04X})", code),
+            LinkError::Protocol => f.write_str("unexpected reply"),
+        }
+    }
+}
+
+fn raw_packet(out: &mut VecDeque<u8>, kind: u8, payload: &[u8]) {
+    out.extend((payload.len() as u32).to_be_bytes());
+    out.push_back(kind);
+    out.extend(payload);
+}
+
+/// One end of the cable: raw framing, virtual packet assembly, and acks
+#[derive(Debug, Clone)]
+struct Endpoint {
+    /// Received bytes not yet forming a whole raw packet
+    pending: Vec<u8>,
+    /// Virtual packet being assembled from raw data packets
+    assembling: Vec<u8>,
+    /// Bytes to send to the other end
+    out: VecDeque<u8>,
+    /// Largest raw payload the other end accepts
+    peer_payload: usize,
+}
+
+impl Default for Endpoint {
+    fn default() -> Self {
+        Self { pending: Vec::new(), assembling: Vec::new(), out: VecDeque::new(), peer_payload: DEFAULT_RAW_PAYLOAD }
+    }
+}
+
+impl Endpoint {
+    /// Take in received bytes, answering buffer size requests and acking
+    /// data. Returns the completed virtual packets as (type, data).
+    fn feed(&mut self, bytes: &[u8]) -> Vec<(u16, Vec<u8>)> {
+        self.pending.extend_from_slice(bytes);
+        let mut packets = Vec::new();
+        let mut pos = 0;
+        while let Some(header) = self.pending.get(pos..pos + RAW_HEADER) {
+            let size = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+            let kind = header[4];
+            if size > MAX_RAW_PAYLOAD {
+                // Lost sync: drop everything buffered
+                pos = self.pending.len();
+                self.assembling.clear();
+                break;
+            }
+            let Some(payload) = self.pending.get(pos + RAW_HEADER..pos + RAW_HEADER + size) else {
+                break;
+            };
+            let payload = payload.to_vec();
+            pos += RAW_HEADER + size;
+            match kind {
+                raw::BUF_SIZE_REQ if size == 4 => {
+                    let requested = u32::from_be_bytes(payload[..4].try_into().unwrap()) as usize;
+                    let size = requested.clamp(1, MAX_RAW_PAYLOAD);
+                    self.peer_payload = size;
+                    raw_packet(&mut self.out, raw::BUF_SIZE_ALLOC, &(size as u32).to_be_bytes());
+                }
+                raw::BUF_SIZE_ALLOC if size == 4 => {
+                    let size = u32::from_be_bytes(payload[..4].try_into().unwrap()) as usize;
+                    self.peer_payload = size.clamp(VIRT_HEADER + 1, MAX_RAW_PAYLOAD);
+                }
+                raw::VIRT_DATA | raw::VIRT_DATA_LAST => {
+                    raw_packet(&mut self.out, raw::VIRT_DATA_ACK, &[0xE0, 0x00]);
+                    self.assembling.extend_from_slice(&payload);
+                    if self.assembling.len() > MAX_VIRT_SIZE + VIRT_HEADER {
+                        self.assembling.clear();
+                    } else if kind == raw::VIRT_DATA_LAST {
+                        let data = std::mem::take(&mut self.assembling);
+                        if let Some(packet) = split_virtual(&data) {
+                            packets.push(packet);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.pending.drain(..pos);
+        packets
+    }
+
+    /// Queue a virtual packet, split to the other end's buffer size
+    fn send(&mut self, kind: u16, data: &[u8]) {
+        let mut packet = Vec::with_capacity(VIRT_HEADER + data.len());
+        packet.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        packet.extend_from_slice(&kind.to_be_bytes());
+        packet.extend_from_slice(data);
+        let mut chunks = packet.chunks(self.peer_payload).peekable();
+        while let Some(chunk) = chunks.next() {
+            let kind = if chunks.peek().is_some() { raw::VIRT_DATA } else { raw::VIRT_DATA_LAST };
+            raw_packet(&mut self.out, kind, chunk);
+        }
+    }
+
+    fn take_output(&mut self, max: usize) -> Vec<u8> {
+        let n = max.min(self.out.len());
+        self.out.drain(..n).collect()
+    }
+}
+
+/// Split an assembled virtual packet into (type, data)
+fn split_virtual(packet: &[u8]) -> Option<(u16, Vec<u8>)> {
+    let header = packet.get(..VIRT_HEADER)?;
+    let size = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+    let kind = u16::from_be_bytes([header[4], header[5]]);
+    let data = packet.get(VIRT_HEADER..VIRT_HEADER + size)?;
+    Some((kind, data.to_vec()))
+}
+
+/// Cursor over virtual packet data
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + n)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+    }
+
+    /// `[len][bytes][0]`, or just `[0]` for an empty folder
+    fn name(&mut self) -> Option<&'a [u8]> {
+        let len = self.u8()? as usize;
+        if len == 0 {
+            return Some(&[]);
+        }
+        let name = self.bytes(len)?;
+        self.u8()?;
+        Some(name)
+    }
+
+    /// `[count u16]` then `[id u16][size u16][value]` each
+    fn attrs(&mut self) -> Option<Vec<(u16, &'a [u8])>> {
+        let count = self.u16()?;
+        (0..count)
+            .map(|_| {
+                let id = self.u16()?;
+                let size = self.u16()? as usize;
+                Some((id, self.bytes(size)?))
+            })
+            .collect()
+    }
+}
+
+fn write_name(out: &mut Vec<u8>, name: &[u8]) {
+    out.push(name.len() as u8);
+    if !name.is_empty() {
+        out.extend_from_slice(name);
+        out.push(0);
+    }
+}
+
+fn write_attrs(out: &mut Vec<u8>, entry: &TiVarEntry) {
+    let attrs: [(u16, Vec<u8>); 3] = [
+        (attr::SIZE, (entry.data.len() as u32).to_be_bytes().to_vec()),
+        (attr::TYPE, vec![0xF0, 0x07, 0x00, entry.var_type.as_u8()]),
+        (attr::ARCHIVED, vec![entry.archived as u8]),
+    ];
+    out.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+    for (id, value) in attrs {
+        out.extend_from_slice(&id.to_be_bytes());
+        out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        out.extend_from_slice(&value);
+    }
+}
+
+/// Variable announced by `RTS` or `VAR_HDR`, waiting for its contents
+#[derive(Debug, Clone)]
+struct Header {
+    name: [u8; 8],
+    var_type: u8,
+    archived: bool,
+    /// Contents, once `VAR_CNTS` arrived
+    data: Option<Vec<u8>>,
+}
+
+impl Header {
+    /// Folder, name, then (for `RTS`) size and mode, then attributes
+    fn parse(data: &[u8], rts: bool) -> Option<Self> {
+        let mut reader = Reader::new(data);
+        reader.name()?;
+        let name_bytes = reader.name()?;
+        if name_bytes.is_empty() || name_bytes.len() > 8 {
+            return None;
+        }
+        if rts {
+            reader.u32()?;
+            reader.u8()?;
+        }
+        let mut name = [0; 8];
+        name[..name_bytes.len()].copy_from_slice(name_bytes);
+        let mut header = Header { name, var_type: VarType::Program.as_u8(), archived: false, data: None };
+        for (id, value) in reader.attrs()? {
+            match (id, value) {
+                (attr::TYPE, [.., var_type]) => header.var_type = *var_type,
+                (attr::ARCHIVED, [flag]) => header.archived = *flag != 0,
+                _ => {}
+            }
+        }
+        Some(header)
+    }
+
+    fn into_entry(self) -> Option<TiVarEntry> {
+        Some(TiVarEntry {
+            var_type: VarType::from(self.var_type),
+            name: self.name,
+            version: 0,
+            archived: self.archived,
+            data: self.data?,
+        })
+    }
+}
+
+/// Name in a `VAR_REQ` (folder, name, then requested attributes, ignored)
+fn requested_name(data: &[u8]) -> Option<&[u8]> {
+    let mut reader = Reader::new(data);
+    reader.name()?;
+    Some(reader.name()?).filter(|name| !name.is_empty() && name.len() <= 8)
+}
+
+/// What the calculator end needs from the emulator
+#[derive(Debug, Clone)]
+pub(crate) enum LinkRequest {
+    /// Store a received variable, then call `store_done`
+    Store(TiVarEntry),
+    /// Look up a variable by name, then call `reply_variable`
+    Fetch(Vec<u8>),
+}
+
+/// The calculator end of the cable
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LinkPort {
+    endpoint: Endpoint,
+    incoming: Option<Header>,
+}
+
+impl LinkPort {
+    /// Take in bytes from the other end; returns what the emulator must do
+    pub fn receive(&mut self, bytes: &[u8]) -> Vec<LinkRequest> {
+        let mut requests = Vec::new();
+        for (kind, data) in self.endpoint.feed(bytes) {
+            match kind {
+                vpkt::PING | vpkt::MODE_SET => self.ack(),
+                vpkt::RTS => match Header::parse(&data, true) {
+                    Some(header) => {
+                        self.incoming = Some(header);
+                        self.ack();
+                    }
+                    None => self.error(error::PROTOCOL),
+                },
+                vpkt::VAR_CNTS => match &mut self.incoming {
+                    Some(header) if header.data.is_none() => {
+                        header.data = Some(data);
+                        self.ack();
+                    }
+                    _ => self.error(error::PROTOCOL),
+                },
+                vpkt::EOT => {
+                    if let Some(entry) = self.incoming.take().and_then(Header::into_entry) {
+                        requests.push(LinkRequest::Store(entry));
+                    }
+                }
+                vpkt::VAR_REQ => match requested_name(&data) {
+                    Some(name) => requests.push(LinkRequest::Fetch(name.to_vec())),
+                    None => self.error(error::PROTOCOL),
+                },
+                vpkt::DATA_ACK => {}
+                _ => self.error(error::PROTOCOL),
+            }
+        }
+        requests
+    }
+
+    /// Answer a `Fetch`
+    pub fn reply_variable(&mut self, entry: Option<&TiVarEntry>) {
+        let Some(entry) = entry else {
+            self.error(error::NOT_FOUND);
+            return;
+        };
+        let mut header = Vec::new();
+        write_name(&mut header, &[]);
+        write_name(&mut header, &entry.name[..entry.name_len()]);
+        write_attrs(&mut header, entry);
+        self.endpoint.send(vpkt::VAR_HDR, &header);
+        self.endpoint.send(vpkt::VAR_CNTS, &entry.data);
+        self.endpoint.send(vpkt::EOT, &[]);
+    }
+
+    /// Answer a `Store`
+    pub fn store_done(&mut self, ok: bool) {
+        if !ok {
+            self.error(error::STORE_FAILED);
+        }
+    }
+
+    pub fn take_output(&mut self, max: usize) -> Vec<u8> {
+        self.endpoint.take_output(max)
+    }
+
+    fn ack(&mut self) {
+        self.endpoint.send(vpkt::DATA_ACK, &[0x00, 0x01]);
+    }
+
+    fn error(&mut self, code: u16) {
+        self.incoming = None;
+        self.endpoint.send(vpkt::ERROR, &code.to_be_bytes());
+    }
+}
+
+/// Something the host end received
+#[derive(Debug, Clone)]
+pub enum HostEvent {
+    /// `DATA_ACK`
+    Ack,
+    /// A whole variable (`VAR_HDR`, `VAR_CNTS`, `EOT`)
+    Variable(TiVarEntry),
+    /// `ERROR` with its code
+    Error(u16),
+}
+
+/// The host end of the cable: builds requests and decodes replies
+#[derive(Debug, Clone)]
+pub struct LinkHost {
+    endpoint: Endpoint,
+    incoming: Option<Header>,
+}
+
+impl Default for LinkHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkHost {
+    /// Host end that starts by asking for the largest buffer size
+    pub fn new() -> Self {
+        let mut endpoint = Endpoint::default();
+        raw_packet(&mut endpoint.out, raw::BUF_SIZE_REQ, &(MAX_RAW_PAYLOAD as u32).to_be_bytes());
+        Self { endpoint, incoming: None }
+    }
+
+    /// Ask for variable `name`
+    pub fn request_variable(&mut self, name: &[u8]) {
+        let mut data = Vec::new();
+        write_name(&mut data, &[]);
+        write_name(&mut data, name);
+        data.extend_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0]); // no attribute ids or attributes, then terminator
+        self.endpoint.send(vpkt::VAR_REQ, &data);
+    }
+
+    /// Send a variable silently (`RTS`, `VAR_CNTS`, `EOT`)
+    pub fn send_variable(&mut self, entry: &TiVarEntry) {
+        let mut rts = Vec::new();
+        write_name(&mut rts, &[]);
+        write_name(&mut rts, &entry.name[..entry.name_len()]);
+        rts.extend_from_slice(&(entry.data.len() as u32).to_be_bytes());
+        rts.push(0x01); // silent
+        write_attrs(&mut rts, entry);
+        self.endpoint.send(vpkt::RTS, &rts);
+        self.endpoint.send(vpkt::VAR_CNTS, &entry.data);
+        self.endpoint.send(vpkt::EOT, &[]);
+    }
+
+    /// Take in bytes from the calculator
+    pub fn receive(&mut self, bytes: &[u8]) -> Vec<HostEvent> {
+        let mut events = Vec::new();
+        for (kind, data) in self.endpoint.feed(bytes) {
+            match kind {
+                vpkt::DATA_ACK => events.push(HostEvent::Ack),
+                vpkt::ERROR => events.push(HostEvent::Error(Reader::new(&data).u16().unwrap_or(0))),
+                vpkt::VAR_HDR => self.incoming = Header::parse(&data, false),
+                vpkt::VAR_CNTS => {
+                    if let Some(header) = &mut self.incoming {
+                        header.data = Some(data);
+                    }
+                }
+                vpkt::EOT => {
+                    if let Some(entry) = self.incoming.take().and_then(Header::into_entry) {
+                        events.push(HostEvent::Variable(entry));
+                    }
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// Bytes to write to the calculator
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.endpoint.take_output(usize::MAX)
+    }
+}
+
+/// Copy variable `name` from one calculator to another over virtual cables
+pub fn transfer(from: &mut Emu, to: &mut Emu, name: &[u8]) -> Result<(), LinkError> {
+    let mut source = LinkHost::new();
+    source.request_variable(name);
+    from.link_send(&source.take_output());
+    let mut variable = None;
+    for event in source.receive(&from.link_recv(usize::MAX)) {
+        match event {
+            HostEvent::Variable(entry) => variable = Some(entry),
+            HostEvent::Error(error::NOT_FOUND) => return Err(LinkError::NotFound),
+            HostEvent::Error(_) => return Err(LinkError::Protocol),
+            HostEvent::Ack => {}
+        }
+    }
+    let variable = variable.ok_or(LinkError::Protocol)?;
+    from.link_send(&source.take_output());
+
+    let mut dest = LinkHost::new();
+    dest.send_variable(&variable);
+    to.link_send(&dest.take_output());
+    let events = dest.receive(&to.link_recv(usize::MAX));
+    if let Some(code) = events.iter().find_map(|e| if let HostEvent::Error(code) = e { Some(*code) } else { None }) {
+        return Err(LinkError::Rejected(code));
+    }
+    to.link_send(&dest.take_output());
+    Ok(())
+}
+
+/// Two emulators' serial links wired to each other.
+///
+/// The cable does not own the calculators: pass the same pair to every
+/// `run_cycles`. Bytes one calculator sends are held until the other has
+/// configured its end of the link, then delivered between turns, so the two
+/// stay within one slice of each other in emulated time. As with
+/// `LinkHub`, do not install a `SerialPeer` on either calculator.
+#[derive(Debug, Clone)]
+pub struct LinkCable {
+    /// Sent by the first calculator, waiting for the second
+    a_to_b: VecDeque<u8>,
+    /// Sent by the second calculator, waiting for the first
+    b_to_a: VecDeque<u8>,
+    slice_cycles: u32,
+}
+
+/// Attach serial accessories to `a` and `b` and return the cable between
+/// them
+pub fn connect(a: &mut Emu, b: &mut Emu) -> LinkCable {
+    a.attach_serial_accessory(SerialAccessoryConfig::default());
+    b.attach_serial_accessory(SerialAccessoryConfig::default());
+    LinkCable { a_to_b: VecDeque::new(), b_to_a: VecDeque::new(), slice_cycles: DEFAULT_SLICE_CYCLES }
+}
+
+impl LinkCable {
+    /// Cycles each calculator runs per turn. Smaller turns mean less link
+    /// latency and slower emulation.
+    pub fn set_slice_cycles(&mut self, cycles: u32) {
+        self.slice_cycles = cycles.max(1);
+    }
+
+    /// Run both calculators for `cycles`, exchanging bytes after each turn
+    pub fn run_cycles(&mut self, a: &mut Emu, b: &mut Emu, cycles: u64) {
+        let mut remaining = cycles;
+        while remaining > 0 {
+            let slice = remaining.min(self.slice_cycles as u64) as u32;
+            a.run_cycles(slice);
+            b.run_cycles(slice);
+            self.exchange(a, b);
+            remaining -= slice as u64;
+        }
+    }
+
+    /// Move sent bytes across, delivering to whichever end is ready
+    pub fn exchange(&mut self, a: &mut Emu, b: &mut Emu) {
+        self.a_to_b.extend(a.serial_read(usize::MAX));
+        self.b_to_a.extend(b.serial_read(usize::MAX));
+        deliver(&mut self.a_to_b, b);
+        deliver(&mut self.b_to_a, a);
+    }
+
+    /// Bytes in flight as (first to second, second to first)
+    pub fn pending(&self) -> (usize, usize) {
+        (self.a_to_b.len(), self.b_to_a.len())
+    }
+
+    /// Unplug both ends, dropping bytes in flight
+    pub fn disconnect(self, a: &mut Emu, b: &mut Emu) {
+        a.detach_serial_accessory();
+        b.detach_serial_accessory();
+    }
+}
+
+fn deliver(queue: &mut VecDeque<u8>, to: &mut Emu) {
+    if !queue.is_empty() && to.serial_accessory_ready() {
+        to.serial_write(queue.make_contiguous());
+        queue.clear();
+    }
+}
+
+/// A single-entry .8x file holding `entry`
+pub(crate) fn entry_file(entry: TiVarEntry) -> Vec<u8> {
+    TiFile { entries: vec![entry] }.to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(name: &[u8], body: &[u8]) -> TiVarEntry {
+        let mut padded = [0; 8];
+        padded[..name.len()].copy_from_slice(name);
+        let mut data = (body.len() as u16).to_le_bytes().to_vec();
+        data.extend_from_slice(body);
+        TiVarEntry { var_type: VarType::Program, name: padded, version: 0, archived: true, data }
+    }
+
+    #[test]
+    fn test_raw_framing_and_negotiation() {
+        let mut host = LinkHost::new();
+        let mut calc = LinkPort::default();
+        // A variable bigger than the default buffer, sent before and after negotiating
+        let entry = program(b"BIG", &[0x55; 600]);
+        host.send_variable(&entry);
+        let bytes = host.take_output();
+        // Fed a byte at a time, it still reassembles
+        let mut requests = Vec::new();
+        for byte in &bytes {
+            requests.extend(calc.receive(std::slice::from_ref(byte)));
+        }
+        let [LinkRequest::Store(stored)] = &requests[..] else {
+            panic!("{:?}", requests);
+        };
+        assert_eq!(stored.data, entry.data);
+        assert_eq!(stored.name_str(), "BIG");
+        assert!(stored.archived);
+        // The calculator allocated the requested size, then acked every raw data packet
+        let reply = calc.take_output(usize::MAX);
+        assert_eq!(&reply[..9], &[0, 0, 0, 4, raw::BUF_SIZE_ALLOC, 0, 0, 0x03, 0xFF]);
+        let events = host.receive(&reply);
+        assert_eq!(events.iter().filter(|e| matches!(e, HostEvent::Ack)).count(), 2);
+        assert_eq!(calc.endpoint.peer_payload, MAX_RAW_PAYLOAD);
+    }
+
+    #[test]
+    fn test_fetch_and_errors() {
+        let mut host = LinkHost::new();
+        let mut calc = LinkPort::default();
+        host.request_variable(b"HELLO");
+        let requests = calc.receive(&host.take_output());
+        let [LinkRequest::Fetch(name)] = &requests[..] else {
+            panic!("{:?}", requests);
+        };
+        assert_eq!(name, b"HELLO");
+        let entry = program(b"HELLO", &[0xEF, 0x7B, 0xC9]);
+        calc.reply_variable(Some(&entry));
+        let events = host.receive(&calc.take_output(usize::MAX));
+        let [HostEvent::Variable(got)] = &events[..] else {
+            panic!("{:?}", events);
+        };
+        assert_eq!((got.name, got.var_type, &got.data), (entry.name, VarType::Program, &entry.data));
+
+        calc.reply_variable(None);
+        let events = host.receive(&calc.take_output(usize::MAX));
+        assert!(matches!(events[..], [HostEvent::Error(error::NOT_FOUND)]));
+
+        // Contents without an RTS
+        host.endpoint.send(vpkt::VAR_CNTS, &[1, 2, 3]);
+        assert!(calc.receive(&host.take_output()).is_empty());
+        let events = host.receive(&calc.take_output(usize::MAX));
+        assert!(matches!(events[..], [HostEvent::Error(error::PROTOCOL)]));
+    }
+}
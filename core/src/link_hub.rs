@@ -0,0 +1,285 @@
+//! Multi-calculator link hub
+//!
+//! Connects three or more emulators in one process through their serial
+//! accessories, in a star: every calculator talks to the hub, and the hub
+//! forwards each message to the calculator it is addressed to. This is
+//! enough for CALCnet-style networking programs and classroom scenarios
+//! with a whole set of calculators.
+//!
+//! Calculators exchange frames of `[address, length (2 bytes LE), payload]`.
+//! On the way out the address is the destination node (`BROADCAST` for all
+//! other nodes); the hub rewrites it to the sender's node id, so a receiver
+//! always knows who a frame came from. Frames to an unknown node are
+//! dropped and counted.
+
+use std::collections::VecDeque;
+
+use crate::emu::Emu;
+use crate::serial::SerialAccessoryConfig;
+
+/// Destination address that reaches every other node
+pub const BROADCAST: u8 = 0xFF;
+
+/// Nodes a hub can hold; ids run from 0 and `BROADCAST` is reserved
+pub const MAX_NODES: usize = BROADCAST as usize;
+
+/// Address byte plus 16-bit payload length
+const FRAME_HEADER: usize = 3;
+
+/// Default emulated cycles each calculator runs before the hub routes
+/// (about 0.2ms at 48MHz, well below any link protocol timeout)
+pub const DEFAULT_SLICE_CYCLES: u32 = 10_000;
+
+/// Per-node framing state
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// Bytes sent by the calculator that do not form a whole frame yet
+    partial: Vec<u8>,
+    /// Routed bytes waiting to be delivered to the calculator
+    inbox: VecDeque<u8>,
+}
+
+/// The routing half of the hub, independent of any emulator
+#[derive(Debug, Clone, Default)]
+pub struct HubRouter {
+    nodes: Vec<Node>,
+    frames_routed: u64,
+    frames_dropped: u64,
+}
+
+impl HubRouter {
+    /// Router with no nodes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node; returns its id, or None if the hub is full
+    pub fn add_node(&mut self) -> Option<u8> {
+        if self.nodes.len() >= MAX_NODES {
+            return None;
+        }
+        self.nodes.push(Node::default());
+        Some((self.nodes.len() - 1) as u8)
+    }
+
+    /// Number of nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Take bytes node `from` sent, routing every frame they complete
+    pub fn receive(&mut self, from: u8, data: &[u8]) {
+        let Some(node) = self.nodes.get_mut(from as usize) else {
+            return;
+        };
+        node.partial.extend_from_slice(data);
+        let mut buffer = std::mem::take(&mut node.partial);
+
+        let mut start = 0;
+        while buffer.len() - start >= FRAME_HEADER {
+            let len = u16::from_le_bytes([buffer[start + 1], buffer[start + 2]]) as usize;
+            let end = start + FRAME_HEADER + len;
+            if end > buffer.len() {
+                break;
+            }
+            let dest = buffer[start];
+            // The receiver sees the sender's id in the address byte
+            buffer[start] = from;
+            self.route(from, dest, &buffer[start..end]);
+            start = end;
+        }
+        buffer.drain(..start);
+        self.nodes[from as usize].partial = buffer;
+    }
+
+    fn route(&mut self, from: u8, dest: u8, frame: &[u8]) {
+        if dest == BROADCAST {
+            for (id, node) in self.nodes.iter_mut().enumerate() {
+                if id != from as usize {
+                    node.inbox.extend(frame);
+                }
+            }
+            self.frames_routed += 1;
+        } else if let Some(node) = self.nodes.get_mut(dest as usize).filter(|_| dest != from) {
+            node.inbox.extend(frame);
+            self.frames_routed += 1;
+        } else {
+            self.frames_dropped += 1;
+        }
+    }
+
+    /// Take everything routed to `node` so far
+    pub fn take_inbox(&mut self, node: u8) -> Vec<u8> {
+        self.nodes.get_mut(node as usize).map_or(Vec::new(), |n| n.inbox.drain(..).collect())
+    }
+
+    /// Frames delivered (a broadcast counts once)
+    pub fn frames_routed(&self) -> u64 {
+        self.frames_routed
+    }
+
+    /// Frames addressed to a node that does not exist, or to the sender
+    pub fn frames_dropped(&self) -> u64 {
+        self.frames_dropped
+    }
+}
+
+/// Emulators wired together through a `HubRouter`.
+///
+/// The hub owns its calculators and runs them in turns of a few thousand
+/// cycles, routing between turns, so they stay within one slice of each
+/// other in emulated time. Each calculator gets a serial accessory when it
+/// is added; do not install a `SerialPeer` on it, since the peer would
+/// consume what the calculator sends before the hub sees it.
+pub struct LinkHub {
+    router: HubRouter,
+    emus: Vec<Emu>,
+    slice_cycles: u32,
+}
+
+impl Default for LinkHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkHub {
+    /// Empty hub
+    pub fn new() -> Self {
+        Self { router: HubRouter::new(), emus: Vec::new(), slice_cycles: DEFAULT_SLICE_CYCLES }
+    }
+
+    /// Cycles each calculator runs per turn. Smaller turns mean less link
+    /// latency and slower emulation.
+    pub fn set_slice_cycles(&mut self, cycles: u32) {
+        self.slice_cycles = cycles.max(1);
+    }
+
+    /// Attach a serial accessory to `emu` and connect it. Returns its node
+    /// id, or None (dropping `emu`) if the hub already has `MAX_NODES`.
+    pub fn add(&mut self, mut emu: Emu) -> Option<u8> {
+        let id = self.router.add_node()?;
+        emu.attach_serial_accessory(SerialAccessoryConfig::default());
+        self.emus.push(emu);
+        Some(id)
+    }
+
+    /// Number of connected calculators
+    pub fn len(&self) -> usize {
+        self.emus.len()
+    }
+
+    /// True if no calculator is connected
+    pub fn is_empty(&self) -> bool {
+        self.emus.is_empty()
+    }
+
+    /// Calculator with node id `id`
+    pub fn emu(&self, id: u8) -> Option<&Emu> {
+        self.emus.get(id as usize)
+    }
+
+    /// Calculator with node id `id`, e.g. to press keys on it
+    pub fn emu_mut(&mut self, id: u8) -> Option<&mut Emu> {
+        self.emus.get_mut(id as usize)
+    }
+
+    /// Routing counters
+    pub fn router(&self) -> &HubRouter {
+        &self.router
+    }
+
+    /// Run every calculator for `cycles`, routing after each turn
+    pub fn run_cycles(&mut self, cycles: u64) {
+        let mut remaining = cycles;
+        while remaining > 0 {
+            let slice = remaining.min(self.slice_cycles as u64) as u32;
+            for emu in &mut self.emus {
+                emu.run_cycles(slice);
+            }
+            self.route();
+            remaining -= slice as u64;
+        }
+    }
+
+    /// Move sent bytes through the router and deliver what is waiting
+    fn route(&mut self) {
+        for (id, emu) in self.emus.iter_mut().enumerate() {
+            let sent = emu.serial_read(usize::MAX);
+            if !sent.is_empty() {
+                self.router.receive(id as u8, &sent);
+            }
+        }
+        for (id, emu) in self.emus.iter_mut().enumerate() {
+            // Hold frames until the calculator has configured the link
+            if emu.serial_accessory_ready() {
+                let inbox = self.router.take_inbox(id as u8);
+                if !inbox.is_empty() {
+                    emu.serial_write(&inbox);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(addr: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![addr];
+        out.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    #[test]
+    fn test_unicast_broadcast_and_split_frames() {
+        let mut router = HubRouter::new();
+        for expected in 0..3 {
+            assert_eq!(router.add_node(), Some(expected));
+        }
+
+        // 0 -> 2, arriving in two pieces
+        let msg = frame(2, b"HELLO");
+        router.receive(0, &msg[..4]);
+        assert!(router.take_inbox(2).is_empty());
+        router.receive(0, &msg[4..]);
+        assert_eq!(router.take_inbox(2), frame(0, b"HELLO"));
+
+        // 1 broadcasts; two frames in one write
+        let mut data = frame(BROADCAST, b"ALL");
+        data.extend(frame(0, b"X"));
+        router.receive(1, &data);
+        let mut expected = frame(1, b"ALL");
+        expected.extend(frame(1, b"X"));
+        assert_eq!(router.take_inbox(0), expected);
+        assert_eq!(router.take_inbox(2), frame(1, b"ALL"));
+        assert!(router.take_inbox(1).is_empty());
+
+        // To a missing node and to itself
+        router.receive(1, &frame(7, b"?"));
+        router.receive(1, &frame(1, b"?"));
+        assert_eq!(router.frames_routed(), 3);
+        assert_eq!(router.frames_dropped(), 2);
+    }
+
+    #[test]
+    fn test_hub_runs_all_nodes_in_step() {
+        let mut hub = LinkHub::new();
+        for expected in 0..3 {
+            let mut emu = Emu::new();
+            emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+            emu.power_on();
+            assert_eq!(hub.add(emu), Some(expected));
+        }
+        hub.set_slice_cycles(1_000);
+        hub.run_cycles(5_500);
+
+        assert_eq!(hub.len(), 3);
+        let cycles: Vec<u64> = (0..3).map(|id| hub.emu(id).unwrap().total_cycles()).collect();
+        assert!(cycles.iter().all(|&c| c >= 5_500 && c.abs_diff(cycles[0]) < 100));
+        // Nobody configured the link, so nothing is ready to receive
+        assert!(!hub.emu(0).unwrap().serial_accessory_ready());
+    }
+}
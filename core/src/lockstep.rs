@@ -0,0 +1,286 @@
+//! Lockstep comparison against a reference emulator core
+//!
+//! Runs our core and a reference core one instruction at a time from the same
+//! ROM and stops at the first register (and optionally memory) divergence,
+//! replacing long trace-diffing sessions.
+//!
+//! The reference is anything implementing `ReferenceCore`. With the
+//! `cemu-lockstep` feature, `CemuCore` drives CEmu's core linked from
+//! `libcemucore.a` through the shim in `tools/cemu-test/lockstep_shim.c`
+//! (set `CEMU_LOCKSTEP_LIB_DIR` to the directory holding `liblockstep.a` and
+//! `libcemucore.a`; see the Makefile there).
+
+use crate::Emu;
+use std::fmt;
+use std::ops::Range;
+
+/// Architectural register state compared after every instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct RegSnapshot {
+    pub pc: u32,
+    /// Active stack pointer (SPL in ADL mode, SPS otherwise)
+    pub sp: u32,
+    pub bc: u32,
+    pub de: u32,
+    pub hl: u32,
+    pub ix: u32,
+    pub iy: u32,
+    pub a: u8,
+    pub f: u8,
+    pub adl: bool,
+    pub iff1: bool,
+    pub iff2: bool,
+    pub im: u8,
+    pub halted: bool,
+}
+
+impl RegSnapshot {
+    /// Capture the register state of our core
+    pub fn of(emu: &Emu) -> Self {
+        Self {
+            pc: emu.pc(),
+            sp: emu.sp(),
+            bc: emu.bc(),
+            de: emu.de(),
+            hl: emu.hl(),
+            ix: emu.ix(),
+            iy: emu.iy(),
+            a: emu.a(),
+            f: emu.f(),
+            adl: emu.adl(),
+            iff1: emu.iff1(),
+            iff2: emu.iff2(),
+            im: emu.im(),
+            halted: emu.is_halted(),
+        }
+    }
+
+    /// Names and values of every field that differs from `other`
+    pub fn diff(&self, other: &RegSnapshot) -> Vec<String> {
+        let mut out = Vec::new();
+        macro_rules! cmp {
+            ($($field:ident),*) => {$(
+                if self.$field != other.$field {
+                    out.push(format!(
+                        "{}: ours={:X} ref={:X}",
+                        stringify!($field), self.$field as u32, other.$field as u32
+                    ));
+                }
+            )*};
+        }
+        cmp!(pc, sp, bc, de, hl, ix, iy, a, f, adl, iff1, iff2, im, halted);
+        out
+    }
+}
+
+/// A second emulator core to compare against
+pub trait ReferenceCore {
+    /// Execute one instruction
+    fn step(&mut self);
+    /// Current register state
+    fn registers(&self) -> RegSnapshot;
+    /// Read memory without side effects
+    fn peek_byte(&mut self, addr: u32) -> u8;
+}
+
+/// Our own core can serve as a reference (e.g. to compare two configurations)
+impl ReferenceCore for Emu {
+    fn step(&mut self) {
+        Emu::step(self);
+    }
+
+    fn registers(&self) -> RegSnapshot {
+        RegSnapshot::of(self)
+    }
+
+    fn peek_byte(&mut self, addr: u32) -> u8 {
+        Emu::peek_byte(self, addr)
+    }
+}
+
+/// First point where the two cores disagree
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// Number of instructions executed by both cores before the divergence
+    pub step: u64,
+    /// Register state before the diverging instruction (identical in both cores)
+    pub before: RegSnapshot,
+    pub ours: RegSnapshot,
+    pub reference: RegSnapshot,
+    /// First differing memory byte: (address, ours, reference)
+    pub memory: Option<(u32, u8, u8)>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "divergence after step {} (instruction at PC={:06X})", self.step, self.before.pc)?;
+        for line in self.ours.diff(&self.reference) {
+            writeln!(f, "  {}", line)?;
+        }
+        if let Some((addr, ours, theirs)) = self.memory {
+            writeln!(f, "  mem[{:06X}]: ours={:02X} ref={:02X}", addr, ours, theirs)?;
+        }
+        Ok(())
+    }
+}
+
+/// Drives our core and a reference core in lockstep
+pub struct Lockstep<R: ReferenceCore> {
+    pub emu: Emu,
+    pub reference: R,
+    /// Compare memory every N steps (0 = registers only)
+    mem_check_interval: u64,
+    mem_range: Range<u32>,
+    steps: u64,
+}
+
+impl<R: ReferenceCore> Lockstep<R> {
+    /// Pair two cores that have already loaded the same ROM
+    pub fn new(emu: Emu, reference: R) -> Self {
+        Self {
+            emu,
+            reference,
+            mem_check_interval: 0,
+            mem_range: 0xD00000..0xD65800,
+            steps: 0,
+        }
+    }
+
+    /// Also compare `range` every `interval` steps (0 disables memory checks)
+    pub fn set_mem_check(&mut self, interval: u64, range: Range<u32>) {
+        self.mem_check_interval = interval;
+        self.mem_range = range;
+    }
+
+    /// Instructions executed so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn first_mem_diff(&mut self) -> Option<(u32, u8, u8)> {
+        self.mem_range.clone().find_map(|addr| {
+            let ours = self.emu.peek_byte(addr);
+            let theirs = self.reference.peek_byte(addr);
+            (ours != theirs).then_some((addr, ours, theirs))
+        })
+    }
+
+    /// Run up to `max_steps` instructions. Returns the step count on
+    /// agreement, or the first divergence.
+    pub fn run(&mut self, max_steps: u64) -> Result<u64, Box<Divergence>> {
+        let end = self.steps + max_steps;
+        while self.steps < end {
+            let before = RegSnapshot::of(&self.emu);
+            self.emu.step();
+            self.reference.step();
+            self.steps += 1;
+
+            let ours = RegSnapshot::of(&self.emu);
+            let reference = self.reference.registers();
+            let check_mem = self.mem_check_interval > 0 && self.steps.is_multiple_of(self.mem_check_interval);
+            let memory = if check_mem { self.first_mem_diff() } else { None };
+            if ours != reference || memory.is_some() {
+                return Err(Box::new(Divergence { step: self.steps, before, ours, reference, memory }));
+            }
+        }
+        Ok(self.steps)
+    }
+}
+
+/// CEmu's core, linked from libcemucore through the lockstep shim.
+/// CEmu keeps its state in globals, so only one instance may exist at a time.
+#[cfg(feature = "cemu-lockstep")]
+pub struct CemuCore {
+    _private: (),
+}
+
+#[cfg(feature = "cemu-lockstep")]
+mod cemu_ffi {
+    use super::RegSnapshot;
+    use std::os::raw::c_char;
+
+    extern "C" {
+        pub fn lockstep_cemu_load_rom(path: *const c_char) -> i32;
+        pub fn lockstep_cemu_step();
+        pub fn lockstep_cemu_registers(out: *mut RegSnapshot);
+        pub fn lockstep_cemu_peek(addr: u32) -> u8;
+    }
+}
+
+#[cfg(feature = "cemu-lockstep")]
+impl CemuCore {
+    /// Load a ROM file into CEmu. Returns CEmu's error code on failure.
+    pub fn load_rom(path: &std::path::Path) -> Result<Self, i32> {
+        let path = std::ffi::CString::new(path.to_string_lossy().as_bytes()).map_err(|_| -1)?;
+        match unsafe { cemu_ffi::lockstep_cemu_load_rom(path.as_ptr()) } {
+            0 => Ok(Self { _private: () }),
+            err => Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "cemu-lockstep")]
+impl ReferenceCore for CemuCore {
+    fn step(&mut self) {
+        unsafe { cemu_ffi::lockstep_cemu_step() }
+    }
+
+    fn registers(&self) -> RegSnapshot {
+        let mut regs = RegSnapshot::default();
+        unsafe { cemu_ffi::lockstep_cemu_registers(&mut regs) };
+        regs
+    }
+
+    fn peek_byte(&mut self, addr: u32) -> u8 {
+        unsafe { cemu_ffi::lockstep_cemu_peek(addr) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn booted(rom: &[u8]) -> Emu {
+        let mut emu = Emu::new();
+        emu.load_rom(rom).unwrap();
+        emu.powered_on = true;
+        emu
+    }
+
+    #[test]
+    fn test_identical_cores_agree() {
+        // LD A,5; INC A; LD (0xD00000),A; JR -7
+        let rom = [0x3E, 0x05, 0x3C, 0x32, 0x00, 0x00, 0xD0, 0x18, 0xF7];
+        let mut ls = Lockstep::new(booted(&rom), booted(&rom));
+        ls.set_mem_check(1, 0xD00000..0xD00010);
+        assert_eq!(ls.run(100).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_reports_first_divergence() {
+        let ours = [0x3E, 0x05, 0x3C, 0x18, 0xFB]; // LD A,5; INC A; JR -5
+        let theirs = [0x3E, 0x05, 0x3D, 0x18, 0xFB]; // LD A,5; DEC A; JR -5
+        let mut ls = Lockstep::new(booted(&ours), booted(&theirs));
+        let div = ls.run(100).unwrap_err();
+        assert_eq!(div.before.pc, 2);
+        assert_eq!(div.ours.a, 6);
+        assert_eq!(div.reference.a, 4);
+        assert!(div.ours.diff(&div.reference).iter().any(|d| d.starts_with("a:")));
+        assert!(div.to_string().contains("PC=000002"));
+    }
+
+    #[test]
+    fn test_memory_divergence() {
+        let ours = [0x3E, 0x01, 0x32, 0x00, 0x00, 0xD0, 0x18, 0xFE]; // LD A,1; LD (D00000),A; JR $
+        let mut theirs = Emu::new();
+        theirs.load_rom(&ours).unwrap();
+        theirs.powered_on = true;
+        let mut ls = Lockstep::new(booted(&ours), theirs);
+        ls.set_mem_check(1, 0xD00000..0xD00004);
+        ls.run(5).unwrap();
+        ls.reference.poke_byte(0xD00001, 0x42);
+        let div = ls.run(5).unwrap_err();
+        assert_eq!(div.memory, Some((0xD00001, 0x00, 0x42)));
+    }
+}
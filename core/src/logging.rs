@@ -0,0 +1,227 @@
+//! Per-subsystem log verbosity
+//!
+//! Each subsystem has its own runtime-settable level so a user chasing a
+//! keypad issue can turn keypad logs up to `Debug` without drowning in LCD
+//! traffic. Messages logged with `log_sub!` are dropped (before formatting)
+//! when their level is above the subsystem's configured level; plain
+//! `log_evt!` messages are not filtered.
+//!
+//! Levels can also be set from a filter string, in the style of
+//! `RUST_LOG`: comma-separated directives, each either a level for every
+//! subsystem (`warn`) or `subsystem=level` (`keypad=debug`), applied in
+//! order, e.g. `"warn,keypad=debug,lcd=off"`.
+
+/// Emulator subsystems with independent log levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSubsystem {
+    Cpu = 0,
+    Bus = 1,
+    Keypad = 2,
+    Lcd = 3,
+    Flash = 4,
+    Interrupt = 5,
+    Panel = 6,
+    Sha256 = 7,
+    Control = 8,
+}
+
+impl LogSubsystem {
+    /// Number of subsystems
+    pub const COUNT: usize = 9;
+
+    /// All subsystems, in id order
+    pub const ALL: [LogSubsystem; Self::COUNT] = [
+        LogSubsystem::Cpu,
+        LogSubsystem::Bus,
+        LogSubsystem::Keypad,
+        LogSubsystem::Lcd,
+        LogSubsystem::Flash,
+        LogSubsystem::Interrupt,
+        LogSubsystem::Panel,
+        LogSubsystem::Sha256,
+        LogSubsystem::Control,
+    ];
+
+    /// Subsystem from its numeric id (used by FFI)
+    pub fn from_id(id: i32) -> Option<Self> {
+        Self::ALL.get(usize::try_from(id).ok()?).copied()
+    }
+
+    /// Short display name
+    pub fn name(self) -> &'static str {
+        match self {
+            LogSubsystem::Cpu => "cpu",
+            LogSubsystem::Bus => "bus",
+            LogSubsystem::Keypad => "keypad",
+            LogSubsystem::Lcd => "lcd",
+            LogSubsystem::Flash => "flash",
+            LogSubsystem::Interrupt => "interrupt",
+            LogSubsystem::Panel => "panel",
+            LogSubsystem::Sha256 => "sha256",
+            LogSubsystem::Control => "control",
+        }
+    }
+
+    /// Subsystem with display name `name` (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sub| sub.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Log verbosity, from quietest to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl LogLevel {
+    /// Level from its numeric value (used by FFI)
+    pub fn from_id(id: i32) -> Option<Self> {
+        Some(match id {
+            0 => LogLevel::Off,
+            1 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Info,
+            4 => LogLevel::Debug,
+            5 => LogLevel::Trace,
+            _ => return None,
+        })
+    }
+
+    /// Level from its name (`off`, `error`, `warn`, `info`, `debug`,
+    /// `trace`; case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "off" => LogLevel::Off,
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" => LogLevel::Debug,
+            "trace" => LogLevel::Trace,
+            _ => return None,
+        })
+    }
+}
+
+/// Configured level for every subsystem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogLevels {
+    levels: [LogLevel; LogSubsystem::COUNT],
+}
+
+impl LogLevels {
+    /// Default level for every subsystem
+    pub const DEFAULT_LEVEL: LogLevel = LogLevel::Info;
+
+    /// All subsystems at the default level
+    pub const fn new() -> Self {
+        Self { levels: [Self::DEFAULT_LEVEL; LogSubsystem::COUNT] }
+    }
+
+    /// Level for one subsystem
+    pub fn get(&self, sub: LogSubsystem) -> LogLevel {
+        self.levels[sub as usize]
+    }
+
+    /// Set the level for one subsystem
+    pub fn set(&mut self, sub: LogSubsystem, level: LogLevel) {
+        self.levels[sub as usize] = level;
+    }
+
+    /// Set the level for every subsystem
+    pub fn set_all(&mut self, level: LogLevel) {
+        self.levels = [level; LogSubsystem::COUNT];
+    }
+
+    /// Apply a filter string (see the module docs). Directives before a bad
+    /// one are still applied; the bad directive is returned as the error.
+    pub fn apply_filter(&mut self, filter: &str) -> Result<(), String> {
+        for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parsed = match directive.split_once('=') {
+                Some((sub, level)) => LogSubsystem::from_name(sub.trim())
+                    .zip(LogLevel::from_name(level.trim()))
+                    .map(|(sub, level)| self.set(sub, level)),
+                None => LogLevel::from_name(directive).map(|level| self.set_all(level)),
+            };
+            if parsed.is_none() {
+                return Err(directive.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if a message at `level` from `sub` should be logged
+    #[inline]
+    pub fn enabled(&self, sub: LogSubsystem, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.levels[sub as usize]
+    }
+}
+
+impl Default for LogLevels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_filter_per_subsystem() {
+        let mut levels = LogLevels::new();
+        assert!(levels.enabled(LogSubsystem::Lcd, LogLevel::Info));
+        assert!(!levels.enabled(LogSubsystem::Keypad, LogLevel::Debug));
+
+        levels.set(LogSubsystem::Keypad, LogLevel::Debug);
+        levels.set(LogSubsystem::Lcd, LogLevel::Off);
+        assert!(levels.enabled(LogSubsystem::Keypad, LogLevel::Debug));
+        assert!(!levels.enabled(LogSubsystem::Keypad, LogLevel::Trace));
+        assert!(!levels.enabled(LogSubsystem::Lcd, LogLevel::Error));
+        assert_eq!(levels.get(LogSubsystem::Cpu), LogLevels::DEFAULT_LEVEL);
+
+        levels.set_all(LogLevel::Warn);
+        assert!(levels.enabled(LogSubsystem::Keypad, LogLevel::Warn));
+        assert!(!levels.enabled(LogSubsystem::Keypad, LogLevel::Info));
+    }
+
+    #[test]
+    fn test_ids() {
+        for (i, s) in LogSubsystem::ALL.iter().enumerate() {
+            assert_eq!(LogSubsystem::from_id(i as i32), Some(*s));
+        }
+        assert_eq!(LogSubsystem::from_id(LogSubsystem::COUNT as i32), None);
+        assert_eq!(LogLevel::from_id(4), Some(LogLevel::Debug));
+        assert_eq!(LogLevel::from_id(6), None);
+        assert_eq!(LogLevel::from_id(-1), None);
+        assert_eq!(LogSubsystem::from_name("SHA256"), Some(LogSubsystem::Sha256));
+        assert_eq!(LogSubsystem::from_name("control"), Some(LogSubsystem::Control));
+        assert_eq!(LogLevel::from_name("Warn"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::from_name("verbose"), None);
+    }
+
+    #[test]
+    fn test_apply_filter() {
+        let mut levels = LogLevels::new();
+        levels.apply_filter("warn, keypad=debug,panel=trace ,lcd=off").unwrap();
+        assert_eq!(levels.get(LogSubsystem::Cpu), LogLevel::Warn);
+        assert_eq!(levels.get(LogSubsystem::Keypad), LogLevel::Debug);
+        assert_eq!(levels.get(LogSubsystem::Panel), LogLevel::Trace);
+        assert_eq!(levels.get(LogSubsystem::Lcd), LogLevel::Off);
+        assert_eq!(levels.apply_filter(""), Ok(()));
+
+        // Later directives override earlier ones
+        levels.apply_filter("sha256=error,info").unwrap();
+        assert_eq!(levels.get(LogSubsystem::Sha256), LogLevel::Info);
+
+        assert_eq!(levels.apply_filter("cpu=debug,usb=trace"), Err("usb=trace".to_string()));
+        assert_eq!(levels.get(LogSubsystem::Cpu), LogLevel::Debug);
+        assert_eq!(levels.apply_filter("bus=loud"), Err("bus=loud".to_string()));
+        assert_eq!(levels.apply_filter("chatty"), Err("chatty".to_string()));
+    }
+}
@@ -71,9 +71,86 @@ enum FlashWriteState {
     SawA0,
 }
 
+/// Copy-on-write page size for memory-mapped flash
+#[cfg(feature = "mmap")]
+const COW_PAGE_SIZE: usize = 0x1000;
+
+/// Backing storage for flash contents
+enum FlashStore {
+    /// Heap copy of the full 4MB image (empty until a ROM is loaded)
+    Owned(Vec<u8>),
+    /// Read-only file mapping. Pages are copied into `overlay` on first write,
+    /// so the mapped file is never modified. Bytes past the end of the file read as 0xFF.
+    #[cfg(feature = "mmap")]
+    Mapped {
+        map: memmap2::Mmap,
+        overlay: Vec<Option<Box<[u8]>>>,
+    },
+}
+
+impl FlashStore {
+    fn is_empty(&self) -> bool {
+        match self {
+            FlashStore::Owned(data) => data.is_empty(),
+            #[cfg(feature = "mmap")]
+            FlashStore::Mapped { .. } => false,
+        }
+    }
+
+    /// Read a byte at a masked offset. Callers check is_empty() first.
+    #[inline(always)]
+    fn get(&self, offset: usize) -> u8 {
+        match self {
+            FlashStore::Owned(data) => data[offset],
+            #[cfg(feature = "mmap")]
+            FlashStore::Mapped { map, overlay } => match &overlay[offset / COW_PAGE_SIZE] {
+                Some(page) => page[offset % COW_PAGE_SIZE],
+                None => map.get(offset).copied().unwrap_or(0xFF),
+            },
+        }
+    }
+
+    /// Mutable reference to a byte at a masked offset, copying its page if mapped
+    #[inline(always)]
+    fn get_mut(&mut self, offset: usize) -> &mut u8 {
+        match self {
+            FlashStore::Owned(data) => &mut data[offset],
+            #[cfg(feature = "mmap")]
+            FlashStore::Mapped { map, overlay } => {
+                let index = offset / COW_PAGE_SIZE;
+                let page = overlay[index].get_or_insert_with(|| {
+                    let start = index * COW_PAGE_SIZE;
+                    (start..start + COW_PAGE_SIZE)
+                        .map(|i| map.get(i).copied().unwrap_or(0xFF))
+                        .collect()
+                });
+                &mut page[offset % COW_PAGE_SIZE]
+            }
+        }
+    }
+
+    /// Full flash contents as a contiguous slice (copies if mapped)
+    fn contents(&self) -> std::borrow::Cow<'_, [u8]> {
+        match self {
+            FlashStore::Owned(data) => std::borrow::Cow::Borrowed(data),
+            #[cfg(feature = "mmap")]
+            FlashStore::Mapped { .. } => {
+                std::borrow::Cow::Owned((0..addr::FLASH_SIZE).map(|i| self.get(i)).collect())
+            }
+        }
+    }
+}
+
+/// Granularity of flash dirty tracking (one bit per 64KB sector)
+pub const FLASH_DIRTY_SECTOR_SIZE: usize = 0x10000;
+
 pub struct Flash {
     /// Flash memory contents
-    data: Vec<u8>,
+    data: FlashStore,
+    /// Sectors modified since the last `take_dirty_sectors()`, one bit per 64KB
+    dirty: u64,
+    /// Incremented on every modification (lets hosts detect quiet periods)
+    write_generation: u64,
     /// Whether flash has been initialized with ROM data
     initialized: bool,
     /// Active flash command (minimal command emulation)
@@ -87,7 +164,9 @@ impl Flash {
     pub fn new() -> Self {
         // Start with empty vec - will be allocated when ROM is loaded
         Self {
-            data: Vec::new(),
+            data: FlashStore::Owned(Vec::new()),
+            dirty: 0,
+            write_generation: 0,
             initialized: false,
             command: FlashCommand::None,
             write_state: FlashWriteState::Idle,
@@ -111,7 +190,8 @@ impl Flash {
         let mut new_data = data.to_vec();
         // Extend with 0xFF to reach full flash size
         new_data.resize(addr::FLASH_SIZE, 0xFF);
-        self.data = new_data;
+        self.data = FlashStore::Owned(new_data);
+        self.dirty = 0;
 
         self.initialized = true;
         self.command = FlashCommand::None;
@@ -119,6 +199,44 @@ impl Flash {
         Ok(())
     }
 
+    /// Load ROM by memory-mapping a file read-only instead of copying it to the heap
+    ///
+    /// Writes (flash programming, file injection, save-state loads) go to a
+    /// copy-on-write page overlay; the file itself is never modified. The file
+    /// must not be changed by other processes while it is mapped.
+    #[cfg(feature = "mmap")]
+    pub fn load_rom_mapped(&mut self, path: &std::path::Path) -> Result<(), FlashError> {
+        let file = std::fs::File::open(path).map_err(|_| FlashError::MapFailed)?;
+        // SAFETY: the mapping is read-only and documented as requiring the file
+        // to stay unmodified while mapped.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|_| FlashError::MapFailed)?;
+        if map.is_empty() {
+            return Err(FlashError::MapFailed);
+        }
+        if map.len() > addr::FLASH_SIZE {
+            return Err(FlashError::RomTooLarge);
+        }
+
+        self.data = FlashStore::Mapped {
+            map,
+            overlay: vec![None; addr::FLASH_SIZE / COW_PAGE_SIZE],
+        };
+        self.dirty = 0;
+        self.initialized = true;
+        self.command = FlashCommand::None;
+        self.write_state = FlashWriteState::Idle;
+        Ok(())
+    }
+
+    /// Check if flash is backed by a memory-mapped file
+    pub fn is_mapped(&self) -> bool {
+        match self.data {
+            FlashStore::Owned(_) => false,
+            #[cfg(feature = "mmap")]
+            FlashStore::Mapped { .. } => true,
+        }
+    }
+
     /// Read a byte from flash
     ///
     /// # Arguments
@@ -144,7 +262,7 @@ impl Flash {
             return 0xFF; // Uninitialized flash reads as 0xFF
         }
         let offset = (addr & (addr::FLASH_SIZE as u32 - 1)) as usize;
-        self.data[offset]
+        self.data.get(offset)
     }
 
     /// Peek flash content with current command status (no state changes)
@@ -166,10 +284,11 @@ impl Flash {
     pub fn write_direct(&mut self, addr: u32, value: u8) {
         // Allocate flash if needed (for testing convenience)
         if self.data.is_empty() {
-            self.data = vec![0xFF; addr::FLASH_SIZE];
+            self.data = FlashStore::Owned(vec![0xFF; addr::FLASH_SIZE]);
         }
         let offset = (addr & (addr::FLASH_SIZE as u32 - 1)) as usize;
-        self.data[offset] = value;
+        *self.data.get_mut(offset) = value;
+        self.mark_dirty(offset, 1);
     }
 
     /// Handle a CPU write to flash (command detection + optional program/erase)
@@ -224,6 +343,9 @@ impl Flash {
                 if value == 0x30 {
                     self.erase_sector(addr);
                     self.command = FlashCommand::SectorErase { reads_left: 3 };
+                } else if addr_masked == 0xAAA && value == 0x10 {
+                    self.erase_chip();
+                    self.command = FlashCommand::SectorErase { reads_left: 3 };
                 }
                 FlashWriteState::Idle
             }
@@ -247,8 +369,23 @@ impl Flash {
         };
         let end = (start + size).min(addr::FLASH_SIZE as u32);
         for offset in start..end {
-            self.data[offset as usize] = 0xFF;
+            *self.data.get_mut(offset as usize) = 0xFF;
+        }
+        self.mark_dirty(start as usize, (end - start) as usize);
+    }
+
+    /// Chip erase (AA/55/80/AA/55/10): every sector back to 0xFF
+    fn erase_chip(&mut self) {
+        if self.data.is_empty() {
+            return;
+        }
+        for offset in 0..addr::FLASH_SIZE {
+            let byte = self.data.get_mut(offset);
+            if *byte != 0xFF {
+                *byte = 0xFF;
+            }
         }
+        self.mark_dirty(0, addr::FLASH_SIZE);
     }
 
     fn program_byte(&mut self, addr: u32, value: u8) {
@@ -256,7 +393,47 @@ impl Flash {
             return;
         }
         let offset = (addr & (addr::FLASH_SIZE as u32 - 1)) as usize;
-        self.data[offset] &= value;
+        *self.data.get_mut(offset) &= value;
+        self.mark_dirty(offset, 1);
+    }
+
+    /// Record a modification of `len` bytes starting at `offset`
+    fn mark_dirty(&mut self, offset: usize, len: usize) {
+        let first = offset / FLASH_DIRTY_SECTOR_SIZE;
+        let last = (offset + len.max(1) - 1) / FLASH_DIRTY_SECTOR_SIZE;
+        for sector in first..=last.min(63) {
+            self.dirty |= 1 << sector;
+        }
+        self.write_generation = self.write_generation.wrapping_add(1);
+    }
+
+    /// Bitmask of 64KB sectors modified since the last `take_dirty_sectors()`
+    pub fn dirty_sectors(&self) -> u64 {
+        self.dirty
+    }
+
+    /// Return and clear the dirty sector bitmask
+    pub fn take_dirty_sectors(&mut self) -> u64 {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Mark sectors dirty again (e.g. after a failed flush)
+    pub fn restore_dirty_sectors(&mut self, mask: u64) {
+        self.dirty |= mask;
+    }
+
+    /// Counter incremented on every flash modification
+    pub fn write_generation(&self) -> u64 {
+        self.write_generation
+    }
+
+    /// Copy of one 64KB dirty-tracking sector
+    pub fn sector(&self, index: usize) -> Vec<u8> {
+        let start = index * FLASH_DIRTY_SECTOR_SIZE;
+        if self.data.is_empty() || start >= addr::FLASH_SIZE {
+            return Vec::new();
+        }
+        (start..start + FLASH_DIRTY_SECTOR_SIZE).map(|i| self.data.get(i)).collect()
     }
 
     /// Check if flash is initialized
@@ -265,14 +442,39 @@ impl Flash {
     }
 
     /// Get raw flash data for save states
-    pub fn data(&self) -> &[u8] {
-        &self.data
+    /// Borrowed for heap-backed flash; a 4MB copy when memory-mapped.
+    pub fn data(&self) -> std::borrow::Cow<'_, [u8]> {
+        self.data.contents()
     }
 
     /// Load flash data from save state
     pub fn load_data(&mut self, data: &[u8]) {
         let len = data.len().min(addr::FLASH_SIZE);
-        self.data[..len].copy_from_slice(&data[..len]);
+        match &mut self.data {
+            FlashStore::Owned(store) => {
+                if store.is_empty() {
+                    *store = vec![0xFF; addr::FLASH_SIZE];
+                }
+                for (sector, chunk) in data[..len].chunks(FLASH_DIRTY_SECTOR_SIZE).enumerate() {
+                    let start = sector * FLASH_DIRTY_SECTOR_SIZE;
+                    if store[start..start + chunk.len()] != *chunk {
+                        store[start..start + chunk.len()].copy_from_slice(chunk);
+                        self.dirty |= 1 << sector;
+                        self.write_generation = self.write_generation.wrapping_add(1);
+                    }
+                }
+            }
+            #[cfg(feature = "mmap")]
+            FlashStore::Mapped { .. } => {
+                // Only touch bytes that differ so unchanged pages stay mapped
+                for (offset, &value) in data[..len].iter().enumerate() {
+                    if self.data.get(offset) != value {
+                        *self.data.get_mut(offset) = value;
+                        self.mark_dirty(offset, 1);
+                    }
+                }
+            }
+        }
         self.initialized = true;
         self.command = FlashCommand::None;
         self.write_state = FlashWriteState::Idle;
@@ -281,8 +483,9 @@ impl Flash {
     /// Reset flash to erased state
     pub fn reset(&mut self) {
         if !self.data.is_empty() {
-            self.data.fill(0xFF);
+            self.data = FlashStore::Owned(vec![0xFF; addr::FLASH_SIZE]);
         }
+        self.dirty = 0;
         self.initialized = false;
         self.command = FlashCommand::None;
         self.write_state = FlashWriteState::Idle;
@@ -302,6 +505,8 @@ pub enum FlashError {
     RomTooLarge,
     /// Flash write protection violation
     WriteProtected,
+    /// ROM file could not be opened or memory-mapped
+    MapFailed,
 }
 
 /// RAM memory state
@@ -457,6 +662,32 @@ mod tests {
             assert_eq!(flash.read(4), 0xFF);
         }
 
+        #[cfg(feature = "mmap")]
+        #[test]
+        fn test_load_rom_mapped_copy_on_write() {
+            let path = std::env::temp_dir()
+                .join(format!("emu_core_mmap_test_{}.rom", std::process::id()));
+            std::fs::write(&path, [0x12, 0x34, 0x56, 0x78]).unwrap();
+
+            let mut flash = Flash::new();
+            assert!(flash.load_rom_mapped(&path).is_ok());
+            assert!(flash.is_mapped());
+            assert_eq!(flash.read(0), 0x12);
+            assert_eq!(flash.read(3), 0x78);
+            assert_eq!(flash.read(4), 0xFF); // Past end of file
+
+            flash.write_direct(1, 0xAB);
+            assert_eq!(flash.read(1), 0xAB);
+            assert_eq!(flash.read(0), 0x12); // Same page, copied from the mapping
+            assert_eq!(&flash.data()[..4], &[0x12, 0xAB, 0x56, 0x78]);
+
+            // The file itself is untouched
+            assert_eq!(std::fs::read(&path).unwrap(), vec![0x12, 0x34, 0x56, 0x78]);
+
+            drop(flash);
+            std::fs::remove_file(&path).unwrap();
+        }
+
         #[test]
         fn test_rom_too_large() {
             let mut flash = Flash::new();
@@ -471,6 +702,82 @@ mod tests {
             assert_eq!(flash.read(0x100), 0xAB);
         }
 
+        #[test]
+        fn test_dirty_sector_tracking() {
+            let mut flash = Flash::new();
+            flash.load_rom(&[0x00; 0x100]).unwrap();
+            assert_eq!(flash.dirty_sectors(), 0);
+            let generation = flash.write_generation();
+
+            // Program a byte in sector 3 via the CPU command sequence
+            flash.write_cpu(0xAAA, 0xAA);
+            flash.write_cpu(0x555, 0x55);
+            flash.write_cpu(0xAAA, 0xA0);
+            flash.write_cpu(0x030010, 0x12);
+            assert_eq!(flash.dirty_sectors(), 1 << 3);
+            assert_ne!(flash.write_generation(), generation);
+            assert_eq!(flash.sector(3)[0x10], 0x12);
+
+            flash.write_direct(0x3F0000, 0);
+            assert_eq!(flash.take_dirty_sectors(), (1 << 3) | (1 << 63));
+            assert_eq!(flash.dirty_sectors(), 0);
+
+            // Loading identical data dirties nothing; a changed sector is dirtied
+            let mut image = flash.data().into_owned();
+            flash.load_data(&image);
+            assert_eq!(flash.dirty_sectors(), 0);
+            image[0x050000] = 0x42;
+            flash.load_data(&image);
+            assert_eq!(flash.dirty_sectors(), 1 << 5);
+        }
+
+        /// Send the six-cycle erase sequence ending in `command` at `addr`
+        fn erase(flash: &mut Flash, addr: u32, command: u8) {
+            for (a, v) in [(0xAAA, 0xAA), (0x555, 0x55), (0xAAA, 0x80), (0xAAA, 0xAA), (0x555, 0x55)] {
+                flash.write_cpu(a, v);
+            }
+            flash.write_cpu(addr, command);
+        }
+
+        #[test]
+        fn test_sector_and_chip_erase() {
+            let mut flash = Flash::new();
+            flash.load_rom(&[0x00; 0x30000]).unwrap();
+
+            // 8KB boot sector: only 0x2000-0x3FFF is erased
+            erase(&mut flash, 0x2100, 0x30);
+            // Busy polling: status for three reads, then data
+            assert_eq!(flash.read(0x2100), 0x80);
+            assert_eq!(flash.read(0x2100), 0x80);
+            assert_eq!(flash.read(0x2100), 0x80);
+            assert_eq!(flash.read(0x2100), 0xFF);
+            assert_eq!(flash.peek(0x1FFF), 0x00);
+            assert_eq!(flash.peek(0x3FFF), 0xFF);
+            assert_eq!(flash.peek(0x4000), 0x00);
+
+            // 64KB sector
+            erase(&mut flash, 0x012345, 0x30);
+            assert_eq!(flash.peek(0x010000), 0xFF);
+            assert_eq!(flash.peek(0x01FFFF), 0xFF);
+            assert_eq!(flash.peek(0x020000), 0x00);
+
+            // A broken sequence does nothing; F0 resets to read mode
+            flash.write_cpu(0xAAA, 0xAA);
+            flash.write_cpu(0x555, 0x55);
+            flash.write_cpu(0x000, 0xF0);
+            flash.write_cpu(0xAAA, 0xA0);
+            flash.write_cpu(0x020000, 0x12);
+            assert_eq!(flash.peek(0x020000), 0x00);
+
+            // Chip erase must be sent to the unlock address
+            erase(&mut flash, 0x020000, 0x10);
+            assert_eq!(flash.peek(0x020000), 0x00);
+            erase(&mut flash, 0xAAA, 0x10);
+            assert_eq!(flash.peek_status(0), 0x80);
+            assert!(flash.data().iter().all(|&b| b == 0xFF));
+            assert_eq!(flash.take_dirty_sectors(), u64::MAX);
+        }
+
         #[test]
         fn test_reset() {
             let mut flash = Flash::new();
@@ -560,7 +867,7 @@ mod tests {
 
     mod port_tests {
         use super::*;
-        use crate::peripherals::{KEYPAD_COLS, KEYPAD_ROWS};
+        use crate::peripherals::{BusAccess, KEYPAD_COLS, KEYPAD_ROWS};
 
         fn empty_keys() -> [[bool; KEYPAD_COLS]; KEYPAD_ROWS] {
             [[false; KEYPAD_COLS]; KEYPAD_ROWS]
@@ -571,7 +878,7 @@ mod tests {
             let mut ports = Ports::new();
             let keys = empty_keys();
             ports.write(0x1000, 0xAB, 0);
-            assert_eq!(ports.read(0x1000, &keys, 0), 0xAB);
+            assert_eq!(ports.read(0x1000, &keys, 0, BusAccess::Cpu), 0xAB);
         }
 
         #[test]
@@ -580,7 +887,7 @@ mod tests {
             let keys = empty_keys();
             ports.write(0x100, 0xFF, 0);
             ports.reset();
-            assert_eq!(ports.read(0x100, &keys, 0), 0x00);
+            assert_eq!(ports.read(0x100, &keys, 0, BusAccess::Cpu), 0x00);
         }
     }
 }
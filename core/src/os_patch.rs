@@ -0,0 +1,172 @@
+//! Load-time OS patches
+//!
+//! Lets OS modders apply byte patches to the flash image every time it is
+//! loaded instead of rebuilding the ROM. Each patch is verified: the
+//! original bytes are checked first and nothing is written unless every
+//! patch in the list matches.
+//!
+//! Patch lists can be written as text, one patch per line:
+//!
+//! ```text
+//! # flash offset: original bytes -> replacement bytes
+//! 021A3C: 3E 01 -> 3E 00
+//! 0x0400F0: CD 3A 12 02 -> 00 00 00 00
+//! ```
+//!
+//! There is no built-in bypass for the boot code's OS signature check. A
+//! patch for it is only trustworthy once verified against a dump of the
+//! boot code it targets, and no such patches ship here; a host that has
+//! one applies it as an ordinary patch list.
+
+use crate::memory::Flash;
+
+/// One verified byte patch in flash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootPatch {
+    /// Flash offset of the first patched byte
+    pub addr: u32,
+    /// Bytes expected at `addr` before patching
+    pub original: Vec<u8>,
+    /// Bytes written in their place (same length as `original`)
+    pub replacement: Vec<u8>,
+}
+
+/// Why a patch list could not be applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// Patch bytes at `addr` do not match the expected original
+    Mismatch { addr: u32 },
+    /// `original` and `replacement` differ in length or run past flash
+    InvalidPatch { addr: u32 },
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::Mismatch { addr } => write!(f, "original bytes differ at {:06X}", addr),
+            PatchError::InvalidPatch { addr } => write!(f, "invalid patch at {:06X}", addr),
+        }
+    }
+}
+
+/// Apply a patch list after verifying every original byte.
+/// Returns the number of bytes written.
+pub fn apply_patches(flash: &mut Flash, patches: &[BootPatch]) -> Result<usize, PatchError> {
+    let data = flash.data();
+    for patch in patches {
+        let start = patch.addr as usize;
+        let end = start + patch.original.len();
+        if patch.original.len() != patch.replacement.len() || end > data.len() {
+            return Err(PatchError::InvalidPatch { addr: patch.addr });
+        }
+        if data[start..end] != patch.original[..] {
+            return Err(PatchError::Mismatch { addr: patch.addr });
+        }
+    }
+    drop(data);
+
+    let mut written = 0;
+    for patch in patches {
+        for (i, &byte) in patch.replacement.iter().enumerate() {
+            flash.write_direct(patch.addr + i as u32, byte);
+        }
+        written += patch.replacement.len();
+    }
+    Ok(written)
+}
+
+/// Why a patch list could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// What was wrong with it
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for PatchParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Parse a text patch list (see the module docs for the format)
+pub fn parse_patch_list(text: &str) -> Result<Vec<BootPatch>, PatchParseError> {
+    let mut patches = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let err = |reason| PatchParseError { line: index + 1, reason };
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (addr, bytes) = line.split_once(':').ok_or(err("missing ':' after address"))?;
+        let (original, replacement) = bytes.split_once("->").ok_or(err("missing '->'"))?;
+        let addr = addr.trim();
+        let addr = addr.strip_prefix("0x").or_else(|| addr.strip_prefix("0X")).unwrap_or(addr);
+        let addr = u32::from_str_radix(addr, 16).map_err(|_| err("invalid address"))?;
+        let original = parse_hex_bytes(original).ok_or(err("invalid original bytes"))?;
+        let replacement = parse_hex_bytes(replacement).ok_or(err("invalid replacement bytes"))?;
+        if original.is_empty() || original.len() != replacement.len() {
+            return Err(err("original and replacement must be the same non-zero length"));
+        }
+        patches.push(BootPatch { addr, original, replacement });
+    }
+    Ok(patches)
+}
+
+/// Hex bytes, optionally separated by whitespace ("3E 01" or "3E01")
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flash_with(bytes: &[u8]) -> Flash {
+        let mut flash = Flash::new();
+        flash.load_rom(bytes).unwrap();
+        flash
+    }
+
+    #[test]
+    fn test_apply_verifies_all_originals_first() {
+        let mut flash = flash_with(&[0x00, 0x11, 0x22, 0x33]);
+        let patches = vec![
+            BootPatch { addr: 1, original: vec![0x11], replacement: vec![0xAA] },
+            BootPatch { addr: 3, original: vec![0x99], replacement: vec![0xBB] },
+        ];
+        assert_eq!(apply_patches(&mut flash, &patches), Err(PatchError::Mismatch { addr: 3 }));
+        // Nothing written on mismatch
+        assert_eq!(&flash.data()[..4], &[0x00, 0x11, 0x22, 0x33]);
+
+        let patches = vec![BootPatch { addr: 2, original: vec![0x22, 0x33], replacement: vec![0x18, 0x00] }];
+        assert_eq!(apply_patches(&mut flash, &patches), Ok(2));
+        assert_eq!(&flash.data()[..4], &[0x00, 0x11, 0x18, 0x00]);
+
+        let bad = vec![BootPatch { addr: 0, original: vec![0x00], replacement: vec![0x00, 0x00] }];
+        assert_eq!(apply_patches(&mut flash, &bad), Err(PatchError::InvalidPatch { addr: 0 }));
+    }
+
+    #[test]
+    fn test_parse_patch_list() {
+        let text = "# comment\n\n021A3C: 3E 01 -> 3E 00\n0x0400F0: CD3A -> 0000 # trailing\n";
+        let patches = parse_patch_list(text).unwrap();
+        assert_eq!(patches, [
+            BootPatch { addr: 0x021A3C, original: vec![0x3E, 0x01], replacement: vec![0x3E, 0x00] },
+            BootPatch { addr: 0x0400F0, original: vec![0xCD, 0x3A], replacement: vec![0x00, 0x00] },
+        ]);
+
+        assert_eq!(parse_patch_list("1000 3E -> 00").unwrap_err().line, 1);
+        assert_eq!(parse_patch_list("\n1000: 3E 01 -> 00").unwrap_err().line, 2);
+        assert!(parse_patch_list("1000: 3 -> 0").is_err());
+        assert!(parse_patch_list("zz: 00 -> 00").is_err());
+    }
+}
@@ -38,7 +38,7 @@ impl Backlight {
                     let old = self.brightness;
                     self.brightness = 0;
                     if old != 0 {
-                        crate::emu::log_evt!("BACKLIGHT: brightness OFF (via control register)");
+                        crate::emu::log_sub!(Lcd, Info, "BACKLIGHT: brightness OFF (via control register)");
                     }
                 }
             }
@@ -47,7 +47,7 @@ impl Backlight {
                 let old = self.brightness;
                 self.brightness = value;
                 if old != value {
-                    crate::emu::log_evt!(
+                    crate::emu::log_sub!(Lcd, Info,
                         "BACKLIGHT: brightness 0x{:02X} -> 0x{:02X} ({}%)",
                         old,
                         value,
@@ -68,4 +68,42 @@ impl Backlight {
     pub fn is_off(&self) -> bool {
         self.brightness < 13 // < 5% brightness
     }
+
+    /// Effective PWM duty the panel is lit at (0-255): the brightness
+    /// register, or 0 once it is too dim to see. Frontends scale the
+    /// rendered screen by `level / 255`.
+    pub fn level(&self) -> u8 {
+        if self.is_off() {
+            0
+        } else {
+            self.brightness
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        let mut backlight = Backlight::new();
+        assert_eq!(backlight.level(), 0xFF);
+
+        backlight.write(0x24, 0x80);
+        assert_eq!(backlight.read(0x24), 0x80);
+        assert_eq!(backlight.level(), 0x80);
+
+        // Below 5% the panel is dark
+        backlight.write(0x24, 12);
+        assert_eq!(backlight.brightness(), 12);
+        assert_eq!(backlight.level(), 0);
+
+        // Control registers switch it off until brightness is written again
+        backlight.write(0x24, 0xC0);
+        backlight.write(0x21, 1);
+        assert_eq!(backlight.level(), 0);
+        backlight.write(0x24, 0xC0);
+        assert_eq!(backlight.level(), 0xC0);
+    }
 }
@@ -3,7 +3,9 @@
 //! Memory-mapped at 0xE00000 (also accessible via OUT0/IN0 at 0xFF00xx)
 //!
 //! These ports control system-level functions like CPU speed, battery status,
-//! and memory protection.
+//! and memory protection. Registers without special behavior read back what
+//! was last written, as CEmu's `control.ports` array does, so the OS sees
+//! the whole 0x00-0xFF block rather than zeros.
 
 /// Register offsets
 mod regs {
@@ -123,6 +125,8 @@ pub struct ControlPorts {
     /// Set when OS writes bit 6 to port 0x00 (checked on original byte before 0x93 mask).
     /// Cleared by ON key wake (keypad_on_check in CEmu).
     off: bool,
+    /// Registers without dedicated handling, read back as written
+    ports: [u8; 0x100],
 }
 
 impl ControlPorts {
@@ -158,12 +162,16 @@ impl ControlPorts {
             battery_charging: false,
             protection_status: 0,
             off: false,
+            ports: [0; 0x100],
         }
     }
 
-    /// Reset the control ports
+    /// Reset the control ports. The battery is not part of the ASIC, so its
+    /// level and charging state are kept.
     pub fn reset(&mut self) {
+        let (status, charging) = self.battery();
         *self = Self::new();
+        self.set_battery(status, charging);
     }
 
     /// Read a control port byte
@@ -232,7 +240,9 @@ impl ControlPorts {
             0x3C => (self.stack_limit >> 16) as u8,
             // Protection status (read-only)
             0x3D => self.protection_status,
-            _ => 0x00,
+            // Write-1-to-clear port for 0x3D
+            0x3E => 0x00,
+            _ => self.ports[addr as usize & 0xFF],
         }
     }
 
@@ -276,7 +286,7 @@ impl ControlPorts {
                 }
 
                 if old != self.power || (value & (1 << 6) != 0) {
-                    crate::emu::log_evt!(
+                    crate::emu::log_sub!(Control, Info,
                         "POWER register: 0x{:02X} -> 0x{:02X} (bit0={} bit1={} bit7={} off={})",
                         old, self.power,
                         self.power & 1,
@@ -341,7 +351,7 @@ impl ControlPorts {
                 self.lcd_enable = (value & 0x0F) << 4 | (value & 0x0F);
                 // Log LCD enable/disable (bit 3 controls LCD on/off)
                 if old != self.lcd_enable {
-                    crate::emu::log_evt!(
+                    crate::emu::log_sub!(Lcd, Info,
                         "LCD_ENABLE: 0x{:02X} -> 0x{:02X} (LCD {})",
                         old, self.lcd_enable,
                         if (self.lcd_enable & (1 << 3)) != 0 { "ON" } else { "OFF" }
@@ -382,7 +392,8 @@ impl ControlPorts {
             0x3C => self.stack_limit = (self.stack_limit & 0x00FFFF) | ((value as u32) << 16),
             // Clear protection status (write-1-to-clear)
             0x3E => self.protection_status &= !value,
-            _ => {}
+            0x3D => {} // Read-only
+            _ => self.ports[addr as usize & 0xFF] = value,
         }
     }
 
@@ -403,6 +414,20 @@ impl ControlPorts {
         self.protection_status = status;
     }
 
+    /// Battery level (0 = discharged through 5 = full, CEmu's BATTERY_*) and
+    /// whether it is charging
+    pub fn battery(&self) -> (u8, bool) {
+        (self.set_battery_status, self.battery_charging)
+    }
+
+    /// Set the battery the boot code probes through the port 0x00/0x02 FSM
+    /// (`status` 0 = discharged through 5 = full, clamped) and the charging
+    /// flag read from port 0x0B bit 1
+    pub fn set_battery(&mut self, status: u8, charging: bool) {
+        self.set_battery_status = status.min(battery::LEVEL_4);
+        self.battery_charging = charging;
+    }
+
     /// Wake device from "off" state.
     /// CEmu: control.off = false; control.readBatteryStatus = ~1;
     /// Clears the off flag and resets battery status FSM.
@@ -534,6 +559,66 @@ impl ControlPorts {
         true
     }
 
+    // ========== State Persistence ==========
+
+    /// Size of control port snapshot in bytes: registers (36) + the
+    /// read-back register block (256)
+    pub const SNAPSHOT_SIZE: usize = 36 + 0x100;
+
+    /// Save every control register, including battery FSM and power-off state
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::SNAPSHOT_SIZE);
+        buf.extend_from_slice(&[
+            self.power, self.cpu_speed, self.prev_clock_mhz as u8, self.cpu_speed_written as u8,
+            self.device_type, self.control_flags, self.unlock_status, self.battery_config,
+            self.panel_control, self.battery_check, self.battery_charging_port, self.battery_reset,
+            self.lcd_enable, self.usb_control, self.flash_unlock, self.general,
+            self.read_battery_status, self.set_battery_status, self.battery_charging as u8,
+            self.protection_status, self.off as u8,
+        ]);
+        for value in [self.privileged, self.protected_start, self.protected_end, self.stack_limit] {
+            buf.extend_from_slice(&value.to_le_bytes()[..3]);
+        }
+        buf.resize(36, 0);
+        buf.extend_from_slice(&self.ports);
+        buf
+    }
+
+    /// Load control registers saved by `to_bytes`
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+        if buf.len() < Self::SNAPSHOT_SIZE {
+            return Err(-105);
+        }
+        let u24 = |pos: usize| u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], 0]);
+        self.power = buf[0];
+        self.cpu_speed = buf[1];
+        self.prev_clock_mhz = buf[2] as u32;
+        self.cpu_speed_written = buf[3] != 0;
+        self.device_type = buf[4];
+        self.control_flags = buf[5];
+        self.unlock_status = buf[6];
+        self.battery_config = buf[7];
+        self.panel_control = buf[8];
+        self.battery_check = buf[9];
+        self.battery_charging_port = buf[10];
+        self.battery_reset = buf[11];
+        self.lcd_enable = buf[12];
+        self.usb_control = buf[13];
+        self.flash_unlock = buf[14];
+        self.general = buf[15];
+        self.read_battery_status = buf[16];
+        self.set_battery_status = buf[17];
+        self.battery_charging = buf[18] != 0;
+        self.protection_status = buf[19];
+        self.off = buf[20] != 0;
+        self.privileged = u24(21);
+        self.protected_start = u24(24);
+        self.protected_end = u24(27);
+        self.stack_limit = u24(30);
+        self.ports.copy_from_slice(&buf[36..Self::SNAPSHOT_SIZE]);
+        Ok(())
+    }
+
     /// Dump all control port values for debugging/comparison with CEmu
     /// Returns a formatted string showing all port values
     pub fn dump(&self) -> String {
@@ -664,6 +749,42 @@ mod tests {
         assert_eq!(ctrl.read(regs::BATTERY_STATUS), 0xFE);
     }
 
+    #[test]
+    fn test_set_battery() {
+        // Probe the way the boot code does: start, then step the FSM through
+        // port 0x09 and 0x00 writes until the status drops to 0
+        fn probe(ctrl: &mut ControlPorts) -> u32 {
+            ctrl.write(regs::BATTERY_CONFIG, 0x90);
+            ctrl.write(regs::PANEL_CONTROL, 0x00);
+            let mut steps = 0;
+            for value in [0x80, 0x00, 0x80, 0x00] {
+                if ctrl.read(regs::BATTERY_STATUS) == 0 {
+                    break;
+                }
+                ctrl.write(regs::POWER, value);
+                steps += 1;
+            }
+            steps
+        }
+
+        let mut ctrl = ControlPorts::new();
+        assert_eq!(ctrl.battery(), (battery::LEVEL_4, false));
+        assert_eq!(probe(&mut ctrl), 4);
+        ctrl.set_battery(battery::LEVEL_1, true);
+        assert_eq!(probe(&mut ctrl), 2);
+        assert_eq!(ctrl.read(regs::BATTERY_CHARGING) & 0x02, 0x02);
+        ctrl.set_battery(battery::DISCHARGED, true);
+        ctrl.write(regs::BATTERY_CONFIG, 0x90);
+        ctrl.write(regs::PANEL_CONTROL, 0x00);
+        assert_eq!(ctrl.read(regs::BATTERY_STATUS), 0);
+
+        ctrl.set_battery(9, false);
+        assert_eq!(ctrl.battery(), (battery::LEVEL_4, false));
+        ctrl.set_battery(battery::LEVEL_2, true);
+        ctrl.reset();
+        assert_eq!(ctrl.battery(), (battery::LEVEL_2, true));
+    }
+
     #[test]
     fn test_device_type_readonly() {
         let mut ctrl = ControlPorts::new();
@@ -835,4 +956,45 @@ mod tests {
         assert_eq!(ctrl.read(0x10), 0x00);
         assert_eq!(ctrl.read(0x30), 0x00);
     }
+
+    #[test]
+    fn test_other_registers_read_back() {
+        let mut ctrl = ControlPorts::new();
+        ctrl.write(0x04, 0x5A);
+        ctrl.write(0x30, 0xC3);
+        ctrl.write(0x3D, 0xFF); // read-only status
+        assert_eq!(ctrl.read(0x04), 0x5A);
+        assert_eq!(ctrl.read(0x30), 0xC3);
+        assert_eq!(ctrl.read(0x3D), 0x00);
+        ctrl.reset();
+        assert_eq!(ctrl.read(0x30), 0x00);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut ctrl = ControlPorts::new();
+        ctrl.write(regs::POWER, 0x40); // off
+        ctrl.write(regs::CPU_SPEED, 0x03);
+        ctrl.write(regs::BATTERY_CONFIG, 0x90); // starts the battery probe
+        ctrl.write(regs::PANEL_CONTROL, 0x00);
+        ctrl.write(regs::GENERAL, 0x01);
+        ctrl.write(0x1D, 0x12);
+        ctrl.write(0x3C, 0xD1);
+        ctrl.write(0x40, 0x77);
+        ctrl.set_stack_violation();
+
+        let bytes = ctrl.to_bytes();
+        assert_eq!(bytes.len(), ControlPorts::SNAPSHOT_SIZE);
+        let mut restored = ControlPorts::new();
+        restored.from_bytes(&bytes).unwrap();
+        assert_eq!(restored.to_bytes(), bytes);
+        assert!(restored.is_off());
+        assert_eq!(restored.read(regs::BATTERY_STATUS), 3);
+        assert_eq!(restored.read(regs::GENERAL), 0x01);
+        assert_eq!(restored.privileged_boundary(), 0xFFFF12);
+        assert_eq!(restored.stack_limit(), 0xD10000);
+        assert_eq!(restored.read(0x3D), 1);
+        assert_eq!(restored.read(0x40), 0x77);
+        assert_eq!(restored.from_bytes(&bytes[..10]), Err(-105));
+    }
 }
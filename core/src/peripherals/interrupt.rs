@@ -10,8 +10,15 @@
 //! - Bit 4: OS Timer
 //! - Bit 10: Keypad (any key in scan mode)
 //! - Bit 11: LCD (VBLANK)
+//! - Bit 12: RTC (tick, alarm, load complete)
 //! - Bit 15: Power
 //! - Bit 19: Wake (power-on wake signal)
+//!
+//! Peripherals drive source lines with `set` (`raise`/`clear_raw`). Status
+//! follows the line (XOR the inversion register) for unlatched sources; a
+//! latched source stays set once its line goes active, until the OS
+//! acknowledges it by writing 1 to the bit at offset 0x08. An IRQ is
+//! pending while any status bit is also enabled.
 
 /// Interrupt source bit masks
 pub mod sources {
@@ -22,6 +29,8 @@ pub mod sources {
     pub const OSTIMER: u32 = 1 << 4;
     pub const KEYPAD: u32 = 1 << 10;
     pub const LCD: u32 = 1 << 11;
+    pub const RTC: u32 = 1 << 12;
+    pub const USB: u32 = 1 << 13;
     pub const PWR: u32 = 1 << 15;
     pub const WAKE: u32 = 1 << 19;
 }
@@ -37,6 +46,8 @@ mod regs {
     pub const RAW: u32 = 0x08;
     /// Latched mode bitmask
     pub const LATCHED: u32 = 0x0C;
+    /// Inverted (active-low) bitmask
+    pub const INVERTED: u32 = 0x10;
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -88,12 +99,12 @@ impl InterruptController {
 
     /// Raise an interrupt (set status bit)
     pub fn raise(&mut self, source: u32) {
-        self.set_source(source, true);
+        self.set(source, true);
     }
 
     /// Clear raw interrupt state (source went inactive)
     pub fn clear_raw(&mut self, source: u32) {
-        self.set_source(source, false);
+        self.set(source, false);
     }
 
     /// Pulse an interrupt (set then clear raw).
@@ -102,8 +113,8 @@ impl InterruptController {
     /// - Inverted: clear step sets status (inverted logic: raw LOW → status HIGH)
     /// Matches CEmu's intrpt_pulse() used for WAKE interrupt on ON key press.
     pub fn pulse(&mut self, mask: u32) {
-        self.set_source(mask, true);
-        self.set_source(mask, false);
+        self.set(mask, true);
+        self.set(mask, false);
     }
 
     /// Acknowledge (clear) interrupt status bits
@@ -113,7 +124,10 @@ impl InterruptController {
         }
     }
 
-    fn set_source(&mut self, mask: u32, set: bool) {
+    /// Drive the `mask` source lines high or low (CEmu's intrpt_set). Status
+    /// follows the line, active-low where inverted; latched bits stay set
+    /// until acknowledged.
+    pub fn set(&mut self, mask: u32, set: bool) {
         if set {
             self.raw |= mask;
         } else {
@@ -178,14 +192,25 @@ impl InterruptController {
             }
             3 | 11 => {
                 bank.latched = (bank.latched & !mask) | (shifted_value & mask);
+                self.refresh(request, mask);
             }
             4 | 12 => {
                 bank.inverted = (bank.inverted & !mask) | (shifted_value & mask);
+                self.refresh(request, mask);
             }
             _ => {}
         }
     }
 
+    /// Recompute `mask` status bits of a bank from the lines after its mode
+    /// changed: unlatched bits follow the (possibly inverted) line, latched
+    /// bits keep what they caught and catch a line that is now active
+    fn refresh(&mut self, request: usize, mask: u32) {
+        let bank = &mut self.banks[request];
+        let active = (self.raw ^ bank.inverted) & mask;
+        bank.status = (bank.status & !mask) | (bank.status & bank.latched & mask) | active;
+    }
+
     /// Get current status (for debugging)
     pub fn status(&self) -> u32 {
         self.banks[0].status
@@ -217,11 +242,14 @@ impl InterruptController {
         if pending & sources::OSTIMER != 0 { names.push("OST"); }
         if pending & sources::KEYPAD != 0 { names.push("KPD"); }
         if pending & sources::LCD != 0 { names.push("LCD"); }
+        if pending & sources::RTC != 0 { names.push("RTC"); }
+        if pending & sources::USB != 0 { names.push("USB"); }
         if pending & sources::PWR != 0 { names.push("PWR"); }
         if pending & sources::WAKE != 0 { names.push("WAKE"); }
         // Check for unknown bits
         let known = sources::ON_KEY | sources::TIMER1 | sources::TIMER2 | sources::TIMER3
-            | sources::OSTIMER | sources::KEYPAD | sources::LCD | sources::PWR | sources::WAKE;
+            | sources::OSTIMER | sources::KEYPAD | sources::LCD | sources::RTC | sources::USB
+            | sources::PWR | sources::WAKE;
         let unknown = pending & !known;
         if unknown != 0 {
             names.push("UNK");
@@ -444,4 +472,35 @@ mod tests {
         ic.acknowledge(sources::TIMER2 | sources::TIMER3);
         assert!(!ic.irq_pending());
     }
+
+    #[test]
+    fn test_inverted_and_latched_modes() {
+        let mut ic = InterruptController::new();
+        ic.write(regs::ENABLED, sources::TIMER1 as u8);
+
+        // Inverting an idle line makes it active at once
+        ic.write(regs::INVERTED, sources::TIMER1 as u8);
+        assert!(ic.irq_pending());
+        ic.raise(sources::TIMER1);
+        assert!(!ic.irq_pending());
+
+        // Back to active-high, unlatched: status follows the line
+        ic.write(regs::INVERTED, 0);
+        assert!(ic.irq_pending());
+        ic.clear_raw(sources::TIMER1);
+        assert!(!ic.irq_pending());
+
+        // Latched: a pulse stays pending until acknowledged, and writing the
+        // raw register only acknowledges latched bits
+        ic.write(regs::LATCHED, sources::TIMER1 as u8);
+        ic.pulse(sources::TIMER1);
+        assert!(ic.irq_pending());
+        ic.write(regs::RAW, sources::TIMER1 as u8);
+        assert!(!ic.irq_pending());
+
+        ic.write(regs::LATCHED, 0);
+        ic.raise(sources::TIMER1);
+        ic.write(regs::RAW, sources::TIMER1 as u8);
+        assert!(ic.irq_pending());
+    }
 }
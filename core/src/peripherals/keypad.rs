@@ -15,12 +15,21 @@
 //! - Index 0x04-0x0B (offset 0x10-0x2F): data[0..15] (16 rows x 2 bytes)
 //! - Index 0x10 (offset 0x40-0x43): gpioEnable
 //!
-//! ## Scan Timing
+//! ## Scan Modes and Timing
 //!
-//! When scanning is initiated (modes 2 or 3), the controller scans one row
-//! at a time with a configurable delay between rows. After all rows are
-//! scanned, status bits are updated and the scan either repeats (modes 1/3)
-//! or stops (mode 2 single scan).
+//! - Mode 0: idle
+//! - Mode 1: any-key — no scan; every data row holds the OR of all rows,
+//!   refreshed on key events and register writes
+//! - Mode 2: single scan, then back to mode 0
+//! - Mode 3: continuous scan
+//!
+//! Scans run on the scheduler (`EventId::Keypad`, 6MHz clock, as CEmu's
+//! keypad_scan_event). Writing mode 2 or 3 starts one row scan after
+//! `rowWait` ticks; each row scan stores that row's data and schedules the
+//! next `rowWait` ticks later. After the last row, status bit 0 is set and
+//! mode 3 starts over after `2 + scanWait + rowWait` ticks, while mode 2
+//! goes idle. The emulator applies the `needs_scan_event`/`needs_scan_clear`
+//! flags set by register writes to the scheduler, like the LCD's.
 //!
 //! ## Status Bits (status register, index 0x02)
 //!
@@ -45,17 +54,16 @@ mod status {
     pub const ANY_KEY: u8 = 0x04;
 }
 
-/// Control register modes (kept for documentation/future use)
-#[allow(dead_code)]
+/// Control register modes
 mod mode {
-    /// Idle mode (no scanning)
+    /// Idle (no scanning)
     pub const IDLE: u8 = 0;
-    /// Single scan mode
-    pub const SINGLE: u8 = 1;
-    /// Continuous scan mode (single scan, goes idle after)
-    pub const CONTINUOUS: u8 = 2;
-    /// Multi-group scan mode (repeating scan)
-    pub const MULTI_GROUP: u8 = 3;
+    /// Any-key detection (all rows ORed, no scan)
+    pub const ANY_KEY: u8 = 1;
+    /// One scan of all rows, then idle
+    pub const SINGLE_SCAN: u8 = 2;
+    /// Repeating scan
+    pub const CONTINUOUS_SCAN: u8 = 3;
 }
 
 /// Register offsets (for documentation; actual addressing uses index-based scheme)
@@ -94,8 +102,12 @@ pub struct KeypadController {
     gpio_enable: u32,
     /// Whether a scan is currently in progress
     scanning: bool,
-    /// Cycles until next row scan or scan completion
-    scan_cycles_remaining: u32,
+    /// Flag: scan started, schedule EventId::Keypad in `row_wait()` ticks
+    /// (set by control writes, cleared by emu)
+    pub needs_scan_event: bool,
+    /// Flag: scan stopped, clear EventId::Keypad (set by control writes,
+    /// cleared by emu)
+    pub needs_scan_clear: bool,
     /// Previous scan results for detecting data changes
     prev_scan_data: [u16; KEYPAD_MAX_ROWS],
     /// Whether any key was detected during current scan
@@ -123,7 +135,8 @@ impl KeypadController {
             data: [0x0000; KEYPAD_MAX_ROWS],
             gpio_enable: 0,
             scanning: false,
-            scan_cycles_remaining: 0,
+            needs_scan_event: false,
+            needs_scan_clear: false,
             prev_scan_data: [0x0000; KEYPAD_MAX_ROWS],
             any_key_in_scan: false,
             data_changed_in_scan: false,
@@ -145,7 +158,8 @@ impl KeypadController {
         self.data = [0x0000; KEYPAD_MAX_ROWS];
         self.gpio_enable = 0;
         self.scanning = false;
-        self.scan_cycles_remaining = 0;
+        self.needs_scan_event = false;
+        self.needs_scan_clear = false;
         self.prev_scan_data = [0x0000; KEYPAD_MAX_ROWS];
         self.any_key_in_scan = false;
         self.data_changed_in_scan = false;
@@ -165,12 +179,12 @@ impl KeypadController {
         self.control = (self.control & !0x03) | (m as u32 & 0x03);
     }
 
-    /// Get row wait cycles (bits 15:2 of control)
-    fn row_wait(&self) -> u32 {
+    /// Get row wait in 6MHz ticks (bits 15:2 of control)
+    pub fn row_wait(&self) -> u32 {
         (self.control >> 2) & 0x3FFF
     }
 
-    /// Get scan wait cycles (bits 31:16 of control)
+    /// Get scan wait in 6MHz ticks (bits 31:16 of control)
     fn scan_wait(&self) -> u32 {
         (self.control >> 16) & 0xFFFF
     }
@@ -241,21 +255,32 @@ impl KeypadController {
 
     // ========== Scan logic ==========
 
-    /// Start a new scan cycle
+    /// Start a new scan cycle (CEmu: row = 0; sched_set(SCHED_KEYPAD, rowWait))
     fn start_scan(&mut self) {
         self.scan_row = 0;
         self.scanning = true;
-        self.scan_cycles_remaining = self.row_wait();
+        self.needs_scan_event = true;
+        self.needs_scan_clear = false;
         self.any_key_in_scan = false;
         self.data_changed_in_scan = false;
     }
 
-    /// Complete the current scan cycle
+    /// Stop scanning (CEmu: sched_clear(SCHED_KEYPAD))
+    fn stop_scan(&mut self) {
+        if self.scanning {
+            self.needs_scan_clear = true;
+        }
+        self.needs_scan_event = false;
+        self.scanning = false;
+    }
+
+    /// Complete the current scan cycle. Returns the ticks until the next
+    /// scan, or None if the controller went idle.
     /// Matches CEmu's keypad_scan_event completion logic:
     /// - Sets status bit 0 (scan done) always
-    /// - If mode & 1 (modes 1 or 3): restart scanning with scanWait + rowWait + 2
+    /// - If mode & 1 (mode 3): restart scanning after scanWait + rowWait + 2
     /// - If mode & 1 == 0 (mode 2): go to idle (set mode = 0)
-    fn finish_scan(&mut self) {
+    fn finish_scan(&mut self) -> Option<u32> {
         // Set scan complete status
         self.status |= status::SCAN_DONE;
 
@@ -272,16 +297,17 @@ impl KeypadController {
         // Save current data as previous for next comparison
         self.prev_scan_data = self.data;
 
-        // CEmu: if (keypad.mode & 1) — modes 1 and 3 keep scanning
-        if self.mode() & 1 != 0 {
-            self.scan_row = 0;
-            self.scan_cycles_remaining = 2 + self.scan_wait() + self.row_wait();
+        // CEmu: if (keypad.mode & 1) — only mode 3 is scanning with bit 0 set
+        self.scan_row = 0;
+        if self.mode() == mode::CONTINUOUS_SCAN {
             self.any_key_in_scan = false;
             self.data_changed_in_scan = false;
+            Some(2 + self.scan_wait() + self.row_wait())
         } else {
-            // Mode 2 (continuous but bit 0 clear): go to idle after single scan
+            // Mode 2: go to idle after a single scan
             self.set_mode(mode::IDLE);
             self.scanning = false;
+            None
         }
     }
 
@@ -303,73 +329,50 @@ impl KeypadController {
         (1u16 << col_limit) - 1
     }
 
-    /// Advance the keypad controller by the given number of CPU cycles.
-    /// This handles scan timing and status bit updates.
-    /// Returns true if an interrupt should be raised.
-    pub fn tick(&mut self, cycles: u32, key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> bool {
+    /// Scan event (EventId::Keypad): scan the current row and return the
+    /// ticks until the next event, or None once a single scan is done.
+    /// The caller raises the keypad interrupt if `interrupt_pending()`.
+    pub fn scan_event(&mut self, key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> Option<u32> {
         if !self.scanning {
-            return false;
+            return None;
         }
 
-        // Scan activity logging removed — was generating 9000+ messages per session
-
-        let mut cycles_left = cycles;
-        let mut interrupt_pending = false;
-
-        while cycles_left > 0 && self.scanning {
-            if cycles_left >= self.scan_cycles_remaining {
-                cycles_left -= self.scan_cycles_remaining;
-                self.scan_cycles_remaining = 0;
-
-                let row = self.scan_row as usize;
-                let row_limit = self.row_limit();
-
-                // Scan the current row
-                if row < row_limit {
-                    let mut row_data: u16 = 0;
-                    if row < KEYPAD_ROWS {
-                        // Use query_row_data for edge detection (CEmu: keypad_query_keymap)
-                        row_data = self.query_row_data(row, key_state) & self.data_mask();
-                    }
-
-                    // Check if data changed from previous scan
-                    if self.data[row] != row_data {
-                        self.status |= status::DATA_CHANGED;
-                        self.data[row] = row_data;
-                    }
-
-                    // Check if any key is pressed in this row
-                    if row_data != 0 {
-                        self.any_key_in_scan = true;
-                        // Key scan logging removed to reduce log noise
-                    }
-
-                    // Check if data changed from previous scan cycle
-                    if row_data != self.prev_scan_data[row] {
-                        self.data_changed_in_scan = true;
-                    }
-                }
+        let row = self.scan_row as usize;
+        if row < self.row_limit() {
+            let mut row_data: u16 = 0;
+            if row < KEYPAD_ROWS {
+                // Use query_row_data for edge detection (CEmu: keypad_query_keymap)
+                row_data = self.query_row_data(row, key_state) & self.data_mask();
+            }
 
-                self.scan_row += 1;
+            // Check if data changed from the stored row
+            if self.data[row] != row_data {
+                self.status |= status::DATA_CHANGED;
+                self.data[row] = row_data;
+            }
 
-                if (self.scan_row as usize) < self.rows() as usize {
-                    // Schedule next row
-                    self.scan_cycles_remaining = self.row_wait();
-                } else {
-                    // Scan complete
-                    self.finish_scan();
-                    // Check if we should raise an interrupt
-                    if (self.status & self.enable) != 0 {
-                        interrupt_pending = true;
-                    }
-                }
-            } else {
-                self.scan_cycles_remaining -= cycles_left;
-                cycles_left = 0;
+            // Check if any key is pressed in this row
+            if row_data != 0 {
+                self.any_key_in_scan = true;
+            }
+
+            // Check if data changed from previous scan cycle
+            if row_data != self.prev_scan_data[row] {
+                self.data_changed_in_scan = true;
             }
         }
 
-        interrupt_pending
+        self.scan_row += 1;
+        if (self.scan_row as usize) < self.rows() as usize {
+            Some(self.row_wait())
+        } else {
+            self.finish_scan()
+        }
+    }
+
+    /// True if an enabled status bit is set (CEmu: status & enable)
+    pub fn interrupt_pending(&self) -> bool {
+        self.status & self.enable != 0
     }
 
     /// Query row data (destructive - clears edge flags after reading)
@@ -401,14 +404,14 @@ impl KeypadController {
     }
 
     /// Check if keypad interrupt should fire based on mode and key state
-    /// Returns true in mode 1 (any-key mode) or mode 2 (continuous) when any key is pressed
+    /// Returns true in mode 1 (any-key) or mode 2 (single scan) when any key is pressed
     /// Note: CPU wake is handled separately via the any_key_wake signal
     pub fn check_interrupt(&self, key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> bool {
         // CEmu's keypad_any_check() runs when mode == 1 (any-key detection mode)
-        // Mode 2 (continuous) also generates interrupts via the scan mechanism
+        // Mode 2 (single scan) also generates interrupts via the scan mechanism
         // The TI-OS typically uses mode 1 for key detection
         let m = self.mode();
-        if m != mode::SINGLE && m != mode::CONTINUOUS {
+        if m != mode::ANY_KEY && m != mode::SINGLE_SCAN {
             return false;
         }
 
@@ -433,7 +436,7 @@ impl KeypadController {
     /// Read a register byte
     /// addr is offset from controller base (0x00-0x4F)
     /// key_state is the current keyboard matrix state
-    pub fn read(&mut self, addr: u32, _key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> u8 {
+    pub fn read(&self, addr: u32, _key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> u8 {
         // Beyond implemented registers (0x00-0x47), return 0
         if addr >= 0x48 {
             return 0;
@@ -504,7 +507,7 @@ impl KeypadController {
                     self.start_scan();
                 } else {
                     // Mode 0 or 1: stop scanning and do immediate key check
-                    self.scanning = false;
+                    self.stop_scan();
                     self.needs_any_key_check = true;
                 }
             }
@@ -567,7 +570,7 @@ impl KeypadController {
         let current_mode = self.mode();
         // CEmu: if (keypad.mode != 1) return;
         // Only run in mode 1 (any-key detection mode)
-        if current_mode != mode::SINGLE {
+        if current_mode != mode::ANY_KEY {
             return false;
         }
 
@@ -590,7 +593,7 @@ impl KeypadController {
         any &= data_mask;
 
         if any != 0 {
-            crate::emu::log_evt!("ANY_KEY_CHECK: any=0x{:04X} mask=0x{:04X} status=0x{:02X}",
+            crate::emu::log_sub!(Keypad, Info, "ANY_KEY_CHECK: any=0x{:04X} mask=0x{:04X} status=0x{:02X}",
                 any, mask, self.status);
         }
 
@@ -617,6 +620,84 @@ impl KeypadController {
     }
 }
 
+// ========== State Persistence ==========
+
+impl KeypadController {
+    /// Size of keypad state snapshot in bytes
+    /// 4 (control) + 4 (size) + 4 (gpio_enable) + 1 (status) + 1 (enable) + 1 (scan_row)
+    /// + 1 (flags) + 32 (data) + 32 (prev_scan_data) + 8 (edge flags) = 88
+    pub const SNAPSHOT_SIZE: usize = 88;
+
+    /// Save keypad state to bytes
+    pub fn to_bytes(&self) -> [u8; Self::SNAPSHOT_SIZE] {
+        let mut buf = [0u8; Self::SNAPSHOT_SIZE];
+        let mut pos = 0;
+
+        buf[pos..pos+4].copy_from_slice(&self.control.to_le_bytes()); pos += 4;
+        buf[pos..pos+4].copy_from_slice(&self.size.to_le_bytes()); pos += 4;
+        buf[pos..pos+4].copy_from_slice(&self.gpio_enable.to_le_bytes()); pos += 4;
+        buf[pos] = self.status; pos += 1;
+        buf[pos] = self.enable; pos += 1;
+        buf[pos] = self.scan_row; pos += 1;
+        buf[pos] = self.scanning as u8
+            | (self.any_key_in_scan as u8) << 1
+            | (self.data_changed_in_scan as u8) << 2;
+        pos += 1;
+        for row in self.data.iter().chain(&self.prev_scan_data) {
+            buf[pos..pos+2].copy_from_slice(&row.to_le_bytes());
+            pos += 2;
+        }
+        for row in &self.key_edge_flags {
+            buf[pos] = row.iter().enumerate().fold(0, |acc, (col, &edge)| acc | (edge as u8) << col);
+            pos += 1;
+        }
+
+        buf
+    }
+
+    /// Load keypad state from bytes. Pending scheduler requests are dropped;
+    /// the scan event is restored with the scheduler.
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+        if buf.len() < Self::SNAPSHOT_SIZE {
+            return Err(-105);
+        }
+
+        let mut pos = 0;
+        let word = |pos: usize| u32::from_le_bytes(buf[pos..pos+4].try_into().unwrap());
+
+        self.control = word(pos); pos += 4;
+        self.size = word(pos); pos += 4;
+        self.gpio_enable = word(pos); pos += 4;
+        self.status = buf[pos]; pos += 1;
+        self.enable = buf[pos] & 0x07; pos += 1;
+        self.scan_row = buf[pos]; pos += 1;
+        let flags = buf[pos]; pos += 1;
+        self.scanning = flags & 1 != 0;
+        self.any_key_in_scan = flags & 2 != 0;
+        self.data_changed_in_scan = flags & 4 != 0;
+        for i in 0..KEYPAD_MAX_ROWS * 2 {
+            let value = u16::from_le_bytes([buf[pos], buf[pos+1]]);
+            if i < KEYPAD_MAX_ROWS {
+                self.data[i] = value;
+            } else {
+                self.prev_scan_data[i - KEYPAD_MAX_ROWS] = value;
+            }
+            pos += 2;
+        }
+        for row in &mut self.key_edge_flags {
+            for (col, edge) in row.iter_mut().enumerate() {
+                *edge = buf[pos] & (1 << col) != 0;
+            }
+            pos += 1;
+        }
+
+        self.needs_any_key_check = false;
+        self.needs_scan_event = false;
+        self.needs_scan_clear = false;
+        Ok(())
+    }
+}
+
 impl Default for KeypadController {
     fn default() -> Self {
         Self::new()
@@ -628,9 +709,11 @@ mod tests {
     use super::*;
 
     fn scan_keys(kp: &mut KeypadController, keys: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) {
-        // Enable scanning in mode 2|1=3 (repeating scan) and run enough cycles.
-        kp.write(regs::CONTROL, mode::MULTI_GROUP);
-        kp.tick(5000, keys);
+        // Start a repeating scan (mode 3) and run one full pass of row events
+        kp.write(regs::CONTROL, mode::CONTINUOUS_SCAN);
+        for _ in 0..kp.rows() {
+            kp.scan_event(keys);
+        }
     }
 
     fn empty_key_state() -> [[bool; KEYPAD_COLS]; KEYPAD_ROWS] {
@@ -640,7 +723,7 @@ mod tests {
     /// Set up mode 1 (any-key) and update data registers with key state.
     /// This matches how TI-OS uses the keypad for key detection.
     fn update_keys(kp: &mut KeypadController, keys: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) {
-        kp.write(regs::CONTROL, mode::SINGLE);
+        kp.write(regs::CONTROL, mode::ANY_KEY);
         kp.any_key_check(keys);
     }
 
@@ -657,7 +740,7 @@ mod tests {
     fn test_reset() {
         let mut kp = KeypadController::new();
         // Set some state via writes
-        kp.write(regs::CONTROL, mode::MULTI_GROUP);
+        kp.write(regs::CONTROL, mode::CONTINUOUS_SCAN);
         kp.enable = 0x04;
         kp.status = 0x01;
 
@@ -672,7 +755,7 @@ mod tests {
 
     #[test]
     fn test_read_no_keys() {
-        let mut kp = KeypadController::new();
+        let kp = KeypadController::new();
         let keys = empty_key_state();
 
         // All rows should return 0x00 (no keys pressed, active-high convention)
@@ -767,8 +850,8 @@ mod tests {
         // No keys, no interrupt
         assert!(!kp.check_interrupt(&keys));
 
-        // Enable continuous mode and interrupt mask
-        kp.write(regs::CONTROL, mode::CONTINUOUS);
+        // Enable single scan mode and interrupt mask
+        kp.write(regs::CONTROL, mode::SINGLE_SCAN);
         kp.enable = 0x04;
 
         // Still no keys
@@ -790,19 +873,81 @@ mod tests {
         kp.write(regs::CONTROL, mode::IDLE);
         assert!(!kp.check_interrupt(&keys));
 
-        // SINGLE mode (mode 1) - interrupt! (CEmu's keypad_any_check runs for mode 1)
-        kp.write(regs::CONTROL, mode::SINGLE);
+        // Any-key mode (mode 1) - interrupt! (CEmu's keypad_any_check runs for mode 1)
+        kp.write(regs::CONTROL, mode::ANY_KEY);
         assert!(kp.check_interrupt(&keys));
 
-        // CONTINUOUS mode - interrupt!
-        kp.write(regs::CONTROL, mode::CONTINUOUS);
+        // Single scan mode - interrupt!
+        kp.write(regs::CONTROL, mode::SINGLE_SCAN);
         assert!(kp.check_interrupt(&keys));
 
-        // MULTI_GROUP mode - no interrupt (handled differently)
-        kp.write(regs::CONTROL, mode::MULTI_GROUP);
+        // Continuous scan mode - no interrupt (raised by the scan events)
+        kp.write(regs::CONTROL, mode::CONTINUOUS_SCAN);
         assert!(!kp.check_interrupt(&keys));
     }
 
+    #[test]
+    fn test_scan_modes_and_timing() {
+        let mut kp = KeypadController::new();
+        let mut keys = empty_key_state();
+        keys[3][5] = true;
+        kp.write(regs::INT_ACK, status::SCAN_DONE);
+
+        // Single scan: rowWait = 10, scanWait = 100
+        kp.write(regs::CONTROL + 2, 100);
+        kp.write(regs::CONTROL, (10 << 2) | mode::SINGLE_SCAN);
+        assert!(kp.is_scanning());
+        assert!(kp.needs_scan_event);
+        assert_eq!(kp.row_wait(), 10);
+        for row in 0..7 {
+            assert_eq!(kp.scan_event(&keys), Some(10), "row {}", row);
+            assert!(!kp.interrupt_pending());
+        }
+        assert_eq!(kp.scan_event(&keys), None);
+        assert_eq!(kp.mode(), mode::IDLE);
+        assert!(!kp.is_scanning());
+        assert!(kp.interrupt_pending());
+        assert_eq!(kp.read(regs::DATA_BASE + 6, &keys), 1 << 5);
+        assert_eq!(kp.read(regs::DATA_BASE + 4, &keys), 0);
+
+        // Continuous scan restarts after 2 + scanWait + rowWait
+        kp.write(regs::INT_STATUS, 0xFF);
+        kp.write(regs::CONTROL, (10 << 2) | mode::CONTINUOUS_SCAN);
+        for _ in 0..7 {
+            kp.scan_event(&keys);
+        }
+        assert_eq!(kp.scan_event(&keys), Some(2 + 100 + 10));
+        assert!(kp.is_scanning());
+
+        // Back to any-key mode stops the scan
+        kp.needs_scan_clear = false;
+        kp.write(regs::CONTROL, mode::ANY_KEY);
+        assert!(!kp.is_scanning());
+        assert!(kp.needs_scan_clear);
+        assert_eq!(kp.scan_event(&keys), None);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut kp = KeypadController::new();
+        let mut keys = empty_key_state();
+        keys[1][2] = true;
+        kp.set_key_edge(6, 1, true);
+        kp.write(regs::INT_ACK, 0x07);
+        kp.write(regs::CONTROL, (5 << 2) | mode::CONTINUOUS_SCAN);
+        kp.scan_event(&keys);
+        kp.scan_event(&keys);
+
+        let mut restored = KeypadController::new();
+        restored.from_bytes(&kp.to_bytes()).unwrap();
+        assert_eq!(restored.to_bytes(), kp.to_bytes());
+        assert!(restored.is_scanning());
+        assert_eq!(restored.scan_row, 2);
+        assert_eq!(restored.read(regs::DATA_BASE + 2, &keys), 1 << 2);
+        assert!(restored.key_edge_flags[6][1]);
+        assert_eq!(restored.from_bytes(&[0; 4]), Err(-105));
+    }
+
     #[test]
     fn test_read_out_of_range_row() {
         let mut kp = KeypadController::new();
@@ -816,7 +961,7 @@ mod tests {
 
     #[test]
     fn test_read_unknown_register() {
-        let mut kp = KeypadController::new();
+        let kp = KeypadController::new();
         let keys = empty_key_state();
 
         // Unknown register should return 0x00 (CEmu returns 0)
@@ -4,6 +4,8 @@
 //!
 //! The LCD controller manages the display timing and DMA from VRAM.
 //! VRAM is typically at 0xD40000 and contains 320x240 RGB565 pixels.
+//! Control bits 1-3 select the format: modes 0-3 are 1/2/4/8bpp palette
+//! indices, the rest 16bpp direct color (`Emu::render_frame` scans it out).
 //!
 //! Register map matches CEmu's lcd.c:
 //! - 0x000-0x00F: Timing registers
@@ -56,6 +58,8 @@ mod ctrl {
     pub const BPP_MASK: u32 = 0x07;
     /// BGR swap (bit 8)
     pub const BGR: u32 = 1 << 8;
+    /// Big-endian pixel order within a byte (bit 10)
+    pub const BEPO: u32 = 1 << 10;
     /// LCD power enable
     pub const PWR: u32 = 1 << 11;
 }
@@ -112,6 +116,11 @@ pub struct LcdController {
     upcurr: u32,
     /// Lower panel current address
     lpcurr: u32,
+    /// UPBASE latched by DMA at the start of the current frame
+    scanout_base: u32,
+    /// Previous scanout base, set when a frame latched a different UPBASE
+    /// (taken by emu.rs to report buffer swaps)
+    base_swap: Option<u32>,
     /// 256-entry color palette (stored as raw bytes, 2 bytes per entry)
     palette: [u8; 512],
     /// Pre-converted palette: BGR565 (from 1555 raw palette)
@@ -210,6 +219,8 @@ impl LcdController {
             lpbase: 0,
             upcurr: 0,
             lpcurr: 0,
+            scanout_base: DEFAULT_VRAM_BASE,
+            base_swap: None,
             palette: [0; 512],
             palette_bgr565: [0; 256],
             palette_rgb565: [0; 256],
@@ -255,6 +266,8 @@ impl LcdController {
         self.lpbase = 0;
         self.upcurr = 0;
         self.lpcurr = 0;
+        self.scanout_base = DEFAULT_VRAM_BASE;
+        self.base_swap = None;
         self.palette = [0; 512];
         self.palette_bgr565 = [0; 256];
         self.palette_rgb565 = [0; 256];
@@ -334,6 +347,12 @@ impl LcdController {
         ((self.control >> ctrl::BPP_SHIFT) & ctrl::BPP_MASK) as u8
     }
 
+    /// True if the first pixel of a byte is in its most significant bits
+    /// (BEPO, control bit 10); only matters below 8bpp
+    pub fn big_endian_pixels(&self) -> bool {
+        self.control & ctrl::BEPO != 0
+    }
+
     /// Get pre-converted palette for current BGR mode.
     /// Returns BGR565 palette when BGR bit (control bit 8) is clear,
     /// or RGB565 palette when BGR bit is set.
@@ -493,6 +512,10 @@ impl LcdController {
             // Prefill phase: fill 64 bytes at a time
             if self.pos == 0 {
                 self.upcurr = self.upbase;
+                if self.upbase != self.scanout_base {
+                    self.base_swap.get_or_insert(self.scanout_base);
+                    self.scanout_base = self.upbase;
+                }
             }
             // Advance UPCURR by 64 bytes (simulating DMA read)
             self.upcurr = self.upcurr.wrapping_add(64);
@@ -808,9 +831,23 @@ impl LcdController {
         self.control = value;
     }
 
-    /// Set upbase directly
+    /// Set upbase directly (also treated as already latched, so restoring
+    /// state does not report a buffer swap)
     pub fn set_upbase(&mut self, value: u32) {
         self.upbase = value;
+        self.scanout_base = value;
+        self.base_swap = None;
+    }
+
+    /// Base address the current frame is being scanned out from
+    pub fn scanout_base(&self) -> u32 {
+        self.scanout_base
+    }
+
+    /// If a frame started scanning out from a new UPBASE since the last call,
+    /// returns (old base, new base)
+    pub fn take_base_swap(&mut self) -> Option<(u32, u32)> {
+        self.base_swap.take().map(|old| (old, self.scanout_base))
     }
 
     /// Set interrupt mask directly
@@ -1229,6 +1266,26 @@ mod tests {
         assert!(result.repeat_ticks.is_none());
     }
 
+    #[test]
+    fn test_base_swap_latched_at_frame_start() {
+        let mut lcd = LcdController::new();
+        lcd.prefill = true;
+        lcd.process_dma();
+        assert_eq!(lcd.take_base_swap(), None);
+
+        // Writing UPBASE mid-frame doesn't swap until the next frame latches it
+        lcd.write(regs::UPBASE, 0x00);
+        lcd.write(regs::UPBASE + 1, 0x2C);
+        lcd.write(regs::UPBASE + 2, 0xD5);
+        assert_eq!(lcd.take_base_swap(), None);
+
+        lcd.pos = 0;
+        lcd.prefill = true;
+        lcd.process_dma();
+        assert_eq!(lcd.take_base_swap(), Some((DEFAULT_VRAM_BASE, 0xD52C00)));
+        assert_eq!(lcd.take_base_swap(), None);
+    }
+
     #[test]
     fn test_process_dma_active() {
         let mut lcd = LcdController::new();
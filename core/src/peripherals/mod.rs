@@ -21,6 +21,7 @@ pub mod rtc;
 pub mod sha256;
 pub mod spi;
 pub mod timer;
+pub mod usb;
 pub mod watchdog;
 
 pub use backlight::Backlight;
@@ -29,13 +30,16 @@ pub use flash::FlashController;
 pub use interrupt::InterruptController;
 pub use keypad::{KeypadController, KEYPAD_COLS, KEYPAD_ROWS};
 pub use lcd::{LcdController, LCD_HEIGHT, LCD_WIDTH};
+pub use panel::{PanelStub, PANEL_HEIGHT, PANEL_WIDTH};
 pub use rtc::RtcController;
 pub use sha256::Sha256Controller;
 pub use spi::SpiController;
 pub use timer::GeneralTimers;
+pub use usb::{SerialAccessoryConfig, UsbController};
 pub use watchdog::WatchdogController;
 
 use interrupt::sources;
+use crate::scheduler::ClockId;
 
 /// Port address regions (offsets from 0xE00000)
 const CONTROL_BASE: u32 = 0x000000; // 0xE00000
@@ -61,6 +65,17 @@ const RTC_END: u32 = 0x180100;
 const BACKLIGHT_BASE: u32 = 0x1B0000; // 0xFB0000
 const BACKLIGHT_END: u32 = 0x1B0100;
 
+/// Who is reading: read-sensitive registers (FIFO data, status that clears
+/// on read) only react to the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BusAccess {
+    /// CPU or DMA access, with hardware side effects
+    #[default]
+    Cpu,
+    /// Debugger access: returns the current value and changes no state
+    Debug,
+}
+
 /// Peripheral subsystem containing all hardware controllers
 #[derive(Debug, Clone)]
 pub struct Peripherals {
@@ -86,12 +101,12 @@ pub struct Peripherals {
     pub backlight: Backlight,
     /// Fallback register storage for unmapped ports
     fallback: Vec<u8>,
+    /// Address of the last access that hit fallback storage (for the bus's unimplemented-access registry)
+    fallback_access: Option<u32>,
     /// Keypad state (updated by Emu)
     key_state: [[bool; KEYPAD_COLS]; KEYPAD_ROWS],
     /// OS Timer state (32KHz crystal-based timer, bit 4 interrupt)
     os_timer_state: bool,
-    /// OS Timer cycle accumulator
-    os_timer_cycles: u64,
 }
 
 impl Peripherals {
@@ -100,10 +115,7 @@ impl Peripherals {
 
     /// OS Timer tick intervals (in 32KHz ticks) based on CPU speed
     /// From CEmu: ost_ticks[4] = { 73, 153, 217, 313 }
-    const OS_TIMER_TICKS: [u32; 4] = [73, 153, 217, 313];
-
-    /// 32KHz crystal frequency
-    const CLOCK_32K: u32 = 32768;
+    const OS_TIMER_TICKS: [u64; 4] = [73, 153, 217, 313];
 
     /// Create new peripheral subsystem
     pub fn new() -> Self {
@@ -119,9 +131,9 @@ impl Peripherals {
             sha256: Sha256Controller::new(),
             backlight: Backlight::new(),
             fallback: vec![0x00; Self::FALLBACK_SIZE],
+            fallback_access: None,
             key_state: [[false; KEYPAD_COLS]; KEYPAD_ROWS],
             os_timer_state: false,
-            os_timer_cycles: 0,
         }
     }
 
@@ -163,21 +175,24 @@ impl Peripherals {
         self.watchdog.reset();
         self.rtc.reset();
         self.sha256.reset();
+        self.backlight.reset();
         self.fallback.fill(0x00);
+        self.fallback_access = None;
         self.key_state = [[false; KEYPAD_COLS]; KEYPAD_ROWS];
         self.os_timer_state = false;
-        self.os_timer_cycles = 0;
     }
 
     /// Read from a port address
     /// addr is offset from 0xE00000
     /// key_state is the current keyboard matrix
     /// current_cycles: CPU cycle count for timing-sensitive peripherals
+    /// access: Debug reads leave every peripheral untouched
     pub fn read(
         &mut self,
         addr: u32,
         key_state: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS],
         current_cycles: u64,
+        access: BusAccess,
     ) -> u8 {
         // Get CPU speed for timing calculations
         let cpu_speed = self.control.cpu_speed();
@@ -219,6 +234,9 @@ impl Peripherals {
 
             // Unmapped - return from fallback storage
             _ => {
+                if access == BusAccess::Cpu {
+                    self.fallback_access = Some(addr);
+                }
                 let offset = (addr as usize) % Self::FALLBACK_SIZE;
                 self.fallback[offset]
             }
@@ -262,11 +280,7 @@ impl Peripherals {
                 let int_state = self.timers.interrupt_state();
                 let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
                 for (i, &src) in timer_sources.iter().enumerate() {
-                    if int_state & (1 << i) != 0 {
-                        self.interrupt.raise(src);
-                    } else {
-                        self.interrupt.clear_raw(src);
-                    }
+                    self.interrupt.set(src, int_state & (1 << i) != 0);
                 }
             }
 
@@ -279,7 +293,7 @@ impl Peripherals {
                 let flag_after = self.keypad.needs_any_key_check;
 
                 if flag_after && !flag_before {
-                    crate::emu::log_evt!("KEYPAD: offset=0x{:02X} set needs_any_key_check flag", offset);
+                    crate::emu::log_sub!(Keypad, Info, "KEYPAD: offset=0x{:02X} set needs_any_key_check flag", offset);
                 }
 
                 // CEmu calls keypad_any_check() after certain writes (STATUS, SIZE, CONTROL mode 0/1)
@@ -290,11 +304,7 @@ impl Peripherals {
                     let should_interrupt = self.keypad.any_key_check(&self.key_state);
 
                     // Update keypad interrupt state
-                    if should_interrupt {
-                        self.interrupt.raise(sources::KEYPAD);
-                    } else {
-                        self.interrupt.clear_raw(sources::KEYPAD);
-                    }
+                    self.interrupt.set(sources::KEYPAD, should_interrupt);
                 }
             }
 
@@ -313,12 +323,18 @@ impl Peripherals {
 
             // Unmapped - store in fallback
             _ => {
+                self.fallback_access = Some(addr);
                 let offset = (addr as usize) % Self::FALLBACK_SIZE;
                 self.fallback[offset] = value;
             }
         }
     }
 
+    /// Take the address of the last access that fell through to fallback storage
+    pub fn take_fallback_access(&mut self) -> Option<u32> {
+        self.fallback_access.take()
+    }
+
     /// Tick all peripherals
     /// delay_remaining: CPU cycles remaining until the TimerDelay event fires (0 if not active)
     /// Returns true if any interrupt is pending
@@ -339,87 +355,49 @@ impl Peripherals {
         let int_state = self.timers.interrupt_state();
         let timer_sources = [sources::TIMER1, sources::TIMER2, sources::TIMER3];
         for (i, &src) in timer_sources.iter().enumerate() {
-            if int_state & (1 << i) != 0 {
-                self.interrupt.raise(src);
-            } else {
-                self.interrupt.clear_raw(src);
-            }
+            self.interrupt.set(src, int_state & (1 << i) != 0);
         }
 
+        // Watchdog counts CPU or 32kHz ticks; emu.rs acts on needs_reset/needs_nmi
+        let cpu_hz = ClockId::Cpu.rate(cpu_speed);
+        self.watchdog.tick(cycles, cpu_hz);
+
         // LCD interrupts are now driven by scheduler events (EventId::Lcd / EventId::LcdDma)
         // in emu.rs, matching CEmu's lcd_event()/lcd_dma() architecture.
         // Check LCD scheduling flags set by control register writes.
         // (The actual scheduling is done by emu.rs which checks these flags.)
 
-        // Tick keypad scan timing and update interrupt state
-        // CEmu calls intrpt_set(INT_KEYPAD, status & enable) which sets OR clears raw
-        let keypad_scan_irq = self.keypad.tick(cycles, &self.key_state);
-        let keypad_any_irq = self.keypad.check_interrupt(&self.key_state);
-        if keypad_scan_irq || keypad_any_irq {
-            self.interrupt.raise(sources::KEYPAD);
-        } else {
-            self.interrupt.clear_raw(sources::KEYPAD);
-        }
+        // Keypad scans are driven by EventId::Keypad in emu.rs. The line is
+        // level-triggered: CEmu calls intrpt_set(INT_KEYPAD, status & enable),
+        // which sets OR clears raw, plus any held key in modes 1 and 2
+        self.interrupt.set(sources::KEYPAD, self.keypad.interrupt_pending() || self.keypad.check_interrupt(&self.key_state));
 
-        // Tick OS Timer (32KHz crystal-based timer)
-        self.tick_os_timer(cycles);
+        // RTC events run on the scheduler; the line follows the status
+        // register (CEmu: intrpt_set(INT_RTC, rtc.interrupt)) so an
+        // acknowledge drops it
+        self.interrupt.set(sources::RTC, self.rtc.has_interrupt());
 
         self.interrupt.irq_pending()
     }
 
-    /// Tick the OS Timer (32KHz crystal timer, generates bit 4 interrupt)
+    /// OS Timer event (EventId::OsTimer, 32KHz crystal timer, bit 4
+    /// interrupt). Returns the 32KHz ticks until the next one.
     /// Based on CEmu's ost_event in timers.c
     ///
     /// CEmu order (from timers.c ost_event):
     ///   1. intrpt_set(INT_OSTIMER, gpt.osTimerState)  — set interrupt to OLD state
     ///   2. sched_repeat(id, ...)                       — reschedule
     ///   3. gpt.osTimerState = !gpt.osTimerState        — toggle state
-    fn tick_os_timer(&mut self, cycles: u32) {
-        // Get CPU speed from control port (bits 0-1)
-        let speed = (self.control.read(0x01) & 0x03) as usize;
-
-        // CPU clock rates: 6MHz, 12MHz, 24MHz, 48MHz
-        let cpu_clock: u64 = match speed {
-            0 => 6_000_000,
-            1 => 12_000_000,
-            2 => 24_000_000,
-            _ => 48_000_000,
-        };
+    pub fn os_timer_event(&mut self) -> u64 {
+        self.interrupt.set(sources::OSTIMER, self.os_timer_state);
 
-        // Cycles per 32KHz tick at current CPU speed
-        let cycles_per_32k_tick = cpu_clock / Self::CLOCK_32K as u64;
-
-        self.os_timer_cycles += cycles as u64;
-
-        // Check if enough cycles have passed to toggle state
-        loop {
-            // OS Timer interval in 32K ticks depends on state:
-            // - When state is false: wait ost_ticks[speed] ticks
-            // - When state is true: wait 1 tick
-            let ticks_needed = if self.os_timer_state {
-                1u64
-            } else {
-                Self::OS_TIMER_TICKS[speed] as u64
-            };
-            let cycles_needed = ticks_needed * cycles_per_32k_tick;
-
-            if self.os_timer_cycles < cycles_needed {
-                break;
-            }
-
-            self.os_timer_cycles -= cycles_needed;
-
-            // CEmu order: set interrupt to OLD state FIRST, then toggle
-            // intrpt_set(INT_OSTIMER, gpt.osTimerState) with old state
-            if self.os_timer_state {
-                self.interrupt.raise(sources::OSTIMER);
-            } else {
-                self.interrupt.clear_raw(sources::OSTIMER);
-            }
+        // Held high for 1 tick, low for ost_ticks[speed]
+        let speed = (self.control.read(0x01) & 0x03) as usize;
+        let ticks = if self.os_timer_state { 1 } else { Self::OS_TIMER_TICKS[speed] };
 
-            // Toggle state AFTER setting interrupt
-            self.os_timer_state = !self.os_timer_state;
-        }
+        // Toggle state AFTER setting interrupt
+        self.os_timer_state = !self.os_timer_state;
+        ticks
     }
 
     /// Check if any interrupt is pending
@@ -505,7 +483,7 @@ impl Peripherals {
         // OS Timer state (16 bytes)
         buf[pos] = if self.os_timer_state { 1 } else { 0 }; pos += 1;
         pos += 7; // Align to 8 bytes
-        buf[pos..pos+8].copy_from_slice(&self.os_timer_cycles.to_le_bytes()); pos += 8;
+        pos += 8; // Cycle accumulator from before EventId::OsTimer
 
         // Key state as bit-packed (8 bytes - 64 bits for 8x8 matrix)
         for row in 0..KEYPAD_ROWS {
@@ -628,7 +606,7 @@ impl Peripherals {
         // OS Timer state
         self.os_timer_state = buf[pos] != 0; pos += 1;
         pos += 7;
-        self.os_timer_cycles = u64::from_le_bytes(buf[pos..pos+8].try_into().unwrap()); pos += 8;
+        pos += 8;
 
         // Key state
         for row in 0..KEYPAD_ROWS {
@@ -719,7 +697,7 @@ mod tests {
 
     impl PeripheralsTestExt for Peripherals {
         fn read_test(&mut self, addr: u32, keys: &[[bool; KEYPAD_COLS]; KEYPAD_ROWS]) -> u8 {
-            self.read(addr, keys, 0)
+            self.read(addr, keys, 0, BusAccess::Cpu)
         }
 
         fn write_test(&mut self, addr: u32, value: u8) {
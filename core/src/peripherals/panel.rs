@@ -7,6 +7,13 @@
 //! pixel format, etc.) but does not read status back. This stub absorbs
 //! commands and stores key register values for future use.
 //!
+//! Pixels sent with RAMWR/RAMWRC land in a 320x240 ARGB8888 framebuffer in
+//! display orientation, so programs that draw through the SPI interface
+//! instead of the LCD controller can still be seen (`framebuffer()`).
+//! CASET/RASET select the window written, and MADCTL's MX/MY/MV bits mirror
+//! and exchange the axes; MV means CASET selects rows and RASET columns.
+//! COLMOD 0x?6 takes 18-bit pixels (3 bytes), anything else 16-bit RGB565.
+//!
 //! Reference: CEmu panel.c / panel.h
 
 /// ST7789V commands used during initialization
@@ -28,6 +35,17 @@ mod cmd {
     pub const COLMOD: u8 = 0x3A;
 }
 
+/// Panel framebuffer width in display orientation
+pub const PANEL_WIDTH: usize = 320;
+/// Panel framebuffer height in display orientation
+pub const PANEL_HEIGHT: usize = 240;
+
+/// MADCTL bits
+const MADCTL_MY: u8 = 0x80;
+const MADCTL_MX: u8 = 0x40;
+const MADCTL_MV: u8 = 0x20;
+const MADCTL_BGR: u8 = 0x08;
+
 /// Panel stub state
 #[derive(Debug, Clone)]
 pub struct PanelStub {
@@ -51,6 +69,16 @@ pub struct PanelStub {
     caset: [u8; 4],
     /// Row address range [start_hi, start_lo, end_hi, end_lo]
     raset: [u8; 4],
+    /// Column address counter for RAMWR
+    col: u16,
+    /// Row address counter for RAMWR
+    row: u16,
+    /// Bytes of the pixel being received
+    pixel: [u8; 3],
+    /// Number of bytes in `pixel` so far
+    pixel_len: u8,
+    /// Frame memory, ARGB8888 in display orientation
+    framebuffer: Vec<u32>,
 }
 
 impl PanelStub {
@@ -66,6 +94,11 @@ impl PanelStub {
             colmod: 0,
             caset: [0; 4],
             raset: [0; 4],
+            col: 0,
+            row: 0,
+            pixel: [0; 3],
+            pixel_len: 0,
+            framebuffer: vec![0xFF000000; PANEL_WIDTH * PANEL_HEIGHT],
         }
     }
 
@@ -73,6 +106,62 @@ impl PanelStub {
         *self = Self::new();
     }
 
+    /// Frame memory as ARGB8888, `PANEL_WIDTH` x `PANEL_HEIGHT`, row-major
+    pub fn framebuffer(&self) -> &[u32] {
+        &self.framebuffer
+    }
+
+    // ========== State Persistence ==========
+
+    /// Size of panel state snapshot in bytes: registers (32) + frame memory
+    /// as 24-bit RGB
+    pub const SNAPSHOT_SIZE: usize = 32 + PANEL_WIDTH * PANEL_HEIGHT * 3;
+
+    /// Save panel state to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::SNAPSHOT_SIZE);
+        buf.extend_from_slice(&[self.current_cmd, self.param_idx, self.param_count]);
+        buf.extend_from_slice(&[self.sleeping as u8, self.display_on as u8, self.inverted as u8]);
+        buf.extend_from_slice(&[self.madctl, self.colmod]);
+        buf.extend_from_slice(&self.caset);
+        buf.extend_from_slice(&self.raset);
+        buf.extend_from_slice(&self.col.to_le_bytes());
+        buf.extend_from_slice(&self.row.to_le_bytes());
+        buf.extend_from_slice(&self.pixel);
+        buf.push(self.pixel_len);
+        buf.resize(32, 0); // Padding
+        for &argb in &self.framebuffer {
+            buf.extend_from_slice(&argb.to_be_bytes()[1..]);
+        }
+        buf
+    }
+
+    /// Load panel state from bytes
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+        if buf.len() < Self::SNAPSHOT_SIZE {
+            return Err(-105);
+        }
+
+        self.current_cmd = buf[0];
+        self.param_idx = buf[1];
+        self.param_count = buf[2];
+        self.sleeping = buf[3] != 0;
+        self.display_on = buf[4] != 0;
+        self.inverted = buf[5] != 0;
+        self.madctl = buf[6];
+        self.colmod = buf[7];
+        self.caset.copy_from_slice(&buf[8..12]);
+        self.raset.copy_from_slice(&buf[12..16]);
+        self.col = u16::from_le_bytes([buf[16], buf[17]]);
+        self.row = u16::from_le_bytes([buf[18], buf[19]]);
+        self.pixel.copy_from_slice(&buf[20..23]);
+        self.pixel_len = buf[23].min(2);
+        for (argb, rgb) in self.framebuffer.iter_mut().zip(buf[32..Self::SNAPSHOT_SIZE].chunks_exact(3)) {
+            *argb = u32::from_be_bytes([0xFF, rgb[0], rgb[1], rgb[2]]);
+        }
+        Ok(())
+    }
+
     /// Process a 9-bit SPI frame from the controller.
     /// Bit 8: 0 = command, 1 = data/parameter.
     /// Returns the number of bits in the response frame (always 9).
@@ -93,6 +182,7 @@ impl PanelStub {
     fn write_cmd(&mut self, cmd: u8) {
         self.current_cmd = cmd;
         self.param_idx = 0;
+        crate::emu::log_sub!(Panel, Trace, "PANEL: command 0x{:02X}", cmd);
 
         // Determine expected parameter count for this command
         self.param_count = match cmd {
@@ -108,16 +198,94 @@ impl PanelStub {
             cmd::MADCTL => 1,
             cmd::COLMOD => 1,
             cmd::RAMWR | cmd::RAMWRC => 0, // Variable length, absorb until next command
-            _ => 0xFF, // Unknown command — absorb all params until next command
+            _ => {
+                // Unknown command — absorb all params until next command
+                crate::emu::log_sub!(Panel, Debug, "PANEL: unknown command 0x{:02X}", cmd);
+                0xFF
+            }
         };
 
-        if cmd == cmd::SWRESET {
-            self.reset();
+        match cmd {
+            cmd::SWRESET => {
+                // Registers return to defaults; frame memory keeps its contents
+                let framebuffer = std::mem::take(&mut self.framebuffer);
+                self.reset();
+                self.framebuffer = framebuffer;
+            }
+            cmd::RAMWR => {
+                self.col = Self::range(self.caset).0;
+                self.row = Self::range(self.raset).0;
+                self.pixel_len = 0;
+            }
+            cmd::RAMWRC => self.pixel_len = 0,
+            _ => {}
+        }
+    }
+
+    /// (start, end) of an address range parameter block
+    fn range(params: [u8; 4]) -> (u16, u16) {
+        let start = u16::from_be_bytes([params[0], params[1]]);
+        let end = u16::from_be_bytes([params[2], params[3]]);
+        (start, end.max(start))
+    }
+
+    /// Collect a byte of pixel data, storing the pixel once it is complete
+    fn write_pixel_byte(&mut self, byte: u8) {
+        self.pixel[self.pixel_len as usize] = byte;
+        self.pixel_len += 1;
+        let (r, g, b) = if self.colmod & 0x07 == 0x06 {
+            if self.pixel_len < 3 {
+                return;
+            }
+            // 18-bit: 6 bits per component in the top of each byte
+            let expand = |c: u8| (c & 0xFC) | (c >> 6);
+            (expand(self.pixel[0]), expand(self.pixel[1]), expand(self.pixel[2]))
+        } else {
+            if self.pixel_len < 2 {
+                return;
+            }
+            let rgb565 = u16::from_be_bytes([self.pixel[0], self.pixel[1]]);
+            let (r, g, b) = ((rgb565 >> 11) as u8 & 0x1F, (rgb565 >> 5) as u8 & 0x3F, rgb565 as u8 & 0x1F);
+            ((r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2))
+        };
+        self.pixel_len = 0;
+        let (r, b) = if self.madctl & MADCTL_BGR != 0 { (b, r) } else { (r, b) };
+        self.store_pixel(0xFF000000 | (r as u32) << 16 | (g as u32) << 8 | b as u32);
+    }
+
+    /// Store a pixel at the address counters and advance them through the
+    /// CASET/RASET window, wrapping to its start
+    fn store_pixel(&mut self, argb: u32) {
+        let (mut x, mut y) = (self.col as usize, self.row as usize);
+        if self.madctl & MADCTL_MV != 0 {
+            std::mem::swap(&mut x, &mut y);
+        }
+        if x < PANEL_WIDTH && y < PANEL_HEIGHT {
+            if self.madctl & MADCTL_MX != 0 {
+                x = PANEL_WIDTH - 1 - x;
+            }
+            if self.madctl & MADCTL_MY != 0 {
+                y = PANEL_HEIGHT - 1 - y;
+            }
+            self.framebuffer[y * PANEL_WIDTH + x] = argb;
+        }
+
+        let (col_start, col_end) = Self::range(self.caset);
+        let (row_start, row_end) = Self::range(self.raset);
+        if self.col >= col_end {
+            self.col = col_start;
+            self.row = if self.row >= row_end { row_start } else { self.row + 1 };
+        } else {
+            self.col += 1;
         }
     }
 
     /// Process a parameter byte for the current command
     fn write_param(&mut self, param: u8) {
+        if matches!(self.current_cmd, cmd::RAMWR | cmd::RAMWRC) {
+            self.write_pixel_byte(param);
+            return;
+        }
         if self.param_count == 0 {
             return; // No parameters expected or already consumed
         }
@@ -219,4 +387,53 @@ mod tests {
         assert_eq!(panel.transfer(0x00), 9); // Always 9-bit
         assert_eq!(panel.transfer(0x100), 9);
     }
+
+    fn send(panel: &mut PanelStub, command: u8, params: &[u8]) {
+        panel.transfer(command as u32);
+        for &param in params {
+            panel.transfer(0x100 | param as u32);
+        }
+    }
+
+    #[test]
+    fn test_ramwr_fills_window() {
+        let mut panel = PanelStub::new();
+        send(&mut panel, cmd::COLMOD, &[0x55]);
+        send(&mut panel, cmd::CASET, &[0x00, 0x0A, 0x00, 0x0B]); // x 10..11
+        send(&mut panel, cmd::RASET, &[0x00, 0x05, 0x00, 0x06]); // y 5..6
+        // Red, green, blue, white, then wrap back to the window start
+        send(&mut panel, cmd::RAMWR, &[0xF8, 0x00, 0x07, 0xE0, 0x00, 0x1F, 0xFF, 0xFF, 0x84, 0x10]);
+
+        let fb = panel.framebuffer();
+        assert_eq!(fb.len(), PANEL_WIDTH * PANEL_HEIGHT);
+        assert_eq!(fb[5 * PANEL_WIDTH + 10], 0xFF848284);
+        assert_eq!(fb[5 * PANEL_WIDTH + 11], 0xFF00FF00);
+        assert_eq!(fb[6 * PANEL_WIDTH + 10], 0xFF0000FF);
+        assert_eq!(fb[6 * PANEL_WIDTH + 11], 0xFFFFFFFF);
+        assert_eq!(fb[5 * PANEL_WIDTH + 12], 0xFF000000);
+
+        // RAMWRC continues where RAMWR stopped
+        send(&mut panel, cmd::RAMWRC, &[0x00, 0x00]);
+        assert_eq!(panel.framebuffer()[5 * PANEL_WIDTH + 11], 0xFF000000);
+
+        // Frame memory survives a software reset
+        send(&mut panel, cmd::SWRESET, &[]);
+        assert_eq!(panel.framebuffer()[6 * PANEL_WIDTH + 11], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_madctl_orientation_and_18bit() {
+        let mut panel = PanelStub::new();
+        send(&mut panel, cmd::COLMOD, &[0x66]);
+        // MV | MX | BGR: CASET picks rows, RASET columns, columns mirrored
+        send(&mut panel, cmd::MADCTL, &[MADCTL_MV | MADCTL_MX | MADCTL_BGR]);
+        send(&mut panel, cmd::CASET, &[0x00, 0x02, 0x00, 0x03]);
+        send(&mut panel, cmd::RASET, &[0x00, 0x00, 0x00, 0x00]);
+        send(&mut panel, cmd::RAMWR, &[0xFC, 0x00, 0x00, 0x00, 0x00, 0xFC]);
+
+        let fb = panel.framebuffer();
+        // First pixel (red sent, BGR panel shows blue) at row 2, mirrored column 0
+        assert_eq!(fb[2 * PANEL_WIDTH + PANEL_WIDTH - 1], 0xFF0000FF);
+        assert_eq!(fb[3 * PANEL_WIDTH + PANEL_WIDTH - 1], 0xFFFF0000);
+    }
 }
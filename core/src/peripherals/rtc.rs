@@ -11,6 +11,11 @@
 //! - LOAD_LATCH: Copies load registers to latched, fires load-latch interrupt
 //!
 //! The RTC uses a 32.768 kHz clock. One full second is TICKS_PER_SECOND (32768) ticks.
+//!
+//! The hardware has no notion of a calendar; TI-OS counts the day register
+//! from 1 January 1997 (`EPOCH_UNIX_SECS`). `HostClockSync` keeps the counter
+//! on the host's wall clock for frontends that want the calculator's clock
+//! to be right without setting it by hand.
 
 /// Number of bits for time fields (8 bits each for sec, min, hour)
 const RTC_TIME_BITS: u8 = 8 * 3; // 24 bits
@@ -36,6 +41,11 @@ pub const LATCH_TICK_OFFSET: u64 = 16429;
 /// Delay for load-latch event after latch
 const LOAD_LATCH_TICK_OFFSET: u64 = LATCH_TICK_OFFSET + 7;
 
+/// Unix time of day 0 of the counter as TI-OS uses it (1997-01-01 00:00)
+pub const EPOCH_UNIX_SECS: i64 = 852_076_800;
+/// Seconds the counter can hold before the 16-bit day register wraps
+const COUNTER_SPAN_SECS: i64 = 0x10000 * 86400;
+
 /// RTC operating mode (matches CEmu's rtc_mode enum)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RtcMode {
@@ -113,6 +123,8 @@ pub struct RtcController {
     load: RtcDatetime,
     /// Alarm time
     alarm: RtcAlarm,
+    /// Loads that have finished writing the counter (the OS set the clock)
+    loads_completed: u32,
 }
 
 impl RtcController {
@@ -131,6 +143,7 @@ impl RtcController {
             latched: RtcDatetime::default(),
             load: RtcDatetime::default(),
             alarm: RtcAlarm::default(),
+            loads_completed: 0,
         }
     }
 
@@ -152,6 +165,7 @@ impl RtcController {
         }
         let effective_end = if end_tick >= RTC_DATETIME_BITS {
             self.control &= !0x40; // Clear load bit
+            self.loads_completed += 1;
             RTC_DATETIME_BITS
         } else {
             end_tick
@@ -166,7 +180,7 @@ impl RtcController {
 
     /// Read a register byte
     /// addr is offset from controller base (0-0xFF)
-    pub fn read(&mut self, addr: u32, _current_cycles: u64, _cpu_speed: u8) -> u8 {
+    pub fn read(&self, addr: u32, _current_cycles: u64, _cpu_speed: u8) -> u8 {
         let index = addr & 0xFF;
         let bit_offset = ((index & 3) << 3) as u32;
 
@@ -336,7 +350,8 @@ impl RtcController {
 
                     // Check alarm match
                     // CEmu: counter.value >> (RTC_DATETIME_BITS - RTC_TIME_BITS) == alarm.value
-                    let counter_time = (self.counter.to_value() >> (RTC_DATETIME_BITS - RTC_TIME_BITS)) as u32;
+                    // (its time fields sit above the day; ours sit below it)
+                    let counter_time = (self.counter.to_value() & ((1 << RTC_TIME_BITS) - 1)) as u32;
                     if counter_time == self.alarm.to_value() {
                         interrupts |= 16;
                     }
@@ -414,6 +429,81 @@ impl RtcController {
     pub fn has_interrupt(&self) -> bool {
         self.interrupt != 0
     }
+
+    /// Counter as seconds since the TI-OS epoch
+    pub fn time_secs(&self) -> u64 {
+        let c = self.counter;
+        c.day as u64 * 86400 + c.hour as u64 * 3600 + c.min as u64 * 60 + c.sec as u64
+    }
+
+    /// Set the counter (and latched registers, so the next read agrees) to
+    /// `secs` since the TI-OS epoch. Days past the 16-bit register wrap.
+    pub fn set_time_secs(&mut self, secs: u64) {
+        let time = secs % 86400;
+        self.counter = RtcDatetime {
+            sec: (time % 60) as u8,
+            min: (time / 60 % 60) as u8,
+            hour: (time / 3600) as u8,
+            day: (secs / 86400) as u16,
+        };
+        self.latched = self.counter;
+    }
+
+    /// Set the counter from a Unix time (seconds, already in the time zone
+    /// the calculator should show). Times before the epoch clamp to it.
+    pub fn set_unix_time(&mut self, unix_secs: i64) {
+        self.set_time_secs(unix_secs.saturating_sub(EPOCH_UNIX_SECS).max(0) as u64);
+    }
+
+    /// Loads that have completed since reset
+    pub fn loads_completed(&self) -> u32 {
+        self.loads_completed
+    }
+}
+
+/// Keeps the RTC counter on host wall-clock time.
+///
+/// The counter runs from the emulated 32kHz clock, so it falls behind while
+/// the emulator is paused or throttled and runs ahead when fast-forwarding.
+/// `check` pulls it back whenever it is more than a second off. When the OS
+/// sets the clock itself (a completed load), the difference it chose is kept
+/// rather than undone, so a user can still set a different time.
+#[derive(Debug, Clone)]
+pub struct HostClockSync {
+    /// Counter seconds minus host Unix seconds
+    offset: i64,
+    /// `loads_completed` when `offset` was last taken
+    loads_seen: u32,
+}
+
+impl HostClockSync {
+    /// Seed `rtc` from `host_unix_secs` shifted by `utc_offset_secs` (the
+    /// calculator shows local time) and start tracking
+    pub fn new(rtc: &mut RtcController, host_unix_secs: i64, utc_offset_secs: i32) -> Self {
+        rtc.set_unix_time(host_unix_secs + utc_offset_secs as i64);
+        Self { offset: Self::offset_of(rtc, host_unix_secs), loads_seen: rtc.loads_completed }
+    }
+
+    fn offset_of(rtc: &RtcController, host_unix_secs: i64) -> i64 {
+        rtc.time_secs() as i64 + EPOCH_UNIX_SECS - host_unix_secs
+    }
+
+    /// Correct `rtc` against `host_unix_secs`. Returns true if it was reset.
+    pub fn check(&mut self, rtc: &mut RtcController, host_unix_secs: i64) -> bool {
+        if rtc.loads_completed > self.loads_seen {
+            self.loads_seen = rtc.loads_completed;
+            self.offset = Self::offset_of(rtc, host_unix_secs);
+            return false;
+        }
+        // Fewer loads than before: the RTC was reset, so put the time back
+        self.loads_seen = rtc.loads_completed;
+        let target = (host_unix_secs + self.offset - EPOCH_UNIX_SECS).rem_euclid(COUNTER_SPAN_SECS);
+        if (rtc.time_secs() as i64 - target).abs() <= 1 {
+            return false;
+        }
+        rtc.set_time_secs(target as u64);
+        true
+    }
 }
 
 impl Default for RtcController {
@@ -438,7 +528,7 @@ mod tests {
 
     #[test]
     fn test_read_time() {
-        let mut rtc = RtcController::new();
+        let rtc = RtcController::new();
         assert_eq!(rtc.read(0x00, 0, CPU_SPEED_48MHZ), 0); // sec
         assert_eq!(rtc.read(0x04, 0, CPU_SPEED_48MHZ), 0); // min
         assert_eq!(rtc.read(0x08, 0, CPU_SPEED_48MHZ), 0); // hour
@@ -446,7 +536,7 @@ mod tests {
 
     #[test]
     fn test_read_revision() {
-        let mut rtc = RtcController::new();
+        let rtc = RtcController::new();
         assert_eq!(rtc.read(0x3C, 0, CPU_SPEED_48MHZ), 0x00);
         assert_eq!(rtc.read(0x3D, 0, CPU_SPEED_48MHZ), 0x05);
         assert_eq!(rtc.read(0x3E, 0, CPU_SPEED_48MHZ), 0x01);
@@ -455,7 +545,7 @@ mod tests {
 
     #[test]
     fn test_load_status_complete() {
-        let mut rtc = RtcController::new();
+        let rtc = RtcController::new();
         assert_eq!(rtc.read(0x40, 0, CPU_SPEED_48MHZ), 0);
     }
 
@@ -670,4 +760,43 @@ mod tests {
         assert_eq!(rtc.read(0x46, 0, CPU_SPEED_48MHZ), ((combined >> 16) & 0xFF) as u8);
         assert_eq!(rtc.read(0x47, 0, CPU_SPEED_48MHZ), ((combined >> 24) & 0xFF) as u8);
     }
+
+    #[test]
+    fn test_set_unix_time() {
+        let mut rtc = RtcController::new();
+        // 2024-03-01 13:45:30 is day 9921 of the TI-OS epoch
+        rtc.set_unix_time(1_709_300_730);
+        assert_eq!((rtc.counter.day, rtc.counter.hour, rtc.counter.min, rtc.counter.sec), (9921, 13, 45, 30));
+        assert_eq!(rtc.latched.day, 9921);
+        assert_eq!(rtc.time_secs(), (1_709_300_730 - EPOCH_UNIX_SECS) as u64);
+
+        rtc.set_unix_time(0);
+        assert_eq!(rtc.time_secs(), 0);
+    }
+
+    #[test]
+    fn test_host_clock_sync() {
+        let mut rtc = RtcController::new();
+        let host = EPOCH_UNIX_SECS + 1000 * 86400;
+        let mut sync = HostClockSync::new(&mut rtc, host, 3600);
+        assert_eq!(rtc.time_secs(), 1000 * 86400 + 3600);
+
+        // Within a second of the host: left alone
+        assert!(!sync.check(&mut rtc, host + 1));
+        // Emulation paused for a minute: caught up
+        assert!(sync.check(&mut rtc, host + 60));
+        assert_eq!(rtc.time_secs(), 1000 * 86400 + 3660);
+
+        // The OS loads a new time: its offset is kept from then on
+        rtc.set_time_secs(5 * 86400);
+        rtc.loads_completed += 1;
+        assert!(!sync.check(&mut rtc, host + 60));
+        assert!(sync.check(&mut rtc, host + 90));
+        assert_eq!(rtc.time_secs(), 5 * 86400 + 30);
+
+        // A reset zeroes the counter; tracking restores the OS-set time
+        rtc.reset();
+        assert!(sync.check(&mut rtc, host + 90));
+        assert_eq!(rtc.time_secs(), 5 * 86400 + 30);
+    }
 }
@@ -6,9 +6,16 @@
 //! Timing is based on CPU cycles with a 24 MHz SPI clock model.
 //!
 //! The SPI bus connects to the ST7789V LCD panel via 9-bit frames.
-//! When a transfer completes, TX data is forwarded to the panel stub.
+//! When a transfer completes, TX data (masked to the CR1 frame width) is
+//! forwarded to the panel stub.
+//!
+//! INTSTATUS follows the Faraday FTSSP010 layout: RX overrun and TX
+//! underrun latch until INTSTATUS is read, the FIFO threshold bits follow
+//! the FIFO levels against the thresholds in INTCTRL. A TX threshold of 0
+//! reports the TX FIFO draining, i.e. the queued frames being sent.
 
 use super::panel::PanelStub;
+use super::BusAccess;
 
 /// SPI FIFO depth (matches CEmu)
 const SPI_RXFIFO_DEPTH: u8 = 16;
@@ -18,6 +25,22 @@ const SPI_TXFIFO_DEPTH: u8 = 16;
 const SPI_FEATURES: u8 = 0xE;
 const SPI_WIDTH: u8 = 32;
 
+/// INTSTATUS bits (enabled by the same bits of INTCTRL)
+pub mod int {
+    /// RX FIFO overrun: a frame arrived with the RX FIFO full
+    pub const RX_OVERRUN: u32 = 1 << 0;
+    /// TX FIFO underrun: a frame was shifted with TX enabled and the FIFO empty
+    pub const TX_UNDERRUN: u32 = 1 << 1;
+    /// RX FIFO at or above its threshold
+    pub const RX_THRESHOLD: u32 = 1 << 2;
+    /// TX FIFO at or below its threshold
+    pub const TX_THRESHOLD: u32 = 1 << 3;
+    /// Bits that latch until INTSTATUS is read
+    pub const LATCHED: u32 = RX_OVERRUN | TX_UNDERRUN;
+    /// Bits of INTCTRL that enable an INTSTATUS bit
+    pub const ENABLE_MASK: u32 = RX_OVERRUN | TX_UNDERRUN | RX_THRESHOLD | TX_THRESHOLD;
+}
+
 /// SPI Controller
 #[derive(Debug, Clone)]
 pub struct SpiController {
@@ -64,7 +87,8 @@ impl SpiController {
             cr1: 0,
             cr2: 0,
             int_ctrl: 0,
-            int_status: 0,
+            // Empty TX FIFO is at the default threshold of 0
+            int_status: int::TX_THRESHOLD,
             tfve: 0,
             tfwi: 0,
             tfvi: 0,
@@ -88,6 +112,11 @@ impl SpiController {
         &self.panel
     }
 
+    /// Get a mutable reference to the panel stub (state restore)
+    pub fn panel_mut(&mut self) -> &mut PanelStub {
+        &mut self.panel
+    }
+
     /// True if SPI is enabled (CR2 bit 0)
     fn spi_enabled(&self) -> bool {
         self.cr2 & 0x1 != 0
@@ -108,6 +137,75 @@ impl SpiController {
         self.cr0 & (1 << 11) != 0
     }
 
+    /// RX FIFO threshold (INTCTRL bits 7-11)
+    fn rx_threshold(&self) -> u8 {
+        (self.int_ctrl >> 7) as u8 & 0x1F
+    }
+
+    /// TX FIFO threshold (INTCTRL bits 12-16)
+    fn tx_threshold(&self) -> u8 {
+        (self.int_ctrl >> 12) as u8 & 0x1F
+    }
+
+    /// Recompute the FIFO threshold bits of INTSTATUS
+    fn update_int_status(&mut self) {
+        let mut status = self.int_status & int::LATCHED;
+        if self.rfve != 0 && self.rfve >= self.rx_threshold() {
+            status |= int::RX_THRESHOLD;
+        }
+        if self.tfve <= self.tx_threshold() {
+            status |= int::TX_THRESHOLD;
+        }
+        self.int_status = status;
+    }
+
+    /// True if an INTSTATUS bit enabled in INTCTRL is set
+    pub fn has_interrupt(&self) -> bool {
+        self.int_status & self.int_ctrl & int::ENABLE_MASK != 0
+    }
+
+    /// Interrupt status register
+    pub fn int_status(&self) -> u32 {
+        self.int_status
+    }
+
+    /// Finish the frame being shifted: deliver it to the panel and push the
+    /// received frame into the RX FIFO
+    fn finish_transfer(&mut self) {
+        // CEmu: panel_transfer(spi.txFifo[idx])
+        let width_mask = (1u64 << self.transfer_bits) - 1;
+        self.panel.transfer(self.current_tx_data & width_mask as u32);
+
+        self.transfer_bits = 0;
+        self.next_event_cycle = None;
+
+        if self.rx_enabled() {
+            if self.rfve < SPI_RXFIFO_DEPTH {
+                self.rfve = self.rfve.saturating_add(1);
+                self.rfvi = self.rfvi.wrapping_add(1);
+            } else {
+                self.int_status |= int::RX_OVERRUN;
+            }
+        }
+        self.update_int_status();
+    }
+
+    /// Take the next frame to shift out of the TX FIFO (0 if none is queued)
+    fn pop_tx_frame(&mut self, tx_available: bool) {
+        if tx_available {
+            let fifo_idx = (self.tfvi & (SPI_TXFIFO_DEPTH - 1)) as usize;
+            self.current_tx_data = self.tx_fifo[fifo_idx];
+            self.tfve = self.tfve.saturating_sub(1);
+            self.tfvi = self.tfvi.wrapping_add(1);
+        } else {
+            if self.tx_enabled() {
+                self.int_status |= int::TX_UNDERRUN;
+            }
+            self.current_tx_data = 0;
+        }
+        self.update_int_status();
+    }
+
     /// CPU clock rate in Hz based on control port speed value
     fn cpu_clock_hz(speed: u8) -> u32 {
         match speed & 0x03 {
@@ -176,15 +274,7 @@ impl SpiController {
         }
 
         let queued_before = self.tfve;
-        if tx_available {
-            // Read TX data from FIFO before consuming
-            let fifo_idx = (self.tfvi & (SPI_TXFIFO_DEPTH - 1)) as usize;
-            self.current_tx_data = self.tx_fifo[fifo_idx];
-            self.tfve = self.tfve.saturating_sub(1);
-            self.tfvi = self.tfvi.wrapping_add(1);
-        } else {
-            self.current_tx_data = 0;
-        }
+        self.pop_tx_frame(tx_available);
         self.transfer_bits = self.transfer_bit_count();
 
         // CEmu always uses (divider + 1) for transfer timing
@@ -235,15 +325,7 @@ impl SpiController {
             }
 
             // Forward TX data to panel on transfer completion
-            self.panel.transfer(self.current_tx_data);
-
-            self.transfer_bits = 0;
-            self.next_event_cycle = None;
-
-            if self.rx_enabled() && self.rfve < SPI_RXFIFO_DEPTH {
-                self.rfve = self.rfve.saturating_add(1);
-                self.rfvi = self.rfvi.wrapping_add(1);
-            }
+            self.finish_transfer();
 
             if !self.start_transfer(next_cycle, cpu_speed) {
                 break;
@@ -257,8 +339,13 @@ impl SpiController {
 
     /// Read from SPI port
     /// addr is the offset within the SPI port range (masked to 0x7F)
-    pub fn read(&mut self, addr: u32, current_cycles: u64, cpu_speed: u8) -> u8 {
-        self.update(current_cycles, cpu_speed);
+    /// Debug reads don't advance transfers, drain the RX FIFO or clear
+    /// latched interrupt status
+    pub fn read(&mut self, addr: u32, current_cycles: u64, cpu_speed: u8, access: BusAccess) -> u8 {
+        let cpu = access == BusAccess::Cpu;
+        if cpu {
+            self.update(current_cycles, cpu_speed);
+        }
 
         let shift = (addr & 3) << 3;
         let reg_idx = addr >> 2;
@@ -286,7 +373,7 @@ impl SpiController {
                     | (transfer_active << 2)
                     | (tx_not_full << 1)
                     | rx_full;
-                if Self::trace_enabled() {
+                if cpu && Self::trace_enabled() {
                     eprintln!(
                         "[spi] status cycle={} speed={} tfve={} rfve={} active={} next={:?} cr0=0x{:04X} cr1=0x{:06X} cr2=0x{:03X}",
                         current_cycles,
@@ -304,13 +391,20 @@ impl SpiController {
             }
             // INTCTRL (0x10-0x13)
             4 => self.int_ctrl,
-            // INTSTATUS (0x14-0x17)
-            5 => self.int_status,
+            // INTSTATUS (0x14-0x17) - reading clears overrun/underrun
+            5 => {
+                let status = self.int_status;
+                if cpu && shift == 0 {
+                    self.int_status &= !int::LATCHED;
+                }
+                status
+            }
             // DATA (0x18-0x1B) - reading drains RX FIFO
             6 => {
-                if shift == 0 && self.rfve > 0 {
+                if cpu && shift == 0 && self.rfve > 0 {
                     self.rfve = self.rfve.saturating_sub(1);
                     self.rfvi = self.rfvi.wrapping_add(1);
+                    self.update_int_status();
                 }
                 0
             }
@@ -380,6 +474,7 @@ impl SpiController {
                     self.tfvi = 0;
                     self.tfve = 0;
                 }
+                self.update_int_status();
                 // Only low CR2 bits are writable (matches CEmu mask)
                 masked_value &= 0xF83;
                 let new_value = (self.cr2 & mask) | masked_value;
@@ -397,7 +492,8 @@ impl SpiController {
             }
             // INTCTRL (0x10-0x13)
             4 => {
-                self.int_ctrl = (self.int_ctrl & mask) | value32;
+                self.int_ctrl = ((self.int_ctrl & mask) | value32) & 0x1FFFF;
+                self.update_int_status();
             }
             // DATA (0x18-0x1B) - writing adds to TX FIFO
             6 => {
@@ -415,6 +511,7 @@ impl SpiController {
                     // Commit entry on byte 0 write
                     self.tfve += 1;
                     self.tfwi = self.tfwi.wrapping_add(1);
+                    self.update_int_status();
                     state_changed = true; // May need to start transfer
                     if Self::trace_enabled() {
                         let fifo_idx = ((self.tfwi.wrapping_sub(1)) & (SPI_TXFIFO_DEPTH - 1)) as usize;
@@ -453,17 +550,7 @@ impl SpiController {
 
         // Complete current transfer
         if self.transfer_bits != 0 {
-            // Forward TX data to the panel (CEmu: panel_transfer(spi.txFifo[idx]))
-            self.panel.transfer(self.current_tx_data);
-
-            self.transfer_bits = 0;
-            self.next_event_cycle = None;
-
-            // Add to RX FIFO if RX enabled
-            if self.rx_enabled() && self.rfve < SPI_RXFIFO_DEPTH {
-                self.rfve = self.rfve.saturating_add(1);
-                self.rfvi = self.rfvi.wrapping_add(1);
-            }
+            self.finish_transfer();
         }
 
         // Try to start next transfer
@@ -499,14 +586,7 @@ impl SpiController {
 
         // Consume from TX FIFO
         let queued_before = self.tfve;
-        if tx_available {
-            let fifo_idx = (self.tfvi & (SPI_TXFIFO_DEPTH - 1)) as usize;
-            self.current_tx_data = self.tx_fifo[fifo_idx];
-            self.tfve = self.tfve.saturating_sub(1);
-            self.tfvi = self.tfvi.wrapping_add(1);
-        } else {
-            self.current_tx_data = 0;
-        }
+        self.pop_tx_frame(tx_available);
 
         self.transfer_bits = self.transfer_bit_count();
 
@@ -577,12 +657,12 @@ mod tests {
     fn test_status_idle() {
         let mut spi = SpiController::new();
         // Read STATUS byte 0 (offset 0x0C)
-        let status0 = spi.read(0x0C, 0, CPU_SPEED_24MHZ);
+        let status0 = spi.read(0x0C, 0, CPU_SPEED_24MHZ, BusAccess::Cpu);
         // Bit 1 should be set (TX not full)
         assert_eq!(status0 & 0x02, 0x02);
 
         // Read STATUS byte 1 (offset 0x0D)
-        let status1 = spi.read(0x0D, 0, CPU_SPEED_24MHZ);
+        let status1 = spi.read(0x0D, 0, CPU_SPEED_24MHZ, BusAccess::Cpu);
         // tfve = 0, so upper nibble should be 0
         assert_eq!(status1, 0x00);
     }
@@ -596,7 +676,7 @@ mod tests {
         assert_eq!(spi.tfve, 1); // Directly check tfve increased
 
         // STATUS byte 1 should report tfve = 1 (upper nibble)
-        let status1 = spi.read(0x0D, 0, CPU_SPEED_24MHZ);
+        let status1 = spi.read(0x0D, 0, CPU_SPEED_24MHZ, BusAccess::Cpu);
         assert_eq!(status1, 0x10);
     }
 
@@ -616,11 +696,98 @@ mod tests {
         spi.write(0x18, 0x00, 0, CPU_SPEED_24MHZ);
 
         // Before completion (24 cycles total), transfer should be active
-        let status0 = spi.read(0x0C, 23, CPU_SPEED_24MHZ);
+        let status0 = spi.read(0x0C, 23, CPU_SPEED_24MHZ, BusAccess::Cpu);
         assert_eq!(status0 & 0x04, 0x04);
 
         // At completion, transfer should be inactive
-        let status0_done = spi.read(0x0C, 24, CPU_SPEED_24MHZ);
+        let status0_done = spi.read(0x0C, 24, CPU_SPEED_24MHZ, BusAccess::Cpu);
         assert_eq!(status0_done & 0x04, 0x00);
     }
+
+    #[test]
+    fn test_tx_threshold_interrupt_and_frame_width() {
+        let mut spi = SpiController::new();
+        // CR1: divider 1, 8-bit frames; SPI and TX enabled
+        spi.write(0x06, 0x07, 0, CPU_SPEED_24MHZ);
+        spi.write(0x08, 0x01, 0, CPU_SPEED_24MHZ);
+        spi.write(0x09, 0x01, 0, CPU_SPEED_24MHZ);
+        // Enable the TX threshold interrupt (threshold 0)
+        spi.write(0x10, int::TX_THRESHOLD as u8, 0, CPU_SPEED_24MHZ);
+        assert!(spi.has_interrupt());
+
+        // Queue NOP, then DISPON with bit 8 set: outside an 8-bit frame,
+        // so the panel sees a command rather than a parameter
+        spi.write(0x18, 0x00, 0, CPU_SPEED_24MHZ);
+        spi.write(0x18, 0x29, 0, CPU_SPEED_24MHZ);
+        spi.write(0x19, 0x01, 0, CPU_SPEED_24MHZ);
+        assert!(!spi.has_interrupt());
+
+        assert_eq!(spi.try_start_transfer_for_scheduler(), Some(8));
+        assert!(!spi.has_interrupt());
+        // Last frame leaves the FIFO: drained
+        assert_eq!(spi.complete_transfer_and_continue(), Some(8));
+        assert!(spi.has_interrupt());
+        assert_eq!(spi.complete_transfer_and_continue(), None);
+        let panel = spi.panel().to_bytes();
+        assert_eq!(panel[0], 0x29); // current command
+        assert_eq!(panel[4], 1); // display on
+        assert_eq!(spi.read(0x14, 0, CPU_SPEED_24MHZ, BusAccess::Cpu) as u32 & int::TX_THRESHOLD, int::TX_THRESHOLD);
+    }
+
+    #[test]
+    fn test_rx_threshold_and_overrun() {
+        let mut spi = SpiController::new();
+        // Flash mode, RX only, 8-bit frames; RX threshold 2, overrun enabled
+        spi.write(0x01, 0x08, 0, CPU_SPEED_24MHZ);
+        spi.write(0x06, 0x07, 0, CPU_SPEED_24MHZ);
+        spi.write(0x08, 0x81, 0, CPU_SPEED_24MHZ);
+        spi.write(0x10, (int::RX_THRESHOLD | int::RX_OVERRUN) as u8, 0, CPU_SPEED_24MHZ);
+        spi.write(0x11, 0x01, 0, CPU_SPEED_24MHZ);
+
+        spi.try_start_transfer_for_scheduler();
+        spi.complete_transfer_and_continue();
+        assert!(!spi.has_interrupt());
+        spi.complete_transfer_and_continue();
+        assert!(spi.has_interrupt());
+        assert_eq!(spi.int_status() & int::RX_THRESHOLD, int::RX_THRESHOLD);
+
+        // Draining below the threshold clears it
+        spi.read(0x18, 0, CPU_SPEED_24MHZ, BusAccess::Cpu);
+        assert!(!spi.has_interrupt());
+
+        // Fill the FIFO, then one more frame overruns
+        while spi.rfve < SPI_RXFIFO_DEPTH {
+            spi.finish_transfer();
+        }
+        spi.transfer_bits = 8;
+        spi.finish_transfer();
+        assert_eq!(spi.int_status() & int::RX_OVERRUN, int::RX_OVERRUN);
+        // Overrun latches until INTSTATUS is read
+        assert_eq!(spi.read(0x14, 0, CPU_SPEED_24MHZ, BusAccess::Cpu) as u32 & int::RX_OVERRUN, int::RX_OVERRUN);
+        assert_eq!(spi.int_status() & int::RX_OVERRUN, 0);
+    }
+
+    #[test]
+    fn test_debug_read_has_no_side_effects() {
+        let mut spi = SpiController::new();
+        spi.write(0x01, 0x08, 0, CPU_SPEED_24MHZ);
+        spi.write(0x06, 0x07, 0, CPU_SPEED_24MHZ);
+        spi.write(0x08, 0x81, 0, CPU_SPEED_24MHZ);
+        spi.write(0x10, int::RX_OVERRUN as u8, 0, CPU_SPEED_24MHZ);
+        while spi.rfve < SPI_RXFIFO_DEPTH {
+            spi.finish_transfer();
+        }
+        spi.transfer_bits = 8;
+        spi.finish_transfer();
+
+        // Neither the FIFO nor the latched overrun change under debug reads
+        let rfve = spi.rfve;
+        spi.read(0x18, 0, CPU_SPEED_24MHZ, BusAccess::Debug);
+        assert_eq!(spi.rfve, rfve);
+        assert_eq!(spi.read(0x14, 0, CPU_SPEED_24MHZ, BusAccess::Debug) as u32 & int::RX_OVERRUN, int::RX_OVERRUN);
+        assert_eq!(spi.int_status() & int::RX_OVERRUN, int::RX_OVERRUN);
+
+        spi.read(0x18, 0, CPU_SPEED_24MHZ, BusAccess::Cpu);
+        assert_eq!(spi.rfve, rfve - 1);
+    }
 }
@@ -0,0 +1,1192 @@
+//! USB Host Controller with a CDC serial accessory
+//!
+//! Memory-mapped at port range 0x3 (0x3000-0x3FFF via IN/OUT)
+//!
+//! The TI-84 Plus CE has a Faraday FOTG210 dual-role controller. Only its
+//! EHCI-compatible host side is modeled: capability/operational registers,
+//! the root port, and the asynchronous schedule (QH/qTD lists in RAM). That is
+//! enough for the calculator to enumerate and talk to a CDC-ACM serial device
+//! such as the TI-Innovator Hub. The OTG block reports the cable state (no
+//! cable: B-device in the peripheral role; accessory: A-device hosting it),
+//! and the device-mode block is a register stub (see `DeviceRegs`) so the OS
+//! can reset and probe it at boot. The periodic schedule and device-mode
+//! transfers are not modeled; other registers read back whatever was written.
+//!
+//! Simplifications:
+//! - The async schedule is walked once every `SCHEDULE_INTERVAL` CPU cycles
+//!   instead of once per microframe.
+//! - qTD buffers are treated as contiguous from buffer pointer 0, which holds
+//!   for buffers allocated in the calculator's flat 24-bit address space.
+//! - Controller state is not part of save states.
+
+use std::collections::VecDeque;
+
+/// Register offsets (EHCI layout)
+mod regs {
+    /// CAPLENGTH (byte 0) and HCIVERSION (bytes 2-3)
+    pub const CAPLENGTH: u32 = 0x00;
+    pub const HCSPARAMS: u32 = 0x04;
+    pub const HCCPARAMS: u32 = 0x08;
+    pub const USBCMD: u32 = 0x10;
+    pub const USBSTS: u32 = 0x14;
+    pub const USBINTR: u32 = 0x18;
+    pub const FRINDEX: u32 = 0x1C;
+    pub const PERIODICLISTBASE: u32 = 0x24;
+    pub const ASYNCLISTADDR: u32 = 0x28;
+    pub const PORTSC: u32 = 0x30;
+    /// OTG control/status (FOTG210)
+    pub const OTGCSR: u32 = 0x80;
+    /// OTG interrupt status, write 1 to clear
+    pub const OTGISR: u32 = 0x84;
+    /// OTG interrupt enable
+    pub const OTGIER: u32 = 0x88;
+    /// Global interrupt status (FOTG210): bit 2 = host controller
+    pub const GISR: u32 = 0xC0;
+    /// Global interrupt mask (FOTG210): set bits mask the source
+    pub const GIMR: u32 = 0xC4;
+    /// Device-mode control
+    pub const DEVCR: u32 = 0x100;
+    /// Device-mode address (bits 0-6) and configured flag (bit 7)
+    pub const DEVADR: u32 = 0x104;
+    /// Device interrupt group mask
+    pub const DEV_GROUP_MASK: u32 = 0x130;
+    /// Device interrupt source masks, groups 0-2
+    pub const DEV_MASK0: u32 = 0x134;
+    pub const DEV_MASK2: u32 = 0x13C;
+    /// Device interrupt group status (read-only)
+    pub const DEV_GROUP_STATUS: u32 = 0x140;
+    /// Device interrupt source status, groups 0-2, write 1 to clear
+    pub const DEV_STATUS0: u32 = 0x144;
+    pub const DEV_STATUS2: u32 = 0x14C;
+}
+
+/// USBCMD bits
+mod cmd {
+    pub const RUN: u32 = 1 << 0;
+    pub const HCRESET: u32 = 1 << 1;
+    pub const PSE: u32 = 1 << 4;
+    pub const ASE: u32 = 1 << 5;
+    pub const IAAD: u32 = 1 << 6;
+}
+
+/// USBSTS bits
+mod sts {
+    pub const USBINT: u32 = 1 << 0;
+    pub const USBERRINT: u32 = 1 << 1;
+    pub const PCD: u32 = 1 << 2;
+    pub const IAA: u32 = 1 << 5;
+    /// Write-1-to-clear interrupt bits
+    pub const W1C: u32 = 0x3F;
+    pub const HCHALTED: u32 = 1 << 12;
+    pub const PSS: u32 = 1 << 14;
+    pub const ASS: u32 = 1 << 15;
+}
+
+/// PORTSC bits
+mod port {
+    pub const CCS: u32 = 1 << 0;
+    pub const CSC: u32 = 1 << 1;
+    pub const PE: u32 = 1 << 2;
+    pub const PEC: u32 = 1 << 3;
+    pub const PR: u32 = 1 << 8;
+}
+
+/// OTGCSR bits
+mod otg {
+    /// Software-controlled bits (bus requests, VBUS drop, HNP/SRP enables)
+    pub const CONTROL: u32 = 0x1FF;
+    pub const B_SESS_END: u32 = 1 << 16;
+    pub const A_SESS_VLD: u32 = 1 << 18;
+    pub const A_VBUS_VLD: u32 = 1 << 19;
+    /// Current role: set = peripheral, clear = host
+    pub const CROLE: u32 = 1 << 20;
+    /// ID pin: set = B-device (no cable or micro-B), clear = A-device
+    pub const ID: u32 = 1 << 21;
+    /// OTGISR: role changed
+    pub const ROLE_CHANGE: u32 = 1 << 8;
+    /// OTGISR: ID pin changed
+    pub const ID_CHANGE: u32 = 1 << 9;
+    /// OTGISR/OTGIER implemented bits
+    pub const INT_MASK: u32 = 0x1F71;
+}
+
+/// DEVCR bits
+mod devcr {
+    pub const GLOBAL_INT_EN: u32 = 1 << 2;
+    /// Soft reset of the device block; self-clearing
+    pub const SOFT_RESET: u32 = 1 << 4;
+}
+
+/// qTD token bits
+mod token {
+    pub const XACTERR: u32 = 1 << 3;
+    pub const HALTED: u32 = 1 << 6;
+    pub const ACTIVE: u32 = 1 << 7;
+    pub const IOC: u32 = 1 << 15;
+    pub const TOGGLE: u32 = 1 << 31;
+}
+
+/// GISR (and GIMR) bits: device, OTG, and host controller interrupts
+const GISR_DEV: u32 = 1 << 0;
+const GISR_OTG: u32 = 1 << 1;
+const GISR_HOST: u32 = 1 << 2;
+
+/// Size of the register window backed by plain storage
+const REG_SIZE: usize = 0x200;
+
+/// CPU cycles between async schedule passes
+const SCHEDULE_INTERVAL: u32 = 4096;
+
+/// Bounds on one schedule pass (guards against malformed lists)
+const MAX_QH_PER_PASS: usize = 16;
+const MAX_QTD_PER_QH: usize = 16;
+
+/// Link pointer address mask (24-bit space, 32-byte aligned)
+const LINK_MASK: u32 = 0xFFFFE0;
+
+/// Bulk IN endpoint (accessory -> calculator)
+const BULK_IN_EP: u8 = 1;
+/// Bulk OUT endpoint (calculator -> accessory)
+const BULK_OUT_EP: u8 = 2;
+/// Notification endpoint (interrupt IN, always NAKs)
+const NOTIFY_EP: u8 = 3;
+/// Max packet size for every endpoint
+const MAX_PACKET: u16 = 64;
+
+/// Memory the controller reads schedules and buffers from
+pub trait UsbDma {
+    fn dma_read(&mut self, addr: u32) -> u8;
+    fn dma_write(&mut self, addr: u32, value: u8);
+}
+
+fn read32<M: UsbDma>(mem: &mut M, addr: u32) -> u32 {
+    (0..4).fold(0, |acc, i| acc | (mem.dma_read(addr + i) as u32) << (i * 8))
+}
+
+fn write32<M: UsbDma>(mem: &mut M, addr: u32, value: u32) {
+    for i in 0..4 {
+        mem.dma_write(addr + i, (value >> (i * 8)) as u8);
+    }
+}
+
+/// Identity reported by the emulated serial accessory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialAccessoryConfig {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub manufacturer: String,
+    pub product: String,
+}
+
+impl Default for SerialAccessoryConfig {
+    fn default() -> Self {
+        Self {
+            vendor_id: 0x0451,
+            product_id: 0xE008,
+            manufacturer: "Texas Instruments".into(),
+            product: "TI-Innovator Hub".into(),
+        }
+    }
+}
+
+/// Device response to a non-SETUP transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handshake {
+    Nak,
+    Stall,
+}
+
+/// Control endpoint state
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ControlStage {
+    Idle,
+    /// IN data stage pending (status stage is an OUT)
+    DataIn { data: Vec<u8>, pos: usize },
+    /// OUT data stage pending for `request` (status stage is an IN)
+    DataOut { request: u8, data: Vec<u8>, len: usize },
+    /// Zero-length IN status stage pending
+    StatusIn,
+    /// Request not supported: stall until the next SETUP
+    Stalled,
+}
+
+/// CDC-ACM serial device plugged into the root port
+#[derive(Debug, Clone)]
+pub struct CdcSerialDevice {
+    config: SerialAccessoryConfig,
+    address: u8,
+    pending_address: Option<u8>,
+    configuration: u8,
+    control: ControlStage,
+    /// dwDTERate, bCharFormat, bParityType, bDataBits
+    line_coding: [u8; 7],
+    /// DTR (bit 0) and RTS (bit 1)
+    control_lines: u16,
+    /// Bytes sent by the calculator, waiting for the host
+    to_host: VecDeque<u8>,
+    /// Bytes from the host, waiting for the calculator
+    to_calc: VecDeque<u8>,
+}
+
+impl CdcSerialDevice {
+    fn new(config: SerialAccessoryConfig) -> Self {
+        Self {
+            config,
+            address: 0,
+            pending_address: None,
+            configuration: 0,
+            control: ControlStage::Idle,
+            line_coding: [0x00, 0x96, 0x00, 0x00, 0, 0, 8], // 38400 8N1
+            control_lines: 0,
+            to_host: VecDeque::new(),
+            to_calc: VecDeque::new(),
+        }
+    }
+
+    /// USB bus reset: back to the default address, unconfigured
+    fn bus_reset(&mut self) {
+        self.address = 0;
+        self.pending_address = None;
+        self.configuration = 0;
+        self.control = ControlStage::Idle;
+    }
+
+    /// True once the calculator has selected a configuration
+    pub fn is_configured(&self) -> bool {
+        self.configuration != 0
+    }
+
+    /// DTR (bit 0) and RTS (bit 1) as last set by the calculator
+    pub fn control_lines(&self) -> u16 {
+        self.control_lines
+    }
+
+    /// Baud rate from the last SET_LINE_CODING
+    pub fn baud_rate(&self) -> u32 {
+        u32::from_le_bytes(self.line_coding[0..4].try_into().unwrap())
+    }
+
+    fn device_descriptor(&self) -> Vec<u8> {
+        let [vid_lo, vid_hi] = self.config.vendor_id.to_le_bytes();
+        let [pid_lo, pid_hi] = self.config.product_id.to_le_bytes();
+        vec![
+            18, 0x01, 0x00, 0x02, // bLength, DEVICE, bcdUSB 2.00
+            0x02, 0x00, 0x00, MAX_PACKET as u8, // CDC class, EP0 max packet
+            vid_lo, vid_hi, pid_lo, pid_hi,
+            0x00, 0x01, // bcdDevice 1.00
+            1, 2, 0, 1, // iManufacturer, iProduct, iSerial, bNumConfigurations
+        ]
+    }
+
+    fn config_descriptor(&self) -> Vec<u8> {
+        let [mp_lo, mp_hi] = MAX_PACKET.to_le_bytes();
+        let mut d = vec![
+            // Configuration (wTotalLength patched below)
+            9, 0x02, 0, 0, 2, 1, 0, 0x80, 50,
+            // Interface 0: CDC communications, ACM
+            9, 0x04, 0, 0, 1, 0x02, 0x02, 0x01, 0,
+            // CDC header, call management, ACM, union
+            5, 0x24, 0x00, 0x10, 0x01,
+            5, 0x24, 0x01, 0x00, 1,
+            4, 0x24, 0x02, 0x02,
+            5, 0x24, 0x06, 0, 1,
+            // Notification endpoint (interrupt IN)
+            7, 0x05, 0x80 | NOTIFY_EP, 0x03, mp_lo, mp_hi, 16,
+            // Interface 1: CDC data
+            9, 0x04, 1, 0, 2, 0x0A, 0x00, 0x00, 0,
+            7, 0x05, BULK_OUT_EP, 0x02, mp_lo, mp_hi, 0,
+            7, 0x05, 0x80 | BULK_IN_EP, 0x02, mp_lo, mp_hi, 0,
+        ];
+        let [len_lo, len_hi] = (d.len() as u16).to_le_bytes();
+        d[2] = len_lo;
+        d[3] = len_hi;
+        d
+    }
+
+    fn string_descriptor(&self, index: u8) -> Option<Vec<u8>> {
+        let text = match index {
+            0 => return Some(vec![4, 0x03, 0x09, 0x04]), // English (US)
+            1 => &self.config.manufacturer,
+            2 => &self.config.product,
+            _ => return None,
+        };
+        let mut d = vec![0, 0x03];
+        for unit in text.encode_utf16().take(126) {
+            d.extend_from_slice(&unit.to_le_bytes());
+        }
+        d[0] = d.len() as u8;
+        Some(d)
+    }
+
+    /// Handle a SETUP packet (always ACKed; errors stall the later stages)
+    fn setup(&mut self, packet: [u8; 8]) {
+        let request_type = packet[0];
+        let request = packet[1];
+        let value = u16::from_le_bytes([packet[2], packet[3]]);
+        let length = u16::from_le_bytes([packet[6], packet[7]]) as usize;
+
+        let reply = |data: Vec<u8>| {
+            let mut data = data;
+            data.truncate(length);
+            ControlStage::DataIn { data, pos: 0 }
+        };
+        self.control = match (request_type, request) {
+            // GET_STATUS (device, interface, endpoint)
+            (0x80..=0x82, 0x00) => reply(vec![0, 0]),
+            // GET_DESCRIPTOR
+            (0x80, 0x06) => {
+                let descriptor = match (value >> 8) as u8 {
+                    0x01 => Some(self.device_descriptor()),
+                    0x02 => Some(self.config_descriptor()),
+                    0x03 => self.string_descriptor(value as u8),
+                    _ => None,
+                };
+                descriptor.map_or(ControlStage::Stalled, reply)
+            }
+            // GET_CONFIGURATION
+            (0x80, 0x08) => reply(vec![self.configuration]),
+            // SET_ADDRESS: takes effect after the status stage
+            (0x00, 0x05) => {
+                self.pending_address = Some(value as u8 & 0x7F);
+                ControlStage::StatusIn
+            }
+            // SET_CONFIGURATION
+            (0x00, 0x09) if value <= 1 => {
+                self.configuration = value as u8;
+                ControlStage::StatusIn
+            }
+            // CLEAR_FEATURE / SET_FEATURE, SET_INTERFACE
+            (0x00..=0x02, 0x01) | (0x00..=0x02, 0x03) | (0x01, 0x0B) => ControlStage::StatusIn,
+            // CDC SET_LINE_CODING
+            (0x21, 0x20) if length == 7 => ControlStage::DataOut { request, data: Vec::new(), len: length },
+            // CDC GET_LINE_CODING
+            (0xA1, 0x21) => reply(self.line_coding.to_vec()),
+            // CDC SET_CONTROL_LINE_STATE
+            (0x21, 0x22) => {
+                self.control_lines = value;
+                ControlStage::StatusIn
+            }
+            // CDC SEND_BREAK
+            (0x21, 0x23) => ControlStage::StatusIn,
+            _ => {
+                crate::emu::log_sub!(Bus, Debug,
+                    "USB: unsupported request type={:02X} req={:02X} value={:04X}",
+                    request_type, request, value
+                );
+                ControlStage::Stalled
+            }
+        };
+    }
+
+    /// IN transaction: returns up to `max` bytes
+    fn data_in(&mut self, ep: u8, max: usize) -> Result<Vec<u8>, Handshake> {
+        match ep {
+            0 => match &mut self.control {
+                ControlStage::DataIn { data, pos } => {
+                    let end = (*pos + max).min(data.len());
+                    let chunk = data[*pos..end].to_vec();
+                    *pos = end;
+                    Ok(chunk)
+                }
+                ControlStage::StatusIn => {
+                    if let Some(address) = self.pending_address.take() {
+                        self.address = address;
+                    }
+                    self.control = ControlStage::Idle;
+                    Ok(Vec::new())
+                }
+                ControlStage::DataOut { request, data, len } if data.len() == *len => {
+                    if *request == 0x20 {
+                        self.line_coding.copy_from_slice(data);
+                    }
+                    self.control = ControlStage::Idle;
+                    Ok(Vec::new())
+                }
+                _ => Err(Handshake::Stall),
+            },
+            BULK_IN_EP if self.is_configured() => {
+                if self.to_calc.is_empty() {
+                    return Err(Handshake::Nak);
+                }
+                let n = max.min(self.to_calc.len());
+                Ok(self.to_calc.drain(..n).collect())
+            }
+            NOTIFY_EP if self.is_configured() => Err(Handshake::Nak),
+            _ => Err(Handshake::Stall),
+        }
+    }
+
+    /// OUT transaction
+    fn data_out(&mut self, ep: u8, bytes: &[u8]) -> Result<(), Handshake> {
+        match ep {
+            0 => match &mut self.control {
+                // Status stage of an IN request
+                ControlStage::DataIn { .. } if bytes.is_empty() => {
+                    self.control = ControlStage::Idle;
+                    Ok(())
+                }
+                ControlStage::DataOut { data, len, .. } if data.len() + bytes.len() <= *len => {
+                    data.extend_from_slice(bytes);
+                    Ok(())
+                }
+                _ => Err(Handshake::Stall),
+            },
+            BULK_OUT_EP if self.is_configured() => {
+                self.to_host.extend(bytes);
+                Ok(())
+            }
+            _ => Err(Handshake::Stall),
+        }
+    }
+}
+
+/// Device-mode register block (FOTG210 peripheral side)
+///
+/// Models control (with soft reset), the device address, and the grouped
+/// interrupt status/mask registers. Endpoint FIFOs and transfers are left
+/// to a device protocol layer, which would report bus events through
+/// `UsbController::raise_device_interrupt`.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceRegs {
+    control: u32,
+    address: u32,
+    group_mask: u32,
+    masks: [u32; 3],
+    status: [u32; 3],
+}
+
+impl DeviceRegs {
+    /// Group status: bit n set while group n has an unmasked source
+    fn group_status(&self) -> u32 {
+        (0..3).filter(|&g| self.status[g] & !self.masks[g] != 0).fold(0, |acc, g| acc | 1 << g)
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.control & devcr::GLOBAL_INT_EN != 0 && self.group_status() & !self.group_mask != 0
+    }
+
+    fn reg(&self, offset: u32) -> Option<u32> {
+        Some(match offset {
+            regs::DEVCR => self.control,
+            regs::DEVADR => self.address,
+            regs::DEV_GROUP_MASK => self.group_mask,
+            regs::DEV_MASK0..=regs::DEV_MASK2 => self.masks[((offset - regs::DEV_MASK0) / 4) as usize],
+            regs::DEV_GROUP_STATUS => self.group_status(),
+            regs::DEV_STATUS0..=regs::DEV_STATUS2 => self.status[((offset - regs::DEV_STATUS0) / 4) as usize],
+            _ => return None,
+        })
+    }
+
+    /// Apply a byte write (`bits` within `mask`). False if `offset` is not
+    /// a device register.
+    fn write(&mut self, offset: u32, bits: u32, mask: u32) -> bool {
+        let merge = |old: u32| (old & !mask) | bits;
+        match offset {
+            regs::DEVCR => {
+                let control = merge(self.control);
+                if control & devcr::SOFT_RESET != 0 {
+                    *self = Self::default();
+                }
+                self.control = control & !devcr::SOFT_RESET;
+            }
+            regs::DEVADR => self.address = merge(self.address) & 0xFF,
+            regs::DEV_GROUP_MASK => self.group_mask = merge(self.group_mask) & 0x7,
+            regs::DEV_MASK0..=regs::DEV_MASK2 => {
+                let mask_reg = &mut self.masks[((offset - regs::DEV_MASK0) / 4) as usize];
+                *mask_reg = merge(*mask_reg);
+            }
+            regs::DEV_GROUP_STATUS => {}
+            regs::DEV_STATUS0..=regs::DEV_STATUS2 => self.status[((offset - regs::DEV_STATUS0) / 4) as usize] &= !bits,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// USB host controller
+#[derive(Debug, Clone)]
+pub struct UsbController {
+    usbcmd: u32,
+    usbsts: u32,
+    usbintr: u32,
+    frindex: u32,
+    periodic_list_base: u32,
+    async_list_addr: u32,
+    portsc: u32,
+    /// OTGCSR software-controlled bits (the status bits are live)
+    otg_control: u32,
+    otgisr: u32,
+    otgier: u32,
+    gimr: u32,
+    device_regs: DeviceRegs,
+    /// Backing storage for registers not modeled above
+    storage: [u8; REG_SIZE],
+    /// Cycles accumulated toward the next schedule pass
+    schedule_cycles: u32,
+    /// Accessory plugged into the root port
+    device: Option<CdcSerialDevice>,
+}
+
+impl UsbController {
+    /// Create a new USB controller with nothing attached
+    pub fn new() -> Self {
+        Self {
+            usbcmd: 0,
+            usbsts: 0,
+            usbintr: 0,
+            frindex: 0,
+            periodic_list_base: 0,
+            async_list_addr: 0,
+            portsc: 0,
+            otg_control: 0,
+            otgisr: 0,
+            otgier: 0,
+            gimr: 0,
+            device_regs: DeviceRegs::default(),
+            storage: [0; REG_SIZE],
+            schedule_cycles: 0,
+            device: None,
+        }
+    }
+
+    /// Reset controller registers. An attached accessory stays plugged in
+    /// and sees a bus reset.
+    pub fn reset(&mut self) {
+        let device = self.device.take();
+        *self = Self::new();
+        if let Some(mut device) = device {
+            device.bus_reset();
+            self.device = Some(device);
+            self.portsc = port::CCS | port::CSC;
+            self.usbsts |= sts::PCD;
+        }
+    }
+
+    /// Plug a serial accessory into the root port (replacing any attached one)
+    pub fn attach(&mut self, config: SerialAccessoryConfig) {
+        if self.device.is_none() {
+            self.otgisr |= otg::ID_CHANGE | otg::ROLE_CHANGE;
+        }
+        self.device = Some(CdcSerialDevice::new(config));
+        self.portsc = (self.portsc & !port::PE) | port::CCS | port::CSC;
+        self.usbsts |= sts::PCD;
+        crate::emu::log_sub!(Bus, Info, "USB: serial accessory attached");
+    }
+
+    /// Unplug the accessory
+    pub fn detach(&mut self) {
+        if self.device.take().is_some() {
+            let was_enabled = self.portsc & port::PE != 0;
+            self.portsc &= !(port::CCS | port::PE);
+            self.portsc |= port::CSC | if was_enabled { port::PEC } else { 0 };
+            self.usbsts |= sts::PCD;
+            self.otgisr |= otg::ID_CHANGE | otg::ROLE_CHANGE;
+            crate::emu::log_sub!(Bus, Info, "USB: serial accessory detached");
+        }
+    }
+
+    /// Attached accessory, if any
+    pub fn device(&self) -> Option<&CdcSerialDevice> {
+        self.device.as_ref()
+    }
+
+    /// Queue bytes for the calculator to read. Returns false if nothing is attached.
+    pub fn push_to_calc(&mut self, data: &[u8]) -> bool {
+        match &mut self.device {
+            Some(device) => {
+                device.to_calc.extend(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drain up to `max` bytes the calculator has sent
+    pub fn take_from_calc(&mut self, max: usize) -> Vec<u8> {
+        match &mut self.device {
+            Some(device) => {
+                let n = max.min(device.to_host.len());
+                device.to_host.drain(..n).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// True if the calculator has sent bytes not yet taken
+    pub fn has_output(&self) -> bool {
+        self.device.as_ref().is_some_and(|d| !d.to_host.is_empty())
+    }
+
+    /// Flag device-mode interrupt sources (`bits` of status group `group`,
+    /// 0-2), as a device protocol layer would on bus events
+    pub fn raise_device_interrupt(&mut self, group: usize, bits: u32) {
+        self.device_regs.status[group] |= bits;
+    }
+
+    /// True if the controller interrupt line is asserted
+    pub fn irq_pending(&self) -> bool {
+        self.global_status() & !self.gimr != 0
+    }
+
+    /// GISR: which blocks are interrupting
+    fn global_status(&self) -> u32 {
+        let mut value = 0;
+        if self.device_regs.irq_pending() {
+            value |= GISR_DEV;
+        }
+        if self.otgisr & self.otgier != 0 {
+            value |= GISR_OTG;
+        }
+        if self.host_irq() {
+            value |= GISR_HOST;
+        }
+        value
+    }
+
+    /// OTGCSR: the cable decides the role. With the accessory's host cable
+    /// the calculator is the A-device, powers VBUS and hosts; otherwise it
+    /// is an idle B-device in the peripheral role.
+    fn otg_status(&self) -> u32 {
+        let live = if self.device.is_some() {
+            otg::A_VBUS_VLD | otg::A_SESS_VLD
+        } else {
+            otg::ID | otg::CROLE | otg::B_SESS_END
+        };
+        self.otg_control | live
+    }
+
+    fn host_irq(&self) -> bool {
+        self.usbsts & self.usbintr & sts::W1C != 0
+    }
+
+    fn running(&self) -> bool {
+        self.usbcmd & cmd::RUN != 0
+    }
+
+    /// USBSTS with the live status bits filled in
+    fn status(&self) -> u32 {
+        let mut value = self.usbsts;
+        if !self.running() {
+            value |= sts::HCHALTED;
+        } else {
+            if self.usbcmd & cmd::ASE != 0 {
+                value |= sts::ASS;
+            }
+            if self.usbcmd & cmd::PSE != 0 {
+                value |= sts::PSS;
+            }
+        }
+        value
+    }
+
+    /// 32-bit register value at a dword-aligned offset
+    fn reg(&self, offset: u32) -> u32 {
+        match offset {
+            regs::CAPLENGTH => 0x0100_0010, // HCIVERSION 1.00, CAPLENGTH 0x10
+            regs::HCSPARAMS => 0x0000_0001, // one port
+            regs::HCCPARAMS => 0x0000_0002, // programmable frame list
+            regs::USBCMD => self.usbcmd,
+            regs::USBSTS => self.status(),
+            regs::USBINTR => self.usbintr,
+            regs::FRINDEX => self.frindex,
+            regs::PERIODICLISTBASE => self.periodic_list_base,
+            regs::ASYNCLISTADDR => self.async_list_addr,
+            regs::PORTSC => self.portsc,
+            regs::OTGCSR => self.otg_status(),
+            regs::OTGISR => self.otgisr,
+            regs::OTGIER => self.otgier,
+            regs::GISR => self.global_status(),
+            regs::GIMR => self.gimr,
+            _ => self.device_regs.reg(offset).unwrap_or_else(|| {
+                let base = offset as usize % REG_SIZE;
+                u32::from_le_bytes(self.storage[base..base + 4].try_into().unwrap())
+            }),
+        }
+    }
+
+    /// Read a register byte
+    pub fn read(&self, offset: u32) -> u8 {
+        let offset = offset & 0xFFF;
+        (self.reg(offset & !3) >> ((offset & 3) * 8)) as u8
+    }
+
+    /// Write a register byte
+    pub fn write(&mut self, offset: u32, value: u8) {
+        let offset = offset & 0xFFF;
+        let shift = (offset & 3) * 8;
+        let mask = 0xFFu32 << shift;
+        let bits = (value as u32) << shift;
+        let merge = |old: u32| (old & !mask) | bits;
+
+        match offset & !3 {
+            regs::CAPLENGTH | regs::HCSPARAMS | regs::HCCPARAMS => {}
+            regs::USBCMD => {
+                self.usbcmd = merge(self.usbcmd);
+                if self.usbcmd & cmd::HCRESET != 0 {
+                    self.reset();
+                    return;
+                }
+                if self.usbcmd & cmd::IAAD != 0 {
+                    // Schedule changes are picked up on the next pass
+                    self.usbcmd &= !cmd::IAAD;
+                    self.usbsts |= sts::IAA;
+                }
+            }
+            regs::USBSTS => self.usbsts &= !(bits & sts::W1C),
+            regs::USBINTR => self.usbintr = merge(self.usbintr) & sts::W1C,
+            regs::FRINDEX => self.frindex = merge(self.frindex) & 0x3FFF,
+            regs::PERIODICLISTBASE => self.periodic_list_base = merge(self.periodic_list_base) & !0xFFF,
+            regs::ASYNCLISTADDR => self.async_list_addr = merge(self.async_list_addr) & !0x1F,
+            regs::PORTSC => self.write_portsc(bits, mask),
+            regs::OTGCSR => self.otg_control = merge(self.otg_control) & otg::CONTROL,
+            regs::OTGISR => self.otgisr &= !bits,
+            regs::OTGIER => self.otgier = merge(self.otgier) & otg::INT_MASK,
+            // Status is live: cleared at the source
+            regs::GISR => {}
+            regs::GIMR => self.gimr = merge(self.gimr),
+            _ => {
+                if !self.device_regs.write(offset & !3, bits, mask) {
+                    self.storage[offset as usize % REG_SIZE] = value;
+                }
+            }
+        }
+    }
+
+    fn write_portsc(&mut self, bits: u32, mask: u32) {
+        // Change bits are write-1-to-clear; PE can only be cleared by software
+        self.portsc &= !(bits & (port::CSC | port::PEC));
+        if mask & port::PE != 0 && bits & port::PE == 0 {
+            self.portsc &= !port::PE;
+        }
+        if mask & port::PR != 0 {
+            if bits & port::PR != 0 {
+                self.portsc |= port::PR;
+                self.portsc &= !port::PE;
+            } else if self.portsc & port::PR != 0 {
+                // Reset finished: enable the port if something is attached
+                self.portsc &= !port::PR;
+                if let Some(device) = &mut self.device {
+                    device.bus_reset();
+                    self.portsc |= port::PE;
+                }
+            }
+        }
+    }
+
+    /// Advance by `cycles` CPU cycles, walking the async schedule when due.
+    /// Returns true if a schedule pass ran.
+    pub fn tick<M: UsbDma>(&mut self, cycles: u32, mem: &mut M) -> bool {
+        if !self.running() {
+            return false;
+        }
+        self.schedule_cycles += cycles;
+        if self.schedule_cycles < SCHEDULE_INTERVAL {
+            return false;
+        }
+        self.schedule_cycles = 0;
+        self.frindex = (self.frindex + 8) & 0x3FFF;
+        if self.usbcmd & cmd::ASE != 0 && self.portsc & port::PE != 0 {
+            self.process_async(mem);
+        }
+        true
+    }
+
+    /// Walk the circular QH list once
+    fn process_async<M: UsbDma>(&mut self, mem: &mut M) {
+        let head = self.async_list_addr & LINK_MASK;
+        let mut qh = head;
+        for _ in 0..MAX_QH_PER_PASS {
+            self.process_qh(qh, mem);
+            let link = read32(mem, qh);
+            if link & 1 != 0 {
+                break;
+            }
+            qh = link & LINK_MASK;
+            if qh == head {
+                break;
+            }
+        }
+    }
+
+    /// Run qTDs queued on one QH until one NAKs, halts, or the queue ends
+    fn process_qh<M: UsbDma>(&mut self, qh: u32, mem: &mut M) {
+        for _ in 0..MAX_QTD_PER_QH {
+            let mut tok = read32(mem, qh + 0x18);
+            if tok & token::HALTED != 0 {
+                return;
+            }
+            if tok & token::ACTIVE == 0 {
+                // Fetch the next qTD into the overlay
+                let next = read32(mem, qh + 0x10);
+                if next & 1 != 0 {
+                    return;
+                }
+                let next = next & LINK_MASK;
+                for i in (0..32).step_by(4) {
+                    let word = read32(mem, next + i);
+                    write32(mem, qh + 0x10 + i, word);
+                }
+                write32(mem, qh + 0x0C, next);
+                tok = read32(mem, qh + 0x18);
+                if tok & token::ACTIVE == 0 {
+                    return;
+                }
+            }
+            if !self.execute_qtd(qh, tok, mem) {
+                return;
+            }
+        }
+    }
+
+    /// Execute the overlay qTD. Returns true if it retired and the queue can advance.
+    fn execute_qtd<M: UsbDma>(&mut self, qh: u32, tok: u32, mem: &mut M) -> bool {
+        let chars = read32(mem, qh + 0x04);
+        let address = (chars & 0x7F) as u8;
+        let ep = ((chars >> 8) & 0xF) as u8;
+        let max_packet = (((chars >> 16) & 0x7FF) as usize).max(1);
+        let pid = (tok >> 8) & 3;
+        let total = ((tok >> 16) & 0x7FFF) as usize;
+        let buffer = read32(mem, qh + 0x1C) & 0xFFFFFF;
+
+        let result = match &mut self.device {
+            Some(device) if device.address == address => match pid {
+                // SETUP
+                2 => {
+                    let mut packet = [0u8; 8];
+                    for (i, byte) in packet.iter_mut().enumerate() {
+                        *byte = mem.dma_read(buffer + i as u32);
+                    }
+                    device.setup(packet);
+                    Ok(8)
+                }
+                // OUT
+                0 => {
+                    let bytes: Vec<u8> = (0..total as u32).map(|i| mem.dma_read(buffer + i)).collect();
+                    device.data_out(ep, &bytes).map(|_| total)
+                }
+                // IN
+                _ => device.data_in(ep, total).map(|data| {
+                    for (i, &byte) in data.iter().enumerate() {
+                        mem.dma_write(buffer + i as u32, byte);
+                    }
+                    data.len()
+                }),
+            },
+            // No device at this address: transaction error
+            _ => Err(Handshake::Stall),
+        };
+
+        let qtd = read32(mem, qh + 0x0C) & LINK_MASK;
+        match result {
+            Err(Handshake::Nak) => false,
+            Err(Handshake::Stall) => {
+                let mut tok = (tok & !token::ACTIVE) | token::HALTED;
+                if self.device.as_ref().is_none_or(|d| d.address != address) {
+                    tok |= token::XACTERR;
+                }
+                write32(mem, qh + 0x18, tok);
+                write32(mem, qtd + 0x08, tok);
+                self.usbsts |= sts::USBERRINT;
+                false
+            }
+            Ok(moved) => {
+                let packets = moved.div_ceil(max_packet).max(1);
+                let mut tok = tok & !token::ACTIVE & !(0x7FFF << 16);
+                tok |= ((total - moved) as u32) << 16;
+                if packets % 2 == 1 {
+                    tok ^= token::TOGGLE;
+                }
+                write32(mem, qh + 0x18, tok);
+                write32(mem, qh + 0x1C, buffer + moved as u32);
+                write32(mem, qtd + 0x08, tok);
+
+                let short = pid == 1 && moved < total;
+                if tok & token::IOC != 0 || short {
+                    self.usbsts |= sts::USBINT;
+                }
+                if short {
+                    // Short packet: continue at the alternate next qTD
+                    let alt = read32(mem, qh + 0x14);
+                    if alt & 1 == 0 {
+                        write32(mem, qh + 0x10, alt);
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    // ========== State Persistence ==========
+
+    /// Size of USB state snapshot in bytes: host and OTG registers (44),
+    /// device registers (36), schedule cycles (4) and register storage
+    pub const SNAPSHOT_SIZE: usize = 84 + REG_SIZE;
+
+    /// Save controller registers to bytes. The attached accessory lives on
+    /// the host side and is not part of the snapshot.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::SNAPSHOT_SIZE);
+        let regs = &self.device_regs;
+        for word in [
+            self.usbcmd, self.usbsts, self.usbintr, self.frindex, self.periodic_list_base,
+            self.async_list_addr, self.portsc, self.otg_control, self.otgisr, self.otgier, self.gimr,
+            regs.control, regs.address, regs.group_mask,
+        ].iter().chain(&regs.masks).chain(&regs.status) {
+            buf.extend_from_slice(&word.to_le_bytes());
+        }
+        buf.extend_from_slice(&self.schedule_cycles.to_le_bytes());
+        buf.extend_from_slice(&self.storage);
+        buf
+    }
+
+    /// Load controller registers from bytes
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+        if buf.len() < Self::SNAPSHOT_SIZE {
+            return Err(-105);
+        }
+        let mut words = buf.chunks_exact(4).map(|w| u32::from_le_bytes(w.try_into().unwrap()));
+        let regs = &mut self.device_regs;
+        for word in [
+            &mut self.usbcmd, &mut self.usbsts, &mut self.usbintr, &mut self.frindex, &mut self.periodic_list_base,
+            &mut self.async_list_addr, &mut self.portsc, &mut self.otg_control, &mut self.otgisr, &mut self.otgier,
+            &mut self.gimr, &mut regs.control, &mut regs.address, &mut regs.group_mask,
+        ].into_iter().chain(regs.masks.iter_mut()).chain(regs.status.iter_mut()) {
+            *word = words.next().unwrap();
+        }
+        self.schedule_cycles = words.next().unwrap();
+        self.storage.copy_from_slice(&buf[84..Self::SNAPSHOT_SIZE]);
+        Ok(())
+    }
+}
+
+impl Default for UsbController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flat test memory
+    struct Mem(Vec<u8>);
+
+    impl UsbDma for Mem {
+        fn dma_read(&mut self, addr: u32) -> u8 {
+            self.0[addr as usize]
+        }
+        fn dma_write(&mut self, addr: u32, value: u8) {
+            self.0[addr as usize] = value;
+        }
+    }
+
+    const QH: u32 = 0x100;
+    const QTD: u32 = 0x200;
+    const BUF: u32 = 0x400;
+
+    fn write_reg(usb: &mut UsbController, offset: u32, value: u32) {
+        for i in 0..4 {
+            usb.write(offset + i, (value >> (i * 8)) as u8);
+        }
+    }
+
+    fn read_reg(usb: &UsbController, offset: u32) -> u32 {
+        (0..4).fold(0, |acc, i| acc | (usb.read(offset + i) as u32) << (i * 8))
+    }
+
+    /// Controller with an accessory attached, port reset done, async schedule on
+    fn setup() -> (UsbController, Mem) {
+        let mut usb = UsbController::new();
+        usb.attach(SerialAccessoryConfig::default());
+        write_reg(&mut usb, regs::PORTSC, port::PR);
+        write_reg(&mut usb, regs::PORTSC, 0);
+        write_reg(&mut usb, regs::ASYNCLISTADDR, QH);
+        write_reg(&mut usb, regs::USBINTR, sts::USBINT | sts::USBERRINT);
+        write_reg(&mut usb, regs::USBCMD, cmd::RUN | cmd::ASE);
+        (usb, Mem(vec![0; 0x1000]))
+    }
+
+    /// Run one transfer (`pid`: 0 OUT, 1 IN, 2 SETUP) and return the retired token
+    fn transfer(usb: &mut UsbController, mem: &mut Mem, address: u8, ep: u8, pid: u32, data: &[u8], len: usize) -> u32 {
+        mem.0[BUF as usize..BUF as usize + data.len()].copy_from_slice(data);
+        write32(mem, QH, QH | 2); // circular, type QH
+        write32(mem, QH + 0x04, address as u32 | (ep as u32) << 8 | (MAX_PACKET as u32) << 16);
+        write32(mem, QH + 0x10, QTD);
+        write32(mem, QH + 0x14, 1);
+        write32(mem, QH + 0x18, 0);
+        write32(mem, QTD, 1);
+        write32(mem, QTD + 0x04, 1);
+        write32(mem, QTD + 0x08, token::ACTIVE | token::IOC | pid << 8 | (len as u32) << 16);
+        write32(mem, QTD + 0x0C, BUF);
+        usb.tick(SCHEDULE_INTERVAL, mem);
+        read32(mem, QTD + 0x08)
+    }
+
+    fn control_in(usb: &mut UsbController, mem: &mut Mem, address: u8, setup: [u8; 8], len: usize) -> Vec<u8> {
+        transfer(usb, mem, address, 0, 2, &setup, 8);
+        let tok = transfer(usb, mem, address, 0, 1, &[], len);
+        let got = len - ((tok >> 16) & 0x7FFF) as usize;
+        let data = mem.0[BUF as usize..BUF as usize + got].to_vec();
+        transfer(usb, mem, address, 0, 0, &[], 0);
+        data
+    }
+
+    fn control_out(usb: &mut UsbController, mem: &mut Mem, address: u8, setup: [u8; 8], data: &[u8]) -> u32 {
+        transfer(usb, mem, address, 0, 2, &setup, 8);
+        if !data.is_empty() {
+            transfer(usb, mem, address, 0, 0, data, data.len());
+        }
+        transfer(usb, mem, address, 0, 1, &[], 0)
+    }
+
+    #[test]
+    fn test_port_connect_and_reset() {
+        let mut usb = UsbController::new();
+        assert_eq!(read_reg(&usb, regs::PORTSC) & port::CCS, 0);
+        assert_ne!(read_reg(&usb, regs::USBSTS) & sts::HCHALTED, 0);
+
+        usb.attach(SerialAccessoryConfig::default());
+        write_reg(&mut usb, regs::USBINTR, sts::PCD);
+        assert!(usb.irq_pending());
+        assert_eq!(read_reg(&usb, regs::PORTSC) & (port::CCS | port::CSC), port::CCS | port::CSC);
+        assert_ne!(read_reg(&usb, regs::GISR) & GISR_HOST, 0);
+
+        // Clearing the change bits drops the interrupt
+        write_reg(&mut usb, regs::USBSTS, sts::PCD);
+        write_reg(&mut usb, regs::PORTSC, port::CSC);
+        assert!(!usb.irq_pending());
+        assert_eq!(read_reg(&usb, regs::PORTSC) & port::CSC, 0);
+
+        write_reg(&mut usb, regs::PORTSC, port::PR);
+        write_reg(&mut usb, regs::PORTSC, 0);
+        assert_ne!(read_reg(&usb, regs::PORTSC) & port::PE, 0);
+
+        usb.detach();
+        assert_eq!(read_reg(&usb, regs::PORTSC) & (port::CCS | port::PE), 0);
+    }
+
+    #[test]
+    fn test_otg_reports_cable_state() {
+        let mut usb = UsbController::new();
+        // No cable: idle B-device in the peripheral role
+        assert_eq!(read_reg(&usb, regs::OTGCSR), otg::ID | otg::CROLE | otg::B_SESS_END);
+        write_reg(&mut usb, regs::OTGCSR, 0xFFFF_FFFF);
+        assert_eq!(read_reg(&usb, regs::OTGCSR), otg::CONTROL | otg::ID | otg::CROLE | otg::B_SESS_END);
+        write_reg(&mut usb, regs::OTGCSR, 0);
+
+        write_reg(&mut usb, regs::OTGIER, otg::ID_CHANGE);
+        usb.attach(SerialAccessoryConfig::default());
+        assert_eq!(read_reg(&usb, regs::OTGCSR), otg::A_VBUS_VLD | otg::A_SESS_VLD);
+        assert_eq!(read_reg(&usb, regs::OTGISR), otg::ID_CHANGE | otg::ROLE_CHANGE);
+        assert_eq!(read_reg(&usb, regs::GISR), GISR_OTG);
+        assert!(usb.irq_pending());
+        write_reg(&mut usb, regs::GIMR, GISR_OTG);
+        assert!(!usb.irq_pending());
+        write_reg(&mut usb, regs::GIMR, 0);
+        write_reg(&mut usb, regs::OTGISR, otg::ID_CHANGE | otg::ROLE_CHANGE);
+        assert!(!usb.irq_pending());
+    }
+
+    #[test]
+    fn test_device_regs_interrupts_and_soft_reset() {
+        let mut usb = UsbController::new();
+        write_reg(&mut usb, regs::DEVADR, 0x85);
+        write_reg(&mut usb, regs::DEV_MASK2, !1);
+        usb.raise_device_interrupt(2, 0b11);
+        assert_eq!(read_reg(&usb, regs::DEV_GROUP_STATUS), 1 << 2);
+        // Global interrupt enable gates the line
+        assert!(!usb.irq_pending());
+        write_reg(&mut usb, regs::DEVCR, devcr::GLOBAL_INT_EN);
+        assert_eq!(read_reg(&usb, regs::GISR), GISR_DEV);
+        assert!(usb.irq_pending());
+
+        // Clearing the unmasked source clears the group
+        write_reg(&mut usb, regs::DEV_STATUS2, 1);
+        assert_eq!(read_reg(&usb, regs::DEV_STATUS2), 0b10);
+        assert_eq!(read_reg(&usb, regs::DEV_GROUP_STATUS), 0);
+        assert!(!usb.irq_pending());
+
+        // Soft reset clears the block and itself
+        write_reg(&mut usb, regs::DEVCR, devcr::GLOBAL_INT_EN | devcr::SOFT_RESET);
+        assert_eq!(read_reg(&usb, regs::DEVCR), devcr::GLOBAL_INT_EN);
+        assert_eq!(read_reg(&usb, regs::DEVADR), 0);
+        assert_eq!(read_reg(&usb, regs::DEV_STATUS2), 0);
+    }
+
+    #[test]
+    fn test_enumerate_and_exchange_bytes() {
+        let (mut usb, mut mem) = setup();
+
+        // GET_DESCRIPTOR(device), first 8 bytes
+        let desc = control_in(&mut usb, &mut mem, 0, [0x80, 0x06, 0x00, 0x01, 0, 0, 8, 0], 8);
+        assert_eq!(desc, [18, 0x01, 0x00, 0x02, 0x02, 0x00, 0x00, 64]);
+        assert_ne!(read_reg(&usb, regs::USBSTS) & sts::USBINT, 0);
+        assert!(usb.irq_pending());
+
+        // SET_ADDRESS(5): old address stops responding
+        control_out(&mut usb, &mut mem, 0, [0x00, 0x05, 5, 0, 0, 0, 0, 0], &[]);
+        let tok = transfer(&mut usb, &mut mem, 0, 0, 2, &[0x80, 0x06, 0x00, 0x01, 0, 0, 18, 0], 8);
+        assert_ne!(tok & token::HALTED, 0);
+
+        // Full configuration descriptor
+        let config = control_in(&mut usb, &mut mem, 5, [0x80, 0x06, 0x00, 0x02, 0, 0, 0xFF, 0], 0xFF);
+        assert_eq!(config.len(), config[2] as usize);
+        let product = control_in(&mut usb, &mut mem, 5, [0x80, 0x06, 0x02, 0x03, 0x09, 0x04, 0xFF, 0], 0xFF);
+        assert_eq!(product[0] as usize, 2 + 2 * "TI-Innovator Hub".len());
+
+        // Bulk endpoints stall until configured
+        assert_ne!(transfer(&mut usb, &mut mem, 5, BULK_OUT_EP, 0, b"x", 1) & token::HALTED, 0);
+        control_out(&mut usb, &mut mem, 5, [0x00, 0x09, 1, 0, 0, 0, 0, 0], &[]);
+        assert!(usb.device().unwrap().is_configured());
+
+        // SET_LINE_CODING 115200 8N1
+        control_out(&mut usb, &mut mem, 5, [0x21, 0x20, 0, 0, 0, 0, 7, 0], &[0x00, 0xC2, 0x01, 0x00, 0, 0, 8]);
+        assert_eq!(usb.device().unwrap().baud_rate(), 115200);
+
+        // Calculator -> host
+        let tok = transfer(&mut usb, &mut mem, 5, BULK_OUT_EP, 0, b"READ LIGHT\n", 11);
+        assert_eq!(tok & token::ACTIVE, 0);
+        assert!(usb.has_output());
+        assert_eq!(usb.take_from_calc(usize::MAX), b"READ LIGHT\n");
+
+        // Host -> calculator: NAK while empty, short packet once data arrives
+        let tok = transfer(&mut usb, &mut mem, 5, BULK_IN_EP, 1, &[], 64);
+        assert_ne!(tok & token::ACTIVE, 0);
+        assert!(usb.push_to_calc(b"42\n"));
+        usb.tick(SCHEDULE_INTERVAL, &mut mem);
+        let tok = read32(&mut mem, QTD + 0x08);
+        assert_eq!(tok & token::ACTIVE, 0);
+        assert_eq!((tok >> 16) & 0x7FFF, 61);
+        assert_eq!(&mem.0[BUF as usize..BUF as usize + 3], b"42\n");
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut usb = UsbController::new();
+        write_reg(&mut usb, regs::USBINTR, sts::USBINT | sts::PCD);
+        write_reg(&mut usb, regs::OTGIER, otg::ID_CHANGE);
+        write_reg(&mut usb, regs::DEVCR, devcr::GLOBAL_INT_EN);
+        write_reg(&mut usb, regs::DEV_MASK2, 0x1234);
+        write_reg(&mut usb, 0x1F0, 0xCAFE);
+
+        let bytes = usb.to_bytes();
+        assert_eq!(bytes.len(), UsbController::SNAPSHOT_SIZE);
+        let mut restored = UsbController::new();
+        restored.from_bytes(&bytes).unwrap();
+        for offset in [regs::USBINTR, regs::OTGIER, regs::DEVCR, regs::DEV_MASK2, 0x1F0] {
+            assert_eq!(read_reg(&restored, offset), read_reg(&usb, offset), "offset {:#X}", offset);
+        }
+        assert_eq!(read_reg(&restored, regs::DEV_MASK2), 0x1234);
+        assert_eq!(restored.from_bytes(&bytes[..10]), Err(-105));
+    }
+
+    #[test]
+    fn test_unsupported_request_stalls() {
+        let (mut usb, mut mem) = setup();
+        transfer(&mut usb, &mut mem, 0, 0, 2, &[0x80, 0x06, 0x00, 0x07, 0, 0, 8, 0], 8);
+        let tok = transfer(&mut usb, &mut mem, 0, 0, 1, &[], 8);
+        assert_ne!(tok & token::HALTED, 0);
+        assert_ne!(read_reg(&usb, regs::USBSTS) & sts::USBERRINT, 0);
+    }
+}
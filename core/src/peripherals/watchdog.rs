@@ -11,6 +11,26 @@
 //!   0x10-0x13: Status (read, write-to-clear)
 //!   0x18:      Pulse load (8-bit)
 //!   0x1C-0x1F: Revision (0x00010602, read-only)
+//!
+//! Control bits (FTWDT010): 0 = enable, 1 = reset the machine on expiry,
+//! 2 = interrupt on expiry, 4 = count the 32kHz clock instead of the CPU
+//! clock. The counter counts down while enabled; the OS kicks it by writing
+//! 0xB9 to the restart register. On reaching zero it sets status bit 0,
+//! reloads, and raises `needs_reset` / `needs_nmi` for `Emu` to act on. The
+//! CE's interrupt controller has no watchdog source, so the interrupt is
+//! delivered as an NMI.
+
+/// Control: counter running
+const CTRL_ENABLE: u8 = 1 << 0;
+/// Control: reset the machine on expiry
+const CTRL_RESET: u8 = 1 << 1;
+/// Control: interrupt on expiry
+const CTRL_INTERRUPT: u8 = 1 << 2;
+/// Control: count the 32kHz clock
+const CTRL_CLOCK_32K: u8 = 1 << 4;
+
+/// 32kHz crystal frequency
+const CLOCK_32K: u64 = 32768;
 
 /// Watchdog Controller
 #[derive(Debug, Clone)]
@@ -25,6 +45,12 @@ pub struct WatchdogController {
     status: u8,
     /// Pulse load value
     pulse_load: u8,
+    /// 32kHz mode: CPU cycles × 32768 not yet worth a whole tick
+    clock_frac: u64,
+    /// Expired with reset enabled (consumed by Emu)
+    pub needs_reset: bool,
+    /// Expired with interrupt enabled (consumed by Emu)
+    pub needs_nmi: bool,
 }
 
 impl WatchdogController {
@@ -42,16 +68,15 @@ impl WatchdogController {
             control: 0x00,
             status: 0x00,
             pulse_load: 0xFF,
+            clock_frac: 0,
+            needs_reset: false,
+            needs_nmi: false,
         }
     }
 
     /// Reset the Watchdog controller
     pub fn reset(&mut self) {
-        self.count = Self::DEFAULT_LOAD;
-        self.load = Self::DEFAULT_LOAD;
-        self.control = 0x00;
-        self.status = 0x00;
-        self.pulse_load = 0xFF;
+        *self = Self::new();
     }
 
     /// Read a register byte
@@ -125,10 +150,67 @@ impl WatchdogController {
         }
     }
 
-    /// Tick the watchdog (called periodically)
-    /// TODO: Implement proper countdown and state machine (Phase 4+)
-    pub fn tick(&mut self, _cycles: u32) -> bool {
-        false
+    /// Count down `cycles` CPU cycles at `cpu_hz`. Returns true if the
+    /// counter expired.
+    pub fn tick(&mut self, cycles: u32, cpu_hz: u64) -> bool {
+        if self.control & CTRL_ENABLE == 0 {
+            return false;
+        }
+        let ticks = if self.control & CTRL_CLOCK_32K != 0 {
+            self.clock_frac += cycles as u64 * CLOCK_32K;
+            let ticks = self.clock_frac / cpu_hz;
+            self.clock_frac %= cpu_hz;
+            ticks
+        } else {
+            cycles as u64
+        };
+        if ticks < self.count as u64 {
+            self.count -= ticks as u32;
+            return false;
+        }
+
+        self.count = self.load;
+        self.status |= 1;
+        if self.control & CTRL_RESET != 0 {
+            self.needs_reset = true;
+        }
+        if self.control & CTRL_INTERRUPT != 0 {
+            self.needs_nmi = true;
+        }
+        true
+    }
+
+    // ========== State Persistence ==========
+
+    /// Size of watchdog state snapshot in bytes
+    pub const SNAPSHOT_SIZE: usize = 20;
+
+    /// Save watchdog state to bytes
+    pub fn to_bytes(&self) -> [u8; Self::SNAPSHOT_SIZE] {
+        let mut buf = [0u8; Self::SNAPSHOT_SIZE];
+        buf[0..4].copy_from_slice(&self.count.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.load.to_le_bytes());
+        buf[8] = self.control;
+        buf[9] = self.status;
+        buf[10] = self.pulse_load;
+        buf[12..20].copy_from_slice(&self.clock_frac.to_le_bytes());
+        buf
+    }
+
+    /// Load watchdog state from bytes
+    pub fn from_bytes(&mut self, buf: &[u8]) -> Result<(), i32> {
+        if buf.len() < Self::SNAPSHOT_SIZE {
+            return Err(-105);
+        }
+        self.count = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+        self.load = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+        self.control = buf[8];
+        self.status = buf[9];
+        self.pulse_load = buf[10];
+        self.clock_frac = u64::from_le_bytes(buf[12..20].try_into().unwrap());
+        self.needs_reset = false;
+        self.needs_nmi = false;
+        Ok(())
     }
 }
 
@@ -242,6 +324,48 @@ mod tests {
     #[test]
     fn test_tick_no_interrupt() {
         let mut wdt = WatchdogController::new();
-        assert!(!wdt.tick(1000));
+        assert!(!wdt.tick(1000, 48_000_000));
+        assert_eq!(wdt.count, WatchdogController::DEFAULT_LOAD);
+    }
+
+    #[test]
+    fn test_countdown_and_expiry() {
+        let mut wdt = WatchdogController::new();
+        wdt.write(0x04, 100);
+        wdt.write(0x05, 0);
+        wdt.write(0x06, 0);
+        wdt.write(0x07, 0);
+        wdt.write(0x08, 0xB9);
+        wdt.write(0x0C, 0x07); // enable, reset, interrupt
+
+        assert!(!wdt.tick(60, 48_000_000));
+        assert_eq!(wdt.read(0x00), 40);
+        // Kicking restarts the count
+        wdt.write(0x08, 0xB9);
+        assert!(!wdt.tick(60, 48_000_000));
+
+        assert!(wdt.tick(60, 48_000_000));
+        assert_eq!(wdt.read(0x10), 1);
+        assert_eq!(wdt.read(0x00), 100); // reloaded
+        assert!(wdt.needs_reset && wdt.needs_nmi);
+    }
+
+    #[test]
+    fn test_32k_clock_and_snapshot() {
+        let mut wdt = WatchdogController::new();
+        wdt.load = 10;
+        wdt.count = 10;
+        wdt.write(0x0C, 0x11); // enable, 32kHz, no action on expiry
+
+        // 6MHz: about 183 cycles per tick
+        assert!(!wdt.tick(183 * 9, 6_000_000));
+        assert_eq!(wdt.count, 2);
+        let bytes = wdt.to_bytes();
+        assert!(wdt.tick(184 * 2, 6_000_000));
+        assert!(!wdt.needs_reset && !wdt.needs_nmi);
+
+        wdt.from_bytes(&bytes).unwrap();
+        assert_eq!(wdt.count, 2);
+        assert_eq!(wdt.read(0x0C), 0x11);
     }
 }
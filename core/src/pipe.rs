@@ -0,0 +1,222 @@
+//! Line protocol for driving a headless emulator over stdin/stdout
+//!
+//! Lets shell scripts and other processes automate the emulator without
+//! linking against the crate (`cargo run --example debug -- pipe`). Each
+//! input line is one command; each command gets exactly one reply line
+//! starting with `ok` or `err`, so a driver can send a command and read
+//! one line back. Blank lines and lines starting with `#` are ignored.
+//!
+//! ```text
+//! key <name>        press and release a key (held 50ms, then 50ms settle)
+//! down <name>       hold a key
+//! up <name>         release a key
+//! type <keys>       press each space-separated key in turn
+//! run <ms>          run for <ms> emulated milliseconds -> ok <cycles>
+//! idle [ms]         run until the OS waits for input (default limit 5000ms)
+//! screen <path>     render the screen to a binary PPM file
+//! screen            render the screen as text -> ok <n>, then n lines
+//! text              homescreen text rows -> ok <n>, then n lines
+//! state             -> ok pc=<hex> cycles=<n> halted=<0|1> off=<0|1>
+//! quit              stop reading commands
+//! ```
+//!
+//! Key names are the keypad labels in lower case: `2nd`, `alpha`, `mode`,
+//! `del`, `enter`, `clear`, `up`, `0`-`9`, `+`, `-`, `*`, `/`, `(`, `)`, and
+//! so on (see `Key`). `on` is the ON key.
+
+use std::io::{self, BufRead, Write};
+
+use crate::emu::Emu;
+use crate::key::Key;
+use crate::term_render::TermStyle;
+
+/// How long `key` holds a key, and then waits after releasing it
+const KEY_HOLD_MS: f64 = 50.0;
+/// Default limit for `idle`
+const IDLE_LIMIT_MS: f64 = 5000.0;
+/// Width of `screen` text output
+const SCREEN_COLS: usize = 80;
+
+/// A key the protocol can press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeKey {
+    /// Keypad matrix key at (row, col)
+    Matrix(usize, usize),
+    /// The ON key, which is wired outside the matrix
+    On,
+}
+
+/// Look up a key by its protocol name (case-insensitive)
+pub fn key_by_name(name: &str) -> Option<PipeKey> {
+    Key::from_name(name).map(|key| match key {
+        Key::On => PipeKey::On,
+        key => {
+            let (row, col) = key.position();
+            PipeKey::Matrix(row, col)
+        }
+    })
+}
+
+/// Read commands from `input` until `quit` or end of input, writing one
+/// reply per command to `output`
+pub fn run<R: BufRead, W: Write>(emu: &mut Emu, input: R, mut output: W) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "quit" {
+            writeln!(output, "ok")?;
+            break;
+        }
+        match execute(emu, line) {
+            Ok(reply) => writeln!(output, "ok{}", reply)?,
+            Err(reason) => writeln!(output, "err {}", reason)?,
+        }
+        output.flush()?;
+    }
+    output.flush()
+}
+
+/// Run one command. The reply is the text after `ok` (including any
+/// following lines); errors are a one-line reason.
+pub fn execute(emu: &mut Emu, line: &str) -> Result<String, String> {
+    let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+    let args = args.trim();
+    match command {
+        "key" => {
+            let key = parse_key(args)?;
+            press(emu, key);
+            Ok(String::new())
+        }
+        "down" | "up" => {
+            set_key(emu, parse_key(args)?, command == "down");
+            Ok(String::new())
+        }
+        "type" => {
+            let keys = args.split_whitespace().map(parse_key).collect::<Result<Vec<_>, _>>()?;
+            for key in keys {
+                press(emu, key);
+            }
+            Ok(String::new())
+        }
+        "run" => {
+            let ms = parse_ms(args)?;
+            Ok(format!(" {}", emu.run_ms(ms)))
+        }
+        "idle" => {
+            let ms = if args.is_empty() { IDLE_LIMIT_MS } else { parse_ms(args)? };
+            match emu.run_until_idle(ms_to_cycles(emu, ms)) {
+                Some(cycles) => Ok(format!(" {}", cycles)),
+                None => Err("not idle before the time limit".to_string()),
+            }
+        }
+        "screen" => {
+            emu.render_frame();
+            if args.is_empty() {
+                let text = emu.screen_to_terminal(SCREEN_COLS, TermStyle::Ascii);
+                Ok(with_lines(text.lines()))
+            } else {
+                std::fs::write(args, screen_ppm(emu)).map_err(|e| format!("{}: {}", args, e))?;
+                Ok(format!(" {}", args))
+            }
+        }
+        "text" => Ok(with_lines(emu.homescreen_text().iter().map(String::as_str))),
+        "state" => Ok(format!(
+            " pc={:06X} cycles={} halted={} off={}",
+            emu.pc(),
+            emu.total_cycles(),
+            emu.is_halted() as u8,
+            emu.is_off() as u8
+        )),
+        _ => Err(format!("unknown command '{}'", command)),
+    }
+}
+
+fn parse_key(name: &str) -> Result<PipeKey, String> {
+    key_by_name(name).ok_or_else(|| format!("unknown key '{}'", name))
+}
+
+fn parse_ms(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(ms) if ms >= 0.0 && ms.is_finite() => Ok(ms),
+        _ => Err(format!("invalid milliseconds '{}'", text)),
+    }
+}
+
+/// " <n>" followed by the n lines, as one reply
+fn with_lines<'a>(lines: impl Iterator<Item = &'a str>) -> String {
+    let lines: Vec<&str> = lines.collect();
+    let mut reply = format!(" {}", lines.len());
+    for line in lines {
+        reply.push('\n');
+        reply.push_str(line);
+    }
+    reply
+}
+
+fn ms_to_cycles(emu: &Emu, ms: f64) -> u64 {
+    (ms * emu.cpu_clock_hz() / 1000.0) as u64
+}
+
+fn set_key(emu: &mut Emu, key: PipeKey, down: bool) {
+    match (key, down) {
+        (PipeKey::Matrix(row, col), _) => emu.set_key(row, col, down),
+        (PipeKey::On, true) => emu.press_on_key(),
+        (PipeKey::On, false) => emu.release_on_key(),
+    }
+}
+
+pub(crate) fn press(emu: &mut Emu, key: PipeKey) {
+    set_key(emu, key, true);
+    emu.run_ms(KEY_HOLD_MS);
+    set_key(emu, key, false);
+    emu.run_ms(KEY_HOLD_MS);
+}
+
+/// The framebuffer as a binary PPM (P6) image
+fn screen_ppm(emu: &Emu) -> Vec<u8> {
+    let (width, height) = emu.framebuffer_size();
+    let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for &pixel in emu.framebuffer_data() {
+        ppm.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+    }
+    ppm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_session() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+
+        let script = "# comment\n\nstate\nrun 1\nkey enter\ndown 2nd\nup 2nd\nkey bogus\nfly\nscreen\nquit\nstate\n";
+        let mut out = Vec::new();
+        run(&mut emu, script.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert!(lines[0].starts_with("ok pc=000000 cycles=") && lines[0].ends_with(" halted=0 off=0"));
+        assert!(lines[1].starts_with("ok ") && lines[1][3..].parse::<u64>().unwrap() >= 6000);
+        assert_eq!(&lines[2..5], ["ok", "ok", "ok"]);
+        assert_eq!(lines[5], "err unknown key 'bogus'");
+        assert_eq!(lines[6], "err unknown command 'fly'");
+        let rows: usize = lines[7][3..].parse().unwrap();
+        assert!(rows > 0);
+        // Nothing is read after quit
+        assert_eq!(lines[8 + rows..], ["ok"]);
+    }
+
+    #[test]
+    fn test_key_names() {
+        assert_eq!(key_by_name("ENTER"), Some(PipeKey::Matrix(6, 0)));
+        assert_eq!(key_by_name("on"), Some(PipeKey::On));
+        assert_eq!(key_by_name("7"), Some(PipeKey::Matrix(3, 3)));
+        assert_eq!(key_by_name("shift"), None);
+    }
+}
@@ -0,0 +1,239 @@
+//! Minimal PNG encoder for screenshots
+//!
+//! Writes 8-bit RGB images with no row filters. Pixel data is deflated with
+//! the fixed Huffman code, using only back-references to the previous pixel
+//! (distance 3): that run-length coding is all a calculator screen needs to
+//! shrink from 230KB to a few KB, and keeps the encoder dependency-free.
+
+use crate::frame_hash::Crc32;
+
+pub(crate) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Deflate length codes 257.. as (base length, extra bits)
+const LENGTHS: [(u16, u8); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0),
+    (11, 1), (13, 1), (15, 1), (17, 1), (19, 2), (23, 2), (27, 2), (31, 2),
+    (35, 3), (43, 3), (51, 3), (59, 3), (67, 4), (83, 4), (99, 4), (115, 4),
+    (131, 5), (163, 5), (195, 5), (227, 5), (258, 0),
+];
+
+/// Longest deflate match
+const MAX_MATCH: usize = 258;
+/// Back-reference distance: one RGB pixel
+const PIXEL_DISTANCE: usize = 3;
+
+/// Encode `width` x `height` ARGB8888 pixels (alpha ignored) as a PNG file
+pub fn encode_argb(pixels: &[u32], width: usize, height: usize) -> Vec<u8> {
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &ihdr(width, height));
+    write_chunk(&mut out, b"IDAT", &image_data(pixels, width, height));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// IHDR body for an 8-bit RGB image
+pub(crate) fn ihdr(width: usize, height: usize) -> Vec<u8> {
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB, deflate, no filter, no interlace
+    ihdr
+}
+
+/// Compressed image data (the IDAT body) for ARGB8888 pixels
+pub(crate) fn image_data(pixels: &[u32], width: usize, height: usize) -> Vec<u8> {
+    assert!(pixels.len() >= width * height, "pixel buffer smaller than image");
+
+    let mut raw = Vec::with_capacity(height * (1 + width * 3));
+    for row in pixels.chunks_exact(width).take(height) {
+        raw.push(0); // filter: none
+        for &p in row {
+            raw.extend_from_slice(&[(p >> 16) as u8, (p >> 8) as u8, p as u8]);
+        }
+    }
+    zlib(&raw)
+}
+
+pub(crate) fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc.finish().to_be_bytes());
+}
+
+/// zlib stream holding one fixed-Huffman deflate block
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    bits.put(1, 1); // final block
+    bits.put(1, 2); // fixed Huffman
+
+    let mut i = 0;
+    while i < data.len() {
+        let len = if i >= PIXEL_DISTANCE {
+            let limit = (data.len() - i).min(MAX_MATCH);
+            (0..limit).take_while(|&k| data[i + k] == data[i + k - PIXEL_DISTANCE]).count()
+        } else {
+            0
+        };
+        if len >= 3 {
+            put_length(&mut bits, len);
+            bits.put_code(PIXEL_DISTANCE as u32 - 1, 5); // distance code 2, no extra bits
+            i += len;
+        } else {
+            put_symbol(&mut bits, data[i] as u16);
+            i += 1;
+        }
+    }
+    put_symbol(&mut bits, 256); // end of block
+
+    let mut out = vec![0x78, 0x01];
+    out.extend_from_slice(&bits.finish());
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn put_length(bits: &mut BitWriter, len: usize) {
+    let code = LENGTHS.iter().rposition(|&(base, _)| base as usize <= len).unwrap();
+    let (base, extra) = LENGTHS[code];
+    put_symbol(bits, 257 + code as u16);
+    bits.put(len as u32 - base as u32, extra);
+}
+
+/// Literal/length symbol in the fixed Huffman code
+fn put_symbol(bits: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => bits.put_code(0x30 + symbol, 8),
+        144..=255 => bits.put_code(0x190 + symbol - 144, 9),
+        256..=279 => bits.put_code(symbol - 256, 7),
+        _ => bits.put_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// LSB-first bit packer for deflate
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    count: u8,
+}
+
+impl BitWriter {
+    /// Write the low `n` bits of `value`, least significant first
+    fn put(&mut self, value: u32, n: u8) {
+        self.acc |= (value as u64) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Write an `n`-bit Huffman code, most significant bit first
+    fn put_code(&mut self, code: u32, n: u8) {
+        self.put(code.reverse_bits() >> (32 - n), n);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Inflate a fixed-Huffman block, enough to check what `zlib` writes
+    fn inflate_fixed(stream: &[u8]) -> Vec<u8> {
+        let mut pos = 0usize;
+        let mut bit = |n: u8| -> u32 {
+            let mut v = 0;
+            for i in 0..n {
+                v |= (((stream[pos / 8] >> (pos % 8)) & 1) as u32) << i;
+                pos += 1;
+            }
+            v
+        };
+        assert_eq!(bit(3), 0b011);
+        let mut out: Vec<u8> = Vec::new();
+        loop {
+            // Read MSB-first until the prefix is a complete fixed code
+            let mut code = 0;
+            let mut len = 0;
+            let symbol = loop {
+                code = (code << 1) | bit(1);
+                len += 1;
+                match (len, code) {
+                    (7, 0..=0x17) => break code + 256,
+                    (8, 0x30..=0xBF) => break code - 0x30,
+                    (8, 0xC0..=0xC7) => break code - 0xC0 + 280,
+                    (9, 0x190..=0x1FF) => break code - 0x190 + 144,
+                    _ => assert!(len < 9),
+                }
+            };
+            match symbol {
+                0..=255 => out.push(symbol as u8),
+                256 => return out,
+                _ => {
+                    let (base, extra) = LENGTHS[symbol as usize - 257];
+                    let len = base as usize + bit(extra) as usize;
+                    let mut dist_code = 0;
+                    for _ in 0..5 {
+                        dist_code = (dist_code << 1) | bit(1);
+                    }
+                    assert_eq!(dist_code, 2);
+                    for _ in 0..len {
+                        out.push(out[out.len() - PIXEL_DISTANCE]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_structure() {
+        let mut pixels = vec![0xFFFFFFFF; 64 * 4];
+        pixels[70] = 0xFF123456;
+        let png = encode_argb(&pixels, 64, 4);
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 64, 0, 0, 0, 4]);
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]));
+
+        // IDAT inflates back to the filtered rows, with a valid Adler-32
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let zlib = &png[41..41 + idat_len];
+        assert_eq!(&zlib[..2], &[0x78, 0x01]);
+        let raw = inflate_fixed(&zlib[2..zlib.len() - 4]);
+        assert_eq!(raw.len(), 4 * (1 + 64 * 3));
+        assert_eq!(&raw[1 + 64 * 3 + 1 + 6 * 3..][..3], &[0x12, 0x34, 0x56]);
+        assert_eq!(&zlib[zlib.len() - 4..], &adler32(&raw).to_be_bytes());
+        assert!(png.len() < 200, "runs should compress: {} bytes", png.len());
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+}
@@ -0,0 +1,228 @@
+//! Optional host-time profiling
+//!
+//! Measures wall-clock time spent in each emulator subsystem so performance
+//! work can target the real bottleneck on each platform. Disabled by default;
+//! when disabled each probe costs a single branch.
+//!
+//! `std::time::Instant` is unavailable on wasm32-unknown-unknown, so probes
+//! are no-ops there and the breakdown is always empty. The same applies to
+//! builds without the `profiler` feature.
+
+#[cfg(all(feature = "profiler", not(target_arch = "wasm32")))]
+use std::time::Instant;
+
+/// Subsystems measured by the profiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileSection {
+    /// CPU instruction interpretation (cpu.step)
+    Cpu = 0,
+    /// Peripheral ticks (timers, OS timer, watchdog)
+    Peripherals = 1,
+    /// Scheduler event processing and DMA cycle stealing
+    Scheduler = 2,
+    /// Keypad any-key scans
+    Keypad = 3,
+    /// LCD render (VRAM to framebuffer)
+    LcdRender = 4,
+    /// Instruction trace logging
+    Trace = 5,
+}
+
+impl ProfileSection {
+    /// Number of sections
+    pub const COUNT: usize = 6;
+
+    /// All sections, in id order
+    pub const ALL: [ProfileSection; Self::COUNT] = [
+        ProfileSection::Cpu,
+        ProfileSection::Peripherals,
+        ProfileSection::Scheduler,
+        ProfileSection::Keypad,
+        ProfileSection::LcdRender,
+        ProfileSection::Trace,
+    ];
+
+    /// Section from its numeric id (used by FFI)
+    pub fn from_id(id: i32) -> Option<Self> {
+        Self::ALL.get(usize::try_from(id).ok()?).copied()
+    }
+
+    /// Short display name
+    pub fn name(self) -> &'static str {
+        match self {
+            ProfileSection::Cpu => "cpu",
+            ProfileSection::Peripherals => "peripherals",
+            ProfileSection::Scheduler => "scheduler",
+            ProfileSection::Keypad => "keypad",
+            ProfileSection::LcdRender => "lcd_render",
+            ProfileSection::Trace => "trace",
+        }
+    }
+}
+
+/// Accumulated time for one section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub section: ProfileSection,
+    /// Total host time in nanoseconds
+    pub nanos: u64,
+    /// Number of measured calls
+    pub calls: u64,
+}
+
+/// Opaque start token returned by `Profiler::start`
+#[cfg(all(feature = "profiler", not(target_arch = "wasm32")))]
+pub type ProbeStart = Option<Instant>;
+#[cfg(not(all(feature = "profiler", not(target_arch = "wasm32"))))]
+pub type ProbeStart = Option<()>;
+
+#[cfg(all(feature = "profiler", not(target_arch = "wasm32")))]
+#[inline(always)]
+fn probe_now() -> ProbeStart {
+    Some(Instant::now())
+}
+
+#[cfg(not(all(feature = "profiler", not(target_arch = "wasm32"))))]
+#[inline(always)]
+fn probe_now() -> ProbeStart {
+    None
+}
+
+#[cfg(all(feature = "profiler", not(target_arch = "wasm32")))]
+#[inline(always)]
+fn probe_elapsed_nanos(start: ProbeStart) -> Option<u64> {
+    start.map(|t| t.elapsed().as_nanos() as u64)
+}
+
+#[cfg(not(all(feature = "profiler", not(target_arch = "wasm32"))))]
+#[inline(always)]
+fn probe_elapsed_nanos(_start: ProbeStart) -> Option<u64> {
+    None
+}
+
+/// Per-subsystem host-time accumulator
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    enabled: bool,
+    nanos: [u64; ProfileSection::COUNT],
+    calls: [u64; ProfileSection::COUNT],
+}
+
+impl Profiler {
+    /// Create a disabled profiler
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            nanos: [0; ProfileSection::COUNT],
+            calls: [0; ProfileSection::COUNT],
+        }
+    }
+
+    /// Enable or disable measurement (accumulated data is kept)
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Check if measurement is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start a probe. Returns None when disabled.
+    #[inline(always)]
+    pub fn start(&self) -> ProbeStart {
+        if !self.enabled {
+            return None;
+        }
+        probe_now()
+    }
+
+    /// Finish a probe started with `start`, charging the elapsed time to `section`
+    #[inline(always)]
+    pub fn stop(&mut self, section: ProfileSection, start: ProbeStart) {
+        if let Some(nanos) = probe_elapsed_nanos(start) {
+            let i = section as usize;
+            self.nanos[i] += nanos;
+            self.calls[i] += 1;
+        }
+    }
+
+    /// Accumulated time for one section
+    pub fn entry(&self, section: ProfileSection) -> ProfileEntry {
+        let i = section as usize;
+        ProfileEntry { section, nanos: self.nanos[i], calls: self.calls[i] }
+    }
+
+    /// Accumulated time for every section, in id order
+    pub fn breakdown(&self) -> Vec<ProfileEntry> {
+        ProfileSection::ALL.iter().map(|&s| self.entry(s)).collect()
+    }
+
+    /// Human-readable breakdown with each section's share of measured time
+    pub fn summary(&self) -> String {
+        let total: u64 = self.nanos.iter().sum();
+        let mut out = String::new();
+        for e in self.breakdown() {
+            let pct = if total > 0 { e.nanos as f64 * 100.0 / total as f64 } else { 0.0 };
+            out.push_str(&format!(
+                "{:<12} {:>12} ns {:>10} calls {:>5.1}%\n",
+                e.section.name(), e.nanos, e.calls, pct
+            ));
+        }
+        out
+    }
+
+    /// Clear accumulated data (keeps enabled state)
+    pub fn reset(&mut self) {
+        self.nanos = [0; ProfileSection::COUNT];
+        self.calls = [0; ProfileSection::COUNT];
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_records_nothing() {
+        let mut p = Profiler::new();
+        let t = p.start();
+        assert!(t.is_none());
+        p.stop(ProfileSection::Cpu, t);
+        assert_eq!(p.entry(ProfileSection::Cpu).calls, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "profiler")]
+    fn test_enabled_records_calls() {
+        let mut p = Profiler::new();
+        p.set_enabled(true);
+        for _ in 0..3 {
+            let t = p.start();
+            p.stop(ProfileSection::LcdRender, t);
+        }
+        assert_eq!(p.entry(ProfileSection::LcdRender).calls, 3);
+        assert_eq!(p.entry(ProfileSection::Cpu).calls, 0);
+        assert!(p.summary().contains("lcd_render"));
+
+        p.reset();
+        assert_eq!(p.entry(ProfileSection::LcdRender).calls, 0);
+        assert!(p.is_enabled());
+    }
+
+    #[test]
+    fn test_section_ids() {
+        for (i, s) in ProfileSection::ALL.iter().enumerate() {
+            assert_eq!(*s as usize, i);
+            assert_eq!(ProfileSection::from_id(i as i32), Some(*s));
+        }
+        assert_eq!(ProfileSection::from_id(-1), None);
+        assert_eq!(ProfileSection::from_id(ProfileSection::COUNT as i32), None);
+    }
+}
@@ -0,0 +1,481 @@
+//! Animated screen recording
+//!
+//! Samples the framebuffer at a fixed rate of emulated time as
+//! `render_frame()` produces it, and encodes the result as a looping GIF or
+//! APNG. A frame that doesn't change just lengthens the previous frame's
+//! delay, so an idle screen costs nothing.
+//!
+//! GIF frames use an exact palette when they have at most 256 colors (true
+//! of almost every TI-OS screen) and fall back to RGB332 otherwise. APNG
+//! frames are lossless but are held in memory until the recording stops,
+//! since the frame count is written up front.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::frame_hash::hash_frame;
+use crate::png;
+
+/// Highest capture rate: GIF delays are in 1/100 s and most viewers slow
+/// down anything under 2
+pub const MAX_FPS: u32 = 50;
+
+/// Output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Gif,
+    Apng,
+}
+
+impl RecordingFormat {
+    /// Pick the format from a file extension (.gif, .png or .apng)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Frame waiting for the next change to learn how long it was shown
+struct Pending {
+    pixels: Vec<u32>,
+    hash: u64,
+    /// Capture slot (emulated seconds x fps) it was first shown in
+    tick: u64,
+}
+
+enum Encoder {
+    Gif,
+    Apng {
+        /// fcTL/IDAT/fdAT chunks so far
+        chunks: Vec<u8>,
+        sequence: u32,
+    },
+}
+
+/// A recording in progress
+pub(crate) struct Recorder {
+    sink: Box<dyn Write + Send>,
+    encoder: Encoder,
+    fps: u32,
+    width: usize,
+    height: usize,
+    pending: Option<Pending>,
+    frames: u64,
+    /// First write error; later frames are dropped
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    /// Start a `width` x `height` recording at `fps` (clamped to 1..=MAX_FPS)
+    pub fn new(format: RecordingFormat, fps: u32, width: usize, height: usize, sink: Box<dyn Write + Send>) -> Self {
+        let encoder = match format {
+            RecordingFormat::Gif => Encoder::Gif,
+            RecordingFormat::Apng => Encoder::Apng { chunks: Vec::new(), sequence: 0 },
+        };
+        let mut recorder = Self {
+            sink,
+            encoder,
+            fps: fps.clamp(1, MAX_FPS),
+            width,
+            height,
+            pending: None,
+            frames: 0,
+            error: None,
+        };
+        if format == RecordingFormat::Gif {
+            let header = gif_header(width, height);
+            recorder.write(&header);
+        }
+        recorder
+    }
+
+    /// Offer a rendered frame at `secs` of emulated time
+    pub fn capture(&mut self, pixels: &[u32], secs: f64) {
+        if self.error.is_some() {
+            return;
+        }
+        let tick = (secs * self.fps as f64) as u64;
+        let hash = hash_frame(pixels);
+        match self.pending.take() {
+            Some(pending) if pending.hash == hash => {
+                self.pending = Some(pending);
+                return;
+            }
+            // Within one capture slot the latest frame wins
+            Some(pending) if tick <= pending.tick => {
+                self.pending = Some(Pending { pixels: pixels.to_vec(), hash, tick: pending.tick });
+                return;
+            }
+            Some(pending) => self.emit(&pending, tick - pending.tick),
+            None => {}
+        }
+        self.pending = Some(Pending { pixels: pixels.to_vec(), hash, tick });
+    }
+
+    /// Write the last frame (shown until `secs`) and the trailer. Returns
+    /// the number of frames written or the first write error.
+    pub fn finish(mut self, secs: f64) -> io::Result<u64> {
+        if let Some(pending) = self.pending.take() {
+            let tick = (secs * self.fps as f64) as u64;
+            self.emit(&pending, tick.saturating_sub(pending.tick).max(1));
+        }
+        let trailer = match &mut self.encoder {
+            Encoder::Gif => vec![0x3B],
+            Encoder::Apng { chunks, .. } => {
+                let mut out = png::SIGNATURE.to_vec();
+                png::write_chunk(&mut out, b"IHDR", &png::ihdr(self.width, self.height));
+                let mut actl = (self.frames as u32).to_be_bytes().to_vec();
+                actl.extend_from_slice(&0u32.to_be_bytes()); // loop forever
+                png::write_chunk(&mut out, b"acTL", &actl);
+                out.append(chunks);
+                png::write_chunk(&mut out, b"IEND", &[]);
+                out
+            }
+        };
+        self.write(&trailer);
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.sink.flush()?;
+        Ok(self.frames)
+    }
+
+    /// Encode a frame shown for `ticks` capture slots
+    fn emit(&mut self, frame: &Pending, ticks: u64) {
+        let (width, height) = (self.width, self.height);
+        match &mut self.encoder {
+            Encoder::Gif => {
+                // Round on the running clock so delays don't drift
+                let cs = |tick: u64| tick * 100 / self.fps as u64;
+                let delay = (cs(frame.tick + ticks) - cs(frame.tick)).min(u16::MAX as u64) as u16;
+                let data = gif_frame(&frame.pixels, width, height, delay);
+                self.write(&data);
+            }
+            Encoder::Apng { chunks, sequence } => {
+                let mut fctl = Vec::with_capacity(26);
+                fctl.extend_from_slice(&sequence.to_be_bytes());
+                fctl.extend_from_slice(&(width as u32).to_be_bytes());
+                fctl.extend_from_slice(&(height as u32).to_be_bytes());
+                fctl.extend_from_slice(&[0; 8]); // x, y offset
+                fctl.extend_from_slice(&(ticks.min(u16::MAX as u64) as u16).to_be_bytes());
+                fctl.extend_from_slice(&(self.fps as u16).to_be_bytes());
+                fctl.extend_from_slice(&[0, 0]); // dispose none, blend source
+                png::write_chunk(chunks, b"fcTL", &fctl);
+                *sequence += 1;
+
+                let data = png::image_data(&frame.pixels, width, height);
+                if self.frames == 0 {
+                    png::write_chunk(chunks, b"IDAT", &data);
+                } else {
+                    let mut fdat = sequence.to_be_bytes().to_vec();
+                    fdat.extend_from_slice(&data);
+                    png::write_chunk(chunks, b"fdAT", &fdat);
+                    *sequence += 1;
+                }
+            }
+        }
+        self.frames += 1;
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        if self.error.is_none() {
+            if let Err(e) = self.sink.write_all(data) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+fn gif_header(width: usize, height: usize) -> Vec<u8> {
+    let mut out = b"GIF89a".to_vec();
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    out.extend_from_slice(&[0, 0, 0]); // no global color table
+    // NETSCAPE2.0 application extension: loop forever
+    out.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+    out
+}
+
+/// Graphic control extension, image descriptor, local color table and
+/// image data for one frame
+fn gif_frame(pixels: &[u32], width: usize, height: usize, delay_cs: u16) -> Vec<u8> {
+    let (palette, indices) = quantize(&pixels[..width * height]);
+
+    let mut out = vec![0x21, 0xF9, 0x04, 0x04]; // dispose: leave in place
+    out.extend_from_slice(&delay_cs.to_le_bytes());
+    out.extend_from_slice(&[0x00, 0x00]);
+
+    out.push(0x2C);
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    out.push(0x87); // 256-entry local color table
+    for i in 0..256 {
+        let rgb = palette.get(i).copied().unwrap_or(0);
+        out.extend_from_slice(&[(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]);
+    }
+
+    out.push(8); // LZW minimum code size
+    for block in lzw(&indices).chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+    out
+}
+
+/// Palette (RGB) and per-pixel indices. Exact when there are at most 256
+/// colors, RGB332 otherwise.
+fn quantize(pixels: &[u32]) -> (Vec<u32>, Vec<u8>) {
+    let mut palette = Vec::new();
+    let mut lookup = HashMap::new();
+    let mut indices = Vec::with_capacity(pixels.len());
+    for &p in pixels {
+        let rgb = p & 0xFFFFFF;
+        let index = match lookup.get(&rgb) {
+            Some(&index) => index,
+            None if palette.len() < 256 => {
+                let index = palette.len() as u8;
+                lookup.insert(rgb, index);
+                palette.push(rgb);
+                index
+            }
+            None => return rgb332(pixels),
+        };
+        indices.push(index);
+    }
+    (palette, indices)
+}
+
+fn rgb332(pixels: &[u32]) -> (Vec<u32>, Vec<u8>) {
+    let palette = (0..256u32)
+        .map(|i| {
+            let r = (i >> 5) * 255 / 7;
+            let g = ((i >> 2) & 7) * 255 / 7;
+            let b = (i & 3) * 255 / 3;
+            (r << 16) | (g << 8) | b
+        })
+        .collect();
+    let indices = pixels
+        .iter()
+        .map(|&p| (((p >> 16) & 0xE0) | ((p >> 11) & 0x1C) | ((p >> 6) & 0x03)) as u8)
+        .collect();
+    (palette, indices)
+}
+
+const LZW_CLEAR: u16 = 256;
+const LZW_END: u16 = 257;
+const LZW_MAX_CODE: u16 = 4095;
+
+/// GIF LZW with an 8-bit minimum code size, packed LSB-first
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let (mut acc, mut count) = (0u32, 0u32);
+    let mut put = |code: u16, size: u32| {
+        acc |= (code as u32) << count;
+        count += size;
+        while count >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            count -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = LZW_END + 1;
+    let mut size = 9;
+    put(LZW_CLEAR, size);
+    let Some((&first, rest)) = indices.split_first() else {
+        put(LZW_END, size);
+        if count > 0 {
+            out.push(acc as u8);
+        }
+        return out;
+    };
+
+    let mut prefix = first as u16;
+    for &byte in rest {
+        if let Some(&code) = table.get(&(prefix, byte)) {
+            prefix = code;
+            continue;
+        }
+        put(prefix, size);
+        // The decoder adds an entry per code read; widen and reset in step
+        let code = next;
+        next += 1;
+        if code == 1 << size {
+            size += 1;
+        }
+        if code == LZW_MAX_CODE {
+            put(LZW_CLEAR, size);
+            table.clear();
+            next = LZW_END + 1;
+            size = 9;
+        } else {
+            table.insert((prefix, byte), code);
+        }
+        prefix = byte as u16;
+    }
+    put(prefix, size);
+    if next == 1 << size {
+        size += 1;
+    }
+    put(LZW_END, size);
+    if count > 0 {
+        out.push(acc as u8);
+    }
+    out
+}
+
+/// Start a recording to a file, choosing the format from its extension
+pub(crate) fn create(path: &Path, fps: u32, width: usize, height: usize) -> io::Result<Recorder> {
+    let format = RecordingFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "recording must be .gif, .png or .apng")
+    })?;
+    let file = std::fs::File::create(path)?;
+    Ok(Recorder::new(format, fps, width, height, Box::new(io::BufWriter::new(file))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+    impl Write for Shared {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Reference GIF LZW decoder
+    fn unlzw(data: &[u8]) -> Vec<u8> {
+        let mut pos = 0usize;
+        let mut read = |size: u32| -> u16 {
+            let mut v = 0;
+            for i in 0..size {
+                v |= (((data[pos / 8] >> (pos % 8)) & 1) as u16) << i;
+                pos += 1;
+            }
+            v
+        };
+        let mut out = Vec::new();
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut size = 9;
+        let mut prev: Option<Vec<u8>> = None;
+        loop {
+            let code = read(size);
+            if code == LZW_CLEAR {
+                table = (0..=255u8).map(|b| vec![b]).chain([vec![], vec![]]).collect();
+                size = 9;
+                prev = None;
+                continue;
+            }
+            if code == LZW_END {
+                return out;
+            }
+            let entry = match (table.get(code as usize), &prev) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(p)) => [p.clone(), vec![p[0]]].concat(),
+                (None, None) => panic!("bad first code"),
+            };
+            if let Some(p) = prev {
+                if table.len() < 4096 {
+                    table.push([p, vec![entry[0]]].concat());
+                }
+            }
+            if table.len() == 1 << size && size < 12 {
+                size += 1;
+            }
+            out.extend_from_slice(&entry);
+            prev = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let runs: Vec<u8> = (0..20_000u32).map(|i| (i / 37 % 5) as u8).collect();
+        let noise: Vec<u8> = (0..20_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        for data in [vec![], vec![7], runs, noise] {
+            assert_eq!(unlzw(&lzw(&data)), data);
+        }
+    }
+
+    #[test]
+    fn test_quantize() {
+        let (palette, indices) = quantize(&[0xFF112233, 0xFFFFFFFF, 0xFF112233]);
+        assert_eq!(palette, [0x112233, 0xFFFFFF]);
+        assert_eq!(indices, [0, 1, 0]);
+
+        let many: Vec<u32> = (0..300).map(|i| 0xFF000000 | (i * 0x010101)).collect();
+        let (palette, indices) = quantize(&many);
+        assert_eq!(palette.len(), 256);
+        assert_eq!(indices[299], rgb332(&[many[299]]).1[0]);
+    }
+
+    #[test]
+    fn test_gif_recording_merges_unchanged_frames() {
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let mut rec = Recorder::new(RecordingFormat::Gif, 10, 4, 2, Box::new(Shared(out.clone())));
+        let a = [0xFF000000u32; 8];
+        let mut b = a;
+        b[3] = 0xFFFFFFFF;
+        rec.capture(&a, 0.0);
+        rec.capture(&a, 0.25);
+        rec.capture(&b, 0.31);
+        assert_eq!(rec.finish(0.5).unwrap(), 2);
+
+        let gif = out.lock().unwrap().clone();
+        assert!(gif.starts_with(b"GIF89a\x04\x00\x02\x00"));
+        assert_eq!(gif.last(), Some(&0x3B));
+        let delays: Vec<u16> = gif
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| w[..3] == [0x21, 0xF9, 0x04])
+            .map(|(i, _)| u16::from_le_bytes([gif[i + 4], gif[i + 5]]))
+            .collect();
+        // a from slot 0 to 3, b from 3 to 5
+        assert_eq!(delays, [30, 20]);
+    }
+
+    #[test]
+    fn test_apng_recording() {
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let mut rec = Recorder::new(RecordingFormat::Apng, 30, 4, 2, Box::new(Shared(out.clone())));
+        rec.capture(&[0xFF000000; 8], 0.0);
+        rec.capture(&[0xFFFFFFFF; 8], 0.1);
+        rec.capture(&[0xFF00FF00; 8], 0.2);
+        assert_eq!(rec.finish(0.3).unwrap(), 3);
+
+        let apng = out.lock().unwrap().clone();
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < apng.len() {
+            let len = u32::from_be_bytes(apng[pos..pos + 4].try_into().unwrap()) as usize;
+            chunks.push((std::str::from_utf8(&apng[pos + 4..pos + 8]).unwrap().to_string(), apng[pos + 8..pos + 8 + len].to_vec()));
+            pos += 12 + len;
+        }
+        let kinds: Vec<&str> = chunks.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(kinds, ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]);
+        assert_eq!(&chunks[1].1[..4], &3u32.to_be_bytes());
+        // Sequence numbers run 0.. across fcTL and fdAT; delays are 3/30 s
+        let sequence: Vec<u32> = chunks[2..8]
+            .iter()
+            .filter(|(k, _)| k != "IDAT")
+            .map(|(_, d)| u32::from_be_bytes(d[..4].try_into().unwrap()))
+            .collect();
+        assert_eq!(sequence, [0, 1, 2, 3, 4]);
+        assert_eq!(&chunks[2].1[20..24], &[0, 3, 0, 30]);
+    }
+}
@@ -0,0 +1,208 @@
+//! Input recording and deterministic replay
+//!
+//! Key events are stamped with the instructions retired and cycles elapsed
+//! since the recording started, and replayed at the same point of a run
+//! started from the same state (a save state or a fresh boot). Instructions
+//! are the primary key because cycle counts are rescaled on CPU speed
+//! changes; cycles place events inside a HALT, where no instructions retire.
+//!
+//! A replay file is an 8-byte header (`MAGIC`, `VERSION`, `EVENT_SIZE`, two
+//! zero bytes) followed by fixed-size little-endian events.
+
+/// Replay file magic
+pub const MAGIC: &[u8; 4] = b"EZRP";
+
+/// Replay file format version
+pub const VERSION: u8 = 1;
+
+/// Header bytes before the first event
+pub const HEADER_SIZE: usize = 8;
+
+/// Bytes per event: instructions (8), cycles (8), row, col, down
+pub const EVENT_SIZE: usize = 19;
+
+/// Replay data was malformed (matches `load_state`'s bad-magic code)
+pub const ERR_INVALID: i32 = -102;
+
+/// Replay data has an unsupported version
+pub const ERR_VERSION: i32 = -103;
+
+/// A key change, stamped relative to the start of the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayEvent {
+    /// Instructions retired before the change
+    pub instructions: u64,
+    /// Cycles elapsed before the change (negative if the CPU was slowed
+    /// down, which rescales the cycle counter)
+    pub cycles: i64,
+    /// Keypad matrix row
+    pub row: usize,
+    /// Keypad matrix column
+    pub col: usize,
+    /// Pressed (true) or released
+    pub down: bool,
+}
+
+impl ReplayEvent {
+    fn to_bytes(self) -> [u8; EVENT_SIZE] {
+        let mut out = [0; EVENT_SIZE];
+        out[0..8].copy_from_slice(&self.instructions.to_le_bytes());
+        out[8..16].copy_from_slice(&self.cycles.to_le_bytes());
+        out[16] = self.row as u8;
+        out[17] = self.col as u8;
+        out[18] = self.down as u8;
+        out
+    }
+
+    fn from_bytes(data: &[u8; EVENT_SIZE]) -> Self {
+        Self {
+            instructions: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+            cycles: i64::from_le_bytes(data[8..16].try_into().unwrap()),
+            row: data[16] as usize,
+            col: data[17] as usize,
+            down: data[18] != 0,
+        }
+    }
+
+    /// True once a run at (`instructions`, `cycles`) has reached this event
+    fn is_due(&self, instructions: u64, cycles: i64) -> bool {
+        instructions > self.instructions || (instructions == self.instructions && cycles >= self.cycles)
+    }
+}
+
+/// Serialize events as a replay file
+pub fn encode(events: &[ReplayEvent]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_SIZE + events.len() * EVENT_SIZE);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[VERSION, EVENT_SIZE as u8, 0, 0]);
+    for event in events {
+        out.extend_from_slice(&event.to_bytes());
+    }
+    out
+}
+
+/// Parse a replay file. Fails with `ERR_INVALID` or `ERR_VERSION`.
+pub fn parse(data: &[u8]) -> Result<Vec<ReplayEvent>, i32> {
+    let header = data.get(..HEADER_SIZE).ok_or(ERR_INVALID)?;
+    if &header[..4] != MAGIC {
+        return Err(ERR_INVALID);
+    }
+    if header[4] != VERSION || header[5] as usize != EVENT_SIZE {
+        return Err(ERR_VERSION);
+    }
+    let body = &data[HEADER_SIZE..];
+    if !body.len().is_multiple_of(EVENT_SIZE) {
+        return Err(ERR_INVALID);
+    }
+    let events: Vec<ReplayEvent> =
+        body.chunks_exact(EVENT_SIZE).map(|chunk| ReplayEvent::from_bytes(chunk.try_into().unwrap())).collect();
+    if events.windows(2).any(|w| (w[1].instructions, w[1].cycles) < (w[0].instructions, w[0].cycles)) {
+        return Err(ERR_INVALID); // out of order
+    }
+    Ok(events)
+}
+
+/// Position of a run, in the counters events are stamped with
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RunPoint {
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+impl RunPoint {
+    fn since(self, start: RunPoint) -> (u64, i64) {
+        (self.instructions.saturating_sub(start.instructions), self.cycles as i64 - start.cycles as i64)
+    }
+}
+
+/// Key events captured since `start`
+pub(crate) struct InputRecorder {
+    start: RunPoint,
+    events: Vec<ReplayEvent>,
+}
+
+impl InputRecorder {
+    pub fn new(start: RunPoint) -> Self {
+        Self { start, events: Vec::new() }
+    }
+
+    pub fn record(&mut self, at: RunPoint, row: usize, col: usize, down: bool) {
+        let (instructions, cycles) = at.since(self.start);
+        self.events.push(ReplayEvent { instructions, cycles, row, col, down });
+    }
+
+    pub fn events(&self) -> &[ReplayEvent] {
+        &self.events
+    }
+}
+
+/// A replay in progress
+pub(crate) struct ReplayPlayer {
+    start: RunPoint,
+    events: Vec<ReplayEvent>,
+    next: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(events: Vec<ReplayEvent>, start: RunPoint) -> Self {
+        Self { start, events, next: 0 }
+    }
+
+    /// Take the next event if the run at `at` has reached it
+    pub fn take_due(&mut self, at: RunPoint) -> Option<ReplayEvent> {
+        let (instructions, cycles) = at.since(self.start);
+        let event = *self.events.get(self.next).filter(|e| e.is_due(instructions, cycles))?;
+        self.next += 1;
+        Some(event)
+    }
+
+    /// Cycles to run before the next event could be due (at least 1)
+    pub fn cycles_until_next(&self, at: RunPoint) -> Option<u64> {
+        let (_, cycles) = at.since(self.start);
+        let event = self.events.get(self.next)?;
+        Some((event.cycles - cycles).max(1) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(instructions: u64, cycles: i64, down: bool) -> ReplayEvent {
+        ReplayEvent { instructions, cycles, row: 6, col: 0, down }
+    }
+
+    #[test]
+    fn test_encode_parse_roundtrip() {
+        let events = vec![event(0, 0, true), event(10, -4, false), event(10, 8, true)];
+        let data = encode(&events);
+        assert_eq!(data.len(), HEADER_SIZE + 3 * EVENT_SIZE);
+        assert_eq!(parse(&data), Ok(events));
+
+        assert_eq!(parse(b"EZR"), Err(ERR_INVALID));
+        assert_eq!(parse(&data[..data.len() - 1]), Err(ERR_INVALID));
+        let mut old = data.clone();
+        old[4] = 0;
+        assert_eq!(parse(&old), Err(ERR_VERSION));
+        let unordered = encode(&[event(10, 0, true), event(9, 50, false)]);
+        assert_eq!(parse(&unordered), Err(ERR_INVALID));
+    }
+
+    #[test]
+    fn test_player_orders_by_instructions_then_cycles() {
+        let start = RunPoint { instructions: 100, cycles: 1000 };
+        let mut recorder = InputRecorder::new(start);
+        recorder.record(RunPoint { instructions: 105, cycles: 1040 }, 6, 0, true);
+        recorder.record(RunPoint { instructions: 105, cycles: 1900 }, 6, 0, false);
+
+        let mut player = ReplayPlayer::new(recorder.events().to_vec(), RunPoint { instructions: 0, cycles: 0 });
+        assert_eq!(player.take_due(RunPoint { instructions: 4, cycles: 60 }), None);
+        assert_eq!(player.cycles_until_next(RunPoint { instructions: 4, cycles: 30 }), Some(10));
+        assert!(player.take_due(RunPoint { instructions: 5, cycles: 40 }).unwrap().down);
+        // Halted: the release waits for its cycle
+        assert_eq!(player.take_due(RunPoint { instructions: 5, cycles: 500 }), None);
+        assert_eq!(player.cycles_until_next(RunPoint { instructions: 5, cycles: 500 }), Some(400));
+        assert!(!player.take_due(RunPoint { instructions: 6, cycles: 10 }).unwrap().down);
+        assert_eq!(player.cycles_until_next(RunPoint { instructions: 6, cycles: 10 }), None);
+    }
+}
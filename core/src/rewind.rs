@@ -0,0 +1,245 @@
+//! Rewind buffer
+//!
+//! Keeps a bounded history of machine states, one every `interval_frames`
+//! LCD frames, so the emulator can step back to shortly before something
+//! went wrong (`Emu::rewind`), e.g. a few seconds before an assembly program
+//! crashed the calculator.
+//!
+//! Only the newest state is kept whole. Each older one is stored as its
+//! difference from the next newer state: the two XORed together, with runs
+//! of zero bytes collapsed. Flash and most of RAM do not change between
+//! snapshots, so each step back costs little more than the bytes that did.
+
+use std::collections::VecDeque;
+
+/// Default LCD frames between snapshots (half a second at 60Hz)
+pub const DEFAULT_INTERVAL_FRAMES: u32 = 30;
+
+/// Default snapshots kept (a minute of history at the default interval)
+pub const DEFAULT_CAPACITY: usize = 120;
+
+/// How often to capture and how much history to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewindConfig {
+    /// LCD frames between snapshots (at least 1)
+    pub interval_frames: u32,
+    /// Snapshots kept, the newest included; older ones are dropped
+    pub capacity: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self { interval_frames: DEFAULT_INTERVAL_FRAMES, capacity: DEFAULT_CAPACITY }
+    }
+}
+
+/// XOR `old` and `new` (same length) into runs of
+/// `[zero run (u32 LE), literal length (u32 LE), literal bytes]`
+pub fn encode_delta(old: &[u8], new: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(old.len(), new.len());
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        let zero_start = i;
+        while i < new.len() && old[i] == new[i] {
+            i += 1;
+        }
+        let literal_start = i;
+        if literal_start == new.len() {
+            break;
+        }
+        // A literal ends at the first run of 8 equal bytes, where starting a
+        // new record costs less than carrying the zeros
+        let mut same = 0;
+        while i < new.len() && same < 8 {
+            same = if old[i] == new[i] { same + 1 } else { 0 };
+            i += 1;
+        }
+        let literal_end = i - same;
+        i = literal_end;
+        out.extend_from_slice(&((literal_start - zero_start) as u32).to_le_bytes());
+        out.extend_from_slice(&((literal_end - literal_start) as u32).to_le_bytes());
+        out.extend(old[literal_start..literal_end].iter().zip(&new[literal_start..literal_end]).map(|(a, b)| a ^ b));
+    }
+    out
+}
+
+/// Apply a delta from `encode_delta` in place, turning one of its states
+/// into the other. Returns false if the delta does not fit `state`.
+pub fn apply_delta(state: &mut [u8], delta: &[u8]) -> bool {
+    let mut pos = 0;
+    let mut at = 0;
+    while pos < delta.len() {
+        let Some(header) = delta.get(pos..pos + 8) else {
+            return false;
+        };
+        let zeros = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let len = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+        pos += 8;
+        at += zeros;
+        let (Some(target), Some(bytes)) = (state.get_mut(at..at + len), delta.get(pos..pos + len)) else {
+            return false;
+        };
+        for (t, b) in target.iter_mut().zip(bytes) {
+            *t ^= b;
+        }
+        at += len;
+        pos += len;
+    }
+    true
+}
+
+/// Ring of snapshots, newest whole and older ones as deltas
+#[derive(Debug, Clone)]
+pub struct RewindBuffer {
+    config: RewindConfig,
+    /// LCD frames seen while recording
+    frames: u64,
+    /// Newest snapshot: frame it was taken at and the full state
+    latest: Option<(u64, Vec<u8>)>,
+    /// Older snapshots, oldest first: frame taken at and the delta that
+    /// turns the next newer state into this one
+    deltas: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl RewindBuffer {
+    /// Empty buffer
+    pub fn new(config: RewindConfig) -> Self {
+        let config = RewindConfig { interval_frames: config.interval_frames.max(1), capacity: config.capacity.max(1) };
+        Self { config, frames: 0, latest: None, deltas: VecDeque::new() }
+    }
+
+    /// Configuration in use
+    pub fn config(&self) -> RewindConfig {
+        self.config
+    }
+
+    /// An LCD frame completed
+    pub fn frame_done(&mut self) {
+        self.frames += 1;
+    }
+
+    /// LCD frames seen while recording
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// True once `interval_frames` have passed since the newest snapshot
+    pub fn is_due(&self) -> bool {
+        match &self.latest {
+            Some((frame, _)) => self.frames - frame >= self.config.interval_frames as u64,
+            None => true,
+        }
+    }
+
+    /// Record `state` as the newest snapshot
+    pub fn push(&mut self, state: Vec<u8>) {
+        if let Some((frame, previous)) = self.latest.take() {
+            if previous.len() == state.len() {
+                self.deltas.push_back((frame, encode_delta(&state, &previous)));
+            } else {
+                // Different state format: older history cannot be rebuilt
+                self.deltas.clear();
+            }
+        }
+        self.latest = Some((self.frames, state));
+        while self.len() > self.config.capacity {
+            self.deltas.pop_front();
+        }
+    }
+
+    /// Snapshots held
+    pub fn len(&self) -> usize {
+        self.latest.is_some() as usize + self.deltas.len()
+    }
+
+    /// True if no snapshot has been taken
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    /// Bytes used by the snapshots
+    pub fn memory_usage(&self) -> usize {
+        self.latest.as_ref().map_or(0, |(_, s)| s.len()) + self.deltas.iter().map(|(_, d)| d.len()).sum::<usize>()
+    }
+
+    /// Step back to the newest snapshot taken at least `frames` frames ago
+    /// (or the oldest one held), dropping everything newer. Returns how many
+    /// frames back that is and the state to load.
+    pub fn rewind(&mut self, frames: u64) -> Option<(u64, Vec<u8>)> {
+        let target = self.frames.saturating_sub(frames);
+        let (mut frame, mut state) = self.latest.take()?;
+        while frame > target {
+            let Some((older, delta)) = self.deltas.pop_back() else {
+                break;
+            };
+            if !apply_delta(&mut state, &delta) {
+                self.deltas.clear();
+                break;
+            }
+            frame = older;
+        }
+        let rewound = self.frames - frame;
+        self.frames = frame;
+        self.latest = Some((frame, state.clone()));
+        Some((rewound, state))
+    }
+
+    /// Drop all snapshots
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_round_trip() {
+        let old: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let mut new = old.clone();
+        new[0] ^= 1;
+        new[500..520].fill(0xAA);
+        new[503] = old[503]; // a short equal run stays inside the literal
+        new[999] = 7;
+
+        let delta = encode_delta(&old, &new);
+        assert!(delta.len() < 80);
+        let mut state = old.clone();
+        assert!(apply_delta(&mut state, &delta));
+        assert_eq!(state, new);
+        assert!(apply_delta(&mut state, &delta));
+        assert_eq!(state, old);
+
+        assert!(encode_delta(&old, &old).is_empty());
+        assert!(!apply_delta(&mut state[..10], &delta));
+    }
+
+    #[test]
+    fn test_rewind_steps_back_and_drops_newer() {
+        let mut buffer = RewindBuffer::new(RewindConfig { interval_frames: 10, capacity: 3 });
+        for n in 0..5u8 {
+            assert!(buffer.is_due());
+            buffer.push(vec![n; 64]);
+            for _ in 0..10 {
+                buffer.frame_done();
+            }
+        }
+        // Frames 20, 30 and 40 are kept
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.frames(), 50);
+
+        // 15 frames back is frame 35: the snapshot from frame 30
+        assert_eq!(buffer.rewind(15), Some((20, vec![3; 64])));
+        assert_eq!(buffer.len(), 2);
+        assert!(!buffer.is_due());
+
+        // Further than the history goes stops at the oldest
+        assert_eq!(buffer.rewind(1000), Some((10, vec![2; 64])));
+        assert_eq!(buffer.len(), 1);
+        buffer.clear();
+        assert_eq!(buffer.rewind(1), None);
+    }
+}
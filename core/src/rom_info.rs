@@ -0,0 +1,223 @@
+//! ROM image validation and metadata
+//!
+//! A TI-84 Plus CE dump is the full 4MB flash: boot code in the first
+//! 128KB (`BOOT_CODE_SIZE`), then the OS from 020000h, then the archive.
+//! `RomInfo::parse` checks that an image looks like such a dump and pulls
+//! out what a frontend wants to show: the boot code and OS versions, and a
+//! hash of the boot code that identifies its exact build.
+//!
+//! Versions are found the way they appear on the About screen: the first
+//! `major.minor.patch.build` string (e.g. `5.8.1.0012`) in each region.
+//! A boot-code-only dump (no OS installed yet) is valid; it has no OS
+//! version.
+
+use std::fmt;
+
+use crate::cemu_import;
+use crate::fnv::fnv1a;
+use crate::memory::addr::FLASH_SIZE;
+
+/// Size of the boot code region at the start of flash
+pub const BOOT_CODE_SIZE: usize = 0x20000;
+
+/// Start of the OS in flash
+pub const OS_START: usize = 0x020000;
+
+/// End of the OS region searched for its version (start of the archive)
+pub const OS_END: usize = 0x0C0000;
+
+/// Why an image is not a usable ROM dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomError {
+    /// No data
+    Empty,
+    /// Larger than the 4MB flash
+    TooLarge(usize),
+    /// Shorter than the 4MB flash: a truncated dump
+    Truncated(usize),
+    /// A CEmu `.ce` state image (with its version word)
+    StateImage(u32),
+    /// The boot code region is blank
+    NoBootCode,
+}
+
+impl RomError {
+    /// Code for FFI, matching `emu_load_rom`: -2 empty, -3 too large,
+    /// -5 truncated, -6 state image, -7 no boot code
+    pub fn code(&self) -> i32 {
+        match self {
+            RomError::Empty => -2,
+            RomError::TooLarge(_) => -3,
+            RomError::Truncated(_) => -5,
+            RomError::StateImage(_) => -6,
+            RomError::NoBootCode => -7,
+        }
+    }
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Empty => write!(f, "ROM image is empty"),
+            RomError::TooLarge(len) => write!(f, "ROM image is {} bytes, larger than the {} byte flash", len, FLASH_SIZE),
+            RomError::Truncated(len) => write!(f, "ROM image is {} bytes, expected {}; the dump is truncated", len, FLASH_SIZE),
+            RomError::StateImage(version) => {
+                write!(f, "CEmu state image (version {:08X}), not a ROM; export the ROM from CEmu instead", version)
+            }
+            RomError::NoBootCode => write!(f, "ROM image has no boot code"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+/// What a ROM image contains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    /// Image size in bytes
+    pub size: usize,
+    /// Hash of the boot code region (`boot_code_hash`)
+    pub boot_code_hash: u64,
+    /// Boot code version string, if found
+    pub boot_version: Option<String>,
+    /// True if an OS is installed (its region is not blank)
+    pub os_present: bool,
+    /// OS version string, if found
+    pub os_version: Option<String>,
+}
+
+impl RomInfo {
+    /// Describe `rom` without checking it (any size)
+    pub fn describe(rom: &[u8]) -> Self {
+        let region = |start: usize, end: usize| rom.get(start..end.min(rom.len())).unwrap_or(&[]);
+        let os = region(OS_START, OS_END);
+        Self {
+            size: rom.len(),
+            boot_code_hash: boot_code_hash(rom),
+            boot_version: find_version(region(0, BOOT_CODE_SIZE)),
+            os_present: os.iter().any(|&b| b != 0xFF),
+            os_version: find_version(os),
+        }
+    }
+
+    /// Check that `rom` is a full dump and describe it
+    pub fn parse(rom: &[u8]) -> Result<Self, RomError> {
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if let Some(version) = cemu_import::state_image_version(rom) {
+            return Err(RomError::StateImage(version));
+        }
+        if rom.len() > FLASH_SIZE {
+            return Err(RomError::TooLarge(rom.len()));
+        }
+        if rom.len() < FLASH_SIZE {
+            return Err(RomError::Truncated(rom.len()));
+        }
+        if rom[..BOOT_CODE_SIZE].iter().all(|&b| b == 0xFF) {
+            return Err(RomError::NoBootCode);
+        }
+        Ok(Self::describe(rom))
+    }
+
+    /// JSON object with the same fields (hash as 16 hex digits, missing
+    /// versions as null)
+    pub fn to_json(&self) -> String {
+        let version = |v: &Option<String>| v.as_ref().map_or("null".to_string(), |v| format!("\"{}\"", v));
+        format!(
+            "{{\"size\":{},\"boot_code_hash\":\"{:016X}\",\"boot_version\":{},\"os_present\":{},\"os_version\":{}}}",
+            self.size,
+            self.boot_code_hash,
+            version(&self.boot_version),
+            self.os_present,
+            version(&self.os_version)
+        )
+    }
+}
+
+/// FNV-1a hash of the boot code region of a ROM image
+pub fn boot_code_hash(rom: &[u8]) -> u64 {
+    fnv1a(&rom[..rom.len().min(BOOT_CODE_SIZE)])
+}
+
+/// First `major.minor.patch.build` version string in `data`: three groups
+/// of 1-2 digits and a 4-digit build number, not part of a longer run of
+/// digits and dots (trailing periods aside)
+fn find_version(data: &[u8]) -> Option<String> {
+    let is_part = |b: u8| b.is_ascii_digit() || b == b'.';
+    let mut i = 0;
+    while i < data.len() {
+        if !data[i].is_ascii_digit() || (i > 0 && is_part(data[i - 1])) {
+            i += 1;
+            continue;
+        }
+        let end = data[i..].iter().position(|&b| !is_part(b)).map_or(data.len(), |n| i + n);
+        let mut candidate = &data[i..end];
+        while let [rest @ .., b'.'] = candidate {
+            candidate = rest;
+        }
+        let groups: Vec<&[u8]> = candidate.split(|&b| b == b'.').collect();
+        if groups.len() == 4
+            && groups[..3].iter().all(|g| (1..=2).contains(&g.len()))
+            && groups[3].len() == 4
+        {
+            return Some(String::from_utf8_lossy(candidate).into_owned());
+        }
+        i = end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with(boot: &[u8], os: &[u8]) -> Vec<u8> {
+        let mut rom = vec![0xFF; FLASH_SIZE];
+        rom[0x100..0x100 + boot.len()].copy_from_slice(boot);
+        rom[OS_START + 0x100..OS_START + 0x100 + os.len()].copy_from_slice(os);
+        rom
+    }
+
+    #[test]
+    fn test_find_version() {
+        assert_eq!(find_version(b"\x00Version 5.8.1.0012\x00"), Some("5.8.1.0012".to_string()));
+        // A trailing period ends the sentence, not the version
+        assert_eq!(find_version(b"1.2.3 then 10.0.0.0089."), Some("10.0.0.0089".to_string()));
+        assert_eq!(find_version(b"x 123.4.5.0001 5.3.0.0037"), Some("5.3.0.0037".to_string()));
+        assert_eq!(find_version(b"5.8.1.12"), None);
+        assert_eq!(find_version(b""), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let rom = rom_with(b"\x01boot 5.3.6.0020\x00", b"OS 5.8.1.0012\x00");
+        let info = RomInfo::parse(&rom).unwrap();
+        assert_eq!(info.size, FLASH_SIZE);
+        assert_eq!(info.boot_version.as_deref(), Some("5.3.6.0020"));
+        assert!(info.os_present);
+        assert_eq!(info.os_version.as_deref(), Some("5.8.1.0012"));
+        assert_eq!(info.boot_code_hash, boot_code_hash(&rom));
+        assert_eq!(
+            info.to_json(),
+            format!(
+                "{{\"size\":4194304,\"boot_code_hash\":\"{:016X}\",\"boot_version\":\"5.3.6.0020\",\"os_present\":true,\"os_version\":\"5.8.1.0012\"}}",
+                info.boot_code_hash
+            )
+        );
+
+        // Boot code only: valid, no OS
+        let info = RomInfo::parse(&rom_with(b"\x01", b"")).unwrap();
+        assert!(!info.os_present);
+        assert_eq!(info.os_version, None);
+        assert!(info.to_json().contains("\"boot_version\":null"));
+
+        assert_eq!(RomInfo::parse(&[]), Err(RomError::Empty));
+        assert_eq!(RomInfo::parse(&rom[..0x200000]), Err(RomError::Truncated(0x200000)));
+        assert_eq!(RomInfo::parse(&vec![0; FLASH_SIZE + 1]), Err(RomError::TooLarge(FLASH_SIZE + 1)));
+        assert_eq!(RomInfo::parse(&vec![0xFF; FLASH_SIZE]), Err(RomError::NoBootCode));
+        let mut image = vec![0x1B, 0x00, 0xCE, 0xCE];
+        image.resize(FLASH_SIZE, 0);
+        assert_eq!(RomInfo::parse(&image), Err(RomError::StateImage(0xCECE001B)));
+    }
+}
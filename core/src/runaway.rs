@@ -0,0 +1,247 @@
+//! Runaway-execution watchdog
+//!
+//! Detects states where the emulated calculator will never recover on its
+//! own, so the frontend can offer a reset instead of looking frozen:
+//!
+//! - a stuck loop: PC confined to a few bytes with interrupts disabled for
+//!   a long time (nothing but a reset can break it)
+//! - a reboot loop: execution restarting at the reset vector again and again
+//!   (typically a crash during OS startup)
+//!
+//! HALT is not a stuck loop: a halted CPU is waiting for an interrupt.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Watchdog thresholds, in CPU cycles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunawayConfig {
+    /// Cycles spent in a tight loop with interrupts disabled before reporting
+    pub stuck_cycles: u64,
+    /// Largest PC range (in bytes) still counted as a tight loop
+    pub loop_span: u32,
+    /// Jumps to the reset vector within `reboot_window` that count as a loop
+    pub reboot_limit: u32,
+    /// Window for `reboot_limit`, in cycles
+    pub reboot_window: u64,
+}
+
+impl Default for RunawayConfig {
+    fn default() -> Self {
+        Self {
+            // ~5 seconds at 48MHz
+            stuck_cycles: 240_000_000,
+            loop_span: 16,
+            reboot_limit: 3,
+            // ~10 seconds at 48MHz
+            reboot_window: 480_000_000,
+        }
+    }
+}
+
+/// What the watchdog detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunawayKind {
+    /// PC stayed within `start..=end` with interrupts disabled
+    StuckLoop { start: u32, end: u32 },
+    /// Execution restarted at the reset vector this many times in the window
+    RebootLoop { reboots: u32 },
+}
+
+impl RunawayKind {
+    /// FFI id (0 = stuck loop, 1 = reboot loop)
+    pub fn id(&self) -> i32 {
+        match self {
+            RunawayKind::StuckLoop { .. } => 0,
+            RunawayKind::RebootLoop { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for RunawayKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunawayKind::StuckLoop { start, end } => {
+                write!(f, "stuck loop at {:06X}-{:06X} with interrupts disabled", start, end)
+            }
+            RunawayKind::RebootLoop { reboots } => write!(f, "reboot loop ({} restarts)", reboots),
+        }
+    }
+}
+
+/// A detected runaway state with context for the user or a bug report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunawayReport {
+    /// What was detected
+    pub kind: RunawayKind,
+    /// `total_cycles` at detection
+    pub cycle: u64,
+    /// PC at detection
+    pub pc: u32,
+    /// Human-readable context: registers and recent execution history
+    pub context: String,
+}
+
+/// Host callback for runaway reports
+pub type RunawayCallback = Box<dyn FnMut(&RunawayReport) + Send>;
+
+/// Detector state
+#[derive(Debug, Clone)]
+pub struct RunawayDetector {
+    config: RunawayConfig,
+    loop_start: u32,
+    loop_end: u32,
+    loop_since: Option<u64>,
+    stuck_reported: bool,
+    last_pc: u32,
+    /// Cycles observed; unlike `total_cycles` this survives resets
+    now: u64,
+    reboots: VecDeque<u64>,
+}
+
+impl RunawayDetector {
+    /// Create a detector with the given thresholds
+    pub fn new(config: RunawayConfig) -> Self {
+        Self {
+            config,
+            loop_start: 0,
+            loop_end: 0,
+            loop_since: None,
+            stuck_reported: false,
+            last_pc: u32::MAX,
+            now: 0,
+            reboots: VecDeque::new(),
+        }
+    }
+
+    /// Thresholds in use
+    pub fn config(&self) -> RunawayConfig {
+        self.config
+    }
+
+    /// Forget everything observed so far
+    pub fn clear(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// The emulator was reset. Ends any loop in progress; the restart at the
+    /// reset vector is not counted as a reboot, but earlier ones are kept.
+    pub fn note_reset(&mut self) {
+        self.loop_since = None;
+        self.stuck_reported = false;
+        self.last_pc = 0;
+    }
+
+    /// Observe one executed instruction: `pc` is the PC after it, `cycles`
+    /// the cycles it took. Returns a detection, at most once per stuck loop
+    /// and once per burst of reboots.
+    pub fn observe(&mut self, pc: u32, iff1: bool, halted: bool, cycles: u32) -> Option<RunawayKind> {
+        self.now += cycles as u64;
+        let cycle = self.now;
+        let entered_reset_vector = pc == 0 && self.last_pc != 0;
+        self.last_pc = pc;
+
+        if entered_reset_vector {
+            self.reboots.push_back(cycle);
+            while self.reboots.front().is_some_and(|&c| cycle - c > self.config.reboot_window) {
+                self.reboots.pop_front();
+            }
+            if self.reboots.len() as u32 >= self.config.reboot_limit {
+                let reboots = self.reboots.len() as u32;
+                self.reboots.clear();
+                return Some(RunawayKind::RebootLoop { reboots });
+            }
+        }
+
+        if iff1 || halted {
+            self.loop_since = None;
+            self.stuck_reported = false;
+            return None;
+        }
+        let Some(since) = self.loop_since else {
+            self.start_loop(pc, cycle);
+            return None;
+        };
+        let start = self.loop_start.min(pc);
+        let end = self.loop_end.max(pc);
+        if end - start > self.config.loop_span {
+            self.start_loop(pc, cycle);
+            return None;
+        }
+        self.loop_start = start;
+        self.loop_end = end;
+        if !self.stuck_reported && cycle - since >= self.config.stuck_cycles {
+            self.stuck_reported = true;
+            return Some(RunawayKind::StuckLoop { start, end });
+        }
+        None
+    }
+
+    fn start_loop(&mut self, pc: u32, cycle: u64) {
+        self.loop_start = pc;
+        self.loop_end = pc;
+        self.loop_since = Some(cycle);
+        self.stuck_reported = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RunawayConfig {
+        RunawayConfig { stuck_cycles: 1000, loop_span: 4, reboot_limit: 3, reboot_window: 10_000 }
+    }
+
+    #[test]
+    fn test_stuck_loop_reported_once() {
+        let mut det = RunawayDetector::new(config());
+        let mut found = Vec::new();
+        for i in 0..2000u32 {
+            found.extend(det.observe(0x1000 + (i % 2) * 2, false, false, 1));
+        }
+        assert_eq!(found, [RunawayKind::StuckLoop { start: 0x1000, end: 0x1002 }]);
+
+        // Enabling interrupts breaks the loop; a new one is reported again
+        det.observe(0x1000, true, false, 1);
+        let found: Vec<_> = (0..2000).filter_map(|_| det.observe(0x1000, false, false, 1)).collect();
+        assert_eq!(found.len(), 1);
+
+        // So does a reset
+        det.note_reset();
+        assert_eq!(det.observe(0, false, false, 999), None);
+        assert_eq!(det.observe(0, false, false, 999), None);
+    }
+
+    #[test]
+    fn test_wide_loop_or_halt_is_not_stuck() {
+        let mut det = RunawayDetector::new(config());
+        for i in 0..5000u32 {
+            assert_eq!(det.observe(0x1000 + (i % 3) * 8, false, false, 1), None);
+        }
+        let mut det = RunawayDetector::new(config());
+        for _ in 0..5000 {
+            assert_eq!(det.observe(0x1000, false, true, 1), None);
+        }
+    }
+
+    #[test]
+    fn test_reboot_loop() {
+        let mut det = RunawayDetector::new(config());
+        let mut found = Vec::new();
+        for _ in 0..3 {
+            found.extend(det.observe(0x2000, true, false, 1000));
+            found.extend(det.observe(0, true, false, 1));
+            // Staying at the reset vector is not another reboot
+            found.extend(det.observe(0, true, false, 1));
+        }
+        assert_eq!(found, [RunawayKind::RebootLoop { reboots: 3 }]);
+
+        // Reboots spread further apart than the window don't count
+        let mut det = RunawayDetector::new(config());
+        for _ in 0..5 {
+            det.observe(0x2000, true, false, 20_000);
+            assert_eq!(det.observe(0, true, false, 1), None);
+        }
+    }
+}
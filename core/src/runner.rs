@@ -0,0 +1,296 @@
+//! Emulator on its own thread
+//!
+//! `EmuRunner` moves an `Emu` onto a dedicated thread and drives it from
+//! commands, so a GUI never blocks its UI thread on emulation or shares the
+//! instance across threads. While running, the thread paces emulation in
+//! real time with `Emu::run_paced` (speed set with `Emu::set_speed`) and
+//! reports what happens as `RunnerEvent`s: rendered frames, log messages,
+//! debugger stops and saved states. A debugger stop pauses it.
+//!
+//! Log messages are forwarded through a per-thread sender, so the runner
+//! replaces the instance's log and frame callbacks while it owns the `Emu`;
+//! `EmuRunner::stop` hands it back with the callbacks it had before.
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "debugger")]
+use crate::debug::DebugEvent;
+use crate::emu::Emu;
+use crate::key::Key;
+
+/// Request to the emulator thread
+pub enum RunnerCommand {
+    /// Run in real time until paused or stopped by the debugger
+    Run,
+    /// Stop running; commands are still handled
+    Pause,
+    /// Execute one instruction (reported as `RunnerEvent::Debug`)
+    #[cfg(feature = "debugger")]
+    Step,
+    /// Press (true) or release a key
+    Key(Key, bool),
+    /// Save state (reported as `RunnerEvent::State`)
+    SaveState,
+    /// Run a closure on the emulator thread (breakpoints, loading, ...)
+    Call(Box<dyn FnOnce(&mut Emu) + Send>),
+    /// End the thread and hand the Emu back
+    Stop,
+}
+
+/// Something that happened on the emulator thread
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunnerEvent {
+    /// A rendered frame, ARGB8888 in rows of `SCREEN_WIDTH` pixels
+    Frame(Vec<u32>),
+    /// A log message
+    Log(String),
+    /// A breakpoint, watchpoint or finished step; the runner is paused
+    #[cfg(feature = "debugger")]
+    Debug(DebugEvent),
+    /// Result of `RunnerCommand::SaveState` (error code as `Emu::save_state_vec`)
+    State(Result<Vec<u8>, i32>),
+}
+
+thread_local! {
+    /// Where `forward_log` sends this runner thread's log messages
+    static LOG_EVENTS: RefCell<Option<Sender<RunnerEvent>>> = const { RefCell::new(None) };
+}
+
+extern "C" fn forward_log(message: *const c_char) {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned();
+    LOG_EVENTS.with(|events| {
+        if let Some(events) = &*events.borrow() {
+            let _ = events.send(RunnerEvent::Log(message));
+        }
+    });
+}
+
+/// Handle to an Emu running on its own thread. Dropping it stops the thread.
+pub struct EmuRunner {
+    commands: Sender<RunnerCommand>,
+    events: Receiver<RunnerEvent>,
+    thread: Option<JoinHandle<Emu>>,
+}
+
+impl EmuRunner {
+    /// Move `emu` onto a new thread, paused
+    pub fn spawn(emu: Emu) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        let thread = thread::spawn(move || run_thread(emu, command_rx, event_tx));
+        Self { commands, events, thread: Some(thread) }
+    }
+
+    /// Send a command. Returns false if the thread has ended.
+    pub fn send(&self, command: RunnerCommand) -> bool {
+        self.commands.send(command).is_ok()
+    }
+
+    /// Start running in real time
+    pub fn run(&self) -> bool {
+        self.send(RunnerCommand::Run)
+    }
+
+    /// Pause
+    pub fn pause(&self) -> bool {
+        self.send(RunnerCommand::Pause)
+    }
+
+    /// Execute one instruction
+    #[cfg(feature = "debugger")]
+    pub fn step(&self) -> bool {
+        self.send(RunnerCommand::Step)
+    }
+
+    /// Press or release a key
+    pub fn set_key(&self, key: Key, down: bool) -> bool {
+        self.send(RunnerCommand::Key(key, down))
+    }
+
+    /// Request a save state
+    pub fn save_state(&self) -> bool {
+        self.send(RunnerCommand::SaveState)
+    }
+
+    /// Events from the emulator thread, oldest first
+    pub fn events(&self) -> &Receiver<RunnerEvent> {
+        &self.events
+    }
+
+    /// End the thread and take the Emu back, with its own log and frame
+    /// callbacks restored
+    pub fn stop(mut self) -> Emu {
+        self.join().expect("emulator thread panicked")
+    }
+
+    fn join(&mut self) -> Option<Emu> {
+        let thread = self.thread.take()?;
+        let _ = self.commands.send(RunnerCommand::Stop);
+        thread.join().ok()
+    }
+}
+
+impl Drop for EmuRunner {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+fn run_thread(mut emu: Emu, commands: Receiver<RunnerCommand>, events: Sender<RunnerEvent>) -> Emu {
+    let log_callback = emu.log_callback();
+    let frame_callback = emu.take_frame_callback();
+    LOG_EVENTS.with(|log| *log.borrow_mut() = Some(events.clone()));
+    emu.set_log_callback(Some(forward_log));
+    let frames = events.clone();
+    emu.set_frame_callback(Some(Box::new(move |pixels, _| {
+        let _ = frames.send(RunnerEvent::Frame(pixels.to_vec()));
+    })));
+
+    let mut running = false;
+    let mut last = Instant::now();
+    loop {
+        let command = if running {
+            let wait = Duration::from_secs_f64(emu.pace_sleep_secs());
+            match commands.recv_timeout(wait) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+
+        match command {
+            Some(RunnerCommand::Run) => {
+                running = true;
+                last = Instant::now();
+            }
+            Some(RunnerCommand::Pause) => running = false,
+            #[cfg(feature = "debugger")]
+            Some(RunnerCommand::Step) => {
+                running = false;
+                if let Some(event) = emu.step_into() {
+                    let _ = events.send(RunnerEvent::Debug(event));
+                }
+            }
+            Some(RunnerCommand::Key(key, down)) => {
+                if down {
+                    emu.press_key(key);
+                } else {
+                    emu.release_key(key);
+                }
+            }
+            Some(RunnerCommand::SaveState) => {
+                let _ = events.send(RunnerEvent::State(emu.save_state_vec()));
+            }
+            Some(RunnerCommand::Call(f)) => f(&mut emu),
+            Some(RunnerCommand::Stop) => break,
+            None => {}
+        }
+
+        if running {
+            let now = Instant::now();
+            emu.run_paced(now.duration_since(last).as_secs_f64());
+            last = now;
+            #[cfg(feature = "debugger")]
+            if let Some(event) = emu.take_debug_event() {
+                running = false;
+                let _ = events.send(RunnerEvent::Debug(event));
+            }
+        }
+    }
+
+    emu.set_frame_callback(frame_callback);
+    emu.set_log_callback(log_callback);
+    LOG_EVENTS.with(|log| *log.borrow_mut() = None);
+    emu
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "debugger")]
+    use crate::debug::DebugEventKind;
+
+    #[cfg(feature = "debugger")]
+    fn next_event(runner: &EmuRunner, matches: impl Fn(&RunnerEvent) -> bool) -> RunnerEvent {
+        loop {
+            let event = runner.events().recv_timeout(Duration::from_secs(10)).expect("no event");
+            if matches(&event) {
+                return event;
+            }
+        }
+    }
+
+    #[test]
+    fn test_stop_restores_callbacks() {
+        use crate::logging::LogLevel;
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static LOGS: AtomicU32 = AtomicU32::new(0);
+        extern "C" fn count_log(_: *const c_char) {
+            LOGS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let mut emu = Emu::new();
+        emu.set_log_callback(Some(count_log));
+        emu.set_frame_callback(Some(Box::new(|_, _| {})));
+        let mut emu = EmuRunner::spawn(emu).stop();
+        assert!(emu.has_frame_callback());
+
+        emu.set_all_log_levels(LogLevel::Info);
+        emu.disable_inst_trace();
+        assert!(LOGS.load(Ordering::SeqCst) > 0);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn test_runner_commands_and_events() {
+        let mut emu = Emu::new();
+        emu.load_rom(&[0x18, 0xFE]).unwrap(); // JR $
+        emu.powered_on = true;
+        emu.write_memory(0xE30000, &[0x4C], true); // 320 pixels per line
+        emu.write_memory(0xE30004, &[0xEF], true); // 240 lines
+        emu.write_memory(0xE3000A, &[0x3F, 0x01], true); // 320 clocks per line
+        emu.write_memory(0xE30018, &[0x2D], true); // enable, 16bpp
+
+        let runner = EmuRunner::spawn(emu);
+        runner.step();
+        let RunnerEvent::Debug(event) = next_event(&runner, |e| matches!(e, RunnerEvent::Debug(_))) else {
+            unreachable!()
+        };
+        assert_eq!(event.kind, DebugEventKind::Step);
+
+        runner.save_state();
+        let RunnerEvent::State(state) = next_event(&runner, |e| matches!(e, RunnerEvent::State(_))) else {
+            unreachable!()
+        };
+        assert!(!state.unwrap().is_empty());
+
+        runner.set_key(Key::Enter, true);
+        runner.run();
+        let RunnerEvent::Frame(pixels) = next_event(&runner, |e| matches!(e, RunnerEvent::Frame(_))) else {
+            unreachable!()
+        };
+        assert_eq!(pixels.len(), crate::emu::SCREEN_WIDTH * crate::emu::SCREEN_HEIGHT);
+
+        // A breakpoint pauses the runner
+        runner.send(RunnerCommand::Call(Box::new(|emu| {
+            emu.add_breakpoint(0);
+        })));
+        let RunnerEvent::Debug(event) = next_event(&runner, |e| matches!(e, RunnerEvent::Debug(_))) else {
+            unreachable!()
+        };
+        assert_eq!(event.kind, DebugEventKind::Breakpoint);
+
+        let emu = runner.stop();
+        assert_eq!(emu.breakpoints().len(), 1);
+    }
+}
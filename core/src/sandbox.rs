@@ -0,0 +1,198 @@
+//! ASM program sandbox
+//!
+//! An analysis mode that reports writes by user programs to RAM the OS owns:
+//! the system flags, the VAT, and the stack frames of whoever launched the
+//! program. Corrupting these usually shows up only after the program exits,
+//! as a "RAM Cleared" or a crash, far from the write that caused it.
+//!
+//! A write counts as coming from a user program when the instruction doing it
+//! runs from RAM. OS routines run from flash, so their (legitimate) updates to
+//! the same regions are never reported, even when a program calls them.
+
+use std::collections::VecDeque;
+
+/// Number of unread violations kept; later ones are counted but dropped
+pub const SANDBOX_QUEUE_SIZE: usize = 256;
+
+/// System flags addressed through IY (`flags` in ti84pce.inc), IY+0..IY+127
+pub const SYSTEM_FLAGS_START: u32 = 0xD00080;
+/// Last byte of the IY-addressed system flags
+pub const SYSTEM_FLAGS_END: u32 = 0xD000FF;
+/// OS pointer to the bottom of the VAT (`pTemp`)
+pub const PTEMP_ADDR: u32 = 0xD0259A;
+/// Top of the VAT (`symTable`); the VAT grows down from here
+pub const SYM_TABLE_END: u32 = 0xD3FFFF;
+/// Top of the OS stack (`stackTop`)
+pub const STACK_TOP: u32 = 0xD1A87E;
+
+/// What a guarded region protects
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardKind {
+    /// IY-relative system flags
+    SystemFlags = 0,
+    /// Variable allocation table
+    Vat = 1,
+    /// Stack frames above the program's entry SP
+    CallerStack = 2,
+    /// Host-defined region
+    Custom = 3,
+}
+
+/// An inclusive address range user programs must not write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuardRegion {
+    /// First guarded address
+    pub start: u32,
+    /// Last guarded address
+    pub end: u32,
+    /// What the region protects
+    pub kind: GuardKind,
+}
+
+impl GuardRegion {
+    /// Region covering `start..=end`
+    pub fn new(start: u32, end: u32, kind: GuardKind) -> Self {
+        Self { start, end, kind }
+    }
+
+    /// True if `addr` is inside the region
+    pub fn contains(&self, addr: u32) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+}
+
+/// One guarded write by a user program
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxViolation {
+    /// Bus cycle count at the write
+    pub cycle: u64,
+    /// PC of the writing instruction
+    pub pc: u32,
+    /// Address written
+    pub addr: u32,
+    /// Value before the write
+    pub old: u8,
+    /// Value written
+    pub value: u8,
+    /// Region the address belongs to
+    pub kind: GuardKind,
+}
+
+/// Sandbox state, checked by the bus on every RAM write
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    enabled: bool,
+    regions: Vec<GuardRegion>,
+    violations: VecDeque<SandboxViolation>,
+    dropped: u64,
+}
+
+impl Sandbox {
+    /// Create a disabled sandbox
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether writes are being checked
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start checking writes against `regions`, clearing earlier reports
+    pub fn enable(&mut self, regions: Vec<GuardRegion>) {
+        self.enabled = true;
+        self.regions = regions;
+        self.violations.clear();
+        self.dropped = 0;
+    }
+
+    /// Stop checking writes (unread violations are kept)
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Guarded regions
+    pub fn regions(&self) -> &[GuardRegion] {
+        &self.regions
+    }
+
+    /// Replace the region of a kind (used to track the moving VAT bottom)
+    pub fn set_region(&mut self, region: GuardRegion) {
+        match self.regions.iter_mut().find(|r| r.kind == region.kind) {
+            Some(existing) => *existing = region,
+            None => self.regions.push(region),
+        }
+    }
+
+    /// Check a write; records a violation if user code wrote a guarded byte
+    #[inline]
+    pub fn check(&mut self, pc: u32, addr: u32, old: u8, value: u8, cycle: u64) {
+        if !is_user_code(pc) {
+            return;
+        }
+        let Some(region) = self.regions.iter().find(|r| r.contains(addr)) else {
+            return;
+        };
+        if self.violations.len() == SANDBOX_QUEUE_SIZE {
+            self.dropped += 1;
+            return;
+        }
+        self.violations.push_back(SandboxViolation { pc, addr, old, value, kind: region.kind, cycle });
+    }
+
+    /// Take up to `max` unread violations, oldest first
+    pub fn take_violations(&mut self, max: usize) -> Vec<SandboxViolation> {
+        let n = max.min(self.violations.len());
+        self.violations.drain(..n).collect()
+    }
+
+    /// Violations dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// True if code at `pc` is a user program (runs from RAM rather than flash)
+pub fn is_user_code(pc: u32) -> bool {
+    (crate::memory::addr::RAM_START..crate::memory::addr::VRAM_START).contains(&pc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_user_code_writes_are_reported() {
+        let mut sandbox = Sandbox::new();
+        sandbox.enable(vec![GuardRegion::new(SYSTEM_FLAGS_START, SYSTEM_FLAGS_END, GuardKind::SystemFlags)]);
+
+        // OS code (flash) may write the flags
+        sandbox.check(0x021234, 0xD00090, 0, 1, 10);
+        // User code writing its own memory is fine
+        sandbox.check(0xD1A881, 0xD1B000, 0, 1, 11);
+        sandbox.check(0xD1A885, 0xD00090, 0x20, 0x00, 12);
+
+        let found = sandbox.take_violations(16);
+        assert_eq!(found, [SandboxViolation {
+            pc: 0xD1A885,
+            addr: 0xD00090,
+            old: 0x20,
+            value: 0x00,
+            kind: GuardKind::SystemFlags,
+            cycle: 12,
+        }]);
+
+        for _ in 0..SANDBOX_QUEUE_SIZE + 3 {
+            sandbox.check(0xD1A885, 0xD00090, 0, 0, 0);
+        }
+        assert_eq!(sandbox.dropped(), 3);
+        assert_eq!(sandbox.take_violations(usize::MAX).len(), SANDBOX_QUEUE_SIZE);
+
+        sandbox.set_region(GuardRegion::new(0xD3F000, SYM_TABLE_END, GuardKind::Vat));
+        sandbox.set_region(GuardRegion::new(0xD3E000, SYM_TABLE_END, GuardKind::Vat));
+        assert_eq!(sandbox.regions().len(), 2);
+        assert_eq!(sandbox.regions()[1].start, 0xD3E000);
+    }
+}
@@ -88,8 +88,10 @@ pub enum EventId {
     Lcd = 7,
     /// LCD DMA (VRAM read)
     LcdDma = 8,
+    /// Keypad row scan
+    Keypad = 9,
     /// Number of event types
-    Count = 9,
+    Count = 10,
 }
 
 /// Bit 63 set indicates event is inactive
@@ -160,6 +162,7 @@ impl Scheduler {
                 SchedItem::new(EventId::OsTimer, ClockId::Clock32K),
                 SchedItem::new(EventId::Lcd, ClockId::Clock24M),
                 SchedItem::new(EventId::LcdDma, ClockId::Clock48M),
+                SchedItem::new(EventId::Keypad, ClockId::Clock6M),
             ],
             base_ticks: 0,
             cpu_speed: 0, // Default 6 MHz
@@ -372,12 +375,25 @@ impl Scheduler {
                 EventId::OsTimer => "OsTimer",
                 EventId::Lcd => "Lcd",
                 EventId::LcdDma => "LcdDma",
+                EventId::Keypad => "Keypad",
                 EventId::Count => "?",
             })
             .collect();
         names.join(",")
     }
 
+    /// Raw timestamp of an event (base ticks, bit 63 set if inactive), for
+    /// events saved outside the scheduler snapshot
+    pub fn raw_timestamp(&self, event: EventId) -> u64 {
+        self.items[event as usize].timestamp
+    }
+
+    /// Restore a timestamp from `raw_timestamp`
+    pub fn set_raw_timestamp(&mut self, event: EventId, timestamp: u64) {
+        self.items[event as usize].timestamp = timestamp;
+        self.recalc_next_event();
+    }
+
     /// Get ticks remaining until event fires (in the event's clock domain)
     /// Returns 0 if event is not active or has already passed
     pub fn ticks_remaining(&self, event: EventId) -> u64 {
@@ -525,6 +541,9 @@ impl Scheduler {
     /// Size of scheduler state snapshot in bytes
     /// 8 (base_ticks) + 1 (cpu_speed) + 9*8 (item timestamps) + 8 (dma_last_mem_timestamp) = 89, round to 96
     pub const SNAPSHOT_SIZE: usize = 96;
+    /// Events in the snapshot; later events are saved with their peripheral
+    /// (see `raw_timestamp`) and inactive if it has none
+    const SNAPSHOT_EVENTS: usize = 9;
 
     /// Save scheduler state to bytes
     pub fn to_bytes(&self) -> [u8; Self::SNAPSHOT_SIZE] {
@@ -536,7 +555,7 @@ impl Scheduler {
         buf[pos] = self.cpu_speed; pos += 1;
 
         // Event timestamps (9 events × 8 bytes each)
-        for item in &self.items {
+        for item in &self.items[..Self::SNAPSHOT_EVENTS] {
             buf[pos..pos+8].copy_from_slice(&item.timestamp.to_le_bytes());
             pos += 8;
         }
@@ -560,10 +579,13 @@ impl Scheduler {
         self.cached_cpu_base_ticks = ClockId::Cpu.base_ticks_per_tick(self.cpu_speed);
 
         // Event timestamps
-        for item in &mut self.items {
+        for item in &mut self.items[..Self::SNAPSHOT_EVENTS] {
             item.timestamp = u64::from_le_bytes(buf[pos..pos+8].try_into().unwrap());
             pos += 8;
         }
+        for item in &mut self.items[Self::SNAPSHOT_EVENTS..] {
+            item.timestamp = INACTIVE_FLAG;
+        }
 
         // DMA state
         self.dma_last_mem_timestamp = u64::from_le_bytes(buf[pos..pos+8].try_into().unwrap());
@@ -0,0 +1,108 @@
+//! Host side of the emulated USB serial accessory
+//!
+//! `Emu::attach_serial_accessory` plugs a CDC-ACM device into the emulated
+//! USB port. The host exchanges bytes with the calculator either directly
+//! (`Emu::serial_write` / `Emu::serial_read`) or by installing a `SerialPeer`
+//! that answers whatever the calculator sends, e.g. a `ScriptedPeer` standing
+//! in for a TI-Innovator Hub in tests. Install an `Arc<Mutex<_>>` of a peer
+//! to keep a handle for inspecting it while the emulator owns it.
+
+use std::sync::{Arc, Mutex};
+
+pub use crate::peripherals::usb::SerialAccessoryConfig;
+
+/// Device on the far end of the serial link
+pub trait SerialPeer {
+    /// Called with bytes the calculator sent; returns bytes to send back
+    fn receive(&mut self, data: &[u8]) -> Vec<u8>;
+
+    /// Called when the accessory is unplugged or the calculator resets, so
+    /// the session the peer was talking to is gone
+    fn disconnected(&mut self) {}
+}
+
+impl<P: SerialPeer> SerialPeer for Arc<Mutex<P>> {
+    fn receive(&mut self, data: &[u8]) -> Vec<u8> {
+        self.lock().unwrap().receive(data)
+    }
+
+    fn disconnected(&mut self) {
+        self.lock().unwrap().disconnected();
+    }
+}
+
+/// Line-oriented peer that answers known commands with canned replies.
+///
+/// Incoming bytes are split into lines on CR or LF. Each complete line is
+/// recorded and, if it matches a rule, the rule's reply is sent back.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedPeer {
+    rules: Vec<(String, Vec<u8>)>,
+    partial: Vec<u8>,
+    lines: Vec<String>,
+}
+
+impl ScriptedPeer {
+    /// Create a peer with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reply with `reply` whenever the calculator sends the line `command`
+    pub fn on(mut self, command: &str, reply: &[u8]) -> Self {
+        self.rules.push((command.to_string(), reply.to_vec()));
+        self
+    }
+
+    /// Every complete line received so far
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+}
+
+impl SerialPeer for ScriptedPeer {
+    fn receive(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for &byte in data {
+            if byte != b'\r' && byte != b'\n' {
+                self.partial.push(byte);
+                continue;
+            }
+            if self.partial.is_empty() {
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.partial).into_owned();
+            self.partial.clear();
+            if let Some((_, reply)) = self.rules.iter().find(|(command, _)| *command == line) {
+                out.extend_from_slice(reply);
+            }
+            self.lines.push(line);
+        }
+        out
+    }
+
+    /// Drop a line cut off by the disconnect; recorded lines are kept
+    fn disconnected(&mut self) {
+        self.partial.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripted_peer_replies_per_line() {
+        let mut peer = ScriptedPeer::new().on("READ BRIGHTNESS", b"57\n").on("BEGIN", b"READY\n");
+        assert!(peer.receive(b"READ BRIGH").is_empty());
+        assert_eq!(peer.receive(b"TNESS\r\nSET LIGHT ON\n"), b"57\n");
+        assert_eq!(peer.receive(b"BEGIN\n"), b"READY\n");
+        assert_eq!(peer.lines(), ["READ BRIGHTNESS", "SET LIGHT ON", "BEGIN"]);
+
+        // A line cut off by a disconnect is not joined to the next session's
+        peer.receive(b"HAL");
+        peer.disconnected();
+        peer.receive(b"T\n");
+        assert_eq!(peer.lines().last().unwrap(), "T");
+    }
+}
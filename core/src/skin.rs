@@ -0,0 +1,199 @@
+//! Skin-composited screenshots
+//!
+//! Renders a "photo" of the calculator for documentation and teaching
+//! material: the skin image with the current screen scaled into its LCD
+//! window, and optionally the keys being held tinted so a reader can see
+//! what was pressed.
+//!
+//! The core does not decode images or read layout files. The host passes
+//! the skin already decoded to ARGB8888 together with its geometry (for the
+//! bundled skin, `assets/calculator_body.png` and the button rectangles in
+//! `assets/button_manifest.json`).
+
+/// Rectangle in skin image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkinRect {
+    /// Left edge
+    pub x: usize,
+    /// Top edge
+    pub y: usize,
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+}
+
+/// Where a keypad key is drawn on the skin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkinKey {
+    /// Keypad matrix row
+    pub row: usize,
+    /// Keypad matrix column
+    pub col: usize,
+    /// Key cap area
+    pub rect: SkinRect,
+}
+
+/// Geometry of a skin image
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkinLayout {
+    /// LCD window the screen is scaled into
+    pub screen: Option<SkinRect>,
+    /// Key positions, used to highlight pressed keys
+    pub keys: Vec<SkinKey>,
+}
+
+impl SkinLayout {
+    /// Layout from flat words, as passed over FFI: `screen` is
+    /// `[x, y, width, height]` (empty for none) and `keys` holds
+    /// `[row, col, x, y, width, height]` per key. None if the lengths are off.
+    pub fn from_words(screen: &[u32], keys: &[u32]) -> Option<Self> {
+        let rect = |w: &[u32]| SkinRect { x: w[0] as usize, y: w[1] as usize, width: w[2] as usize, height: w[3] as usize };
+        let screen = match screen.len() {
+            0 => None,
+            4 => Some(rect(screen)),
+            _ => return None,
+        };
+        if !keys.len().is_multiple_of(6) {
+            return None;
+        }
+        let keys = keys
+            .chunks(6)
+            .map(|k| SkinKey { row: k[0] as usize, col: k[1] as usize, rect: rect(&k[2..]) })
+            .collect();
+        Some(Self { screen, keys })
+    }
+}
+
+/// A decoded skin image and its layout
+#[derive(Debug, Clone)]
+pub struct Skin {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+    layout: SkinLayout,
+}
+
+impl Skin {
+    /// Skin from `width` x `height` ARGB8888 pixels, row by row.
+    /// None if `pixels` has the wrong length.
+    pub fn new(width: usize, height: usize, pixels: Vec<u32>, layout: SkinLayout) -> Option<Self> {
+        (width.checked_mul(height) == Some(pixels.len())).then_some(Self { width, height, pixels, layout })
+    }
+
+    /// Image size in pixels
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Skin geometry
+    pub fn layout(&self) -> &SkinLayout {
+        &self.layout
+    }
+
+    /// Composite `screen` (`screen_width` pixels wide) into the LCD window,
+    /// then blend `highlight` over every key `pressed(row, col)` reports as
+    /// held. The highlight's alpha byte sets its opacity.
+    pub fn compose(
+        &self,
+        screen: &[u32],
+        screen_width: usize,
+        pressed: impl Fn(usize, usize) -> bool,
+        highlight: Option<u32>,
+    ) -> Vec<u32> {
+        let mut out = self.pixels.clone();
+        if let Some(rect) = self.layout.screen {
+            let screen_height = screen.len().checked_div(screen_width).unwrap_or(0);
+            if screen_height > 0 {
+                self.blit_scaled(&mut out, rect, screen, screen_width, screen_height);
+            }
+        }
+        if let Some(color) = highlight {
+            for key in self.layout.keys.iter().filter(|key| pressed(key.row, key.col)) {
+                self.tint(&mut out, key.rect, color);
+            }
+        }
+        out
+    }
+
+    /// Pixel rows and columns of `rect` that lie inside the image
+    fn clip(&self, rect: SkinRect) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let x_end = rect.x.saturating_add(rect.width).min(self.width);
+        let y_end = rect.y.saturating_add(rect.height).min(self.height);
+        (rect.y.min(y_end)..y_end, rect.x.min(x_end)..x_end)
+    }
+
+    /// Nearest-neighbour scale of the screen into `rect`
+    fn blit_scaled(&self, out: &mut [u32], rect: SkinRect, screen: &[u32], width: usize, height: usize) {
+        let (rows, cols) = self.clip(rect);
+        for y in rows {
+            let src_y = (y - rect.y) * height / rect.height;
+            for x in cols.clone() {
+                let src_x = (x - rect.x) * width / rect.width;
+                out[y * self.width + x] = screen[src_y * width + src_x] | 0xFF000000;
+            }
+        }
+    }
+
+    fn tint(&self, out: &mut [u32], rect: SkinRect, color: u32) {
+        let alpha = color >> 24;
+        let (rows, cols) = self.clip(rect);
+        for y in rows {
+            for x in cols.clone() {
+                let pixel = &mut out[y * self.width + x];
+                *pixel = blend(*pixel, color, alpha);
+            }
+        }
+    }
+}
+
+/// Blend `over` onto `base` with opacity `alpha` (0-255), keeping `base`'s alpha
+fn blend(base: u32, over: u32, alpha: u32) -> u32 {
+    let channel = |shift: u32| {
+        let b = (base >> shift) & 0xFF;
+        let o = (over >> shift) & 0xFF;
+        ((o * alpha + b * (255 - alpha)) / 255) << shift
+    };
+    (base & 0xFF000000) | channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_screen_and_keys() {
+        let layout = SkinLayout {
+            screen: Some(SkinRect { x: 1, y: 1, width: 4, height: 2 }),
+            keys: vec![
+                SkinKey { row: 6, col: 0, rect: SkinRect { x: 0, y: 4, width: 2, height: 1 } },
+                SkinKey { row: 1, col: 5, rect: SkinRect { x: 4, y: 4, width: 4, height: 4 } },
+            ],
+        };
+        assert!(Skin::new(6, 5, vec![0; 29], layout.clone()).is_none());
+        let skin = Skin::new(6, 5, vec![0xFF000000; 30], layout).unwrap();
+
+        // 2x1 screen: left half red, right half blue
+        let screen = [0xFFFF0000, 0xFF0000FF];
+        let out = skin.compose(&screen, 2, |row, col| (row, col) == (1, 5), Some(0xFFFFFFFF));
+        assert_eq!(&out[7..11], [0xFFFF0000, 0xFFFF0000, 0xFF0000FF, 0xFF0000FF]);
+        assert_eq!(out[13..17], out[7..11]);
+        assert_eq!(out[0], 0xFF000000);
+        // Only the held key is tinted, clipped to the image
+        assert_eq!(out[24], 0xFF000000);
+        assert_eq!(&out[28..30], [0xFFFFFFFF, 0xFFFFFFFF]);
+
+        let half = skin.compose(&screen, 2, |_, _| true, Some(0x80FFFFFF));
+        assert_eq!(half[24], 0xFF808080);
+    }
+
+    #[test]
+    fn test_layout_from_words() {
+        let layout = SkinLayout::from_words(&[1, 2, 3, 4], &[6, 0, 10, 20, 30, 40]).unwrap();
+        assert_eq!(layout.screen, Some(SkinRect { x: 1, y: 2, width: 3, height: 4 }));
+        assert_eq!(layout.keys, [SkinKey { row: 6, col: 0, rect: SkinRect { x: 10, y: 20, width: 30, height: 40 } }]);
+        assert_eq!(SkinLayout::from_words(&[], &[]), Some(SkinLayout::default()));
+        assert_eq!(SkinLayout::from_words(&[1, 2], &[]), None);
+        assert_eq!(SkinLayout::from_words(&[], &[1, 2, 3]), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Debugger symbol table
+//!
+//! Maps names to addresses for programs whose symbols are known (for
+//! example an ELF loaded with `Emu::load_elf`), so addresses can be shown
+//! as `name+offset` and breakpoints set by name.
+
+/// A named address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Symbol name
+    pub name: String,
+    /// Start address
+    pub addr: u32,
+    /// Size in bytes, 0 if unknown
+    pub size: u32,
+}
+
+/// Symbols sorted by address
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a symbol, replacing any existing one with the same name
+    pub fn insert(&mut self, symbol: Symbol) {
+        self.symbols.retain(|s| s.name != symbol.name);
+        let at = self.symbols.partition_point(|s| s.addr <= symbol.addr);
+        self.symbols.insert(at, symbol);
+    }
+
+    /// Remove all symbols
+    pub fn clear(&mut self) {
+        self.symbols.clear();
+    }
+
+    /// Number of symbols
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// True if there are no symbols
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// All symbols in address order
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Address of the symbol called `name`
+    pub fn addr_of(&self, name: &str) -> Option<u32> {
+        self.symbols.iter().find(|s| s.name == name).map(|s| s.addr)
+    }
+
+    /// The symbol containing `addr` and the offset into it. Without a size,
+    /// a symbol extends to the next one.
+    pub fn lookup(&self, addr: u32) -> Option<(&Symbol, u32)> {
+        let at = self.symbols.partition_point(|s| s.addr <= addr);
+        let symbol = self.symbols[..at].last()?;
+        let offset = addr - symbol.addr;
+        (symbol.size == 0 || offset < symbol.size).then_some((symbol, offset))
+    }
+
+    /// `addr` as `name` or `name+0x12`, or six hex digits if no symbol covers it
+    pub fn describe(&self, addr: u32) -> String {
+        match self.lookup(addr) {
+            Some((symbol, 0)) => symbol.name.clone(),
+            Some((symbol, offset)) => format!("{}+0x{:X}", symbol.name, offset),
+            None => format!("{:06X}", addr),
+        }
+    }
+}
+
+impl FromIterator<Symbol> for SymbolTable {
+    fn from_iter<I: IntoIterator<Item = Symbol>>(iter: I) -> Self {
+        let mut symbols: Vec<Symbol> = iter.into_iter().collect();
+        symbols.sort_by_key(|s| s.addr);
+        Self { symbols }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(name: &str, addr: u32, size: u32) -> Symbol {
+        Symbol { name: name.to_string(), addr, size }
+    }
+
+    #[test]
+    fn test_lookup_and_describe() {
+        let mut table = SymbolTable::new();
+        table.insert(sym("main", 0xD1A900, 0x20));
+        table.insert(sym("_start", 0xD1A881, 0));
+        table.insert(sym("buffer", 0xD40000, 0x100));
+
+        assert_eq!(table.describe(0xD1A881), "_start");
+        assert_eq!(table.describe(0xD1A890), "_start+0xF");
+        assert_eq!(table.describe(0xD1A905), "main+0x5");
+        // Past the end of a sized symbol
+        assert_eq!(table.describe(0xD1A930), "D1A930");
+        assert_eq!(table.describe(0xD400FF), "buffer+0xFF");
+        assert_eq!(table.describe(0xD40100), "D40100");
+        assert_eq!(table.describe(0x000010), "000010");
+        assert_eq!(table.addr_of("main"), Some(0xD1A900));
+
+        // Re-inserting a name moves it
+        table.insert(sym("main", 0xD1AA00, 0));
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.addr_of("main"), Some(0xD1AA00));
+        assert_eq!(table.describe(0xD1AA01), "main+0x1");
+    }
+}
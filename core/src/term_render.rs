@@ -0,0 +1,137 @@
+//! Terminal screen renderer
+//!
+//! Downsamples an ARGB8888 frame to text so the headless CLI and CI logs can
+//! show what the screen looks like without writing image files.
+
+/// Output style for `render_terminal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermStyle {
+    /// 24-bit color half blocks ('▀' with fg/bg colors): two pixels per character
+    Blocks,
+    /// Monochrome braille (2x4 dots per character); dark pixels are dots
+    Braille,
+    /// Plain ASCII luminance ramp, safe for logs without ANSI/Unicode support
+    Ascii,
+}
+
+/// Dark-to-light ramp for `TermStyle::Ascii`
+const ASCII_RAMP: &[u8] = b"@%#*+=-:. ";
+
+/// Luminance at or below which a pixel counts as a braille dot
+const BRAILLE_THRESHOLD: u32 = 128;
+
+/// Average color of a box of pixels (clamped to the frame)
+fn average(pixels: &[u32], width: usize, height: usize, x0: usize, y0: usize, w: usize, h: usize) -> (u32, u32, u32) {
+    let (mut r, mut g, mut b, mut n) = (0u32, 0u32, 0u32, 0u32);
+    for y in y0..(y0 + h).min(height) {
+        for x in x0..(x0 + w).min(width) {
+            let p = pixels[y * width + x];
+            r += (p >> 16) & 0xFF;
+            g += (p >> 8) & 0xFF;
+            b += p & 0xFF;
+            n += 1;
+        }
+    }
+    if n == 0 {
+        return (0, 0, 0);
+    }
+    (r / n, g / n, b / n)
+}
+
+/// Rec. 601 luma of an RGB triple, 0-255
+fn luma((r, g, b): (u32, u32, u32)) -> u32 {
+    (r * 299 + g * 587 + b * 114) / 1000
+}
+
+/// Render a frame as text roughly `cols` characters wide.
+/// Each line ends with '\n'; `Blocks` lines also reset the terminal colors.
+pub fn render_terminal(pixels: &[u32], width: usize, height: usize, cols: usize, style: TermStyle) -> String {
+    assert_eq!(pixels.len(), width * height, "frame size mismatch");
+    let cols = cols.clamp(1, width);
+    let mut out = String::new();
+
+    match style {
+        TermStyle::Blocks => {
+            // Each character is one cell wide and two cells tall
+            let cell = width.div_ceil(cols);
+            for y in (0..height).step_by(cell * 2) {
+                for x in (0..width).step_by(cell) {
+                    let (tr, tg, tb) = average(pixels, width, height, x, y, cell, cell);
+                    let (br, bg, bb) = average(pixels, width, height, x, y + cell, cell, cell);
+                    out.push_str(&format!(
+                        "\x1b[38;2;{tr};{tg};{tb}m\x1b[48;2;{br};{bg};{bb}m\u{2580}"
+                    ));
+                }
+                out.push_str("\x1b[0m\n");
+            }
+        }
+        TermStyle::Braille => {
+            // Each character is 2x4 dots
+            let dot = width.div_ceil(cols * 2);
+            // Braille dot bit for (column, row) within a character
+            const BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+            for y in (0..height).step_by(dot * 4) {
+                for x in (0..width).step_by(dot * 2) {
+                    let mut bits = 0u8;
+                    for (dx, col) in BITS.iter().enumerate() {
+                        for (dy, bit) in col.iter().enumerate() {
+                            let px = x + dx * dot;
+                            let py = y + dy * dot;
+                            if px < width && py < height
+                                && luma(average(pixels, width, height, px, py, dot, dot)) <= BRAILLE_THRESHOLD
+                            {
+                                bits |= bit;
+                            }
+                        }
+                    }
+                    out.push(char::from_u32(0x2800 + bits as u32).unwrap());
+                }
+                out.push('\n');
+            }
+        }
+        TermStyle::Ascii => {
+            // Characters are about twice as tall as wide
+            let cell = width.div_ceil(cols);
+            for y in (0..height).step_by(cell * 2) {
+                for x in (0..width).step_by(cell) {
+                    let l = luma(average(pixels, width, height, x, y, cell, cell * 2));
+                    let idx = (l as usize * (ASCII_RAMP.len() - 1) + 127) / 255;
+                    out.push(ASCII_RAMP[idx] as char);
+                }
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 8x8 frame: black left half, white right half
+    fn split_frame() -> Vec<u32> {
+        (0..64).map(|i| if i % 8 < 4 { 0xFF000000 } else { 0xFFFFFFFF }).collect()
+    }
+
+    #[test]
+    fn test_ascii() {
+        let text = render_terminal(&split_frame(), 8, 8, 4, TermStyle::Ascii);
+        assert_eq!(text, "@@  \n@@  \n");
+    }
+
+    #[test]
+    fn test_braille() {
+        let text = render_terminal(&split_frame(), 8, 8, 2, TermStyle::Braille);
+        // Left character fully dotted, right one empty; 8 rows / (2px * 4) = 1 line
+        assert_eq!(text, "\u{28FF}\u{2800}\n");
+    }
+
+    #[test]
+    fn test_blocks() {
+        let text = render_terminal(&split_frame(), 8, 8, 2, TermStyle::Blocks);
+        assert_eq!(text.lines().count(), 1);
+        assert!(text.starts_with("\x1b[38;2;0;0;0m\x1b[48;2;0;0;0m\u{2580}"));
+        assert!(text.contains("\x1b[38;2;255;255;255m"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Real-time speed control
+//!
+//! Paces emulation against the host clock so frontends don't each write
+//! their own cycle budgeting. The host calls `Emu::run_paced` with the
+//! wall-clock time since its previous call and sleeps for
+//! `Emu::pace_sleep_secs` in between; the throttle turns that into how much
+//! emulated time to run.
+//!
+//! Time owed to or by the host is carried between calls, so a sleep that
+//! overshoots is made up on the next call and a run that overshoots (an
+//! instruction or HALT skip past the budget) is taken off the next one. The
+//! carry is capped so a host stall (debugger, suspended tab) doesn't turn
+//! into a long burst of catch-up. As with the governor, wall time comes from
+//! the host because `std::time::Instant` is unavailable on wasm.
+
+/// Most emulated time owed to the host; anything beyond is dropped
+const MAX_OWED_SECS: f64 = 0.1;
+
+/// Emulated time run per call in turbo mode (one 60 Hz frame, so the host
+/// still gets to render between calls)
+const TURBO_SLICE_SECS: f64 = 1.0 / 60.0;
+
+/// Emulated time the host should let build up before calling again
+const PACE_SECS: f64 = 1.0 / 60.0;
+
+/// How fast emulation runs relative to the host clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpeedMode {
+    /// 100%: one emulated second per host second
+    #[default]
+    RealTime,
+    /// As fast as the host can go; the host should not sleep
+    Turbo,
+    /// A percentage of real time (0 pauses)
+    Percent(u32),
+}
+
+impl SpeedMode {
+    /// Emulated seconds per host second (None for turbo)
+    pub fn factor(self) -> Option<f64> {
+        match self {
+            SpeedMode::RealTime => Some(1.0),
+            SpeedMode::Turbo => None,
+            SpeedMode::Percent(percent) => Some(percent as f64 / 100.0),
+        }
+    }
+}
+
+/// Throttle state
+#[derive(Debug, Clone, Default)]
+pub struct Throttle {
+    mode: SpeedMode,
+    /// Emulated seconds owed to the host (negative when ahead)
+    owed: f64,
+}
+
+impl Throttle {
+    /// Create a throttle in the given mode
+    pub fn new(mode: SpeedMode) -> Self {
+        Self { mode, owed: 0.0 }
+    }
+
+    /// Current mode
+    pub fn mode(&self) -> SpeedMode {
+        self.mode
+    }
+
+    /// Change mode, dropping any time carried from the old one
+    pub fn set_mode(&mut self, mode: SpeedMode) {
+        self.mode = mode;
+        self.owed = 0.0;
+    }
+
+    /// Emulated seconds to run now, `host_secs` after the previous call
+    pub fn budget(&mut self, host_secs: f64) -> f64 {
+        match self.mode.factor() {
+            None => TURBO_SLICE_SECS,
+            Some(factor) => {
+                self.owed = (self.owed + host_secs.max(0.0) * factor).min(MAX_OWED_SECS);
+                self.owed.max(0.0)
+            }
+        }
+    }
+
+    /// Record emulated seconds actually run against the last budget
+    pub fn ran(&mut self, emulated_secs: f64) {
+        if self.mode.factor().is_some() {
+            self.owed -= emulated_secs;
+        }
+    }
+
+    /// Host seconds to sleep before the next call: until a frame's worth of
+    /// emulated time is owed. 0 in turbo mode.
+    pub fn sleep_secs(&self) -> f64 {
+        match self.mode.factor() {
+            None => 0.0,
+            Some(factor) if factor <= 0.0 => PACE_SECS,
+            Some(factor) => ((PACE_SECS - self.owed) / factor).max(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_follows_speed_and_carries_jitter() {
+        let mut throttle = Throttle::default();
+        assert_eq!(throttle.budget(0.01), 0.01);
+        // Ran 2 ms too long: taken off the next budget
+        throttle.ran(0.012);
+        assert!((throttle.budget(0.01) - 0.008).abs() < 1e-12);
+        throttle.ran(0.008);
+        // Overslept by half a frame: the next budget makes it up
+        let sleep = throttle.sleep_secs();
+        assert!((sleep - PACE_SECS).abs() < 1e-12);
+        assert!((throttle.budget(sleep * 1.5) - PACE_SECS * 1.5).abs() < 1e-12);
+
+        // A long stall is capped
+        let owed = throttle.budget(0.0);
+        throttle.ran(owed);
+        assert_eq!(throttle.budget(5.0), MAX_OWED_SECS);
+
+        throttle.set_mode(SpeedMode::Percent(50));
+        assert_eq!(throttle.budget(0.02), 0.01);
+        throttle.ran(0.01);
+        assert!((throttle.sleep_secs() - PACE_SECS * 2.0).abs() < 1e-12);
+
+        throttle.set_mode(SpeedMode::Percent(0));
+        assert_eq!(throttle.budget(1.0), 0.0);
+
+        throttle.set_mode(SpeedMode::Turbo);
+        assert_eq!(throttle.budget(0.0), TURBO_SLICE_SECS);
+        assert_eq!(throttle.sleep_secs(), 0.0);
+    }
+}
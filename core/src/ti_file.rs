@@ -11,8 +11,11 @@
 /// Magic signature at the start of every TI 8x file
 const MAGIC: &[u8; 8] = b"**TI83F*";
 
-/// Minimum file size: 55 header + 17 min entry + 2 checksum
-const MIN_FILE_SIZE: usize = 74;
+/// Comment written by `TiFile::to_bytes`
+const COMMENT: &[u8] = b"Exported from TI-84 Plus CE emulator";
+
+/// Minimum file size: 55 header + 15 min entry (0x0B header) + 2 checksum
+const MIN_FILE_SIZE: usize = 72;
 
 /// Variable type codes (from CEmu core/vat.h)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -191,9 +194,12 @@ impl TiFile {
         let data_end = 55 + data_len;
         let mut entries = Vec::new();
 
-        while offset + 17 <= data_end {
-            // Header size (should be 0x0D = 13 for standard entries)
-            let _header_size = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        while offset + 15 <= data_end {
+            // Header size: 0x0D, or 0x0B for files without version and flag bytes
+            let header_size = u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+            if header_size < 11 {
+                return Err(TiFileError::TruncatedEntry);
+            }
             // Data size
             let var_data_len = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
             // Type byte
@@ -202,14 +208,14 @@ impl TiFile {
             let mut name = [0u8; 8];
             name.copy_from_slice(&data[offset + 5..offset + 13]);
             // Version
-            let version = data[offset + 13];
+            let version = if header_size >= 12 { data[offset + 13] } else { 0 };
             // Flag (bit 7 = archived)
-            let flag = data[offset + 14];
+            let flag = if header_size >= 13 { data[offset + 14] } else { 0 };
             let archived = (flag & 0x80) != 0;
-            // Skip duplicate data size at offset+15..+17
+            // Skip duplicate data size after the header
 
             // Extract variable data
-            let var_data_start = offset + 17;
+            let var_data_start = offset + 2 + header_size + 2;
             let var_data_end = var_data_start + var_data_len;
             if var_data_end > data_end {
                 return Err(TiFileError::TruncatedEntry);
@@ -228,6 +234,35 @@ impl TiFile {
 
         Ok(TiFile { entries })
     }
+
+    /// Write the file back out, with 0x0D entry headers and the checksum.
+    /// Entries must fit the 16-bit size fields (65535 bytes in total).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut section = Vec::new();
+        for entry in &self.entries {
+            let len = (entry.data.len() as u16).to_le_bytes();
+            section.extend_from_slice(&13u16.to_le_bytes());
+            section.extend_from_slice(&len);
+            section.push(entry.var_type.as_u8());
+            section.extend_from_slice(&entry.name);
+            section.push(entry.version);
+            section.push(if entry.archived { 0x80 } else { 0x00 });
+            section.extend_from_slice(&len);
+            section.extend_from_slice(&entry.data);
+        }
+
+        let mut file = Vec::with_capacity(55 + section.len() + 2);
+        file.extend_from_slice(MAGIC);
+        file.extend_from_slice(&[0x1A, 0x0A, 0x00]);
+        let mut comment = [0u8; 42];
+        comment[..COMMENT.len()].copy_from_slice(COMMENT);
+        file.extend_from_slice(&comment);
+        file.extend_from_slice(&(section.len() as u16).to_le_bytes());
+        let checksum = section.iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+        file.extend_from_slice(&section);
+        file.extend_from_slice(&checksum.to_le_bytes());
+        file
+    }
 }
 
 #[cfg(test)]
@@ -307,6 +342,39 @@ mod tests {
         assert!(entry.is_asm_program());
     }
 
+    #[test]
+    fn test_parse_short_entry_header() {
+        // 0x0B-byte entry header: no version or flag bytes
+        let mut file = make_8xp(0x15, b"OLDVAR\0\0", 0, 0, &[0x01, 0x00, 0x42]);
+        file.drain(68..70);
+        file[55] = 0x0B;
+        let entry_len = (file.len() - 57) as u16;
+        file[53..55].copy_from_slice(&entry_len.to_le_bytes());
+        let checksum: u16 = file[55..file.len() - 2].iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+        let len = file.len();
+        file[len - 2..].copy_from_slice(&checksum.to_le_bytes());
+
+        let parsed = TiFile::parse(&file).unwrap();
+        assert_eq!(parsed.entries.len(), 1);
+        let entry = &parsed.entries[0];
+        assert_eq!(entry.name_str(), "OLDVAR");
+        assert_eq!(entry.version, 0);
+        assert!(!entry.archived);
+        assert_eq!(entry.data, [0x01, 0x00, 0x42]);
+    }
+
+    #[test]
+    fn test_to_bytes_round_trip() {
+        let file = make_8xp(0x15, b"GRAPHX\0\0", 0, 0x80, &[0x02, 0x00, 0xAB, 0xCD]);
+        let parsed = TiFile::parse(&file).unwrap();
+        let written = parsed.to_bytes();
+        // Same entries; only the comment differs
+        assert_eq!(written[55..], file[55..]);
+        let reparsed = TiFile::parse(&written).unwrap();
+        assert_eq!(reparsed.entries[0].name_str(), "GRAPHX");
+        assert!(reparsed.entries[0].archived);
+    }
+
     #[test]
     fn test_reject_bad_magic() {
         let mut file = make_8xp(0x05, b"TEST\0\0\0\0", 0, 0, &[0, 0]);
@@ -0,0 +1,230 @@
+//! Diff a CEmu trace log against a structured trace
+//!
+//! Reads the text log written by `tools/cemu-test/trace_gen` and records
+//! from `trace_record`. The log has one line per instruction (`step cycles
+//! PC SP AF BC DE HL IX IY ADL IFF1 IFF2 IM HALT opcode`) holding the state
+//! *after* it, with steps numbered from one; records hold the state *before*
+//! each instruction. CEmu's step N is therefore our record N, and records
+//! are aligned by index; gaps left by a PC filter are skipped.
+//!
+//! Registers, ADL and the interrupt flip-flops are compared. Cycles are not
+//! (CEmu logs base ticks), and records carry no IM or HALT state.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::lockstep::RegSnapshot;
+use crate::trace_record::{flags, TraceRecord};
+
+/// Instructions of context kept before a divergence
+pub const DEFAULT_CONTEXT: usize = 20;
+
+/// One line of a CEmu trace log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CemuLine {
+    /// Instructions executed (1 for the line after the first instruction)
+    pub step: u64,
+    /// State after the instruction (IM and HALT as logged)
+    pub regs: RegSnapshot,
+    /// Opcode bytes at the new PC, as logged
+    pub opcode: String,
+}
+
+impl CemuLine {
+    /// Parse a log line. None for malformed lines.
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [step, _cycles, pc, sp, af, bc, de, hl, ix, iy, adl, iff1, iff2, im, halted, opcode] = fields[..] else {
+            return None;
+        };
+        let hex = |s: &str| u32::from_str_radix(s, 16).ok();
+        let bit = |s: &str| match s {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        };
+        let af = hex(af)?;
+        let regs = RegSnapshot {
+            pc: hex(pc)?,
+            sp: hex(sp)?,
+            bc: hex(bc)?,
+            de: hex(de)?,
+            hl: hex(hl)?,
+            ix: hex(ix)?,
+            iy: hex(iy)?,
+            a: (af >> 8) as u8,
+            f: af as u8,
+            adl: bit(adl)?,
+            iff1: bit(iff1)?,
+            iff2: bit(iff2)?,
+            im: im.strip_prefix("Mode")?.parse().ok()?,
+            halted: bit(halted)?,
+        };
+        Some(Self { step: step.parse().ok()?, regs, opcode: opcode.to_string() })
+    }
+
+    /// The compared fields (IM and HALT cleared, as records lack them)
+    fn compared(&self) -> RegSnapshot {
+        RegSnapshot { im: 0, halted: false, ..self.regs }
+    }
+}
+
+/// Register state in a record, in the form CEmu lines are compared in
+pub fn record_regs(record: &TraceRecord) -> RegSnapshot {
+    RegSnapshot {
+        pc: record.pc,
+        sp: record.sp,
+        bc: record.bc,
+        de: record.de,
+        hl: record.hl,
+        ix: record.ix,
+        iy: record.iy,
+        a: record.a,
+        f: record.f,
+        adl: record.flags & flags::ADL != 0,
+        iff1: record.flags & flags::IFF1 != 0,
+        iff2: record.flags & flags::IFF2 != 0,
+        im: 0,
+        halted: false,
+    }
+}
+
+/// The first instruction where the traces disagree
+#[derive(Debug, Clone)]
+pub struct TraceDivergence {
+    pub ours: TraceRecord,
+    pub reference: CemuLine,
+    /// Matching pairs leading up to the divergence, oldest first
+    pub context: Vec<(TraceRecord, CemuLine)>,
+}
+
+impl fmt::Display for TraceDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "first divergence at instruction {} (PC ours={:06X} ref={:06X})",
+            self.ours.index, self.ours.pc, self.reference.regs.pc)?;
+        for line in record_regs(&self.ours).diff(&self.reference.compared()) {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "  ours: {}", format_regs(&record_regs(&self.ours)))?;
+        writeln!(f, "  ref:  {}", format_regs(&self.reference.regs))?;
+        if !self.context.is_empty() {
+            writeln!(f, "last {} matching instructions:", self.context.len())?;
+            for (record, _) in &self.context {
+                let op: String = record.opcode().iter().map(|b| format!("{:02X}", b)).collect();
+                writeln!(f, "  {:8} {:06X} {:8} {}", record.index, record.pc, op, format_regs(&record_regs(record)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn format_regs(r: &RegSnapshot) -> String {
+    format!(
+        "AF={:02X}{:02X} BC={:06X} DE={:06X} HL={:06X} IX={:06X} IY={:06X} SP={:06X} ADL={} IFF={}{}",
+        r.a, r.f, r.bc, r.de, r.hl, r.ix, r.iy, r.sp, r.adl as u8, r.iff1 as u8, r.iff2 as u8
+    )
+}
+
+/// Result of a diff
+#[derive(Debug, Clone)]
+pub struct DiffReport {
+    /// Instructions compared before stopping
+    pub compared: u64,
+    pub divergence: Option<TraceDivergence>,
+}
+
+/// Compare `ours` against a CEmu log until the first divergence or until
+/// either runs out, keeping `context` matching instructions for the report
+pub fn diff(cemu: impl BufRead, ours: impl IntoIterator<Item = TraceRecord>, context: usize) -> io::Result<DiffReport> {
+    let mut lines = cemu.lines();
+    let mut pending: Option<CemuLine> = None;
+    let mut history = VecDeque::with_capacity(context);
+    let mut compared = 0;
+
+    'records: for record in ours {
+        // Advance the log to this record's step; malformed lines are skipped
+        let line = loop {
+            if let Some(line) = pending.take() {
+                if line.step == record.index {
+                    break line;
+                }
+                if line.step > record.index {
+                    pending = Some(line);
+                    continue 'records;
+                }
+            }
+            match lines.next() {
+                Some(text) => pending = CemuLine::parse(&text?),
+                None => break 'records,
+            }
+        };
+
+        compared += 1;
+        if record_regs(&record) != line.compared() {
+            let context = history.into_iter().collect();
+            return Ok(DiffReport { compared, divergence: Some(TraceDivergence { ours: record, reference: line, context }) });
+        }
+        if context > 0 {
+            if history.len() == context {
+                history.pop_front();
+            }
+            history.push_back((record, line));
+        }
+    }
+    Ok(DiffReport { compared, divergence: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cemu_line(step: u64, pc: u32, a: u8) -> String {
+        format!("{:06} {:08} {:06X} D1A87E {:02X}40 000001 000000 000000 000000 000000 1 0 0 Mode1 0 00", step, step * 10, pc, a)
+    }
+
+    fn record(index: u64, pc: u32, a: u8) -> TraceRecord {
+        TraceRecord {
+            index,
+            pc,
+            sp: 0xD1A87E,
+            bc: 1,
+            a,
+            f: 0x40,
+            flags: flags::ADL,
+            opcode_len: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_cemu_line() {
+        let line = CemuLine::parse(&cemu_line(3, 0x1234, 0x42)).unwrap();
+        assert_eq!(line.step, 3);
+        assert_eq!((line.regs.pc, line.regs.a, line.regs.f, line.regs.im), (0x1234, 0x42, 0x40, 1));
+        assert!(line.regs.adl && !line.regs.iff1);
+        assert!(CemuLine::parse("garbage").is_none());
+    }
+
+    #[test]
+    fn test_diff_aligns_and_finds_first_divergence() {
+        let log: String = (1..=6).map(|i| cemu_line(i, i as u32 * 2, if i == 5 { 0x99 } else { 0 }) + "\n").collect();
+        // Index 0 has no CEmu line; index 3 is filtered out of ours
+        let ours: Vec<_> = [0, 1, 2, 4, 5, 6].iter().map(|&i| record(i, i as u32 * 2, 0)).collect();
+
+        let report = diff(log.as_bytes(), ours.clone(), 2).unwrap();
+        let div = report.divergence.unwrap();
+        assert_eq!(report.compared, 4);
+        assert_eq!(div.ours.index, 5);
+        assert_eq!(div.reference.step, 5);
+        assert_eq!(div.context.iter().map(|(r, l)| (r.index, l.step)).collect::<Vec<_>>(), [(2, 2), (4, 4)]);
+        let text = div.to_string();
+        assert!(text.starts_with("first divergence at instruction 5"), "{}", text);
+        assert!(text.contains("a: ours=0 ref=99"), "{}", text);
+
+        // Matching up to where the log ends
+        let report = diff(log.as_bytes(), ours[..4].to_vec(), 2).unwrap();
+        assert!(report.divergence.is_none());
+        assert_eq!(report.compared, 3);
+    }
+}
@@ -0,0 +1,168 @@
+//! TI-OS variable allocation table (VAT)
+//!
+//! The VAT grows down from `symTable` (D3FFFFh). Between there and `progPtr`
+//! is the symbol table: fixed 9-byte entries for reals, lists, matrices,
+//! strings and the like, with 3-byte names. Below `progPtr`, down to
+//! `pTemp`, are programs, appvars and groups, whose names are up to 8 bytes
+//! with a length byte. Read downward, every entry starts with:
+//!
+//!   type, T2, version, data address (low, high, upper)
+//!
+//! The data address is in RAM for unarchived variables. For archived ones it
+//! is the flag byte of the entry in the flash archive, and the data follows
+//! that entry's header (see `Emu::send_file`).
+//!
+//! `walk` reads the table through a `peek` function so it works on the live
+//! emulator and on test memory alike.
+
+use crate::memory::addr::RAM_START;
+use crate::sandbox::SYM_TABLE_END;
+
+/// Symbol table entry size (type, T2, version, 3 address bytes, 3 name bytes)
+const SYM_ENTRY_SIZE: u32 = 9;
+
+/// Archive entry bytes before the name length: flag, size (2), type, T2,
+/// version, self address (3)
+const ARCHIVE_NAME_LEN_OFFSET: u32 = 9;
+
+/// Entries read before giving up on a corrupt table
+const MAX_ENTRIES: usize = 0x4000;
+
+/// A variable found in the VAT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VatEntry {
+    /// Type byte (flag bits masked off)
+    pub var_type: u8,
+    /// Version byte
+    pub version: u8,
+    /// Name bytes, without padding
+    pub name: Vec<u8>,
+    /// True if the data is in the flash archive
+    pub archived: bool,
+    /// Address of the variable data (archive header already skipped)
+    pub data_addr: u32,
+}
+
+/// Read the VAT from `SYM_TABLE_END` down to `p_temp`. Returns nothing if
+/// the pointers do not describe a table (e.g. the OS has not booted).
+pub fn walk(mut peek: impl FnMut(u32) -> u8, prog_ptr: u32, p_temp: u32) -> Vec<VatEntry> {
+    let mut entries = Vec::new();
+    if !(RAM_START..=SYM_TABLE_END).contains(&p_temp) || !(p_temp..=SYM_TABLE_END).contains(&prog_ptr) {
+        return entries;
+    }
+
+    let mut entry = SYM_TABLE_END;
+    while entry > p_temp && entries.len() < MAX_ENTRIES {
+        let var_type = peek(entry) & 0x3F;
+        let version = peek(entry - 2);
+        let addr = peek(entry - 3) as u32 | (peek(entry - 4) as u32) << 8 | (peek(entry - 5) as u32) << 16;
+        let name: Vec<u8> = if entry > prog_ptr {
+            if entry - prog_ptr < SYM_ENTRY_SIZE {
+                break;
+            }
+            let name: Vec<u8> = (6..9).map(|i| peek(entry - i)).collect();
+            entry -= SYM_ENTRY_SIZE;
+            let len = name.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            name[..len].to_vec()
+        } else {
+            let len = peek(entry - 6) as u32;
+            if len == 0 || len > 8 || entry - p_temp < 7 + len {
+                break;
+            }
+            let name = (0..len).map(|i| peek(entry - 7 - i)).collect();
+            entry -= 7 + len;
+            name
+        };
+
+        let archived = addr < RAM_START;
+        let data_addr = if archived {
+            addr + ARCHIVE_NAME_LEN_OFFSET + 1 + peek(addr + ARCHIVE_NAME_LEN_OFFSET) as u32
+        } else {
+            addr
+        };
+        entries.push(VatEntry { var_type, version, name, archived, data_addr });
+    }
+    entries
+}
+
+/// Size in bytes of a variable's data (as stored in a .8x* file), read from
+/// its first bytes. None for types whose size is not known.
+pub fn data_len(mut peek: impl FnMut(u32) -> u8, var_type: u8, data_addr: u32) -> Option<u32> {
+    let word = u16::from_le_bytes([peek(data_addr), peek(data_addr + 1)]) as u32;
+    match var_type {
+        // Real, complex
+        0x00 => Some(9),
+        0x0C => Some(18),
+        // Lists: element count, then the elements
+        0x01 => Some(2 + word * 9),
+        0x0D => Some(2 + word * 18),
+        // Matrix: columns and rows, then the elements
+        0x02 => Some(2 + (word & 0xFF) * (word >> 8) * 9),
+        // Equation, string, program, protected program, picture, GDB,
+        // appvar, group: size word, then that many bytes
+        0x03..=0x08 | 0x15 | 0x17 => Some(2 + word),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a VAT entry ending at `top` (bytes go downward); returns the
+    /// address below it
+    fn put_entry(mem: &mut [u8], top: u32, var_type: u8, addr: u32, name: &[u8], long_name: bool) -> u32 {
+        let mut bytes = vec![var_type, 0, 0, addr as u8, (addr >> 8) as u8, (addr >> 16) as u8];
+        if long_name {
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name);
+        } else {
+            let mut fixed = [0u8; 3];
+            fixed[..name.len()].copy_from_slice(name);
+            bytes.extend_from_slice(&fixed);
+        }
+        for (i, &b) in bytes.iter().enumerate() {
+            mem[(top - i as u32 - RAM_START) as usize] = b;
+        }
+        top - bytes.len() as u32
+    }
+
+    #[test]
+    fn test_walk_symbol_and_program_tables() {
+        let mut mem = vec![0u8; (SYM_TABLE_END - RAM_START + 1) as usize];
+        let prog_ptr = put_entry(&mut mem, SYM_TABLE_END, 0x00, 0xD10000, b"A", false);
+        let mid = put_entry(&mut mem, prog_ptr, 0x05, 0xD10100, b"HELLO", true);
+        // Archived: address of the archive entry's flag byte; name length at +9
+        let p_temp = put_entry(&mut mem, mid, 0x15, 0x0C0001, b"DATA", true);
+        let peek = |addr: u32| match addr {
+            0x0C000A => 4,
+            a if a >= RAM_START => mem[(a - RAM_START) as usize],
+            _ => 0xFF,
+        };
+
+        let entries = walk(peek, prog_ptr, p_temp);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].name, b"A");
+        assert_eq!(entries[0].var_type, 0x00);
+        assert_eq!(entries[1].name, b"HELLO");
+        assert_eq!(entries[1].data_addr, 0xD10100);
+        assert!(!entries[1].archived);
+        assert_eq!(entries[2].name, b"DATA");
+        assert!(entries[2].archived);
+        assert_eq!(entries[2].data_addr, 0x0C0001 + 10 + 4);
+
+        // Pointers outside RAM: no table yet
+        assert!(walk(peek, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_data_len() {
+        let bytes = [0x03, 0x02];
+        let peek = |addr: u32| bytes[addr as usize];
+        assert_eq!(data_len(peek, 0x00, 0), Some(9));
+        assert_eq!(data_len(peek, 0x01, 0), Some(2 + 0x203 * 9));
+        assert_eq!(data_len(peek, 0x02, 0), Some(2 + 3 * 2 * 9));
+        assert_eq!(data_len(peek, 0x15, 0), Some(2 + 0x203));
+        assert_eq!(data_len(peek, 0x24, 0), None);
+    }
+}
@@ -4,6 +4,7 @@
 
 use wasm_bindgen::prelude::*;
 use crate::emu::Emu;
+use crate::key::Key;
 
 #[wasm_bindgen]
 extern "C" {
@@ -40,6 +41,20 @@ impl WasmEmu {
         }
     }
 
+    /// Replace the ROM without recreating the emulator, keeping configuration.
+    /// Powers back on if the emulator was running.
+    /// Returns 0 on success, negative error code on failure.
+    #[wasm_bindgen]
+    pub fn swap_rom(&mut self, data: &[u8]) -> i32 {
+        match self.inner.swap_rom(data) {
+            Ok(()) => 0,
+            Err(code) => {
+                warn(&format!("[WASM] swap_rom: error {}", code));
+                code
+            }
+        }
+    }
+
     /// Load ROM data into the emulator.
     /// Returns 0 on success, negative error code on failure.
     /// Does NOT auto power-on - call power_on() separately.
@@ -58,6 +73,28 @@ impl WasmEmu {
         }
     }
 
+    /// Load a ROM dump after validating it (full 4MB with boot code).
+    /// Returns 0 on success, or the error code from `RomError::code`.
+    #[wasm_bindgen]
+    pub fn load_rom_checked(&mut self, data: &[u8]) -> i32 {
+        match self.inner.load_rom_checked(data) {
+            Ok(info) => {
+                log(&format!("[WASM] load_rom_checked: {}", info.to_json()));
+                0
+            }
+            Err(e) => {
+                warn(&format!("[WASM] load_rom_checked: {}", e));
+                e.code()
+            }
+        }
+    }
+
+    /// The loaded ROM as JSON (see `RomInfo::to_json`), or "null" if none.
+    #[wasm_bindgen]
+    pub fn rom_info_json(&self) -> String {
+        self.inner.rom_info().map_or("null".to_string(), |info| info.to_json())
+    }
+
     /// Send a .8xp/.8xv file to be injected into flash archive.
     /// Must be called after load_rom() and before power_on().
     /// Returns number of entries injected (>=0), or negative error code.
@@ -95,6 +132,22 @@ impl WasmEmu {
         }
     }
 
+    /// Send a file before or after boot (send_file or send_file_live).
+    /// Returns number of entries injected (>=0), or negative error code.
+    #[wasm_bindgen]
+    pub fn send_variable(&mut self, data: &[u8]) -> i32 {
+        match self.inner.send_variable(data) {
+            Ok(count) => count as i32,
+            Err(code) => code,
+        }
+    }
+
+    /// Read a variable back as a .8x* file (empty if not found).
+    #[wasm_bindgen]
+    pub fn receive_variable(&mut self, name: &str) -> Vec<u8> {
+        self.inner.receive_variable(name.as_bytes()).unwrap_or_default()
+    }
+
     /// Power on the emulator (simulates ON key press).
     #[wasm_bindgen]
     pub fn power_on(&mut self) {
@@ -102,12 +155,86 @@ impl WasmEmu {
         self.inner.power_on();
     }
 
+    /// Press or release the ON key (power button); wakes a halted CPU.
+    #[wasm_bindgen]
+    pub fn set_on_key(&mut self, down: bool) {
+        if down {
+            self.inner.press_on_key();
+        } else {
+            self.inner.release_on_key();
+        }
+    }
+
     /// Reset the emulator to initial state.
     #[wasm_bindgen]
     pub fn reset(&mut self) {
         self.inner.reset();
     }
 
+    /// Pause emulation; run calls execute nothing until resume().
+    #[wasm_bindgen]
+    pub fn pause(&mut self) {
+        self.inner.pause();
+    }
+
+    /// Resume after pause().
+    #[wasm_bindgen]
+    pub fn resume(&mut self) {
+        self.inner.resume();
+    }
+
+    /// Whether emulation is paused.
+    #[wasm_bindgen]
+    pub fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+
+    /// Why the calculator last restarted: 0-2 reset_with kind, 3 power-on,
+    /// 4 watchdog, 5 protection violation.
+    #[wasm_bindgen]
+    pub fn last_reset_cause(&self) -> i32 {
+        self.inner.last_reset_cause().code()
+    }
+
+    /// Perform a specific kind of reset.
+    /// kind: 0 = warm (reset button, RAM preserved), 1 = RAM clear, 2 = full power cycle.
+    /// Returns 0 on success, -1 on invalid kind.
+    #[wasm_bindgen]
+    pub fn reset_with(&mut self, kind: i32) -> i32 {
+        let kind = match kind {
+            0 => crate::ResetKind::Warm,
+            1 => crate::ResetKind::RamClear,
+            2 => crate::ResetKind::PowerCycle,
+            _ => return -1,
+        };
+        self.inner.reset_with(kind);
+        0
+    }
+
+    /// Enable or disable deterministic mode. Call before load_rom().
+    #[wasm_bindgen]
+    pub fn set_deterministic(&mut self, enabled: bool, seed: u64) {
+        self.inner.set_deterministic(if enabled { Some(seed) } else { None });
+    }
+
+    /// Set the OS patches applied on every ROM load from a text patch list
+    /// ("ADDR: ORIG -> REPL" per line). Call before load_rom().
+    /// Returns the number of patches, or -(100 + line) for a parse error.
+    #[wasm_bindgen]
+    pub fn set_os_patches(&mut self, text: &str) -> i32 {
+        match crate::os_patch::parse_patch_list(text) {
+            Ok(patches) => {
+                let count = patches.len() as i32;
+                self.inner.set_os_patches(patches);
+                count
+            }
+            Err(e) => {
+                warn(&format!("[WASM] set_os_patches: {}", e));
+                -(100 + e.line as i32)
+            }
+        }
+    }
+
     /// Run the emulator for the specified number of cycles.
     /// Returns the number of cycles actually executed.
     #[wasm_bindgen]
@@ -165,6 +292,15 @@ impl WasmEmu {
         executed
     }
 
+    /// Run until the next LCD vsync and render the frame.
+    /// Returns the elapsed cycles.
+    #[wasm_bindgen]
+    pub fn run_frame(&mut self) -> u32 {
+        let executed = self.inner.run_frame();
+        self.inner.render_frame();
+        executed
+    }
+
     /// Get diagnostic info for debugging freezes.
     #[wasm_bindgen]
     pub fn debug_status(&self) -> String {
@@ -180,6 +316,12 @@ impl WasmEmu {
         )
     }
 
+    /// Get a summary of accesses to unimplemented hardware (one line per address).
+    #[wasm_bindgen]
+    pub fn unimpl_summary(&self) -> String {
+        self.inner.unimpl_summary()
+    }
+
     /// Get framebuffer width.
     #[wasm_bindgen]
     pub fn framebuffer_width(&self) -> i32 {
@@ -194,26 +336,323 @@ impl WasmEmu {
         height as i32
     }
 
+    /// Enable or disable the adaptive performance governor.
+    #[wasm_bindgen]
+    pub fn set_governor(&mut self, enabled: bool) {
+        self.inner.set_governor(enabled.then(crate::governor::GovernorConfig::default));
+    }
+
+    /// Report the wall-clock milliseconds spent on the last frame
+    /// (e.g. from `performance.now()`). Returns true if a knob changed.
+    #[wasm_bindgen]
+    pub fn governor_report(&mut self, host_ms: f64) -> bool {
+        self.inner.governor_report(host_ms / 1000.0).is_some()
+    }
+
+    /// Speed over the governor's last window (1.0 = real time), or -1.
+    #[wasm_bindgen]
+    pub fn governor_speed(&self) -> f64 {
+        self.inner.governor().and_then(|g| g.speed()).unwrap_or(-1.0)
+    }
+
+    /// Current frame skip (chosen by the governor when enabled).
+    #[wasm_bindgen]
+    pub fn frame_skip(&self) -> u32 {
+        self.inner.frame_skip()
+    }
+
+    /// Set the `run_paced` speed in percent of real time (100 = real time);
+    /// negative means turbo.
+    #[wasm_bindgen]
+    pub fn set_speed(&mut self, percent: i32) {
+        use crate::throttle::SpeedMode;
+        self.inner.set_speed(match percent {
+            p if p < 0 => SpeedMode::Turbo,
+            100 => SpeedMode::RealTime,
+            p => SpeedMode::Percent(p as u32),
+        });
+    }
+
+    /// Run the emulated time due `host_ms` of wall-clock time after the
+    /// previous call, then render. Returns the executed cycles.
+    #[wasm_bindgen]
+    pub fn run_paced(&mut self, host_ms: f64) -> u32 {
+        let executed = self.inner.run_paced(host_ms / 1000.0);
+        self.inner.render_frame();
+        executed
+    }
+
+    /// Milliseconds to wait before the next `run_paced` call.
+    #[wasm_bindgen]
+    pub fn pace_sleep_ms(&self) -> f64 {
+        self.inner.pace_sleep_secs() * 1000.0
+    }
+
+    /// Enable or disable the runaway-execution watchdog. Poll
+    /// `take_runaway_report()` after each frame to find out if it fired.
+    #[wasm_bindgen]
+    pub fn set_runaway_watchdog(&mut self, enabled: bool) {
+        self.inner.set_runaway_watchdog(enabled.then(crate::runaway::RunawayConfig::default), None);
+    }
+
+    /// Diagnostic context of the last runaway detection, if one is pending.
+    #[wasm_bindgen]
+    pub fn take_runaway_report(&mut self) -> Option<String> {
+        self.inner.take_runaway_report().map(|report| report.context)
+    }
+
+    /// Hash each rendered frame, or only the w x h rectangle at (x, y) when
+    /// w and h are non-zero. Returns false if the rectangle is off screen.
+    #[wasm_bindgen]
+    pub fn set_frame_hashing(&mut self, enabled: bool, x: u32, y: u32, w: u32, h: u32) -> bool {
+        let rect = (w > 0 && h > 0).then_some(crate::frame_hash::HashRect {
+            x: x as usize,
+            y: y as usize,
+            width: w as usize,
+            height: h as usize,
+        });
+        self.inner.set_frame_hashing(enabled, rect)
+    }
+
+    /// Take the CRC32s of frames rendered since the last call, oldest first.
+    #[wasm_bindgen]
+    pub fn take_frame_hashes(&mut self) -> Vec<u32> {
+        self.inner.take_frame_hashes(usize::MAX).into_iter().map(|h| h.crc).collect()
+    }
+
+    /// 64-bit hash of the last rendered frame; equal screens hash equal.
+    #[wasm_bindgen]
+    pub fn frame_hash(&self) -> u64 {
+        self.inner.frame_hash()
+    }
+
+    /// The last rendered frame as a PNG file.
+    #[cfg(feature = "png")]
+    #[wasm_bindgen]
+    pub fn screenshot_png(&self) -> Vec<u8> {
+        self.inner.screenshot_png()
+    }
+
+    /// Composite the last frame into a skin image (ARGB8888, skin_w wide).
+    /// `screen_rect` is [x, y, w, h] (empty for none), `keys` is
+    /// [row, col, x, y, w, h] per key, and held keys are tinted with
+    /// `highlight` (0 = off). Returns an empty array if the sizes are invalid.
+    #[wasm_bindgen]
+    pub fn render_skin(&self, skin: Vec<u32>, skin_w: u32, screen_rect: Vec<u32>, keys: Vec<u32>, highlight: u32) -> Vec<u32> {
+        let Some(layout) = crate::skin::SkinLayout::from_words(&screen_rect, &keys) else {
+            return Vec::new();
+        };
+        let skin_h = skin.len().checked_div(skin_w as usize).unwrap_or(0);
+        match crate::skin::Skin::new(skin_w as usize, skin_h, skin, layout) {
+            Some(skin) => self.inner.render_skin_screenshot(&skin, (highlight != 0).then_some(highlight)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Start or stop per-frame CPU utilization statistics.
+    #[wasm_bindgen]
+    pub fn set_cpu_usage_stats(&mut self, enabled: bool) {
+        self.inner.set_cpu_usage_stats(enabled);
+    }
+
+    /// Fraction (0-1) of the last completed frame the CPU spent executing,
+    /// or -1 if there is no completed frame yet.
+    #[wasm_bindgen]
+    pub fn cpu_busy_fraction(&self) -> f64 {
+        self.inner.last_frame_usage().map_or(-1.0, |usage| usage.busy_fraction())
+    }
+
+    /// Start or stop measuring input-to-photon latency (key press to the
+    /// first rendered frame that changed)
+    #[wasm_bindgen]
+    pub fn set_latency_tracking(&mut self, enabled: bool) {
+        self.inner.set_latency_tracking(enabled);
+    }
+
+    /// Input latency summary as [samples, dropped, mean, min, max] with
+    /// emulated times in microseconds; empty if not tracking. Host time is
+    /// not measured on wasm; time frames with performance.now() instead.
+    #[wasm_bindgen]
+    pub fn input_latency_stats(&self) -> Vec<f64> {
+        self.inner.input_latency_stats().map_or(Vec::new(), |stats| {
+            vec![
+                stats.samples as f64,
+                stats.dropped as f64,
+                stats.emulated_mean_us,
+                stats.emulated_min_us,
+                stats.emulated_max_us,
+            ]
+        })
+    }
+
+    /// Take per-frame statistics since the last call, oldest first, flattened
+    /// as [busy fraction, wakes, wake source bits] per frame.
+    #[wasm_bindgen]
+    pub fn take_frame_usage(&mut self) -> Vec<f64> {
+        self.inner
+            .take_frame_usage(usize::MAX)
+            .into_iter()
+            .flat_map(|usage| [usage.busy_fraction(), usage.wakes as f64, usage.wake_sources as f64])
+            .collect()
+    }
+
+    /// Start recording a Chrome trace (scheduler, interrupts, frames, and
+    /// optionally bcalls).
+    #[wasm_bindgen]
+    pub fn chrome_trace_start(&mut self, bcalls: bool) {
+        let config = crate::chrome_trace::ChromeTraceConfig { bcalls, ..Default::default() };
+        self.inner.start_chrome_trace(config);
+    }
+
+    /// Stop recording the Chrome trace.
+    #[wasm_bindgen]
+    pub fn chrome_trace_stop(&mut self) {
+        self.inner.stop_chrome_trace();
+    }
+
+    /// The recorded Chrome trace as JSON, or an empty string if none.
+    #[wasm_bindgen]
+    pub fn chrome_trace_json(&self) -> String {
+        let mut out = Vec::new();
+        match self.inner.write_chrome_trace(&mut out) {
+            Ok(_) => String::from_utf8(out).unwrap_or_default(),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// Start reporting user-program writes to OS-owned RAM.
+    #[wasm_bindgen]
+    pub fn sandbox_enable(&mut self) {
+        self.inner.enable_sandbox(Vec::new());
+    }
+
+    /// Stop reporting sandbox violations.
+    #[wasm_bindgen]
+    pub fn sandbox_disable(&mut self) {
+        self.inner.disable_sandbox();
+    }
+
+    /// Take unread sandbox violations as text, one per line.
+    #[wasm_bindgen]
+    pub fn sandbox_report(&mut self) -> String {
+        self.inner.sandbox_report()
+    }
+
+    /// Add a breakpoint; returns its ID.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn add_breakpoint(&mut self, pc: u32) -> u32 {
+        self.inner.add_breakpoint(pc)
+    }
+
+    /// Remove a breakpoint. Returns false if the ID is unknown.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn remove_breakpoint(&mut self, id: u32) -> bool {
+        self.inner.remove_breakpoint(id)
+    }
+
+    /// Watch memory for `access` bits (1 read, 2 write, 4 execute).
+    /// Returns the ID, or 0 if the range or bits are empty.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn add_watchpoint(&mut self, start: u32, end: u32, access: u8) -> u32 {
+        self.inner.add_watchpoint(start, end, access).unwrap_or(0)
+    }
+
+    /// Watch I/O ports for reads (1) and/or writes (2).
+    /// Returns the ID, or 0 if the range or bits are empty.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn add_port_watchpoint(&mut self, start: u16, end: u16, access: u8) -> u32 {
+        self.inner.add_port_watchpoint(start, end, access).unwrap_or(0)
+    }
+
+    /// Remove a memory or port watchpoint. Returns false if the ID is unknown.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        self.inner.remove_watchpoint(id)
+    }
+
+    /// Why the last run stopped for the debugger, as text.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn take_debug_event(&mut self) -> Option<String> {
+        self.inner.take_debug_event().map(|e| e.to_string())
+    }
+
+    /// Execute one instruction; returns the resulting event as text.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn step_into(&mut self) -> Option<String> {
+        self.inner.step_into().map(|e| e.to_string())
+    }
+
+    /// Step over a CALL/RST, running at most `max_cycles`.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn step_over(&mut self, max_cycles: u32) -> Option<String> {
+        self.inner.step_over(max_cycles).map(|e| e.to_string())
+    }
+
+    /// Run until the current function returns, at most `max_cycles`.
+    #[cfg(feature = "debugger")]
+    #[wasm_bindgen]
+    pub fn step_out(&mut self, max_cycles: u32) -> Option<String> {
+        self.inner.step_out(max_cycles).map(|e| e.to_string())
+    }
+
+    /// Enter exam mode by simulating [left]+[right]+[ON] from a power cycle.
+    #[wasm_bindgen]
+    pub fn enter_exam_mode(&mut self) {
+        self.inner.enter_exam_mode();
+    }
+
+    /// Leave exam mode.
+    #[wasm_bindgen]
+    pub fn exit_exam_mode(&mut self) {
+        self.inner.exit_exam_mode();
+    }
+
+    /// Whether exam mode is active.
+    #[wasm_bindgen]
+    pub fn exam_mode_active(&mut self) -> bool {
+        self.inner.exam_mode_active()
+    }
+
+    /// Whether the simulated exam LED is lit.
+    #[wasm_bindgen]
+    pub fn exam_led_on(&mut self) -> bool {
+        self.inner.exam_led_on()
+    }
+
+    /// Set the color profile applied to presented frames (0 = ideal sRGB, 1 = CE panel).
+    /// Returns false for an unknown profile.
+    #[wasm_bindgen]
+    pub fn set_color_profile(&mut self, profile: u8) -> bool {
+        match crate::color_profile::ColorProfile::from_id(profile) {
+            Some(profile) => {
+                self.inner.set_color_profile(profile);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Copy framebuffer data to a Uint8ClampedArray for canvas rendering.
     /// Returns RGBA8888 format suitable for ImageData.
     #[wasm_bindgen]
-    pub fn get_framebuffer_rgba(&self) -> Vec<u8> {
-        let framebuffer = self.inner.framebuffer_data();
-        let len = framebuffer.len();
-
-        // Convert from ARGB8888 to RGBA8888 for canvas
-        let mut rgba = Vec::with_capacity(len * 4);
-        for &argb in framebuffer {
-            let a = ((argb >> 24) & 0xFF) as u8;
-            let r = ((argb >> 16) & 0xFF) as u8;
-            let g = ((argb >> 8) & 0xFF) as u8;
-            let b = (argb & 0xFF) as u8;
-            rgba.push(r);
-            rgba.push(g);
-            rgba.push(b);
-            rgba.push(a);
-        }
-        rgba
+    pub fn get_framebuffer_rgba(&mut self) -> Vec<u8> {
+        self.inner.present_frame();
+        argb_to_rgba(self.inner.presented_data())
+    }
+
+    /// Copy the SPI panel's own frame memory (what programs drew with
+    /// RAMWR), 320x240 RGBA8888 like `get_framebuffer_rgba`.
+    #[wasm_bindgen]
+    pub fn get_panel_framebuffer_rgba(&self) -> Vec<u8> {
+        argb_to_rgba(self.inner.panel_framebuffer())
     }
 
     /// Set key state.
@@ -224,12 +663,148 @@ impl WasmEmu {
         self.inner.set_key(row as usize, col as usize, down);
     }
 
+    /// Press the key named `name` (as in the pipe protocol, e.g. "enter")
+    /// and release it after `ms` emulated milliseconds.
+    /// Returns false for an unknown name.
+    #[wasm_bindgen]
+    pub fn tap_key(&mut self, name: &str, ms: u32) -> bool {
+        match Key::from_name(name) {
+            Some(key) => {
+                self.inner.tap_key(key, ms);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Type `text` on the keypad, running the emulator while it types.
+    /// Returns false (typing nothing) if a character has no key.
+    #[wasm_bindgen]
+    pub fn type_text(&mut self, text: &str) -> bool {
+        self.inner.type_text(text).is_ok()
+    }
+
+    /// The homescreen text, rows joined with newlines.
+    #[wasm_bindgen]
+    pub fn read_screen_text(&mut self) -> String {
+        self.inner.read_screen_text()
+    }
+
+    /// Add a memory poke applied every frame. `cond_addr`/`cond_value`, if
+    /// given, restrict it to frames where that byte holds that value.
+    /// Returns the cheat id.
+    #[wasm_bindgen]
+    pub fn cheat_add(&mut self, addr: u32, value: u8, cond_addr: Option<u32>, cond_value: Option<u8>) -> u32 {
+        let mut cheat = crate::cheats::Cheat::new(addr, value);
+        if let (Some(cond_addr), Some(cond_value)) = (cond_addr, cond_value) {
+            cheat = cheat.when(cond_addr, cond_value);
+        }
+        self.inner.add_cheat(cheat)
+    }
+
+    /// Enable or disable a cheat. Returns false if the id is unknown.
+    #[wasm_bindgen]
+    pub fn cheat_set_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        self.inner.set_cheat_enabled(id, enabled)
+    }
+
+    /// Remove a cheat. Returns false if the id is unknown.
+    #[wasm_bindgen]
+    pub fn cheat_remove(&mut self, id: u32) -> bool {
+        self.inner.remove_cheat(id)
+    }
+
+    /// Remove every cheat.
+    #[wasm_bindgen]
+    pub fn cheat_clear(&mut self) {
+        self.inner.clear_cheats();
+    }
+
+    /// Choose when cheats apply (0 = start of each run, 1 = LCD vblank).
+    /// Returns false for an unknown point.
+    #[wasm_bindgen]
+    pub fn cheat_set_point(&mut self, point: u8) -> bool {
+        match crate::cheats::CheatPoint::from_id(point) {
+            Some(point) => {
+                self.inner.set_cheat_point(point);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Plug or unplug the USB serial accessory.
+    #[wasm_bindgen]
+    pub fn serial_attach(&mut self, attached: bool) {
+        if attached {
+            self.inner.attach_serial_accessory(crate::serial::SerialAccessoryConfig::default());
+        } else {
+            self.inner.detach_serial_accessory();
+        }
+    }
+
+    /// True once the calculator has configured the serial accessory.
+    #[wasm_bindgen]
+    pub fn serial_ready(&self) -> bool {
+        self.inner.serial_accessory_ready()
+    }
+
+    /// Queue bytes for the calculator. Returns false if nothing is attached.
+    #[wasm_bindgen]
+    pub fn serial_write(&mut self, data: &[u8]) -> bool {
+        self.inner.serial_write(data)
+    }
+
+    /// Take all bytes the calculator has sent to the accessory.
+    #[wasm_bindgen]
+    pub fn serial_read(&mut self) -> Vec<u8> {
+        self.inner.serial_read(usize::MAX)
+    }
+
+    /// Read `len` bytes from `addr`, through the CPU's bus path if
+    /// `side_effects`, else as a peek.
+    #[wasm_bindgen]
+    pub fn read_memory(&mut self, addr: u32, len: usize, side_effects: bool) -> Vec<u8> {
+        let mut buf = vec![0; len];
+        self.inner.read_memory(addr, &mut buf, side_effects);
+        buf
+    }
+
+    /// Write `data` to `addr`, through the CPU's bus path if `side_effects`,
+    /// else as a direct poke.
+    #[wasm_bindgen]
+    pub fn write_memory(&mut self, addr: u32, data: &[u8], side_effects: bool) {
+        self.inner.write_memory(addr, data, side_effects);
+    }
+
+    /// Take queued LCD buffer swaps as the new base address of each, oldest first.
+    #[wasm_bindgen]
+    pub fn take_lcd_base_changes(&mut self) -> Vec<u32> {
+        self.inner
+            .take_lcd_base_changes(usize::MAX)
+            .iter()
+            .map(|change| change.new_base)
+            .collect()
+    }
+
     /// Get the backlight brightness level (0-255).
     #[wasm_bindgen]
     pub fn get_backlight(&self) -> u8 {
         self.inner.get_backlight()
     }
 
+    /// Get the level the screen is lit at (0-255, 0 when too dim to see).
+    #[wasm_bindgen]
+    pub fn backlight_level(&self) -> u8 {
+        self.inner.backlight_level()
+    }
+
+    /// Set the simulated battery: percent charge (0-100) and whether it is charging.
+    #[wasm_bindgen]
+    pub fn set_battery(&mut self, percent: u8, charging: bool) {
+        self.inner.set_battery(percent, charging);
+    }
+
     /// Check if LCD is on (should display content).
     #[wasm_bindgen]
     pub fn is_lcd_on(&self) -> bool {
@@ -243,6 +818,32 @@ impl WasmEmu {
         self.inner.is_off()
     }
 
+    /// Full 4MB flash image, for saving the archive.
+    #[wasm_bindgen]
+    pub fn export_flash(&self) -> Vec<u8> {
+        self.inner.export_flash()
+    }
+
+    /// Number of 64KB flash sectors modified since they were last exported.
+    #[wasm_bindgen]
+    pub fn flash_dirty_count(&self) -> usize {
+        self.inner.flash_dirty_count()
+    }
+
+    /// Take one modified flash sector: 4-byte little-endian image offset
+    /// followed by the 64KB contents. Empty when nothing is dirty.
+    #[wasm_bindgen]
+    pub fn export_dirty_flash_sector(&mut self) -> Vec<u8> {
+        match self.inner.export_dirty_flash(1).pop() {
+            Some((offset, data)) => {
+                let mut out = offset.to_le_bytes().to_vec();
+                out.extend_from_slice(&data);
+                out
+            }
+            None => Vec::new(),
+        }
+    }
+
     /// Get the size needed for a save state buffer.
     #[wasm_bindgen]
     pub fn save_state_size(&self) -> usize {
@@ -264,6 +865,64 @@ impl WasmEmu {
         }
     }
 
+    /// Boot to the home screen and capture a warm-boot snapshot.
+    /// Returns the snapshot or an empty array on failure.
+    #[wasm_bindgen]
+    pub fn capture_warm_boot(&mut self, max_cycles: u64) -> Vec<u8> {
+        match self.inner.capture_warm_boot_state(max_cycles) {
+            Ok(snapshot) => snapshot,
+            Err(code) => {
+                warn(&format!("[WASM] capture_warm_boot FAILED: error {}", code));
+                Vec::new()
+            }
+        }
+    }
+
+    /// Start from a warm-boot snapshot, skipping the ROM boot sequence.
+    /// Returns 0 on success, negative error code on failure.
+    #[wasm_bindgen]
+    pub fn boot_warm(&mut self, data: &[u8]) -> i32 {
+        match self.inner.boot_from_warm_state(data) {
+            Ok(()) => 0,
+            Err(code) => code,
+        }
+    }
+
+    /// Start recording key events for replay, stamped relative to now.
+    #[wasm_bindgen]
+    pub fn start_input_recording(&mut self) {
+        self.inner.start_input_recording();
+    }
+
+    /// Stop recording key events and return them as a replay file (empty
+    /// if not recording).
+    #[wasm_bindgen]
+    pub fn stop_input_recording(&mut self) -> Vec<u8> {
+        self.inner.stop_input_recording().unwrap_or_default()
+    }
+
+    /// Replay a recording from `stop_input_recording`, starting now.
+    /// Returns the number of events or a negative error code.
+    #[wasm_bindgen]
+    pub fn play_replay(&mut self, data: &[u8]) -> i32 {
+        match self.inner.play_replay(data) {
+            Ok(events) => events as i32,
+            Err(code) => code,
+        }
+    }
+
+    /// Write bytes into the virtual link cable, as a host would.
+    #[wasm_bindgen]
+    pub fn link_send(&mut self, data: &[u8]) {
+        self.inner.link_send(data);
+    }
+
+    /// Take everything the calculator has written to the virtual link cable.
+    #[wasm_bindgen]
+    pub fn link_recv(&mut self) -> Vec<u8> {
+        self.inner.link_recv(usize::MAX)
+    }
+
     /// Load emulator state from a byte array.
     /// Returns 0 on success, negative error code on failure.
     #[wasm_bindgen]
@@ -302,6 +961,44 @@ impl WasmEmu {
         }
     }
 
+    /// Record rewind history every `interval_frames` LCD frames, keeping
+    /// `capacity` snapshots. Either one 0 stops recording.
+    #[wasm_bindgen]
+    pub fn set_rewind(&mut self, interval_frames: u32, capacity: u32) {
+        let config = (interval_frames > 0 && capacity > 0)
+            .then_some(crate::RewindConfig { interval_frames, capacity: capacity as usize });
+        self.inner.set_rewind(config);
+    }
+
+    /// Step back at least `frames` LCD frames.
+    /// Returns frames rewound, or a negative error code.
+    #[wasm_bindgen]
+    pub fn rewind(&mut self, frames: u32) -> f64 {
+        match self.inner.rewind(frames) {
+            Ok(rewound) => rewound as f64,
+            Err(code) => code as f64,
+        }
+    }
+
+    /// Set the calculator clock to `unix_secs` (local time). Stops host sync.
+    #[wasm_bindgen]
+    pub fn set_rtc_time(&mut self, unix_secs: f64) {
+        self.inner.set_rtc_time(unix_secs as i64);
+    }
+
+    /// Keep the calculator clock on the browser clock, shifted by
+    /// `utc_offset_secs` (e.g. `-new Date().getTimezoneOffset() * 60`).
+    #[wasm_bindgen]
+    pub fn set_rtc_host_sync(&mut self, enabled: bool, utc_offset_secs: i32) -> bool {
+        self.inner.set_rtc_host_sync(enabled.then_some(utc_offset_secs))
+    }
+
+    /// Calculator clock as Unix seconds
+    #[wasm_bindgen]
+    pub fn rtc_time(&self) -> f64 {
+        self.inner.rtc_time() as f64
+    }
+
     /// Dump diagnostic state for debugging.
     #[wasm_bindgen]
     pub fn dump_state(&self) -> String {
@@ -322,3 +1019,19 @@ impl Default for WasmEmu {
         Self::new()
     }
 }
+
+/// Convert ARGB8888 pixels to RGBA8888 for canvas
+fn argb_to_rgba(framebuffer: &[u32]) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(framebuffer.len() * 4);
+    for &argb in framebuffer {
+        let a = ((argb >> 24) & 0xFF) as u8;
+        let r = ((argb >> 16) & 0xFF) as u8;
+        let g = ((argb >> 8) & 0xFF) as u8;
+        let b = (argb & 0xFF) as u8;
+        rgba.push(r);
+        rgba.push(g);
+        rgba.push(b);
+        rgba.push(a);
+    }
+    rgba
+}
@@ -192,7 +192,7 @@ build_android() {
         for target in $TARGETS; do
             rustup target add "$target" 2>/dev/null || true
             if [ "$BUILD_CONFIG" = "Release" ]; then
-                cargo build --release --target "$target"
+                cargo build --release --target "$target" --no-default-features
             else
                 cargo build --target "$target"
             fi
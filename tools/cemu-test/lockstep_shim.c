@@ -0,0 +1,67 @@
+/*
+ * Lockstep shim - exposes CEmu's core to the Rust lockstep harness
+ * (core/src/lockstep.rs, cargo feature "cemu-lockstep").
+ *
+ * CEmu keeps its state in globals, so there is a single instance.
+ * Stepping uses the same technique as trace_gen: run one base tick at a
+ * time until PC or the halted state changes.
+ */
+#include "../../cemu-ref/core/emu.h"
+#include "../../cemu-ref/core/cpu.h"
+#include "../../cemu-ref/core/mem.h"
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Must match RegSnapshot in core/src/lockstep.rs (#[repr(C)]) */
+typedef struct {
+    uint32_t pc, sp, bc, de, hl, ix, iy;
+    uint8_t a, f;
+    bool adl, iff1, iff2;
+    uint8_t im;
+    bool halted;
+} LockstepRegs;
+
+/* Upper bound on base ticks per instruction before giving up */
+#define MAX_TICKS_PER_STEP 100000
+
+int lockstep_cemu_load_rom(const char* path) {
+    emu_state_t state = emu_load(EMU_DATA_ROM, path);
+    if (state != EMU_STATE_VALID) {
+        return -(int)state - 1;
+    }
+    emu_set_run_rate(48000000);
+    return 0;
+}
+
+void lockstep_cemu_step(void) {
+    uint32_t pc_before = cpu.registers.PC;
+    bool halted_before = cpu.halted;
+    for (int i = 0; i < MAX_TICKS_PER_STEP; i++) {
+        emu_run(1);
+        if (cpu.registers.PC != pc_before || cpu.halted != halted_before) {
+            return;
+        }
+    }
+}
+
+void lockstep_cemu_registers(LockstepRegs* out) {
+    out->pc = cpu.registers.PC;
+    out->sp = cpu.ADL ? cpu.registers.SPL : cpu.registers.SPS;
+    out->bc = cpu.registers.BC;
+    out->de = cpu.registers.DE;
+    out->hl = cpu.registers.HL;
+    out->ix = cpu.registers.IX;
+    out->iy = cpu.registers.IY;
+    out->a = cpu.registers.A;
+    out->f = cpu.registers.F;
+    out->adl = cpu.ADL;
+    out->iff1 = cpu.IEF1;
+    out->iff2 = cpu.IEF2;
+    out->im = cpu.IM;
+    out->halted = cpu.halted;
+}
+
+uint8_t lockstep_cemu_peek(uint32_t addr) {
+    return mem_peek_byte(addr);
+}